@@ -0,0 +1,227 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+
+use crate::error::Error;
+use crate::functions::as_number;
+use crate::functions::compare;
+use crate::functions::is_null;
+use crate::input::JsonInput;
+use crate::input::RawJsonb;
+use crate::jsonpath::JsonPath;
+use crate::jsonpath::Selector;
+use crate::number::Number;
+
+/// Aggregates the values selected by a `JSONPath` across many `JSONB` documents.
+///
+/// All the values selected from each document are aggregated, `null` values are only counted.
+/// The minimum and maximum values are compared by [`compare`], so values of different types
+/// can be compared. The sum and average only include the numbers.
+///
+/// ```
+/// use jsonb::jsonpath::parse_json_path;
+/// use jsonb::Aggregator;
+///
+/// let mut agg = Aggregator::new(parse_json_path(b"$.price").unwrap());
+/// for doc in [r#"{"price":1}"#, r#"{"price":2.5}"#, r#"{"price":null}"#, r#"{}"#] {
+///     agg.update(doc).unwrap();
+/// }
+/// assert_eq!(agg.count(), 3);
+/// assert_eq!(agg.null_count(), 1);
+/// assert_eq!(agg.missing_count(), 1);
+/// assert_eq!(agg.avg(), Some(1.75));
+/// ```
+pub struct Aggregator<'a> {
+    selector: Selector<'a>,
+    count: u64,
+    null_count: u64,
+    missing_count: u64,
+    number_count: u64,
+    sum: Option<Sum>,
+    min: Option<Vec<u8>>,
+    max: Option<Vec<u8>>,
+}
+
+// Integers are summed exactly until they overflow or a float is added.
+#[derive(Debug, Clone, Copy)]
+enum Sum {
+    Int(i128),
+    Float(f64),
+}
+
+impl Sum {
+    fn add(self, other: Sum) -> Sum {
+        match (self, other) {
+            (Sum::Int(l), Sum::Int(r)) => match l.checked_add(r) {
+                Some(v) => Sum::Int(v),
+                None => Sum::Float(l as f64 + r as f64),
+            },
+            (Sum::Int(l), Sum::Float(r)) => Sum::Float(l as f64 + r),
+            (Sum::Float(l), Sum::Int(r)) => Sum::Float(l + r as f64),
+            (Sum::Float(l), Sum::Float(r)) => Sum::Float(l + r),
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Sum::Int(v) => v as f64,
+            Sum::Float(v) => v,
+        }
+    }
+}
+
+impl From<Number> for Sum {
+    fn from(n: Number) -> Self {
+        match n {
+            Number::Int64(v) => Sum::Int(v as i128),
+            Number::UInt64(v) => Sum::Int(v as i128),
+            Number::Float64(v) => Sum::Float(v),
+        }
+    }
+}
+
+impl<'a> Aggregator<'a> {
+    pub fn new(json_path: JsonPath<'a>) -> Self {
+        Self {
+            selector: Selector::new(json_path),
+            count: 0,
+            null_count: 0,
+            missing_count: 0,
+            number_count: 0,
+            sum: None,
+            min: None,
+            max: None,
+        }
+    }
+
+    /// Aggregate the values selected from the document.
+    pub fn update<T: JsonInput + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let value = value.to_jsonb()?;
+        let values = self.selector.select(value.as_ref());
+        if values.is_empty() {
+            self.missing_count += 1;
+        }
+        for value in values {
+            self.update_value(value)?;
+        }
+        Ok(())
+    }
+
+    fn update_value(&mut self, value: Vec<u8>) -> Result<(), Error> {
+        self.count += 1;
+        let raw = RawJsonb::new(&value);
+        if is_null(&raw) {
+            self.null_count += 1;
+            return Ok(());
+        }
+        if let Some(n) = as_number(&raw) {
+            self.number_count += 1;
+            self.add_sum(n.into());
+        }
+        replace_if(&mut self.min, &value, Ordering::Less)?;
+        replace_if(&mut self.max, &value, Ordering::Greater)
+    }
+
+    fn add_sum(&mut self, sum: Sum) {
+        self.sum = Some(match self.sum {
+            Some(s) => s.add(sum),
+            None => sum,
+        });
+    }
+
+    /// Merge the partial aggregation of another aggregator, which is usually
+    /// created with the same path for another set of documents.
+    pub fn merge(&mut self, other: &Aggregator<'_>) -> Result<(), Error> {
+        self.count += other.count;
+        self.null_count += other.null_count;
+        self.missing_count += other.missing_count;
+        self.number_count += other.number_count;
+        if let Some(sum) = other.sum {
+            self.add_sum(sum);
+        }
+        if let Some(min) = &other.min {
+            replace_if(&mut self.min, min, Ordering::Less)?;
+        }
+        if let Some(max) = &other.max {
+            replace_if(&mut self.max, max, Ordering::Greater)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the number of selected values, including `null` values.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the number of selected `null` values.
+    pub fn null_count(&self) -> u64 {
+        self.null_count
+    }
+
+    /// Returns the number of documents that the path selects nothing.
+    pub fn missing_count(&self) -> u64 {
+        self.missing_count
+    }
+
+    /// Returns the number of selected numbers.
+    pub fn number_count(&self) -> u64 {
+        self.number_count
+    }
+
+    /// Returns the minimum non-null value as `JSONB`.
+    pub fn min(&self) -> Option<&[u8]> {
+        self.min.as_deref()
+    }
+
+    /// Returns the maximum non-null value as `JSONB`.
+    pub fn max(&self) -> Option<&[u8]> {
+        self.max.as_deref()
+    }
+
+    /// Returns the sum of the numbers, or `None` if no number is selected.
+    ///
+    /// The sum of integers is exact, it is returned as a float only if it overflows `i64` and `u64`.
+    pub fn sum(&self) -> Option<Number> {
+        self.sum.map(|sum| match sum {
+            Sum::Int(v) => {
+                if let Ok(v) = i64::try_from(v) {
+                    Number::Int64(v)
+                } else if let Ok(v) = u64::try_from(v) {
+                    Number::UInt64(v)
+                } else {
+                    Number::Float64(v as f64)
+                }
+            }
+            Sum::Float(v) => Number::Float64(v),
+        })
+    }
+
+    /// Returns the average of the numbers, or `None` if no number is selected.
+    pub fn avg(&self) -> Option<f64> {
+        self.sum.map(|sum| sum.as_f64() / self.number_count as f64)
+    }
+}
+
+// Replace the value in the slot if the new value is ordered as `order` compared to it.
+fn replace_if(slot: &mut Option<Vec<u8>>, value: &[u8], order: Ordering) -> Result<(), Error> {
+    let replace = match slot {
+        Some(old) => compare(&RawJsonb::new(value), &RawJsonb::new(old))? == order,
+        None => true,
+    };
+    if replace {
+        *slot = Some(value.to_vec());
+    }
+    Ok(())
+}
@@ -0,0 +1,198 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::error::Error;
+use crate::input::JsonInput;
+use crate::value::Value;
+
+// The estimated size of a dictionary id that replaces a key.
+const DICTIONARY_ID_SIZE: usize = 2;
+
+/// The statistics of the `JSONB` encoding collected by [`analyze`],
+/// with the estimated savings of the encoding options.
+///
+/// The savings are rough estimations to choose the options, not the exact sizes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EncodingReport {
+    /// The number of analyzed documents.
+    pub documents: usize,
+    /// The size of the encoded documents.
+    pub total_bytes: usize,
+    /// The size of the headers and `JEntry`s.
+    pub structure_bytes: usize,
+    /// The size of the object keys.
+    pub key_bytes: usize,
+    /// The size of the distinct object keys.
+    pub distinct_key_bytes: usize,
+    pub key_count: usize,
+    pub distinct_key_count: usize,
+    pub object_count: usize,
+    /// The number of objects with distinct key lists.
+    pub distinct_shape_count: usize,
+    /// The estimated savings of replacing the keys with the ids of a dictionary.
+    pub dictionary_savings: usize,
+    /// The estimated savings of storing the key list once for the objects with the same keys.
+    /// The keys are always sorted in `JSONB`, so the objects with the same keys have the same list.
+    pub shape_savings: usize,
+    /// The estimated savings of a general purpose compression, by the entropy of the bytes.
+    pub compression_savings: usize,
+}
+
+impl EncodingReport {
+    /// Returns true if the dictionary encoding saves at least `ratio` of the total size.
+    pub fn suggest_dictionary(&self, ratio: f64) -> bool {
+        self.saves(self.dictionary_savings, ratio)
+    }
+
+    /// Returns true if sharing the key lists saves at least `ratio` of the total size.
+    pub fn suggest_shapes(&self, ratio: f64) -> bool {
+        self.saves(self.shape_savings, ratio)
+    }
+
+    /// Returns true if the compression saves at least `ratio` of the total size.
+    pub fn suggest_compression(&self, ratio: f64) -> bool {
+        self.saves(self.compression_savings, ratio)
+    }
+
+    fn saves(&self, savings: usize, ratio: f64) -> bool {
+        self.total_bytes > 0 && savings as f64 >= self.total_bytes as f64 * ratio
+    }
+}
+
+/// Analyze the encoding of a `JSONB` document, see [`analyze_all`].
+pub fn analyze<T: JsonInput + ?Sized>(value: &T) -> Result<EncodingReport, Error> {
+    analyze_all([value])
+}
+
+/// Analyze the encoding of the `JSONB` documents, usually a sample of a column,
+/// to find out whether dictionary-encoding the keys, sharing the key lists of the objects
+/// or compressing the documents would help.
+///
+/// ```
+/// use jsonb::analyze_all;
+///
+/// let docs: Vec<_> = (0..100)
+///     .map(|i| format!(r#"{{"identifier":{},"description":"item"}}"#, i))
+///     .collect();
+/// let report = analyze_all(docs.iter().map(|doc| doc.as_str())).unwrap();
+/// assert_eq!(report.distinct_key_count, 2);
+/// assert!(report.suggest_dictionary(0.2));
+/// ```
+pub fn analyze_all<T: JsonInput, I: IntoIterator<Item = T>>(
+    values: I,
+) -> Result<EncodingReport, Error> {
+    let mut report = EncodingReport::default();
+    let mut keys = HashSet::new();
+    let mut shapes = HashMap::new();
+    let mut byte_counts = [0_usize; 256];
+    for value in values {
+        let buf = value.to_jsonb()?;
+        let buf = buf.as_ref();
+        report.documents += 1;
+        report.total_bytes += buf.len();
+        for b in buf {
+            byte_counts[*b as usize] += 1;
+        }
+        let value = value.to_value()?;
+        // The header and the `JEntry` of the top-level value.
+        report.structure_bytes += 4;
+        if !value.is_array() && !value.is_object() {
+            report.structure_bytes += 4;
+        }
+        collect(&value, &mut report, &mut keys, &mut shapes);
+    }
+
+    report.distinct_key_count = keys.len();
+    report.distinct_key_bytes = keys.iter().map(|key| key.len()).sum();
+    report.distinct_shape_count = shapes.len();
+    report.dictionary_savings = report
+        .key_bytes
+        .saturating_sub(report.distinct_key_bytes + report.key_count * DICTIONARY_ID_SIZE);
+    // The repeated key lists are replaced by a shape id.
+    report.shape_savings = shapes
+        .values()
+        .map(|(count, size)| (count - 1) * size.saturating_sub(DICTIONARY_ID_SIZE))
+        .sum();
+    report.compression_savings = report
+        .total_bytes
+        .saturating_sub(entropy_size(&byte_counts, report.total_bytes));
+    Ok(report)
+}
+
+fn collect(
+    value: &Value<'_>,
+    report: &mut EncodingReport,
+    keys: &mut HashSet<String>,
+    shapes: &mut HashMap<Vec<String>, (usize, usize)>,
+) {
+    match value {
+        Value::Array(vals) => {
+            report.structure_bytes += 4 * vals.len();
+            for val in vals {
+                collect_child(val, report, keys, shapes);
+            }
+        }
+        Value::Object(obj) => {
+            report.object_count += 1;
+            report.key_count += obj.len();
+            report.structure_bytes += 8 * obj.len();
+            let mut size = 0;
+            for (key, val) in obj {
+                report.key_bytes += key.len();
+                size += 4 + key.len();
+                if !keys.contains(key) {
+                    keys.insert(key.clone());
+                }
+                collect_child(val, report, keys, shapes);
+            }
+            let shape = shapes
+                .entry(obj.keys().cloned().collect())
+                .or_insert((0, size));
+            shape.0 += 1;
+        }
+        _ => {}
+    }
+}
+
+fn collect_child(
+    value: &Value<'_>,
+    report: &mut EncodingReport,
+    keys: &mut HashSet<String>,
+    shapes: &mut HashMap<Vec<String>, (usize, usize)>,
+) {
+    // Nested containers have their own header.
+    if value.is_array() || value.is_object() {
+        report.structure_bytes += 4;
+    }
+    collect(value, report, keys, shapes);
+}
+
+// The estimated size of the bytes compressed by the order-0 entropy.
+fn entropy_size(byte_counts: &[usize; 256], total: usize) -> usize {
+    if total == 0 {
+        return 0;
+    }
+    let bits: f64 = byte_counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / total as f64;
+            -p.log2() * *count as f64
+        })
+        .sum();
+    (bits / 8.0).ceil() as usize
+}
@@ -0,0 +1,86 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::BufReader;
+
+use super::error::Error;
+use super::parser::parse_value;
+use super::partial::PartialParser;
+use super::value::Value;
+
+const CHUNK_SIZE: usize = 8192;
+
+/// Read the `JSON` text from the reader until EOF and parse it to `Value`.
+///
+/// The text is read in chunks and fed to a [`PartialParser`], so an invalid nesting, like a `]`
+/// closing an object, or trailing characters after the value are reported as soon as the chunk
+/// arrives, without reading the rest. Use [`NdjsonReader`] to parse newline-delimited values
+/// as soon as each line arrives.
+pub async fn parse_value_async<R: AsyncRead + Unpin>(
+    reader: &mut R,
+) -> Result<Value<'static>, Error> {
+    let mut parser = PartialParser::new();
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let len = reader
+            .read(&mut chunk)
+            .await
+            .map_err(|err| Error::Io(err.kind()))?;
+        if len == 0 {
+            return parser.finish_value();
+        }
+        parser.feed(&chunk[..len])?;
+    }
+}
+
+/// Reader of newline-delimited `JSON` values, parses one value per line, blank lines are skipped.
+pub struct NdjsonReader<R> {
+    reader: BufReader<R>,
+    buf: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> NdjsonReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            buf: Vec::new(),
+        }
+    }
+
+    /// Read and parse the next value, returns `None` at EOF.
+    ///
+    /// A line that can't be parsed returns the error, the following lines can still be read.
+    pub async fn next_value(&mut self) -> Option<Result<Value<'static>, Error>> {
+        loop {
+            self.buf.clear();
+            match self.reader.read_until(b'\n', &mut self.buf).await {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(err) => return Some(Err(Error::Io(err.kind()))),
+            }
+            if self.buf.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            return Some(parse_value(&self.buf).map(Value::into_owned));
+        }
+    }
+
+    /// Returns the underlying reader, the buffered data that hasn't been parsed is lost.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Command line tool to inspect `JSONB` values.
+//!
+//! The inputs are read from the files or stdin if the file is omitted or `-`,
+//! and can be either `JSON` text or `JSONB`, except `layout` which requires `JSONB`.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::process;
+
+use jsonb::diff;
+use jsonb::dump_layout;
+use jsonb::from_slice;
+use jsonb::get_by_path;
+use jsonb::jsonpath::parse_json_path;
+use jsonb::pretty;
+use jsonb::to_string;
+use jsonb::RawJsonb;
+
+const USAGE: &str = "Usage: jsonb-cli <COMMAND> [ARGS]
+
+Commands:
+  encode [FILE]         Convert the value to JSONB
+  decode [FILE]         Convert the value to JSON text
+  pretty [FILE]         Pretty print the value as JSON text
+  path <PATH> [FILE]    Select the values by JSONPath, one per line
+  diff <FILE> <FILE>    Print the JSON Patch from the first value to the second
+  layout [FILE]         Dump the header layout of the JSONB value";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(err) = run(&args) {
+        eprintln!("jsonb-cli: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    let mut stdout = io::stdout().lock();
+    match args.as_slice() {
+        ["encode", rest @ ..] if rest.len() <= 1 => {
+            let value = read_jsonb(rest.first())?;
+            stdout.write_all(&value).map_err(|e| e.to_string())
+        }
+        ["decode", rest @ ..] if rest.len() <= 1 => {
+            let value = read_jsonb(rest.first())?;
+            writeln!(stdout, "{}", to_string(&RawJsonb::new(&value))).map_err(|e| e.to_string())
+        }
+        ["pretty", rest @ ..] if rest.len() <= 1 => {
+            let value = read_jsonb(rest.first())?;
+            writeln!(stdout, "{}", to_pretty_string(&value)).map_err(|e| e.to_string())
+        }
+        ["path", path, rest @ ..] if rest.len() <= 1 => {
+            let value = read_jsonb(rest.first())?;
+            let json_path = parse_json_path(path.as_bytes()).map_err(|e| e.to_string())?;
+            for v in get_by_path(&RawJsonb::new(&value), json_path) {
+                writeln!(stdout, "{}", to_string(&RawJsonb::new(&v))).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        }
+        ["diff", left, right] => {
+            let left = read_jsonb(Some(left))?;
+            let right = read_jsonb(Some(right))?;
+            let ops =
+                diff(&RawJsonb::new(&left), &RawJsonb::new(&right)).map_err(|e| e.to_string())?;
+            writeln!(stdout, "{}", to_pretty_string(&ops)).map_err(|e| e.to_string())
+        }
+        ["layout", rest @ ..] if rest.len() <= 1 => {
+            let value = read_input(rest.first())?;
+            let layout = dump_layout(&value).map_err(|e| e.to_string())?;
+            write!(stdout, "{}", layout).map_err(|e| e.to_string())
+        }
+        ["-h" | "--help" | "help"] => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(format!("invalid arguments\n\n{}", USAGE)),
+    }
+}
+
+fn read_input(file: Option<&&str>) -> Result<Vec<u8>, String> {
+    let mut buf = Vec::new();
+    match file {
+        None | Some(&"-") => io::stdin()
+            .read_to_end(&mut buf)
+            .map_err(|e| format!("failed to read stdin: {}", e))?,
+        Some(file) => {
+            buf = fs::read(file).map_err(|e| format!("failed to read {}: {}", file, e))?;
+            buf.len()
+        }
+    };
+    Ok(buf)
+}
+
+// Reads the input as `JSONB`, `JSON` text is encoded first.
+fn read_jsonb(file: Option<&&str>) -> Result<Vec<u8>, String> {
+    let buf = read_input(file)?;
+    let value = from_slice(&buf).map_err(|e| e.to_string())?;
+    Ok(value.to_vec())
+}
+
+fn to_pretty_string(value: &[u8]) -> String {
+    let mut json = String::new();
+    pretty(&RawJsonb::new(value), &mut json);
+    json
+}
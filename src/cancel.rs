@@ -0,0 +1,93 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::error::Error;
+
+// The number of the steps of an operation between the checks of the token.
+pub(crate) const CHECK_INTERVAL: usize = 1024;
+
+/// A token to interrupt the long-running operations, like parsing a huge document,
+/// the recursive descent of a JSON path or the diff of large values.
+///
+/// The token is cancelled by [`cancel`](CancellationToken::cancel) from any clone of it,
+/// like the query cancellation of a database engine, or once the deadline is reached.
+/// The operations check the token as they go, and stop with `Error::Cancelled`.
+///
+/// ```
+/// use jsonb::{parse_value_with_options, CancellationToken, Error, ParseOptions};
+///
+/// let token = CancellationToken::new();
+/// let options = ParseOptions {
+///     cancel: Some(token.clone()),
+///     ..Default::default()
+/// };
+/// assert!(parse_value_with_options(b"[1,2,3]", &options).is_ok());
+/// token.cancel();
+/// let result = parse_value_with_options(b"[1,2,3]", &options);
+/// assert_eq!(result, Err(Error::Cancelled));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// A token that is also cancelled once the deadline is reached, to limit the time
+    /// of the operations.
+    pub fn with_deadline(deadline: Instant) -> CancellationToken {
+        CancellationToken {
+            cancelled: Arc::default(),
+            deadline: Some(deadline),
+        }
+    }
+
+    /// Cancel the operations checking the token or any of its clones.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .map(|deadline| Instant::now() >= deadline)
+                .unwrap_or_default()
+    }
+
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+// The clones of a token are equal.
+impl PartialEq for CancellationToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled) && self.deadline == other.deadline
+    }
+}
+
+impl Eq for CancellationToken {}
@@ -0,0 +1,194 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Differential checks of the behavior of this crate against `serde_json`.
+//!
+//! Each check runs the same `JSON` text through this crate and `serde_json`, and returns
+//! a [`Divergence`] if the outcomes differ, so that the semantic drift between the two
+//! can be validated continuously, such as over the documents of a pipeline.
+//!
+//! The outcomes are compared as `serde_json` values, so `serde_json` decides the equality:
+//! the order of the object keys doesn't matter, and `1` is not equal to `1.0`.
+//!
+//! ```
+//! use jsonb::conformance::{check, check_compare, Behavior};
+//!
+//! assert!(check(&[r#"{"a":[1,2]}"#, r#"{"a":[1,"2"]}"#, "[1,]"]).is_empty());
+//! let divergence = check_compare("[1,2]", "[1.0,2]").unwrap();
+//! assert_eq!(divergence.behavior, Behavior::Compare);
+//! assert_eq!(divergence.jsonb, "equal");
+//! assert_eq!(divergence.serde_json, "not equal");
+//! ```
+
+use std::cmp::Ordering;
+
+use serde_json::Map as JsonMap;
+use serde_json::Number as JsonNumber;
+use serde_json::Value as JsonValue;
+
+use crate::functions::compare;
+use crate::functions::to_string;
+use crate::number::Number;
+use crate::parser::parse_value;
+use crate::value::Value;
+
+/// The behavior compared by a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Behavior {
+    /// Whether the text is accepted, and the parsed value.
+    Parse,
+    /// The value parsed again from the text written by [`to_string`](crate::to_string).
+    Serialize,
+    /// Whether two values are equal.
+    Compare,
+}
+
+/// A difference of the outcomes of this crate and `serde_json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub behavior: Behavior,
+    /// The input texts, two texts for [`Behavior::Compare`].
+    pub inputs: Vec<String>,
+    /// The outcome of this crate, a value as `JSON` text, an error, or the result of comparison.
+    pub jsonb: String,
+    /// The outcome of `serde_json`, in the same form as `jsonb`.
+    pub serde_json: String,
+}
+
+/// Runs all the checks over the texts, the parse and the serialize checks of each text,
+/// and the compare checks of each pair of texts, returns the divergences in this order.
+pub fn check(texts: &[&str]) -> Vec<Divergence> {
+    let mut divergences = Vec::new();
+    for text in texts {
+        divergences.extend(check_parse(text));
+        divergences.extend(check_serialize(text));
+    }
+    for (i, left) in texts.iter().enumerate() {
+        for right in &texts[i + 1..] {
+            divergences.extend(check_compare(left, right));
+        }
+    }
+    divergences
+}
+
+/// Checks that the text is accepted by both or rejected by both, and parsed to the same value.
+pub fn check_parse(text: &str) -> Option<Divergence> {
+    let jsonb = parse_jsonb(text);
+    let serde = parse_serde(text);
+    let same = match (&jsonb, &serde) {
+        (Ok(left), Ok(right)) => left == right,
+        (Err(_), Err(_)) => true,
+        _ => false,
+    };
+    if same {
+        return None;
+    }
+    Some(Divergence {
+        behavior: Behavior::Parse,
+        inputs: vec![text.to_string()],
+        jsonb: describe(&jsonb),
+        serde_json: describe(&serde),
+    })
+}
+
+/// Checks that the text written by this crate is parsed by `serde_json` to the same value
+/// as the original text. Texts that are not accepted by both are not checked.
+pub fn check_serialize(text: &str) -> Option<Divergence> {
+    let (Ok(value), Ok(expected)) = (parse_value(text.as_bytes()), parse_serde(text)) else {
+        return None;
+    };
+    let written = to_string(&value.to_vec());
+    let actual = parse_serde(&written);
+    if actual.as_ref() == Ok(&expected) {
+        return None;
+    }
+    Some(Divergence {
+        behavior: Behavior::Serialize,
+        inputs: vec![text.to_string()],
+        jsonb: match actual {
+            Ok(_) => written,
+            Err(err) => format!("{err}: {written}"),
+        },
+        serde_json: describe(&Ok(expected)),
+    })
+}
+
+/// Checks that two values are equal with [`compare`](crate::compare) if and only if
+/// they are equal in `serde_json`. Texts that are not accepted by both are not checked.
+pub fn check_compare(left: &str, right: &str) -> Option<Divergence> {
+    let (Ok(left_value), Ok(right_value)) =
+        (parse_value(left.as_bytes()), parse_value(right.as_bytes()))
+    else {
+        return None;
+    };
+    let (Ok(left_serde), Ok(right_serde)) = (parse_serde(left), parse_serde(right)) else {
+        return None;
+    };
+    let jsonb = compare(&left_value.to_vec(), &right_value.to_vec()) == Ok(Ordering::Equal);
+    let serde = left_serde == right_serde;
+    if jsonb == serde {
+        return None;
+    }
+    let describe = |equal: bool| if equal { "equal" } else { "not equal" }.to_string();
+    Some(Divergence {
+        behavior: Behavior::Compare,
+        inputs: vec![left.to_string(), right.to_string()],
+        jsonb: describe(jsonb),
+        serde_json: describe(serde),
+    })
+}
+
+fn parse_jsonb(text: &str) -> Result<JsonValue, String> {
+    let value = parse_value(text.as_bytes()).map_err(|err| err.to_string())?;
+    to_json_value(value)
+}
+
+fn parse_serde(text: &str) -> Result<JsonValue, String> {
+    serde_json::from_str(text).map_err(|err| err.to_string())
+}
+
+fn describe(outcome: &Result<JsonValue, String>) -> String {
+    match outcome {
+        Ok(value) => value.to_string(),
+        Err(err) => format!("error: {err}"),
+    }
+}
+
+// Unlike `From<Value>`, the values that `serde_json` can't represent,
+// such as the non-finite floats, are errors.
+fn to_json_value(value: Value<'_>) -> Result<JsonValue, String> {
+    let value = match value {
+        Value::Null => JsonValue::Null,
+        Value::Bool(v) => JsonValue::Bool(v),
+        Value::Number(Number::Int64(v)) => JsonValue::Number(v.into()),
+        Value::Number(Number::UInt64(v)) => JsonValue::Number(v.into()),
+        Value::Number(Number::Float64(v)) => match JsonNumber::from_f64(v) {
+            Some(v) => JsonValue::Number(v),
+            None => return Err(format!("non-finite number {v}")),
+        },
+        Value::String(v) => JsonValue::String(v.into_owned()),
+        Value::Array(vals) => JsonValue::Array(
+            vals.into_iter()
+                .map(to_json_value)
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(obj) => JsonValue::Object(
+            obj.into_iter()
+                .map(|(k, v)| Ok((k, to_json_value(v)?)))
+                .collect::<Result<JsonMap<_, _>, String>>()?,
+        ),
+        Value::Extension(v) => return Err(format!("extension value of tag {}", v.tag)),
+    };
+    Ok(value)
+}
@@ -31,6 +31,7 @@ pub(crate) const NUMBER_TAG: u32 = 0x20000000;
 pub(crate) const FALSE_TAG: u32 = 0x30000000;
 pub(crate) const TRUE_TAG: u32 = 0x40000000;
 pub(crate) const CONTAINER_TAG: u32 = 0x50000000;
+pub(crate) const EXTENSION_TAG: u32 = 0x60000000;
 
 // JSONB number constants
 pub(crate) const NUMBER_ZERO: u8 = 0x00;
@@ -0,0 +1,225 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Databend compatible variant functions.
+//!
+//! Functions keep the names of the Databend variant functions, like [`get_path`] and [`as_integer`],
+//! so that the engine only needs to convert the arguments and the results.
+//! Databend `GET` is overloaded by the argument type, it is split into [`get`] and [`get_index`].
+//!
+//! A SQL `NULL` result is returned as `None`, the `JSON` `null` is a value like any other:
+//! the getters return the `null` value if it exists, while [`as_boolean`] and the other
+//! `as_*` functions return `None` for it.
+//! Variant values are ordered by [`compare`]: `null` > array > object > string > number > boolean.
+
+use std::cmp::Ordering;
+
+use crate::error::Error;
+use crate::functions;
+use crate::input::JsonInput;
+use crate::jsonpath::parse_json_path;
+use crate::parser::parse_value;
+use crate::value::Value;
+
+/// `GET(variant, field_name)` returns the value of the field, the name is case sensitive.
+pub fn get<T: JsonInput + ?Sized>(value: &T, name: &str) -> Option<Vec<u8>> {
+    functions::get_by_name(value, name, false)
+}
+
+/// `GET(variant, index)` returns the element of the array, `None` if the index is out of bounds.
+pub fn get_index<T: JsonInput + ?Sized>(value: &T, index: i64) -> Option<Vec<u8>> {
+    let index = usize::try_from(index).ok()?;
+    functions::get_by_index(value, index)
+}
+
+/// `GET_IGNORE_CASE(variant, field_name)` returns the value of the field,
+/// the exactly matched name is preferred over the names that only differ in case.
+pub fn get_ignore_case<T: JsonInput + ?Sized>(value: &T, name: &str) -> Option<Vec<u8>> {
+    functions::get_by_name(value, name, true)
+}
+
+/// `GET_PATH(variant, path_name)` returns the value selected by the path, like `k1[0].k2` or `["k1"]:k2`.
+pub fn get_path<T: JsonInput + ?Sized>(value: &T, path: &str) -> Result<Option<Vec<u8>>, Error> {
+    let json_path = parse_json_path(path.as_bytes())?;
+    Ok(functions::get_by_path_first(value, json_path))
+}
+
+/// `JSON_EXTRACT_PATH_TEXT(expr, path_name)` returns the value selected by the path as text.
+/// Strings are returned without quotes, `None` if the `JSON` text is invalid or the path selects nothing.
+pub fn json_extract_path_text(text: &str, path: &str) -> Result<Option<String>, Error> {
+    let json_path = parse_json_path(path.as_bytes())?;
+    let Ok(value) = parse_value(text.as_bytes()) else {
+        return Ok(None);
+    };
+    let res = functions::get_by_path_first(&value, json_path)
+        .map(|v| functions::as_str(&v).map_or_else(|| functions::to_string(&v), String::from));
+    Ok(res)
+}
+
+/// `PARSE_JSON(string)` parses the `JSON` text to variant.
+pub fn parse_json(text: &str) -> Result<Vec<u8>, Error> {
+    Ok(parse_value(text.as_bytes())?.to_vec())
+}
+
+/// `TRY_PARSE_JSON(string)` parses the `JSON` text to variant, `None` if the text is invalid.
+pub fn try_parse_json(text: &str) -> Option<Vec<u8>> {
+    parse_json(text).ok()
+}
+
+/// `CHECK_JSON(string)` returns `None` if the text is valid `JSON`, otherwise the error message.
+pub fn check_json(text: &str) -> Option<String> {
+    parse_value(text.as_bytes())
+        .err()
+        .map(|err| err.to_string())
+}
+
+/// `JSON_TYPEOF(variant)` returns the type of the value.
+pub fn json_typeof<T: JsonInput + ?Sized>(value: &T) -> Result<&'static str, Error> {
+    let ty = match value.to_value()? {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::String(_) => "string",
+        Value::Number(_) => "number",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+        Value::Extension(_) => "extension",
+    };
+    Ok(ty)
+}
+
+/// `JSON_ARRAY_LENGTH(variant)` returns the number of elements, `None` if the value is not an array.
+pub fn json_array_length<T: JsonInput + ?Sized>(value: &T) -> Option<usize> {
+    functions::array_length(value)
+}
+
+/// `JSON_OBJECT_KEYS(variant)` returns the keys as an array, `None` if the value is not an object.
+pub fn json_object_keys<T: JsonInput + ?Sized>(value: &T) -> Option<Vec<u8>> {
+    functions::object_keys(value)
+}
+
+/// `JSON_TO_STRING(variant)` returns the value as `JSON` text.
+pub fn json_to_string<T: JsonInput + ?Sized>(value: &T) -> String {
+    functions::to_string(value)
+}
+
+/// `IS_NULL_VALUE(variant)` returns true if the value is `JSON` `null`.
+pub fn is_null_value<T: JsonInput + ?Sized>(value: &T) -> bool {
+    functions::is_null(value)
+}
+
+/// `IS_BOOLEAN(variant)`
+pub fn is_boolean<T: JsonInput + ?Sized>(value: &T) -> bool {
+    functions::is_boolean(value)
+}
+
+/// `IS_INTEGER(variant)` returns true if the value is a number that fits in `i64` or `u64`.
+pub fn is_integer<T: JsonInput + ?Sized>(value: &T) -> bool {
+    functions::is_i64(value) || functions::is_u64(value)
+}
+
+/// `IS_FLOAT(variant)` returns true if the value is a number that can be represented as `f64`.
+pub fn is_float<T: JsonInput + ?Sized>(value: &T) -> bool {
+    functions::is_f64(value)
+}
+
+/// `IS_STRING(variant)`
+pub fn is_string<T: JsonInput + ?Sized>(value: &T) -> bool {
+    functions::is_string(value)
+}
+
+/// `IS_ARRAY(variant)`
+pub fn is_array<T: JsonInput + ?Sized>(value: &T) -> bool {
+    functions::is_array(value)
+}
+
+/// `IS_OBJECT(variant)`
+pub fn is_object<T: JsonInput + ?Sized>(value: &T) -> bool {
+    functions::is_object(value)
+}
+
+/// `AS_BOOLEAN(variant)` returns `None` if the value is not a boolean.
+pub fn as_boolean<T: JsonInput + ?Sized>(value: &T) -> Option<bool> {
+    functions::as_bool(value)
+}
+
+/// `AS_INTEGER(variant)` returns `None` if the value is not a number that fits in `i64`.
+pub fn as_integer<T: JsonInput + ?Sized>(value: &T) -> Option<i64> {
+    functions::as_i64(value)
+}
+
+/// `AS_FLOAT(variant)` returns `None` if the value is not a number.
+pub fn as_float<T: JsonInput + ?Sized>(value: &T) -> Option<f64> {
+    functions::as_f64(value)
+}
+
+/// `AS_STRING(variant)` returns the string without quotes, `None` if the value is not a string.
+pub fn as_string<T: JsonInput + ?Sized>(value: &T) -> Option<String> {
+    functions::as_str(value).map(String::from)
+}
+
+/// `AS_ARRAY(variant)` returns `None` if the value is not an array.
+pub fn as_array<T: JsonInput + ?Sized>(value: &T) -> Option<Vec<u8>> {
+    if functions::is_array(value) {
+        value.to_jsonb().ok().map(|v| v.into_owned())
+    } else {
+        None
+    }
+}
+
+/// `AS_OBJECT(variant)` returns `None` if the value is not an object.
+pub fn as_object<T: JsonInput + ?Sized>(value: &T) -> Option<Vec<u8>> {
+    if functions::is_object(value) {
+        value.to_jsonb().ok().map(|v| v.into_owned())
+    } else {
+        None
+    }
+}
+
+/// `TO_BOOLEAN(variant)` casts the boolean or the string `true` and `false`,
+/// returns [`Error::InvalidCast`] for other values.
+pub fn to_boolean<T: JsonInput + ?Sized>(value: &T) -> Result<bool, Error> {
+    functions::to_bool(value)
+}
+
+/// `TO_INT64(variant)` casts the number, boolean or numeric string,
+/// returns [`Error::InvalidCast`] for other values.
+pub fn to_int64<T: JsonInput + ?Sized>(value: &T) -> Result<i64, Error> {
+    functions::to_i64(value)
+}
+
+/// `TO_UINT64(variant)` casts the number, boolean or numeric string,
+/// returns [`Error::InvalidCast`] for other values.
+pub fn to_uint64<T: JsonInput + ?Sized>(value: &T) -> Result<u64, Error> {
+    functions::to_u64(value)
+}
+
+/// `TO_FLOAT64(variant)` casts the number, boolean or numeric string,
+/// returns [`Error::InvalidCast`] for other values.
+pub fn to_float64<T: JsonInput + ?Sized>(value: &T) -> Result<f64, Error> {
+    functions::to_f64(value)
+}
+
+/// `TO_STRING(variant)` casts the string, boolean or number,
+/// returns [`Error::InvalidCast`] for other values.
+pub fn to_string<T: JsonInput + ?Sized>(value: &T) -> Result<String, Error> {
+    functions::to_str(value)
+}
+
+/// Compare the variant values in the order of `ORDER BY`.
+pub fn compare<L: JsonInput + ?Sized, R: JsonInput + ?Sized>(
+    left: &L,
+    right: &R,
+) -> Result<Ordering, Error> {
+    functions::compare(left, right)
+}
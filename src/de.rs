@@ -20,9 +20,13 @@ use byteorder::ReadBytesExt;
 
 use super::constants::*;
 use super::error::*;
+use super::extension::ExtensionValue;
 use super::jentry::JEntry;
+use super::memory::MemoryTracker;
 use super::number::Number;
 use super::parser::parse_value;
+use super::parser::parse_value_with_tracker;
+use super::parser::ParseOptions;
 use super::value::Object;
 use super::value::Value;
 
@@ -53,6 +57,7 @@ use super::value::Value;
 /// Decode can be executed recursively.
 
 /// Decode `JSONB` Value from binary bytes.
+#[allow(clippy::empty_line_after_doc_comments, clippy::doc_lazy_continuation)]
 pub fn from_slice(buf: &[u8]) -> Result<Value<'_>, Error> {
     let mut decoder = Decoder::new(buf);
     match decoder.decode() {
@@ -62,6 +67,25 @@ pub fn from_slice(buf: &[u8]) -> Result<Value<'_>, Error> {
     }
 }
 
+/// Decode `JSONB` Value from binary bytes like [`from_slice`], the estimated memory
+/// of the decoded values is reserved from the tracker, the decoding fails with the error
+/// of the tracker if the memory can't be reserved.
+pub fn from_slice_with_tracker<'a>(
+    buf: &'a [u8],
+    tracker: &dyn MemoryTracker,
+) -> Result<Value<'a>, Error> {
+    let mut decoder = Decoder::new(buf);
+    match decoder.decode_with_tracker(tracker) {
+        Ok(value) => Ok(value),
+        Err(Error::MemoryLimitExceeded) => Err(Error::MemoryLimitExceeded),
+        // for compatible with the first version of `JSON` text, parse it again
+        Err(_) => parse_value_with_tracker(buf, &ParseOptions::default(), tracker),
+    }
+}
+
+// The estimated memory of a value, without the nested values and the content of the keys.
+const VALUE_SIZE: usize = std::mem::size_of::<Value>();
+
 #[repr(transparent)]
 pub struct Decoder<'a> {
     buf: &'a [u8],
@@ -73,28 +97,38 @@ impl<'a> Decoder<'a> {
     }
 
     pub fn decode(&mut self) -> Result<Value<'a>, Error> {
+        self.decode_root(None)
+    }
+
+    /// Decode the value like `decode`, the estimated memory of the decoded values
+    /// is reserved from the tracker.
+    pub fn decode_with_tracker(&mut self, tracker: &dyn MemoryTracker) -> Result<Value<'a>, Error> {
+        self.decode_root(Some(tracker))
+    }
+
+    fn decode_root(&mut self, tracker: Option<&dyn MemoryTracker>) -> Result<Value<'a>, Error> {
         // Valid `JSONB` Value has at least one `Header`
         if self.buf.len() < 4 {
             return Err(Error::InvalidJsonb);
         }
-        let value = self.decode_jsonb()?;
+        let value = self.decode_jsonb(tracker)?;
         Ok(value)
     }
 
     // Read value type from the `Header`
     // `Scalar` has one `JEntry`
     // `Array` and `Object` store the numbers of elements
-    fn decode_jsonb(&mut self) -> Result<Value<'a>, Error> {
+    fn decode_jsonb(&mut self, tracker: Option<&dyn MemoryTracker>) -> Result<Value<'a>, Error> {
         let container_header = self.buf.read_u32::<BigEndian>()?;
 
         match container_header & CONTAINER_HEADER_TYPE_MASK {
             SCALAR_CONTAINER_TAG => {
                 let encoded = self.buf.read_u32::<BigEndian>()?;
                 let jentry = JEntry::decode_jentry(encoded);
-                self.decode_scalar(jentry)
+                self.decode_scalar(jentry, tracker)
             }
-            ARRAY_CONTAINER_TAG => self.decode_array(container_header),
-            OBJECT_CONTAINER_TAG => self.decode_object(container_header),
+            ARRAY_CONTAINER_TAG => self.decode_array(container_header, tracker),
+            OBJECT_CONTAINER_TAG => self.decode_object(container_header, tracker),
             _ => Err(Error::InvalidJsonbHeader),
         }
     }
@@ -104,7 +138,11 @@ impl<'a> Decoder<'a> {
     // `Number` and `String` `JEntry` stores the length or offset of the data,
     // read them and decode to the `Value`
     // `Array` and `Object` need to read nested data from the lower-level `Header`
-    fn decode_scalar(&mut self, jentry: JEntry) -> Result<Value<'a>, Error> {
+    fn decode_scalar(
+        &mut self,
+        jentry: JEntry,
+        tracker: Option<&dyn MemoryTracker>,
+    ) -> Result<Value<'a>, Error> {
         match jentry.type_code {
             NULL_TAG => Ok(Value::Null),
             TRUE_TAG => Ok(Value::Bool(true)),
@@ -121,20 +159,38 @@ impl<'a> Decoder<'a> {
                 self.buf = &self.buf[offset..];
                 Ok(Value::Number(n))
             }
-            CONTAINER_TAG => self.decode_jsonb(),
+            EXTENSION_TAG => {
+                let offset = jentry.length as usize;
+                if offset == 0 {
+                    return Err(Error::InvalidJsonbJEntry);
+                }
+                let tag = self.buf[0];
+                let payload = &self.buf[1..offset];
+                self.buf = &self.buf[offset..];
+                Ok(Value::Extension(ExtensionValue {
+                    tag,
+                    payload: Cow::Borrowed(payload),
+                }))
+            }
+            CONTAINER_TAG => self.decode_jsonb(tracker),
             _ => Err(Error::InvalidJsonbJEntry),
         }
     }
 
     // Decode the numbers of values from the `Header`,
     // then read all `JEntries`, finally decode the `Value` by `JEntry`
-    fn decode_array(&mut self, container_header: u32) -> Result<Value<'a>, Error> {
+    fn decode_array(
+        &mut self,
+        container_header: u32,
+        tracker: Option<&dyn MemoryTracker>,
+    ) -> Result<Value<'a>, Error> {
         let length = (container_header & CONTAINER_HEADER_LEN_MASK) as usize;
         let jentries = self.decode_jentries(length)?;
+        reserve(tracker, length * VALUE_SIZE)?;
         let mut values: Vec<Value> = Vec::with_capacity(length);
         // decode all values
         for jentry in jentries.into_iter() {
-            let value = self.decode_scalar(jentry)?;
+            let value = self.decode_scalar(jentry, tracker)?;
             values.push(value);
         }
 
@@ -144,7 +200,11 @@ impl<'a> Decoder<'a> {
 
     // The basic process is the same as that of `Array`
     // but first decode the keys and then decode the values
-    fn decode_object(&mut self, container_header: u32) -> Result<Value<'a>, Error> {
+    fn decode_object(
+        &mut self,
+        container_header: u32,
+        tracker: Option<&dyn MemoryTracker>,
+    ) -> Result<Value<'a>, Error> {
         let length = (container_header & CONTAINER_HEADER_LEN_MASK) as usize;
         let mut jentries = self.decode_jentries(length * 2)?;
 
@@ -152,7 +212,7 @@ impl<'a> Decoder<'a> {
         // decode all keys first
         for _ in 0..length {
             let jentry = jentries.pop_front().unwrap();
-            let key = self.decode_scalar(jentry)?;
+            let key = self.decode_scalar(jentry, tracker)?;
             keys.push_back(key);
         }
 
@@ -161,8 +221,9 @@ impl<'a> Decoder<'a> {
         for _ in 0..length {
             let key = keys.pop_front().unwrap();
             let k = key.as_str().unwrap();
+            reserve(tracker, VALUE_SIZE + k.len())?;
             let jentry = jentries.pop_front().unwrap();
-            let value = self.decode_scalar(jentry)?;
+            let value = self.decode_scalar(jentry, tracker)?;
             obj.insert(k.to_string(), value);
         }
 
@@ -181,3 +242,10 @@ impl<'a> Decoder<'a> {
         Ok(jentries)
     }
 }
+
+fn reserve(tracker: Option<&dyn MemoryTracker>, bytes: usize) -> Result<(), Error> {
+    match tracker {
+        Some(tracker) => tracker.reserve(bytes),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use byteorder::BigEndian;
+use byteorder::WriteBytesExt;
+
+use crate::de::from_slice;
+use crate::error::Error;
+use crate::input::JsonInput;
+use crate::pg::delete_path_value;
+use crate::pg::get_path_mut;
+use crate::pg::set_value;
+use crate::value::Value;
+
+const OP_SET: u8 = 0;
+const OP_REMOVE: u8 = 1;
+const OP_APPEND: u8 = 2;
+
+/// An edit of [`JsonbDelta`].
+///
+/// The paths are the same as the Postgres `text[]` paths of [`crate::pg`], each element
+/// selects a key of an object or an integer index of an array, negative index counts from the end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeltaOp {
+    /// Replace the item designated by the path with the `JSONB` value, or add it if it
+    /// doesn't exist, like `jsonb_set` with `create_missing`. An empty path replaces the document.
+    Set { path: Vec<String>, value: Vec<u8> },
+    /// Remove the item designated by the path, like the `#-` operator.
+    Remove { path: Vec<String> },
+    /// Append the `JSONB` value to the array designated by the path.
+    Append { path: Vec<String>, value: Vec<u8> },
+}
+
+impl DeltaOp {
+    fn path(&self) -> &[String] {
+        match self {
+            DeltaOp::Set { path, .. } | DeltaOp::Remove { path } | DeltaOp::Append { path, .. } => {
+                path
+            }
+        }
+    }
+
+    // Whether the edit may shift the indexes of an array along the prefix, so that the prefix
+    // designates another item. Appending shifts the negative indexes, and setting an index
+    // out of range inserts the item at the start or the end.
+    fn shifts(&self, prefix: &[String]) -> bool {
+        let array = match self {
+            DeltaOp::Append { path, .. } => &path[..],
+            DeltaOp::Remove { path } => path.split_last().map_or(&path[..], |(_, p)| p),
+            DeltaOp::Set { path, .. } => match path.split_last() {
+                Some((last, parents)) if last.parse::<i64>().is_ok() => parents,
+                _ => return false,
+            },
+        };
+        prefix.len() > array.len() && prefix.starts_with(array)
+    }
+}
+
+/// A sequence of edits that can be applied to a `JSONB` document, so that the changes of
+/// a document can be shipped without the whole document.
+///
+/// The edits are applied in order, the items that don't exist are skipped like the
+/// Postgres functions. The delta is encoded in a compact binary format by [`JsonbDelta::encode`].
+///
+/// ```
+/// use jsonb::DeltaOp;
+/// use jsonb::JsonbDelta;
+///
+/// let mut delta = JsonbDelta::new();
+/// delta.push(DeltaOp::Set {
+///     path: vec!["a".to_string()],
+///     value: jsonb::parse_value(b"2").unwrap().to_vec(),
+/// });
+/// delta.push(DeltaOp::Append {
+///     path: vec!["b".to_string()],
+///     value: jsonb::parse_value(b"3").unwrap().to_vec(),
+/// });
+///
+/// let delta = JsonbDelta::decode(&delta.encode()).unwrap();
+/// let value = delta.apply(r#"{"a":1,"b":[1]}"#).unwrap();
+/// assert_eq!(jsonb::to_string(&value), r#"{"a":2,"b":[1,3]}"#);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonbDelta {
+    ops: Vec<DeltaOp>,
+}
+
+impl JsonbDelta {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, op: DeltaOp) {
+        self.ops.push(op);
+    }
+
+    pub fn ops(&self) -> &[DeltaOp] {
+        &self.ops
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Apply the edits to the base document and returns the new document as `JSONB`.
+    pub fn apply<T: JsonInput + ?Sized>(&self, base: &T) -> Result<Vec<u8>, Error> {
+        let mut value = base.to_value()?;
+        for op in self.ops.iter() {
+            apply_op(&mut value, op)?;
+        }
+        Ok(value.to_vec())
+    }
+
+    /// Append the edits of a later delta, applying the composed delta is the same as
+    /// applying this delta and then the other one.
+    ///
+    /// The edits inside the items that are replaced or removed later are dropped,
+    /// if the edits in between don't shift the array indexes along their paths,
+    /// by removing, appending or inserting an item.
+    pub fn compose(&mut self, other: &JsonbDelta) {
+        self.ops.extend(other.ops.iter().cloned());
+
+        let mut keep = vec![true; self.ops.len()];
+        for (i, later) in self.ops.iter().enumerate() {
+            let prefix = match later {
+                DeltaOp::Set { path, .. } | DeltaOp::Remove { path } => path,
+                DeltaOp::Append { .. } => continue,
+            };
+            for (j, earlier) in self.ops[..i].iter().enumerate().rev() {
+                let path = earlier.path();
+                // An edit at the same path may add or remove an array element,
+                // except appending to the array, which is replaced later.
+                let overwritten = path.starts_with(prefix)
+                    && (path.len() > prefix.len() || matches!(earlier, DeltaOp::Append { .. }));
+                if overwritten {
+                    keep[j] = false;
+                }
+                if earlier.shifts(prefix) {
+                    break;
+                }
+            }
+        }
+        let mut keep = keep.into_iter();
+        self.ops.retain(|_| keep.next().unwrap());
+    }
+
+    /// Encode the delta as bytes.
+    ///
+    /// The delta is encoded as the number of edits followed by the edits, each edit is
+    /// a kind byte, the number of path elements, the path elements and the `JSONB` value
+    /// if any. The lengths and numbers are big-endian `u32`, strings and values are
+    /// prefixed with their length.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let _ = buf.write_u32::<BigEndian>(self.ops.len() as u32);
+        for op in self.ops.iter() {
+            let (kind, value) = match op {
+                DeltaOp::Set { value, .. } => (OP_SET, Some(value)),
+                DeltaOp::Remove { .. } => (OP_REMOVE, None),
+                DeltaOp::Append { value, .. } => (OP_APPEND, Some(value)),
+            };
+            buf.push(kind);
+            let path = op.path();
+            let _ = buf.write_u32::<BigEndian>(path.len() as u32);
+            for elem in path {
+                let _ = buf.write_u32::<BigEndian>(elem.len() as u32);
+                buf.extend_from_slice(elem.as_bytes());
+            }
+            if let Some(value) = value {
+                let _ = buf.write_u32::<BigEndian>(value.len() as u32);
+                buf.extend_from_slice(value);
+            }
+        }
+        buf
+    }
+
+    /// Decode the delta encoded by [`JsonbDelta::encode`].
+    pub fn decode(buf: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader { buf };
+        let len = reader.read_u32()? as usize;
+        let mut ops = Vec::with_capacity(len.min(buf.len()));
+        for _ in 0..len {
+            let kind = reader.read_bytes(1)?[0];
+            let path_len = reader.read_u32()? as usize;
+            let mut path = Vec::with_capacity(path_len.min(buf.len()));
+            for _ in 0..path_len {
+                let elem_len = reader.read_u32()? as usize;
+                let elem = std::str::from_utf8(reader.read_bytes(elem_len)?)?;
+                path.push(elem.to_string());
+            }
+            let op = match kind {
+                OP_SET => DeltaOp::Set {
+                    path,
+                    value: reader.read_value()?,
+                },
+                OP_REMOVE => DeltaOp::Remove { path },
+                OP_APPEND => DeltaOp::Append {
+                    path,
+                    value: reader.read_value()?,
+                },
+                _ => return Err(Error::InvalidJsonb),
+            };
+            ops.push(op);
+        }
+        if !reader.buf.is_empty() {
+            return Err(Error::InvalidJsonb);
+        }
+        Ok(Self { ops })
+    }
+}
+
+fn apply_op<'a>(value: &mut Value<'a>, op: &'a DeltaOp) -> Result<(), Error> {
+    let path: Vec<&str> = op.path().iter().map(|elem| elem.as_str()).collect();
+    match op {
+        DeltaOp::Set {
+            value: new_value, ..
+        } => {
+            let new_value = from_slice(new_value)?;
+            match path.split_last() {
+                Some((last, parents)) => {
+                    if let Some(parent) = get_path_mut(value, parents)? {
+                        set_value(parent, last, new_value, true)?;
+                    }
+                }
+                None => *value = new_value,
+            }
+        }
+        DeltaOp::Remove { .. } => {
+            delete_path_value(value, &path)?;
+        }
+        DeltaOp::Append {
+            value: new_value, ..
+        } => {
+            let new_value = from_slice(new_value)?;
+            match get_path_mut(value, &path)? {
+                Some(Value::Array(items)) => items.push(new_value),
+                Some(_) => return Err(Error::InvalidJsonType),
+                None => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.buf.len() < len {
+            return Err(Error::InvalidEOF);
+        }
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn read_value(&mut self) -> Result<Vec<u8>, Error> {
+        let len = self.read_u32()? as usize;
+        Ok(self.read_bytes(len)?.to_vec())
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Write;
+
+use crate::error::Error;
+use crate::input::JsonInput;
+use crate::number::Number;
+use crate::util::quote_string;
+use crate::value::Value;
+
+/// The ecosystem whose `JSON` text output is reproduced by [`to_text_compatible`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// `serde_json::to_string`: compact, control characters escaped as `\u00xx`,
+    /// floats in the shortest form that round-trips, such as `1.0` and `1e20`,
+    /// and the non-finite floats as `null`. The object keys are sorted like in `JSONB`,
+    /// which is the order of `serde_json` without the `preserve_order` feature.
+    SerdeJson,
+    /// The Postgres `jsonb` output: a space after `:` and `,`, the object keys sorted
+    /// by length first, and the same escapes as `SerdeJson`, which are also the escapes
+    /// of the Postgres `escape_json`.
+    /// Floats are written without the exponent, and the integral ones without the fraction,
+    /// as the original text of a Postgres `numeric` is not stored.
+    Postgres,
+}
+
+/// Convert a `JSONB` value to `JSON` text that byte-matches the output of the `dialect`,
+/// for the systems that hash or compare the text produced by another ecosystem.
+/// Extension values are written as their plain values.
+///
+/// ```
+/// use jsonb::{to_text_compatible, Dialect};
+///
+/// let value = r#"{"bb":[1.5,"\u0001"],"a":2.0}"#;
+/// let text = to_text_compatible(value, Dialect::SerdeJson).unwrap();
+/// assert_eq!(text, r#"{"a":2.0,"bb":[1.5,"\u0001"]}"#);
+/// let text = to_text_compatible(value, Dialect::Postgres).unwrap();
+/// assert_eq!(text, r#"{"a": 2, "bb": [1.5, "\u0001"]}"#);
+/// ```
+pub fn to_text_compatible<T: JsonInput + ?Sized>(
+    value: &T,
+    dialect: Dialect,
+) -> Result<String, Error> {
+    let value = value.to_value()?;
+    match dialect {
+        Dialect::SerdeJson => serde_json::to_string(&value).map_err(|_| Error::InvalidJsonType),
+        Dialect::Postgres => {
+            let mut text = String::new();
+            write_postgres(&value, &mut text)?;
+            Ok(text)
+        }
+    }
+}
+
+fn write_postgres(value: &Value<'_>, text: &mut String) -> Result<(), Error> {
+    match value {
+        Value::Null => text.push_str("null"),
+        Value::Bool(v) => text.push_str(if *v { "true" } else { "false" }),
+        Value::Number(Number::Int64(v)) => text.push_str(itoa::Buffer::new().format(*v)),
+        Value::Number(Number::UInt64(v)) => text.push_str(itoa::Buffer::new().format(*v)),
+        Value::Number(Number::Float64(v)) if v.is_finite() => {
+            let _ = write!(text, "{}", v);
+        }
+        Value::Number(Number::Float64(_)) => text.push_str("null"),
+        Value::String(v) => quote_string(v, text),
+        Value::Array(vals) => {
+            text.push('[');
+            for (i, val) in vals.iter().enumerate() {
+                if i > 0 {
+                    text.push_str(", ");
+                }
+                write_postgres(val, text)?;
+            }
+            text.push(']');
+        }
+        Value::Object(obj) => {
+            // Postgres orders the shorter keys first, then the keys of the same length bytewise.
+            let mut fields: Vec<_> = obj.iter().collect();
+            fields.sort_by(|(l, _), (r, _)| l.len().cmp(&r.len()).then_with(|| l.cmp(r)));
+            text.push('{');
+            for (i, (key, val)) in fields.into_iter().enumerate() {
+                if i > 0 {
+                    text.push_str(", ");
+                }
+                quote_string(key, text);
+                text.push_str(": ");
+                write_postgres(val, text)?;
+            }
+            text.push('}');
+        }
+        Value::Extension(v) => write_postgres(&v.to_plain_value()?, text)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,283 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use crate::cancel::CancellationToken;
+use crate::error::Error;
+use crate::functions::convert_to_comparable;
+use crate::input::JsonInput;
+use crate::profiler::push_key;
+use crate::value::Value;
+
+/// Compute the differences from the `left` value to the `right` value,
+/// returns them as a `JSONB` array of JSON Patch (RFC 6902) operations.
+///
+/// Objects are compared by keys and arrays by positions, other changed values are replaced.
+/// Applying the operations in order to the `left` value produces the `right` value.
+pub fn diff<L: JsonInput + ?Sized, R: JsonInput + ?Sized>(
+    left: &L,
+    right: &R,
+) -> Result<Vec<u8>, Error> {
+    let left = left.to_value()?;
+    let right = right.to_value()?;
+    let mut ops = Vec::new();
+    diff_values(&left, &right, &mut String::new(), &mut ops, None)?;
+    Ok(Value::Array(ops).to_vec())
+}
+
+/// Compute the differences like [`diff`], the comparison is interrupted with
+/// `Error::Cancelled` once the token is cancelled.
+pub fn diff_with_cancellation<L: JsonInput + ?Sized, R: JsonInput + ?Sized>(
+    left: &L,
+    right: &R,
+    cancel: &CancellationToken,
+) -> Result<Vec<u8>, Error> {
+    let left = left.to_value()?;
+    let right = right.to_value()?;
+    let mut ops = Vec::new();
+    diff_values(&left, &right, &mut String::new(), &mut ops, Some(cancel))?;
+    Ok(Value::Array(ops).to_vec())
+}
+
+/// The kind of a [`ChangeEntry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// A change reported by [`diff_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeEntry {
+    /// The path of the changed item written as `JSONPath`, like `$.a[0]["unit price"]`.
+    pub path: String,
+    pub kind: ChangeKind,
+    /// The old value as `JSONB`, `None` if the item is added.
+    pub old: Option<Vec<u8>>,
+    /// The new value as `JSONB`, `None` if the item is removed.
+    pub new: Option<Vec<u8>>,
+}
+
+/// Compare the `old` value with the `new` value and report the changed items,
+/// in the order of the paths in the documents.
+///
+/// Unlike [`diff`], the report is not intended to be applied, but to show what changed
+/// in audit logs and user interfaces. Objects are compared by keys and arrays by positions,
+/// only the innermost changed items are reported.
+///
+/// ```
+/// use jsonb::diff_report;
+/// use jsonb::ChangeKind;
+///
+/// let changes = diff_report(r#"{"a":1,"b":[1,2]}"#, r#"{"a":2,"b":[1]}"#).unwrap();
+/// assert_eq!(changes.len(), 2);
+/// assert_eq!(changes[0].path, "$.a");
+/// assert_eq!(changes[0].kind, ChangeKind::Modified);
+/// assert_eq!(changes[1].path, "$.b[1]");
+/// assert_eq!(changes[1].kind, ChangeKind::Removed);
+/// ```
+pub fn diff_report<L: JsonInput + ?Sized, R: JsonInput + ?Sized>(
+    old: &L,
+    new: &R,
+) -> Result<Vec<ChangeEntry>, Error> {
+    let old = old.to_value()?;
+    let new = new.to_value()?;
+    let mut changes = Vec::new();
+    report_values(Some(&old), Some(&new), &mut String::from("$"), &mut changes);
+    Ok(changes)
+}
+
+/// Compute a structural similarity score of two values between `0.0` and `1.0`,
+/// `1.0` means the values are equal.
+///
+/// The values are split into leaves, the scalars and the empty containers, with their paths.
+/// Objects are matched by keys and arrays by positions, the score is the number of
+/// leaves with the same path and value in both values over the number of distinct leaves.
+///
+/// ```
+/// use jsonb::similarity;
+///
+/// let score = similarity(r#"{"a":1,"b":[1,2]}"#, r#"{"a":1,"b":[1,3]}"#).unwrap();
+/// assert_eq!(score, 0.5);
+/// ```
+pub fn similarity<L: JsonInput + ?Sized, R: JsonInput + ?Sized>(
+    left: &L,
+    right: &R,
+) -> Result<f64, Error> {
+    let left = left.to_value()?;
+    let right = right.to_value()?;
+    let mut left_leaves = BTreeSet::new();
+    collect_leaves(&left, &mut String::from("$"), &mut left_leaves);
+    let mut right_leaves = BTreeSet::new();
+    collect_leaves(&right, &mut String::from("$"), &mut right_leaves);
+    let shared = left_leaves.intersection(&right_leaves).count();
+    let union = left_leaves.len() + right_leaves.len() - shared;
+    Ok(shared as f64 / union as f64)
+}
+
+// Collects the paths and the comparable values of the scalars and empty containers.
+fn collect_leaves(value: &Value<'_>, path: &mut String, leaves: &mut BTreeSet<(String, Vec<u8>)>) {
+    match value {
+        Value::Object(obj) if !obj.is_empty() => {
+            for (key, val) in obj.iter() {
+                let len = path.len();
+                push_key(path, key);
+                collect_leaves(val, path, leaves);
+                path.truncate(len);
+            }
+        }
+        Value::Array(vals) if !vals.is_empty() => {
+            for (i, val) in vals.iter().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{}]", i));
+                collect_leaves(val, path, leaves);
+                path.truncate(len);
+            }
+        }
+        _ => {
+            // The comparable vector is the same for equal numbers of different types.
+            let mut buf = Vec::new();
+            convert_to_comparable(value.to_vec().as_slice(), &mut buf);
+            leaves.insert((path.clone(), buf));
+        }
+    }
+}
+
+fn report_values(
+    old: Option<&Value<'_>>,
+    new: Option<&Value<'_>>,
+    path: &mut String,
+    changes: &mut Vec<ChangeEntry>,
+) {
+    match (old, new) {
+        (Some(Value::Object(l)), Some(Value::Object(r))) => {
+            let keys: BTreeSet<_> = l.keys().chain(r.keys()).collect();
+            for key in keys {
+                let len = path.len();
+                push_key(path, key);
+                report_values(l.get(key), r.get(key), path, changes);
+                path.truncate(len);
+            }
+        }
+        (Some(Value::Array(l)), Some(Value::Array(r))) => {
+            for i in 0..l.len().max(r.len()) {
+                let len = path.len();
+                path.push_str(&format!("[{}]", i));
+                report_values(l.get(i), r.get(i), path, changes);
+                path.truncate(len);
+            }
+        }
+        (Some(l), Some(r)) if l == r => {}
+        (None, None) => {}
+        (old, new) => {
+            let kind = match (old, new) {
+                (None, _) => ChangeKind::Added,
+                (_, None) => ChangeKind::Removed,
+                (_, _) => ChangeKind::Modified,
+            };
+            changes.push(ChangeEntry {
+                path: path.clone(),
+                kind,
+                old: old.map(|v| v.to_vec()),
+                new: new.map(|v| v.to_vec()),
+            });
+        }
+    }
+}
+
+fn diff_values<'a>(
+    left: &Value<'a>,
+    right: &Value<'a>,
+    path: &mut String,
+    ops: &mut Vec<Value<'a>>,
+    cancel: Option<&CancellationToken>,
+) -> Result<(), Error> {
+    match (left, right) {
+        (Value::Object(l), Value::Object(r)) => {
+            if let Some(cancel) = cancel {
+                cancel.check()?;
+            }
+            for (key, val) in l.iter() {
+                let len = path.len();
+                push_pointer_token(path, key);
+                match r.get(key) {
+                    Some(r_val) => diff_values(val, r_val, path, ops, cancel)?,
+                    None => ops.push(patch_op("remove", path, None)),
+                }
+                path.truncate(len);
+            }
+            for (key, val) in r.iter() {
+                if !l.contains_key(key) {
+                    let len = path.len();
+                    push_pointer_token(path, key);
+                    ops.push(patch_op("add", path, Some(val.clone())));
+                    path.truncate(len);
+                }
+            }
+        }
+        (Value::Array(l), Value::Array(r)) => {
+            if let Some(cancel) = cancel {
+                cancel.check()?;
+            }
+            for (i, (l_val, r_val)) in l.iter().zip(r.iter()).enumerate() {
+                let len = path.len();
+                push_pointer_token(path, &i.to_string());
+                diff_values(l_val, r_val, path, ops, cancel)?;
+                path.truncate(len);
+            }
+            // Removes from the end, so that the indexes of the remaining elements are not shifted.
+            for i in (r.len()..l.len()).rev() {
+                let len = path.len();
+                push_pointer_token(path, &i.to_string());
+                ops.push(patch_op("remove", path, None));
+                path.truncate(len);
+            }
+            for (i, val) in r.iter().enumerate().skip(l.len()) {
+                let len = path.len();
+                push_pointer_token(path, &i.to_string());
+                ops.push(patch_op("add", path, Some(val.clone())));
+                path.truncate(len);
+            }
+        }
+        (_, _) => {
+            if left != right {
+                ops.push(patch_op("replace", path, Some(right.clone())));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Escapes the token as JSON Pointer (RFC 6901).
+fn push_pointer_token(path: &mut String, token: &str) {
+    path.push('/');
+    path.push_str(&token.replace('~', "~0").replace('/', "~1"));
+}
+
+fn patch_op<'a>(op: &str, path: &str, value: Option<Value<'a>>) -> Value<'a> {
+    let mut obj = BTreeMap::new();
+    obj.insert("op".to_string(), Value::String(Cow::Owned(op.to_string())));
+    obj.insert(
+        "path".to_string(),
+        Value::String(Cow::Owned(path.to_string())),
+    );
+    if let Some(value) = value {
+        obj.insert("value".to_string(), value);
+    }
+    Value::Object(obj)
+}
@@ -0,0 +1,387 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The constants and low-level helpers of the `JSONB` binary encoding,
+//! for the tools that read or write the encoded values directly, such as storage compaction
+//! or readers in other languages.
+//!
+//! A value starts with a 4 bytes big-endian header, the high 3 bits are the container type
+//! and the low 29 bits are the number of elements (or fields of an object).
+//! The header is followed by a 4 bytes `JEntry` for each element, the high 4 bits are the type
+//! and the low 28 bits are the length of the data, then the data of the elements.
+//! An object has the `JEntry`s of all the keys before the values, the keys are sorted.
+//! A scalar is stored as the scalar header with a length of 0, followed by one `JEntry` and the data.
+//!
+//! ```
+//! use jsonb::encoding::{ContainerKind, ContainerReader, EntryKind};
+//!
+//! let value = jsonb::parse_value(br#"{"a":1,"b":[true]}"#).unwrap().to_vec();
+//! let reader = ContainerReader::new(&value).unwrap();
+//! assert_eq!(reader.kind(), ContainerKind::Object);
+//! assert_eq!(reader.key(1), Some("b"));
+//! let (entry, data) = reader.value(1).unwrap();
+//! assert_eq!(entry.kind, EntryKind::Container);
+//! assert_eq!(ContainerReader::new(data).unwrap().kind(), ContainerKind::Array);
+//! ```
+
+use crate::constants;
+use crate::error::Error;
+
+/// The size of the container header in bytes.
+pub const HEADER_SIZE: usize = 4;
+/// The size of a `JEntry` in bytes.
+pub const JENTRY_SIZE: usize = 4;
+
+/// The container type bits of an array header.
+pub const ARRAY_CONTAINER_TAG: u32 = constants::ARRAY_CONTAINER_TAG;
+/// The container type bits of an object header.
+pub const OBJECT_CONTAINER_TAG: u32 = constants::OBJECT_CONTAINER_TAG;
+/// The container type bits of a scalar header.
+pub const SCALAR_CONTAINER_TAG: u32 = constants::SCALAR_CONTAINER_TAG;
+/// The mask of the container type bits in the header.
+pub const CONTAINER_HEADER_TYPE_MASK: u32 = constants::CONTAINER_HEADER_TYPE_MASK;
+/// The mask of the number of elements in the header.
+pub const CONTAINER_HEADER_LEN_MASK: u32 = constants::CONTAINER_HEADER_LEN_MASK;
+
+/// The `JEntry` type bits of `null`.
+pub const NULL_TAG: u32 = constants::NULL_TAG;
+/// The `JEntry` type bits of a string, the data is the UTF-8 bytes.
+pub const STRING_TAG: u32 = constants::STRING_TAG;
+/// The `JEntry` type bits of a number, the data is the encoded [`Number`](crate::Number).
+pub const NUMBER_TAG: u32 = constants::NUMBER_TAG;
+/// The `JEntry` type bits of `false`.
+pub const FALSE_TAG: u32 = constants::FALSE_TAG;
+/// The `JEntry` type bits of `true`.
+pub const TRUE_TAG: u32 = constants::TRUE_TAG;
+/// The `JEntry` type bits of a nested container, the data is the encoded container.
+pub const CONTAINER_TAG: u32 = constants::CONTAINER_TAG;
+/// The `JEntry` type bits of an extension value, the data is the extension type and payload.
+pub const EXTENSION_TAG: u32 = constants::EXTENSION_TAG;
+/// The mask of the type bits in the `JEntry`.
+pub const JENTRY_TYPE_MASK: u32 = constants::JENTRY_TYPE_MASK;
+/// The mask of the data length in the `JEntry`.
+pub const JENTRY_OFF_LEN_MASK: u32 = constants::JENTRY_OFF_LEN_MASK;
+
+/// The type of a container.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerKind {
+    Scalar,
+    Array,
+    Object,
+}
+
+/// The decoded container header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub kind: ContainerKind,
+    /// The number of elements, or the number of fields of an object, 0 for a scalar.
+    pub length: usize,
+}
+
+impl Header {
+    pub fn decode(encoded: u32) -> Result<Header, Error> {
+        let kind = match encoded & CONTAINER_HEADER_TYPE_MASK {
+            ARRAY_CONTAINER_TAG => ContainerKind::Array,
+            OBJECT_CONTAINER_TAG => ContainerKind::Object,
+            SCALAR_CONTAINER_TAG => ContainerKind::Scalar,
+            _ => return Err(Error::InvalidJsonbHeader),
+        };
+        let length = (encoded & CONTAINER_HEADER_LEN_MASK) as usize;
+        Ok(Header { kind, length })
+    }
+
+    /// Returns `Error::InvalidJsonbHeader` if the length doesn't fit in the header.
+    pub fn encode(&self) -> Result<u32, Error> {
+        if self.length > CONTAINER_HEADER_LEN_MASK as usize {
+            return Err(Error::InvalidJsonbHeader);
+        }
+        let tag = match self.kind {
+            ContainerKind::Scalar => SCALAR_CONTAINER_TAG,
+            ContainerKind::Array => ARRAY_CONTAINER_TAG,
+            ContainerKind::Object => OBJECT_CONTAINER_TAG,
+        };
+        Ok(tag | self.length as u32)
+    }
+}
+
+/// The type of an element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Null,
+    String,
+    Number,
+    False,
+    True,
+    Container,
+    Extension,
+}
+
+/// The decoded `JEntry` of an element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub kind: EntryKind,
+    /// The length of the data in bytes.
+    pub length: usize,
+}
+
+impl Entry {
+    pub fn decode(encoded: u32) -> Result<Entry, Error> {
+        let kind = match encoded & JENTRY_TYPE_MASK {
+            NULL_TAG => EntryKind::Null,
+            STRING_TAG => EntryKind::String,
+            NUMBER_TAG => EntryKind::Number,
+            FALSE_TAG => EntryKind::False,
+            TRUE_TAG => EntryKind::True,
+            CONTAINER_TAG => EntryKind::Container,
+            EXTENSION_TAG => EntryKind::Extension,
+            _ => return Err(Error::InvalidJsonbJEntry),
+        };
+        let length = (encoded & JENTRY_OFF_LEN_MASK) as usize;
+        Ok(Entry { kind, length })
+    }
+
+    /// Returns `Error::InvalidJsonbJEntry` if the length doesn't fit in the `JEntry`.
+    pub fn encode(&self) -> Result<u32, Error> {
+        if self.length > JENTRY_OFF_LEN_MASK as usize {
+            return Err(Error::InvalidJsonbJEntry);
+        }
+        let tag = match self.kind {
+            EntryKind::Null => NULL_TAG,
+            EntryKind::String => STRING_TAG,
+            EntryKind::Number => NUMBER_TAG,
+            EntryKind::False => FALSE_TAG,
+            EntryKind::True => TRUE_TAG,
+            EntryKind::Container => CONTAINER_TAG,
+            EntryKind::Extension => EXTENSION_TAG,
+        };
+        Ok(tag | self.length as u32)
+    }
+}
+
+fn read_u32(value: &[u8], offset: usize) -> Result<u32, Error> {
+    let bytes = value.get(offset..offset + 4).ok_or(Error::InvalidEOF)?;
+    Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+/// A reader of the elements of an encoded container.
+/// The `JEntry`s are checked against the buffer when the reader is created,
+/// so the elements can be read without further bounds checks failing.
+/// Nested containers are read by a new reader on the data of the element.
+#[derive(Debug, Clone)]
+pub struct ContainerReader<'a> {
+    header: Header,
+    // The number of elements, 1 for a scalar.
+    length: usize,
+    // The entry and the data range of the keys followed by the values.
+    entries: Vec<(Entry, usize, usize)>,
+    value: &'a [u8],
+}
+
+impl<'a> ContainerReader<'a> {
+    pub fn new(value: &'a [u8]) -> Result<ContainerReader<'a>, Error> {
+        let header = Header::decode(read_u32(value, 0)?)?;
+        let length = match header.kind {
+            ContainerKind::Scalar => 1,
+            _ => header.length,
+        };
+        let count = match header.kind {
+            ContainerKind::Object => length * 2,
+            _ => length,
+        };
+        let mut data_offset = count
+            .checked_mul(JENTRY_SIZE)
+            .and_then(|size| size.checked_add(HEADER_SIZE))
+            .filter(|offset| *offset <= value.len())
+            .ok_or(Error::InvalidEOF)?;
+        let mut entries = Vec::with_capacity(count);
+        for i in 0..count {
+            let entry = Entry::decode(read_u32(value, HEADER_SIZE + i * JENTRY_SIZE)?)?;
+            let end = data_offset + entry.length;
+            if end > value.len() {
+                return Err(Error::InvalidEOF);
+            }
+            // The keys of an object must be strings.
+            if header.kind == ContainerKind::Object && i < header.length {
+                if entry.kind != EntryKind::String {
+                    return Err(Error::InvalidJsonbJEntry);
+                }
+                std::str::from_utf8(&value[data_offset..end])?;
+            }
+            entries.push((entry, data_offset, end));
+            data_offset = end;
+        }
+        Ok(ContainerReader {
+            header,
+            length,
+            entries,
+            value,
+        })
+    }
+
+    pub fn header(&self) -> Header {
+        self.header
+    }
+
+    pub fn kind(&self) -> ContainerKind {
+        self.header.kind
+    }
+
+    /// The number of elements, or the number of fields of an object, 1 for a scalar.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// The key of the field at `index`, or `None` if the container is not an object.
+    pub fn key(&self, index: usize) -> Option<&'a str> {
+        if self.header.kind != ContainerKind::Object || index >= self.header.length {
+            return None;
+        }
+        let (_, start, end) = self.entries[index];
+        // The keys are checked to be valid UTF-8 when the reader is created.
+        Some(unsafe { std::str::from_utf8_unchecked(&self.value[start..end]) })
+    }
+
+    /// The entry and the data of the element at `index`, the value of the field of an object,
+    /// or the value of a scalar at index 0.
+    pub fn value(&self, index: usize) -> Option<(Entry, &'a [u8])> {
+        if index >= self.length {
+            return None;
+        }
+        let index = match self.header.kind {
+            ContainerKind::Object => index + self.header.length,
+            _ => index,
+        };
+        let (entry, start, end) = self.entries[index];
+        Some((entry, &self.value[start..end]))
+    }
+
+    /// The length of the encoded container, the trailing bytes of the buffer are not included.
+    pub fn encoded_len(&self) -> usize {
+        self.entries
+            .last()
+            .map(|(_, _, end)| *end)
+            .unwrap_or(HEADER_SIZE)
+    }
+}
+
+/// A writer of an encoded array or object from the entries and the data of the elements.
+/// The keys of an object must be written in sorted order, as required by the encoding.
+#[derive(Debug, Clone)]
+pub struct ContainerWriter {
+    kind: ContainerKind,
+    keys: Vec<String>,
+    entries: Vec<u32>,
+    data: Vec<u8>,
+}
+
+impl ContainerWriter {
+    pub fn array() -> ContainerWriter {
+        ContainerWriter::new(ContainerKind::Array)
+    }
+
+    pub fn object() -> ContainerWriter {
+        ContainerWriter::new(ContainerKind::Object)
+    }
+
+    fn new(kind: ContainerKind) -> ContainerWriter {
+        ContainerWriter {
+            kind,
+            keys: Vec::new(),
+            entries: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    /// Write the key of the next field of an object, followed by the value.
+    pub fn push_key(&mut self, key: &str) -> Result<(), Error> {
+        if self.kind != ContainerKind::Object || self.keys.len() != self.entries.len() {
+            return Err(Error::InvalidJsonbHeader);
+        }
+        self.keys.push(key.to_string());
+        Ok(())
+    }
+
+    /// Write an element from the entry and the data, the length of the entry is the data length.
+    pub fn push_value(&mut self, kind: EntryKind, data: &[u8]) -> Result<(), Error> {
+        if self.kind == ContainerKind::Object && self.keys.len() != self.entries.len() + 1 {
+            return Err(Error::InvalidJsonbHeader);
+        }
+        let entry = Entry {
+            kind,
+            length: data.len(),
+        };
+        self.entries.push(entry.encode()?);
+        self.data.extend_from_slice(data);
+        Ok(())
+    }
+
+    /// Write an element from an encoded `JSONB` value, a scalar is unwrapped to its entry.
+    pub fn push_jsonb(&mut self, value: &[u8]) -> Result<(), Error> {
+        let reader = ContainerReader::new(value)?;
+        match reader.kind() {
+            ContainerKind::Scalar => {
+                let (entry, data) = reader.value(0).unwrap();
+                self.push_value(entry.kind, data)
+            }
+            _ => self.push_value(EntryKind::Container, &value[..reader.encoded_len()]),
+        }
+    }
+
+    /// Append the encoded container to `buf`.
+    pub fn finish(self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        if self.kind == ContainerKind::Object && self.keys.len() != self.entries.len() {
+            return Err(Error::InvalidJsonbHeader);
+        }
+        let header = Header {
+            kind: self.kind,
+            length: self.entries.len(),
+        };
+        buf.extend_from_slice(&header.encode()?.to_be_bytes());
+        for key in &self.keys {
+            let entry = Entry {
+                kind: EntryKind::String,
+                length: key.len(),
+            };
+            buf.extend_from_slice(&entry.encode()?.to_be_bytes());
+        }
+        for entry in &self.entries {
+            buf.extend_from_slice(&entry.to_be_bytes());
+        }
+        for key in &self.keys {
+            buf.extend_from_slice(key.as_bytes());
+        }
+        buf.extend_from_slice(&self.data);
+        Ok(())
+    }
+}
+
+/// Append a scalar value encoded from the entry and the data to `buf`.
+pub fn write_scalar(kind: EntryKind, data: &[u8], buf: &mut Vec<u8>) -> Result<(), Error> {
+    let header = Header {
+        kind: ContainerKind::Scalar,
+        length: 0,
+    };
+    let entry = Entry {
+        kind,
+        length: data.len(),
+    };
+    buf.extend_from_slice(&header.encode()?.to_be_bytes());
+    buf.extend_from_slice(&entry.encode()?.to_be_bytes());
+    buf.extend_from_slice(data);
+    Ok(())
+}
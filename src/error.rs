@@ -32,6 +32,7 @@ pub enum ParseErrorCode {
     InvalidLoneLeadingSurrogateInHexEscape(u16),
     InvalidSurrogateInHexEscape(u16),
     UnexpectedEndOfHexEscape,
+    RecursionLimitExceeded,
 }
 
 impl Display for ParseErrorCode {
@@ -63,6 +64,61 @@ impl Display for ParseErrorCode {
                 write!(f, "invalid surrogate in hex escape '{:X}'", n)
             }
             ParseErrorCode::UnexpectedEndOfHexEscape => f.write_str("unexpected end of hex escape"),
+            ParseErrorCode::RecursionLimitExceeded => f.write_str("recursion limit exceeded"),
+        }
+    }
+}
+
+/// The reason of a strict-mode `JSONPath` evaluation error.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathErrorCode {
+    /// A member accessor or `.*` is applied to a value that is not an object.
+    ObjectExpected,
+    /// An array subscript or `[*]` is applied to a value that is not an array.
+    ArrayExpected,
+    /// The object doesn't have the member.
+    MemberNotFound(String),
+    /// The array subscript is out of bounds, `last` is resolved to the index.
+    IndexOutOfBounds(i32),
+    /// A numeric item method is applied to a value that is not a number,
+    /// `double()` and `number()` also accept a string of a number.
+    NumberExpected,
+}
+
+impl Display for PathErrorCode {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            PathErrorCode::ObjectExpected => f.write_str("expected an object"),
+            PathErrorCode::ArrayExpected => f.write_str("expected an array"),
+            PathErrorCode::MemberNotFound(name) => write!(f, "member {:?} not found", name),
+            PathErrorCode::IndexOutOfBounds(index) => {
+                write!(f, "array index {} out of bounds", index)
+            }
+            PathErrorCode::NumberExpected => f.write_str("expected a number or a numeric string"),
+        }
+    }
+}
+
+/// The reason why a value can't be cast exactly to the target type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CastErrorCode {
+    /// The value is not a number.
+    NotNumber,
+    /// The number is a float with a fractional part.
+    Fractional,
+    /// The number is a float `NaN` or infinity.
+    NotFinite,
+    /// The number is out of the range of the target type.
+    OutOfRange,
+}
+
+impl Display for CastErrorCode {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            CastErrorCode::NotNumber => f.write_str("not a number"),
+            CastErrorCode::Fractional => f.write_str("number has a fractional part"),
+            CastErrorCode::NotFinite => f.write_str("number is not finite"),
+            CastErrorCode::OutOfRange => f.write_str("number out of range"),
         }
     }
 }
@@ -80,14 +136,35 @@ pub enum Error {
     InvalidJsonbJEntry,
 
     InvalidJsonPath,
+    InvalidJqFilter,
+    InvalidJsonType,
+    InvalidKeyPath,
+    ObjectDuplicateKey,
+
+    DuplicateExtensionType(u8),
+    UnknownExtensionType(u8),
 
     Syntax(ParseErrorCode, usize),
+    /// An exact cast error, such as a float with a fractional part cast to an integer.
+    Cast(CastErrorCode),
+    /// A strict-mode `JSONPath` evaluation error, with the path of the value
+    /// at which the evaluation stopped, like `$.a[1]`.
+    JsonPath(PathErrorCode, String),
+
+    Io(std::io::ErrorKind),
+
+    /// The memory can't be reserved by the [`MemoryTracker`](crate::MemoryTracker).
+    MemoryLimitExceeded,
+    /// The operation is interrupted by the [`CancellationToken`](crate::CancellationToken).
+    Cancelled,
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Error::Syntax(code, pos) => write!(f, "{}, pos {}", code, pos),
+            Error::JsonPath(code, path) => write!(f, "{}, path {}", code, path),
+            Error::Cast(code) => write!(f, "{}", code),
             _ => write!(f, "{:?}", self),
         }
     }
@@ -0,0 +1,155 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+use super::constants::*;
+use super::error::Error;
+use super::jentry::JEntry;
+use super::value::Value;
+
+// Extension values are stored as a scalar with the `EXTENSION_TAG` `JEntry`,
+// the `RawData` starts with one byte of extension type tag followed by the payload.
+
+/// A user-defined scalar type that can be stored inside `JSONB` values.
+///
+/// The payload of an extension value is an opaque byte sequence owned by the
+/// extension type, the callbacks describe how to build, read and print it.
+pub trait ExtensionType: Send + Sync {
+    /// The name of the extension type, such as `geo_point`.
+    fn name(&self) -> &str;
+
+    /// Convert the application data into the payload stored in `JSONB`.
+    /// The default implementation stores the data unchanged.
+    fn encode(&self, data: &[u8], buf: &mut Vec<u8>) -> Result<(), Error> {
+        buf.extend_from_slice(data);
+        Ok(())
+    }
+
+    /// Convert the payload to a plain `JSONB` Value.
+    /// The extension values are ordered by their plain values, so that the order is the same
+    /// for the comparable keys and with the other types of values. The plain value is also used
+    /// when it is converted to a type that does not support extension values.
+    /// The plain value can't contain extension values.
+    fn decode(&self, payload: &[u8]) -> Result<Value<'static>, Error>;
+
+    /// Format the payload as `JSON` text.
+    /// The default implementation formats the decoded plain value.
+    fn format(&self, payload: &[u8], buf: &mut String) {
+        match self.decode(payload) {
+            Ok(val) => buf.push_str(&val.to_string()),
+            Err(_) => buf.push_str("null"),
+        }
+    }
+}
+
+static EXTENSION_TYPES: RwLock<BTreeMap<u8, Arc<dyn ExtensionType>>> = RwLock::new(BTreeMap::new());
+
+/// Register an extension type with the tag.
+/// Each tag can only be claimed by one extension type.
+pub fn register_extension_type(tag: u8, ty: Arc<dyn ExtensionType>) -> Result<(), Error> {
+    let mut types = EXTENSION_TYPES.write().unwrap();
+    if types.contains_key(&tag) {
+        return Err(Error::DuplicateExtensionType(tag));
+    }
+    types.insert(tag, ty);
+    Ok(())
+}
+
+/// Remove the extension type registered with the tag, returns the removed extension type.
+pub fn unregister_extension_type(tag: u8) -> Option<Arc<dyn ExtensionType>> {
+    let mut types = EXTENSION_TYPES.write().unwrap();
+    types.remove(&tag)
+}
+
+/// Get the extension type registered with the tag.
+pub fn get_extension_type(tag: u8) -> Option<Arc<dyn ExtensionType>> {
+    let types = EXTENSION_TYPES.read().unwrap();
+    types.get(&tag).cloned()
+}
+
+/// An extension value, consists of the tag of extension type and the encoded payload.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ExtensionValue<'a> {
+    pub tag: u8,
+    pub payload: Cow<'a, [u8]>,
+}
+
+impl<'a> ExtensionValue<'a> {
+    /// Build an extension value from the application data,
+    /// the data is encoded by the extension type registered with the tag.
+    pub fn new(tag: u8, data: &[u8]) -> Result<ExtensionValue<'static>, Error> {
+        let ty = get_extension_type(tag).ok_or(Error::UnknownExtensionType(tag))?;
+        let mut payload = Vec::new();
+        ty.encode(data, &mut payload)?;
+        Ok(ExtensionValue {
+            tag,
+            payload: Cow::Owned(payload),
+        })
+    }
+
+    /// Convert the extension value to a plain `JSONB` Value.
+    pub fn to_plain_value(&self) -> Result<Value<'static>, Error> {
+        let ty = get_extension_type(self.tag).ok_or(Error::UnknownExtensionType(self.tag))?;
+        ty.decode(&self.payload)
+    }
+
+    /// Format the extension value as `JSON` text.
+    pub(crate) fn format(&self, buf: &mut String) {
+        format_extension(self.tag, &self.payload, buf)
+    }
+}
+
+pub(crate) fn format_extension(tag: u8, payload: &[u8], buf: &mut String) {
+    match get_extension_type(tag) {
+        Some(ty) => ty.format(payload, buf),
+        None => buf.push_str("null"),
+    }
+}
+
+// Convert the raw data of an extension value to the `JEntry` and raw data
+// of the plain value, so that it can be compared with builtin values.
+// Returns None if the plain value contains extension values, which could be decoded endlessly.
+pub(crate) fn extension_to_plain(raw: &[u8]) -> Option<(JEntry, Vec<u8>)> {
+    let (tag, payload) = raw.split_first()?;
+    let ty = get_extension_type(*tag)?;
+    let value = ty.decode(payload).ok()?;
+    if contains_extension(&value) {
+        return None;
+    }
+    let buf = value.to_vec();
+    let header = u32::from_be_bytes(buf[0..4].try_into().unwrap());
+    if header & CONTAINER_HEADER_TYPE_MASK == SCALAR_CONTAINER_TAG {
+        let encoded = u32::from_be_bytes(buf[4..8].try_into().unwrap());
+        Some((JEntry::decode_jentry(encoded), buf[8..].to_vec()))
+    } else {
+        Some((JEntry::make_container_jentry(buf.len()), buf))
+    }
+}
+
+fn contains_extension(value: &Value<'_>) -> bool {
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        match value {
+            Value::Extension(_) => return true,
+            Value::Array(values) => stack.extend(values),
+            Value::Object(obj) => stack.extend(obj.values()),
+            _ => {}
+        }
+    }
+    false
+}
@@ -0,0 +1,217 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! C ABI of the core functions.
+//!
+//! All functions return a status code, `JSONB_OK` on success, and write the results
+//! to the output arguments. The input bytes are borrowed for the duration of the call.
+//! Output buffers are allocated by this library and owned by the caller,
+//! they must be released with [`jsonb_buffer_free`], exactly once.
+//! The `JSONB` inputs are validated before they are decoded, and a panic never unwinds
+//! across the boundary, it is reported as `JSONB_ERROR_INTERNAL` instead.
+//!
+//! ```c
+//! JsonbBuffer buf;
+//! if (jsonb_parse(text, strlen(text), &buf) == JSONB_OK) {
+//!     /* use buf.data and buf.len */
+//!     jsonb_buffer_free(buf);
+//! }
+//! ```
+
+use std::cmp::Ordering;
+use std::panic::catch_unwind;
+use std::panic::AssertUnwindSafe;
+use std::slice;
+
+use crate::functions::compare;
+use crate::functions::get_by_path_array;
+use crate::functions::to_string;
+use crate::input::RawJsonb;
+use crate::jsonpath::parse_json_path;
+use crate::layout::validate;
+use crate::parser::parse_value;
+
+/// The call succeeded.
+pub const JSONB_OK: i32 = 0;
+/// A pointer argument is null.
+pub const JSONB_ERROR_NULL_POINTER: i32 = -1;
+/// The input is not valid `JSON` text.
+pub const JSONB_ERROR_INVALID_JSON: i32 = -2;
+/// The input is not valid `JSONB`.
+pub const JSONB_ERROR_INVALID_JSONB: i32 = -3;
+/// The path is not a valid `JSONPath`.
+pub const JSONB_ERROR_INVALID_PATH: i32 = -4;
+/// The call failed unexpectedly.
+pub const JSONB_ERROR_INTERNAL: i32 = -5;
+
+/// A buffer allocated by this library, released by [`jsonb_buffer_free`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct JsonbBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+    pub cap: usize,
+}
+
+impl JsonbBuffer {
+    fn from_vec(buf: Vec<u8>) -> Self {
+        let mut buf = std::mem::ManuallyDrop::new(buf);
+        Self {
+            data: buf.as_mut_ptr(),
+            len: buf.len(),
+            cap: buf.capacity(),
+        }
+    }
+}
+
+// Runs the body of an exported function, unwinding into the caller is undefined behavior.
+fn guard(f: impl FnOnce() -> i32) -> i32 {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(JSONB_ERROR_INTERNAL)
+}
+
+/// Parse the `JSON` text and write the encoded `JSONB` to `out`.
+///
+/// # Safety
+///
+/// `text` must point to `len` readable bytes, `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jsonb_parse(text: *const u8, len: usize, out: *mut JsonbBuffer) -> i32 {
+    if text.is_null() || out.is_null() {
+        return JSONB_ERROR_NULL_POINTER;
+    }
+    let text = slice::from_raw_parts(text, len);
+    guard(|| match parse_value(text) {
+        Ok(value) => {
+            out.write(JsonbBuffer::from_vec(value.to_vec()));
+            JSONB_OK
+        }
+        Err(_) => JSONB_ERROR_INVALID_JSON,
+    })
+}
+
+/// Write the `JSONB` value as `JSON` text to `out`, the text is not null-terminated.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jsonb_to_string(
+    data: *const u8,
+    len: usize,
+    out: *mut JsonbBuffer,
+) -> i32 {
+    if data.is_null() || out.is_null() {
+        return JSONB_ERROR_NULL_POINTER;
+    }
+    let data = slice::from_raw_parts(data, len);
+    if validate(data).is_err() {
+        return JSONB_ERROR_INVALID_JSONB;
+    }
+    guard(|| {
+        let text = to_string(&RawJsonb::new(data));
+        out.write(JsonbBuffer::from_vec(text.into_bytes()));
+        JSONB_OK
+    })
+}
+
+/// Select the values of the `JSONB` value by the `JSONPath`,
+/// and write them as an encoded `JSONB` array to `out`.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, `path` must point to `path_len` readable bytes,
+/// `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jsonb_get_by_path(
+    data: *const u8,
+    len: usize,
+    path: *const u8,
+    path_len: usize,
+    out: *mut JsonbBuffer,
+) -> i32 {
+    if data.is_null() || path.is_null() || out.is_null() {
+        return JSONB_ERROR_NULL_POINTER;
+    }
+    let data = slice::from_raw_parts(data, len);
+    let path = slice::from_raw_parts(path, path_len);
+    if validate(data).is_err() {
+        return JSONB_ERROR_INVALID_JSONB;
+    }
+    guard(|| {
+        let Ok(json_path) = parse_json_path(path) else {
+            return JSONB_ERROR_INVALID_PATH;
+        };
+        match get_by_path_array(&RawJsonb::new(data), json_path) {
+            Some(values) => {
+                out.write(JsonbBuffer::from_vec(values));
+                JSONB_OK
+            }
+            None => JSONB_ERROR_INVALID_JSONB,
+        }
+    })
+}
+
+/// Compare two `JSONB` values, and write `-1`, `0` or `1` to `out`
+/// if the left value is less than, equal to or greater than the right value.
+///
+/// # Safety
+///
+/// `left` must point to `left_len` readable bytes, `right` must point to `right_len` readable bytes,
+/// `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jsonb_compare(
+    left: *const u8,
+    left_len: usize,
+    right: *const u8,
+    right_len: usize,
+    out: *mut i32,
+) -> i32 {
+    if left.is_null() || right.is_null() || out.is_null() {
+        return JSONB_ERROR_NULL_POINTER;
+    }
+    let left = slice::from_raw_parts(left, left_len);
+    let right = slice::from_raw_parts(right, right_len);
+    if validate(left).is_err() || validate(right).is_err() {
+        return JSONB_ERROR_INVALID_JSONB;
+    }
+    guard(
+        || match compare(&RawJsonb::new(left), &RawJsonb::new(right)) {
+            Ok(order) => {
+                let res = match order {
+                    Ordering::Less => -1,
+                    Ordering::Equal => 0,
+                    Ordering::Greater => 1,
+                };
+                out.write(res);
+                JSONB_OK
+            }
+            Err(_) => JSONB_ERROR_INVALID_JSONB,
+        },
+    )
+}
+
+/// Release the buffer returned by the functions of this library.
+///
+/// # Safety
+///
+/// The buffer must be returned by this library and not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn jsonb_buffer_free(buf: JsonbBuffer) {
+    if !buf.data.is_null() {
+        guard(|| {
+            drop(Vec::from_raw_parts(buf.data, buf.len, buf.cap));
+            JSONB_OK
+        });
+    }
+}
@@ -0,0 +1,180 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::Error;
+use crate::input::JsonInput;
+use crate::jsonpath::JsonPath;
+use crate::rewrite::path_steps;
+use crate::rewrite::PathStep;
+use crate::value::Object;
+use crate::value::Value;
+
+/// Keep the values of a `JSONB` value at the paths of the `allowlist` and remove the values
+/// at the paths of the `denylist`, and write the filtered value to `buf`.
+/// This enforces the field-level permissions, the paths a caller may see, in one pass.
+///
+/// - An empty `allowlist` allows all the values, otherwise only the values at the allowed paths,
+///   including their nested values, are kept, with the containers leading to them.
+///   A nested container that has none of the allowed paths is removed.
+/// - The `denylist` takes precedence, the values at the denied paths are always removed,
+///   including the values at the allowed paths nested in them.
+/// - The removed elements of an array are skipped, the paths match the original indices.
+/// - If the whole value is denied or is a scalar that is not allowed, `null` is written.
+///
+/// The paths can only have the member accessors, non-negative array indices and wildcards,
+/// like `$.a[0].*`, otherwise returns `Error::InvalidJsonPath`.
+///
+/// ```
+/// use jsonb::filter_paths;
+/// use jsonb::jsonpath::parse_json_path;
+///
+/// let value = r#"{"id":1,"user":{"name":"a","ssn":"x"},"tags":[{"k":1,"v":2}]}"#;
+/// let allow = [
+///     parse_json_path(b"$.user").unwrap(),
+///     parse_json_path(b"$.tags[*].k").unwrap(),
+/// ];
+/// let deny = [parse_json_path(b"$.user.ssn").unwrap()];
+/// let mut buf = Vec::new();
+/// filter_paths(value, &allow, &deny, &mut buf).unwrap();
+/// assert_eq!(jsonb::to_string(&buf), r#"{"tags":[{"k":1}],"user":{"name":"a"}}"#);
+/// ```
+pub fn filter_paths<T: JsonInput + ?Sized>(
+    value: &T,
+    allowlist: &[JsonPath<'_>],
+    denylist: &[JsonPath<'_>],
+    buf: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let allow = allowlist
+        .iter()
+        .map(path_steps)
+        .collect::<Result<Vec<_>, _>>()?;
+    let deny = denylist
+        .iter()
+        .map(path_steps)
+        .collect::<Result<Vec<_>, _>>()?;
+    let filter = Filter {
+        allow: &allow,
+        deny: &deny,
+    };
+    let value = filter
+        .filter(value.to_value()?, &mut Vec::new(), allow.is_empty())
+        .unwrap_or(Value::Null);
+    value.write_to_vec(buf);
+    Ok(())
+}
+
+// A step of the location of a value in the document.
+enum Location {
+    Key(String),
+    Index(usize),
+}
+
+// How a path relates to the location of a value.
+enum Match {
+    // The path locates the value or one of its ancestors.
+    Covers,
+    // The path locates some of the nested values.
+    Leads,
+    None,
+}
+
+struct Filter<'p> {
+    allow: &'p [Vec<PathStep>],
+    deny: &'p [Vec<PathStep>],
+}
+
+impl<'p> Filter<'p> {
+    // Returns the filtered value, or `None` if the value is removed.
+    // If `allowed` is true, the value is at an allowed path and only the denied values are removed.
+    fn filter<'a>(
+        &self,
+        value: Value<'a>,
+        location: &mut Vec<Location>,
+        allowed: bool,
+    ) -> Option<Value<'a>> {
+        let mut denied_nested = false;
+        for path in self.deny {
+            match path_match(path, location) {
+                Match::Covers => return None,
+                Match::Leads => denied_nested = true,
+                Match::None => {}
+            }
+        }
+        let mut allowed = allowed;
+        let mut allowed_nested = false;
+        for path in self.allow {
+            match path_match(path, location) {
+                Match::Covers => allowed = true,
+                Match::Leads => allowed_nested = true,
+                Match::None => {}
+            }
+        }
+        if allowed && !denied_nested {
+            return Some(value);
+        }
+        if !allowed && !allowed_nested {
+            return None;
+        }
+        let value = match value {
+            Value::Array(vals) => {
+                let mut filtered = Vec::with_capacity(vals.len());
+                for (i, val) in vals.into_iter().enumerate() {
+                    location.push(Location::Index(i));
+                    filtered.extend(self.filter(val, location, allowed));
+                    location.pop();
+                }
+                if !allowed && filtered.is_empty() && !location.is_empty() {
+                    return None;
+                }
+                Value::Array(filtered)
+            }
+            Value::Object(obj) => {
+                let mut filtered = Object::new();
+                for (key, val) in obj {
+                    location.push(Location::Key(key));
+                    let val = self.filter(val, location, allowed);
+                    if let (Some(Location::Key(key)), Some(val)) = (location.pop(), val) {
+                        filtered.insert(key, val);
+                    }
+                }
+                if !allowed && filtered.is_empty() && !location.is_empty() {
+                    return None;
+                }
+                Value::Object(filtered)
+            }
+            // The scalars have no nested values for the paths leading into them.
+            _ if allowed => value,
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+fn path_match(path: &[PathStep], location: &[Location]) -> Match {
+    let matched = path
+        .iter()
+        .zip(location)
+        .all(|(step, location)| match (step, location) {
+            (PathStep::Key(name), Location::Key(key)) => name == key,
+            (PathStep::AnyKey, Location::Key(_)) => true,
+            (PathStep::Index(idx), Location::Index(i)) => idx == i,
+            (PathStep::AnyIndex, Location::Index(_)) => true,
+            _ => false,
+        });
+    match (matched, path.len() <= location.len()) {
+        (false, _) => Match::None,
+        (true, true) => Match::Covers,
+        (true, false) => Match::Leads,
+    }
+}
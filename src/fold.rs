@@ -0,0 +1,112 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use caseless::Caseless;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::from_slice;
+use crate::functions::get_by_path;
+use crate::input::JsonInput;
+use crate::jsonpath::JsonPath;
+use crate::value::Value;
+
+/// How the strings are folded before they are compared by [`string_equals`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fold {
+    /// Compare the strings with the Unicode full case folding, like `"STRASSE"` and `"straße"`.
+    Case,
+    /// Compare the strings with the case folding, ignoring the accents and other combining marks
+    /// of the canonical decomposition, like `"Cafe"` and `"café"`.
+    CaseAndAccent,
+}
+
+impl Fold {
+    // The folded string, two strings are equal under the fold if the folded strings are equal.
+    // The strings are compared in the canonical decomposition, so the composed and decomposed
+    // forms of a character are equal.
+    fn fold(self, s: &str) -> String {
+        match self {
+            Fold::Case => s.chars().nfd().default_case_fold().nfd().collect(),
+            Fold::CaseAndAccent => s
+                .chars()
+                .nfd()
+                .filter(|c| !is_combining_mark(*c))
+                .default_case_fold()
+                .nfd()
+                .filter(|c| !is_combining_mark(*c))
+                .collect(),
+        }
+    }
+}
+
+/// Returns true if any of the string leaves of a `JSONB` value, the value itself or the
+/// nested strings of the containers, is equal to the needle under the fold.
+/// The keys of the objects are not compared.
+///
+/// ```
+/// use jsonb::string_equals;
+/// use jsonb::Fold;
+///
+/// let value = r#"{"city":"Zürich","tags":["CAFÉ"]}"#;
+/// assert!(string_equals(value, "zürich", Fold::Case));
+/// assert!(!string_equals(value, "zurich", Fold::Case));
+/// assert!(string_equals(value, "cafe", Fold::CaseAndAccent));
+/// assert!(!string_equals(value, "city", Fold::CaseAndAccent));
+/// ```
+pub fn string_equals<T: JsonInput + ?Sized>(value: &T, needle: &str, fold: Fold) -> bool {
+    let Ok(value) = value.to_value() else {
+        return false;
+    };
+    let needle = fold.fold(needle);
+    leaf_equals(&value, &needle, fold)
+}
+
+/// Returns true if any of the values selected by the JSON path is a string equal to the
+/// needle under the fold, the nested strings of the selected containers are not compared.
+///
+/// ```
+/// use jsonb::jsonpath::parse_json_path;
+/// use jsonb::string_equals_by_path;
+/// use jsonb::Fold;
+///
+/// let value = r#"{"name":"Ærøskøbing","alias":["Aeroskobing"]}"#;
+/// let json_path = parse_json_path(b"$.name").unwrap();
+/// assert!(string_equals_by_path(value, json_path, "ÆRØSKØBING", Fold::Case));
+/// let json_path = parse_json_path(b"$.alias").unwrap();
+/// assert!(!string_equals_by_path(value, json_path, "aeroskobing", Fold::Case));
+/// ```
+pub fn string_equals_by_path<'a, T: JsonInput + ?Sized>(
+    value: &T,
+    json_path: JsonPath<'a>,
+    needle: &str,
+    fold: Fold,
+) -> bool {
+    let needle = fold.fold(needle);
+    get_by_path(value, json_path)
+        .iter()
+        .any(|val| match from_slice(val) {
+            Ok(Value::String(s)) => fold.fold(&s) == needle,
+            _ => false,
+        })
+}
+
+fn leaf_equals(value: &Value<'_>, needle: &str, fold: Fold) -> bool {
+    match value {
+        Value::String(s) => fold.fold(s) == needle,
+        Value::Array(vals) => vals.iter().any(|val| leaf_equals(val, needle, fold)),
+        Value::Object(obj) => obj.values().any(|val| leaf_equals(val, needle, fold)),
+        _ => false,
+    }
+}
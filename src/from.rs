@@ -214,6 +214,10 @@ impl<'a> From<Value<'a>> for JsonValue {
                 }
                 JsonValue::Object(map)
             }
+            Value::Extension(v) => match v.to_plain_value() {
+                Ok(val) => val.into(),
+                Err(_) => JsonValue::Null,
+            },
         }
     }
 }
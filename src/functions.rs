@@ -16,28 +16,42 @@ use core::convert::TryInto;
 use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::VecDeque;
+use std::fmt::Write;
+use std::ops::Range;
 
 use crate::constants::*;
 use crate::error::*;
+use crate::extension::extension_to_plain;
+use crate::extension::format_extension;
+use crate::input::AsJsonb;
+use crate::input::JsonInput;
+use crate::input::RawJsonb;
 use crate::jentry::JEntry;
 use crate::jsonpath::JsonPath;
 use crate::jsonpath::Selector;
+use crate::jsonpath::SelectorOptions;
+use crate::layout::validate;
+use crate::layout::validate_scalar;
+use crate::memory::MemoryTracker;
 use crate::number::Number;
-use crate::parser::parse_value;
+use crate::util::escape_str_into;
+use crate::util::parse_timestamp;
 use crate::value::Object;
 use crate::value::Value;
 use rand::distributions::Alphanumeric;
 use rand::distributions::DistString;
 use rand::thread_rng;
 use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 
 // builtin functions for `JSONB` bytes and `JSON` strings without decode all Values.
 // The input value must be valid `JSONB' or `JSON`.
 
 /// Build `JSONB` array from items.
-/// Assuming that the input values is valid JSONB data.
-pub fn build_array<'a>(
-    items: impl IntoIterator<Item = &'a [u8]>,
+/// Assuming that the input bytes are valid JSONB data.
+pub fn build_array<T: AsJsonb>(
+    items: impl IntoIterator<Item = T>,
     buf: &mut Vec<u8>,
 ) -> Result<(), Error> {
     let start = buf.len();
@@ -45,7 +59,9 @@ pub fn build_array<'a>(
     buf.resize(start + 4, 0);
     let mut len: u32 = 0;
     let mut data = Vec::new();
-    for value in items.into_iter() {
+    for item in items.into_iter() {
+        let value = item.as_jsonb()?;
+        let value = value.as_ref();
         let header = read_u32(value, 0)?;
         let encoded_jentry = match header & CONTAINER_HEADER_TYPE_MASK {
             SCALAR_CONTAINER_TAG => {
@@ -73,65 +89,64 @@ pub fn build_array<'a>(
 }
 
 /// Build `JSONB` object from items.
-/// Assuming that the input values is valid JSONB data.
-pub fn build_object<'a, K: AsRef<str>>(
-    items: impl IntoIterator<Item = (K, &'a [u8])>,
+/// Assuming that the input bytes are valid JSONB data.
+/// The keys are sorted like the objects encoded from `Value`, which the readers depend on.
+pub fn build_object<K: AsRef<str>, V: AsJsonb>(
+    items: impl IntoIterator<Item = (K, V)>,
     buf: &mut Vec<u8>,
 ) -> Result<(), Error> {
     let start = buf.len();
     // reserve space for header
     buf.resize(start + 4, 0);
-    let mut len: u32 = 0;
-    let mut key_data = Vec::new();
-    let mut val_data = Vec::new();
-    let mut val_jentries = VecDeque::new();
-    for (key, value) in items.into_iter() {
-        let key = key.as_ref();
-        // write key jentry and key data
-        let encoded_key_jentry = (STRING_TAG | key.len() as u32).to_be_bytes();
-        buf.extend_from_slice(&encoded_key_jentry);
-        key_data.extend_from_slice(key.as_bytes());
+    let mut entries = Vec::new();
+    for (key, item) in items.into_iter() {
+        let value = item.as_jsonb()?;
+        let value = value.as_ref();
 
-        // build value jentry and write value data
+        // build value jentry and value data
         let header = read_u32(value, 0)?;
-        let encoded_val_jentry = match header & CONTAINER_HEADER_TYPE_MASK {
+        let (encoded_val_jentry, val_data) = match header & CONTAINER_HEADER_TYPE_MASK {
             SCALAR_CONTAINER_TAG => {
                 let jentry = &value[4..8];
-                val_data.extend_from_slice(&value[8..]);
-                jentry.try_into().unwrap()
-            }
-            ARRAY_CONTAINER_TAG | OBJECT_CONTAINER_TAG => {
-                val_data.extend_from_slice(value);
-                (CONTAINER_TAG | value.len() as u32).to_be_bytes()
+                (jentry.try_into().unwrap(), value[8..].to_vec())
             }
+            ARRAY_CONTAINER_TAG | OBJECT_CONTAINER_TAG => (
+                (CONTAINER_TAG | value.len() as u32).to_be_bytes(),
+                value.to_vec(),
+            ),
             _ => return Err(Error::InvalidJsonbHeader),
         };
-        val_jentries.push_back(encoded_val_jentry);
-        len += 1;
+        entries.push((key, encoded_val_jentry, val_data));
     }
-    // write header and value jentry
-    let header = OBJECT_CONTAINER_TAG | len;
+    entries.sort_by(|(left, _, _), (right, _, _)| left.as_ref().cmp(right.as_ref()));
+
+    // write header, key jentries and value jentries
+    let header = OBJECT_CONTAINER_TAG | entries.len() as u32;
     for (i, b) in header.to_be_bytes().iter().enumerate() {
         buf[start + i] = *b;
     }
-    while let Some(val_jentry) = val_jentries.pop_front() {
-        buf.extend_from_slice(&val_jentry);
+    for (key, _, _) in entries.iter() {
+        let encoded_key_jentry = (STRING_TAG | key.as_ref().len() as u32).to_be_bytes();
+        buf.extend_from_slice(&encoded_key_jentry);
+    }
+    for (_, val_jentry, _) in entries.iter() {
+        buf.extend_from_slice(val_jentry);
     }
     // write key data and value data
-    buf.extend_from_slice(&key_data);
-    buf.extend_from_slice(&val_data);
+    for (key, _, _) in entries.iter() {
+        buf.extend_from_slice(key.as_ref().as_bytes());
+    }
+    for (_, _, val_data) in entries.iter() {
+        buf.extend_from_slice(val_data);
+    }
 
     Ok(())
 }
 
 /// Get the length of `JSONB` array.
-pub fn array_length(value: &[u8]) -> Option<usize> {
-    if !is_jsonb(value) {
-        return match parse_value(value) {
-            Ok(val) => val.array_length(),
-            Err(_) => None,
-        };
-    }
+pub fn array_length<T: JsonInput + ?Sized>(value: &T) -> Option<usize> {
+    let buf = value.to_jsonb().ok()?;
+    let value = buf.as_ref();
     let header = read_u32(value, 0).unwrap();
     match header & CONTAINER_HEADER_TYPE_MASK {
         ARRAY_CONTAINER_TAG => {
@@ -143,26 +158,79 @@ pub fn array_length(value: &[u8]) -> Option<usize> {
 }
 
 /// Get the inner elements of `JSONB` value by JSON path.
-/// The return value may contains multiple matching elements.
-pub fn get_by_path<'a>(value: &'a [u8], json_path: JsonPath<'a>) -> Vec<Vec<u8>> {
-    let selector = Selector::new(json_path);
-    if !is_jsonb(value) {
-        match parse_value(value) {
-            Ok(val) => {
-                let value = val.to_vec();
-                selector.select(value.as_slice())
-            }
-            Err(_) => vec![],
-        }
-    } else {
-        selector.select(value)
+/// The return value may contains multiple matching elements, in the order of the document.
+pub fn get_by_path<'a, T: JsonInput + ?Sized>(value: &T, json_path: JsonPath<'a>) -> Vec<Vec<u8>> {
+    get_by_path_with_options(value, json_path, SelectorOptions::default())
+}
+
+/// Get the inner elements of `JSONB` value by JSON path,
+/// the order and the duplicates of the elements are controlled by the options.
+pub fn get_by_path_with_options<'a, T: JsonInput + ?Sized>(
+    value: &T,
+    json_path: JsonPath<'a>,
+    options: SelectorOptions,
+) -> Vec<Vec<u8>> {
+    let selector = Selector::with_options(json_path, options);
+    match value.to_jsonb() {
+        Ok(buf) => selector.select(buf.as_ref()),
+        Err(_) => vec![],
     }
 }
 
+/// Get the inner elements of `JSONB` value by JSON path, the variables in the filter
+/// expressions, like `$min_qty` in `$.items[*]?(@.qty > $min_qty)`, are the fields of
+/// the `vars` object, like `jsonb_path_query(target, path, vars)` in Postgres.
+/// The paths of the missing variables select no values.
+pub fn get_by_path_with_vars<'a, T: JsonInput + ?Sized, V: JsonInput + ?Sized>(
+    value: &T,
+    json_path: JsonPath<'a>,
+    vars: &V,
+) -> Vec<Vec<u8>> {
+    let (Ok(buf), Ok(vars)) = (value.to_jsonb(), vars.to_jsonb()) else {
+        return vec![];
+    };
+    let selector = Selector::new(json_path).with_vars(vars.as_ref());
+    selector.select(buf.as_ref())
+}
+
+/// Get the inner elements of `JSONB` value by JSON path like `get_by_path_with_options`,
+/// with the `strict` option, the evaluation errors are returned with the path of the value
+/// at which the evaluation stopped, like `member "b" not found, path $.a[1]`.
+pub fn try_get_by_path_with_options<'a, T: JsonInput + ?Sized>(
+    value: &T,
+    json_path: JsonPath<'a>,
+    options: SelectorOptions,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let selector = Selector::with_options(json_path, options);
+    let buf = value.to_jsonb()?;
+    selector.try_select(buf.as_ref())
+}
+
+/// Get the inner elements of `JSONB` value by JSON path like `try_get_by_path_with_options`,
+/// the memory of the selected values is reserved from the tracker before they are copied,
+/// the error of the tracker is returned if the memory can't be reserved.
+pub fn try_get_by_path_with_tracker<'a, T: JsonInput + ?Sized>(
+    value: &T,
+    json_path: JsonPath<'a>,
+    options: SelectorOptions,
+    tracker: &dyn MemoryTracker,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let buf = value.to_jsonb()?;
+    let selector = Selector::with_options(json_path, options).with_tracker(tracker);
+    selector.try_select(buf.as_ref())
+}
+
 /// Get the inner element of `JSONB` value by JSON path.
 /// If there are multiple matching elements, only the first one is returned
-pub fn get_by_path_first<'a>(value: &'a [u8], json_path: JsonPath<'a>) -> Option<Vec<u8>> {
-    let mut values = get_by_path(value, json_path);
+pub fn get_by_path_first<'a, T: JsonInput + ?Sized>(
+    value: &T,
+    json_path: JsonPath<'a>,
+) -> Option<Vec<u8>> {
+    let options = SelectorOptions {
+        limit: Some(1),
+        ..Default::default()
+    };
+    let mut values = get_by_path_with_options(value, json_path, options);
     if values.is_empty() {
         None
     } else {
@@ -172,7 +240,10 @@ pub fn get_by_path_first<'a>(value: &'a [u8], json_path: JsonPath<'a>) -> Option
 
 /// Get the inner elements of `JSONB` value by JSON path.
 /// If there are multiple matching elements, return an `JSONB` Array.
-pub fn get_by_path_array<'a>(value: &'a [u8], json_path: JsonPath<'a>) -> Option<Vec<u8>> {
+pub fn get_by_path_array<'a, T: JsonInput + ?Sized>(
+    value: &T,
+    json_path: JsonPath<'a>,
+) -> Option<Vec<u8>> {
     let values = get_by_path(value, json_path);
     let mut array_value = Vec::new();
     let items: Vec<_> = values.iter().map(|v| v.as_slice()).collect();
@@ -180,18 +251,69 @@ pub fn get_by_path_array<'a>(value: &'a [u8], json_path: JsonPath<'a>) -> Option
     Some(array_value)
 }
 
-/// Get the inner element of `JSONB` Array by index.
-pub fn get_by_index(value: &[u8], index: usize) -> Option<Vec<u8>> {
-    if !is_jsonb(value) {
-        return match parse_value(value) {
-            Ok(val) => match val {
-                Value::Array(vals) => vals.get(index).map(|v| v.to_vec()),
-                _ => None,
-            },
-            Err(_) => None,
-        };
+/// Get the inner elements of `JSONB` value by several JSON paths in one pass,
+/// the result of each path is the same as `get_by_path`.
+/// The paths with the same leading steps share the traversal of these steps.
+pub fn get_by_paths<T: JsonInput + ?Sized>(
+    value: &T,
+    json_paths: &[JsonPath<'_>],
+) -> Vec<Vec<Vec<u8>>> {
+    match value.to_jsonb() {
+        Ok(buf) => {
+            let json_paths: Vec<_> = json_paths.iter().collect();
+            Selector::select_paths(&json_paths, buf.as_ref())
+        }
+        Err(_) => vec![Vec::new(); json_paths.len()],
     }
+}
+
+/// Get the inner elements of `JSONB` value by several JSON paths like `get_by_paths`,
+/// the result of each path is the same as `get_by_path_with_options` with the options.
+/// With the `limit` or the `strict` option, each path is selected on its own.
+pub fn get_by_paths_with_options<T: JsonInput + ?Sized>(
+    value: &T,
+    json_paths: &[JsonPath<'_>],
+    options: SelectorOptions,
+) -> Vec<Vec<Vec<u8>>> {
+    match value.to_jsonb() {
+        Ok(buf) => {
+            let json_paths: Vec<_> = json_paths.iter().collect();
+            Selector::select_paths_with_options(&json_paths, buf.as_ref(), options)
+        }
+        Err(_) => vec![Vec::new(); json_paths.len()],
+    }
+}
+
+/// Get the first inner element of `JSONB` value by JSON path like `get_by_path_first`,
+/// returns the `default` value if nothing is matched or the element is `null`.
+pub fn get_or_default<T: JsonInput + ?Sized>(
+    value: &T,
+    json_path: &JsonPath<'_>,
+    default: &[u8],
+) -> Vec<u8> {
+    coalesce(value, std::slice::from_ref(json_path)).unwrap_or_else(|| default.to_vec())
+}
+
+/// Returns the first non-null inner element of `JSONB` value matched by the JSON paths,
+/// like the SQL `COALESCE`. The paths are tried in turn, and only the first element
+/// matched by each path is considered.
+pub fn coalesce<T: JsonInput + ?Sized>(value: &T, json_paths: &[JsonPath<'_>]) -> Option<Vec<u8>> {
+    let buf = value.to_jsonb().ok()?;
+    json_paths.iter().find_map(|json_path| {
+        let selector = Selector::from_ref(json_path);
+        let matched = selector.select_first(buf.as_ref())?;
+        if is_null(&RawJsonb::new(&matched)) {
+            None
+        } else {
+            Some(matched.into_owned())
+        }
+    })
+}
 
+/// Get the inner element of `JSONB` Array by index.
+pub fn get_by_index<T: JsonInput + ?Sized>(value: &T, index: usize) -> Option<Vec<u8>> {
+    let buf = value.to_jsonb().ok()?;
+    let value = buf.as_ref();
     let header = read_u32(value, 0).unwrap();
     match header & CONTAINER_HEADER_TYPE_MASK {
         ARRAY_CONTAINER_TAG => {
@@ -232,23 +354,13 @@ pub fn get_by_index(value: &[u8], index: usize) -> Option<Vec<u8>> {
 
 /// Get the inner element of `JSONB` Object by key name,
 /// if `ignore_case` is true, enables case-insensitive matching.
-pub fn get_by_name(value: &[u8], name: &str, ignore_case: bool) -> Option<Vec<u8>> {
-    if !is_jsonb(value) {
-        return match parse_value(value) {
-            Ok(val) => {
-                if ignore_case {
-                    val.get_by_name_ignore_case(name).map(Value::to_vec)
-                } else {
-                    match val {
-                        Value::Object(obj) => obj.get(name).map(|v| v.to_vec()),
-                        _ => None,
-                    }
-                }
-            }
-            Err(_) => None,
-        };
-    }
-
+pub fn get_by_name<T: JsonInput + ?Sized>(
+    value: &T,
+    name: &str,
+    ignore_case: bool,
+) -> Option<Vec<u8>> {
+    let buf = value.to_jsonb().ok()?;
+    let value = buf.as_ref();
     let header = read_u32(value, 0).unwrap();
     match header & CONTAINER_HEADER_TYPE_MASK {
         OBJECT_CONTAINER_TAG => {
@@ -311,15 +423,121 @@ pub fn get_by_name(value: &[u8], name: &str, ignore_case: bool) -> Option<Vec<u8
     }
 }
 
-/// Get the keys of a `JSONB` object.
-pub fn object_keys(value: &[u8]) -> Option<Vec<u8>> {
-    if !is_jsonb(value) {
-        return match parse_value(value) {
-            Ok(val) => val.object_keys().map(|val| val.to_vec()),
-            Err(_) => None,
+/// Iterate the key/value pairs of a `JSONB` object whose keys start with `prefix`,
+/// such as the namespaced keys `ext.*`, return `None` if the value is not an object.
+/// The keys are stored sorted, so the matched keys are a contiguous range found by binary search,
+/// the keys of objects that are not sorted are scanned instead.
+/// Keys and container values are borrowed from the `JSONB` input, scalar values are encoded
+/// as scalar `JSONB`.
+pub fn get_by_name_prefix<'a, T: JsonInput + ?Sized>(
+    value: &'a T,
+    prefix: &'a str,
+) -> Option<impl Iterator<Item = (Cow<'a, str>, Cow<'a, [u8]>)> + 'a> {
+    let value = value.to_jsonb().ok()?;
+    let entries = prefix_entries(&value, prefix)?;
+    Some(entries.into_iter().map(move |entry| {
+        let PrefixEntry { key, encoded, val } = entry;
+        let scalar = |data: &[u8]| {
+            let mut buf = Vec::with_capacity(8 + data.len());
+            buf.extend_from_slice(&SCALAR_CONTAINER_TAG.to_be_bytes());
+            buf.extend_from_slice(&encoded.to_be_bytes());
+            buf.extend_from_slice(data);
+            buf
         };
+        let is_container = JEntry::decode_jentry(encoded).type_code == CONTAINER_TAG;
+        match &value {
+            Cow::Borrowed(value) => {
+                let value: &'a [u8] = value;
+                let key = unsafe { std::str::from_utf8_unchecked(&value[key]) };
+                let data = &value[val];
+                let val = if is_container {
+                    Cow::Borrowed(data)
+                } else {
+                    Cow::Owned(scalar(data))
+                };
+                (Cow::Borrowed(key), val)
+            }
+            Cow::Owned(value) => {
+                let key = unsafe { std::str::from_utf8_unchecked(&value[key]) };
+                let data = &value[val];
+                let val = if is_container {
+                    data.to_vec()
+                } else {
+                    scalar(data)
+                };
+                (Cow::Owned(key.to_string()), Cow::Owned(val))
+            }
+        }
+    }))
+}
+
+// An object entry matched by `get_by_name_prefix`, the ranges are checked to be in the value.
+struct PrefixEntry {
+    key: Range<usize>,
+    // The encoded `JEntry` of the value.
+    encoded: u32,
+    val: Range<usize>,
+}
+
+fn prefix_entries(value: &[u8], prefix: &str) -> Option<Vec<PrefixEntry>> {
+    let header = read_u32(value, 0).ok()?;
+    if header & CONTAINER_HEADER_TYPE_MASK != OBJECT_CONTAINER_TAG {
+        return None;
+    }
+    let length = (header & CONTAINER_HEADER_LEN_MASK) as usize;
+    let mut keys = Vec::with_capacity(length);
+    let mut key_offset = 8 * length + 4;
+    let mut sorted = true;
+    for i in 0..length {
+        let encoded = read_u32(value, 4 + 4 * i).ok()?;
+        let key_length = JEntry::decode_jentry(encoded).length as usize;
+        let key = value.get(key_offset..key_offset + key_length)?;
+        let key = std::str::from_utf8(key).ok()?;
+        sorted = sorted && keys.last().map_or(true, |(last, _)| *last <= key);
+        keys.push((key, key_offset..key_offset + key_length));
+        key_offset += key_length;
     }
+    let start = if sorted {
+        keys.partition_point(|(key, _)| *key < prefix)
+    } else {
+        0
+    };
 
+    // The values are stored after all the keys.
+    let mut jentry_offset = 4 * length + 4;
+    let mut val_offset = key_offset;
+    for _ in 0..start {
+        let encoded = read_u32(value, jentry_offset).ok()?;
+        val_offset += JEntry::decode_jentry(encoded).length as usize;
+        jentry_offset += 4;
+    }
+    let mut entries = Vec::new();
+    for (key, key_range) in keys.into_iter().skip(start) {
+        let matched = key.starts_with(prefix);
+        if !matched && sorted {
+            break;
+        }
+        let encoded = read_u32(value, jentry_offset).ok()?;
+        let val_length = JEntry::decode_jentry(encoded).length as usize;
+        let val_range = val_offset..val_offset + val_length;
+        value.get(val_range.clone())?;
+        if matched {
+            entries.push(PrefixEntry {
+                key: key_range,
+                encoded,
+                val: val_range,
+            });
+        }
+        jentry_offset += 4;
+        val_offset += val_length;
+    }
+    Some(entries)
+}
+
+/// Get the keys of a `JSONB` object.
+pub fn object_keys<T: JsonInput + ?Sized>(value: &T) -> Option<Vec<u8>> {
+    let buf = value.to_jsonb().ok()?;
+    let value = buf.as_ref();
     let header = read_u32(value, 0).unwrap();
     match header & CONTAINER_HEADER_TYPE_MASK {
         OBJECT_CONTAINER_TAG => {
@@ -353,100 +571,463 @@ pub fn object_keys(value: &[u8]) -> Option<Vec<u8>> {
     }
 }
 
+/// Get the values of a `JSONB` object, return a `JSONB` array.
+pub fn object_values<T: JsonInput + ?Sized>(value: &T) -> Option<Vec<u8>> {
+    let buf = value.to_jsonb().ok()?;
+    let value = buf.as_ref();
+    let header = read_u32(value, 0).unwrap();
+    match header & CONTAINER_HEADER_TYPE_MASK {
+        OBJECT_CONTAINER_TAG => {
+            let length = (header & CONTAINER_HEADER_LEN_MASK) as usize;
+            let mut buf: Vec<u8> = Vec::new();
+            let val_header = ARRAY_CONTAINER_TAG | length as u32;
+            buf.extend_from_slice(&val_header.to_be_bytes());
+
+            // The values are stored after the keys, the `JEntries` and the data can be copied directly.
+            let mut key_length = 0;
+            let mut val_length = 0;
+            for i in 0..length {
+                let key_encoded = read_u32(value, 4 + i * 4).unwrap();
+                key_length += JEntry::decode_jentry(key_encoded).length as usize;
+                let val_encoded = read_u32(value, 4 + (length + i) * 4).unwrap();
+                val_length += JEntry::decode_jentry(val_encoded).length as usize;
+                buf.extend_from_slice(&val_encoded.to_be_bytes());
+            }
+            let val_offset = 8 * length + 4 + key_length;
+            buf.extend_from_slice(&value[val_offset..val_offset + val_length]);
+            Some(buf)
+        }
+        _ => None,
+    }
+}
+
 /// Convert the values of a `JSONB` array to vector.
-pub fn array_values(value: &[u8]) -> Option<Vec<Vec<u8>>> {
-    if !is_jsonb(value) {
-        return match parse_value(value) {
-            Ok(val) => match val {
-                Value::Array(vals) => {
-                    Some(vals.into_iter().map(|val| val.to_vec()).collect::<Vec<_>>())
-                }
-                _ => None,
-            },
-            Err(_) => None,
+pub fn array_values<T: JsonInput + ?Sized>(value: &T) -> Option<Vec<Vec<u8>>> {
+    array_values_iter(value).map(|iter| iter.collect())
+}
+
+/// Get the first element of a `JSONB` array, return `None` if the array is empty.
+pub fn array_first<T: JsonInput + ?Sized>(value: &T) -> Option<Vec<u8>> {
+    array_values_iter(value)?.next()
+}
+
+/// Get the last element of a `JSONB` array, return `None` if the array is empty.
+pub fn array_last<T: JsonInput + ?Sized>(value: &T) -> Option<Vec<u8>> {
+    let buf = value.to_jsonb().ok()?;
+    let value = RawJsonb::new(buf.as_ref());
+    let length = array_length(&value)?;
+    if length == 0 {
+        return None;
+    }
+    get_by_index(&value, length - 1)
+}
+
+/// Write the first `n` elements of a `JSONB` array to `buf` as a `JSONB` array,
+/// all the elements are written if the array has no more than `n` elements.
+pub fn array_head<T: JsonInput + ?Sized>(
+    value: &T,
+    n: usize,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let value = value.to_jsonb()?;
+    let offsets = array_data_offsets(&value)?;
+    let length = offsets.len() - 1;
+    write_array_elements(&value, &offsets, 0..n.min(length), buf);
+    Ok(())
+}
+
+/// Write the last `n` elements of a `JSONB` array to `buf` as a `JSONB` array,
+/// all the elements are written if the array has no more than `n` elements.
+pub fn array_tail<T: JsonInput + ?Sized>(
+    value: &T,
+    n: usize,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let value = value.to_jsonb()?;
+    let offsets = array_data_offsets(&value)?;
+    let length = offsets.len() - 1;
+    write_array_elements(&value, &offsets, length.saturating_sub(n)..length, buf);
+    Ok(())
+}
+
+/// Write `n` elements randomly sampled from a `JSONB` array to `buf` as a `JSONB` array,
+/// the sampled elements keep their order in the array.
+///
+/// The elements are sampled by reservoir sampling, each element has the same probability
+/// to be sampled. The same seed always samples the same elements from the same array.
+pub fn array_sample<T: JsonInput + ?Sized>(
+    value: &T,
+    n: usize,
+    seed: u64,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let value = value.to_jsonb()?;
+    let offsets = array_data_offsets(&value)?;
+    let length = offsets.len() - 1;
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut reservoir: Vec<usize> = (0..n.min(length)).collect();
+    for i in n..length {
+        let j = rng.gen_range(0..=i);
+        if j < n {
+            reservoir[j] = i;
+        }
+    }
+    reservoir.sort_unstable();
+    write_array_elements(&value, &offsets, reservoir.into_iter(), buf);
+    Ok(())
+}
+
+/// Write the elements of a `JSONB` array to `buf` in reverse order as a `JSONB` array.
+pub fn array_reverse<T: JsonInput + ?Sized>(value: &T, buf: &mut Vec<u8>) -> Result<(), Error> {
+    let value = value.to_jsonb()?;
+    let offsets = array_data_offsets(&value)?;
+    let length = offsets.len() - 1;
+    write_array_elements(&value, &offsets, (0..length).rev(), buf);
+    Ok(())
+}
+
+/// Write the elements of a `JSONB` array to `buf` rotated to the left by `n` as a `JSONB` array,
+/// the element at index `n` becomes the first element. A negative `n` rotates to the right,
+/// and `n` wraps around the length of the array.
+pub fn array_rotate<T: JsonInput + ?Sized>(
+    value: &T,
+    n: i64,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let value = value.to_jsonb()?;
+    let offsets = array_data_offsets(&value)?;
+    let length = offsets.len() - 1;
+    let mid = if length == 0 {
+        0
+    } else {
+        n.rem_euclid(length as i64) as usize
+    };
+    write_array_elements(&value, &offsets, (mid..length).chain(0..mid), buf);
+    Ok(())
+}
+
+// Returns the offsets of the data of each element in a `JSONB` array,
+// followed by the end offset of the data.
+fn array_data_offsets(value: &[u8]) -> Result<Vec<usize>, Error> {
+    let header = read_u32(value, 0)?;
+    if header & CONTAINER_HEADER_TYPE_MASK != ARRAY_CONTAINER_TAG {
+        return Err(Error::InvalidJsonType);
+    }
+    let length = (header & CONTAINER_HEADER_LEN_MASK) as usize;
+    let mut offsets = Vec::with_capacity(length + 1);
+    let mut offset = 4 * length + 4;
+    offsets.push(offset);
+    for i in 0..length {
+        let encoded = read_u32(value, 4 + i * 4)?;
+        offset += JEntry::decode_jentry(encoded).length as usize;
+        offsets.push(offset);
+    }
+    if offset > value.len() {
+        return Err(Error::InvalidEOF);
+    }
+    Ok(offsets)
+}
+
+// Write the elements at the indexes as a `JSONB` array, the `JEntries` and the data are copied directly.
+fn write_array_elements(
+    value: &[u8],
+    offsets: &[usize],
+    indexes: impl Iterator<Item = usize> + Clone,
+    buf: &mut Vec<u8>,
+) {
+    let length = indexes.clone().count();
+    let header = ARRAY_CONTAINER_TAG | length as u32;
+    buf.extend_from_slice(&header.to_be_bytes());
+    for i in indexes.clone() {
+        buf.extend_from_slice(&value[4 + i * 4..8 + i * 4]);
+    }
+    for i in indexes {
+        buf.extend_from_slice(&value[offsets[i]..offsets[i + 1]]);
+    }
+}
+
+/// Lazy iterator over the keys, the values or the elements of a `JSONB` container,
+/// each item is an encoded `JSONB` value.
+///
+/// Created by [`object_keys_iter`], [`object_values_iter`] and [`array_values_iter`].
+pub struct JsonbIter<'a> {
+    value: Cow<'a, [u8]>,
+    jentry_offset: usize,
+    val_offset: usize,
+    remaining: usize,
+}
+
+impl<'a> Iterator for JsonbIter<'a> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let value = self.value.as_ref();
+        let encoded = read_u32(value, self.jentry_offset).unwrap();
+        let jentry = JEntry::decode_jentry(encoded);
+        let val_length = jentry.length as usize;
+        let data = &value[self.val_offset..self.val_offset + val_length];
+        let item = match jentry.type_code {
+            CONTAINER_TAG => data.to_vec(),
+            _ => {
+                let mut buf = Vec::with_capacity(8 + val_length);
+                buf.extend_from_slice(&SCALAR_CONTAINER_TAG.to_be_bytes());
+                buf.extend_from_slice(&encoded.to_be_bytes());
+                buf.extend_from_slice(data);
+                buf
+            }
         };
+        self.jentry_offset += 4;
+        self.val_offset += val_length;
+        self.remaining -= 1;
+        Some(item)
     }
 
-    let header = read_u32(value, 0).unwrap();
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for JsonbIter<'a> {}
+
+/// Iterate the keys of a `JSONB` object lazily, return `None` if the value is not an object.
+pub fn object_keys_iter<T: JsonInput + ?Sized>(value: &T) -> Option<JsonbIter<'_>> {
+    object_keys_iter_of(value.to_jsonb().ok()?)
+}
+
+fn object_keys_iter_of(value: Cow<'_, [u8]>) -> Option<JsonbIter<'_>> {
+    let header = read_u32(&value, 0).ok()?;
     match header & CONTAINER_HEADER_TYPE_MASK {
-        ARRAY_CONTAINER_TAG => {
+        OBJECT_CONTAINER_TAG => {
             let length = (header & CONTAINER_HEADER_LEN_MASK) as usize;
-            let mut jentry_offset = 4;
-            let mut val_offset = 4 * length + 4;
-            let mut items = Vec::with_capacity(length);
-            for _ in 0..length {
-                let encoded = read_u32(value, jentry_offset).unwrap();
-                let jentry = JEntry::decode_jentry(encoded);
-                let val_length = jentry.length as usize;
-                let item = match jentry.type_code {
-                    CONTAINER_TAG => value[val_offset..val_offset + val_length].to_vec(),
-                    _ => {
-                        let mut buf = Vec::with_capacity(8 + val_length);
-                        buf.extend_from_slice(&SCALAR_CONTAINER_TAG.to_be_bytes());
-                        buf.extend_from_slice(&encoded.to_be_bytes());
-                        if jentry.length > 0 {
-                            buf.extend_from_slice(&value[val_offset..val_offset + val_length]);
-                        }
-                        buf
-                    }
-                };
-                items.push(item);
+            Some(JsonbIter {
+                value,
+                jentry_offset: 4,
+                val_offset: 8 * length + 4,
+                remaining: length,
+            })
+        }
+        _ => None,
+    }
+}
 
-                jentry_offset += 4;
-                val_offset += val_length;
+/// Iterate the values of a `JSONB` object lazily, return `None` if the value is not an object.
+pub fn object_values_iter<T: JsonInput + ?Sized>(value: &T) -> Option<JsonbIter<'_>> {
+    object_values_iter_of(value.to_jsonb().ok()?)
+}
+
+fn object_values_iter_of(value: Cow<'_, [u8]>) -> Option<JsonbIter<'_>> {
+    let header = read_u32(&value, 0).ok()?;
+    match header & CONTAINER_HEADER_TYPE_MASK {
+        OBJECT_CONTAINER_TAG => {
+            let length = (header & CONTAINER_HEADER_LEN_MASK) as usize;
+            let mut val_offset = 8 * length + 4;
+            for i in 0..length {
+                let encoded = read_u32(&value, 4 + i * 4).ok()?;
+                val_offset += JEntry::decode_jentry(encoded).length as usize;
             }
-            Some(items)
+            Some(JsonbIter {
+                value,
+                jentry_offset: 4 * length + 4,
+                val_offset,
+                remaining: length,
+            })
         }
         _ => None,
     }
 }
 
-/// `JSONB` values supports partial decode for comparison,
-/// if the values are found to be unequal, the result will be returned immediately.
-/// In first level header, values compare as the following order:
-/// Scalar Null > Array > Object > Other Scalars(String > Number > Boolean).
-pub fn compare(left: &[u8], right: &[u8]) -> Result<Ordering, Error> {
-    if !is_jsonb(left) && !is_jsonb(right) {
-        let lres = parse_value(left);
-        let rres = parse_value(right);
-        match (lres, rres) {
-            (Ok(lval), Ok(rval)) => {
-                let lbuf = lval.to_vec();
-                let rbuf = rval.to_vec();
-                return compare(&lbuf, &rbuf);
-            }
-            (Ok(_), Err(_)) => {
-                return Ok(Ordering::Greater);
-            }
-            (Err(_), Ok(_)) => {
-                return Ok(Ordering::Less);
-            }
-            (Err(_), Err(_)) => {
-                return Ok(left.cmp(right));
-            }
+/// Iterate the elements of a `JSONB` array lazily, return `None` if the value is not an array.
+pub fn array_values_iter<T: JsonInput + ?Sized>(value: &T) -> Option<JsonbIter<'_>> {
+    array_values_iter_of(value.to_jsonb().ok()?)
+}
+
+fn array_values_iter_of(value: Cow<'_, [u8]>) -> Option<JsonbIter<'_>> {
+    let header = read_u32(&value, 0).ok()?;
+    match header & CONTAINER_HEADER_TYPE_MASK {
+        ARRAY_CONTAINER_TAG => {
+            let length = (header & CONTAINER_HEADER_LEN_MASK) as usize;
+            Some(JsonbIter {
+                value,
+                jentry_offset: 4,
+                val_offset: 4 * length + 4,
+                remaining: length,
+            })
         }
-    } else if !is_jsonb(left) {
-        match parse_value(left) {
-            Ok(lval) => {
-                let lbuf = lval.to_vec();
-                return compare(&lbuf, right);
-            }
-            Err(_) => {
-                return Ok(Ordering::Less);
-            }
+        _ => None,
+    }
+}
+
+/// Iterate the elements of the value matched first by the JSON path lazily in batches
+/// of `batch_size` elements, like the SQL `LATERAL FLATTEN`, so the elements of a huge array
+/// are not all decoded at once. The elements of an array are iterated, other values
+/// are iterated as the only element, and nothing is iterated if the path matches nothing.
+///
+/// # Panics
+///
+/// Panics if `batch_size` is 0.
+///
+/// ```
+/// use jsonb::jsonpath::parse_json_path;
+///
+/// let value = jsonb::parse_value(br#"{"items":[1,2,3,4,5]}"#).unwrap().to_vec();
+/// let json_path = parse_json_path(b"$.items").unwrap();
+/// let batches: Vec<_> = jsonb::explode(&value, &json_path, 2).map(|batch| batch.len()).collect();
+/// assert_eq!(batches, vec![2, 2, 1]);
+/// ```
+pub fn explode<'a, T: JsonInput + ?Sized>(
+    value: &'a T,
+    json_path: &JsonPath<'_>,
+    batch_size: usize,
+) -> impl Iterator<Item = Vec<Vec<u8>>> + 'a {
+    assert!(batch_size > 0, "batch size must be greater than 0");
+    let matched = value
+        .to_jsonb()
+        .ok()
+        .and_then(|value| select_first_of(value, json_path));
+    let (elements, single) = match matched {
+        Some(matched) if is_array(&RawJsonb::new(&matched)) => {
+            (array_values_iter_of(matched), None)
         }
-    } else if !is_jsonb(right) {
-        match parse_value(right) {
-            Ok(rval) => {
-                let rbuf = rval.to_vec();
-                return compare(left, &rbuf);
-            }
-            Err(_) => {
-                return Ok(Ordering::Greater);
-            }
+        matched => (None, matched.map(Cow::into_owned)),
+    };
+    let mut elements = elements.into_iter().flatten().chain(single);
+    std::iter::from_fn(move || {
+        let batch: Vec<_> = elements.by_ref().take(batch_size).collect();
+        if batch.is_empty() {
+            None
+        } else {
+            Some(batch)
+        }
+    })
+}
+
+// Returns the value matched first by the JSON path, borrowed from the value if it is a container.
+fn select_first_of<'a>(value: Cow<'a, [u8]>, json_path: &JsonPath<'_>) -> Option<Cow<'a, [u8]>> {
+    let selector = Selector::from_ref(json_path);
+    let range = match selector.select_first(value.as_ref())? {
+        Cow::Borrowed(matched) => {
+            let start = matched.as_ptr() as usize - value.as_ptr() as usize;
+            start..start + matched.len()
+        }
+        Cow::Owned(matched) => return Some(Cow::Owned(matched)),
+    };
+    match value {
+        Cow::Borrowed(value) => Some(Cow::Borrowed(&value[range])),
+        Cow::Owned(value) => Some(Cow::Owned(value[range].to_vec())),
+    }
+}
+
+/// Iterate the key/value pairs of a `JSONB` object as text lazily, like Postgres `jsonb_each_text`,
+/// return `None` if the value is not an object.
+/// Strings are unquoted, other scalars and containers are rendered as JSON, `null` is `None`.
+pub fn object_each_text<T: JsonInput + ?Sized>(
+    value: &T,
+) -> Option<impl Iterator<Item = (String, Option<String>)> + '_> {
+    let value = value.to_jsonb().ok()?;
+    let keys = object_keys_iter_of(value.clone())?;
+    let values = object_values_iter_of(value)?;
+    Some(keys.zip(values).map(|(key, val)| {
+        let key = as_str(&RawJsonb::new(&key))
+            .map(|key| key.into_owned())
+            .unwrap_or_default();
+        (key, to_text(&val))
+    }))
+}
+
+/// Iterate the elements of a `JSONB` array as text lazily, like Postgres `jsonb_array_elements_text`,
+/// return `None` if the value is not an array.
+/// Strings are unquoted, other scalars and containers are rendered as JSON, `null` is `None`.
+pub fn array_elements_text<T: JsonInput + ?Sized>(
+    value: &T,
+) -> Option<impl Iterator<Item = Option<String>> + '_> {
+    let values = array_values_iter(value)?;
+    Some(values.map(|val| to_text(&val)))
+}
+
+fn to_text(value: &[u8]) -> Option<String> {
+    let value = RawJsonb::new(value);
+    if is_null(&value) {
+        return None;
+    }
+    match as_str(&value) {
+        Some(s) => Some(s.into_owned()),
+        None => Some(to_string(&value)),
+    }
+}
+
+/// Options to control the behavior of `compare_with_options`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompareOptions {
+    /// If both strings are valid ISO-8601 timestamps, such as `2023-05-06T10:20:30Z`,
+    /// compare them by the point in time they represent instead of lexically.
+    /// Timestamps without an offset are treated as UTC. The timestamps are less than
+    /// the other strings, which are compared lexically.
+    pub temporal_strings: bool,
+    /// The position of `null` in the order, see [`NullOrder`].
+    pub nulls: NullOrder,
+}
+
+/// The position of `null` relative to the other values, like the SQL `NULLS FIRST`/`NULLS LAST`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NullOrder {
+    /// `null` is greater than all other values.
+    #[default]
+    NullsLast,
+    /// `null` is less than all other values, except the invalid ones.
+    NullsFirst,
+}
+
+impl NullOrder {
+    // The order of `null` compared with a value of other type.
+    fn null_ordering(self) -> Ordering {
+        match self {
+            NullOrder::NullsLast => Ordering::Greater,
+            NullOrder::NullsFirst => Ordering::Less,
         }
     }
 
+    // Move `null` below the `FALSE_LEVEL` and shift the levels in between up,
+    // the invalid values are still the least.
+    fn level(self, level: u8) -> u8 {
+        match (self, level) {
+            (NullOrder::NullsLast, level) => level,
+            (NullOrder::NullsFirst, NULL_LEVEL) => FALSE_LEVEL,
+            (NullOrder::NullsFirst, INVALID_LEVEL) => INVALID_LEVEL,
+            (NullOrder::NullsFirst, level) => level + 1,
+        }
+    }
+}
+
+/// `JSONB` values supports partial decode for comparison,
+/// if the values are found to be unequal, the result will be returned immediately.
+/// In first level header, values compare as the following order:
+/// Scalar Null > Array > Object > Other Scalars(String > Number > Boolean).
+/// Use `compare_with_options` with [`NullOrder::NullsFirst`] to put the `null` first.
+pub fn compare<L: JsonInput + ?Sized, R: JsonInput + ?Sized>(
+    left: &L,
+    right: &R,
+) -> Result<Ordering, Error> {
+    compare_with_options(left, right, &CompareOptions::default())
+}
+
+/// Compare two `JSONB` values like `compare`, with the behavior controlled by `options`.
+pub fn compare_with_options<L: JsonInput + ?Sized, R: JsonInput + ?Sized>(
+    left: &L,
+    right: &R,
+    options: &CompareOptions,
+) -> Result<Ordering, Error> {
+    // `JSON` text that can't be parsed is less than other values.
+    let (left, right) = match (left.to_jsonb(), right.to_jsonb()) {
+        (Ok(left), Ok(right)) => (left, right),
+        (Ok(_), Err(_)) => return Ok(Ordering::Greater),
+        (Err(_), Ok(_)) => return Ok(Ordering::Less),
+        (Err(_), Err(_)) => return Ok(left.text().cmp(&right.text())),
+    };
+    let (left, right) = (left.as_ref(), right.as_ref());
+
     let left_header = read_u32(left, 0)?;
     let right_header = read_u32(right, 0)?;
     match (
@@ -458,19 +1039,25 @@ pub fn compare(left: &[u8], right: &[u8]) -> Result<Ordering, Error> {
             let left_jentry = JEntry::decode_jentry(left_encoded);
             let right_encoded = read_u32(right, 4)?;
             let right_jentry = JEntry::decode_jentry(right_encoded);
-            compare_scalar(&left_jentry, &left[8..], &right_jentry, &right[8..])
-        }
-        (ARRAY_CONTAINER_TAG, ARRAY_CONTAINER_TAG) => {
-            compare_array(left_header, &left[4..], right_header, &right[4..])
-        }
-        (OBJECT_CONTAINER_TAG, OBJECT_CONTAINER_TAG) => {
-            compare_object(left_header, &left[4..], right_header, &right[4..])
+            compare_scalar(
+                &left_jentry,
+                &left[8..],
+                &right_jentry,
+                &right[8..],
+                options,
+            )
         }
+        (ARRAY_CONTAINER_TAG, ARRAY_CONTAINER_TAG)
+        | (OBJECT_CONTAINER_TAG, OBJECT_CONTAINER_TAG) => compare_container(left, right, options),
         (SCALAR_CONTAINER_TAG, ARRAY_CONTAINER_TAG | OBJECT_CONTAINER_TAG) => {
             let left_encoded = read_u32(left, 4)?;
             let left_jentry = JEntry::decode_jentry(left_encoded);
             match left_jentry.type_code {
-                NULL_TAG => Ok(Ordering::Greater),
+                EXTENSION_TAG => {
+                    let right_jentry = JEntry::make_container_jentry(right.len());
+                    compare_scalar(&left_jentry, &left[8..], &right_jentry, right, options)
+                }
+                NULL_TAG => Ok(options.nulls.null_ordering()),
                 _ => Ok(Ordering::Less),
             }
         }
@@ -478,7 +1065,11 @@ pub fn compare(left: &[u8], right: &[u8]) -> Result<Ordering, Error> {
             let right_encoded = read_u32(right, 4)?;
             let right_jentry = JEntry::decode_jentry(right_encoded);
             match right_jentry.type_code {
-                NULL_TAG => Ok(Ordering::Less),
+                EXTENSION_TAG => {
+                    let left_jentry = JEntry::make_container_jentry(left.len());
+                    compare_scalar(&left_jentry, left, &right_jentry, &right[8..], options)
+                }
+                NULL_TAG => Ok(options.nulls.null_ordering().reverse()),
                 _ => Ok(Ordering::Greater),
             }
         }
@@ -488,6 +1079,41 @@ pub fn compare(left: &[u8], right: &[u8]) -> Result<Ordering, Error> {
     }
 }
 
+/// Compare two `JSONB` values like `compare`, but give up after reading `max_bytes` bytes
+/// of the encoded values, for the best-effort comparison of values that may be huge.
+/// Returns `None` if the order is not found within the budget, or the values are invalid.
+///
+/// Containers are compared element by element and only the bytes read so far are counted,
+/// so values that differ early are ordered even if they are larger than the budget.
+/// Other values are only compared if both of them together fit in the budget.
+pub fn compare_bounded<L: JsonInput + ?Sized, R: JsonInput + ?Sized>(
+    left: &L,
+    right: &R,
+    max_bytes: usize,
+) -> Option<Ordering> {
+    let left = left.to_jsonb().ok()?;
+    let right = right.to_jsonb().ok()?;
+    let (left, right) = (left.as_ref(), right.as_ref());
+    let options = CompareOptions::default();
+    let is_container = |value: &[u8]| {
+        matches!(
+            read_u32(value, 0).map(|header| header & CONTAINER_HEADER_TYPE_MASK),
+            Ok(ARRAY_CONTAINER_TAG | OBJECT_CONTAINER_TAG)
+        )
+    };
+    if is_container(left) && is_container(right) {
+        return compare_container_within(left, right, &options, &mut Budget::bounded(max_bytes))
+            .ok()
+            .flatten();
+    }
+    if left.len() + right.len() > max_bytes {
+        return None;
+    }
+    validate(left).ok()?;
+    validate(right).ok()?;
+    compare_with_options(left, right, &options).ok()
+}
+
 // Different types of values have different levels and are definitely not equal
 fn jentry_compare_level(jentry: &JEntry) -> u8 {
     match jentry.type_code {
@@ -508,21 +1134,34 @@ fn compare_scalar(
     left: &[u8],
     right_jentry: &JEntry,
     right: &[u8],
+    options: &CompareOptions,
 ) -> Result<Ordering, Error> {
-    let left_level = jentry_compare_level(left_jentry);
-    let right_level = jentry_compare_level(right_jentry);
+    if left_jentry.type_code == EXTENSION_TAG || right_jentry.type_code == EXTENSION_TAG {
+        return compare_extension_scalar(left_jentry, left, right_jentry, right, options);
+    }
+    let left_level = options.nulls.level(jentry_compare_level(left_jentry));
+    let right_level = options.nulls.level(jentry_compare_level(right_jentry));
     if left_level != right_level {
         return Ok(left_level.cmp(&right_level));
     }
 
     match (left_jentry.type_code, right_jentry.type_code) {
         (NULL_TAG, NULL_TAG) => Ok(Ordering::Equal),
-        (CONTAINER_TAG, CONTAINER_TAG) => compare_container(left, right),
+        (CONTAINER_TAG, CONTAINER_TAG) => compare_container(left, right, options),
         (STRING_TAG, STRING_TAG) => {
             let left_offset = left_jentry.length as usize;
             let left_str = unsafe { std::str::from_utf8_unchecked(&left[..left_offset]) };
             let right_offset = right_jentry.length as usize;
             let right_str = unsafe { std::str::from_utf8_unchecked(&right[..right_offset]) };
+            if options.temporal_strings {
+                // Timestamps are less than the other strings, so that the order is total.
+                match (parse_timestamp(left_str), parse_timestamp(right_str)) {
+                    (Some(left_ts), Some(right_ts)) => return Ok(left_ts.cmp(&right_ts)),
+                    (Some(_), None) => return Ok(Ordering::Less),
+                    (None, Some(_)) => return Ok(Ordering::Greater),
+                    (None, None) => {}
+                }
+            }
             Ok(left_str.cmp(right_str))
         }
         (NUMBER_TAG, NUMBER_TAG) => {
@@ -538,164 +1177,322 @@ fn compare_scalar(
     }
 }
 
-fn compare_container(left: &[u8], right: &[u8]) -> Result<Ordering, Error> {
-    let left_header = read_u32(left, 0)?;
-    let right_header = read_u32(right, 0)?;
-
-    match (
-        left_header & CONTAINER_HEADER_TYPE_MASK,
-        right_header & CONTAINER_HEADER_TYPE_MASK,
-    ) {
-        (ARRAY_CONTAINER_TAG, ARRAY_CONTAINER_TAG) => {
-            compare_array(left_header, &left[4..], right_header, &right[4..])
-        }
-        (OBJECT_CONTAINER_TAG, OBJECT_CONTAINER_TAG) => {
-            compare_object(left_header, &left[4..], right_header, &right[4..])
+// Extension values are compared as their plain values, like in `convert_to_comparable`,
+// so the order is total. Values of unregistered extension types are less than other values.
+fn compare_extension_scalar(
+    left_jentry: &JEntry,
+    left: &[u8],
+    right_jentry: &JEntry,
+    right: &[u8],
+    options: &CompareOptions,
+) -> Result<Ordering, Error> {
+    let left_raw = &left[..left_jentry.length as usize];
+    let right_raw = &right[..right_jentry.length as usize];
+    let left_plain = plain_scalar(left_jentry, left_raw);
+    let right_plain = plain_scalar(right_jentry, right_raw);
+    match (left_plain, right_plain) {
+        (Some((left_jentry, left)), Some((right_jentry, right))) => {
+            compare_scalar(&left_jentry, &left, &right_jentry, &right, options)
         }
-        (ARRAY_CONTAINER_TAG, OBJECT_CONTAINER_TAG) => Ok(Ordering::Greater),
-        (OBJECT_CONTAINER_TAG, ARRAY_CONTAINER_TAG) => Ok(Ordering::Less),
-        (_, _) => Err(Error::InvalidJsonbHeader),
+        (Some(_), None) => Ok(Ordering::Greater),
+        (None, Some(_)) => Ok(Ordering::Less),
+        (None, None) => Ok(left_raw.cmp(right_raw)),
     }
 }
 
-// `Array` values compares each element in turn.
-fn compare_array(
-    left_header: u32,
+fn plain_scalar(jentry: &JEntry, raw: &[u8]) -> Option<(JEntry, Vec<u8>)> {
+    match jentry.type_code {
+        EXTENSION_TAG => extension_to_plain(raw),
+        type_code => Some((
+            JEntry {
+                type_code,
+                length: jentry.length,
+            },
+            raw.to_vec(),
+        )),
+    }
+}
+
+// Containers are compared from an explicit stack instead of recursion,
+// so the deeply nested documents can't overflow the thread stack.
+fn compare_container(
     left: &[u8],
-    right_header: u32,
     right: &[u8],
+    options: &CompareOptions,
 ) -> Result<Ordering, Error> {
-    let left_length = (left_header & CONTAINER_HEADER_LEN_MASK) as usize;
-    let right_length = (right_header & CONTAINER_HEADER_LEN_MASK) as usize;
+    let order = compare_container_within(left, right, options, &mut Budget::unlimited())?;
+    // The unlimited budget can't be exhausted.
+    Ok(order.unwrap_or(Ordering::Equal))
+}
 
-    let mut jentry_offset = 0;
-    let mut left_val_offset = 4 * left_length;
-    let mut right_val_offset = 4 * right_length;
-    let length = if left_length < right_length {
-        left_length
-    } else {
-        right_length
-    };
-    for _ in 0..length {
-        let left_encoded = read_u32(left, jentry_offset)?;
-        let left_jentry = JEntry::decode_jentry(left_encoded);
-        let right_encoded = read_u32(right, jentry_offset)?;
-        let right_jentry = JEntry::decode_jentry(right_encoded);
+// Compare the containers like `compare_container`,
+// returns `None` if the budget is exhausted before the order is found.
+fn compare_container_within(
+    left: &[u8],
+    right: &[u8],
+    options: &CompareOptions,
+    budget: &mut Budget,
+) -> Result<Option<Ordering>, Error> {
+    let mut stack = Vec::new();
+    if !budget.spend(8) {
+        return Ok(None);
+    }
+    if let Some(order) = CompareFrame::open(left, right, &mut stack)? {
+        return Ok(Some(order));
+    }
+    while let Some(frame) = stack.last_mut() {
+        match frame.step(options, budget)? {
+            CompareStep::Order(Ordering::Equal) => {}
+            CompareStep::Order(order) => return Ok(Some(order)),
+            CompareStep::End(order) => {
+                if order != Ordering::Equal {
+                    return Ok(Some(order));
+                }
+                stack.pop();
+            }
+            CompareStep::Descend(left, right) => {
+                if !budget.spend(8) {
+                    return Ok(None);
+                }
+                if let Some(order) = CompareFrame::open(left, right, &mut stack)? {
+                    if order != Ordering::Equal {
+                        return Ok(Some(order));
+                    }
+                }
+            }
+            CompareStep::Exhausted => return Ok(None),
+        }
+    }
+    Ok(Some(Ordering::Equal))
+}
 
-        let order = compare_scalar(
-            &left_jentry,
-            &left[left_val_offset..],
-            &right_jentry,
-            &right[right_val_offset..],
-        )?;
-        if order != Ordering::Equal {
-            return Ok(order);
+// The number of bytes that can still be read by a comparison.
+struct Budget {
+    bytes: usize,
+    // Whether the keys and scalars are validated before they are compared,
+    // only the bounded comparison accepts the values that have not been validated.
+    validate: bool,
+}
+
+impl Budget {
+    fn unlimited() -> Self {
+        Self {
+            bytes: usize::MAX,
+            validate: false,
         }
-        jentry_offset += 4;
+    }
+
+    fn bounded(bytes: usize) -> Self {
+        Self {
+            bytes,
+            validate: true,
+        }
+    }
 
-        left_val_offset += left_jentry.length as usize;
-        right_val_offset += right_jentry.length as usize;
+    // Returns false if there are not enough bytes left to read.
+    fn spend(&mut self, bytes: usize) -> bool {
+        match self.bytes.checked_sub(bytes) {
+            Some(left) => {
+                self.bytes = left;
+                true
+            }
+            None => false,
+        }
     }
+}
 
-    Ok(left_length.cmp(&right_length))
+enum CompareStep<'a> {
+    // The order of the current values.
+    Order(Ordering),
+    // The current values are containers and must be compared before continuing.
+    Descend(&'a [u8], &'a [u8]),
+    // All the common values are equal, the order of the lengths.
+    End(Ordering),
+    // The budget is exhausted before the current values are read.
+    Exhausted,
 }
 
+// A pair of containers of the same type being compared.
+// `Array` values compares each element in turn.
 // `Object` values compares each key-value in turn,
 // first compare the key, and then compare the value if the key is equal.
 // The larger the key/value, the larger the `Object`.
-fn compare_object(
-    left_header: u32,
-    left: &[u8],
-    right_header: u32,
-    right: &[u8],
-) -> Result<Ordering, Error> {
-    let left_length = (left_header & CONTAINER_HEADER_LEN_MASK) as usize;
-    let right_length = (right_header & CONTAINER_HEADER_LEN_MASK) as usize;
-
-    let mut jentry_offset = 0;
-    let mut left_val_offset = 8 * left_length;
-    let mut right_val_offset = 8 * right_length;
-
-    let length = if left_length < right_length {
-        left_length
-    } else {
-        right_length
-    };
-    // read all key jentries first
-    let mut left_key_jentries: VecDeque<JEntry> = VecDeque::with_capacity(length);
-    let mut right_key_jentries: VecDeque<JEntry> = VecDeque::with_capacity(length);
-    for _ in 0..length {
-        let left_encoded = read_u32(left, jentry_offset)?;
-        let left_key_jentry = JEntry::decode_jentry(left_encoded);
-        let right_encoded = read_u32(right, jentry_offset)?;
-        let right_key_jentry = JEntry::decode_jentry(right_encoded);
-
-        jentry_offset += 4;
-        left_val_offset += left_key_jentry.length as usize;
-        right_val_offset += right_key_jentry.length as usize;
+struct CompareFrame<'a> {
+    left: &'a [u8],
+    right: &'a [u8],
+    is_object: bool,
+    left_length: usize,
+    right_length: usize,
+    index: usize,
+    left_jentry_offset: usize,
+    right_jentry_offset: usize,
+    left_key_offset: usize,
+    right_key_offset: usize,
+    left_val_offset: usize,
+    right_val_offset: usize,
+}
 
-        left_key_jentries.push_back(left_key_jentry);
-        right_key_jentries.push_back(right_key_jentry);
+impl<'a> CompareFrame<'a> {
+    // Push the containers to the stack if they have the same type,
+    // otherwise returns the order of them.
+    fn open(
+        left: &'a [u8],
+        right: &'a [u8],
+        stack: &mut Vec<CompareFrame<'a>>,
+    ) -> Result<Option<Ordering>, Error> {
+        let left_header = read_u32(left, 0)?;
+        let right_header = read_u32(right, 0)?;
+        let left_length = (left_header & CONTAINER_HEADER_LEN_MASK) as usize;
+        let right_length = (right_header & CONTAINER_HEADER_LEN_MASK) as usize;
+        let is_object = match (
+            left_header & CONTAINER_HEADER_TYPE_MASK,
+            right_header & CONTAINER_HEADER_TYPE_MASK,
+        ) {
+            (ARRAY_CONTAINER_TAG, ARRAY_CONTAINER_TAG) => false,
+            (OBJECT_CONTAINER_TAG, OBJECT_CONTAINER_TAG) => true,
+            (ARRAY_CONTAINER_TAG, OBJECT_CONTAINER_TAG) => return Ok(Some(Ordering::Greater)),
+            (OBJECT_CONTAINER_TAG, ARRAY_CONTAINER_TAG) => return Ok(Some(Ordering::Less)),
+            (_, _) => return Err(Error::InvalidJsonbHeader),
+        };
+        let frame = if is_object {
+            let left_key_offset = 4 + 8 * left_length;
+            let right_key_offset = 4 + 8 * right_length;
+            CompareFrame {
+                left,
+                right,
+                is_object,
+                left_length,
+                right_length,
+                index: 0,
+                left_jentry_offset: 4 + 4 * left_length,
+                right_jentry_offset: 4 + 4 * right_length,
+                left_key_offset,
+                right_key_offset,
+                left_val_offset: left_key_offset + keys_length(left, left_length)?,
+                right_val_offset: right_key_offset + keys_length(right, right_length)?,
+            }
+        } else {
+            CompareFrame {
+                left,
+                right,
+                is_object,
+                left_length,
+                right_length,
+                index: 0,
+                left_jentry_offset: 4,
+                right_jentry_offset: 4,
+                left_key_offset: 0,
+                right_key_offset: 0,
+                left_val_offset: 4 + 4 * left_length,
+                right_val_offset: 4 + 4 * right_length,
+            }
+        };
+        stack.push(frame);
+        Ok(None)
     }
 
-    let mut left_jentry_offset = 4 * left_length;
-    let mut right_jentry_offset = 4 * right_length;
-    let mut left_key_offset = 8 * left_length;
-    let mut right_key_offset = 8 * right_length;
-    for _ in 0..length {
-        // first compare key, if keys are equal, then compare the value
-        let left_key_jentry = left_key_jentries.pop_front().unwrap();
-        let right_key_jentry = right_key_jentries.pop_front().unwrap();
-
-        let key_order = compare_scalar(
-            &left_key_jentry,
-            &left[left_key_offset..],
-            &right_key_jentry,
-            &right[right_key_offset..],
-        )?;
-        if key_order != Ordering::Equal {
-            return Ok(key_order);
+    fn step(
+        &mut self,
+        options: &CompareOptions,
+        budget: &mut Budget,
+    ) -> Result<CompareStep<'a>, Error> {
+        if self.index == self.left_length.min(self.right_length) {
+            return Ok(CompareStep::End(self.left_length.cmp(&self.right_length)));
         }
-
-        let left_encoded = read_u32(left, left_jentry_offset)?;
-        let left_val_jentry = JEntry::decode_jentry(left_encoded);
-        let right_encoded = read_u32(right, right_jentry_offset)?;
-        let right_val_jentry = JEntry::decode_jentry(right_encoded);
-
-        let val_order = compare_scalar(
-            &left_val_jentry,
-            &left[left_val_offset..],
-            &right_val_jentry,
-            &right[right_val_offset..],
-        )?;
-        if val_order != Ordering::Equal {
-            return Ok(val_order);
+        if self.is_object {
+            // first compare key, if keys are equal, then compare the value
+            let left_encoded = read_u32(self.left, 4 + 4 * self.index)?;
+            let left_key_jentry = JEntry::decode_jentry(left_encoded);
+            let right_encoded = read_u32(self.right, 4 + 4 * self.index)?;
+            let right_key_jentry = JEntry::decode_jentry(right_encoded);
+            let length = left_key_jentry.length as usize + right_key_jentry.length as usize;
+            if !budget.spend(8 + length) {
+                return Ok(CompareStep::Exhausted);
+            }
+            let left_key = checked_data(self.left, self.left_key_offset, &left_key_jentry)?;
+            let right_key = checked_data(self.right, self.right_key_offset, &right_key_jentry)?;
+            if budget.validate {
+                if left_key_jentry.type_code != STRING_TAG
+                    || right_key_jentry.type_code != STRING_TAG
+                {
+                    return Err(Error::InvalidJsonbJEntry);
+                }
+                validate_scalar(STRING_TAG, left_key)?;
+                validate_scalar(STRING_TAG, right_key)?;
+            }
+            let key_order = compare_scalar(
+                &left_key_jentry,
+                left_key,
+                &right_key_jentry,
+                right_key,
+                options,
+            )?;
+            if key_order != Ordering::Equal {
+                return Ok(CompareStep::Order(key_order));
+            }
+            self.left_key_offset += left_key_jentry.length as usize;
+            self.right_key_offset += right_key_jentry.length as usize;
         }
-        left_jentry_offset += 4;
-        right_jentry_offset += 4;
 
-        left_key_offset += left_key_jentry.length as usize;
-        right_key_offset += right_key_jentry.length as usize;
-        left_val_offset += left_val_jentry.length as usize;
-        right_val_offset += right_val_jentry.length as usize;
+        let left_encoded = read_u32(self.left, self.left_jentry_offset)?;
+        let left_jentry = JEntry::decode_jentry(left_encoded);
+        let right_encoded = read_u32(self.right, self.right_jentry_offset)?;
+        let right_jentry = JEntry::decode_jentry(right_encoded);
+        if !budget.spend(8) {
+            return Ok(CompareStep::Exhausted);
+        }
+        let left = checked_data(self.left, self.left_val_offset, &left_jentry)?;
+        let right = checked_data(self.right, self.right_val_offset, &right_jentry)?;
+        self.index += 1;
+        self.left_jentry_offset += 4;
+        self.right_jentry_offset += 4;
+        self.left_val_offset += left_jentry.length as usize;
+        self.right_val_offset += right_jentry.length as usize;
+
+        if left_jentry.type_code == CONTAINER_TAG && right_jentry.type_code == CONTAINER_TAG {
+            return Ok(CompareStep::Descend(left, right));
+        }
+        if !budget.spend(left_jentry.length as usize + right_jentry.length as usize) {
+            return Ok(CompareStep::Exhausted);
+        }
+        if budget.validate {
+            for (jentry, data) in [(&left_jentry, left), (&right_jentry, right)] {
+                if jentry.type_code != CONTAINER_TAG {
+                    validate_scalar(jentry.type_code, data)?;
+                }
+            }
+        }
+        let order = compare_scalar(&left_jentry, left, &right_jentry, right, options)?;
+        Ok(CompareStep::Order(order))
     }
+}
+
+// The data of the `JEntry` at the offset, the buffer may be corrupted.
+fn checked_data<'a>(value: &'a [u8], offset: usize, jentry: &JEntry) -> Result<&'a [u8], Error> {
+    value
+        .get(offset..offset + jentry.length as usize)
+        .ok_or(Error::InvalidEOF)
+}
 
-    Ok(left_length.cmp(&right_length))
+// The total length of the keys of an object, the values are stored after the keys.
+fn keys_length(value: &[u8], length: usize) -> Result<usize, Error> {
+    let mut keys_length = 0;
+    for i in 0..length {
+        let encoded = read_u32(value, 4 + 4 * i)?;
+        keys_length += JEntry::decode_jentry(encoded).length as usize;
+    }
+    Ok(keys_length)
 }
 
 /// Returns true if the `JSONB` is a Null.
-pub fn is_null(value: &[u8]) -> bool {
+pub fn is_null<T: JsonInput + ?Sized>(value: &T) -> bool {
     as_null(value).is_some()
 }
 
 /// If the `JSONB` is a Null, returns (). Returns None otherwise.
-pub fn as_null(value: &[u8]) -> Option<()> {
-    if !is_jsonb(value) {
-        return match parse_value(value) {
-            Ok(val) => val.as_null(),
-            Err(_) => None,
-        };
-    }
+pub fn as_null<T: JsonInput + ?Sized>(value: &T) -> Option<()> {
+    let buf = value.to_jsonb().ok()?;
+    let value = buf.as_ref();
     let header = read_u32(value, 0).unwrap();
     match header & CONTAINER_HEADER_TYPE_MASK {
         SCALAR_CONTAINER_TAG => {
@@ -710,18 +1507,14 @@ pub fn as_null(value: &[u8]) -> Option<()> {
 }
 
 /// Returns true if the `JSONB` is a Boolean. Returns false otherwise.
-pub fn is_boolean(value: &[u8]) -> bool {
+pub fn is_boolean<T: JsonInput + ?Sized>(value: &T) -> bool {
     as_bool(value).is_some()
 }
 
 /// If the `JSONB` is a Boolean, returns the associated bool. Returns None otherwise.
-pub fn as_bool(value: &[u8]) -> Option<bool> {
-    if !is_jsonb(value) {
-        return match parse_value(value) {
-            Ok(val) => val.as_bool(),
-            Err(_) => None,
-        };
-    }
+pub fn as_bool<T: JsonInput + ?Sized>(value: &T) -> Option<bool> {
+    let buf = value.to_jsonb().ok()?;
+    let value = buf.as_ref();
     let header = read_u32(value, 0).unwrap();
     match header & CONTAINER_HEADER_TYPE_MASK {
         SCALAR_CONTAINER_TAG => {
@@ -737,32 +1530,30 @@ pub fn as_bool(value: &[u8]) -> Option<bool> {
 }
 
 /// Cast `JSONB` value to Boolean
-pub fn to_bool(value: &[u8]) -> Result<bool, Error> {
-    if let Some(v) = as_bool(value) {
-        return Ok(v);
-    } else if let Some(v) = as_str(value) {
-        if &v.to_lowercase() == "true" {
-            return Ok(true);
-        } else if &v.to_lowercase() == "false" {
-            return Ok(false);
-        }
+pub fn to_bool<T: JsonInput + ?Sized>(value: &T) -> Result<bool, Error> {
+    try_to_bool(value).ok_or(Error::InvalidCast)
+}
+
+/// Cast `JSONB` value to Boolean like `to_bool`, returns `None` if the value can't be cast.
+pub fn try_to_bool<T: JsonInput + ?Sized>(value: &T) -> Option<bool> {
+    let buf = value.to_jsonb().ok()?;
+    match cast_scalar(&buf)? {
+        CastScalar::Bool(v) => Some(v),
+        CastScalar::String(v) if v.eq_ignore_ascii_case("true") => Some(true),
+        CastScalar::String(v) if v.eq_ignore_ascii_case("false") => Some(false),
+        _ => None,
     }
-    Err(Error::InvalidCast)
 }
 
 /// Returns true if the `JSONB` is a Number. Returns false otherwise.
-pub fn is_number(value: &[u8]) -> bool {
+pub fn is_number<T: JsonInput + ?Sized>(value: &T) -> bool {
     as_number(value).is_some()
 }
 
 /// If the `JSONB` is a Number, returns the Number. Returns None otherwise.
-pub fn as_number(value: &[u8]) -> Option<Number> {
-    if !is_jsonb(value) {
-        return match parse_value(value) {
-            Ok(val) => val.as_number().cloned(),
-            Err(_) => None,
-        };
-    }
+pub fn as_number<T: JsonInput + ?Sized>(value: &T) -> Option<Number> {
+    let buf = value.to_jsonb().ok()?;
+    let value = buf.as_ref();
     let header = read_u32(value, 0).unwrap();
     match header & CONTAINER_HEADER_TYPE_MASK {
         SCALAR_CONTAINER_TAG => {
@@ -782,74 +1573,88 @@ pub fn as_number(value: &[u8]) -> Option<Number> {
 }
 
 /// Returns true if the `JSONB` is a i64 Number. Returns false otherwise.
-pub fn is_i64(value: &[u8]) -> bool {
+pub fn is_i64<T: JsonInput + ?Sized>(value: &T) -> bool {
     as_i64(value).is_some()
 }
 
 /// Cast `JSONB` value to i64
-pub fn to_i64(value: &[u8]) -> Result<i64, Error> {
-    if let Some(v) = as_i64(value) {
-        return Ok(v);
-    } else if let Some(v) = as_bool(value) {
-        if v {
-            return Ok(1_i64);
-        } else {
-            return Ok(0_i64);
-        }
-    } else if let Some(v) = as_str(value) {
-        if let Ok(v) = v.parse::<i64>() {
-            return Ok(v);
-        }
+pub fn to_i64<T: JsonInput + ?Sized>(value: &T) -> Result<i64, Error> {
+    try_to_i64(value).ok_or(Error::InvalidCast)
+}
+
+/// Cast `JSONB` value to i64 like `to_i64`, returns `None` if the value can't be cast.
+pub fn try_to_i64<T: JsonInput + ?Sized>(value: &T) -> Option<i64> {
+    let buf = value.to_jsonb().ok()?;
+    match cast_scalar(&buf)? {
+        CastScalar::Number(v) => v.as_i64(),
+        CastScalar::Bool(v) => Some(v as i64),
+        CastScalar::String(v) => v.parse::<i64>().ok(),
     }
-    Err(Error::InvalidCast)
 }
 
 /// If the `JSONB` is a Number, represent it as i64 if possible. Returns None otherwise.
-pub fn as_i64(value: &[u8]) -> Option<i64> {
+pub fn as_i64<T: JsonInput + ?Sized>(value: &T) -> Option<i64> {
     match as_number(value) {
         Some(num) => num.as_i64(),
         None => None,
     }
 }
 
+/// If the `JSONB` is a Number, represent it as i64 if it is exactly representable,
+/// including the floats with a zero fractional part, such as `3.0`.
+/// Returns [`Error::Cast`] with the reason otherwise.
+pub fn as_i64_exact<T: JsonInput + ?Sized>(value: &T) -> Result<i64, Error> {
+    match as_number(value) {
+        Some(num) => num.as_i64_exact(),
+        None => Err(Error::Cast(CastErrorCode::NotNumber)),
+    }
+}
+
 /// Returns true if the `JSONB` is a u64 Number. Returns false otherwise.
-pub fn is_u64(value: &[u8]) -> bool {
+pub fn is_u64<T: JsonInput + ?Sized>(value: &T) -> bool {
     as_u64(value).is_some()
 }
 
 /// If the `JSONB` is a Number, represent it as u64 if possible. Returns None otherwise.
-pub fn as_u64(value: &[u8]) -> Option<u64> {
+pub fn as_u64<T: JsonInput + ?Sized>(value: &T) -> Option<u64> {
     match as_number(value) {
         Some(num) => num.as_u64(),
         None => None,
     }
 }
 
+/// If the `JSONB` is a Number, represent it as u64 if it is exactly representable,
+/// including the floats with a zero fractional part, such as `3.0`.
+/// Returns [`Error::Cast`] with the reason otherwise.
+pub fn as_u64_exact<T: JsonInput + ?Sized>(value: &T) -> Result<u64, Error> {
+    match as_number(value) {
+        Some(num) => num.as_u64_exact(),
+        None => Err(Error::Cast(CastErrorCode::NotNumber)),
+    }
+}
+
 /// Cast `JSONB` value to u64
-pub fn to_u64(value: &[u8]) -> Result<u64, Error> {
-    if let Some(v) = as_u64(value) {
-        return Ok(v);
-    } else if let Some(v) = as_bool(value) {
-        if v {
-            return Ok(1_u64);
-        } else {
-            return Ok(0_u64);
-        }
-    } else if let Some(v) = as_str(value) {
-        if let Ok(v) = v.parse::<u64>() {
-            return Ok(v);
-        }
+pub fn to_u64<T: JsonInput + ?Sized>(value: &T) -> Result<u64, Error> {
+    try_to_u64(value).ok_or(Error::InvalidCast)
+}
+
+/// Cast `JSONB` value to u64 like `to_u64`, returns `None` if the value can't be cast.
+pub fn try_to_u64<T: JsonInput + ?Sized>(value: &T) -> Option<u64> {
+    let buf = value.to_jsonb().ok()?;
+    match cast_scalar(&buf)? {
+        CastScalar::Number(v) => v.as_u64(),
+        CastScalar::Bool(v) => Some(v as u64),
+        CastScalar::String(v) => v.parse::<u64>().ok(),
     }
-    Err(Error::InvalidCast)
 }
 
 /// Returns true if the `JSONB` is a f64 Number. Returns false otherwise.
-pub fn is_f64(value: &[u8]) -> bool {
+pub fn is_f64<T: JsonInput + ?Sized>(value: &T) -> bool {
     as_f64(value).is_some()
 }
 
 /// If the `JSONB` is a Number, represent it as f64 if possible. Returns None otherwise.
-pub fn as_f64(value: &[u8]) -> Option<f64> {
+pub fn as_f64<T: JsonInput + ?Sized>(value: &T) -> Option<f64> {
     match as_number(value) {
         Some(num) => num.as_f64(),
         None => None,
@@ -857,49 +1662,201 @@ pub fn as_f64(value: &[u8]) -> Option<f64> {
 }
 
 /// Cast `JSONB` value to f64
-pub fn to_f64(value: &[u8]) -> Result<f64, Error> {
-    if let Some(v) = as_f64(value) {
-        return Ok(v);
-    } else if let Some(v) = as_bool(value) {
-        if v {
-            return Ok(1_f64);
-        } else {
-            return Ok(0_f64);
-        }
-    } else if let Some(v) = as_str(value) {
-        if let Ok(v) = v.parse::<f64>() {
-            return Ok(v);
-        }
+pub fn to_f64<T: JsonInput + ?Sized>(value: &T) -> Result<f64, Error> {
+    try_to_f64(value).ok_or(Error::InvalidCast)
+}
+
+/// Cast `JSONB` value to f64 like `to_f64`, returns `None` if the value can't be cast.
+pub fn try_to_f64<T: JsonInput + ?Sized>(value: &T) -> Option<f64> {
+    let buf = value.to_jsonb().ok()?;
+    match cast_scalar(&buf)? {
+        CastScalar::Number(v) => v.as_f64(),
+        CastScalar::Bool(v) => Some(if v { 1_f64 } else { 0_f64 }),
+        CastScalar::String(v) => v.parse::<f64>().ok(),
+    }
+}
+
+// The scalar of a `JSONB` value that can be cast, decoded once from its `JEntry`.
+enum CastScalar<'a> {
+    Bool(bool),
+    Number(Number),
+    String(&'a str),
+}
+
+// Returns `None` for the containers, `null` and the extension values.
+fn cast_scalar(value: &[u8]) -> Option<CastScalar<'_>> {
+    let header = read_u32(value, 0).ok()?;
+    if header & CONTAINER_HEADER_TYPE_MASK != SCALAR_CONTAINER_TAG {
+        return None;
+    }
+    let jentry = JEntry::decode_jentry(read_u32(value, 4).ok()?);
+    let data = value.get(8..8 + jentry.length as usize)?;
+    match jentry.type_code {
+        TRUE_TAG => Some(CastScalar::Bool(true)),
+        FALSE_TAG => Some(CastScalar::Bool(false)),
+        NUMBER_TAG => Some(CastScalar::Number(Number::decode(data))),
+        STRING_TAG => std::str::from_utf8(data).ok().map(CastScalar::String),
+        _ => None,
+    }
+}
+
+/// What the lenient casts, like `to_i64_lenient`, and [`crate::shred`] do
+/// if a value can't be cast to the target type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnError {
+    /// Returns null for the value, like the SQL `TRY_CAST`.
+    #[default]
+    Null,
+    /// Returns [`Error::InvalidCast`].
+    Fail,
+}
+
+/// Cast `JSONB` value to i64 like `to_i64`, also accepts the numeric strings with
+/// surrounding whitespaces and the strings of integral floats, such as `" 42 "` and `"1e3"`.
+/// Returns `None` for `null`, the failures are handled by `on_error`.
+pub fn to_i64_lenient<T: JsonInput + ?Sized>(
+    value: &T,
+    on_error: OnError,
+) -> Result<Option<i64>, Error> {
+    cast_lenient(value, on_error, |value| {
+        try_to_i64(value).or_else(|| {
+            let s = as_str(value)?;
+            let s = s.trim();
+            s.parse::<i64>().ok().or_else(|| {
+                let v = s.parse::<f64>().ok()?;
+                // `i64::MAX as f64` is rounded up to 2^63, which is out of range.
+                (v.fract() == 0.0 && v >= i64::MIN as f64 && v < i64::MAX as f64)
+                    .then_some(v as i64)
+            })
+        })
+    })
+}
+
+/// Cast `JSONB` value to u64 like `to_u64`, also accepts the numeric strings with
+/// surrounding whitespaces and the strings of integral floats, such as `" 42 "` and `"1e3"`.
+/// Returns `None` for `null`, the failures are handled by `on_error`.
+pub fn to_u64_lenient<T: JsonInput + ?Sized>(
+    value: &T,
+    on_error: OnError,
+) -> Result<Option<u64>, Error> {
+    cast_lenient(value, on_error, |value| {
+        try_to_u64(value).or_else(|| {
+            let s = as_str(value)?;
+            let s = s.trim();
+            s.parse::<u64>().ok().or_else(|| {
+                let v = s.parse::<f64>().ok()?;
+                (v.fract() == 0.0 && v >= 0.0 && v < u64::MAX as f64).then_some(v as u64)
+            })
+        })
+    })
+}
+
+/// Cast `JSONB` value to f64 like `to_f64`, also accepts the numeric strings with
+/// surrounding whitespaces, such as `" 4.2 "`.
+/// Returns `None` for `null`, the failures are handled by `on_error`.
+pub fn to_f64_lenient<T: JsonInput + ?Sized>(
+    value: &T,
+    on_error: OnError,
+) -> Result<Option<f64>, Error> {
+    cast_lenient(value, on_error, |value| {
+        try_to_f64(value).or_else(|| as_str(value)?.trim().parse::<f64>().ok())
+    })
+}
+
+/// How the elements are converted by `extract_f64_array` and `extract_i64_array`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Coercion {
+    /// Only the numbers are accepted, the numbers extracted as i64 must be exact integers.
+    #[default]
+    Strict,
+    /// The values are also cast like `to_f64_lenient` and `to_i64_lenient`,
+    /// such as booleans and numeric strings.
+    Lenient,
+}
+
+/// Extract the array matched first by the JSON path as a vector of f64 in one pass.
+/// Returns `None` if the path doesn't match an array, or an element can't be converted
+/// with the `coercion`, `null` elements are never converted.
+pub fn extract_f64_array<T: JsonInput + ?Sized>(
+    value: &T,
+    json_path: &JsonPath<'_>,
+    coercion: Coercion,
+) -> Option<Vec<f64>> {
+    extract_array(value, json_path, |element| match coercion {
+        Coercion::Strict => as_f64(element),
+        Coercion::Lenient => to_f64_lenient(element, OnError::Null).ok()?,
+    })
+}
+
+/// Extract the array matched first by the JSON path as a vector of i64 in one pass.
+/// Returns `None` if the path doesn't match an array, or an element can't be converted
+/// with the `coercion`, `null` elements are never converted.
+pub fn extract_i64_array<T: JsonInput + ?Sized>(
+    value: &T,
+    json_path: &JsonPath<'_>,
+    coercion: Coercion,
+) -> Option<Vec<i64>> {
+    extract_array(value, json_path, |element| match coercion {
+        Coercion::Strict => as_i64_exact(element).ok(),
+        Coercion::Lenient => as_i64_exact(element)
+            .ok()
+            .or_else(|| to_i64_lenient(element, OnError::Null).ok()?),
+    })
+}
+
+fn extract_array<T: JsonInput + ?Sized, V>(
+    value: &T,
+    json_path: &JsonPath<'_>,
+    convert: impl Fn(&[u8]) -> Option<V>,
+) -> Option<Vec<V>> {
+    let value = value.to_jsonb().ok()?;
+    let elements = array_values_iter_of(select_first_of(value, json_path)?)?;
+    elements.map(|element| convert(&element)).collect()
+}
+
+fn cast_lenient<T: JsonInput + ?Sized, V>(
+    value: &T,
+    on_error: OnError,
+    cast: impl FnOnce(&T) -> Option<V>,
+) -> Result<Option<V>, Error> {
+    if is_null(value) {
+        return Ok(None);
+    }
+    match (cast(value), on_error) {
+        (Some(v), _) => Ok(Some(v)),
+        (None, OnError::Null) => Ok(None),
+        (None, OnError::Fail) => Err(Error::InvalidCast),
     }
-    Err(Error::InvalidCast)
 }
 
 /// Returns true if the `JSONB` is a String. Returns false otherwise.
-pub fn is_string(value: &[u8]) -> bool {
+pub fn is_string<T: JsonInput + ?Sized>(value: &T) -> bool {
     as_str(value).is_some()
 }
 
 /// If the `JSONB` is a String, returns the String. Returns None otherwise.
-pub fn as_str(value: &[u8]) -> Option<Cow<'_, str>> {
-    if !is_jsonb(value) {
-        return match parse_value(value) {
-            Ok(val) => match val {
-                Value::String(s) => Some(s.clone()),
-                _ => None,
-            },
-            Err(_) => None,
-        };
-    }
-    let header = read_u32(value, 0).unwrap();
+pub fn as_str<T: JsonInput + ?Sized>(value: &T) -> Option<Cow<'_, str>> {
+    let buf = value.to_jsonb().ok()?;
+    let header = read_u32(&buf, 0).unwrap();
     match header & CONTAINER_HEADER_TYPE_MASK {
         SCALAR_CONTAINER_TAG => {
-            let jentry_encoded = read_u32(value, 4).unwrap();
+            let jentry_encoded = read_u32(&buf, 4).unwrap();
             let jentry = JEntry::decode_jentry(jentry_encoded);
             match jentry.type_code {
                 STRING_TAG => {
                     let length = jentry.length as usize;
-                    let s = unsafe { std::str::from_utf8_unchecked(&value[8..8 + length]) };
-                    Some(Cow::Borrowed(s))
+                    match buf {
+                        Cow::Borrowed(value) => {
+                            let s = unsafe { std::str::from_utf8_unchecked(&value[8..8 + length]) };
+                            Some(Cow::Borrowed(s))
+                        }
+                        Cow::Owned(mut value) => {
+                            value.truncate(8 + length);
+                            value.drain(..8);
+                            let s = unsafe { String::from_utf8_unchecked(value) };
+                            Some(Cow::Owned(s))
+                        }
+                    }
                 }
                 _ => None,
             }
@@ -909,211 +1866,476 @@ pub fn as_str(value: &[u8]) -> Option<Cow<'_, str>> {
 }
 
 /// Cast `JSONB` value to String
-pub fn to_str(value: &[u8]) -> Result<String, Error> {
-    if let Some(v) = as_str(value) {
-        return Ok(v.to_string());
-    } else if let Some(v) = as_bool(value) {
-        if v {
-            return Ok("true".to_string());
-        } else {
-            return Ok("false".to_string());
-        }
-    } else if let Some(v) = as_number(value) {
-        return Ok(format!("{}", v));
+pub fn to_str<T: JsonInput + ?Sized>(value: &T) -> Result<String, Error> {
+    try_to_str(value).ok_or(Error::InvalidCast)
+}
+
+/// Cast `JSONB` value to String like `to_str`, returns `None` if the value can't be cast.
+pub fn try_to_str<T: JsonInput + ?Sized>(value: &T) -> Option<String> {
+    let buf = value.to_jsonb().ok()?;
+    match cast_scalar(&buf)? {
+        CastScalar::String(v) => Some(v.to_string()),
+        CastScalar::Bool(v) => Some(v.to_string()),
+        CastScalar::Number(v) => Some(format!("{}", v)),
     }
-    Err(Error::InvalidCast)
+}
+
+/// Extract `len` characters from the 0-based character `start` of a `JSONB` string,
+/// returns the substring as a `JSONB` string. Returns `None` if the value is not a string.
+///
+/// The strings are stored unescaped, so an escape sequence of the JSON text, like `\n`,
+/// is a single character, and the characters are counted as Unicode scalar values.
+/// The range is clamped to the end of the string.
+pub fn str_slice<T: JsonInput + ?Sized>(value: &T, start: usize, len: usize) -> Option<Vec<u8>> {
+    let s = as_str(value)?;
+    let begin = s.char_indices().nth(start).map_or(s.len(), |(i, _)| i);
+    let rest = &s[begin..];
+    let end = rest.char_indices().nth(len).map_or(rest.len(), |(i, _)| i);
+    Some(Value::String(Cow::Borrowed(&rest[..end])).to_vec())
 }
 
 /// Returns true if the `JSONB` is An Array. Returns false otherwise.
-pub fn is_array(value: &[u8]) -> bool {
-    if !is_jsonb(value) {
-        return match parse_value(value) {
-            Ok(val) => val.is_array(),
-            Err(_) => false,
-        };
-    }
-    let header = read_u32(value, 0).unwrap();
+pub fn is_array<T: JsonInput + ?Sized>(value: &T) -> bool {
+    let Ok(buf) = value.to_jsonb() else {
+        return false;
+    };
+    let header = read_u32(&buf, 0).unwrap();
     matches!(header & CONTAINER_HEADER_TYPE_MASK, ARRAY_CONTAINER_TAG)
 }
 
 /// Returns true if the `JSONB` is An Object. Returns false otherwise.
-pub fn is_object(value: &[u8]) -> bool {
-    if !is_jsonb(value) {
-        return match parse_value(value) {
-            Ok(val) => val.is_object(),
-            Err(_) => false,
-        };
-    }
-    let header = read_u32(value, 0).unwrap();
+pub fn is_object<T: JsonInput + ?Sized>(value: &T) -> bool {
+    let Ok(buf) = value.to_jsonb() else {
+        return false;
+    };
+    let header = read_u32(&buf, 0).unwrap();
     matches!(header & CONTAINER_HEADER_TYPE_MASK, OBJECT_CONTAINER_TAG)
 }
 
 /// Convert `JSONB` value to String
-pub fn to_string(value: &[u8]) -> String {
-    if !is_jsonb(value) {
-        return String::from_utf8_lossy(value).to_string();
+///
+/// The floats with a whole value are written without the fractional part, like `1`,
+/// use [`to_string_with_options`] to write them like `1.0`.
+pub fn to_string<T: JsonInput + ?Sized>(value: &T) -> String {
+    let mut json = String::new();
+    to_string_into(value, &mut json);
+    json
+}
+
+/// Options to control the `JSON` text written by `to_string_with_options`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Write the floats with a whole value with the fractional part, like `1.0` instead of `1`,
+    /// so the consumers that re-parse the text with strict typing still read a float.
+    /// The integers are always written without the fractional part.
+    pub float_fraction: bool,
+}
+
+/// Convert `JSONB` value to String like [`to_string`], formatted with the options.
+pub fn to_string_with_options<T: JsonInput + ?Sized>(value: &T, options: &FormatOptions) -> String {
+    let Ok(buf) = value.to_jsonb() else {
+        return String::from_utf8_lossy(value.text().unwrap_or_default()).into_owned();
+    };
+    let mut json = String::with_capacity(buf.len());
+    write_json(&buf, false, options, usize::MAX, &mut json);
+    json
+}
+
+/// Convert `JSONB` value to `JSON` text and append it to the `json` buffer,
+/// the buffer can be reused to avoid allocating a `String` for each value.
+pub fn to_string_into<T: JsonInput + ?Sized>(value: &T, json: &mut String) {
+    let Ok(buf) = value.to_jsonb() else {
+        // `JSON` text that can't be parsed is returned as it is.
+        json.push_str(&String::from_utf8_lossy(value.text().unwrap_or_default()));
+        return;
+    };
+
+    // The text is usually not much longer than the encoded value.
+    json.reserve(buf.len());
+    write_json(&buf, false, &FormatOptions::default(), usize::MAX, json);
+}
+
+/// Format `JSONB` value for the snapshots of the tests like [`Value::debug_stable`],
+/// `JSON` text that can't be parsed is returned as it is.
+pub fn debug_stable<T: JsonInput + ?Sized>(value: &T, max_len: usize) -> String {
+    match value.to_value() {
+        Ok(value) => value.debug_stable(max_len),
+        Err(_) => String::from_utf8_lossy(value.text().unwrap_or_default()).into_owned(),
     }
+}
+
+/// The error of `to_string_bounded` if the `JSON` text is longer than the budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Truncated {
+    /// The beginning of the text, at most `max_bytes` long and cut at a character boundary.
+    pub text: String,
+}
 
+/// Convert `JSONB` value to `JSON` text with at most `max_bytes` bytes,
+/// the serialization stops once the budget is exceeded,
+/// so the large values are not serialized only to be thrown away.
+pub fn to_string_bounded<T: JsonInput + ?Sized>(
+    value: &T,
+    max_bytes: usize,
+) -> Result<String, Truncated> {
     let mut json = String::new();
-    container_to_string(value, &mut 0, &mut json);
-    json
+    let complete = match value.to_jsonb() {
+        Ok(buf) => {
+            json.reserve(buf.len().min(max_bytes));
+            write_json(&buf, false, &FormatOptions::default(), max_bytes, &mut json)
+        }
+        Err(_) => {
+            json.push_str(&String::from_utf8_lossy(value.text().unwrap_or_default()));
+            json.len() <= max_bytes
+        }
+    };
+    if complete {
+        return Ok(json);
+    }
+    let mut len = max_bytes;
+    while !json.is_char_boundary(len) {
+        len -= 1;
+    }
+    json.truncate(len);
+    Err(Truncated { text: json })
+}
+
+// A container being written. The values are written from an explicit stack
+// instead of recursion, so the deeply nested documents can't overflow the thread stack.
+struct WriteFrame {
+    is_object: bool,
+    length: usize,
+    index: usize,
+    key_jentry_offset: usize,
+    jentry_offset: usize,
+    key_offset: usize,
+    value_offset: usize,
+}
+
+// Returns false if the written text exceeds `max_bytes`, the rest of the value is not written.
+fn write_json(
+    value: &[u8],
+    pretty: bool,
+    options: &FormatOptions,
+    max_bytes: usize,
+    json: &mut String,
+) -> bool {
+    let start = json.len();
+    let mut stack = Vec::new();
+    open_container(value, 0, options, &mut stack, json);
+    while let Some(frame) = stack.last_mut() {
+        if json.len() - start > max_bytes {
+            return false;
+        }
+        if frame.index == frame.length {
+            let is_object = frame.is_object;
+            stack.pop();
+            // Like Postgres, the closing bracket of an empty container is also on a new line.
+            if pretty {
+                push_indent(stack.len(), json);
+            }
+            json.push(if is_object { '}' } else { ']' });
+            continue;
+        }
+        if frame.index > 0 {
+            json.push(',');
+        }
+        if pretty {
+            push_indent(stack.len(), json);
+        }
+        let frame = stack.last_mut().unwrap();
+        if frame.is_object {
+            let key_encoded = read_u32(value, frame.key_jentry_offset).unwrap();
+            let key_jentry = JEntry::decode_jentry(key_encoded);
+            let key_end = frame.key_offset + key_jentry.length as usize;
+            escape_scalar_string(value, frame.key_offset, key_end, json);
+            json.push_str(if pretty { ": " } else { ":" });
+            frame.key_jentry_offset += 4;
+            frame.key_offset = key_end;
+        }
+        let jentry_encoded = read_u32(value, frame.jentry_offset).unwrap();
+        let jentry = JEntry::decode_jentry(jentry_encoded);
+        let value_offset = frame.value_offset;
+        let length = jentry.length as usize;
+        frame.index += 1;
+        frame.jentry_offset += 4;
+        frame.value_offset += length;
+        if jentry.type_code == CONTAINER_TAG {
+            open_container(value, value_offset, options, &mut stack, json);
+        } else {
+            let data = &value[value_offset..value_offset + length];
+            scalar_to_string(&jentry, data, options, json);
+        }
+    }
+    json.len() - start <= max_bytes
 }
 
-fn container_to_string(value: &[u8], offset: &mut usize, json: &mut String) {
-    let header = read_u32(value, *offset).unwrap();
+// Write the opening bracket of the container at `offset` and push it to the stack,
+// the scalar container is written directly.
+fn open_container(
+    value: &[u8],
+    offset: usize,
+    options: &FormatOptions,
+    stack: &mut Vec<WriteFrame>,
+    json: &mut String,
+) {
+    let header = read_u32(value, offset).unwrap();
+    let length = (header & CONTAINER_HEADER_LEN_MASK) as usize;
     match header & CONTAINER_HEADER_TYPE_MASK {
         SCALAR_CONTAINER_TAG => {
-            let mut jentry_offset = 4 + *offset;
-            let mut value_offset = 8 + *offset;
-            scalar_to_string(value, &mut jentry_offset, &mut value_offset, json);
+            let jentry_encoded = read_u32(value, offset + 4).unwrap();
+            let jentry = JEntry::decode_jentry(jentry_encoded);
+            let data = &value[offset + 8..offset + 8 + jentry.length as usize];
+            scalar_to_string(&jentry, data, options, json);
         }
         ARRAY_CONTAINER_TAG => {
             json.push('[');
-            let length = (header & CONTAINER_HEADER_LEN_MASK) as usize;
-            let mut jentry_offset = 4 + *offset;
-            let mut value_offset = 4 + *offset + 4 * length;
-            for i in 0..length {
-                if i > 0 {
-                    json.push(',');
-                }
-                scalar_to_string(value, &mut jentry_offset, &mut value_offset, json);
-            }
-            json.push(']');
+            stack.push(WriteFrame {
+                is_object: false,
+                length,
+                index: 0,
+                key_jentry_offset: 0,
+                jentry_offset: offset + 4,
+                key_offset: 0,
+                value_offset: offset + 4 + 4 * length,
+            });
         }
         OBJECT_CONTAINER_TAG => {
             json.push('{');
-            let length = (header & CONTAINER_HEADER_LEN_MASK) as usize;
-            let mut jentry_offset = 4 + *offset;
-            let mut key_offset = 4 + *offset + 8 * length;
-            let mut keys = VecDeque::with_capacity(length);
-            for _ in 0..length {
-                let jentry_encoded = read_u32(value, jentry_offset).unwrap();
-                let jentry = JEntry::decode_jentry(jentry_encoded);
-                let key_length = jentry.length as usize;
-                keys.push_back((key_offset, key_offset + key_length));
-                jentry_offset += 4;
-                key_offset += key_length;
-            }
+            let key_offset = offset + 4 + 8 * length;
+            // The values are stored after all the keys.
             let mut value_offset = key_offset;
             for i in 0..length {
-                if i > 0 {
-                    json.push(',');
-                }
-                let (key_start, key_end) = keys.pop_front().unwrap();
-                escape_scalar_string(value, key_start, key_end, json);
-                json.push(':');
-                scalar_to_string(value, &mut jentry_offset, &mut value_offset, json);
+                let jentry_encoded = read_u32(value, offset + 4 + 4 * i).unwrap();
+                value_offset += JEntry::decode_jentry(jentry_encoded).length as usize;
             }
-            json.push('}');
+            stack.push(WriteFrame {
+                is_object: true,
+                length,
+                index: 0,
+                key_jentry_offset: offset + 4,
+                jentry_offset: offset + 4 + 4 * length,
+                key_offset,
+                value_offset,
+            });
         }
         _ => {}
     }
 }
 
-fn scalar_to_string(
-    value: &[u8],
-    jentry_offset: &mut usize,
-    value_offset: &mut usize,
-    json: &mut String,
-) {
-    let jentry_encoded = read_u32(value, *jentry_offset).unwrap();
-    let jentry = JEntry::decode_jentry(jentry_encoded);
-    let length = jentry.length as usize;
+fn scalar_to_string(jentry: &JEntry, data: &[u8], options: &FormatOptions, json: &mut String) {
     match jentry.type_code {
         NULL_TAG => json.push_str("null"),
         TRUE_TAG => json.push_str("true"),
         FALSE_TAG => json.push_str("false"),
         NUMBER_TAG => {
-            let num = Number::decode(&value[*value_offset..*value_offset + length]);
-            json.push_str(&format!("{num}"));
+            let num = Number::decode(data);
+            number_to_string(&num, options, json);
         }
         STRING_TAG => {
-            escape_scalar_string(value, *value_offset, *value_offset + length, json);
-        }
-        CONTAINER_TAG => {
-            container_to_string(value, value_offset, json);
+            escape_scalar_string(data, 0, data.len(), json);
         }
+        EXTENSION_TAG => match data.split_first() {
+            Some((tag, payload)) => format_extension(*tag, payload, json),
+            None => json.push_str("null"),
+        },
         _ => {}
     }
-    *jentry_offset += 4;
-    *value_offset += length;
 }
 
-fn escape_scalar_string(value: &[u8], start: usize, end: usize, json: &mut String) {
-    json.push('\"');
-    let mut last_start = start;
-    for i in start..end {
-        // add backslash for escaped characters.
-        let c = match value[i] {
-            0x5C => "\\\\",
-            0x22 => "\\\"",
-            0x2F => "\\/",
-            0x08 => "\\b",
-            0x0C => "\\f",
-            0x0A => "\\n",
-            0x0D => "\\r",
-            0x09 => "\\t",
-            _ => {
-                continue;
+fn number_to_string(num: &Number, options: &FormatOptions, json: &mut String) {
+    match num {
+        Number::Int64(v) => json.push_str(itoa::Buffer::new().format(*v)),
+        Number::UInt64(v) => json.push_str(itoa::Buffer::new().format(*v)),
+        // Keep the format of `Display`, which never uses the exponent notation.
+        Number::Float64(v) => {
+            let _ = write!(json, "{}", v);
+            if options.float_fraction && v.is_finite() && v.fract() == 0.0 {
+                json.push_str(".0");
             }
-        };
-        if i > last_start {
-            let val = String::from_utf8_lossy(&value[last_start..i]);
-            json.push_str(&val);
         }
-        json.push_str(c);
-        last_start = i + 1;
-    }
-    if last_start < end {
-        let val = String::from_utf8_lossy(&value[last_start..end]);
-        json.push_str(&val);
     }
+}
+
+fn escape_scalar_string(value: &[u8], start: usize, end: usize, json: &mut String) {
     json.push('\"');
+    escape_str_into(&String::from_utf8_lossy(&value[start..end]), json);
+    json.push('\"');
+}
+
+/// Convert `JSONB` value to indented `JSON` text and append it to the `json` buffer,
+/// the layout is the same as the Postgres `jsonb_pretty`.
+/// The text is formatted directly from the encoded value without decoding it.
+pub fn pretty<T: JsonInput + ?Sized>(value: &T, json: &mut String) {
+    let Ok(buf) = value.to_jsonb() else {
+        // `JSON` text that can't be parsed is returned as it is.
+        json.push_str(&String::from_utf8_lossy(value.text().unwrap_or_default()));
+        return;
+    };
+
+    json.reserve(buf.len());
+    write_json(&buf, true, &FormatOptions::default(), usize::MAX, json);
+}
+
+const PRETTY_INDENT: &str = "    ";
+
+// Start a new line with the indent.
+fn push_indent(indent: usize, json: &mut String) {
+    json.push('\n');
+    for _ in 0..indent {
+        json.push_str(PRETTY_INDENT);
+    }
 }
 
 /// Convert `JSONB` value to comparable vector.
 /// The compare rules are the same as the `compare` function.
 /// Scalar Null > Array > Object > Other Scalars(String > Number > Boolean).
-pub fn convert_to_comparable(value: &[u8], buf: &mut Vec<u8>) {
-    let depth = 0;
-    if !is_jsonb(value) {
-        match parse_value(value) {
-            Ok(val) => {
-                let val_buf = val.to_vec();
-                convert_to_comparable(&val_buf, buf);
-            }
-            Err(_) => {
-                buf.push(depth);
-                buf.push(INVALID_LEVEL);
-                buf.extend_from_slice(value);
-            }
+pub fn convert_to_comparable<T: JsonInput + ?Sized>(value: &T, buf: &mut Vec<u8>) {
+    convert_to_comparable_with_options(value, buf, &CompareOptions::default())
+}
+
+/// Convert `JSONB` value to comparable vector like `convert_to_comparable`,
+/// with the position of `null` controlled by `options.nulls`, and the timestamp strings
+/// ordered by time if `options.temporal_strings` is true, the same as `compare_with_options`.
+pub fn convert_to_comparable_with_options<T: JsonInput + ?Sized>(
+    value: &T,
+    buf: &mut Vec<u8>,
+    options: &CompareOptions,
+) {
+    convert_to_comparable_at(value, 0, buf, options)
+}
+
+// Convert the value to comparable vector as an element nested at the `depth`,
+// the comparable vector of a container is the concatenation of its elements.
+pub(crate) fn convert_to_comparable_at<T: JsonInput + ?Sized>(
+    value: &T,
+    depth: u8,
+    buf: &mut Vec<u8>,
+    options: &CompareOptions,
+) {
+    let val_buf = match value.to_jsonb() {
+        Ok(val_buf) => val_buf,
+        Err(_) => {
+            buf.push(depth);
+            buf.push(INVALID_LEVEL);
+            buf.extend_from_slice(value.text().unwrap_or_default());
+            return;
         }
-        return;
-    }
+    };
+    let value = val_buf.as_ref();
     let header = read_u32(value, 0).unwrap();
     match header & CONTAINER_HEADER_TYPE_MASK {
         SCALAR_CONTAINER_TAG => {
             let encoded = read_u32(value, 4).unwrap();
             let jentry = JEntry::decode_jentry(encoded);
-            scalar_convert_to_comparable(depth, &jentry, &value[8..], buf);
+            scalar_convert_to_comparable(depth, &jentry, &value[8..], buf, options);
         }
         ARRAY_CONTAINER_TAG => {
             buf.push(depth);
-            buf.push(ARRAY_LEVEL);
+            buf.push(options.nulls.level(ARRAY_LEVEL));
             let length = (header & CONTAINER_HEADER_LEN_MASK) as usize;
-            array_convert_to_comparable(depth + 1, length, &value[4..], buf);
+            array_convert_to_comparable(depth + 1, length, &value[4..], buf, options);
         }
         OBJECT_CONTAINER_TAG => {
             buf.push(depth);
-            buf.push(OBJECT_LEVEL);
+            buf.push(options.nulls.level(OBJECT_LEVEL));
             let length = (header & CONTAINER_HEADER_LEN_MASK) as usize;
-            object_convert_to_comparable(depth + 1, length, &value[4..], buf);
+            object_convert_to_comparable(depth + 1, length, &value[4..], buf, options);
         }
         _ => {}
     }
 }
 
-fn scalar_convert_to_comparable(depth: u8, jentry: &JEntry, value: &[u8], buf: &mut Vec<u8>) {
+/// Extract the first value matched by the JSON path and convert it to comparable vector,
+/// the result is the same as `get_by_path_first` followed by `convert_to_comparable`,
+/// but the matched container is not copied.
+/// Returns false and leaves `buf` unchanged if nothing is matched, like SQL NULL group keys.
+pub fn group_key<T: JsonInput + ?Sized>(
+    value: &T,
+    json_path: &JsonPath<'_>,
+    buf: &mut Vec<u8>,
+) -> bool {
+    let Ok(val_buf) = value.to_jsonb() else {
+        return false;
+    };
+    let selector = Selector::from_ref(json_path);
+    match selector.select_first(val_buf.as_ref()) {
+        Some(matched) => {
+            convert_to_comparable(&RawJsonb::new(matched.as_ref()), buf);
+            true
+        }
+        None => false,
+    }
+}
+
+/// The result of [`join_key`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JoinKey {
+    /// The comparable vector of the matched value is appended to the buffer.
+    Key,
+    /// The path matches nothing or a JSON `null`, an SQL NULL that joins no rows.
+    Null,
+    /// The path matches more than one value or a container, like the SQL/JSON `JSON_VALUE`,
+    /// or the value is invalid.
+    Error(Error),
+}
+
+/// Extract the scalar value matched by the JSON path as the key of a hash join, the key is
+/// the comparable vector of the value, so equal numbers of different types have equal keys.
+///
+/// Like the SQL/JSON `JSON_VALUE` in lax mode, a missing value and a JSON `null` are SQL NULL,
+/// and more than one value or a container is an error.
+/// `buf` is unchanged unless the result is [`JoinKey::Key`].
+pub fn join_key<T: JsonInput + ?Sized>(
+    value: &T,
+    json_path: &JsonPath<'_>,
+    buf: &mut Vec<u8>,
+) -> JoinKey {
+    let val_buf = match value.to_jsonb() {
+        Ok(val_buf) => val_buf,
+        Err(err) => return JoinKey::Error(err),
+    };
+    let selector = Selector::from_ref(json_path);
+    let mut matched = selector.select(val_buf.as_ref());
+    if matched.len() > 1 {
+        return JoinKey::Error(Error::InvalidJsonPath);
+    }
+    let Some(matched) = matched.pop() else {
+        return JoinKey::Null;
+    };
+    let matched = RawJsonb::new(&matched);
+    if is_null(&matched) {
+        JoinKey::Null
+    } else if is_array(&matched) || is_object(&matched) {
+        JoinKey::Error(Error::InvalidJsonType)
+    } else {
+        convert_to_comparable(&matched, buf);
+        JoinKey::Key
+    }
+}
+
+fn scalar_convert_to_comparable(
+    depth: u8,
+    jentry: &JEntry,
+    value: &[u8],
+    buf: &mut Vec<u8>,
+    options: &CompareOptions,
+) {
+    if jentry.type_code == EXTENSION_TAG {
+        // extension values are converted as their plain values
+        let raw = &value[..jentry.length as usize];
+        match extension_to_plain(raw) {
+            Some((plain_jentry, plain)) => {
+                scalar_convert_to_comparable(depth, &plain_jentry, &plain, buf, options);
+            }
+            None => {
+                buf.push(depth);
+                buf.push(INVALID_LEVEL);
+                buf.extend_from_slice(raw);
+            }
+        }
+        return;
+    }
     buf.push(depth);
     let level = jentry_compare_level(jentry);
     match jentry.type_code {
@@ -1122,22 +2344,40 @@ fn scalar_convert_to_comparable(depth: u8, jentry: &JEntry, value: &[u8], buf: &
             let length = (header & CONTAINER_HEADER_LEN_MASK) as usize;
             match header & CONTAINER_HEADER_TYPE_MASK {
                 ARRAY_CONTAINER_TAG => {
-                    buf.push(ARRAY_LEVEL);
-                    array_convert_to_comparable(depth + 1, length, &value[4..], buf);
+                    buf.push(options.nulls.level(ARRAY_LEVEL));
+                    array_convert_to_comparable(depth + 1, length, &value[4..], buf, options);
                 }
                 OBJECT_CONTAINER_TAG => {
-                    buf.push(OBJECT_LEVEL);
-                    object_convert_to_comparable(depth + 1, length, &value[4..], buf);
+                    buf.push(options.nulls.level(OBJECT_LEVEL));
+                    object_convert_to_comparable(depth + 1, length, &value[4..], buf, options);
                 }
                 _ => {}
             }
         }
         _ => {
-            buf.push(level);
+            buf.push(options.nulls.level(level));
             match jentry.type_code {
                 STRING_TAG => {
                     let length = jentry.length as usize;
-                    buf.extend_from_slice(&value[..length]);
+                    let s = &value[..length];
+                    if options.temporal_strings {
+                        // Timestamps are less than the other strings, like `compare`.
+                        match std::str::from_utf8(s).ok().and_then(parse_timestamp) {
+                            Some(ts) => {
+                                buf.push(0);
+                                // Toggle the sign bit, so that the timestamps before the epoch
+                                // are ordered first.
+                                let ts = (ts as u128) ^ (1 << 127);
+                                buf.extend_from_slice(&ts.to_be_bytes());
+                            }
+                            None => {
+                                buf.push(1);
+                                buf.extend_from_slice(s);
+                            }
+                        }
+                    } else {
+                        buf.extend_from_slice(s);
+                    }
                 }
                 NUMBER_TAG => {
                     let length = jentry.length as usize;
@@ -1157,19 +2397,31 @@ fn scalar_convert_to_comparable(depth: u8, jentry: &JEntry, value: &[u8], buf: &
     }
 }
 
-fn array_convert_to_comparable(depth: u8, length: usize, value: &[u8], buf: &mut Vec<u8>) {
+fn array_convert_to_comparable(
+    depth: u8,
+    length: usize,
+    value: &[u8],
+    buf: &mut Vec<u8>,
+    options: &CompareOptions,
+) {
     let mut jentry_offset = 0;
     let mut val_offset = 4 * length;
     for _ in 0..length {
         let encoded = read_u32(value, jentry_offset).unwrap();
         let jentry = JEntry::decode_jentry(encoded);
-        scalar_convert_to_comparable(depth, &jentry, &value[val_offset..], buf);
+        scalar_convert_to_comparable(depth, &jentry, &value[val_offset..], buf, options);
         jentry_offset += 4;
         val_offset += jentry.length as usize;
     }
 }
 
-fn object_convert_to_comparable(depth: u8, length: usize, value: &[u8], buf: &mut Vec<u8>) {
+fn object_convert_to_comparable(
+    depth: u8,
+    length: usize,
+    value: &[u8],
+    buf: &mut Vec<u8>,
+    options: &CompareOptions,
+) {
     let mut jentry_offset = 0;
     let mut val_offset = 8 * length;
 
@@ -1187,11 +2439,11 @@ fn object_convert_to_comparable(depth: u8, length: usize, value: &[u8], buf: &mu
     let mut key_offset = 8 * length;
     for _ in 0..length {
         let key_jentry = key_jentries.pop_front().unwrap();
-        scalar_convert_to_comparable(depth, &key_jentry, &value[key_offset..], buf);
+        scalar_convert_to_comparable(depth, &key_jentry, &value[key_offset..], buf, options);
 
         let encoded = read_u32(value, jentry_offset).unwrap();
         let val_jentry = JEntry::decode_jentry(encoded);
-        scalar_convert_to_comparable(depth, &val_jentry, &value[val_offset..], buf);
+        scalar_convert_to_comparable(depth, &val_jentry, &value[val_offset..], buf, options);
 
         jentry_offset += 4;
         key_offset += key_jentry.length as usize;
@@ -1256,17 +2508,6 @@ fn rand_scalar_value() -> Value<'static> {
     val
 }
 
-// Check whether the value is `JSONB` format,
-// for compatibility with previous `JSON` string.
-fn is_jsonb(value: &[u8]) -> bool {
-    if let Some(v) = value.first() {
-        if matches!(*v, ARRAY_PREFIX | OBJECT_PREFIX | SCALAR_PREFIX) {
-            return true;
-        }
-    }
-    false
-}
-
 fn read_u32(buf: &[u8], idx: usize) -> Result<u32, Error> {
     let bytes: [u8; 4] = buf
         .get(idx..idx + 4)
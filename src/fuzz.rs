@@ -0,0 +1,246 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Malformed values derived from valid `JSONB` values, to test the handling of corrupted input.
+//!
+//! The mutations are the failure modes of the binary encoding checked by the validating readers,
+//! such as [`dump_layout`](crate::dump_layout) and [`ContainerReader`]: truncated buffers,
+//! undefined or mismatched type bits, and lengths past the end of the buffer.
+//! Only a single header or `JEntry` is changed in each mutation, so the rest of the value is
+//! still well-formed and the readers have to find the corrupted part.
+//!
+//! ```
+//! use jsonb::fuzz::{mutations, MutationKind};
+//!
+//! let value = jsonb::parse_value(br#"{"a":[1,"b"]}"#).unwrap().to_vec();
+//! let mutations = mutations(&value).unwrap();
+//! assert!(mutations.iter().any(|m| m.kind == MutationKind::InvalidTag));
+//! for mutation in mutations {
+//!     assert_ne!(mutation.value, value);
+//! }
+//! ```
+
+use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::encoding::ContainerKind;
+use crate::encoding::ContainerReader;
+use crate::encoding::Entry;
+use crate::encoding::EntryKind;
+use crate::encoding::Header;
+use crate::encoding::CONTAINER_HEADER_LEN_MASK;
+use crate::encoding::CONTAINER_HEADER_TYPE_MASK;
+use crate::encoding::HEADER_SIZE;
+use crate::encoding::JENTRY_OFF_LEN_MASK;
+use crate::encoding::JENTRY_SIZE;
+use crate::encoding::JENTRY_TYPE_MASK;
+use crate::error::Error;
+
+// Type bits that are not assigned to any container or `JEntry` type.
+const UNDEFINED_CONTAINER_TAG: u32 = 0x00000000;
+const UNDEFINED_JENTRY_TAG: u32 = 0x70000000;
+
+/// The kind of corruption of a [`Mutation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MutationKind {
+    /// The buffer is cut at the offset.
+    Truncate,
+    /// The type bits of the header at the offset are undefined.
+    InvalidHeader,
+    /// The header at the offset has one more element than the entries in the buffer.
+    HeaderLength,
+    /// The type bits of the `JEntry` at the offset are undefined.
+    InvalidTag,
+    /// The type bits of the `JEntry` at the offset are another defined type,
+    /// the data may still happen to decode as that type.
+    FlippedTag,
+    /// The length of the `JEntry` at the offset ends one byte past the end of the buffer.
+    BadOffset,
+}
+
+/// A malformed value and the corruption it was derived with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mutation {
+    pub kind: MutationKind,
+    /// The offset of the truncation, or of the changed header or `JEntry`.
+    pub offset: usize,
+    pub value: Vec<u8>,
+}
+
+// A header or a `JEntry` of the value.
+enum Location {
+    Header {
+        offset: usize,
+        header: Header,
+    },
+    Entry {
+        offset: usize,
+        entry: Entry,
+        data: usize,
+    },
+}
+
+/// Returns all the mutations of the value, in the order of the changed offsets.
+/// Returns an error if the value is not a valid `JSONB` value.
+pub fn mutations(value: &[u8]) -> Result<Vec<Mutation>, Error> {
+    let mut mutations = Vec::new();
+    for location in locations(value)? {
+        match location {
+            Location::Header { offset, header } => {
+                mutations.push(truncate(value, offset + HEADER_SIZE / 2));
+                let encoded = header.encode()?;
+                let invalid = (encoded & !CONTAINER_HEADER_TYPE_MASK) | UNDEFINED_CONTAINER_TAG;
+                mutations.push(replace(value, MutationKind::InvalidHeader, offset, invalid));
+                if header.kind != ContainerKind::Scalar
+                    && header.length < CONTAINER_HEADER_LEN_MASK as usize
+                {
+                    mutations.push(replace(
+                        value,
+                        MutationKind::HeaderLength,
+                        offset,
+                        encoded + 1,
+                    ));
+                }
+            }
+            Location::Entry {
+                offset,
+                entry,
+                data,
+            } => {
+                mutations.push(truncate(value, offset + JENTRY_SIZE / 2));
+                if entry.length > 1 {
+                    mutations.push(truncate(value, data + entry.length / 2));
+                }
+                let encoded = entry.encode()?;
+                let length = encoded & JENTRY_OFF_LEN_MASK;
+                mutations.push(replace(
+                    value,
+                    MutationKind::InvalidTag,
+                    offset,
+                    UNDEFINED_JENTRY_TAG | length,
+                ));
+                let flipped = Entry {
+                    kind: flip(entry.kind),
+                    length: entry.length,
+                };
+                mutations.push(replace(
+                    value,
+                    MutationKind::FlippedTag,
+                    offset,
+                    flipped.encode()?,
+                ));
+                // The length of `null` and booleans is never read.
+                let length = entry.length + value.len() - (data + entry.length) + 1;
+                if !matches!(
+                    entry.kind,
+                    EntryKind::Null | EntryKind::False | EntryKind::True
+                ) && length <= JENTRY_OFF_LEN_MASK as usize
+                {
+                    let encoded = (encoded & JENTRY_TYPE_MASK) | length as u32;
+                    mutations.push(replace(value, MutationKind::BadOffset, offset, encoded));
+                }
+            }
+        }
+    }
+    Ok(mutations)
+}
+
+/// A generator of random mutations of values, the same seed always produces
+/// the same sequence of mutations for the same values.
+#[derive(Debug, Clone)]
+pub struct Mutator {
+    rng: ChaCha8Rng,
+}
+
+impl Mutator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Returns a random mutation of the value.
+    /// Returns an error if the value is not a valid `JSONB` value.
+    pub fn mutate(&mut self, value: &[u8]) -> Result<Mutation, Error> {
+        let mut mutations = mutations(value)?;
+        let index = self.rng.gen_range(0..mutations.len());
+        Ok(mutations.swap_remove(index))
+    }
+}
+
+// The headers and `JEntry`s of the value and its nested containers, in the order of the offsets.
+fn locations(value: &[u8]) -> Result<Vec<Location>, Error> {
+    let mut locations = Vec::new();
+    let mut stack = vec![0];
+    while let Some(start) = stack.pop() {
+        let reader = ContainerReader::new(&value[start..])?;
+        let header = reader.header();
+        locations.push(Location::Header {
+            offset: start,
+            header,
+        });
+        let count = match header.kind {
+            ContainerKind::Object => reader.len() * 2,
+            _ => reader.len(),
+        };
+        let mut data = start + HEADER_SIZE + count * JENTRY_SIZE;
+        let mut containers = Vec::new();
+        for i in 0..count {
+            let offset = start + HEADER_SIZE + i * JENTRY_SIZE;
+            let bytes = value[offset..offset + JENTRY_SIZE].try_into().unwrap();
+            let entry = Entry::decode(u32::from_be_bytes(bytes))?;
+            locations.push(Location::Entry {
+                offset,
+                entry,
+                data,
+            });
+            if entry.kind == EntryKind::Container {
+                containers.push(data);
+            }
+            data += entry.length;
+        }
+        stack.extend(containers.into_iter().rev());
+    }
+    locations.sort_by_key(|location| match location {
+        Location::Header { offset, .. } | Location::Entry { offset, .. } => *offset,
+    });
+    Ok(locations)
+}
+
+// Another defined type, the data of a scalar is decoded as a container and the other way around.
+fn flip(kind: EntryKind) -> EntryKind {
+    match kind {
+        EntryKind::Container => EntryKind::String,
+        _ => EntryKind::Container,
+    }
+}
+
+fn truncate(value: &[u8], offset: usize) -> Mutation {
+    Mutation {
+        kind: MutationKind::Truncate,
+        offset,
+        value: value[..offset].to_vec(),
+    }
+}
+
+fn replace(value: &[u8], kind: MutationKind, offset: usize, encoded: u32) -> Mutation {
+    let mut mutated = value.to_vec();
+    mutated[offset..offset + 4].copy_from_slice(&encoded.to_be_bytes());
+    Mutation {
+        kind,
+        offset,
+        value: mutated,
+    }
+}
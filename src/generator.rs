@@ -0,0 +1,191 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deterministic generator of random documents.
+//!
+//! The same seed and [`GeneratorSpec`] always produce the same sequence of documents
+//! with the same version of this crate, so benchmarks and fuzz corpora are reproducible.
+//! The random numbers come from `ChaCha8`, which unlike `StdRng` has a fixed algorithm
+//! and doesn't change with the versions of `rand`.
+//!
+//! ```
+//! use jsonb::generator::Generator;
+//! use jsonb::generator::GeneratorSpec;
+//!
+//! let mut generator = Generator::new(42, GeneratorSpec::default());
+//! let value = generator.generate();
+//! assert_eq!(value, Generator::new(42, GeneratorSpec::default()).generate());
+//! ```
+
+use std::borrow::Cow;
+
+use rand::distributions::Distribution;
+use rand::distributions::WeightedIndex;
+use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::number::Number;
+use crate::value::Object;
+use crate::value::Value;
+
+/// The relative weights of the value types, a type with zero weight is never generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueWeights {
+    pub null: u32,
+    pub boolean: u32,
+    pub number: u32,
+    pub string: u32,
+    pub array: u32,
+    pub object: u32,
+}
+
+impl Default for ValueWeights {
+    fn default() -> Self {
+        Self {
+            null: 1,
+            boolean: 1,
+            number: 2,
+            string: 2,
+            array: 1,
+            object: 1,
+        }
+    }
+}
+
+/// The shape of the generated documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratorSpec {
+    /// The maximum nesting depth of containers, the values at this depth are scalars.
+    pub max_depth: usize,
+    /// The maximum number of elements of an array or fields of an object.
+    pub max_len: usize,
+    /// The characters of the object keys and the strings.
+    pub key_alphabet: Vec<char>,
+    /// The maximum number of characters of the object keys, at least one character is used
+    /// if the alphabet is not empty.
+    pub max_key_len: usize,
+    /// The maximum number of characters of the strings.
+    pub max_string_len: usize,
+    pub weights: ValueWeights,
+}
+
+impl Default for GeneratorSpec {
+    fn default() -> Self {
+        Self {
+            max_depth: 3,
+            max_len: 5,
+            key_alphabet: ('a'..='z').collect(),
+            max_key_len: 8,
+            max_string_len: 16,
+            weights: ValueWeights::default(),
+        }
+    }
+}
+
+/// Generator of random documents from a seed, see the [module documentation](self).
+pub struct Generator {
+    spec: GeneratorSpec,
+    rng: ChaCha8Rng,
+}
+
+impl Generator {
+    pub fn new(seed: u64, spec: GeneratorSpec) -> Self {
+        Self {
+            spec,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Generate the next document as encoded `JSONB`.
+    pub fn generate(&mut self) -> Vec<u8> {
+        self.generate_value().to_vec()
+    }
+
+    /// Generate the next document as `Value`.
+    pub fn generate_value(&mut self) -> Value<'static> {
+        self.random_value(0)
+    }
+
+    fn random_value(&mut self, depth: usize) -> Value<'static> {
+        let weights = &self.spec.weights;
+        let mut choices = vec![
+            weights.null,
+            weights.boolean,
+            weights.number,
+            weights.string,
+        ];
+        if depth < self.spec.max_depth {
+            choices.push(weights.array);
+            choices.push(weights.object);
+        }
+        // All the allowed types have zero weight.
+        let Ok(dist) = WeightedIndex::new(&choices) else {
+            return Value::Null;
+        };
+        match dist.sample(&mut self.rng) {
+            0 => Value::Null,
+            1 => Value::Bool(self.rng.gen_bool(0.5)),
+            2 => Value::Number(self.random_number()),
+            3 => {
+                let len = self.rng.gen_range(0..=self.spec.max_string_len);
+                Value::String(Cow::Owned(self.random_string(len)))
+            }
+            4 => {
+                let len = self.rng.gen_range(0..=self.spec.max_len);
+                let values = (0..len).map(|_| self.random_value(depth + 1)).collect();
+                Value::Array(values)
+            }
+            _ => {
+                let len = self.rng.gen_range(0..=self.spec.max_len);
+                let mut obj = Object::new();
+                for _ in 0..len {
+                    let key_len = self.rng.gen_range(1..=self.spec.max_key_len.max(1));
+                    let key = self.random_string(key_len);
+                    let val = self.random_value(depth + 1);
+                    obj.insert(key, val);
+                }
+                Value::Object(obj)
+            }
+        }
+    }
+
+    fn random_number(&mut self) -> Number {
+        match self.rng.gen_range(0..3) {
+            0 => Number::UInt64(self.rng.gen_range(0..=u32::MAX as u64)),
+            1 => Number::Int64(self.rng.gen_range(i32::MIN as i64..0)),
+            _ => Number::Float64(self.rng.gen_range(-1.0e6..1.0e6)),
+        }
+    }
+
+    fn random_string(&mut self, len: usize) -> String {
+        if self.spec.key_alphabet.is_empty() {
+            return String::new();
+        }
+        (0..len)
+            .map(|_| {
+                let idx = self.rng.gen_range(0..self.spec.key_alphabet.len());
+                self.spec.key_alphabet[idx]
+            })
+            .collect()
+    }
+}
+
+impl Iterator for Generator {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.generate())
+    }
+}
@@ -0,0 +1,139 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::hash::Hasher;
+
+use crate::constants::ARRAY_LEVEL;
+use crate::constants::OBJECT_LEVEL;
+use crate::constants::STRING_LEVEL;
+use crate::functions::convert_to_comparable_at;
+use crate::functions::CompareOptions;
+use crate::input::JsonInput;
+
+// The bytes are passed to the inner hasher in blocks of this size,
+// so the digest doesn't depend on how the input is split into chunks.
+const BLOCK_SIZE: usize = 64;
+
+/// Hash `JSONB` documents incrementally by their comparable form, see [`crate::convert_to_comparable`].
+///
+/// A document can be fed as a whole by [`JsonbHasher::write_value`], piece by piece
+/// by opening the containers and writing the keys and elements, or as chunks of
+/// its comparable form by [`Hasher::write`]. All of them produce the same digest,
+/// so a large document can be fingerprinted while it is streamed.
+/// The keys of an object must be written in the order of `JSONB`, which is sorted.
+///
+/// The values that are equal by `compare` have the same digest.
+///
+/// ```
+/// use std::collections::hash_map::DefaultHasher;
+///
+/// use jsonb::JsonbHasher;
+///
+/// let mut whole = JsonbHasher::new(DefaultHasher::new());
+/// whole.write_value(r#"{"a":[1,2],"b":"x"}"#);
+///
+/// let mut pieces = JsonbHasher::new(DefaultHasher::new());
+/// pieces.begin_object();
+/// pieces.write_key("a");
+/// pieces.begin_array();
+/// pieces.write_value("1");
+/// pieces.write_value("2");
+/// pieces.end();
+/// pieces.write_key("b");
+/// pieces.write_value(r#""x""#);
+/// pieces.end();
+///
+/// assert_eq!(whole.digest(), pieces.digest());
+/// ```
+#[derive(Debug, Clone)]
+pub struct JsonbHasher<H> {
+    hasher: H,
+    depth: u8,
+    block: Vec<u8>,
+    scratch: Vec<u8>,
+}
+
+impl<H: Hasher + Clone> JsonbHasher<H> {
+    pub fn new(hasher: H) -> Self {
+        Self {
+            hasher,
+            depth: 0,
+            block: Vec::with_capacity(BLOCK_SIZE),
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Write a complete value at the current position, the whole document
+    /// or an element of the opened container.
+    pub fn write_value<T: JsonInput + ?Sized>(&mut self, value: &T) {
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.clear();
+        convert_to_comparable_at(value, self.depth, &mut scratch, &CompareOptions::default());
+        self.write(&scratch);
+        self.scratch = scratch;
+    }
+
+    /// Open an array, the elements are written until the matching [`JsonbHasher::end`].
+    pub fn begin_array(&mut self) {
+        self.write(&[self.depth, ARRAY_LEVEL]);
+        self.depth += 1;
+    }
+
+    /// Open an object, the keys and values are written until the matching [`JsonbHasher::end`].
+    pub fn begin_object(&mut self) {
+        self.write(&[self.depth, OBJECT_LEVEL]);
+        self.depth += 1;
+    }
+
+    /// Write the key of the opened object, followed by its value.
+    pub fn write_key(&mut self, key: &str) {
+        self.write(&[self.depth, STRING_LEVEL]);
+        self.write(key.as_bytes());
+    }
+
+    /// Close the opened array or object.
+    pub fn end(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+
+    /// Returns the digest of the bytes written so far, more bytes can still be written.
+    pub fn digest(&self) -> u64 {
+        let mut hasher = self.hasher.clone();
+        hasher.write(&self.block);
+        hasher.finish()
+    }
+
+    fn write_block(&mut self) {
+        self.hasher.write(&self.block);
+        self.block.clear();
+    }
+}
+
+impl<H: Hasher + Clone> Hasher for JsonbHasher<H> {
+    fn finish(&self) -> u64 {
+        self.digest()
+    }
+
+    /// Write a chunk of the comparable form of the documents.
+    fn write(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            let len = (BLOCK_SIZE - self.block.len()).min(bytes.len());
+            self.block.extend_from_slice(&bytes[..len]);
+            bytes = &bytes[len..];
+            if self.block.len() == BLOCK_SIZE {
+                self.write_block();
+            }
+        }
+    }
+}
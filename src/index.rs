@@ -0,0 +1,150 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+
+use crate::input::JsonInput;
+use crate::number::Number;
+use crate::value::Value;
+
+// The flags of index entries, same as the flags of Postgres `jsonb_ops`.
+const ENTRY_FLAG_KEY: u8 = 0x01;
+const ENTRY_FLAG_NULL: u8 = 0x02;
+const ENTRY_FLAG_BOOL: u8 = 0x03;
+const ENTRY_FLAG_NUMBER: u8 = 0x04;
+const ENTRY_FLAG_STRING: u8 = 0x05;
+
+/// The kind of entries produced by [`index_entries`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexMode {
+    /// Entries of the object keys and the scalar values, like Postgres `jsonb_ops`.
+    /// String elements of arrays are also indexed as keys, so that they can be found by the key existence.
+    Keys,
+    /// Entries of the key paths from the root to each object field.
+    Paths,
+    /// Entries of the key paths together with the scalar values at the end of the paths,
+    /// like Postgres `jsonb_path_ops`.
+    PathValues,
+}
+
+/// Extract the entries of the `JSONB` value to build a GIN style inverted index.
+///
+/// The entries are sorted and deduplicated, invalid values have no entries.
+/// Array elements are transparent in the key paths, and numbers are normalized,
+/// so `1` and `1.0` produce the same entries.
+///
+/// If `a @> b`, the entries of `b` are a subset of the entries of `a` in the same mode,
+/// so the entries of the query value can be used to look up the candidates of the containment.
+pub fn index_entries<T: JsonInput + ?Sized>(
+    value: &T,
+    mode: IndexMode,
+) -> impl Iterator<Item = Vec<u8>> {
+    let mut entries = BTreeSet::new();
+    if let Ok(value) = value.to_value() {
+        // The top-level scalar is indexed like the element of an array.
+        let value = match value {
+            Value::Array(_) | Value::Object(_) => value,
+            _ => Value::Array(vec![value]),
+        };
+        collect_entries(&value, mode, &mut Vec::new(), &mut entries);
+    }
+    entries.into_iter()
+}
+
+fn collect_entries(
+    value: &Value<'_>,
+    mode: IndexMode,
+    path: &mut Vec<u8>,
+    entries: &mut BTreeSet<Vec<u8>>,
+) {
+    match value {
+        Value::Object(obj) => {
+            for (key, val) in obj.iter() {
+                match mode {
+                    IndexMode::Keys => {
+                        let mut entry = vec![ENTRY_FLAG_KEY];
+                        entry.extend_from_slice(key.as_bytes());
+                        entries.insert(entry);
+                        collect_entries(val, mode, path, entries);
+                    }
+                    IndexMode::Paths | IndexMode::PathValues => {
+                        // Each key is prefixed by the length to keep the path unambiguous.
+                        let len = path.len();
+                        path.extend_from_slice(&(key.len() as u32).to_be_bytes());
+                        path.extend_from_slice(key.as_bytes());
+                        if mode == IndexMode::Paths {
+                            entries.insert(path.clone());
+                        }
+                        collect_entries(val, mode, path, entries);
+                        path.truncate(len);
+                    }
+                }
+            }
+        }
+        Value::Array(vals) => {
+            for val in vals.iter() {
+                match (mode, val) {
+                    (IndexMode::Keys, Value::String(s)) => {
+                        let mut entry = vec![ENTRY_FLAG_KEY];
+                        entry.extend_from_slice(s.as_bytes());
+                        entries.insert(entry);
+                    }
+                    (_, _) => collect_entries(val, mode, path, entries),
+                }
+            }
+        }
+        _ => match mode {
+            IndexMode::Keys => {
+                entries.insert(scalar_entry(value));
+            }
+            IndexMode::Paths => {}
+            IndexMode::PathValues => {
+                let mut entry = path.clone();
+                entry.extend_from_slice(&scalar_entry(value));
+                entries.insert(entry);
+            }
+        },
+    }
+}
+
+fn scalar_entry(value: &Value<'_>) -> Vec<u8> {
+    match value {
+        Value::Null => vec![ENTRY_FLAG_NULL],
+        Value::Bool(v) => vec![ENTRY_FLAG_BOOL, if *v { b't' } else { b'f' }],
+        Value::Number(n) => {
+            let mut entry = vec![ENTRY_FLAG_NUMBER];
+            entry.extend_from_slice(normalize_number(n).as_bytes());
+            entry
+        }
+        Value::String(s) => {
+            let mut entry = vec![ENTRY_FLAG_STRING];
+            entry.extend_from_slice(s.as_bytes());
+            entry
+        }
+        // Extension values are indexed by their text.
+        _ => {
+            let mut entry = vec![ENTRY_FLAG_STRING];
+            entry.extend_from_slice(value.to_string().as_bytes());
+            entry
+        }
+    }
+}
+
+// Integral floats are displayed as integers, `-0.0` is normalized to `0`.
+fn normalize_number(n: &Number) -> String {
+    match n {
+        Number::Float64(v) if *v == 0.0 => "0".to_string(),
+        _ => n.to_string(),
+    }
+}
@@ -0,0 +1,258 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+
+use serde::Serialize;
+
+use super::constants::*;
+use super::de::from_slice;
+use super::error::Error;
+use super::parser::parse_value;
+use super::value::Value;
+
+/// Binary `JSONB` value.
+///
+/// The bytes are used as `JSONB` directly without checking whether they are `JSON` text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawJsonb<'a>(&'a [u8]);
+
+impl<'a> RawJsonb<'a> {
+    /// Wrap the bytes of binary `JSONB`, the bytes are not validated.
+    pub fn new(data: &'a [u8]) -> RawJsonb<'a> {
+        Self(data)
+    }
+
+    /// Returns the bytes of binary `JSONB`.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+}
+
+/// The input of `JSONB` functions.
+///
+/// Implemented for the binary [`RawJsonb`], `JSON` text in `str` and `String`,
+/// the decoded [`Value`], and for compatibility, bytes in `[u8]` and `Vec<u8>`,
+/// which are checked whether they are `JSONB` or `JSON` text on each call.
+pub trait JsonInput {
+    /// Returns the binary `JSONB`, `JSON` text is parsed and encoded.
+    fn to_jsonb(&self) -> Result<Cow<'_, [u8]>, Error>;
+
+    /// Returns the decoded `Value`.
+    fn to_value(&self) -> Result<Value<'_>, Error>;
+
+    /// Returns the original bytes if the input is `JSON` text,
+    /// used as the fallback of the text that can't be parsed.
+    fn text(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl JsonInput for RawJsonb<'_> {
+    fn to_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        Ok(Cow::Borrowed(self.0))
+    }
+
+    fn to_value(&self) -> Result<Value<'_>, Error> {
+        from_slice(self.0)
+    }
+}
+
+impl JsonInput for str {
+    fn to_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        let value = parse_value(self.as_bytes())?;
+        Ok(Cow::Owned(value.to_vec()))
+    }
+
+    fn to_value(&self) -> Result<Value<'_>, Error> {
+        parse_value(self.as_bytes())
+    }
+
+    fn text(&self) -> Option<&[u8]> {
+        Some(self.as_bytes())
+    }
+}
+
+impl JsonInput for String {
+    fn to_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        self.as_str().to_jsonb()
+    }
+
+    fn to_value(&self) -> Result<Value<'_>, Error> {
+        self.as_str().to_value()
+    }
+
+    fn text(&self) -> Option<&[u8]> {
+        Some(self.as_bytes())
+    }
+}
+
+impl JsonInput for Value<'_> {
+    fn to_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        Ok(Cow::Owned(self.to_vec()))
+    }
+
+    fn to_value(&self) -> Result<Value<'_>, Error> {
+        Ok(self.clone())
+    }
+}
+
+impl JsonInput for [u8] {
+    fn to_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        if is_jsonb(self) {
+            Ok(Cow::Borrowed(self))
+        } else {
+            let value = parse_value(self)?;
+            Ok(Cow::Owned(value.to_vec()))
+        }
+    }
+
+    fn to_value(&self) -> Result<Value<'_>, Error> {
+        if is_jsonb(self) {
+            from_slice(self)
+        } else {
+            parse_value(self)
+        }
+    }
+
+    fn text(&self) -> Option<&[u8]> {
+        if is_jsonb(self) {
+            None
+        } else {
+            Some(self)
+        }
+    }
+}
+
+impl JsonInput for Vec<u8> {
+    fn to_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        self.as_slice().to_jsonb()
+    }
+
+    fn to_value(&self) -> Result<Value<'_>, Error> {
+        self.as_slice().to_value()
+    }
+
+    fn text(&self) -> Option<&[u8]> {
+        self.as_slice().text()
+    }
+}
+
+impl<T: JsonInput + ?Sized> JsonInput for &T {
+    fn to_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        (**self).to_jsonb()
+    }
+
+    fn to_value(&self) -> Result<Value<'_>, Error> {
+        (**self).to_value()
+    }
+
+    fn text(&self) -> Option<&[u8]> {
+        (**self).text()
+    }
+}
+
+/// Values that can be encoded as `JSONB`, used as the items of [`build_array`](crate::build_array)
+/// and [`build_object`](crate::build_object).
+///
+/// Bytes in `[u8]` and `Vec<u8>` are treated as encoded `JSONB` and used as they are.
+/// Strings, numbers, booleans and the decoded [`Value`] are encoded as `JSONB` scalars or containers,
+/// `None` is encoded as `null`. Application types can be encoded through [`Serialized`].
+pub trait AsJsonb {
+    /// Returns the encoded `JSONB`.
+    fn as_jsonb(&self) -> Result<Cow<'_, [u8]>, Error>;
+}
+
+/// Wrapper to encode values that implement [`serde::Serialize`] as `JSONB`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Serialized<T>(pub T);
+
+impl<T: Serialize> AsJsonb for Serialized<T> {
+    fn as_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        let value = serde_json::to_value(&self.0).map_err(|_| Error::InvalidJsonType)?;
+        Ok(Cow::Owned(Value::from(value).to_vec()))
+    }
+}
+
+impl AsJsonb for RawJsonb<'_> {
+    fn as_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        Ok(Cow::Borrowed(self.0))
+    }
+}
+
+impl AsJsonb for [u8] {
+    fn as_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        Ok(Cow::Borrowed(self))
+    }
+}
+
+impl AsJsonb for Vec<u8> {
+    fn as_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        Ok(Cow::Borrowed(self))
+    }
+}
+
+impl AsJsonb for Value<'_> {
+    fn as_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        Ok(Cow::Owned(self.to_vec()))
+    }
+}
+
+impl AsJsonb for str {
+    fn as_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        Ok(Cow::Owned(Value::from(self).to_vec()))
+    }
+}
+
+impl<T: AsJsonb> AsJsonb for Option<T> {
+    fn as_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        match self {
+            Some(v) => v.as_jsonb(),
+            None => Ok(Cow::Owned(Value::Null.to_vec())),
+        }
+    }
+}
+
+impl<T: AsJsonb + ?Sized> AsJsonb for &T {
+    fn as_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+        (**self).as_jsonb()
+    }
+}
+
+macro_rules! as_jsonb_scalar {
+    ($($ty:ident)*) => {
+        $(
+            impl AsJsonb for $ty {
+                fn as_jsonb(&self) -> Result<Cow<'_, [u8]>, Error> {
+                    Ok(Cow::Owned(Value::from(self.clone()).to_vec()))
+                }
+            }
+        )*
+    };
+}
+
+as_jsonb_scalar! {
+    bool String i8 i16 i32 i64 isize u8 u16 u32 u64 usize f32 f64
+}
+
+// Check whether the value is `JSONB` format,
+// for compatibility with previous `JSON` string.
+pub(crate) fn is_jsonb(value: &[u8]) -> bool {
+    if let Some(v) = value.first() {
+        if matches!(*v, ARRAY_PREFIX | OBJECT_PREFIX | SCALAR_PREFIX) {
+            return true;
+        }
+    }
+    false
+}
@@ -0,0 +1,103 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::error::Error;
+use crate::input::JsonInput;
+
+/// A pool of shared `JSONB` values, the identical encoded values are stored once.
+///
+/// Useful when a few small documents, like enum-like objects, repeat across many rows.
+/// The values are kept by the pool until [`InternPool::purge`] removes the ones that
+/// are not used outside the pool.
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use jsonb::InternPool;
+///
+/// let mut pool = InternPool::new();
+/// let a = pool.intern(r#"{"status":"active"}"#).unwrap();
+/// let b = pool.intern(r#"{ "status": "active" }"#).unwrap();
+/// assert!(Arc::ptr_eq(&a, &b));
+/// assert_eq!(pool.len(), 1);
+/// assert_eq!(pool.ref_count(&a), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InternPool {
+    values: HashSet<Arc<[u8]>>,
+}
+
+impl InternPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the shared handle of the value, adding it to the pool if it's not there.
+    /// The `JSON` text is encoded first, the objects with the same keys and values are
+    /// identical since the keys are sorted.
+    pub fn intern<T: JsonInput + ?Sized>(&mut self, value: &T) -> Result<Arc<[u8]>, Error> {
+        let buf = value.to_jsonb()?;
+        Ok(self.intern_jsonb(buf.as_ref()))
+    }
+
+    /// Returns the shared handle of the encoded `JSONB` value, like a document or
+    /// a sub-value extracted by a JSON path, adding it to the pool if it's not there.
+    pub fn intern_jsonb(&mut self, value: &[u8]) -> Arc<[u8]> {
+        if let Some(shared) = self.values.get(value) {
+            return shared.clone();
+        }
+        let shared: Arc<[u8]> = Arc::from(value);
+        self.values.insert(shared.clone());
+        shared
+    }
+
+    /// Returns the shared handle of the value if it's in the pool.
+    pub fn get(&self, value: &[u8]) -> Option<Arc<[u8]>> {
+        self.values.get(value).cloned()
+    }
+
+    /// Returns the number of the handles of the value outside the pool,
+    /// `0` if the value is not in the pool or not used.
+    pub fn ref_count(&self, value: &[u8]) -> usize {
+        self.values
+            .get(value)
+            .map(|shared| Arc::strong_count(shared) - 1)
+            .unwrap_or(0)
+    }
+
+    /// Removes the values that are not used outside the pool,
+    /// returns the number of the removed values.
+    pub fn purge(&mut self) -> usize {
+        let len = self.values.len();
+        self.values.retain(|shared| Arc::strong_count(shared) > 1);
+        len - self.values.len()
+    }
+
+    /// Returns the number of the distinct values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the total size of the distinct values.
+    pub fn bytes(&self) -> usize {
+        self.values.iter().map(|shared| shared.len()).sum()
+    }
+}
@@ -69,6 +69,13 @@ impl JEntry {
         }
     }
 
+    pub(crate) fn make_extension_jentry(length: usize) -> JEntry {
+        JEntry {
+            type_code: EXTENSION_TAG,
+            length: length as u32,
+        }
+    }
+
     pub(crate) fn encoded(&self) -> u32 {
         self.type_code | self.length
     }
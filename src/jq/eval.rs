@@ -0,0 +1,697 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use crate::error::Error;
+use crate::functions::to_string;
+use crate::input::JsonInput;
+use crate::jq::filter::*;
+use crate::number::Number;
+use crate::value::Object;
+use crate::value::Value;
+
+// The builtin functions with their number of arguments.
+const BUILTINS: &[(&str, usize)] = &[
+    ("empty", 0),
+    ("not", 0),
+    ("length", 0),
+    ("keys", 0),
+    ("type", 0),
+    ("add", 0),
+    ("tostring", 0),
+    ("tonumber", 0),
+    ("sort", 0),
+    ("reverse", 0),
+    ("unique", 0),
+    ("min", 0),
+    ("max", 0),
+    ("to_entries", 0),
+    ("from_entries", 0),
+    ("first", 0),
+    ("last", 0),
+    ("any", 0),
+    ("all", 0),
+    ("select", 1),
+    ("map", 1),
+    ("has", 1),
+    ("sort_by", 1),
+    ("with_entries", 1),
+];
+
+/// Run the jq filter over the `JSONB` value, returns all the outputs as `JSONB` values.
+///
+/// Type errors, like indexing a number or iterating over a string, are returned as
+/// [`Error::InvalidJsonType`], unless they are suppressed by the `?` operator.
+pub fn run_jq<T: JsonInput + ?Sized>(
+    value: &T,
+    filter: &Filter<'_>,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let value = value.to_value()?;
+    let outputs = eval(filter, &value)?;
+    Ok(outputs.iter().map(Value::to_vec).collect())
+}
+
+// Check that all the called functions are builtin functions with the right number of arguments.
+pub(crate) fn check_calls(filter: &Filter<'_>) -> Result<(), Error> {
+    match filter {
+        Filter::Identity | Filter::RecurseAll | Filter::Literal(_) => Ok(()),
+        Filter::Index(target, index) => {
+            check_calls(target)?;
+            check_calls(index)
+        }
+        Filter::Slice(target, from, to) => {
+            check_calls(target)?;
+            for f in from.iter().chain(to.iter()) {
+                check_calls(f)?;
+            }
+            Ok(())
+        }
+        Filter::Iterate(inner) | Filter::Optional(inner) | Filter::Neg(inner) => check_calls(inner),
+        Filter::Array(inner) => inner.iter().try_for_each(|f| check_calls(f)),
+        Filter::Object(entries) => {
+            for (key, value) in entries.iter() {
+                if let ObjectKey::Filter(key) = key {
+                    check_calls(key)?;
+                }
+                check_calls(value)?;
+            }
+            Ok(())
+        }
+        Filter::Pipe(left, right)
+        | Filter::Comma(left, right)
+        | Filter::BinaryOp { left, right, .. } => {
+            check_calls(left)?;
+            check_calls(right)
+        }
+        Filter::IfThenElse(cond, then, otherwise) => {
+            check_calls(cond)?;
+            check_calls(then)?;
+            otherwise.iter().try_for_each(|f| check_calls(f))
+        }
+        Filter::Call(name, args) => {
+            if !BUILTINS.contains(&(name.as_ref(), args.len())) {
+                return Err(Error::InvalidJqFilter);
+            }
+            args.iter().try_for_each(check_calls)
+        }
+    }
+}
+
+fn eval<'a>(filter: &Filter<'_>, input: &Value<'a>) -> Result<Vec<Value<'a>>, Error> {
+    match filter {
+        Filter::Identity => Ok(vec![input.clone()]),
+        Filter::RecurseAll => {
+            let mut outputs = Vec::new();
+            recurse(input, &mut outputs);
+            Ok(outputs)
+        }
+        Filter::Literal(v) => Ok(vec![v.clone()]),
+        Filter::Index(target, index) => {
+            let indexes = eval(index, input)?;
+            let mut outputs = Vec::new();
+            for target in eval(target, input)? {
+                for index in indexes.iter() {
+                    outputs.push(index_value(&target, index)?);
+                }
+            }
+            Ok(outputs)
+        }
+        Filter::Slice(target, from, to) => {
+            let froms = match from {
+                Some(from) => eval(from, input)?,
+                None => vec![Value::Null],
+            };
+            let tos = match to {
+                Some(to) => eval(to, input)?,
+                None => vec![Value::Null],
+            };
+            let mut outputs = Vec::new();
+            for target in eval(target, input)? {
+                for to in tos.iter() {
+                    for from in froms.iter() {
+                        outputs.push(slice_value(&target, from, to)?);
+                    }
+                }
+            }
+            Ok(outputs)
+        }
+        Filter::Iterate(target) => {
+            let mut outputs = Vec::new();
+            for target in eval(target, input)? {
+                outputs.extend(iterate(target)?);
+            }
+            Ok(outputs)
+        }
+        Filter::Optional(inner) => Ok(eval(inner, input).unwrap_or_default()),
+        Filter::Array(inner) => {
+            let items = match inner {
+                Some(inner) => eval(inner, input)?,
+                None => Vec::new(),
+            };
+            Ok(vec![Value::Array(items)])
+        }
+        Filter::Object(entries) => {
+            let mut objects = vec![Object::new()];
+            for (key, value) in entries.iter() {
+                let keys = match key {
+                    ObjectKey::Name(name) => vec![name.to_string()],
+                    ObjectKey::Filter(key) => eval(key, input)?
+                        .into_iter()
+                        .map(|key| match key {
+                            Value::String(key) => Ok(key.into_owned()),
+                            _ => Err(Error::InvalidJsonType),
+                        })
+                        .collect::<Result<_, _>>()?,
+                };
+                let values = eval(value, input)?;
+                let mut next = Vec::with_capacity(objects.len() * keys.len() * values.len());
+                for obj in objects.iter() {
+                    for key in keys.iter() {
+                        for value in values.iter() {
+                            let mut obj = obj.clone();
+                            obj.insert(key.clone(), value.clone());
+                            next.push(obj);
+                        }
+                    }
+                }
+                objects = next;
+            }
+            Ok(objects.into_iter().map(Value::Object).collect())
+        }
+        Filter::Neg(inner) => eval(inner, input)?
+            .into_iter()
+            .map(|v| match v {
+                Value::Number(n) => Ok(number_value(-n.as_f64().unwrap_or_default())),
+                _ => Err(Error::InvalidJsonType),
+            })
+            .collect(),
+        Filter::Pipe(left, right) => {
+            let mut outputs = Vec::new();
+            for v in eval(left, input)? {
+                outputs.extend(eval(right, &v)?);
+            }
+            Ok(outputs)
+        }
+        Filter::Comma(left, right) => {
+            let mut outputs = eval(left, input)?;
+            outputs.extend(eval(right, input)?);
+            Ok(outputs)
+        }
+        Filter::BinaryOp { op, left, right } => eval_binary_op(*op, left, right, input),
+        Filter::IfThenElse(cond, then, otherwise) => {
+            let mut outputs = Vec::new();
+            for cond in eval(cond, input)? {
+                if is_truthy(&cond) {
+                    outputs.extend(eval(then, input)?);
+                } else if let Some(otherwise) = otherwise {
+                    outputs.extend(eval(otherwise, input)?);
+                } else {
+                    outputs.push(input.clone());
+                }
+            }
+            Ok(outputs)
+        }
+        Filter::Call(name, args) => eval_call(name, args, input),
+    }
+}
+
+fn eval_binary_op<'a>(
+    op: BinaryOperator,
+    left: &Filter<'_>,
+    right: &Filter<'_>,
+    input: &Value<'a>,
+) -> Result<Vec<Value<'a>>, Error> {
+    let mut outputs = Vec::new();
+    match op {
+        BinaryOperator::And | BinaryOperator::Or => {
+            for l in eval(left, input)? {
+                let l = is_truthy(&l);
+                if (op == BinaryOperator::And && !l) || (op == BinaryOperator::Or && l) {
+                    outputs.push(Value::Bool(l));
+                    continue;
+                }
+                for r in eval(right, input)? {
+                    outputs.push(Value::Bool(is_truthy(&r)));
+                }
+            }
+        }
+        BinaryOperator::Alternative => {
+            outputs = eval(left, input)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(is_truthy)
+                .collect();
+            if outputs.is_empty() {
+                outputs = eval(right, input)?;
+            }
+        }
+        _ => {
+            let lefts = eval(left, input)?;
+            for r in eval(right, input)? {
+                for l in lefts.iter() {
+                    outputs.push(apply_op(op, l.clone(), r.clone())?);
+                }
+            }
+        }
+    }
+    Ok(outputs)
+}
+
+fn eval_call<'a>(
+    name: &str,
+    args: &[Filter<'_>],
+    input: &Value<'a>,
+) -> Result<Vec<Value<'a>>, Error> {
+    let output = match (name, args) {
+        ("empty", []) => return Ok(Vec::new()),
+        ("not", []) => Value::Bool(!is_truthy(input)),
+        ("length", []) => match input {
+            Value::Null => Value::Number(Number::UInt64(0)),
+            Value::Number(n) => number_value(n.as_f64().unwrap_or_default().abs()),
+            Value::String(s) => Value::Number(Number::UInt64(s.chars().count() as u64)),
+            Value::Array(vals) => Value::Number(Number::UInt64(vals.len() as u64)),
+            Value::Object(obj) => Value::Number(Number::UInt64(obj.len() as u64)),
+            _ => return Err(Error::InvalidJsonType),
+        },
+        ("keys", []) => match input {
+            Value::Array(vals) => Value::Array(
+                (0..vals.len())
+                    .map(|i| Value::Number(Number::UInt64(i as u64)))
+                    .collect(),
+            ),
+            Value::Object(obj) => Value::Array(
+                obj.keys()
+                    .map(|key| Value::String(Cow::Owned(key.clone())))
+                    .collect(),
+            ),
+            _ => return Err(Error::InvalidJsonType),
+        },
+        ("type", []) => Value::String(Cow::Borrowed(type_name(input))),
+        ("add", []) => {
+            let mut acc = Value::Null;
+            for v in iterate(input.clone())? {
+                acc = apply_op(BinaryOperator::Add, acc, v)?;
+            }
+            acc
+        }
+        ("tostring", []) => match input {
+            Value::String(_) => input.clone(),
+            _ => Value::String(Cow::Owned(to_string(input))),
+        },
+        ("tonumber", []) => match input {
+            Value::Number(_) => input.clone(),
+            Value::String(s) => match s.parse::<u64>() {
+                Ok(v) => Value::Number(Number::UInt64(v)),
+                Err(_) => s
+                    .parse::<f64>()
+                    .map(number_value)
+                    .map_err(|_| Error::InvalidJsonType)?,
+            },
+            _ => return Err(Error::InvalidJsonType),
+        },
+        ("sort", []) => {
+            let mut vals = as_array(input)?.to_vec();
+            vals.sort_by(compare_values);
+            Value::Array(vals)
+        }
+        ("reverse", []) => match input {
+            Value::Null => Value::Array(Vec::new()),
+            Value::String(s) => Value::String(Cow::Owned(s.chars().rev().collect())),
+            Value::Array(vals) => Value::Array(vals.iter().rev().cloned().collect()),
+            _ => return Err(Error::InvalidJsonType),
+        },
+        ("unique", []) => {
+            let mut vals = as_array(input)?.to_vec();
+            vals.sort_by(compare_values);
+            vals.dedup_by(|a, b| compare_values(a, b) == Ordering::Equal);
+            Value::Array(vals)
+        }
+        ("min", []) => as_array(input)?
+            .iter()
+            .min_by(|a, b| compare_values(a, b))
+            .cloned()
+            .unwrap_or_default(),
+        ("max", []) => as_array(input)?
+            .iter()
+            .max_by(|a, b| compare_values(a, b))
+            .cloned()
+            .unwrap_or_default(),
+        ("to_entries", []) => to_entries(input)?,
+        ("from_entries", []) => from_entries(input)?,
+        ("first", []) => index_value(input, &Value::Number(Number::Int64(0)))?,
+        ("last", []) => index_value(input, &Value::Number(Number::Int64(-1)))?,
+        ("any", []) => Value::Bool(as_array(input)?.iter().any(is_truthy)),
+        ("all", []) => Value::Bool(as_array(input)?.iter().all(is_truthy)),
+        ("select", [cond]) => {
+            let mut outputs = Vec::new();
+            for c in eval(cond, input)? {
+                if is_truthy(&c) {
+                    outputs.push(input.clone());
+                }
+            }
+            return Ok(outputs);
+        }
+        ("map", [f]) => {
+            let mut vals = Vec::new();
+            for v in iterate(input.clone())? {
+                vals.extend(eval(f, &v)?);
+            }
+            Value::Array(vals)
+        }
+        ("has", [key]) => {
+            let mut outputs = Vec::new();
+            for key in eval(key, input)? {
+                let has = match (input, &key) {
+                    (Value::Object(obj), Value::String(key)) => obj.contains_key(key.as_ref()),
+                    (Value::Array(vals), Value::Number(n)) => {
+                        let idx = n.as_f64().unwrap_or_default();
+                        idx >= 0.0 && (idx as usize) < vals.len()
+                    }
+                    (_, _) => return Err(Error::InvalidJsonType),
+                };
+                outputs.push(Value::Bool(has));
+            }
+            return Ok(outputs);
+        }
+        ("sort_by", [f]) => {
+            let mut keyed = Vec::new();
+            for v in as_array(input)?.iter() {
+                keyed.push((Value::Array(eval(f, v)?), v.clone()));
+            }
+            keyed.sort_by(|(a, _), (b, _)| compare_values(a, b));
+            Value::Array(keyed.into_iter().map(|(_, v)| v).collect())
+        }
+        ("with_entries", [f]) => {
+            let mut entries = Vec::new();
+            for entry in iterate(to_entries(input)?)? {
+                entries.extend(eval(f, &entry)?);
+            }
+            from_entries(&Value::Array(entries))?
+        }
+        (_, _) => return Err(Error::InvalidJqFilter),
+    };
+    Ok(vec![output])
+}
+
+fn apply_op<'a>(op: BinaryOperator, left: Value<'a>, right: Value<'a>) -> Result<Value<'a>, Error> {
+    let value = match op {
+        BinaryOperator::Eq => Value::Bool(compare_values(&left, &right) == Ordering::Equal),
+        BinaryOperator::NotEq => Value::Bool(compare_values(&left, &right) != Ordering::Equal),
+        BinaryOperator::Lt => Value::Bool(compare_values(&left, &right) == Ordering::Less),
+        BinaryOperator::Lte => Value::Bool(compare_values(&left, &right) != Ordering::Greater),
+        BinaryOperator::Gt => Value::Bool(compare_values(&left, &right) == Ordering::Greater),
+        BinaryOperator::Gte => Value::Bool(compare_values(&left, &right) != Ordering::Less),
+        BinaryOperator::Add => match (left, right) {
+            (Value::Null, v) | (v, Value::Null) => v,
+            (Value::Number(l), Value::Number(r)) => arithmetic(op, &l, &r)?,
+            (Value::String(l), Value::String(r)) => Value::String(Cow::Owned(format!("{l}{r}"))),
+            (Value::Array(mut l), Value::Array(r)) => {
+                l.extend(r);
+                Value::Array(l)
+            }
+            (Value::Object(mut l), Value::Object(r)) => {
+                l.extend(r);
+                Value::Object(l)
+            }
+            (_, _) => return Err(Error::InvalidJsonType),
+        },
+        BinaryOperator::Sub => match (left, right) {
+            (Value::Number(l), Value::Number(r)) => arithmetic(op, &l, &r)?,
+            (Value::Array(l), Value::Array(r)) => Value::Array(
+                l.into_iter()
+                    .filter(|v| !r.iter().any(|r| compare_values(v, r) == Ordering::Equal))
+                    .collect(),
+            ),
+            (_, _) => return Err(Error::InvalidJsonType),
+        },
+        BinaryOperator::Mul => match (left, right) {
+            (Value::Number(l), Value::Number(r)) => arithmetic(op, &l, &r)?,
+            (Value::Object(l), Value::Object(r)) => Value::Object(deep_merge(l, r)),
+            (_, _) => return Err(Error::InvalidJsonType),
+        },
+        BinaryOperator::Div => match (left, right) {
+            (Value::Number(l), Value::Number(r)) => arithmetic(op, &l, &r)?,
+            (Value::String(l), Value::String(r)) => {
+                let parts = if l.is_empty() {
+                    Vec::new()
+                } else {
+                    l.split(r.as_ref())
+                        .map(|s| Value::String(Cow::Owned(s.to_string())))
+                        .collect()
+                };
+                Value::Array(parts)
+            }
+            (_, _) => return Err(Error::InvalidJsonType),
+        },
+        BinaryOperator::Mod => match (left, right) {
+            (Value::Number(l), Value::Number(r)) => arithmetic(op, &l, &r)?,
+            (_, _) => return Err(Error::InvalidJsonType),
+        },
+        BinaryOperator::And | BinaryOperator::Or | BinaryOperator::Alternative => unreachable!(),
+    };
+    Ok(value)
+}
+
+fn arithmetic<'a>(op: BinaryOperator, left: &Number, right: &Number) -> Result<Value<'a>, Error> {
+    // Integers are calculated exactly if the result doesn't overflow.
+    if let (Some(l), Some(r)) = (left.as_i64(), right.as_i64()) {
+        let res = match op {
+            BinaryOperator::Add => l.checked_add(r),
+            BinaryOperator::Sub => l.checked_sub(r),
+            BinaryOperator::Mul => l.checked_mul(r),
+            BinaryOperator::Mod if r == 0 => return Err(Error::InvalidJsonType),
+            BinaryOperator::Mod => l.checked_rem(r),
+            _ => None,
+        };
+        if let Some(res) = res {
+            return Ok(Value::Number(Number::Int64(res)));
+        }
+    }
+    let l = left.as_f64().unwrap_or_default();
+    let r = right.as_f64().unwrap_or_default();
+    let res = match op {
+        BinaryOperator::Add => l + r,
+        BinaryOperator::Sub => l - r,
+        BinaryOperator::Mul => l * r,
+        BinaryOperator::Div if r == 0.0 => return Err(Error::InvalidJsonType),
+        BinaryOperator::Div => l / r,
+        // Both operands are truncated to integers, like `5 % 2.5` is `1`.
+        BinaryOperator::Mod => match (l as i64, r as i64) {
+            (_, 0) => return Err(Error::InvalidJsonType),
+            (l, r) => l.wrapping_rem(r) as f64,
+        },
+        _ => unreachable!(),
+    };
+    Ok(number_value(res))
+}
+
+// Integral results are represented as integers, like `4 / 2` is `2` rather than `2.0`.
+// Like jq, infinities are clamped to the largest finite numbers and `NaN` is `null`.
+fn number_value<'a>(v: f64) -> Value<'a> {
+    if v.is_nan() {
+        Value::Null
+    } else if v.is_infinite() {
+        Value::Number(Number::Float64(v.clamp(f64::MIN, f64::MAX)))
+    } else if v.fract() == 0.0 && v >= i64::MIN as f64 && v < i64::MAX as f64 {
+        Value::Number(Number::Int64(v as i64))
+    } else {
+        Value::Number(Number::Float64(v))
+    }
+}
+
+fn deep_merge<'a>(mut left: Object<'a>, right: Object<'a>) -> Object<'a> {
+    for (key, r) in right {
+        let merged = match (left.remove(&key), r) {
+            (Some(Value::Object(l)), Value::Object(r)) => Value::Object(deep_merge(l, r)),
+            (_, r) => r,
+        };
+        left.insert(key, merged);
+    }
+    left
+}
+
+fn index_value<'a>(target: &Value<'a>, index: &Value<'_>) -> Result<Value<'a>, Error> {
+    match (target, index) {
+        (Value::Null, Value::String(_) | Value::Number(_)) => Ok(Value::Null),
+        (Value::Object(obj), Value::String(key)) => {
+            Ok(obj.get(key.as_ref()).cloned().unwrap_or_default())
+        }
+        (Value::Array(vals), Value::Number(n)) => {
+            let idx = n.as_f64().unwrap_or_default().floor();
+            let idx = if idx < 0.0 {
+                idx + vals.len() as f64
+            } else {
+                idx
+            };
+            if idx < 0.0 {
+                return Ok(Value::Null);
+            }
+            Ok(vals.get(idx as usize).cloned().unwrap_or_default())
+        }
+        (_, _) => Err(Error::InvalidJsonType),
+    }
+}
+
+fn slice_value<'a>(
+    target: &Value<'a>,
+    from: &Value<'_>,
+    to: &Value<'_>,
+) -> Result<Value<'a>, Error> {
+    let len = match target {
+        Value::Null => return Ok(Value::Null),
+        Value::String(s) => s.chars().count(),
+        Value::Array(vals) => vals.len(),
+        _ => return Err(Error::InvalidJsonType),
+    };
+    let resolve = |v: &Value<'_>, default: usize| -> Result<usize, Error> {
+        match v {
+            Value::Null => Ok(default),
+            Value::Number(n) => {
+                let idx = n.as_f64().unwrap_or_default().floor();
+                let idx = if idx < 0.0 { idx + len as f64 } else { idx };
+                Ok(idx.clamp(0.0, len as f64) as usize)
+            }
+            _ => Err(Error::InvalidJsonType),
+        }
+    };
+    let from = resolve(from, 0)?;
+    let to = resolve(to, len)?.max(from);
+    match target {
+        Value::String(s) => Ok(Value::String(Cow::Owned(
+            s.chars().skip(from).take(to - from).collect(),
+        ))),
+        Value::Array(vals) => Ok(Value::Array(vals[from..to].to_vec())),
+        _ => unreachable!(),
+    }
+}
+
+fn iterate(value: Value<'_>) -> Result<Vec<Value<'_>>, Error> {
+    match value {
+        Value::Array(vals) => Ok(vals),
+        Value::Object(obj) => Ok(obj.into_values().collect()),
+        _ => Err(Error::InvalidJsonType),
+    }
+}
+
+fn recurse<'a>(value: &Value<'a>, outputs: &mut Vec<Value<'a>>) {
+    outputs.push(value.clone());
+    match value {
+        Value::Array(vals) => vals.iter().for_each(|v| recurse(v, outputs)),
+        Value::Object(obj) => obj.values().for_each(|v| recurse(v, outputs)),
+        _ => {}
+    }
+}
+
+fn as_array<'a, 'b>(value: &'b Value<'a>) -> Result<&'b [Value<'a>], Error> {
+    match value {
+        Value::Array(vals) => Ok(vals),
+        _ => Err(Error::InvalidJsonType),
+    }
+}
+
+fn to_entries<'a>(value: &Value<'a>) -> Result<Value<'a>, Error> {
+    let Value::Object(obj) = value else {
+        return Err(Error::InvalidJsonType);
+    };
+    let entries = obj
+        .iter()
+        .map(|(key, value)| {
+            let mut entry = Object::new();
+            entry.insert("key".to_string(), Value::String(Cow::Owned(key.clone())));
+            entry.insert("value".to_string(), value.clone());
+            Value::Object(entry)
+        })
+        .collect();
+    Ok(Value::Array(entries))
+}
+
+// The entries can use `key`, `k` or `name` as the key and `value` or `v` as the value.
+fn from_entries<'a>(value: &Value<'a>) -> Result<Value<'a>, Error> {
+    let mut obj = Object::new();
+    for entry in as_array(value)?.iter() {
+        let Value::Object(entry) = entry else {
+            return Err(Error::InvalidJsonType);
+        };
+        let key = ["key", "k", "name"]
+            .iter()
+            .find_map(|name| entry.get(*name).filter(|v| !v.is_null()));
+        let key = match key {
+            Some(Value::String(key)) => key.to_string(),
+            Some(key @ (Value::Number(_) | Value::Bool(_))) => key.to_string(),
+            _ => return Err(Error::InvalidJsonType),
+        };
+        let value = entry
+            .get("value")
+            .or_else(|| entry.get("v"))
+            .cloned()
+            .unwrap_or_default();
+        obj.insert(key, value);
+    }
+    Ok(Value::Object(obj))
+}
+
+fn is_truthy(value: &Value<'_>) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+fn type_name(value: &Value<'_>) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+        Value::Extension(_) => "extension",
+    }
+}
+
+fn type_rank(value: &Value<'_>) -> u8 {
+    match value {
+        Value::Null => 0,
+        Value::Bool(false) => 1,
+        Value::Bool(true) => 2,
+        Value::Number(_) => 3,
+        Value::String(_) => 4,
+        Value::Array(_) => 5,
+        Value::Object(_) => 6,
+        Value::Extension(_) => 7,
+    }
+}
+
+// Values are ordered as jq does: `null < false < true < numbers < strings < arrays < objects`,
+// objects are compared by their sorted key sets first, then by the values of the keys.
+fn compare_values(left: &Value<'_>, right: &Value<'_>) -> Ordering {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => l.cmp(r),
+        (Value::String(l), Value::String(r)) => l.cmp(r),
+        (Value::Array(l), Value::Array(r)) => l
+            .iter()
+            .zip(r.iter())
+            .map(|(l, r)| compare_values(l, r))
+            .find(|ord| ord.is_ne())
+            .unwrap_or_else(|| l.len().cmp(&r.len())),
+        (Value::Object(l), Value::Object(r)) => l.keys().cmp(r.keys()).then_with(|| {
+            l.values()
+                .zip(r.values())
+                .map(|(l, r)| compare_values(l, r))
+                .find(|ord| ord.is_ne())
+                .unwrap_or(Ordering::Equal)
+        }),
+        (Value::Extension(_), Value::Extension(_)) => left.to_string().cmp(&right.to_string()),
+        (_, _) => type_rank(left).cmp(&type_rank(right)),
+    }
+}
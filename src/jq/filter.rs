@@ -0,0 +1,234 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use crate::util::quote_string;
+use crate::value::Value;
+
+/// Represents a jq filter, each filter takes an input value and produces zero or more output values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter<'a> {
+    /// `.` produces the input value.
+    Identity,
+    /// `..` produces the input value and all of its descendants.
+    RecurseAll,
+    /// Literal value, like `1`, `"a"`, `true` or `null`.
+    Literal(Value<'static>),
+    /// `t.name` or `t[i]` selects the object field or array element of the outputs of `t`.
+    /// The index is evaluated with the same input as `t`.
+    Index(Box<Filter<'a>>, Box<Filter<'a>>),
+    /// `t[from:to]` selects a slice of the array or string outputs of `t`.
+    Slice(
+        Box<Filter<'a>>,
+        Option<Box<Filter<'a>>>,
+        Option<Box<Filter<'a>>>,
+    ),
+    /// `t[]` produces all the elements of the array or object outputs of `t`.
+    Iterate(Box<Filter<'a>>),
+    /// `t?` suppresses the errors of `t`.
+    Optional(Box<Filter<'a>>),
+    /// `[f]` collects the outputs of `f` into an array.
+    Array(Option<Box<Filter<'a>>>),
+    /// `{k: v, ...}` constructs objects, produces one object for each combination of the outputs.
+    Object(Vec<(ObjectKey<'a>, Filter<'a>)>),
+    /// `-f` negates the number outputs of `f`.
+    Neg(Box<Filter<'a>>),
+    /// `l | r` feeds the outputs of `l` into `r`.
+    Pipe(Box<Filter<'a>>, Box<Filter<'a>>),
+    /// `l, r` produces the outputs of `l` followed by the outputs of `r`.
+    Comma(Box<Filter<'a>>, Box<Filter<'a>>),
+    /// Binary operation on the outputs of both sides.
+    BinaryOp {
+        op: BinaryOperator,
+        left: Box<Filter<'a>>,
+        right: Box<Filter<'a>>,
+    },
+    /// `if c then t else e end` produces the outputs of `t` for truthy outputs of `c`,
+    /// otherwise the outputs of `e`, or the input value if there is no `else` branch.
+    IfThenElse(Box<Filter<'a>>, Box<Filter<'a>>, Option<Box<Filter<'a>>>),
+    /// Call of a builtin function, like `length` or `select(.a > 1)`.
+    Call(Cow<'a, str>, Vec<Filter<'a>>),
+}
+
+/// Represents the key of an object construction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectKey<'a> {
+    /// `name` or `"name"`
+    Name(Cow<'a, str>),
+    /// `(f)` uses the string outputs of the filter as keys.
+    Filter(Filter<'a>),
+}
+
+/// Represents the binary operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    /// `+`
+    Add,
+    /// `-`
+    Sub,
+    /// `*`
+    Mul,
+    /// `/`
+    Div,
+    /// `%`
+    Mod,
+    /// `==`
+    Eq,
+    /// `!=`
+    NotEq,
+    /// `<`
+    Lt,
+    /// `<=`
+    Lte,
+    /// `>`
+    Gt,
+    /// `>=`
+    Gte,
+    /// `and`
+    And,
+    /// `or`
+    Or,
+    /// `//` produces the truthy outputs of the left side, or the outputs of the right side if there are none.
+    Alternative,
+}
+
+impl Display for BinaryOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Mul => "*",
+            BinaryOperator::Div => "/",
+            BinaryOperator::Mod => "%",
+            BinaryOperator::Eq => "==",
+            BinaryOperator::NotEq => "!=",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::Lte => "<=",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::Gte => ">=",
+            BinaryOperator::And => "and",
+            BinaryOperator::Or => "or",
+            BinaryOperator::Alternative => "//",
+        };
+        write!(f, "{op}")
+    }
+}
+
+impl<'a> Display for ObjectKey<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjectKey::Name(name) => write!(f, "{}", quote(name)),
+            ObjectKey::Filter(filter) => write!(f, "({filter})"),
+        }
+    }
+}
+
+// Filters are displayed with parentheses around the operations,
+// so that the display is unambiguous and can be parsed again.
+impl<'a> Display for Filter<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Filter::Identity => write!(f, "."),
+            Filter::RecurseAll => write!(f, ".."),
+            Filter::Literal(v) => write!(f, "{v}"),
+            Filter::Index(target, index) => match &**index {
+                Filter::Literal(Value::String(name)) => {
+                    write_target(target, "", f)?;
+                    write!(f, ".{}", quote(name))
+                }
+                index => {
+                    write_target(target, ".", f)?;
+                    write!(f, "[{index}]")
+                }
+            },
+            Filter::Slice(target, from, to) => {
+                write_target(target, ".", f)?;
+                write!(f, "[")?;
+                if let Some(from) = from {
+                    write!(f, "{from}")?;
+                }
+                write!(f, ":")?;
+                if let Some(to) = to {
+                    write!(f, "{to}")?;
+                }
+                write!(f, "]")
+            }
+            Filter::Iterate(target) => {
+                write_target(target, ".", f)?;
+                write!(f, "[]")
+            }
+            Filter::Optional(target) => {
+                write_target(target, ".", f)?;
+                write!(f, "?")
+            }
+            Filter::Array(inner) => match inner {
+                Some(inner) => write!(f, "[{inner}]"),
+                None => write!(f, "[]"),
+            },
+            Filter::Object(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{key}: ({value})")?;
+                }
+                write!(f, "}}")
+            }
+            Filter::Neg(inner) => write!(f, "-({inner})"),
+            Filter::Pipe(left, right) => write!(f, "({left} | {right})"),
+            Filter::Comma(left, right) => write!(f, "({left}, {right})"),
+            Filter::BinaryOp { op, left, right } => write!(f, "({left} {op} {right})"),
+            Filter::IfThenElse(cond, then, otherwise) => {
+                write!(f, "if {cond} then {then}")?;
+                if let Some(otherwise) = otherwise {
+                    write!(f, " else {otherwise}")?;
+                }
+                write!(f, " end")
+            }
+            Filter::Call(name, args) => {
+                write!(f, "{name}")?;
+                if !args.is_empty() {
+                    write!(f, "(")?;
+                    for (i, arg) in args.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, "; ")?;
+                        }
+                        write!(f, "{arg}")?;
+                    }
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// The identity target is written as `identity`, like `.a` instead of `..a`.
+fn write_target(target: &Filter<'_>, identity: &str, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match target {
+        Filter::Identity => write!(f, "{identity}"),
+        Filter::Literal(_) | Filter::Neg(_) => write!(f, "({target})"),
+        _ => write!(f, "{target}"),
+    }
+}
+
+fn quote(name: &str) -> String {
+    let mut buf = String::with_capacity(name.len() + 2);
+    quote_string(name, &mut buf);
+    buf
+}
@@ -0,0 +1,33 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A practical subset of the jq language to reshape `JSONB` values.
+//!
+//! Supported syntax:
+//!
+//! - `.`, `..`, `.name`, `."name"`, `.[i]`, `.[from:to]`, `.[]` and the optional operator `?`.
+//! - Literals, array construction `[f]` and object construction `{a, b: f, "c": f, (f): g}`.
+//! - Pipe `|`, comma `,`, alternative `//`, `and`, `or`, comparisons and arithmetic operators.
+//! - `if c then t elif c then t else e end`.
+//! - Builtin functions: `empty`, `not`, `length`, `keys`, `type`, `add`, `tostring`, `tonumber`,
+//!   `sort`, `reverse`, `unique`, `min`, `max`, `to_entries`, `from_entries`, `first`, `last`,
+//!   `any`, `all`, `select(f)`, `map(f)`, `has(k)`, `sort_by(f)` and `with_entries(f)`.
+
+mod eval;
+mod filter;
+mod parser;
+
+pub use eval::run_jq;
+pub use filter::*;
+pub use parser::parse_jq_filter;
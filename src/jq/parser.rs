@@ -0,0 +1,430 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while, take_while1},
+    character::complete::{char, multispace0},
+    combinator::{map, map_res, not, opt, peek, recognize, value, verify},
+    error::{Error as NomError, ErrorKind},
+    multi::{many0, separated_list0, separated_list1},
+    number::complete::recognize_float,
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
+    IResult,
+};
+
+use crate::error::Error;
+use crate::jq::eval::check_calls;
+use crate::jq::filter::*;
+use crate::number::Number;
+use crate::util::parse_string;
+use crate::value::Value;
+
+const KEYWORDS: &[&str] = &[
+    "and", "or", "if", "then", "elif", "else", "end", "true", "false", "null",
+];
+
+/// Parsing the input string to jq filter.
+///
+/// Calls of unknown functions, or with the wrong number of arguments, are rejected.
+pub fn parse_jq_filter(input: &[u8]) -> Result<Filter<'_>, Error> {
+    match delimited(multispace0, pipe, multispace0)(input) {
+        Ok((rest, filter)) => {
+            if !rest.is_empty() {
+                return Err(Error::InvalidJqFilter);
+            }
+            check_calls(&filter)?;
+            Ok(filter)
+        }
+        Err(nom::Err::Error(_err) | nom::Err::Failure(_err)) => Err(Error::InvalidJqFilter),
+        Err(nom::Err::Incomplete(_)) => unreachable!(),
+    }
+}
+
+fn ws<'a, O>(
+    inner: impl FnMut(&'a [u8]) -> IResult<&'a [u8], O>,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], O> {
+    delimited(multispace0, inner, multispace0)
+}
+
+fn ident(input: &[u8]) -> IResult<&[u8], &str> {
+    map_res(
+        recognize(pair(
+            take_while1(|c: u8| c.is_ascii_alphabetic() || c == b'_'),
+            take_while(|c: u8| c.is_ascii_alphanumeric() || c == b'_'),
+        )),
+        std::str::from_utf8,
+    )(input)
+}
+
+fn keyword<'a>(kw: &'static str) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    terminated(
+        tag(kw),
+        not(peek(take_while1(|c: u8| {
+            c.is_ascii_alphanumeric() || c == b'_'
+        }))),
+    )
+}
+
+fn function_name(input: &[u8]) -> IResult<&[u8], &str> {
+    verify(ident, |name: &str| !KEYWORDS.contains(&name))(input)
+}
+
+fn string(input: &[u8]) -> IResult<&[u8], Cow<'_, str>> {
+    if input.is_empty() || input[0] != b'"' {
+        return Err(nom::Err::Error(NomError::new(input, ErrorKind::Char)));
+    }
+    let mut i = 1;
+    let mut escapes = 0;
+    while i < input.len() {
+        match input[i] {
+            b'\\' => {
+                escapes += 1;
+                i += 2;
+            }
+            b'"' => {
+                let data = &input[1..i];
+                let s = if escapes == 0 {
+                    std::str::from_utf8(data)
+                        .map(Cow::Borrowed)
+                        .map_err(|_| nom::Err::Error(NomError::new(input, ErrorKind::Char)))?
+                } else {
+                    let mut idx = 1;
+                    parse_string(data, data.len() - escapes, &mut idx)
+                        .map(Cow::Owned)
+                        .map_err(|_| nom::Err::Error(NomError::new(input, ErrorKind::Char)))?
+                };
+                return Ok((&input[i + 1..], s));
+            }
+            _ => i += 1,
+        }
+    }
+    Err(nom::Err::Error(NomError::new(input, ErrorKind::Char)))
+}
+
+fn number(input: &[u8]) -> IResult<&[u8], Number> {
+    map_res(
+        preceded(
+            peek(take_while1(|c: u8| c.is_ascii_digit())),
+            recognize_float,
+        ),
+        |s: &[u8]| {
+            let s = std::str::from_utf8(s).map_err(|_| Error::InvalidJqFilter)?;
+            match s.parse::<u64>() {
+                Ok(v) => Ok(Number::UInt64(v)),
+                Err(_) => s
+                    .parse::<f64>()
+                    .map(Number::Float64)
+                    .map_err(|_| Error::InvalidJqFilter),
+            }
+        },
+    )(input)
+}
+
+fn literal(input: &[u8]) -> IResult<&[u8], Value<'static>> {
+    alt((
+        value(Value::Null, keyword("null")),
+        value(Value::Bool(true), keyword("true")),
+        value(Value::Bool(false), keyword("false")),
+        map(number, Value::Number),
+        map(string, |s| Value::String(Cow::Owned(s.into_owned()))),
+    ))(input)
+}
+
+fn field_name(input: &[u8]) -> IResult<&[u8], Cow<'_, str>> {
+    alt((string, map(ident, Cow::Borrowed)))(input)
+}
+
+fn name_index(name: Cow<'_, str>) -> Box<Filter<'static>> {
+    Box::new(Filter::Literal(Value::String(Cow::Owned(
+        name.into_owned(),
+    ))))
+}
+
+// `.`, `..`, `.name` and `."name"`
+fn dot(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    alt((
+        value(Filter::RecurseAll, tag("..")),
+        map(preceded(char('.'), field_name), |name| {
+            Filter::Index(Box::new(Filter::Identity), name_index(name))
+        }),
+        value(Filter::Identity, char('.')),
+    ))(input)
+}
+
+fn array(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    map(delimited(char('['), ws(opt(pipe)), char(']')), |inner| {
+        Filter::Array(inner.map(Box::new))
+    })(input)
+}
+
+fn object_entry(input: &[u8]) -> IResult<&[u8], (ObjectKey<'_>, Filter<'_>)> {
+    alt((
+        map(
+            separated_pair(
+                ws(alt((
+                    map(field_name, ObjectKey::Name),
+                    map(delimited(char('('), ws(pipe), char(')')), ObjectKey::Filter),
+                ))),
+                char(':'),
+                ws(alternative),
+            ),
+            |(key, value)| (key, value),
+        ),
+        // `{name}` is the shorthand of `{name: .name}`
+        map(ws(field_name), |name| {
+            let value = Filter::Index(Box::new(Filter::Identity), name_index(name.clone()));
+            (ObjectKey::Name(name), value)
+        }),
+    ))(input)
+}
+
+fn object(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    map(
+        delimited(
+            char('{'),
+            ws(separated_list0(char(','), object_entry)),
+            char('}'),
+        ),
+        Filter::Object,
+    )(input)
+}
+
+fn if_then_else(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    map(
+        tuple((
+            preceded(keyword("if"), ws(pipe)),
+            preceded(keyword("then"), ws(pipe)),
+            many0(pair(
+                preceded(keyword("elif"), ws(pipe)),
+                preceded(keyword("then"), ws(pipe)),
+            )),
+            opt(preceded(keyword("else"), ws(pipe))),
+            keyword("end"),
+        )),
+        |(cond, then, elifs, otherwise, _)| {
+            // `elif` branches are nested into the `else` branch.
+            let otherwise =
+                elifs
+                    .into_iter()
+                    .rev()
+                    .fold(otherwise.map(Box::new), |otherwise, (cond, then)| {
+                        Some(Box::new(Filter::IfThenElse(
+                            Box::new(cond),
+                            Box::new(then),
+                            otherwise,
+                        )))
+                    });
+            Filter::IfThenElse(Box::new(cond), Box::new(then), otherwise)
+        },
+    )(input)
+}
+
+fn call(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    map(
+        pair(
+            function_name,
+            opt(delimited(
+                char('('),
+                separated_list1(char(';'), ws(pipe)),
+                char(')'),
+            )),
+        ),
+        |(name, args)| Filter::Call(Cow::Borrowed(name), args.unwrap_or_default()),
+    )(input)
+}
+
+fn primary(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    alt((
+        map(literal, Filter::Literal),
+        dot,
+        delimited(char('('), ws(pipe), char(')')),
+        array,
+        object,
+        if_then_else,
+        call,
+    ))(input)
+}
+
+#[derive(Clone)]
+enum Suffix<'a> {
+    Index(Filter<'a>),
+    Slice(Option<Filter<'a>>, Option<Filter<'a>>),
+    Iterate,
+    Optional,
+}
+
+fn suffix(input: &[u8]) -> IResult<&[u8], Suffix<'_>> {
+    let bracket = alt((
+        value(Suffix::Iterate, pair(char('['), ws(char(']')))),
+        map(
+            delimited(
+                char('['),
+                separated_pair(ws(opt(pipe)), char(':'), ws(opt(pipe))),
+                char(']'),
+            ),
+            |(from, to)| Suffix::Slice(from, to),
+        ),
+        map(delimited(char('['), ws(pipe), char(']')), Suffix::Index),
+    ));
+    preceded(
+        multispace0,
+        alt((
+            map(preceded(char('.'), field_name), |name| {
+                Suffix::Index(*name_index(name))
+            }),
+            preceded(opt(char('.')), bracket),
+            value(Suffix::Optional, char('?')),
+        )),
+    )(input)
+}
+
+fn term(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    map(pair(primary, many0(suffix)), |(primary, suffixes)| {
+        suffixes
+            .into_iter()
+            .fold(primary, |target, suffix| match suffix {
+                Suffix::Index(index) => Filter::Index(Box::new(target), Box::new(index)),
+                Suffix::Slice(from, to) => {
+                    Filter::Slice(Box::new(target), from.map(Box::new), to.map(Box::new))
+                }
+                Suffix::Iterate => Filter::Iterate(Box::new(target)),
+                Suffix::Optional => Filter::Optional(Box::new(target)),
+            })
+    })(input)
+}
+
+fn unary(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    alt((
+        map(preceded(char('-'), ws(unary)), |inner| {
+            Filter::Neg(Box::new(inner))
+        }),
+        term,
+    ))(input)
+}
+
+fn fold_left<'a>(first: Filter<'a>, rest: Vec<(BinaryOperator, Filter<'a>)>) -> Filter<'a> {
+    rest.into_iter()
+        .fold(first, |left, (op, right)| Filter::BinaryOp {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        })
+}
+
+fn multiplicative(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    let op = alt((
+        value(BinaryOperator::Mul, char('*')),
+        // `//` is the alternative operator.
+        value(BinaryOperator::Div, terminated(char('/'), not(char('/')))),
+        value(BinaryOperator::Mod, char('%')),
+    ));
+    map(
+        pair(ws(unary), many0(pair(op, ws(unary)))),
+        |(first, rest)| fold_left(first, rest),
+    )(input)
+}
+
+fn additive(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    let op = alt((
+        value(BinaryOperator::Add, char('+')),
+        value(BinaryOperator::Sub, char('-')),
+    ));
+    map(
+        pair(multiplicative, many0(pair(op, multiplicative))),
+        |(first, rest)| fold_left(first, rest),
+    )(input)
+}
+
+fn comparison(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    let op = alt((
+        value(BinaryOperator::Eq, tag("==")),
+        value(BinaryOperator::NotEq, tag("!=")),
+        value(BinaryOperator::Lte, tag("<=")),
+        value(BinaryOperator::Lt, char('<')),
+        value(BinaryOperator::Gte, tag(">=")),
+        value(BinaryOperator::Gt, char('>')),
+    ));
+    // comparison operators are non-associative.
+    map(
+        pair(additive, opt(pair(op, additive))),
+        |(left, right)| match right {
+            Some((op, right)) => Filter::BinaryOp {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+            None => left,
+        },
+    )(input)
+}
+
+fn and(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    map(
+        pair(
+            comparison,
+            many0(pair(value(BinaryOperator::And, keyword("and")), comparison)),
+        ),
+        |(first, rest)| fold_left(first, rest),
+    )(input)
+}
+
+fn or(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    map(
+        pair(
+            and,
+            many0(pair(value(BinaryOperator::Or, keyword("or")), and)),
+        ),
+        |(first, rest)| fold_left(first, rest),
+    )(input)
+}
+
+// `//` is right-associative.
+fn alternative(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    map(
+        pair(or, opt(preceded(tag("//"), alternative))),
+        |(left, right)| match right {
+            Some(right) => Filter::BinaryOp {
+                op: BinaryOperator::Alternative,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+            None => left,
+        },
+    )(input)
+}
+
+fn comma(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    map(
+        pair(alternative, many0(preceded(char(','), alternative))),
+        |(first, rest)| {
+            rest.into_iter().fold(first, |left, right| {
+                Filter::Comma(Box::new(left), Box::new(right))
+            })
+        },
+    )(input)
+}
+
+// `|` is right-associative and has the lowest precedence.
+fn pipe(input: &[u8]) -> IResult<&[u8], Filter<'_>> {
+    map(
+        pair(comma, opt(preceded(char('|'), pipe))),
+        |(left, right)| match right {
+            Some(right) => Filter::Pipe(Box::new(left), Box::new(right)),
+            None => left,
+        },
+    )(input)
+}
@@ -0,0 +1,114 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::error::Error;
+use crate::jsonpath::parse_json_path;
+use crate::jsonpath::Selector;
+
+/// A thread-safe cache of the compiled [`Selector`]s keyed by the path text.
+///
+/// The cache holds at most `capacity` selectors, the least recently used one is evicted
+/// when a new path is inserted into a full cache. Invalid paths are not cached.
+///
+/// ```
+/// use jsonb::jsonpath::PathCache;
+///
+/// let cache = PathCache::new(16);
+/// let value = jsonb::parse_value(br#"{"a":[1,2]}"#).unwrap().to_vec();
+/// let selector = cache.get("$.a[0]").unwrap();
+/// assert_eq!(selector.select(&value).len(), 1);
+/// ```
+pub struct PathCache {
+    capacity: usize,
+    inner: Mutex<CacheInner>,
+}
+
+struct CacheInner {
+    // The selectors with the tick of the last access.
+    entries: HashMap<String, (Arc<Selector<'static>>, u64)>,
+    tick: u64,
+}
+
+impl PathCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(CacheInner {
+                entries: HashMap::new(),
+                tick: 0,
+            }),
+        }
+    }
+
+    /// Returns the selector of the path, the path is parsed only if it is not in the cache.
+    pub fn get(&self, path: &str) -> Result<Arc<Selector<'static>>, Error> {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.tick += 1;
+            let tick = inner.tick;
+            if let Some((selector, last_used)) = inner.entries.get_mut(path) {
+                *last_used = tick;
+                return Ok(selector.clone());
+            }
+        }
+
+        // Parse without holding the lock, concurrent misses of the same path may parse it twice.
+        let json_path = parse_json_path(path.as_bytes())?.into_owned();
+        let selector = Arc::new(Selector::new(json_path));
+        if self.capacity == 0 {
+            return Ok(selector);
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.entries.contains_key(path) && inner.entries.len() >= self.capacity {
+            let lru = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(lru) = lru {
+                inner.entries.remove(&lru);
+            }
+        }
+        inner.tick += 1;
+        let tick = inner.tick;
+        inner
+            .entries
+            .insert(path.to_string(), (selector.clone(), tick));
+        Ok(selector)
+    }
+
+    /// Returns the maximum number of cached selectors.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of cached selectors.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all the cached selectors.
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().entries.clear();
+    }
+}
@@ -12,10 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod cache;
 mod parser;
 mod path;
 mod selector;
+pub mod visitor;
 
+pub use cache::PathCache;
 pub use parser::parse_json_path;
+pub(crate) use parser::parse_json_path_predicate;
 pub use path::*;
 pub use selector::*;
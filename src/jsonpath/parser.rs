@@ -15,8 +15,8 @@
 use nom::{
     branch::alt,
     bytes::complete::{tag, tag_no_case},
-    character::complete::{char, i32, i64, multispace0, u64},
-    combinator::{map, opt, value},
+    character::complete::{char, i32, i64, multispace0, multispace1, one_of, u64},
+    combinator::{map, map_opt, not, opt, peek, value},
     error::{Error as NomError, ErrorKind},
     multi::{many0, separated_list1},
     number::complete::double,
@@ -27,6 +27,8 @@ use nom::{
 use crate::constants::UNICODE_LEN;
 use crate::error::Error;
 use crate::jsonpath::path::*;
+#[cfg(feature = "regex")]
+use crate::jsonpath::selector::build_regex;
 use crate::number::Number;
 use crate::util::parse_string;
 use std::borrow::Cow;
@@ -45,6 +47,18 @@ pub fn parse_json_path(input: &[u8]) -> Result<JsonPath<'_>, Error> {
     }
 }
 
+/// Parsing the input string to a predicate check expression, like `$.a[*] > 2`,
+/// the expression is returned as the filter of the root, `$ ? (<predicate>)`,
+/// which selects the root if the predicate is true.
+pub(crate) fn parse_json_path_predicate(input: &[u8]) -> Result<JsonPath<'_>, Error> {
+    match delimited(multispace0, expr_or, multispace0)(input) {
+        Ok((b"", expr)) => Ok(JsonPath {
+            paths: vec![Path::Root, Path::FilterExpr(Box::new(expr))],
+        }),
+        _ => Err(Error::InvalidJsonPath),
+    }
+}
+
 fn json_path(input: &[u8]) -> IResult<&[u8], JsonPath<'_>> {
     map(delimited(multispace0, paths, multispace0), |paths| {
         JsonPath { paths }
@@ -112,7 +126,15 @@ fn raw_string(input: &[u8]) -> IResult<&[u8], Cow<'_, str>> {
 }
 
 fn string(input: &[u8]) -> IResult<&[u8], Cow<'_, str>> {
-    if input.is_empty() || input[0] != b'"' {
+    quoted_string(input, b'"')
+}
+
+fn single_quoted_string(input: &[u8]) -> IResult<&[u8], Cow<'_, str>> {
+    quoted_string(input, b'\'')
+}
+
+fn quoted_string(input: &[u8], quote: u8) -> IResult<&[u8], Cow<'_, str>> {
+    if input.is_empty() || input[0] != quote {
         return Err(nom::Err::Error(NomError::new(input, ErrorKind::Char)));
     }
     let mut i = 1;
@@ -126,7 +148,7 @@ fn string(input: &[u8]) -> IResult<&[u8], Cow<'_, str>> {
                     return Err(nom::Err::Error(NomError::new(input, ErrorKind::Char)));
                 }
             }
-            b'"' => {
+            c if c == quote => {
                 break;
             }
             _ => {
@@ -134,16 +156,27 @@ fn string(input: &[u8]) -> IResult<&[u8], Cow<'_, str>> {
             }
         }
     }
-    if i > 1 {
+    if i > 1 && i < input.len() {
         if escapes == 0 {
             if let Ok(s) = std::str::from_utf8(&input[1..i]) {
                 return Ok((&input[i + 1..], Cow::Borrowed(s)));
             }
         } else {
-            let data = &input[1..i];
+            let mut data = Cow::Borrowed(&input[1..i]);
+            // The escaped single quote is not a JSON escape, every single quote
+            // in the string is escaped, otherwise it ends the string.
+            if quote == b'\'' {
+                let mut unescaped = Vec::with_capacity(data.len());
+                for (j, c) in data.iter().enumerate() {
+                    if !(*c == b'\\' && data.get(j + 1) == Some(&b'\'')) {
+                        unescaped.push(*c);
+                    }
+                }
+                data = Cow::Owned(unescaped);
+            }
             let len = i - 1 - escapes;
             let mut idx = 1;
-            let s = parse_string(data, len, &mut idx)
+            let s = parse_string(&data, len, &mut idx)
                 .map_err(|_| nom::Err::Error(NomError::new(input, ErrorKind::Char)))?;
             return Ok((&input[i + 1..], Cow::Owned(s)));
         }
@@ -170,37 +203,113 @@ fn dot_field(input: &[u8]) -> IResult<&[u8], Cow<'_, str>> {
     alt((preceded(char('.'), string), preceded(char('.'), raw_string)))(input)
 }
 
-fn object_field(input: &[u8]) -> IResult<&[u8], Cow<'_, str>> {
+fn item_method(input: &[u8]) -> IResult<&[u8], ItemMethod> {
+    delimited(
+        char('.'),
+        alt((
+            value(ItemMethod::Type, tag("type")),
+            value(ItemMethod::Size, tag("size")),
+            value(ItemMethod::KeyValue, tag("keyvalue")),
+            value(ItemMethod::Double, tag("double")),
+            value(ItemMethod::Number, tag("number")),
+            value(ItemMethod::Abs, tag("abs")),
+            value(ItemMethod::Floor, tag("floor")),
+            value(ItemMethod::Ceiling, tag("ceiling")),
+        )),
+        tuple((multispace0, char('('), multispace0, char(')'))),
+    )(input)
+}
+
+fn object_fields(input: &[u8]) -> IResult<&[u8], Vec<Cow<'_, str>>> {
     delimited(
         terminated(char('['), multispace0),
-        string,
+        separated_list1(
+            delimited(multispace0, char(','), multispace0),
+            alt((string, single_quoted_string)),
+        ),
         preceded(multispace0, char(']')),
     )(input)
 }
 
-fn index(input: &[u8]) -> IResult<&[u8], Index> {
-    alt((
-        map(i32, Index::Index),
-        map(
-            preceded(
-                tuple((tag_no_case("last"), multispace0, char('-'), multispace0)),
+// The product of integer literals in an index, the division is truncated toward zero.
+fn index_product(input: &[u8]) -> IResult<&[u8], i64> {
+    map_opt(
+        pair(
+            i32,
+            many0(pair(
+                delimited(multispace0, multiplicative_op, multispace0),
                 i32,
-            ),
-            |v| Index::LastIndex(v.saturating_neg()),
+            )),
         ),
-        map(
-            preceded(
-                tuple((tag_no_case("last"), multispace0, char('+'), multispace0)),
-                i32,
-            ),
-            Index::LastIndex,
+        |(first, rest)| {
+            let mut product = first as i64;
+            for (op, v) in rest {
+                let v = v as i64;
+                product = match op {
+                    BinaryOperator::Mul => product.checked_mul(v)?,
+                    BinaryOperator::Div => product.checked_div(v)?,
+                    _ => product.checked_rem(v)?,
+                };
+            }
+            Some(product)
+        },
+    )(input)
+}
+
+// An index is the sum of the products of integer literals and at most one `last`,
+// like `last - 1` or `2 * 3 + 1`, the arithmetic is evaluated when the path is parsed.
+fn index(input: &[u8]) -> IResult<&[u8], Index> {
+    let term = |input| alt((value(None, tag_no_case("last")), map(index_product, Some)))(input);
+    map_opt(
+        pair(
+            term,
+            many0(pair(
+                delimited(multispace0, alt((char('+'), char('-'))), multispace0),
+                term,
+            )),
         ),
-        map(tag_no_case("last"), |_| Index::LastIndex(0)),
-    ))(input)
+        |(first, rest)| {
+            let mut lasts = 0;
+            let mut sum = 0_i64;
+            for (op, term) in std::iter::once(('+', first)).chain(rest) {
+                let sign = if op == '+' { 1 } else { -1 };
+                match term {
+                    Some(v) => sum = sum.checked_add(sign * v)?,
+                    None => lasts += sign,
+                }
+            }
+            let sum = i32::try_from(sum).ok()?;
+            match lasts {
+                0 => Some(Index::Index(sum)),
+                1 => Some(Index::LastIndex(sum)),
+                _ => None,
+            }
+        },
+    )(input)
+}
+
+fn step_slice(input: &[u8]) -> IResult<&[u8], ArrayIndex> {
+    map(
+        tuple((
+            opt(index),
+            delimited(multispace0, char(':'), multispace0),
+            opt(index),
+            opt(preceded(
+                delimited(multispace0, char(':'), multispace0),
+                opt(i32),
+            )),
+        )),
+        |(start, _, end, step)| ArrayIndex::StepSlice {
+            start,
+            end,
+            step: step.flatten().unwrap_or(1),
+        },
+    )(input)
 }
 
 fn array_index(input: &[u8]) -> IResult<&[u8], ArrayIndex> {
     alt((
+        step_slice,
         map(
             separated_pair(
                 index,
@@ -221,14 +330,35 @@ fn array_indices(input: &[u8]) -> IResult<&[u8], Vec<ArrayIndex>> {
     )(input)
 }
 
+// `..` followed by a member accessor or `.*` only consumes the first `.`,
+// the rest is parsed as the following path.
+fn recursive_descent(input: &[u8]) -> IResult<&[u8], ()> {
+    alt((
+        value((), terminated(tag(".."), peek(char('[')))),
+        value(
+            (),
+            terminated(char('.'), peek(pair(char('.'), not(char('.'))))),
+        ),
+    ))(input)
+}
+
 fn inner_path(input: &[u8]) -> IResult<&[u8], Path<'_>> {
     alt((
+        value(Path::RecursiveWildcard, tag("..*")),
+        value(Path::RecursiveDescent, recursive_descent),
         value(Path::DotWildcard, tag(".*")),
         value(Path::BracketWildcard, bracket_wildcard),
         map(colon_field, Path::ColonField),
+        map(item_method, Path::Method),
         map(dot_field, Path::DotField),
         map(array_indices, Path::ArrayIndices),
-        map(object_field, Path::ObjectField),
+        map(object_fields, |mut names| {
+            if names.len() == 1 {
+                Path::ObjectField(names.pop().unwrap())
+            } else {
+                Path::ObjectFields(names)
+            }
+        }),
     ))(input)
 }
 
@@ -267,6 +397,7 @@ fn expr_paths(input: &[u8]) -> IResult<&[u8], Vec<Path<'_>>> {
     map(
         pair(
             alt((
+                map(preceded(char('$'), raw_string), Path::Variable),
                 value(Path::Root, char('$')),
                 value(Path::Current, char('@')),
             )),
@@ -307,8 +438,13 @@ fn path_value(input: &[u8]) -> IResult<&[u8], PathValue<'_>> {
         value(PathValue::Null, tag("null")),
         value(PathValue::Boolean(true), tag("true")),
         value(PathValue::Boolean(false), tag("false")),
-        map(u64, |v| PathValue::Number(Number::UInt64(v))),
-        map(i64, |v| PathValue::Number(Number::Int64(v))),
+        // The integers are not followed by a fraction or an exponent, like `2.5` and `1e3`.
+        map(terminated(u64, not(one_of(".eE"))), |v| {
+            PathValue::Number(Number::UInt64(v))
+        }),
+        map(terminated(i64, not(one_of(".eE"))), |v| {
+            PathValue::Number(Number::Int64(v))
+        }),
         map(double, |v| PathValue::Number(Number::Float64(v))),
         map(string, PathValue::String),
     ))(input)
@@ -321,14 +457,146 @@ fn inner_expr(input: &[u8]) -> IResult<&[u8], Expr<'_>> {
     ))(input)
 }
 
+fn filter_func(input: &[u8]) -> IResult<&[u8], FilterFunc<'_>> {
+    alt((exists_func, like_regex_func, starts_with_func))(input)
+}
+
+fn exists_func(input: &[u8]) -> IResult<&[u8], FilterFunc<'_>> {
+    map(
+        preceded(
+            terminated(tag("exists"), multispace0),
+            delimited(
+                terminated(char('('), multispace0),
+                expr_paths,
+                preceded(multispace0, char(')')),
+            ),
+        ),
+        FilterFunc::Exists,
+    )(input)
+}
+
+fn starts_with_func(input: &[u8]) -> IResult<&[u8], FilterFunc<'_>> {
+    map(
+        pair(
+            expr_paths,
+            preceded(
+                delimited(
+                    multispace0,
+                    tuple((tag("starts"), multispace1, tag("with"))),
+                    multispace0,
+                ),
+                string,
+            ),
+        ),
+        |(paths, prefix)| FilterFunc::StartsWith { paths, prefix },
+    )(input)
+}
+
+// The pattern and the flags are checked when the path is parsed.
+#[cfg(feature = "regex")]
+fn like_regex_func(input: &[u8]) -> IResult<&[u8], FilterFunc<'_>> {
+    map_opt(
+        tuple((
+            expr_paths,
+            preceded(
+                delimited(multispace0, tag("like_regex"), multispace0),
+                string,
+            ),
+            opt(preceded(
+                delimited(multispace0, tag("flag"), multispace0),
+                string,
+            )),
+        )),
+        |(paths, pattern, flags)| {
+            build_regex(&pattern, flags.as_deref().unwrap_or_default())?;
+            Some(FilterFunc::LikeRegex {
+                paths,
+                pattern,
+                flags,
+            })
+        },
+    )(input)
+}
+
+#[cfg(not(feature = "regex"))]
+fn like_regex_func(input: &[u8]) -> IResult<&[u8], FilterFunc<'_>> {
+    Err(nom::Err::Error(NomError::new(input, ErrorKind::Tag)))
+}
+
+fn additive_op(input: &[u8]) -> IResult<&[u8], BinaryOperator> {
+    alt((
+        value(BinaryOperator::Add, char('+')),
+        value(BinaryOperator::Sub, char('-')),
+    ))(input)
+}
+
+fn multiplicative_op(input: &[u8]) -> IResult<&[u8], BinaryOperator> {
+    alt((
+        value(BinaryOperator::Mul, char('*')),
+        value(BinaryOperator::Div, char('/')),
+        value(BinaryOperator::Mod, char('%')),
+    ))(input)
+}
+
+// Folds the operands separated by the operators of the same precedence, left-associative.
+fn fold_binary_ops<'a>(first: Expr<'a>, rest: Vec<(BinaryOperator, Expr<'a>)>) -> Expr<'a> {
+    rest.into_iter()
+        .fold(first, |left, (op, right)| Expr::BinaryOp {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        })
+}
+
+fn arithmetic_atom(input: &[u8]) -> IResult<&[u8], Expr<'_>> {
+    alt((
+        inner_expr,
+        delimited(
+            terminated(char('('), multispace0),
+            expr_additive,
+            preceded(multispace0, char(')')),
+        ),
+    ))(input)
+}
+
+fn expr_multiplicative(input: &[u8]) -> IResult<&[u8], Expr<'_>> {
+    map(
+        pair(
+            arithmetic_atom,
+            many0(pair(
+                delimited(multispace0, multiplicative_op, multispace0),
+                arithmetic_atom,
+            )),
+        ),
+        |(first, rest)| fold_binary_ops(first, rest),
+    )(input)
+}
+
+// The operands of the comparisons, `*`, `/` and `%` bind more tightly than `+` and `-`.
+fn expr_additive(input: &[u8]) -> IResult<&[u8], Expr<'_>> {
+    map(
+        pair(
+            expr_multiplicative,
+            many0(pair(
+                delimited(multispace0, additive_op, multispace0),
+                expr_multiplicative,
+            )),
+        ),
+        |(first, rest)| fold_binary_ops(first, rest),
+    )(input)
+}
+
 fn expr_atom(input: &[u8]) -> IResult<&[u8], Expr<'_>> {
-    // TODO, support arithmetic expressions.
     alt((
+        map(
+            delimited(multispace0, filter_func, multispace0),
+            Expr::FilterFunc,
+        ),
         map(
             tuple((
-                delimited(multispace0, inner_expr, multispace0),
+                delimited(multispace0, expr_additive, multispace0),
                 op,
-                delimited(multispace0, inner_expr, multispace0),
+                delimited(multispace0, expr_additive, multispace0),
             )),
             |(left, op, right)| Expr::BinaryOp {
                 op,
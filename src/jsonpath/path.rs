@@ -18,6 +18,7 @@ use std::fmt::Display;
 use std::fmt::Formatter;
 
 use crate::number::Number;
+use crate::util::quote_string;
 
 /// Represents a set of JSON Path chains.
 #[derive(Debug, Clone, PartialEq)]
@@ -32,6 +33,9 @@ pub enum Path<'a> {
     Root,
     /// `@` represents the current node or element being processed in the filter expression.
     Current,
+    /// `$<name>` represents the value of the named variable passed to the evaluation,
+    /// like `$min_qty`, it can only start the paths in the filter expression.
+    Variable(Cow<'a, str>),
     /// `.*` represents selecting all elements in an Object.
     DotWildcard,
     /// `[*]` represents selecting all elements in an Array.
@@ -42,28 +46,73 @@ pub enum Path<'a> {
     /// `:<name>` represents selecting element that matched the name in an Object, like `$:event`.
     ColonField(Cow<'a, str>),
     /// `["<name>"]` represents selecting element that matched the name in an Object, like `$["event"]`.
+    /// The name can also be single quoted, and can have any character, like `$['first.name[0]']`.
     ObjectField(Cow<'a, str>),
+    /// `["<name1>", "<name2>", ..]` represents selecting the elements that matched any of the
+    /// names in an Object, like `$["event", "date"]`. The names can also be single quoted.
+    ObjectFields(Vec<Cow<'a, str>>),
     /// `[<index1>,<index2>,..]` represents selecting elements specified by the indices in an Array.
     /// There are several forms of index.
-    /// 1. A single number representing the 0-based `n-th` element in the Array.
-    ///    e.g. `$[0]` represents the first element in an Array.
+    /// 1. A single number representing the 0-based `n-th` element in the Array,
+    ///    a negative number counts from the end of the Array.
+    ///    e.g. `$[0]` represents the first element and `$[-1]` the last element in an Array.
     /// 2. The keyword `last` represents the last element in the Array,
     ///    and last minus a number represents the n-th element before the last element,
     ///    e.g. `$[last-1]` represents the penultimate element.
+    ///    The integers and `last` can be added and subtracted, like `$[last - 3 + 1]`,
+    ///    the arithmetic is evaluated when the path is parsed.
     /// 3. The keyword `to` between two numbers represent all elements of a range in an Array,
     ///    e.g. `$[1 to last]` represents all the elements in the Array from the second to the last.
     ///
+    /// 4. A Python style slice `<start>:<end>:<step>` represents the elements from the start
+    ///    to before the end, taking every `step`-th element, the start, the end and the step
+    ///    are optional, e.g. `$[1:10:2]` represents the second, fourth, ..., tenth elements.
+    ///    A negative step selects the elements backwards.
+    ///
     /// There can be more than one index, e.g. `$[0, last-1 to last, 5]` represents the first,
     /// the last two, and the sixth element in an Array.
     ArrayIndices(Vec<ArrayIndex>),
+    /// `..` represents selecting the current element and all its descendants in the order
+    /// of the document, the following path selects from each of them, like `$..name`.
+    RecursiveDescent,
+    /// `..*` represents selecting all the descendants of the current element in the order
+    /// of the document, both the values of Objects and the elements of Arrays.
+    RecursiveWildcard,
+    /// `.<method>()` represents applying the item method to each of the selected values,
+    /// the values are replaced with the results of the method, like `$.a.type()`.
+    Method(ItemMethod),
     /// `?(<expression>)` represents selecting all elements in an object or array that match the filter expression, like `$.book[?(@.price < 10)]`.
     FilterExpr(Box<Expr<'a>>),
 }
 
+/// Represents the item methods, which compute a new value from each selected value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemMethod {
+    /// `type()` returns the type of the value as a string, one of `"null"`, `"boolean"`,
+    /// `"number"`, `"string"`, `"array"`, `"object"` and `"extension"`.
+    Type,
+    /// `size()` returns the number of the elements of an array, in lax mode the size
+    /// of other values is 1, in strict mode they are an error.
+    Size,
+    /// `keyvalue()` expands an object into one `{"key": k, "value": v, "id": n}` object
+    /// per field, `n` identifies the object the fields belong to.
+    KeyValue,
+    /// `double()` converts a number, or a string of a number, to a float.
+    Double,
+    /// `number()` converts a string of a number to the number, a number is kept as it is.
+    Number,
+    /// `abs()` returns the absolute value of a number.
+    Abs,
+    /// `floor()` returns the largest integral value not greater than a number.
+    Floor,
+    /// `ceiling()` returns the smallest integral value not less than a number.
+    Ceiling,
+}
+
 /// Represents the single index in an Array.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Index {
-    /// The 0-based index in an Array.
+    /// The 0-based index in an Array, a negative index counts from the end.
     Index(i32),
     /// The last n-th index in an Array.
     LastIndex(i32),
@@ -76,6 +125,13 @@ pub enum ArrayIndex {
     Index(Index),
     /// The range index between two number.
     Slice((Index, Index)),
+    /// The range index from the start to before the end with a step,
+    /// the missing start and end are the ends of the array in the direction of the step.
+    StepSlice {
+        start: Option<Index>,
+        end: Option<Index>,
+        step: i32,
+    },
 }
 
 /// Represents a literal value used in filter expression.
@@ -110,30 +166,211 @@ pub enum BinaryOperator {
     Gt,
     /// `>=` represents left is greater than or equal to right.
     Gte,
+    /// `+` represents the sum of the numbers.
+    Add,
+    /// `-` represents the difference of the numbers.
+    Sub,
+    /// `*` represents the product of the numbers.
+    Mul,
+    /// `/` represents the quotient of the numbers.
+    Div,
+    /// `%` represents the remainder of the division of the numbers.
+    Mod,
+}
+
+impl BinaryOperator {
+    /// Returns true if the operator is an arithmetic operator, which returns a number.
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            BinaryOperator::Add
+                | BinaryOperator::Sub
+                | BinaryOperator::Mul
+                | BinaryOperator::Div
+                | BinaryOperator::Mod
+        )
+    }
+
+    // The operators with a higher precedence bind more tightly.
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Or => 0,
+            BinaryOperator::And => 1,
+            BinaryOperator::Add | BinaryOperator::Sub => 3,
+            BinaryOperator::Mul | BinaryOperator::Div | BinaryOperator::Mod => 4,
+            _ => 2,
+        }
+    }
 }
 
 /// Represents a filter expression used to filter Array or Object.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr<'a> {
-    /// JSON Path chains.
+    /// JSON Path chains, starting from the current value with `@`,
+    /// or from the root value of the document with `$`.
     Paths(Vec<Path<'a>>),
     /// Literal value.
     Value(Box<PathValue<'a>>),
     /// Filter expression that performs a binary operation, returns a boolean value.
+    /// Both operands of a comparison can be paths, like `@.paid < @.total`, the comparison
+    /// is true if any pair of their values matches.
+    /// The operands of a comparison can also be arithmetic operations, like `@.a * 2 > @.b + 1`,
+    /// which return a number if both of their operands are a single number.
     BinaryOp {
         op: BinaryOperator,
         left: Box<Expr<'a>>,
         right: Box<Expr<'a>>,
     },
+    /// Filter function, returns a boolean value.
+    FilterFunc(FilterFunc<'a>),
+}
+
+/// Represents a filter function used in a filter expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterFunc<'a> {
+    /// `exists(path)` returns true if the JSON Path chain selects at least one value,
+    /// whatever the value is.
+    Exists(Vec<Path<'a>>),
+    /// `path like_regex "pattern" flag "flags"` returns true if a string value selected by the
+    /// JSON Path chain matches the regular expression anywhere, like SQL/JSON. The flags are
+    /// `i` (case-insensitive), `s` (`.` matches `\n`), `m` (multi-line), `x` (ignore whitespace)
+    /// and `q` (the pattern is a literal string). Requires the `regex` feature.
+    LikeRegex {
+        paths: Vec<Path<'a>>,
+        pattern: Cow<'a, str>,
+        flags: Option<Cow<'a, str>>,
+    },
+    /// `path starts with "prefix"` returns true if a string value selected by the
+    /// JSON Path chain starts with the prefix.
+    StartsWith {
+        paths: Vec<Path<'a>>,
+        prefix: Cow<'a, str>,
+    },
+}
+
+impl<'a> JsonPath<'a> {
+    /// Convert the JsonPath into an owned JsonPath that doesn't borrow the path text.
+    pub fn into_owned(self) -> JsonPath<'static> {
+        JsonPath {
+            paths: self.paths.into_iter().map(Path::into_owned).collect(),
+        }
+    }
+}
+
+impl<'a> Path<'a> {
+    /// Convert the Path into an owned Path that doesn't borrow the path text.
+    pub fn into_owned(self) -> Path<'static> {
+        match self {
+            Path::Root => Path::Root,
+            Path::Current => Path::Current,
+            Path::Variable(v) => Path::Variable(Cow::Owned(v.into_owned())),
+            Path::DotWildcard => Path::DotWildcard,
+            Path::BracketWildcard => Path::BracketWildcard,
+            Path::RecursiveDescent => Path::RecursiveDescent,
+            Path::RecursiveWildcard => Path::RecursiveWildcard,
+            Path::DotField(v) => Path::DotField(Cow::Owned(v.into_owned())),
+            Path::ColonField(v) => Path::ColonField(Cow::Owned(v.into_owned())),
+            Path::ObjectField(v) => Path::ObjectField(Cow::Owned(v.into_owned())),
+            Path::ObjectFields(v) => {
+                Path::ObjectFields(v.into_iter().map(|v| Cow::Owned(v.into_owned())).collect())
+            }
+            Path::ArrayIndices(v) => Path::ArrayIndices(v),
+            Path::Method(v) => Path::Method(v),
+            Path::FilterExpr(v) => Path::FilterExpr(Box::new(v.into_owned())),
+        }
+    }
+}
+
+impl<'a> PathValue<'a> {
+    /// Convert the PathValue into an owned PathValue that doesn't borrow the path text.
+    pub fn into_owned(self) -> PathValue<'static> {
+        match self {
+            PathValue::Null => PathValue::Null,
+            PathValue::Boolean(v) => PathValue::Boolean(v),
+            PathValue::Number(v) => PathValue::Number(v),
+            PathValue::String(v) => PathValue::String(Cow::Owned(v.into_owned())),
+        }
+    }
+}
+
+impl<'a> Expr<'a> {
+    /// Convert the Expr into an owned Expr that doesn't borrow the path text.
+    pub fn into_owned(self) -> Expr<'static> {
+        match self {
+            Expr::Paths(v) => Expr::Paths(v.into_iter().map(Path::into_owned).collect()),
+            Expr::Value(v) => Expr::Value(Box::new(v.into_owned())),
+            Expr::BinaryOp { op, left, right } => Expr::BinaryOp {
+                op,
+                left: Box::new(left.into_owned()),
+                right: Box::new(right.into_owned()),
+            },
+            Expr::FilterFunc(func) => Expr::FilterFunc(func.into_owned()),
+        }
+    }
+}
+
+impl<'a> FilterFunc<'a> {
+    /// Convert the FilterFunc into an owned FilterFunc that doesn't borrow the path text.
+    pub fn into_owned(self) -> FilterFunc<'static> {
+        match self {
+            FilterFunc::Exists(v) => {
+                FilterFunc::Exists(v.into_iter().map(Path::into_owned).collect())
+            }
+            FilterFunc::LikeRegex {
+                paths,
+                pattern,
+                flags,
+            } => FilterFunc::LikeRegex {
+                paths: paths.into_iter().map(Path::into_owned).collect(),
+                pattern: Cow::Owned(pattern.into_owned()),
+                flags: flags.map(|flags| Cow::Owned(flags.into_owned())),
+            },
+            FilterFunc::StartsWith { paths, prefix } => FilterFunc::StartsWith {
+                paths: paths.into_iter().map(Path::into_owned).collect(),
+                prefix: Cow::Owned(prefix.into_owned()),
+            },
+        }
+    }
 }
 
 impl<'a> Display for JsonPath<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for path in &self.paths {
-            write!(f, "{path}")?;
+        fmt_paths(&self.paths, f)
+    }
+}
+
+// The `.` of a member accessor or `.*` is merged into the preceding `..`, like `$..name`.
+// Write the string quoted and escaped, like `"a\"b"`.
+fn fmt_quoted(s: &str, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let mut buf = String::new();
+    quote_string(s, &mut buf);
+    f.write_str(&buf)
+}
+
+// Write the name of a member accessor unquoted if it only has letters, digits, `_`
+// and other non-ASCII characters than whitespace, otherwise quoted, like `."first name"`,
+// so the name is parsed back as it is.
+fn fmt_member(name: &str, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let plain =
+        |c: char| c.is_ascii_alphanumeric() || c == '_' || !(c.is_ascii() || c.is_whitespace());
+    if !name.is_empty() && name.chars().all(plain) {
+        f.write_str(name)
+    } else {
+        fmt_quoted(name, f)
+    }
+}
+
+fn fmt_paths(paths: &[Path<'_>], f: &mut Formatter<'_>) -> std::fmt::Result {
+    let mut prev: Option<&Path<'_>> = None;
+    for path in paths {
+        match (prev, path) {
+            (Some(Path::RecursiveDescent), Path::DotField(field)) => fmt_member(field, f)?,
+            (Some(Path::RecursiveDescent), Path::DotWildcard) => write!(f, "*")?,
+            _ => write!(f, "{path}")?,
         }
-        Ok(())
+        prev = Some(path);
     }
+    Ok(())
 }
 
 impl Display for Index {
@@ -168,11 +405,38 @@ impl Display for ArrayIndex {
             ArrayIndex::Slice((start, end)) => {
                 write!(f, "{start} to {end}")?;
             }
+            ArrayIndex::StepSlice { start, end, step } => {
+                if let Some(start) = start {
+                    write!(f, "{start}")?;
+                }
+                write!(f, ":")?;
+                if let Some(end) = end {
+                    write!(f, "{end}")?;
+                }
+                if *step != 1 {
+                    write!(f, ":{step}")?;
+                }
+            }
         }
         Ok(())
     }
 }
 
+impl Display for ItemMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ItemMethod::Type => write!(f, "type"),
+            ItemMethod::Size => write!(f, "size"),
+            ItemMethod::KeyValue => write!(f, "keyvalue"),
+            ItemMethod::Double => write!(f, "double"),
+            ItemMethod::Number => write!(f, "number"),
+            ItemMethod::Abs => write!(f, "abs"),
+            ItemMethod::Floor => write!(f, "floor"),
+            ItemMethod::Ceiling => write!(f, "ceiling"),
+        }
+    }
+}
+
 impl<'a> Display for Path<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -188,14 +452,40 @@ impl<'a> Display for Path<'a> {
             Path::BracketWildcard => {
                 write!(f, "[*]")?;
             }
+            Path::RecursiveDescent => {
+                write!(f, "..")?;
+            }
+            Path::RecursiveWildcard => {
+                write!(f, "..*")?;
+            }
+            Path::Variable(name) => {
+                write!(f, "${name}")?;
+            }
+            Path::Method(method) => {
+                write!(f, ".{method}()")?;
+            }
             Path::ColonField(field) => {
-                write!(f, ":{field}")?;
+                write!(f, ":")?;
+                fmt_member(field, f)?;
             }
             Path::DotField(field) => {
-                write!(f, ".{field}")?;
+                write!(f, ".")?;
+                fmt_member(field, f)?;
             }
             Path::ObjectField(field) => {
-                write!(f, "[\"{field}\"]")?;
+                write!(f, "[")?;
+                fmt_quoted(field, f)?;
+                write!(f, "]")?;
+            }
+            Path::ObjectFields(fields) => {
+                write!(f, "[")?;
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    fmt_quoted(field, f)?;
+                }
+                write!(f, "]")?;
             }
             Path::ArrayIndices(indices) => {
                 write!(f, "[")?;
@@ -231,9 +521,7 @@ impl<'a> Display for PathValue<'a> {
             PathValue::Number(v) => {
                 write!(f, "{v}")
             }
-            PathValue::String(v) => {
-                write!(f, "\"{v}\"")
-            }
+            PathValue::String(v) => fmt_quoted(v, f),
         }
     }
 }
@@ -265,6 +553,21 @@ impl Display for BinaryOperator {
             BinaryOperator::Gte => {
                 write!(f, ">=")
             }
+            BinaryOperator::Add => {
+                write!(f, "+")
+            }
+            BinaryOperator::Sub => {
+                write!(f, "-")
+            }
+            BinaryOperator::Mul => {
+                write!(f, "*")
+            }
+            BinaryOperator::Div => {
+                write!(f, "/")
+            }
+            BinaryOperator::Mod => {
+                write!(f, "%")
+            }
         }
     }
 }
@@ -273,35 +576,71 @@ impl<'a> Display for Expr<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Expr::Paths(paths) => {
-                for path in paths {
-                    write!(f, "{path}")?;
-                }
+                fmt_paths(paths, f)?;
             }
             Expr::Value(v) => {
                 write!(f, "{v}")?;
             }
             Expr::BinaryOp { op, left, right } => {
-                if let Expr::BinaryOp { op: left_op, .. } = &**left {
-                    if left_op == &BinaryOperator::And || left_op == &BinaryOperator::Or {
-                        write!(f, "({left})")?;
-                    } else {
-                        write!(f, "{left}")?;
+                // The logical operands are always parenthesized, the others only if they bind
+                // less tightly than the operator, the operators are left-associative.
+                let parenthesize = |expr: &Expr<'_>, is_right: bool| match expr {
+                    Expr::BinaryOp { op: child, .. } => {
+                        child == &BinaryOperator::And
+                            || child == &BinaryOperator::Or
+                            || child.precedence() < op.precedence()
+                            || (is_right && child.precedence() == op.precedence())
                     }
+                    _ => false,
+                };
+                if parenthesize(left, false) {
+                    write!(f, "({left})")?;
                 } else {
                     write!(f, "{left}")?;
                 }
                 write!(f, " {op} ")?;
-                if let Expr::BinaryOp { op: right_op, .. } = &**right {
-                    if right_op == &BinaryOperator::And || right_op == &BinaryOperator::Or {
-                        write!(f, "({right})")?;
-                    } else {
-                        write!(f, "{right}")?;
-                    }
+                if parenthesize(right, true) {
+                    write!(f, "({right})")?;
                 } else {
                     write!(f, "{right}")?;
                 }
             }
+            Expr::FilterFunc(func) => {
+                write!(f, "{func}")?;
+            }
         }
         Ok(())
     }
 }
+
+impl<'a> Display for FilterFunc<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterFunc::Exists(paths) => {
+                write!(f, "exists(")?;
+                fmt_paths(paths, f)?;
+                write!(f, ")")
+            }
+            FilterFunc::LikeRegex {
+                paths,
+                pattern,
+                flags,
+            } => {
+                fmt_paths(paths, f)?;
+                let mut buf = String::new();
+                quote_string(pattern, &mut buf);
+                if let Some(flags) = flags {
+                    buf.push_str(" flag ");
+                    quote_string(flags, &mut buf);
+                }
+                write!(f, " like_regex {buf}")
+            }
+            FilterFunc::StartsWith { paths, prefix } => {
+                fmt_paths(paths, f)?;
+                let mut buf = String::new();
+                quote_string(prefix, &mut buf);
+                write!(f, " starts with {buf}")
+            }
+        }
+    }
+}
@@ -17,26 +17,90 @@ use byteorder::WriteBytesExt;
 
 use std::borrow::Cow;
 use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::collections::VecDeque;
 
+use crate::cancel::CancellationToken;
 use crate::constants::*;
+use crate::encoding::ContainerKind;
+use crate::encoding::ContainerReader;
+use crate::encoding::ContainerWriter;
+use crate::encoding::EntryKind;
+use crate::error::Error;
+use crate::error::PathErrorCode;
 use crate::jsonpath::ArrayIndex;
 use crate::jsonpath::BinaryOperator;
 use crate::jsonpath::Expr;
+use crate::jsonpath::FilterFunc;
 use crate::jsonpath::Index;
+use crate::jsonpath::ItemMethod;
 use crate::jsonpath::JsonPath;
 use crate::jsonpath::Path;
 use crate::jsonpath::PathValue;
+use crate::memory::MemoryTracker;
 use crate::number::Number;
+use crate::parser::parse_value;
+use crate::value::Value;
+
+#[cfg(feature = "regex")]
+use crate::jsonpath::visitor::{visit_expr, Visitor};
+#[cfg(feature = "regex")]
+use regex::{Regex, RegexBuilder};
 
 use nom::{
     bytes::complete::take, combinator::map, multi::count, number::complete::be_u32, IResult,
 };
 
-#[derive(Debug)]
+// Scalar values are copied with the address of their `JEntry`, which identifies the value.
+#[derive(Debug, Clone)]
 enum Item<'a> {
     Container(&'a [u8]),
-    Scalar(Vec<u8>),
+    Scalar(Vec<u8>, usize),
+}
+
+impl<'a> Item<'a> {
+    fn to_vec(&self) -> Vec<u8> {
+        match self {
+            Item::Container(val) => val.to_vec(),
+            Item::Scalar(val, _) => val.clone(),
+        }
+    }
+
+    // The identity of the selected value in the document.
+    fn id(&self) -> (bool, usize) {
+        match self {
+            Item::Container(val) => (true, val.as_ptr() as usize),
+            Item::Scalar(_, addr) => (false, *addr),
+        }
+    }
+}
+
+/// The order of the values selected by [`Selector`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResultOrder {
+    /// The values are in the order of the document,
+    /// array subscripts like `$[2,0]` select the elements in ascending order.
+    #[default]
+    Document,
+    /// Array subscripts select the elements in the order as written, like Postgres.
+    Written,
+}
+
+/// Options to control the results of [`Selector`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SelectorOptions {
+    pub order: ResultOrder,
+    /// Select each value only once, even if it is selected by overlapping subscripts
+    /// like `$[0, 0 to 1]`, the first occurrence is kept.
+    pub dedup: bool,
+    /// Evaluate the path in strict mode with [`Selector::try_select`], the structural errors
+    /// are reported instead of being ignored as in lax mode. `select` returns no values
+    /// if the strict evaluation fails.
+    pub strict: bool,
+    /// Select at most this many values, the evaluation stops once the limit is reached,
+    /// so the rest of the document is not visited. In strict mode, the whole path is still
+    /// evaluated to report the errors, and the values are truncated to the limit.
+    pub limit: Option<usize>,
 }
 
 #[derive(Debug)]
@@ -46,71 +110,620 @@ enum ExprValue<'a> {
 }
 
 pub struct Selector<'a> {
-    json_path: JsonPath<'a>,
+    json_path: SelectorPath<'a>,
+    options: SelectorOptions,
+    // The `JSONB` object of the variables in the filter expressions.
+    vars: Option<&'a [u8]>,
+    // Reserves the memory of the copied values.
+    tracker: Option<&'a dyn MemoryTracker>,
+    // Interrupts the evaluation, the selected values are discarded.
+    cancel: Option<&'a CancellationToken>,
+    // The compiled patterns and flags of the `like_regex` predicates,
+    // each pattern is compiled once for all the selected values.
+    #[cfg(feature = "regex")]
+    regexes: Vec<(String, String, Regex)>,
+}
+
+// Collects the patterns of the `like_regex` predicates in the path.
+#[cfg(feature = "regex")]
+#[derive(Default)]
+struct RegexCollector {
+    regexes: Vec<(String, String, Regex)>,
+}
+
+#[cfg(feature = "regex")]
+impl<'a> Visitor<'a> for RegexCollector {
+    fn visit_expr(&mut self, expr: &Expr<'a>) {
+        if let Expr::FilterFunc(FilterFunc::LikeRegex { pattern, flags, .. }) = expr {
+            let flags = flags.as_deref().unwrap_or_default();
+            let compiled = self
+                .regexes
+                .iter()
+                .any(|(p, f, _)| p == pattern && f == flags);
+            if !compiled {
+                if let Some(regex) = build_regex(pattern, flags) {
+                    self.regexes
+                        .push((pattern.to_string(), flags.to_string(), regex));
+                }
+            }
+        }
+        visit_expr(self, expr);
+    }
+}
+
+// Not a `Cow`, which would make the selector invariant over the lifetime.
+enum SelectorPath<'a> {
+    Owned(JsonPath<'a>),
+    Borrowed(&'a JsonPath<'a>),
+}
+
+impl<'a> std::ops::Deref for SelectorPath<'a> {
+    type Target = JsonPath<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            SelectorPath::Owned(json_path) => json_path,
+            SelectorPath::Borrowed(json_path) => json_path,
+        }
+    }
 }
 
 impl<'a> Selector<'a> {
     pub fn new(json_path: JsonPath<'a>) -> Self {
-        Self { json_path }
+        Self::with_options(json_path, SelectorOptions::default())
+    }
+
+    pub fn with_options(json_path: JsonPath<'a>, options: SelectorOptions) -> Self {
+        Self::from_path(SelectorPath::Owned(json_path), options)
+    }
+
+    // Borrows the path, so that it doesn't need to be cloned for each selection.
+    pub(crate) fn from_ref(json_path: &'a JsonPath<'a>) -> Self {
+        Self::from_path(
+            SelectorPath::Borrowed(json_path),
+            SelectorOptions::default(),
+        )
+    }
+
+    fn from_path(json_path: SelectorPath<'a>, options: SelectorOptions) -> Self {
+        #[cfg(feature = "regex")]
+        let regexes = {
+            let mut collector = RegexCollector::default();
+            collector.visit_json_path(&json_path);
+            collector.regexes
+        };
+        Self {
+            json_path,
+            options,
+            vars: None,
+            tracker: None,
+            cancel: None,
+            #[cfg(feature = "regex")]
+            regexes,
+        }
+    }
+
+    /// Binds the variables in the filter expressions, like `$min_qty`, to the fields
+    /// of the `JSONB` object. The paths of the missing variables select no values.
+    pub fn with_vars(mut self, vars: &'a [u8]) -> Self {
+        self.vars = Some(vars);
+        self
+    }
+
+    /// Reserves the memory of the selected values from the tracker before they are copied,
+    /// `try_select` returns the error of the tracker if the memory can't be reserved,
+    /// and `select` returns no values.
+    pub fn with_tracker(mut self, tracker: &'a dyn MemoryTracker) -> Self {
+        self.tracker = Some(tracker);
+        self
+    }
+
+    /// Interrupts the evaluation once the token is cancelled, `try_select` returns
+    /// `Error::Cancelled` and `select` returns no values.
+    pub fn with_cancellation(mut self, cancel: &'a CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
     }
 
     pub fn select(&'a self, value: &'a [u8]) -> Vec<Vec<u8>> {
-        let root = value;
+        if self.options.strict || self.tracker.is_some() || self.cancel.is_some() {
+            return self.try_select(value).unwrap_or_default();
+        }
+        let items = self.select_items(value);
+        let mut values = Vec::with_capacity(items.len());
+        for item in items {
+            match item {
+                Item::Container(val) => {
+                    values.push(val.to_vec());
+                }
+                Item::Scalar(val, _) => {
+                    values.push(val);
+                }
+            }
+        }
+        values
+    }
+
+    // Copies the selected values, reserving the memory from the tracker.
+    // The values are discarded if the evaluation is cancelled.
+    fn copy_values(&self, items: VecDeque<Item<'a>>) -> Result<Vec<Vec<u8>>, Error> {
+        if let Some(cancel) = self.cancel {
+            cancel.check()?;
+        }
+        let mut values = Vec::with_capacity(items.len());
+        for item in items {
+            if let Some(tracker) = self.tracker {
+                let len = match &item {
+                    Item::Container(val) => val.len(),
+                    Item::Scalar(val, _) => val.len(),
+                };
+                tracker.reserve(len)?;
+            }
+            values.push(item.to_vec());
+        }
+        Ok(values)
+    }
+
+    /// Selects the values like `select`, in strict mode the structural errors, like a member
+    /// accessor on an array or an array index out of bounds, are returned as `Error::JsonPath`
+    /// with the path of the value at which the evaluation stopped.
+    /// In lax mode, the values are selected as `select`, only the errors of the tracker
+    /// and the cancellation are returned.
+    pub fn try_select(&'a self, value: &'a [u8]) -> Result<Vec<Vec<u8>>, Error> {
+        if !self.options.strict {
+            return self.copy_values(self.select_items(value));
+        }
         let mut items = VecDeque::new();
-        items.push_back(Item::Container(value));
+        items.push_back((Item::Container(value), "$".to_string()));
+        let paths = &self.json_path.paths;
+        let mut steps = paths.iter().enumerate();
+        let mut items = loop {
+            match steps.next() {
+                // Like Postgres, the structural errors are ignored after the recursive descent,
+                // as most of the descendants don't match the following paths.
+                Some((i, Path::RecursiveDescent | Path::RecursiveWildcard)) => {
+                    let mut items = items.into_iter().map(|(item, _)| item).collect();
+                    for path in paths[i..].iter() {
+                        self.select_step(value, path, &mut items);
+                    }
+                    break items;
+                }
+                Some((_, path)) => {
+                    if let Some(cancel) = self.cancel {
+                        cancel.check()?;
+                    }
+                    self.select_step_strict(value, path, &mut items)?
+                }
+                None => break items.into_iter().map(|(item, _)| item).collect(),
+            }
+        };
+        if self.options.dedup {
+            let mut ids = HashSet::with_capacity(items.len());
+            items.retain(|item| ids.insert(item.id()));
+        }
+        if let Some(limit) = self.options.limit {
+            items.truncate(limit);
+        }
+        self.copy_values(items)
+    }
 
-        for path in self.json_path.paths.iter() {
-            match path {
-                &Path::Root => {
+    // Selects the values of the step like `select_step`, the items keep the concrete path
+    // of the values to report the errors.
+    fn select_step_strict(
+        &'a self,
+        root: &'a [u8],
+        path: &'a Path<'a>,
+        items: &mut VecDeque<(Item<'a>, String)>,
+    ) -> Result<(), Error> {
+        let len = items.len();
+        for _ in 0..len {
+            let (item, item_path) = items.pop_front().unwrap();
+            let expected = match path {
+                Path::Root => {
+                    items.push_back((item, item_path));
                     continue;
                 }
-                &Path::Current => unreachable!(),
+                Path::Current => unreachable!(),
                 Path::FilterExpr(expr) => {
-                    let mut tmp_items = Vec::with_capacity(items.len());
-                    while let Some(item) = items.pop_front() {
-                        let current = match item {
-                            Item::Container(val) => val,
-                            Item::Scalar(ref val) => val.as_slice(),
-                        };
-                        if self.filter_expr(root, current, expr) {
-                            tmp_items.push(item);
+                    let current = match item {
+                        Item::Container(val) => val,
+                        Item::Scalar(ref val, _) => val.as_slice(),
+                    };
+                    if self.filter_expr(root, current, expr) {
+                        items.push_back((item, item_path));
+                    }
+                    continue;
+                }
+                Path::Method(method) => {
+                    let mut selected = VecDeque::new();
+                    self.apply_method(root, *method, &item, true, &mut selected)
+                        .map_err(|code| Error::JsonPath(code, item_path.clone()))?;
+                    let item_path = format!("{}.{}()", item_path, method);
+                    items.extend(selected.into_iter().map(|item| (item, item_path.clone())));
+                    continue;
+                }
+                Path::BracketWildcard | Path::ArrayIndices(_) => ARRAY_CONTAINER_TAG,
+                _ => OBJECT_CONTAINER_TAG,
+            };
+            if let Item::Scalar(ref buf, id) = item {
+                if container_type(buf) == expected {
+                    let mut selected = VecDeque::from([(Item::Container(buf), item_path)]);
+                    self.select_step_strict(root, path, &mut selected)?;
+                    items.extend(
+                        selected
+                            .iter()
+                            .map(|(val, path)| (Self::owned_item(buf, id, val), path.clone())),
+                    );
+                    continue;
+                }
+            }
+            let current = match item {
+                Item::Container(current) if container_type(current) == expected => current,
+                _ => {
+                    let code = if expected == ARRAY_CONTAINER_TAG {
+                        PathErrorCode::ArrayExpected
+                    } else {
+                        PathErrorCode::ObjectExpected
+                    };
+                    return Err(Error::JsonPath(code, item_path));
+                }
+            };
+            let mut selected = VecDeque::new();
+            match path {
+                Path::DotWildcard => {
+                    self.select_object_values(current, &mut selected);
+                    for (item, key) in selected.into_iter().zip(Self::object_keys(current)) {
+                        items.push_back((item, member_path(&item_path, key)));
+                    }
+                }
+                Path::ColonField(name) | Path::DotField(name) | Path::ObjectField(name) => {
+                    self.select_by_name(current, name, &mut selected);
+                    match selected.pop_front() {
+                        Some(item) => items.push_back((item, member_path(&item_path, name))),
+                        None => {
+                            let code = PathErrorCode::MemberNotFound(name.to_string());
+                            return Err(Error::JsonPath(code, item_path));
                         }
                     }
-                    while let Some(item) = tmp_items.pop() {
-                        items.push_front(item);
+                }
+                Path::ObjectFields(names) => {
+                    for name in self.ordered_names(current, names) {
+                        self.select_by_name(current, name, &mut selected);
+                        match selected.pop_front() {
+                            Some(item) => items.push_back((item, member_path(&item_path, name))),
+                            None => {
+                                let code = PathErrorCode::MemberNotFound(name.to_string());
+                                return Err(Error::JsonPath(code, item_path));
+                            }
+                        }
                     }
                 }
-                _ => {
-                    let len = items.len();
-                    for _ in 0..len {
-                        let item = items.pop_front().unwrap();
-                        match item {
-                            Item::Container(current) => {
-                                self.select_path(current, path, &mut items);
+                Path::BracketWildcard => {
+                    self.select_array_values(current, &mut selected);
+                    for (i, item) in selected.into_iter().enumerate() {
+                        items.push_back((item, format!("{}[{}]", item_path, i)));
+                    }
+                }
+                Path::ArrayIndices(indices) => {
+                    let (_, (_, length)) = decode_header(current).unwrap();
+                    let mut val_indices = Vec::new();
+                    for index in indices {
+                        let (start, end) = match index {
+                            // The slices with a step are clamped to the array.
+                            ArrayIndex::StepSlice { start, end, step } => {
+                                val_indices.extend(Self::convert_step_slice(
+                                    start.as_ref(),
+                                    end.as_ref(),
+                                    *step,
+                                    length as i32,
+                                ));
+                                continue;
                             }
-                            Item::Scalar(_) => {
-                                // In lax mode, bracket wildcard allow Scalar value.
-                                if path == &Path::BracketWildcard {
-                                    items.push_back(item);
-                                }
+                            ArrayIndex::Index(idx) => {
+                                let idx = Self::resolve_index(idx, length as i32);
+                                (idx, idx)
+                            }
+                            ArrayIndex::Slice((start, end)) => (
+                                Self::resolve_index(start, length as i32),
+                                Self::resolve_index(end, length as i32),
+                            ),
+                        };
+                        for idx in [start, end] {
+                            if idx < 0 || idx >= length as i32 {
+                                let code = PathErrorCode::IndexOutOfBounds(idx);
+                                return Err(Error::JsonPath(code, item_path));
                             }
                         }
+                        val_indices.extend(start as usize..=end as usize);
+                    }
+                    if self.options.order == ResultOrder::Document {
+                        val_indices.sort_unstable();
+                    }
+                    for i in val_indices {
+                        let index = ArrayIndex::Index(Index::Index(i as i32));
+                        self.select_by_indices(current, &vec![index], &mut selected);
+                        let item = selected.pop_front().unwrap();
+                        items.push_back((item, format!("{}[{}]", item_path, i)));
                     }
                 }
+                _ => unreachable!(),
             }
         }
-        let mut values = Vec::new();
-        while let Some(item) = items.pop_front() {
-            match item {
-                Item::Container(val) => {
-                    values.push(val.to_vec());
+        Ok(())
+    }
+
+    // The keys of an Object in the order of the values.
+    fn object_keys(current: &'a [u8]) -> Vec<&'a str> {
+        let (rest, (_, length)) = decode_header(current).unwrap();
+        let (rest, key_jentries) = decode_jentries(rest, length).unwrap();
+        let mut rest = &rest[4 * length..];
+        let mut keys = Vec::with_capacity(length);
+        for (_, jlength) in key_jentries {
+            let (next, key) = decode_string(rest, jlength).unwrap();
+            keys.push(unsafe { std::str::from_utf8_unchecked(key) });
+            rest = next;
+        }
+        keys
+    }
+
+    // Returns the first selected value, the container value is borrowed without copying.
+    pub(crate) fn select_first(&'a self, value: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        let mut items = self.select_items_limited(value, 1);
+        items.pop_front().map(|item| match item {
+            Item::Container(val) => Cow::Borrowed(val),
+            Item::Scalar(val, _) => Cow::Owned(val),
+        })
+    }
+
+    fn select_items(&'a self, value: &'a [u8]) -> VecDeque<Item<'a>> {
+        if let Some(limit) = self.options.limit {
+            return self.select_items_limited(value, limit);
+        }
+        let mut items = VecDeque::new();
+        items.push_back(Item::Container(value));
+        for path in self.json_path.paths.iter() {
+            self.select_step(value, path, &mut items);
+        }
+        if self.options.dedup {
+            let mut ids = HashSet::with_capacity(items.len());
+            items.retain(|item| ids.insert(item.id()));
+        }
+        items
+    }
+
+    // Selects the values depth-first, so that the selection stops once the limit is reached.
+    // Each step selects the values of the items one by one, so the values are in the same
+    // order as the values selected step by step.
+    fn select_items_limited(&'a self, value: &'a [u8], limit: usize) -> VecDeque<Item<'a>> {
+        let mut items = VecDeque::new();
+        if limit > 0 {
+            let mut ids = HashSet::new();
+            let paths = &self.json_path.paths;
+            self.select_item_limited(
+                value,
+                Item::Container(value),
+                paths,
+                limit,
+                &mut ids,
+                &mut items,
+            );
+        }
+        items
+    }
+
+    fn select_item_limited(
+        &'a self,
+        root: &'a [u8],
+        item: Item<'a>,
+        paths: &'a [Path<'a>],
+        limit: usize,
+        ids: &mut HashSet<(bool, usize)>,
+        results: &mut VecDeque<Item<'a>>,
+    ) {
+        let Some((path, rest)) = paths.split_first() else {
+            if !self.options.dedup || ids.insert(item.id()) {
+                results.push_back(item);
+            }
+            return;
+        };
+        let mut items = VecDeque::new();
+        items.push_back(item);
+        self.select_step(root, path, &mut items);
+        for item in items {
+            self.select_item_limited(root, item, rest, limit, ids, results);
+            if results.len() >= limit {
+                return;
+            }
+        }
+    }
+
+    // Selects the values of several paths, the paths with the same leading steps share the
+    // selected items of these steps, so the common parts are only evaluated once.
+    pub(crate) fn select_paths(
+        json_paths: &[&'a JsonPath<'a>],
+        value: &'a [u8],
+    ) -> Vec<Vec<Vec<u8>>> {
+        Self::select_paths_with_options(json_paths, value, SelectorOptions::default())
+    }
+
+    // Selects the values of several paths like `select_paths` with the options. With a limit,
+    // or in strict mode, each path is selected on its own to stop at its own values or errors.
+    pub(crate) fn select_paths_with_options(
+        json_paths: &[&'a JsonPath<'a>],
+        value: &'a [u8],
+        options: SelectorOptions,
+    ) -> Vec<Vec<Vec<u8>>> {
+        if options.limit.is_some() || options.strict {
+            return json_paths
+                .iter()
+                .map(|json_path| {
+                    let selector = Selector::from_path(SelectorPath::Borrowed(json_path), options);
+                    selector.select(value)
+                })
+                .collect();
+        }
+        let mut results = vec![Vec::new(); json_paths.len()];
+        if let Some(json_path) = json_paths.first().copied() {
+            // The steps don't depend on the path of the selector.
+            let selector = Selector::from_path(SelectorPath::Borrowed(json_path), options);
+            let mut items = VecDeque::new();
+            items.push_back(Item::Container(value));
+            let indices = (0..json_paths.len()).collect();
+            selector.select_group(value, json_paths, indices, 0, items, &mut results);
+        }
+        results
+    }
+
+    fn select_group(
+        &'a self,
+        root: &'a [u8],
+        json_paths: &[&'a JsonPath<'a>],
+        indices: Vec<usize>,
+        depth: usize,
+        items: VecDeque<Item<'a>>,
+        results: &mut [Vec<Vec<u8>>],
+    ) {
+        // Group the unfinished paths by the next step, keeping the order of the paths.
+        let mut groups: Vec<(&'a Path<'a>, Vec<usize>)> = Vec::new();
+        for idx in indices {
+            match json_paths[idx].paths.get(depth) {
+                Some(path) => match groups.iter_mut().find(|(p, _)| *p == path) {
+                    Some((_, group)) => group.push(idx),
+                    None => groups.push((path, vec![idx])),
+                },
+                None => {
+                    let mut ids = HashSet::new();
+                    results[idx] = items
+                        .iter()
+                        .filter(|item| !self.options.dedup || ids.insert(item.id()))
+                        .map(|item| item.to_vec())
+                        .collect();
                 }
-                Item::Scalar(val) => {
-                    values.push(val);
+            }
+        }
+        if items.is_empty() {
+            return;
+        }
+
+        let last = groups.len().saturating_sub(1);
+        let mut items = Some(items);
+        for (i, (path, group)) in groups.into_iter().enumerate() {
+            let mut group_items = if i == last {
+                items.take().unwrap()
+            } else {
+                items.as_ref().unwrap().clone()
+            };
+            self.select_step(root, path, &mut group_items);
+            self.select_group(root, json_paths, group, depth + 1, group_items, results);
+        }
+    }
+
+    fn select_step(&'a self, root: &'a [u8], path: &'a Path<'a>, items: &mut VecDeque<Item<'a>>) {
+        if self.is_cancelled() {
+            items.clear();
+            return;
+        }
+        match path {
+            &Path::Root => {}
+            &Path::Current => unreachable!(),
+            &Path::RecursiveDescent => {
+                self.select_descendants(items, true);
+            }
+            &Path::RecursiveWildcard => {
+                self.select_descendants(items, false);
+            }
+            Path::FilterExpr(expr) => {
+                let mut tmp_items = Vec::with_capacity(items.len());
+                while let Some(item) = items.pop_front() {
+                    let current = match item {
+                        Item::Container(val) => val,
+                        Item::Scalar(ref val, _) => val.as_slice(),
+                    };
+                    if self.filter_expr(root, current, expr) {
+                        tmp_items.push(item);
+                    }
+                }
+                while let Some(item) = tmp_items.pop() {
+                    items.push_front(item);
+                }
+            }
+            Path::Method(method) => self.apply_methods(root, *method, items),
+            _ => {
+                let len = items.len();
+                for _ in 0..len {
+                    let item = items.pop_front().unwrap();
+                    match item {
+                        Item::Container(current) => {
+                            self.select_path(current, path, items);
+                        }
+                        Item::Scalar(ref buf, id) if is_container(buf) => {
+                            let mut selected = VecDeque::new();
+                            self.select_path(buf, path, &mut selected);
+                            items.extend(selected.iter().map(|val| Self::owned_item(buf, id, val)));
+                        }
+                        Item::Scalar(..) => {
+                            // In lax mode, bracket wildcard allow Scalar value.
+                            if path == &Path::BracketWildcard {
+                                items.push_back(item);
+                            }
+                        }
+                    }
                 }
             }
         }
-        values
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .map(|cancel| cancel.is_cancelled())
+            .unwrap_or_default()
+    }
+
+    // Replaces each item with all its descendants in the order of the document,
+    // preceded by the item itself if `with_self` is true.
+    fn select_descendants(&'a self, items: &mut VecDeque<Item<'a>>, with_self: bool) {
+        let len = items.len();
+        for _ in 0..len {
+            let item = items.pop_front().unwrap();
+            let mut level = VecDeque::new();
+            if with_self {
+                level.push_back(item);
+            } else if let Item::Container(current) = item {
+                self.select_children(current, &mut level);
+            }
+            self.push_descendants(level, items);
+        }
+    }
+
+    // Pushes the items and their descendants depth-first, the unvisited siblings of each level
+    // are kept on an explicit stack, so deeply nested values don't overflow the call stack.
+    fn push_descendants(&'a self, level: VecDeque<Item<'a>>, items: &mut VecDeque<Item<'a>>) {
+        let mut stack = vec![level];
+        while let Some(level) = stack.last_mut() {
+            // The items are discarded after the step if the evaluation is cancelled.
+            if self.is_cancelled() {
+                return;
+            }
+            let Some(item) = level.pop_front() else {
+                stack.pop();
+                continue;
+            };
+            if let Item::Container(current) = item {
+                let mut children = VecDeque::new();
+                self.select_children(current, &mut children);
+                stack.push(children);
+            }
+            items.push_back(item);
+        }
+    }
+
+    // select the values of an Object or the elements of an Array.
+    fn select_children(&'a self, current: &'a [u8], items: &mut VecDeque<Item<'a>>) {
+        match container_type(current) {
+            OBJECT_CONTAINER_TAG => self.select_object_values(current, items),
+            ARRAY_CONTAINER_TAG => self.select_array_values(current, items),
+            _ => {}
+        }
     }
 
     fn select_path(&'a self, current: &'a [u8], path: &Path<'a>, items: &mut VecDeque<Item<'a>>) {
@@ -124,6 +737,11 @@ impl<'a> Selector<'a> {
             Path::ColonField(name) | Path::DotField(name) | Path::ObjectField(name) => {
                 self.select_by_name(current, name, items);
             }
+            Path::ObjectFields(names) => {
+                for name in self.ordered_names(current, names) {
+                    self.select_by_name(current, name, items);
+                }
+            }
             Path::ArrayIndices(indices) => {
                 self.select_by_indices(current, indices, items);
             }
@@ -131,6 +749,19 @@ impl<'a> Selector<'a> {
         }
     }
 
+    // The names of a key union in the order of the fields in the Object,
+    // or as written if the results are in the written order.
+    fn ordered_names<'b>(&self, current: &'a [u8], names: &'b [Cow<'b, str>]) -> Vec<&'b str> {
+        let mut names: Vec<_> = names.iter().map(|name| name.as_ref()).collect();
+        if self.options.order == ResultOrder::Document
+            && container_type(current) == OBJECT_CONTAINER_TAG
+        {
+            let keys = Self::object_keys(current);
+            names.sort_by_key(|name| keys.iter().position(|key| key == name));
+        }
+        names
+    }
+
     // select all values in an Object.
     fn select_object_values(&'a self, current: &'a [u8], items: &mut VecDeque<Item<'a>>) {
         let (rest, (ty, length)) = decode_header(current).unwrap();
@@ -138,6 +769,7 @@ impl<'a> Selector<'a> {
             return;
         }
         let (rest, key_jentries) = decode_jentries(rest, length).unwrap();
+        let jentries_addr = rest.as_ptr() as usize;
         let (rest, val_jentries) = decode_jentries(rest, length).unwrap();
         let mut offset = 0;
         for (_, length) in key_jentries.iter() {
@@ -145,13 +777,13 @@ impl<'a> Selector<'a> {
         }
         let rest = &rest[offset..];
         offset = 0;
-        for (jty, jlength) in val_jentries.iter() {
+        for (i, (jty, jlength)) in val_jentries.iter().enumerate() {
             let val = &rest[offset..offset + jlength];
             let item = if *jty == CONTAINER_TAG {
                 Item::Container(val)
             } else {
                 let buf = Self::build_scalar_buf(*jty, *jlength, val);
-                Item::Scalar(buf)
+                Item::Scalar(buf, jentries_addr + 4 * i)
             };
             items.push_back(item);
             offset += jlength;
@@ -166,15 +798,16 @@ impl<'a> Selector<'a> {
             items.push_back(Item::Container(current));
             return;
         }
+        let jentries_addr = rest.as_ptr() as usize;
         let (rest, val_jentries) = decode_jentries(rest, length).unwrap();
         let mut offset = 0;
-        for (jty, jlength) in val_jentries.iter() {
+        for (i, (jty, jlength)) in val_jentries.iter().enumerate() {
             let val = &rest[offset..offset + jlength];
             let item = if *jty == CONTAINER_TAG {
                 Item::Container(val)
             } else {
                 let buf = Self::build_scalar_buf(*jty, *jlength, val);
-                Item::Scalar(buf)
+                Item::Scalar(buf, jentries_addr + 4 * i)
             };
             items.push_back(item);
             offset += jlength;
@@ -188,6 +821,7 @@ impl<'a> Selector<'a> {
             return;
         }
         let (rest, key_jentries) = decode_jentries(rest, length).unwrap();
+        let jentries_addr = rest.as_ptr() as usize;
         let (rest, val_jentries) = decode_jentries(rest, length).unwrap();
         let mut idx = 0;
         let mut offset = 0;
@@ -219,7 +853,7 @@ impl<'a> Selector<'a> {
                 Item::Container(val)
             } else {
                 let buf = Self::build_scalar_buf(*jty, *jlength, val);
-                Item::Scalar(buf)
+                Item::Scalar(buf, jentries_addr + 4 * i)
             };
             items.push_back(item);
             break;
@@ -250,11 +884,23 @@ impl<'a> Selector<'a> {
                         val_indices.append(&mut idxes);
                     }
                 }
+                ArrayIndex::StepSlice { start, end, step } => {
+                    val_indices.extend(Self::convert_step_slice(
+                        start.as_ref(),
+                        end.as_ref(),
+                        *step,
+                        length as i32,
+                    ));
+                }
             }
         }
         if val_indices.is_empty() {
             return;
         }
+        if self.options.order == ResultOrder::Document {
+            val_indices.sort_unstable();
+        }
+        let jentries_addr = rest.as_ptr() as usize;
         let (rest, jentries) = decode_jentries(rest, length).unwrap();
         let mut offset = 0;
         let mut offsets = Vec::with_capacity(jentries.len());
@@ -270,12 +916,117 @@ impl<'a> Selector<'a> {
                 Item::Container(val)
             } else {
                 let buf = Self::build_scalar_buf(jty, jlength, val);
-                Item::Scalar(buf)
+                Item::Scalar(buf, jentries_addr + 4 * i)
             };
             items.push_back(item);
         }
     }
 
+    // Replaces the items with the values computed by the item method in lax mode,
+    // the items the method can't be applied to are removed.
+    fn apply_methods(&'a self, root: &'a [u8], method: ItemMethod, items: &mut VecDeque<Item<'a>>) {
+        let len = items.len();
+        for _ in 0..len {
+            let item = items.pop_front().unwrap();
+            let _ = self.apply_method(root, method, &item, false, items);
+        }
+    }
+
+    // Pushes the values computed by the item method from the value of the item, the identity
+    // of the item is kept, except for `keyvalue()`, whose pairs are identified by their keys.
+    // Returns the error in strict mode if the method can't be applied.
+    fn apply_method(
+        &'a self,
+        root: &'a [u8],
+        method: ItemMethod,
+        item: &Item<'a>,
+        strict: bool,
+        items: &mut VecDeque<Item<'a>>,
+    ) -> Result<(), PathErrorCode> {
+        let (val, id) = match item {
+            Item::Container(val) => (*val, val.as_ptr() as usize),
+            Item::Scalar(val, addr) => (val.as_slice(), *addr),
+        };
+        let buf = match method {
+            ItemMethod::Type => {
+                let name = type_name(val);
+                Self::build_scalar_buf(STRING_TAG, name.len(), name.as_bytes())
+            }
+            ItemMethod::Size => {
+                let size = match decode_header(val) {
+                    Ok((_, (ARRAY_CONTAINER_TAG, length))) => length,
+                    _ if strict => return Err(PathErrorCode::ArrayExpected),
+                    _ => 1,
+                };
+                Value::Number(Number::UInt64(size as u64)).to_vec()
+            }
+            ItemMethod::Double | ItemMethod::Number => {
+                let number = to_number(val, method).ok_or(PathErrorCode::NumberExpected)?;
+                Value::Number(number).to_vec()
+            }
+            ItemMethod::Abs | ItemMethod::Floor | ItemMethod::Ceiling => {
+                let Some(PathValue::Number(number)) = decode_scalar_value(val) else {
+                    return Err(PathErrorCode::NumberExpected);
+                };
+                Value::Number(round_number(number, method)).to_vec()
+            }
+            ItemMethod::KeyValue => {
+                let reader = match ContainerReader::new(val) {
+                    Ok(reader) if reader.kind() == ContainerKind::Object => reader,
+                    _ => return Err(PathErrorCode::ObjectExpected),
+                };
+                let object_id = Value::Number(Number::UInt64(self.object_id(root, id))).to_vec();
+                for i in 0..reader.len() {
+                    let (Some(key), Some((entry, data))) = (reader.key(i), reader.value(i)) else {
+                        break;
+                    };
+                    let mut writer = ContainerWriter::object();
+                    let mut buf = Vec::new();
+                    writer
+                        .push_key("id")
+                        .and_then(|_| writer.push_jsonb(&object_id))
+                        .and_then(|_| writer.push_key("key"))
+                        .and_then(|_| writer.push_value(EntryKind::String, key.as_bytes()))
+                        .and_then(|_| writer.push_key("value"))
+                        .and_then(|_| writer.push_value(entry.kind, data))
+                        .and_then(|_| writer.finish(&mut buf))
+                        .map_err(|_| PathErrorCode::ObjectExpected)?;
+                    // The pair is identified by the address of the `JEntry` of its key.
+                    items.push_back(Item::Scalar(buf, id + 4 * (i + 1)));
+                }
+                return Ok(());
+            }
+        };
+        items.push_back(Item::Scalar(buf, id));
+        Ok(())
+    }
+
+    // The `id` of the pairs of the object returned by `keyvalue()`, like Postgres, it is the offset
+    // of the object in the document, or the offset in the variables plus 10000000000.
+    fn object_id(&self, root: &[u8], addr: usize) -> u64 {
+        let offset = |buf: &[u8]| {
+            let start = buf.as_ptr() as usize;
+            (start..start + buf.len())
+                .contains(&addr)
+                .then(|| (addr - start) as u64)
+        };
+        if let Some(offset) = offset(root) {
+            offset
+        } else if let Some(offset) = self.vars.and_then(offset) {
+            10_000_000_000 + offset
+        } else {
+            0
+        }
+    }
+
+    // The containers built by the item methods, like the pairs of `keyvalue()`, are owned by
+    // their items, so the values selected from them are copied, the identity of a value is
+    // its offset in the container from the identity of the container.
+    fn owned_item(buf: &[u8], id: usize, val: &Item<'_>) -> Item<'a> {
+        let offset = val.id().1 - buf.as_ptr() as usize;
+        Item::Scalar(val.to_vec(), id + offset)
+    }
+
     fn build_scalar_buf(jty: u32, jlength: usize, val: &'a [u8]) -> Vec<u8> {
         let mut buf = Vec::with_capacity(8 + jlength);
         buf.write_u32::<BigEndian>(SCALAR_CONTAINER_TAG).unwrap();
@@ -285,12 +1036,19 @@ impl<'a> Selector<'a> {
         buf
     }
 
-    // check and convert index to Array index.
-    fn convert_index(index: &Index, length: i32) -> Option<usize> {
-        let idx = match index {
+    // resolve `last` of the index to the Array index, which may be out of bounds.
+    fn resolve_index(index: &Index, length: i32) -> i32 {
+        match index {
+            // A negative index counts from the end, `-1` is the last element.
+            Index::Index(idx) if *idx < 0 => length + *idx,
             Index::Index(idx) => *idx,
             Index::LastIndex(idx) => length + *idx - 1,
-        };
+        }
+    }
+
+    // check and convert index to Array index.
+    fn convert_index(index: &Index, length: i32) -> Option<usize> {
+        let idx = Self::resolve_index(index, length);
         if idx >= 0 && idx < length {
             Some(idx as usize)
         } else {
@@ -300,14 +1058,8 @@ impl<'a> Selector<'a> {
 
     // check and convert slice to Array indices.
     fn convert_slice(start: &Index, end: &Index, length: i32) -> Option<Vec<usize>> {
-        let start = match start {
-            Index::Index(idx) => *idx,
-            Index::LastIndex(idx) => length + *idx - 1,
-        };
-        let end = match end {
-            Index::Index(idx) => *idx,
-            Index::LastIndex(idx) => length + *idx - 1,
-        };
+        let start = Self::resolve_index(start, length);
+        let end = Self::resolve_index(end, length);
         if start > end || start >= length || end < 0 {
             None
         } else {
@@ -321,6 +1073,36 @@ impl<'a> Selector<'a> {
         }
     }
 
+    // Convert the slice with a step to Array indices in the order of the step, like Python,
+    // the start and the end are clamped to the array, a zero step selects no element.
+    fn convert_step_slice(
+        start: Option<&Index>,
+        end: Option<&Index>,
+        step: i32,
+        length: i32,
+    ) -> Vec<usize> {
+        let resolve = |index: Option<&Index>, default: i32, min: i32, max: i32| {
+            index.map_or(default, |index| {
+                Self::resolve_index(index, length).clamp(min, max)
+            })
+        };
+        let mut indices = Vec::new();
+        if step > 0 {
+            let start = resolve(start, 0, 0, length);
+            let end = resolve(end, length, 0, length);
+            indices.extend((start..end).step_by(step as usize).map(|i| i as usize));
+        } else if step < 0 {
+            let start = resolve(start, length - 1, -1, length - 1);
+            let end = resolve(end, -1, -1, length - 1);
+            let mut i = start;
+            while i > end {
+                indices.push(i as usize);
+                i += step;
+            }
+        }
+        indices
+    }
+
     fn filter_expr(&'a self, root: &'a [u8], current: &'a [u8], expr: &Expr<'a>) -> bool {
         match expr {
             Expr::BinaryOp { op, left, right } => match op {
@@ -334,16 +1116,159 @@ impl<'a> Selector<'a> {
                     let rhs = self.filter_expr(root, current, right);
                     lhs && rhs
                 }
+                // A number is not a boolean.
+                _ if op.is_arithmetic() => false,
                 _ => {
                     let lhs = self.convert_expr_val(root, current, *left.clone());
                     let rhs = self.convert_expr_val(root, current, *right.clone());
                     self.compare(op, &lhs, &rhs)
                 }
             },
+            Expr::FilterFunc(FilterFunc::Exists(paths)) => {
+                !self.select_expr_paths(root, current, paths).is_empty()
+            }
+            Expr::FilterFunc(FilterFunc::LikeRegex {
+                paths,
+                pattern,
+                flags,
+            }) => self.like_regex(root, current, paths, pattern, flags.as_deref()),
+            Expr::FilterFunc(FilterFunc::StartsWith { paths, prefix }) => self
+                .select_expr_paths(root, current, paths)
+                .iter()
+                .any(|item| {
+                    let val = match item {
+                        Item::Container(val) => val,
+                        Item::Scalar(val, _) => val.as_slice(),
+                    };
+                    string_starts_with(val, prefix)
+                }),
             _ => todo!(),
         }
     }
 
+    /// Evaluates the path of a predicate check expression, `$ ? (<predicate>)`, like the
+    /// Postgres `@@` operator. Returns `None` if the result of the predicate is unknown,
+    /// like a comparison of the values of different types, or the path is not a predicate.
+    pub(crate) fn predicate(&'a self, value: &'a [u8]) -> Option<bool> {
+        match self.json_path.paths.as_slice() {
+            [Path::Root, Path::FilterExpr(expr)] => self.predicate_expr(value, value, expr),
+            _ => None,
+        }
+    }
+
+    // The three-valued logic of SQL/JSON, the unknown result is `None`.
+    fn predicate_expr(
+        &'a self,
+        root: &'a [u8],
+        current: &'a [u8],
+        expr: &Expr<'a>,
+    ) -> Option<bool> {
+        match expr {
+            Expr::BinaryOp { op, left, right } => match op {
+                BinaryOperator::Or => {
+                    let lhs = self.predicate_expr(root, current, left);
+                    let rhs = self.predicate_expr(root, current, right);
+                    match (lhs, rhs) {
+                        (Some(true), _) | (_, Some(true)) => Some(true),
+                        (Some(false), Some(false)) => Some(false),
+                        _ => None,
+                    }
+                }
+                BinaryOperator::And => {
+                    let lhs = self.predicate_expr(root, current, left);
+                    let rhs = self.predicate_expr(root, current, right);
+                    match (lhs, rhs) {
+                        (Some(false), _) | (_, Some(false)) => Some(false),
+                        (Some(true), Some(true)) => Some(true),
+                        _ => None,
+                    }
+                }
+                _ if op.is_arithmetic() => None,
+                _ => {
+                    let lhs = self.predicate_operand(root, current, left)?;
+                    let rhs = self.predicate_operand(root, current, right)?;
+                    // Like the lax mode of Postgres, the result is true if any pair
+                    // of the values is true, even if other pairs are unknown.
+                    let mut unknown = false;
+                    for lhs in lhs.iter() {
+                        for rhs in rhs.iter() {
+                            match self.compare_value_opt(op, lhs.clone(), rhs.clone()) {
+                                Some(true) => return Some(true),
+                                Some(false) => {}
+                                None => unknown = true,
+                            }
+                        }
+                    }
+                    if unknown {
+                        None
+                    } else {
+                        Some(false)
+                    }
+                }
+            },
+            Expr::FilterFunc(FilterFunc::Exists(paths)) => {
+                Some(!self.select_expr_paths(root, current, paths).is_empty())
+            }
+            Expr::FilterFunc(
+                FilterFunc::LikeRegex { paths, .. } | FilterFunc::StartsWith { paths, .. },
+            ) => {
+                if self.filter_expr(root, current, expr) {
+                    return Some(true);
+                }
+                // The values that are not strings can't be matched.
+                let all_strings = self
+                    .select_expr_paths(root, current, paths)
+                    .iter()
+                    .all(|item| {
+                        let val = match item {
+                            Item::Container(val) => val,
+                            Item::Scalar(val, _) => val.as_slice(),
+                        };
+                        matches!(decode_scalar_value(val), Some(PathValue::String(_)))
+                    });
+                if all_strings {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // The values of an operand of a comparison in a predicate check expression,
+    // returns `None` if the result is unknown, like a container value
+    // or an arithmetic expression that is not a single number.
+    fn predicate_operand(
+        &'a self,
+        root: &'a [u8],
+        current: &'a [u8],
+        expr: &Expr<'a>,
+    ) -> Option<Vec<PathValue<'a>>> {
+        match expr {
+            Expr::Value(value) => Some(vec![*value.clone()]),
+            Expr::Paths(paths) => self
+                .select_expr_paths(root, current, paths)
+                .iter()
+                .map(|item| {
+                    let val = match item {
+                        Item::Container(val) => val,
+                        Item::Scalar(val, _) => val.as_slice(),
+                    };
+                    decode_scalar_value(val)
+                })
+                .collect(),
+            Expr::BinaryOp { op, left, right } if op.is_arithmetic() => {
+                let lhs = self.predicate_operand(root, current, left)?;
+                let rhs = self.predicate_operand(root, current, right)?;
+                let lhs = single_number(ExprValue::Values(lhs))?;
+                let rhs = single_number(ExprValue::Values(rhs))?;
+                arithmetic(op.clone(), lhs, rhs).map(|number| vec![PathValue::Number(number)])
+            }
+            _ => None,
+        }
+    }
+
     fn convert_expr_val(
         &'a self,
         root: &'a [u8],
@@ -354,69 +1279,134 @@ impl<'a> Selector<'a> {
             Expr::Value(value) => ExprValue::Value(value.clone()),
             Expr::Paths(paths) => {
                 // get value from path and convert to `ExprValue`.
-                let mut items = VecDeque::new();
-                if let Some(Path::Current) = paths.get(0) {
-                    items.push_back(Item::Container(current));
-                } else {
-                    items.push_back(Item::Container(root));
-                }
-
-                for path in paths.iter().skip(1) {
-                    match path {
-                        &Path::Root | &Path::Current | &Path::FilterExpr(_) => unreachable!(),
-                        _ => {
-                            let len = items.len();
-                            for _ in 0..len {
-                                let item = items.pop_front().unwrap();
-                                match item {
-                                    Item::Container(current) => {
-                                        self.select_path(current, path, &mut items);
-                                    }
-                                    Item::Scalar(_) => {
-                                        // In lax mode, bracket wildcard allow Scalar value.
-                                        if path == &Path::BracketWildcard {
-                                            items.push_back(item);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+                let mut items = self.select_expr_paths(root, current, &paths);
                 let mut values = Vec::with_capacity(items.len());
                 while let Some(item) = items.pop_front() {
                     let val = match item {
                         Item::Container(val) => val,
-                        Item::Scalar(ref val) => val.as_slice(),
+                        Item::Scalar(ref val, _) => val.as_slice(),
                     };
-                    let (rest, (ty, _)) = decode_header(val).unwrap();
-                    if ty == SCALAR_CONTAINER_TAG {
-                        let (rest, (jty, jlength)) = decode_jentry(rest).unwrap();
-                        let value = match jty {
-                            NULL_TAG => PathValue::Null,
-                            TRUE_TAG => PathValue::Boolean(true),
-                            FALSE_TAG => PathValue::Boolean(false),
-                            NUMBER_TAG => {
-                                let n = Number::decode(&rest[0..jlength]);
-                                PathValue::Number(n)
-                            }
-                            STRING_TAG => {
-                                let v = &rest[0..jlength];
-                                PathValue::String(Cow::Owned(unsafe {
-                                    String::from_utf8_unchecked(v.to_vec())
-                                }))
-                            }
-                            _ => unreachable!(),
-                        };
+                    if let Some(value) = decode_scalar_value(val) {
                         values.push(value);
                     }
                 }
                 ExprValue::Values(values)
             }
+            // Like SQL/JSON, the operands must be single numbers, otherwise the result is
+            // an error, which has no values, so the comparison is false.
+            Expr::BinaryOp { op, left, right } if op.is_arithmetic() => {
+                let lhs = self.convert_expr_val(root, current, *left);
+                let rhs = self.convert_expr_val(root, current, *right);
+                let value = match (single_number(lhs), single_number(rhs)) {
+                    (Some(lhs), Some(rhs)) => arithmetic(op, lhs, rhs),
+                    _ => None,
+                };
+                ExprValue::Values(value.map(PathValue::Number).into_iter().collect())
+            }
             _ => unreachable!(),
         }
     }
 
+    // Select the values of the JSON Path chain in the filter expression,
+    // the chain starts from the root, the current value or a variable.
+    fn select_expr_paths(
+        &'a self,
+        root: &'a [u8],
+        current: &'a [u8],
+        paths: &[Path<'a>],
+    ) -> VecDeque<Item<'a>> {
+        let mut items = VecDeque::new();
+        match paths.first() {
+            Some(Path::Current) => items.push_back(Item::Container(current)),
+            Some(Path::Variable(name)) => {
+                if let Some(vars) = self.vars {
+                    self.select_by_name(vars, name, &mut items);
+                }
+            }
+            _ => items.push_back(Item::Container(root)),
+        }
+
+        for path in paths.iter().skip(1) {
+            match path {
+                &Path::Root | &Path::Current | &Path::Variable(_) | &Path::FilterExpr(_) => {
+                    unreachable!()
+                }
+                &Path::RecursiveDescent => self.select_descendants(&mut items, true),
+                &Path::RecursiveWildcard => self.select_descendants(&mut items, false),
+                &Path::Method(method) => self.apply_methods(root, method, &mut items),
+                _ => {
+                    let len = items.len();
+                    for _ in 0..len {
+                        let item = items.pop_front().unwrap();
+                        match item {
+                            Item::Container(current) => {
+                                self.select_path(current, path, &mut items);
+                            }
+                            Item::Scalar(ref buf, id) if is_container(buf) => {
+                                let mut selected = VecDeque::new();
+                                self.select_path(buf, path, &mut selected);
+                                items.extend(
+                                    selected.iter().map(|val| Self::owned_item(buf, id, val)),
+                                );
+                            }
+                            Item::Scalar(..) => {
+                                // In lax mode, bracket wildcard allow Scalar value.
+                                if path == &Path::BracketWildcard {
+                                    items.push_back(item);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        items
+    }
+
+    // Returns true if a string value selected by the paths matches the pattern,
+    // the other values never match.
+    #[cfg(feature = "regex")]
+    fn like_regex(
+        &'a self,
+        root: &'a [u8],
+        current: &'a [u8],
+        paths: &[Path<'a>],
+        pattern: &str,
+        flags: Option<&str>,
+    ) -> bool {
+        let flags = flags.unwrap_or_default();
+        // The patterns of the paths that are not parsed may be invalid.
+        let Some((_, _, regex)) = self
+            .regexes
+            .iter()
+            .find(|(p, f, _)| p == pattern && f == flags)
+        else {
+            return false;
+        };
+        self.select_expr_paths(root, current, paths)
+            .iter()
+            .any(|item| {
+                let val = match item {
+                    Item::Container(val) => val,
+                    Item::Scalar(val, _) => val.as_slice(),
+                };
+                matches!(decode_scalar_value(val), Some(PathValue::String(s)) if regex.is_match(&s))
+            })
+    }
+
+    // The patterns are not parsed without the `regex` feature.
+    #[cfg(not(feature = "regex"))]
+    fn like_regex(
+        &'a self,
+        _root: &'a [u8],
+        _current: &'a [u8],
+        _paths: &[Path<'a>],
+        _pattern: &str,
+        _flags: Option<&str>,
+    ) -> bool {
+        false
+    }
+
     fn compare(&'a self, op: &BinaryOperator, lhs: &ExprValue<'a>, rhs: &ExprValue<'a>) -> bool {
         match (lhs, rhs) {
             (ExprValue::Value(lhs), ExprValue::Value(rhs)) => {
@@ -457,21 +1447,117 @@ impl<'a> Selector<'a> {
         lhs: PathValue<'a>,
         rhs: PathValue<'a>,
     ) -> bool {
-        let order = lhs.partial_cmp(&rhs);
-        if let Some(order) = order {
-            match op {
-                BinaryOperator::Eq => order == Ordering::Equal,
-                BinaryOperator::NotEq => order != Ordering::Equal,
-                BinaryOperator::Lt => order == Ordering::Less,
-                BinaryOperator::Lte => order == Ordering::Equal || order == Ordering::Less,
-                BinaryOperator::Gt => order == Ordering::Greater,
-                BinaryOperator::Gte => order == Ordering::Equal || order == Ordering::Greater,
-                _ => unreachable!(),
+        self.compare_value_opt(op, lhs, rhs).unwrap_or(false)
+    }
+
+    // Returns `None` if the values are not comparable.
+    fn compare_value_opt(
+        &'a self,
+        op: &BinaryOperator,
+        lhs: PathValue<'a>,
+        rhs: PathValue<'a>,
+    ) -> Option<bool> {
+        // Like SQL/JSON, the values of different types are not comparable,
+        // except that `null` is only equal to `null`.
+        let order = match (&lhs, &rhs) {
+            (PathValue::Null, _) | (_, PathValue::Null) if lhs != rhs => {
+                return Some(*op == BinaryOperator::NotEq);
             }
-        } else {
-            false
-        }
+            (PathValue::Null, PathValue::Null)
+            | (PathValue::Boolean(_), PathValue::Boolean(_))
+            | (PathValue::Number(_), PathValue::Number(_))
+            | (PathValue::String(_), PathValue::String(_)) => lhs.partial_cmp(&rhs),
+            _ => None,
+        };
+        let order = order?;
+        let result = match op {
+            BinaryOperator::Eq => order == Ordering::Equal,
+            BinaryOperator::NotEq => order != Ordering::Equal,
+            BinaryOperator::Lt => order == Ordering::Less,
+            BinaryOperator::Lte => order == Ordering::Equal || order == Ordering::Less,
+            BinaryOperator::Gt => order == Ordering::Greater,
+            BinaryOperator::Gte => order == Ordering::Equal || order == Ordering::Greater,
+            _ => unreachable!(),
+        };
+        Some(result)
+    }
+}
+
+// Compile the pattern of a `like_regex` predicate with the SQL/JSON flags,
+// returns `None` if the pattern or a flag is invalid.
+#[cfg(feature = "regex")]
+pub(crate) fn build_regex(pattern: &str, flags: &str) -> Option<Regex> {
+    let pattern = if flags.contains('q') {
+        Cow::Owned(regex::escape(pattern))
+    } else {
+        Cow::Borrowed(pattern)
+    };
+    let mut builder = RegexBuilder::new(&pattern);
+    for flag in flags.chars() {
+        match flag {
+            'i' => builder.case_insensitive(true),
+            's' => builder.dot_matches_new_line(true),
+            'm' => builder.multi_line(true),
+            'x' => builder.ignore_whitespace(true),
+            'q' => &mut builder,
+            _ => return None,
+        };
+    }
+    builder.build().ok()
+}
+
+// Append the member accessor of the name to the path,
+// the names that are not identifiers are quoted.
+fn member_path(path: &str, name: &str) -> String {
+    let is_ident = matches!(name.chars().next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_ident {
+        format!("{}.{}", path, name)
+    } else {
+        format!("{}[{:?}]", path, name)
+    }
+}
+
+// Returns true if the scalar `JSONB` value is a string that starts with the prefix,
+// the string is compared as bytes without being decoded.
+fn string_starts_with(val: &[u8], prefix: &str) -> bool {
+    let Ok((rest, (SCALAR_CONTAINER_TAG, _))) = decode_header(val) else {
+        return false;
+    };
+    match decode_jentry(rest) {
+        Ok((rest, (STRING_TAG, jlength))) => rest
+            .get(..jlength)
+            .map_or(false, |s| s.starts_with(prefix.as_bytes())),
+        _ => false,
+    }
+}
+
+// Decode the scalar `JSONB` value as `PathValue` to compare with literal values,
+// returns `None` for containers and extension values.
+pub(crate) fn decode_scalar_value(val: &[u8]) -> Option<PathValue<'static>> {
+    let (rest, (ty, _)) = decode_header(val).ok()?;
+    if ty != SCALAR_CONTAINER_TAG {
+        return None;
     }
+    let (rest, (jty, jlength)) = decode_jentry(rest).ok()?;
+    let value = match jty {
+        NULL_TAG => PathValue::Null,
+        TRUE_TAG => PathValue::Boolean(true),
+        FALSE_TAG => PathValue::Boolean(false),
+        NUMBER_TAG => {
+            let n = Number::decode(&rest[0..jlength]);
+            PathValue::Number(n)
+        }
+        STRING_TAG => {
+            let v = &rest[0..jlength];
+            PathValue::String(Cow::Owned(unsafe {
+                String::from_utf8_unchecked(v.to_vec())
+            }))
+        }
+        // extension values can't be compared with literal values.
+        _ => return None,
+    };
+    Some(value)
 }
 
 fn decode_header(input: &[u8]) -> IResult<&[u8], (u32, usize)> {
@@ -483,6 +1569,127 @@ fn decode_header(input: &[u8]) -> IResult<&[u8], (u32, usize)> {
     })(input)
 }
 
+// The type of the value returned by the `type()` item method.
+fn type_name(val: &[u8]) -> &'static str {
+    let Ok((rest, (ty, _))) = decode_header(val) else {
+        return "null";
+    };
+    match ty {
+        ARRAY_CONTAINER_TAG => "array",
+        OBJECT_CONTAINER_TAG => "object",
+        _ => match decode_jentry(rest).map(|(_, (jty, _))| jty) {
+            Ok(STRING_TAG) => "string",
+            Ok(NUMBER_TAG) => "number",
+            Ok(TRUE_TAG) | Ok(FALSE_TAG) => "boolean",
+            Ok(EXTENSION_TAG) => "extension",
+            _ => "null",
+        },
+    }
+}
+
+// The number converted by the `double()` and `number()` item methods from a number,
+// or from a string of a number, the infinite and NaN floats are rejected.
+fn to_number(val: &[u8], method: ItemMethod) -> Option<Number> {
+    let number = match decode_scalar_value(val)? {
+        PathValue::Number(number) => number,
+        PathValue::String(s) => match parse_value(s.trim().as_bytes()) {
+            Ok(Value::Number(number)) => number,
+            _ => Number::Float64(s.trim().parse().ok()?),
+        },
+        _ => return None,
+    };
+    let number = match method {
+        ItemMethod::Double => Number::Float64(number.as_f64()?),
+        _ => number,
+    };
+    match number {
+        Number::Float64(f) if !f.is_finite() => None,
+        number => Some(number),
+    }
+}
+
+// The number computed by the `abs()`, `floor()` and `ceiling()` item methods,
+// the integers are kept as they are, except for the absolute value of the negative ones.
+fn round_number(number: Number, method: ItemMethod) -> Number {
+    match (number, method) {
+        (Number::Int64(v), ItemMethod::Abs) if v < 0 => Number::UInt64(v.unsigned_abs()),
+        (Number::Float64(v), ItemMethod::Abs) => Number::Float64(v.abs()),
+        (Number::Float64(v), ItemMethod::Floor) => Number::Float64(v.floor()),
+        // Adding zero turns the `-0` of a negative fraction into `0`.
+        (Number::Float64(v), ItemMethod::Ceiling) => Number::Float64(v.ceil() + 0.0),
+        (number, _) => number,
+    }
+}
+
+fn single_number(value: ExprValue<'_>) -> Option<Number> {
+    match value {
+        ExprValue::Value(value) => match *value {
+            PathValue::Number(number) => Some(number),
+            _ => None,
+        },
+        ExprValue::Values(mut values) if values.len() == 1 => match values.pop() {
+            Some(PathValue::Number(number)) => Some(number),
+            _ => None,
+        },
+        ExprValue::Values(_) => None,
+    }
+}
+
+// The arithmetic of SQL/JSON numbers, the integers stay integers unless the result overflows
+// or, for the division, has a fraction, then the result is a float. The division by zero
+// and the infinite or NaN results are errors.
+fn arithmetic(op: BinaryOperator, lhs: Number, rhs: Number) -> Option<Number> {
+    let integer = |number: &Number| match number {
+        Number::Int64(v) => Some(*v as i128),
+        Number::UInt64(v) => Some(*v as i128),
+        Number::Float64(_) => None,
+    };
+    if let (Some(l), Some(r)) = (integer(&lhs), integer(&rhs)) {
+        let result = match op {
+            BinaryOperator::Add => l.checked_add(r),
+            BinaryOperator::Sub => l.checked_sub(r),
+            BinaryOperator::Mul => l.checked_mul(r),
+            BinaryOperator::Div if r == 0 => return None,
+            BinaryOperator::Div if l % r == 0 => Some(l / r),
+            BinaryOperator::Div => None,
+            BinaryOperator::Mod => l.checked_rem(r),
+            _ => return None,
+        };
+        if let Some(result) = result {
+            if let Ok(v) = i64::try_from(result) {
+                return Some(Number::Int64(v));
+            }
+            if let Ok(v) = u64::try_from(result) {
+                return Some(Number::UInt64(v));
+            }
+        }
+    }
+    let (l, r) = (lhs.as_f64()?, rhs.as_f64()?);
+    let result = match op {
+        BinaryOperator::Add => l + r,
+        BinaryOperator::Sub => l - r,
+        BinaryOperator::Mul => l * r,
+        BinaryOperator::Div | BinaryOperator::Mod if r == 0.0 => return None,
+        BinaryOperator::Div => l / r,
+        BinaryOperator::Mod => l % r,
+        _ => return None,
+    };
+    result.is_finite().then_some(Number::Float64(result))
+}
+
+fn is_container(value: &[u8]) -> bool {
+    matches!(
+        container_type(value),
+        ARRAY_CONTAINER_TAG | OBJECT_CONTAINER_TAG
+    )
+}
+
+fn container_type(value: &[u8]) -> u32 {
+    decode_header(value)
+        .map(|(_, (ty, _))| ty)
+        .unwrap_or_default()
+}
+
 fn decode_jentry(input: &[u8]) -> IResult<&[u8], (u32, usize)> {
     map(be_u32, |jentry| {
         (
@@ -0,0 +1,216 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Traversal of the JSON Path AST, to analyze the paths before evaluating them,
+//! such as collecting the referenced field names, and to rewrite them,
+//! such as folding the constant filter expressions.
+//!
+//! A [`Visitor`] walks the AST by reference and a [`Fold`] rebuilds it by value.
+//! The default methods walk into the nested nodes through the free functions of the same name,
+//! an implementation overrides the methods of the nodes it is interested in, and calls
+//! the free function to continue into the nested nodes.
+//!
+//! ```
+//! use jsonb::jsonpath::visitor::{self, Fold, Visitor};
+//! use jsonb::jsonpath::{parse_json_path, BinaryOperator, Expr, Path, PathValue};
+//!
+//! #[derive(Default)]
+//! struct Fields(Vec<String>);
+//!
+//! impl<'a> Visitor<'a> for Fields {
+//!     fn visit_path(&mut self, path: &Path<'a>) {
+//!         if let Path::DotField(name) = path {
+//!             self.0.push(name.to_string());
+//!         }
+//!         visitor::visit_path(self, path);
+//!     }
+//! }
+//!
+//! // Fold `1 == 1` to `true`.
+//! struct ConstantFold;
+//!
+//! impl<'a> Fold<'a> for ConstantFold {
+//!     fn fold_expr(&mut self, expr: Expr<'a>) -> Expr<'a> {
+//!         match visitor::fold_expr(self, expr) {
+//!             Expr::BinaryOp { op: BinaryOperator::Eq, left, right } => match (*left, *right) {
+//!                 (Expr::Value(left), Expr::Value(right)) => {
+//!                     Expr::Value(Box::new(PathValue::Boolean(left == right)))
+//!                 }
+//!                 (left, right) => Expr::BinaryOp {
+//!                     op: BinaryOperator::Eq,
+//!                     left: Box::new(left),
+//!                     right: Box::new(right),
+//!                 },
+//!             },
+//!             expr => expr,
+//!         }
+//!     }
+//! }
+//!
+//! let json_path = parse_json_path(b"$.a[*]?(@.b > 1 && 1 == 1).c").unwrap();
+//! let mut fields = Fields::default();
+//! fields.visit_json_path(&json_path);
+//! assert_eq!(fields.0, vec!["a", "b", "c"]);
+//!
+//! let json_path = ConstantFold.fold_json_path(json_path);
+//! assert_eq!(json_path.to_string(), "$.a[*]?(@.b > 1 && true).c");
+//! ```
+
+use super::path::Expr;
+use super::path::FilterFunc;
+use super::path::JsonPath;
+use super::path::Path;
+use super::path::PathValue;
+
+/// Walks the nodes of a JSON Path by reference.
+pub trait Visitor<'a> {
+    fn visit_json_path(&mut self, json_path: &JsonPath<'a>) {
+        visit_json_path(self, json_path)
+    }
+
+    fn visit_path(&mut self, path: &Path<'a>) {
+        visit_path(self, path)
+    }
+
+    fn visit_expr(&mut self, expr: &Expr<'a>) {
+        visit_expr(self, expr)
+    }
+
+    fn visit_value(&mut self, _value: &PathValue<'a>) {}
+}
+
+/// Visits each path of the chain.
+pub fn visit_json_path<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, json_path: &JsonPath<'a>) {
+    for path in &json_path.paths {
+        visitor.visit_path(path);
+    }
+}
+
+/// Visits the expression of a filter, other paths have no nested nodes.
+pub fn visit_path<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, path: &Path<'a>) {
+    if let Path::FilterExpr(expr) = path {
+        visitor.visit_expr(expr);
+    }
+}
+
+/// Visits the paths, the value, the operands, or the paths of the function of the expression.
+pub fn visit_expr<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, expr: &Expr<'a>) {
+    match expr {
+        Expr::Paths(paths) => {
+            for path in paths {
+                visitor.visit_path(path);
+            }
+        }
+        Expr::Value(value) => visitor.visit_value(value),
+        Expr::BinaryOp { left, right, .. } => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        }
+        Expr::FilterFunc(
+            FilterFunc::Exists(paths)
+            | FilterFunc::LikeRegex { paths, .. }
+            | FilterFunc::StartsWith { paths, .. },
+        ) => {
+            for path in paths {
+                visitor.visit_path(path);
+            }
+        }
+    }
+}
+
+/// Rebuilds the nodes of a JSON Path by value.
+pub trait Fold<'a> {
+    fn fold_json_path(&mut self, json_path: JsonPath<'a>) -> JsonPath<'a> {
+        fold_json_path(self, json_path)
+    }
+
+    fn fold_path(&mut self, path: Path<'a>) -> Path<'a> {
+        fold_path(self, path)
+    }
+
+    fn fold_expr(&mut self, expr: Expr<'a>) -> Expr<'a> {
+        fold_expr(self, expr)
+    }
+
+    fn fold_value(&mut self, value: PathValue<'a>) -> PathValue<'a> {
+        value
+    }
+}
+
+/// Folds each path of the chain.
+pub fn fold_json_path<'a, F: Fold<'a> + ?Sized>(
+    folder: &mut F,
+    json_path: JsonPath<'a>,
+) -> JsonPath<'a> {
+    JsonPath {
+        paths: json_path
+            .paths
+            .into_iter()
+            .map(|path| folder.fold_path(path))
+            .collect(),
+    }
+}
+
+/// Folds the expression of a filter, other paths are returned as they are.
+pub fn fold_path<'a, F: Fold<'a> + ?Sized>(folder: &mut F, path: Path<'a>) -> Path<'a> {
+    match path {
+        Path::FilterExpr(expr) => Path::FilterExpr(Box::new(folder.fold_expr(*expr))),
+        path => path,
+    }
+}
+
+/// Folds the paths, the value, the operands, or the paths of the function of the expression.
+pub fn fold_expr<'a, F: Fold<'a> + ?Sized>(folder: &mut F, expr: Expr<'a>) -> Expr<'a> {
+    match expr {
+        Expr::Paths(paths) => Expr::Paths(
+            paths
+                .into_iter()
+                .map(|path| folder.fold_path(path))
+                .collect(),
+        ),
+        Expr::Value(value) => Expr::Value(Box::new(folder.fold_value(*value))),
+        Expr::BinaryOp { op, left, right } => Expr::BinaryOp {
+            op,
+            left: Box::new(folder.fold_expr(*left)),
+            right: Box::new(folder.fold_expr(*right)),
+        },
+        Expr::FilterFunc(FilterFunc::Exists(paths)) => Expr::FilterFunc(FilterFunc::Exists(
+            paths
+                .into_iter()
+                .map(|path| folder.fold_path(path))
+                .collect(),
+        )),
+        Expr::FilterFunc(FilterFunc::LikeRegex {
+            paths,
+            pattern,
+            flags,
+        }) => Expr::FilterFunc(FilterFunc::LikeRegex {
+            paths: paths
+                .into_iter()
+                .map(|path| folder.fold_path(path))
+                .collect(),
+            pattern,
+            flags,
+        }),
+        Expr::FilterFunc(FilterFunc::StartsWith { paths, prefix }) => {
+            Expr::FilterFunc(FilterFunc::StartsWith {
+                paths: paths
+                    .into_iter()
+                    .map(|path| folder.fold_path(path))
+                    .collect(),
+                prefix,
+            })
+        }
+    }
+}
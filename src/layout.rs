@@ -0,0 +1,205 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Write;
+
+use super::constants::*;
+use super::error::Error;
+use super::jentry::JEntry;
+use super::number::Number;
+
+/// Dump the binary layout of the `JSONB` value, one line for each header, `JEntry` and data,
+/// prefixed with the offset in bytes. Nested containers are indented.
+///
+/// This is intended for debugging the stored buffers, the output format is not stable.
+///
+/// ```text
+/// 0x0000  header  array, 1 elements
+/// 0x0004  jentry  [0] number, 2 bytes
+/// 0x0008  data    [0] number 1
+/// ```
+pub fn dump_layout(value: &[u8]) -> Result<String, Error> {
+    let mut out = String::new();
+    dump_container(value, 0, 0, &mut out)?;
+    Ok(out)
+}
+
+fn read_u32(value: &[u8], offset: usize) -> Result<u32, Error> {
+    let bytes = value.get(offset..offset + 4).ok_or(Error::InvalidEOF)?;
+    Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn write_line(out: &mut String, offset: usize, depth: usize, kind: &str, desc: &str) {
+    let _ = writeln!(
+        out,
+        "{:#06x}  {:indent$}{:<6}  {}",
+        offset,
+        "",
+        kind,
+        desc,
+        indent = depth * 2
+    );
+}
+
+fn dump_container(value: &[u8], base: usize, depth: usize, out: &mut String) -> Result<(), Error> {
+    let header = read_u32(value, 0)?;
+    let len = (header & CONTAINER_HEADER_LEN_MASK) as usize;
+    let (desc, count, keys) = match header & CONTAINER_HEADER_TYPE_MASK {
+        SCALAR_CONTAINER_TAG => ("scalar".to_string(), 1, 0),
+        ARRAY_CONTAINER_TAG => (format!("array, {} elements", len), len, 0),
+        OBJECT_CONTAINER_TAG => (format!("object, {} fields", len), len * 2, len),
+        _ => return Err(Error::InvalidJsonbHeader),
+    };
+    write_line(out, base, depth, "header", &desc);
+
+    // Object keys are stored before the values, so the entries are named by their kind.
+    let name = |i: usize| {
+        if i < keys {
+            format!("key[{}]", i)
+        } else if keys > 0 {
+            format!("value[{}]", i - keys)
+        } else {
+            format!("[{}]", i)
+        }
+    };
+
+    let mut jentries = Vec::with_capacity(count.min(value.len() / 4));
+    for i in 0..count {
+        let offset = 4 + i * 4;
+        let jentry = JEntry::decode_jentry(read_u32(value, offset)?);
+        let desc = match jentry.type_code {
+            NULL_TAG => format!("{} null", name(i)),
+            TRUE_TAG => format!("{} true", name(i)),
+            FALSE_TAG => format!("{} false", name(i)),
+            STRING_TAG => format!("{} string, {} bytes", name(i), jentry.length),
+            NUMBER_TAG => format!("{} number, {} bytes", name(i), jentry.length),
+            CONTAINER_TAG => format!("{} container, {} bytes", name(i), jentry.length),
+            EXTENSION_TAG => format!("{} extension, {} bytes", name(i), jentry.length),
+            _ => return Err(Error::InvalidJsonbJEntry),
+        };
+        write_line(out, base + offset, depth, "jentry", &desc);
+        jentries.push(jentry);
+    }
+
+    let mut offset = 4 + count * 4;
+    for (i, jentry) in jentries.iter().enumerate() {
+        let length = jentry.length as usize;
+        let data = value
+            .get(offset..offset + length)
+            .ok_or(Error::InvalidEOF)?;
+        match jentry.type_code {
+            NULL_TAG | TRUE_TAG | FALSE_TAG => {}
+            STRING_TAG => {
+                let s = String::from_utf8_lossy(data);
+                write_line(
+                    out,
+                    base + offset,
+                    depth,
+                    "data",
+                    &format!("{} {:?}", name(i), s),
+                );
+            }
+            NUMBER_TAG => {
+                let desc = match decode_number(data) {
+                    Some(n) => format!("{} number {}", name(i), n),
+                    None => format!("{} invalid number {:02x?}", name(i), data),
+                };
+                write_line(out, base + offset, depth, "data", &desc);
+            }
+            CONTAINER_TAG => {
+                write_line(
+                    out,
+                    base + offset,
+                    depth,
+                    "data",
+                    &format!("{} container", name(i)),
+                );
+                dump_container(data, base + offset, depth + 1, out)?;
+            }
+            _ => {
+                let desc = match data.split_first() {
+                    Some((tag, payload)) => {
+                        format!("{} extension tag {}, {:02x?}", name(i), tag, payload)
+                    }
+                    None => format!("{} invalid extension", name(i)),
+                };
+                write_line(out, base + offset, depth, "data", &desc);
+            }
+        }
+        offset += length;
+    }
+    Ok(())
+}
+
+/// Check that the `JSONB` value is well-formed before it is decoded by the other functions,
+/// the headers and `JEntries` are in bounds, the strings are `UTF-8`,
+/// the numbers are encoded correctly and the object keys are strings.
+pub(crate) fn validate(value: &[u8]) -> Result<(), Error> {
+    let header = read_u32(value, 0)?;
+    let len = (header & CONTAINER_HEADER_LEN_MASK) as usize;
+    let (count, keys) = match header & CONTAINER_HEADER_TYPE_MASK {
+        SCALAR_CONTAINER_TAG => (1, 0),
+        ARRAY_CONTAINER_TAG => (len, 0),
+        OBJECT_CONTAINER_TAG => (len * 2, len),
+        _ => return Err(Error::InvalidJsonbHeader),
+    };
+    if count > value.len() / 4 {
+        return Err(Error::InvalidEOF);
+    }
+
+    let mut offset = 4 + count * 4;
+    for i in 0..count {
+        let jentry = JEntry::decode_jentry(read_u32(value, 4 + i * 4)?);
+        if i < keys && jentry.type_code != STRING_TAG {
+            return Err(Error::InvalidJsonbJEntry);
+        }
+        let length = jentry.length as usize;
+        let data = value
+            .get(offset..offset + length)
+            .ok_or(Error::InvalidEOF)?;
+        match jentry.type_code {
+            CONTAINER_TAG => validate(data)?,
+            type_code => validate_scalar(type_code, data)?,
+        }
+        offset += length;
+    }
+    Ok(())
+}
+
+/// Check that the data of a scalar `JEntry` can be decoded.
+pub(crate) fn validate_scalar(type_code: u32, data: &[u8]) -> Result<(), Error> {
+    match type_code {
+        NULL_TAG | TRUE_TAG | FALSE_TAG => {}
+        STRING_TAG => {
+            std::str::from_utf8(data)?;
+        }
+        NUMBER_TAG => {
+            decode_number(data).ok_or(Error::InvalidJsonbJEntry)?;
+        }
+        EXTENSION_TAG if !data.is_empty() => {}
+        _ => return Err(Error::InvalidJsonbJEntry),
+    }
+    Ok(())
+}
+
+// Checks the length of the encoded number before decoding, the buffer may be corrupted.
+fn decode_number(data: &[u8]) -> Option<Number> {
+    let valid = match (data.first()?, data.len()) {
+        (&NUMBER_ZERO | &NUMBER_NAN | &NUMBER_INF | &NUMBER_NEG_INF, 1) => true,
+        (&NUMBER_INT | &NUMBER_UINT, 2 | 3 | 5 | 9) => true,
+        (&NUMBER_FLOAT, 9) => true,
+        (_, _) => false,
+    };
+    valid.then(|| Number::decode(data))
+}
@@ -0,0 +1,254 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use crate::de::from_slice;
+use crate::encoding::write_scalar;
+use crate::encoding::ContainerKind;
+use crate::encoding::ContainerReader;
+use crate::encoding::ContainerWriter;
+use crate::encoding::EntryKind;
+use crate::error::Error;
+use crate::value::Value;
+
+/// A `JSONB` value whose nested values are decoded only when they are accessed.
+///
+/// A container is split into its encoded elements when an element is accessed or changed,
+/// the other elements are kept encoded and copied verbatim when the value is encoded again,
+/// so changing one field of a large document doesn't decode the untouched fields.
+///
+/// ```
+/// use jsonb::{LazyValue, Value};
+///
+/// let doc = jsonb::parse_value(br#"{"a":1,"b":{"c":[1,2,3]}}"#).unwrap().to_vec();
+/// let mut value = LazyValue::from_slice(&doc);
+/// value.insert("a".to_string(), Value::from(2)).unwrap();
+/// let mut buf = Vec::new();
+/// value.write_to_vec(&mut buf).unwrap();
+/// assert_eq!(jsonb::to_string(&buf), r#"{"a":2,"b":{"c":[1,2,3]}}"#);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum LazyValue<'a> {
+    /// An encoded value that is not decoded yet.
+    Raw(Cow<'a, [u8]>),
+    /// An array split into its elements.
+    Array(Vec<LazyValue<'a>>),
+    /// An object split into its fields.
+    Object(BTreeMap<String, LazyValue<'a>>),
+    /// A decoded value.
+    Value(Value<'a>),
+}
+
+impl<'a> LazyValue<'a> {
+    /// Wraps an encoded value without decoding it.
+    pub fn from_slice(value: &'a [u8]) -> LazyValue<'a> {
+        LazyValue::Raw(Cow::Borrowed(value))
+    }
+
+    /// Returns the value of the field with the key, the object is split into its fields.
+    /// Returns `None` if the value is not an object or doesn't have the key.
+    pub fn get_mut(&mut self, key: &str) -> Result<Option<&mut LazyValue<'a>>, Error> {
+        self.split()?;
+        match self {
+            LazyValue::Object(fields) => Ok(fields.get_mut(key)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the element at the index, the array is split into its elements.
+    /// Returns `None` if the value is not an array or the index is out of bounds.
+    pub fn index_mut(&mut self, index: usize) -> Result<Option<&mut LazyValue<'a>>, Error> {
+        self.split()?;
+        match self {
+            LazyValue::Array(elements) => Ok(elements.get_mut(index)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Inserts a field into the object, returns the old value of the field.
+    /// Returns [`Error::InvalidJsonType`] if the value is not an object.
+    pub fn insert(
+        &mut self,
+        key: String,
+        value: impl Into<LazyValue<'a>>,
+    ) -> Result<Option<LazyValue<'a>>, Error> {
+        self.split()?;
+        match self {
+            LazyValue::Object(fields) => Ok(fields.insert(key, value.into())),
+            _ => Err(Error::InvalidJsonType),
+        }
+    }
+
+    /// Removes a field from the object, returns the value of the field.
+    /// Returns [`Error::InvalidJsonType`] if the value is not an object.
+    pub fn remove(&mut self, key: &str) -> Result<Option<LazyValue<'a>>, Error> {
+        self.split()?;
+        match self {
+            LazyValue::Object(fields) => Ok(fields.remove(key)),
+            _ => Err(Error::InvalidJsonType),
+        }
+    }
+
+    /// Appends an element to the array.
+    /// Returns [`Error::InvalidJsonType`] if the value is not an array.
+    pub fn push(&mut self, value: impl Into<LazyValue<'a>>) -> Result<(), Error> {
+        self.split()?;
+        match self {
+            LazyValue::Array(elements) => {
+                elements.push(value.into());
+                Ok(())
+            }
+            _ => Err(Error::InvalidJsonType),
+        }
+    }
+
+    /// Decodes the whole value.
+    pub fn to_value(&self) -> Result<Value<'_>, Error> {
+        match self {
+            LazyValue::Raw(raw) => from_slice(raw),
+            LazyValue::Array(elements) => Ok(Value::Array(
+                elements
+                    .iter()
+                    .map(LazyValue::to_value)
+                    .collect::<Result<_, _>>()?,
+            )),
+            LazyValue::Object(fields) => Ok(Value::Object(
+                fields
+                    .iter()
+                    .map(|(key, val)| Ok((key.clone(), val.to_value()?)))
+                    .collect::<Result<_, Error>>()?,
+            )),
+            LazyValue::Value(value) => Ok(value.clone()),
+        }
+    }
+
+    /// Serialize the value into a byte stream, the encoded values are copied verbatim.
+    pub fn write_to_vec(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        match self {
+            LazyValue::Raw(raw) => buf.extend_from_slice(raw),
+            LazyValue::Array(elements) => {
+                let mut writer = ContainerWriter::array();
+                let mut element = Vec::new();
+                for val in elements {
+                    element.clear();
+                    val.write_to_vec(&mut element)?;
+                    writer.push_jsonb(&element)?;
+                }
+                writer.finish(buf)?;
+            }
+            LazyValue::Object(fields) => {
+                let mut writer = ContainerWriter::object();
+                let mut field = Vec::new();
+                for (key, val) in fields {
+                    field.clear();
+                    val.write_to_vec(&mut field)?;
+                    writer.push_key(key)?;
+                    writer.push_jsonb(&field)?;
+                }
+                writer.finish(buf)?;
+            }
+            LazyValue::Value(value) => value.write_to_vec(buf),
+        }
+        Ok(())
+    }
+
+    /// Serialize the value into a byte stream, the encoded values are copied verbatim.
+    pub fn to_vec(&self) -> Result<Vec<u8>, Error> {
+        let mut buf = Vec::new();
+        self.write_to_vec(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Convert the LazyValue into an owned LazyValue that doesn't borrow the input.
+    pub fn into_owned(self) -> LazyValue<'static> {
+        match self {
+            LazyValue::Raw(raw) => LazyValue::Raw(Cow::Owned(raw.into_owned())),
+            LazyValue::Array(elements) => {
+                LazyValue::Array(elements.into_iter().map(LazyValue::into_owned).collect())
+            }
+            LazyValue::Object(fields) => LazyValue::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, val)| (key, val.into_owned()))
+                    .collect(),
+            ),
+            LazyValue::Value(value) => LazyValue::Value(value.into_owned()),
+        }
+    }
+
+    // Splits an encoded or decoded container into its elements, the nested values are kept
+    // as they are. Scalars are not changed.
+    fn split(&mut self) -> Result<(), Error> {
+        let split = match self {
+            LazyValue::Raw(Cow::Borrowed(raw)) => split_raw(raw)?,
+            LazyValue::Raw(Cow::Owned(raw)) => split_raw(raw)?.map(LazyValue::into_owned),
+            LazyValue::Value(Value::Array(_) | Value::Object(_)) => match std::mem::take(self) {
+                LazyValue::Value(Value::Array(elements)) => Some(LazyValue::Array(
+                    elements.into_iter().map(LazyValue::Value).collect(),
+                )),
+                LazyValue::Value(Value::Object(fields)) => Some(LazyValue::Object(
+                    fields
+                        .into_iter()
+                        .map(|(key, val)| (key, LazyValue::Value(val)))
+                        .collect(),
+                )),
+                _ => unreachable!(),
+            },
+            _ => None,
+        };
+        if let Some(split) = split {
+            *self = split;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Default for LazyValue<'a> {
+    fn default() -> Self {
+        LazyValue::Value(Value::Null)
+    }
+}
+
+impl<'a> From<Value<'a>> for LazyValue<'a> {
+    fn from(value: Value<'a>) -> Self {
+        LazyValue::Value(value)
+    }
+}
+
+// The elements of an encoded container as encoded values, `None` for a scalar.
+fn split_raw(raw: &[u8]) -> Result<Option<LazyValue<'_>>, Error> {
+    let reader = ContainerReader::new(raw)?;
+    let element = |index: usize| -> Result<LazyValue<'_>, Error> {
+        let (entry, data) = reader.value(index).unwrap();
+        if entry.kind == EntryKind::Container {
+            return Ok(LazyValue::Raw(Cow::Borrowed(data)));
+        }
+        let mut buf = Vec::with_capacity(data.len() + 8);
+        write_scalar(entry.kind, data, &mut buf)?;
+        Ok(LazyValue::Raw(Cow::Owned(buf)))
+    };
+    match reader.kind() {
+        ContainerKind::Scalar => Ok(None),
+        ContainerKind::Array => Ok(Some(LazyValue::Array(
+            (0..reader.len()).map(element).collect::<Result<_, _>>()?,
+        ))),
+        ContainerKind::Object => Ok(Some(LazyValue::Object(
+            (0..reader.len())
+                .map(|i| Ok((reader.key(i).unwrap().to_string(), element(i)?)))
+                .collect::<Result<_, Error>>()?,
+        ))),
+    }
+}
@@ -14,23 +14,118 @@
 
 #![allow(clippy::uninlined_format_args)]
 
+mod aggregate;
+mod analyze;
+#[cfg(feature = "tokio")]
+mod async_parser;
+mod cancel;
+pub mod conformance;
 mod constants;
+pub mod databend;
 mod de;
+mod delta;
+mod dialect;
+mod diff;
+pub mod encoding;
 mod error;
+mod extension;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod filter;
+#[cfg(feature = "unicode")]
+mod fold;
 mod from;
 mod functions;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+pub mod generator;
+mod hash;
+mod index;
+mod input;
+mod intern;
 mod jentry;
+#[cfg(feature = "jq")]
+pub mod jq;
 pub mod jsonpath;
+mod layout;
+mod lazy;
+mod memory;
+mod merge;
+pub mod mysql;
+mod normalize;
 mod number;
 mod parser;
+mod partial;
+mod path_trie;
+pub mod pg;
+mod predicate;
+mod presence;
+mod profiler;
+mod prune;
+mod rewrite;
 mod ser;
+mod shred;
+mod sort;
+mod spill;
+pub mod sqlite;
+mod stream;
+mod table;
+mod transcode;
 mod util;
 mod value;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
+pub use aggregate::Aggregator;
+pub use analyze::*;
+#[cfg(feature = "tokio")]
+pub use async_parser::*;
+pub use cancel::CancellationToken;
 pub use de::from_slice;
+pub use de::from_slice_with_tracker;
+pub use delta::*;
+pub use dialect::*;
+pub use diff::*;
+pub use error::CastErrorCode;
 pub use error::Error;
+pub use error::PathErrorCode;
+pub use extension::*;
+pub use filter::*;
+#[cfg(feature = "unicode")]
+pub use fold::*;
+// The module only has the `From` impls for now, which need no re-export.
+#[allow(unused_imports)]
 pub use from::*;
 pub use functions::*;
+pub use hash::*;
+pub use index::*;
+pub use input::*;
+pub use intern::*;
+pub use layout::dump_layout;
+pub use lazy::LazyValue;
+pub use memory::*;
+pub use merge::*;
+pub use normalize::*;
 pub use number::Number;
 pub use parser::parse_value;
+pub use parser::parse_value_with_options;
+pub use parser::parse_value_with_spill;
+pub use parser::parse_value_with_tracker;
+pub use parser::ParseOptions;
+pub use parser::DEFAULT_MAX_DEPTH;
+pub use partial::PartialParser;
+pub use path_trie::PathTrie;
+pub use predicate::*;
+pub use presence::*;
+pub use profiler::*;
+pub use prune::*;
+pub use rewrite::*;
+pub use shred::*;
+pub use sort::*;
+pub use spill::*;
+pub use stream::*;
+pub use table::*;
+pub use transcode::*;
+pub use util::escape_str_into;
+pub use util::unescape_into;
 pub use value::*;
@@ -0,0 +1,82 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use crate::error::Error;
+
+/// A hook to account the memory of the `JSONB` operations, like the memory manager of
+/// a database enforcing the memory limit of a query.
+///
+/// The estimated memory of the values is reserved as they are allocated by the operations
+/// accepting a tracker, [`parse_value_with_tracker`], [`from_slice_with_tracker`], and the
+/// path evaluation of [`Selector::with_tracker`]. If the reservation fails, the operation
+/// stops and returns the error instead of allocating more memory. The memory is not released
+/// by the operations, the reservations are cumulative.
+///
+/// [`parse_value_with_tracker`]: crate::parse_value_with_tracker
+/// [`from_slice_with_tracker`]: crate::from_slice_with_tracker
+/// [`Selector::with_tracker`]: crate::jsonpath::Selector::with_tracker
+pub trait MemoryTracker: Sync {
+    /// Reserves the bytes to be allocated, returns an error like `Error::MemoryLimitExceeded`
+    /// to fail the operation.
+    fn reserve(&self, bytes: usize) -> Result<(), Error>;
+}
+
+/// A [`MemoryTracker`] with a fixed limit of the reserved bytes,
+/// it can be shared by the operations of a query.
+///
+/// ```
+/// use jsonb::{parse_value_with_tracker, Error, MemoryLimit, ParseOptions};
+///
+/// let text = br#"{"a":[1,2,3],"b":"a long string"}"#;
+/// let limit = MemoryLimit::new(1024);
+/// parse_value_with_tracker(text, &ParseOptions::default(), &limit).unwrap();
+/// assert!(limit.used() > 0);
+///
+/// let limit = MemoryLimit::new(16);
+/// let result = parse_value_with_tracker(text, &ParseOptions::default(), &limit);
+/// assert_eq!(result, Err(Error::MemoryLimitExceeded));
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryLimit {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl MemoryLimit {
+    pub fn new(limit: usize) -> MemoryLimit {
+        MemoryLimit {
+            limit,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    /// The reserved bytes.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+}
+
+impl MemoryTracker for MemoryLimit {
+    fn reserve(&self, bytes: usize) -> Result<(), Error> {
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes).filter(|used| *used <= self.limit)
+            })
+            .map(|_| ())
+            .map_err(|_| Error::MemoryLimitExceeded)
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use crate::error::Error;
+use crate::functions::convert_to_comparable;
+use crate::input::JsonInput;
+use crate::value::Object;
+use crate::value::Value;
+
+/// How [`array_merge_by_key`] merges the objects with the same key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// The fields of the right object replace the fields of the left object with the same names.
+    #[default]
+    RightWins,
+    /// Like `RightWins`, but the nested objects are merged recursively.
+    DeepMerge,
+}
+
+/// Merge two `JSONB` arrays of objects by the join key, and write the merged array to `buf`.
+///
+/// The left objects are merged with the right objects that have the same value of the key,
+/// the values are equal as `compare`. The merged array keeps the order of the left array,
+/// followed by the right elements that don't match any left object.
+/// The elements that are not objects or don't have the key are never matched.
+///
+/// Returns [`Error::InvalidJsonType`] if any of the values is not an array.
+///
+/// ```
+/// use jsonb::array_merge_by_key;
+/// use jsonb::MergeStrategy;
+///
+/// let left = r#"[{"id":1,"name":"a","tags":{"x":1}},{"id":2,"name":"b"}]"#;
+/// let right = r#"[{"id":2,"name":"c"},{"id":1,"tags":{"y":2}},{"id":3}]"#;
+/// let mut buf = Vec::new();
+/// array_merge_by_key(left, right, "id", MergeStrategy::DeepMerge, &mut buf).unwrap();
+/// assert_eq!(
+///     jsonb::to_string(&buf),
+///     r#"[{"id":1,"name":"a","tags":{"x":1,"y":2}},{"id":2,"name":"c"},{"id":3}]"#
+/// );
+/// ```
+pub fn array_merge_by_key<L: JsonInput + ?Sized, R: JsonInput + ?Sized>(
+    left: &L,
+    right: &R,
+    key: &str,
+    strategy: MergeStrategy,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let (Value::Array(mut merged), Value::Array(right)) = (left.to_value()?, right.to_value()?)
+    else {
+        return Err(Error::InvalidJsonType);
+    };
+
+    let mut positions: HashMap<Vec<u8>, Vec<usize>> = HashMap::new();
+    for (i, val) in merged.iter().enumerate() {
+        if let Some(join_key) = join_key(val, key) {
+            positions.entry(join_key).or_default().push(i);
+        }
+    }
+    let mut unmatched = Vec::new();
+    for val in right {
+        let matched = join_key(&val, key).and_then(|join_key| positions.get(&join_key));
+        match (matched, val) {
+            (Some(matched), Value::Object(obj)) => {
+                for i in matched {
+                    if let Value::Object(target) = &mut merged[*i] {
+                        merge_object(target, obj.clone(), strategy);
+                    }
+                }
+            }
+            (_, val) => unmatched.push(val),
+        }
+    }
+    merged.extend(unmatched);
+    Value::Array(merged).write_to_vec(buf);
+    Ok(())
+}
+
+// The comparable form of the key value, so the keys equal as `compare` are the same.
+fn join_key(value: &Value<'_>, key: &str) -> Option<Vec<u8>> {
+    let Value::Object(obj) = value else {
+        return None;
+    };
+    let mut join_key = Vec::new();
+    convert_to_comparable(&obj.get(key)?.to_vec(), &mut join_key);
+    Some(join_key)
+}
+
+fn merge_object<'a>(target: &mut Object<'a>, source: Object<'a>, strategy: MergeStrategy) {
+    for (key, val) in source {
+        match (target.get_mut(&key), val, strategy) {
+            (Some(Value::Object(nested)), Value::Object(val), MergeStrategy::DeepMerge) => {
+                merge_object(nested, val, strategy);
+            }
+            (_, val, _) => {
+                target.insert(key, val);
+            }
+        }
+    }
+}
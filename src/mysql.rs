@@ -0,0 +1,444 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! MySQL compatible `JSON` functions.
+//!
+//! The path arguments use the MySQL path syntax, which differs from the SQL/JSON Path:
+//!
+//! - `$` represents the document, followed by zero or more path legs.
+//! - `.key` or `."key"` selects the member of an object, `.*` selects all members.
+//! - `[n]`, `[last]`, `[last-n]` select an array element, `[m to n]` selects a range
+//!   of elements and `[*]` selects all elements. A non-array value is treated as
+//!   a single-element array when it is selected by index.
+//! - `**` selects the value and all of its descendants, it must be followed by a leg,
+//!   like `$**.b`.
+
+use crate::error::Error;
+use crate::input::JsonInput;
+use crate::util::quote_string;
+use crate::value::Value;
+
+/// The mode of [`json_contains_path`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainsPathMode {
+    /// `'one'`, at least one path exists.
+    One,
+    /// `'all'`, all the paths exist.
+    All,
+}
+
+/// `JSON_EXTRACT` returns the data selected by the paths.
+///
+/// If there is only one path without wildcards or ranges, returns the selected value,
+/// otherwise the selected values are wrapped in an array.
+/// Returns `None` if no value is selected.
+pub fn json_extract<T: JsonInput + ?Sized>(
+    value: &T,
+    paths: &[&str],
+) -> Result<Option<Vec<u8>>, Error> {
+    let value = value.to_value()?;
+    let paths = paths
+        .iter()
+        .map(|path| parse_mysql_path(path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut items = Vec::new();
+    for legs in paths.iter() {
+        select(&value, legs, &mut items);
+    }
+    if items.is_empty() {
+        return Ok(None);
+    }
+    if paths.len() == 1 && !paths[0].iter().any(PathLeg::is_wildcard) {
+        return Ok(Some(items[0].to_vec()));
+    }
+    let items = items.into_iter().cloned().collect();
+    Ok(Some(Value::Array(items).to_vec()))
+}
+
+/// `JSON_CONTAINS_PATH` checks whether the paths exist in the value.
+pub fn json_contains_path<T: JsonInput + ?Sized>(
+    value: &T,
+    mode: ContainsPathMode,
+    paths: &[&str],
+) -> Result<bool, Error> {
+    let value = value.to_value()?;
+    let paths = paths
+        .iter()
+        .map(|path| parse_mysql_path(path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let exists = |legs: &Vec<PathLeg>| {
+        let mut items = Vec::new();
+        select(&value, legs, &mut items);
+        !items.is_empty()
+    };
+    match mode {
+        ContainsPathMode::One => Ok(paths.iter().any(exists)),
+        ContainsPathMode::All => Ok(paths.iter().all(exists)),
+    }
+}
+
+/// `JSON_MERGE_PRESERVE` merges the values.
+///
+/// Adjacent arrays are merged into a single array, adjacent objects are merged into
+/// a single object, the values of duplicate keys are merged recursively.
+/// A non-array value is wrapped as an array before merged with an array.
+pub fn json_merge_preserve<T: JsonInput>(values: &[T]) -> Result<Vec<u8>, Error> {
+    let mut merged: Option<Value<'_>> = None;
+    for value in values {
+        let value = value.to_value()?;
+        merged = Some(match merged {
+            Some(merged) => merge_preserve(merged, value),
+            None => value,
+        });
+    }
+    Ok(merged.unwrap_or_default().to_vec())
+}
+
+/// `JSON_QUOTE` quotes a string as a `JSON` string literal.
+pub fn json_quote(s: &str) -> String {
+    let mut buf = String::with_capacity(s.len() + 2);
+    quote_string(s, &mut buf);
+    buf
+}
+
+/// `JSON_UNQUOTE` unquotes a `JSON` string literal,
+/// the string is returned unchanged if it is not surrounded by double quotes.
+pub fn json_unquote(s: &str) -> Result<String, Error> {
+    if s.len() < 2 || !s.starts_with('"') || !s.ends_with('"') {
+        return Ok(s.to_string());
+    }
+    match s.to_value()? {
+        Value::String(v) => Ok(v.into_owned()),
+        _ => Err(Error::InvalidJsonType),
+    }
+}
+
+// The index of array element.
+#[derive(Debug, Clone, PartialEq)]
+enum ArrayIndex {
+    // `n` selects the n-th element.
+    Index(usize),
+    // `last-n` selects the n-th element before the last element.
+    Last(usize),
+}
+
+impl ArrayIndex {
+    // Resolve the index in the array, `None` if the index is before the first element.
+    fn resolve(&self, len: usize) -> Option<usize> {
+        match self {
+            ArrayIndex::Index(idx) => Some(*idx),
+            ArrayIndex::Last(idx) => (len as isize - 1 - *idx as isize).try_into().ok(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PathLeg {
+    // `.key` or `."key"`
+    Member(String),
+    // `.*`
+    MemberWildcard,
+    // `[n]`
+    Index(ArrayIndex),
+    // `[m to n]`
+    Range(ArrayIndex, ArrayIndex),
+    // `[*]`
+    IndexWildcard,
+    // `**`
+    Ellipsis,
+}
+
+impl PathLeg {
+    fn is_wildcard(&self) -> bool {
+        matches!(
+            self,
+            PathLeg::MemberWildcard
+                | PathLeg::Range(_, _)
+                | PathLeg::IndexWildcard
+                | PathLeg::Ellipsis
+        )
+    }
+}
+
+fn select<'a, 'b>(value: &'b Value<'a>, legs: &[PathLeg], items: &mut Vec<&'b Value<'a>>) {
+    let Some((leg, rest)) = legs.split_first() else {
+        items.push(value);
+        return;
+    };
+    match leg {
+        PathLeg::Member(key) => {
+            if let Value::Object(obj) = value {
+                if let Some(val) = obj.get(key) {
+                    select(val, rest, items);
+                }
+            }
+        }
+        PathLeg::MemberWildcard => {
+            if let Value::Object(obj) = value {
+                for val in obj.values() {
+                    select(val, rest, items);
+                }
+            }
+        }
+        PathLeg::Index(index) => {
+            let elems = as_elements(value);
+            if let Some(val) = index.resolve(elems.len()).and_then(|idx| elems.get(idx)) {
+                select(val, rest, items);
+            }
+        }
+        PathLeg::Range(start, end) => {
+            let elems = as_elements(value);
+            let start = start.resolve(elems.len()).unwrap_or(0);
+            if let Some(end) = end.resolve(elems.len()) {
+                let end = end.min(elems.len().saturating_sub(1));
+                if start <= end && start < elems.len() {
+                    for val in &elems[start..=end] {
+                        select(val, rest, items);
+                    }
+                }
+            }
+        }
+        PathLeg::IndexWildcard => {
+            if let Value::Array(vals) = value {
+                for val in vals {
+                    select(val, rest, items);
+                }
+            }
+        }
+        PathLeg::Ellipsis => {
+            select(value, rest, items);
+            match value {
+                Value::Array(vals) => {
+                    for val in vals {
+                        select(val, legs, items);
+                    }
+                }
+                Value::Object(obj) => {
+                    for val in obj.values() {
+                        select(val, legs, items);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// The elements selected by array index, non-array value is treated as a single-element array.
+fn as_elements<'a, 'b>(value: &'b Value<'a>) -> &'b [Value<'a>] {
+    match value {
+        Value::Array(vals) => vals,
+        _ => std::slice::from_ref(value),
+    }
+}
+
+fn merge_preserve<'a>(left: Value<'a>, right: Value<'a>) -> Value<'a> {
+    match (left, right) {
+        (Value::Object(left), Value::Object(right)) => {
+            let mut obj = left;
+            for (key, val) in right {
+                let val = match obj.remove(&key) {
+                    Some(old) => merge_preserve(old, val),
+                    None => val,
+                };
+                obj.insert(key, val);
+            }
+            Value::Object(obj)
+        }
+        (left, right) => {
+            let mut vals = into_elements(left);
+            vals.extend(into_elements(right));
+            Value::Array(vals)
+        }
+    }
+}
+
+fn into_elements(value: Value<'_>) -> Vec<Value<'_>> {
+    match value {
+        Value::Array(vals) => vals,
+        _ => vec![value],
+    }
+}
+
+fn parse_mysql_path(path: &str) -> Result<Vec<PathLeg>, Error> {
+    let mut parser = PathParser {
+        buf: path.as_bytes(),
+        idx: 0,
+    };
+    parser.parse()
+}
+
+struct PathParser<'a> {
+    buf: &'a [u8],
+    idx: usize,
+}
+
+impl<'a> PathParser<'a> {
+    fn parse(&mut self) -> Result<Vec<PathLeg>, Error> {
+        self.skip_whitespace();
+        self.expect(b'$')?;
+        let mut legs = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let Some(c) = self.peek() else {
+                break;
+            };
+            let leg = match c {
+                b'.' => {
+                    self.idx += 1;
+                    self.skip_whitespace();
+                    self.parse_member()?
+                }
+                b'[' => {
+                    self.idx += 1;
+                    self.parse_array_location()?
+                }
+                b'*' => {
+                    self.idx += 1;
+                    self.expect(b'*')?;
+                    PathLeg::Ellipsis
+                }
+                _ => return Err(Error::InvalidJsonPath),
+            };
+            legs.push(leg);
+        }
+        // `**` can't be the last leg.
+        if legs.last() == Some(&PathLeg::Ellipsis) {
+            return Err(Error::InvalidJsonPath);
+        }
+        Ok(legs)
+    }
+
+    fn parse_member(&mut self) -> Result<PathLeg, Error> {
+        match self.peek() {
+            Some(b'*') => {
+                self.idx += 1;
+                Ok(PathLeg::MemberWildcard)
+            }
+            Some(b'"') => {
+                let start = self.idx;
+                self.idx += 1;
+                while let Some(c) = self.peek() {
+                    self.idx += 1;
+                    match c {
+                        b'\\' => self.idx += 1,
+                        b'"' => {
+                            let quoted = std::str::from_utf8(&self.buf[start..self.idx])
+                                .map_err(|_| Error::InvalidJsonPath)?;
+                            let key = json_unquote(quoted).map_err(|_| Error::InvalidJsonPath)?;
+                            return Ok(PathLeg::Member(key));
+                        }
+                        _ => {}
+                    }
+                }
+                Err(Error::InvalidJsonPath)
+            }
+            _ => {
+                let start = self.idx;
+                while let Some(c) = self.peek() {
+                    if c.is_ascii_alphanumeric() || c == b'_' || c == b'$' || c >= 0x80 {
+                        self.idx += 1;
+                    } else {
+                        break;
+                    }
+                }
+                let key = &self.buf[start..self.idx];
+                if key.is_empty() || key[0].is_ascii_digit() {
+                    return Err(Error::InvalidJsonPath);
+                }
+                let key = std::str::from_utf8(key).map_err(|_| Error::InvalidJsonPath)?;
+                Ok(PathLeg::Member(key.to_string()))
+            }
+        }
+    }
+
+    fn parse_array_location(&mut self) -> Result<PathLeg, Error> {
+        self.skip_whitespace();
+        if self.peek() == Some(b'*') {
+            self.idx += 1;
+            self.skip_whitespace();
+            self.expect(b']')?;
+            return Ok(PathLeg::IndexWildcard);
+        }
+        let start = self.parse_array_index()?;
+        self.skip_whitespace();
+        let leg = if self.eat_keyword("to") {
+            self.skip_whitespace();
+            let end = self.parse_array_index()?;
+            self.skip_whitespace();
+            PathLeg::Range(start, end)
+        } else {
+            PathLeg::Index(start)
+        };
+        self.expect(b']')?;
+        Ok(leg)
+    }
+
+    fn parse_array_index(&mut self) -> Result<ArrayIndex, Error> {
+        if self.eat_keyword("last") {
+            self.skip_whitespace();
+            if self.peek() == Some(b'-') {
+                self.idx += 1;
+                self.skip_whitespace();
+                let idx = self.parse_number()?;
+                return Ok(ArrayIndex::Last(idx));
+            }
+            return Ok(ArrayIndex::Last(0));
+        }
+        let idx = self.parse_number()?;
+        Ok(ArrayIndex::Index(idx))
+    }
+
+    fn parse_number(&mut self) -> Result<usize, Error> {
+        let start = self.idx;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+            self.idx += 1;
+        }
+        std::str::from_utf8(&self.buf[start..self.idx])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or(Error::InvalidJsonPath)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let end = self.idx + keyword.len();
+        if self.buf.len() >= end && &self.buf[self.idx..end] == keyword.as_bytes() {
+            self.idx = end;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), Error> {
+        if self.peek() == Some(c) {
+            self.idx += 1;
+            Ok(())
+        } else {
+            Err(Error::InvalidJsonPath)
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.buf.get(self.idx).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_ascii_whitespace()) {
+            self.idx += 1;
+        }
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::btree_map::Entry;
+
+use crate::error::Error;
+use crate::input::JsonInput;
+use crate::value::Object;
+use crate::value::Value;
+
+/// The case of the keys normalized by [`normalize_keys`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    Lower,
+    Upper,
+}
+
+/// What [`normalize_keys`] and [`object_invert`] do if several keys of an object are the same
+/// after the rewrite.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyCollision {
+    /// Returns [`Error::ObjectDuplicateKey`].
+    #[default]
+    Error,
+    /// Keeps the value of the first key, in the sorted order of the original keys.
+    FirstWins,
+    /// Keeps the value of the last key, in the sorted order of the original keys.
+    LastWins,
+}
+
+/// Rewrite all the object keys of a `JSONB` value, including the nested objects,
+/// to the lower or upper case, and write the value to `buf`.
+/// The keys that become the same are resolved by the `on_collision` policy.
+///
+/// ```
+/// use jsonb::{normalize_keys, Case, KeyCollision};
+///
+/// let mut buf = Vec::new();
+/// let value = r#"{"Id":1,"Tags":[{"Name":"a"}]}"#;
+/// normalize_keys(value, Case::Lower, KeyCollision::Error, &mut buf).unwrap();
+/// assert_eq!(jsonb::to_string(&buf), r#"{"id":1,"tags":[{"name":"a"}]}"#);
+/// ```
+pub fn normalize_keys<T: JsonInput + ?Sized>(
+    value: &T,
+    case: Case,
+    on_collision: KeyCollision,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let value = normalize_value(value.to_value()?, case, on_collision)?;
+    value.write_to_vec(buf);
+    Ok(())
+}
+
+fn normalize_value(
+    value: Value<'_>,
+    case: Case,
+    on_collision: KeyCollision,
+) -> Result<Value<'_>, Error> {
+    match value {
+        Value::Array(vals) => {
+            let vals = vals
+                .into_iter()
+                .map(|val| normalize_value(val, case, on_collision))
+                .collect::<Result<_, _>>()?;
+            Ok(Value::Array(vals))
+        }
+        Value::Object(obj) => {
+            let mut normalized = Object::new();
+            for (key, val) in obj {
+                let key = match case {
+                    Case::Lower => key.to_lowercase(),
+                    Case::Upper => key.to_uppercase(),
+                };
+                let val = normalize_value(val, case, on_collision)?;
+                match (normalized.entry(key), on_collision) {
+                    (Entry::Vacant(entry), _) => {
+                        entry.insert(val);
+                    }
+                    (Entry::Occupied(_), KeyCollision::Error) => {
+                        return Err(Error::ObjectDuplicateKey);
+                    }
+                    (Entry::Occupied(_), KeyCollision::FirstWins) => {}
+                    (Entry::Occupied(mut entry), KeyCollision::LastWins) => {
+                        entry.insert(val);
+                    }
+                }
+            }
+            Ok(Value::Object(normalized))
+        }
+        val => Ok(val),
+    }
+}
+
+/// Swap the keys and the values of a `JSONB` object, and write the inverted object to `buf`.
+/// The values must be strings or numbers, the numbers become keys as their `JSON` text,
+/// and the original keys become string values.
+/// The values that are the same are resolved by the `on_collision` policy.
+///
+/// Returns [`Error::InvalidJsonType`] if the value is not an object,
+/// or any of the values is not a string or a number.
+///
+/// ```
+/// use jsonb::{object_invert, KeyCollision};
+///
+/// let mut buf = Vec::new();
+/// let value = r#"{"a":"x","b":2,"c":"x"}"#;
+/// object_invert(value, KeyCollision::LastWins, &mut buf).unwrap();
+/// assert_eq!(jsonb::to_string(&buf), r#"{"2":"b","x":"c"}"#);
+/// ```
+pub fn object_invert<T: JsonInput + ?Sized>(
+    value: &T,
+    on_collision: KeyCollision,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let Value::Object(obj) = value.to_value()? else {
+        return Err(Error::InvalidJsonType);
+    };
+    let mut inverted = Object::new();
+    for (key, val) in obj {
+        let val = match val {
+            Value::String(s) => s.into_owned(),
+            Value::Number(n) => n.to_string(),
+            _ => return Err(Error::InvalidJsonType),
+        };
+        match (inverted.entry(val), on_collision) {
+            (Entry::Vacant(entry), _) => {
+                entry.insert(Value::String(key.into()));
+            }
+            (Entry::Occupied(_), KeyCollision::Error) => {
+                return Err(Error::ObjectDuplicateKey);
+            }
+            (Entry::Occupied(_), KeyCollision::FirstWins) => {}
+            (Entry::Occupied(mut entry), KeyCollision::LastWins) => {
+                entry.insert(Value::String(key.into()));
+            }
+        }
+    }
+    Value::Object(inverted).write_to_vec(buf);
+    Ok(())
+}
@@ -21,6 +21,7 @@ use std::io::Write;
 use ordered_float::OrderedFloat;
 
 use super::constants::*;
+use super::error::CastErrorCode;
 use super::error::Error;
 
 #[derive(Debug, Clone)]
@@ -159,6 +160,49 @@ impl Number {
             Number::Float64(v) => Some(*v),
         }
     }
+
+    /// Represent the number as i64 if it is exactly representable,
+    /// including the floats with a zero fractional part.
+    pub fn as_i64_exact(&self) -> Result<i64, Error> {
+        match self {
+            Number::Float64(v) => {
+                check_integral(*v)?;
+                // `i64::MAX as f64` is rounded up to 2^63, which is out of range.
+                if *v >= i64::MIN as f64 && *v < i64::MAX as f64 {
+                    Ok(*v as i64)
+                } else {
+                    Err(Error::Cast(CastErrorCode::OutOfRange))
+                }
+            }
+            _ => self.as_i64().ok_or(Error::Cast(CastErrorCode::OutOfRange)),
+        }
+    }
+
+    /// Represent the number as u64 if it is exactly representable,
+    /// including the floats with a zero fractional part.
+    pub fn as_u64_exact(&self) -> Result<u64, Error> {
+        match self {
+            Number::Float64(v) => {
+                check_integral(*v)?;
+                if *v >= 0.0 && *v < u64::MAX as f64 {
+                    Ok(*v as u64)
+                } else {
+                    Err(Error::Cast(CastErrorCode::OutOfRange))
+                }
+            }
+            _ => self.as_u64().ok_or(Error::Cast(CastErrorCode::OutOfRange)),
+        }
+    }
+}
+
+fn check_integral(v: f64) -> Result<(), Error> {
+    if !v.is_finite() {
+        Err(Error::Cast(CastErrorCode::NotFinite))
+    } else if v.fract() != 0.0 {
+        Err(Error::Cast(CastErrorCode::Fractional))
+    } else {
+        Ok(())
+    }
 }
 
 impl Default for Number {
@@ -14,30 +14,151 @@
 
 use std::borrow::Cow;
 
+use super::cancel::CancellationToken;
+use super::cancel::CHECK_INTERVAL;
 use super::constants::*;
 use super::error::Error;
 use super::error::ParseErrorCode;
+use super::memory::MemoryTracker;
 use super::number::Number;
+use super::profiler::push_key;
+use super::rewrite::PathStep;
+use super::rewrite::RewriteRule;
+use super::spill::SpillOptions;
+use super::spill::SpillStore;
+use super::spill::SpilledValue;
+use super::spill::Spiller;
 use super::util::parse_string;
 use super::value::Object;
 use super::value::Value;
 
+/// The default limit of the nesting depth of [`parse_value`].
+pub const DEFAULT_MAX_DEPTH: usize = 1024;
+
+/// Options of [`parse_value_with_options`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// The maximum nesting depth of the arrays and objects, deeper values are rejected with
+    /// the `RecursionLimitExceeded` syntax error. The parser itself doesn't recurse,
+    /// the limit protects the recursive routines over the parsed `Value`, like dropping it.
+    pub max_depth: usize,
+    /// The rules to rewrite the values while parsing, the first matched rule of a value is applied.
+    pub rewrites: Vec<RewriteRule>,
+    /// The token to interrupt the parsing, it's checked periodically while parsing.
+    pub cancel: Option<CancellationToken>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            rewrites: Vec::new(),
+            cancel: None,
+        }
+    }
+}
+
 // Parse JSON text to JSONB Value.
 // Inspired by `https://github.com/jorgecarleitao/json-deserializer`
 // Thanks Jorge Leitao.
 pub fn parse_value(buf: &[u8]) -> Result<Value<'_>, Error> {
-    let mut parser = Parser::new(buf);
+    parse_value_with_options(buf, &ParseOptions::default())
+}
+
+/// Parse JSON text to JSONB Value like [`parse_value`], with the limits of the options.
+pub fn parse_value_with_options<'a>(
+    buf: &'a [u8],
+    options: &ParseOptions,
+) -> Result<Value<'a>, Error> {
+    let mut parser = Parser::new(buf, options);
+    parser.parse()
+}
+
+/// Parse JSON text to JSONB Value like [`parse_value_with_options`], the large strings and
+/// arrays are spilled to the store once the estimated memory of the parsed values is beyond
+/// the budget, so that a huge document doesn't have to be held in memory.
+///
+/// The spilled values are replaced with `null`, and returned with their paths and the ids
+/// in the store, in the order they are completed, the nested values first.
+/// The root value is never spilled.
+///
+/// ```
+/// use jsonb::{parse_value_with_spill, ParseOptions, SpillOptions};
+///
+/// let options = SpillOptions {
+///     budget: 0,
+///     min_size: 64,
+/// };
+/// let text = r#"{"id":1,"blob":"a very long string that is spilled to the store"}"#;
+/// let mut store: Vec<Vec<u8>> = Vec::new();
+/// let (value, spilled) =
+///     parse_value_with_spill(text.as_bytes(), &ParseOptions::default(), options, &mut store)
+///         .unwrap();
+/// assert_eq!(value.to_string(), r#"{"blob":null,"id":1}"#);
+/// assert_eq!(spilled[0].path, "$.blob");
+/// let blob = jsonb::from_slice(&store[spilled[0].id as usize]).unwrap();
+/// assert_eq!(blob.as_str().unwrap(), "a very long string that is spilled to the store");
+/// ```
+pub fn parse_value_with_spill<'a>(
+    buf: &'a [u8],
+    options: &ParseOptions,
+    spill_options: SpillOptions,
+    store: &mut dyn SpillStore,
+) -> Result<(Value<'a>, Vec<SpilledValue>), Error> {
+    let mut parser = Parser::new(buf, options);
+    parser.spiller = Some(Spiller::new(spill_options, store));
+    let value = parser.parse()?;
+    let spilled = parser.spiller.map(|spiller| spiller.spilled);
+    Ok((value, spilled.unwrap_or_default()))
+}
+
+/// Parse JSON text to JSONB Value like [`parse_value_with_options`], the estimated memory
+/// of each parsed value is reserved from the tracker, the parsing fails with the error
+/// of the tracker if the memory can't be reserved.
+pub fn parse_value_with_tracker<'a>(
+    buf: &'a [u8],
+    options: &ParseOptions,
+    tracker: &dyn MemoryTracker,
+) -> Result<Value<'a>, Error> {
+    let mut parser = Parser::new(buf, options);
+    parser.tracker = Some(tracker);
     parser.parse()
 }
 
-struct Parser<'a> {
+// The estimated memory of a value, without the nested values and the content of the strings.
+const VALUE_SIZE: usize = std::mem::size_of::<Value>();
+
+// The containers being parsed, the object holds the key of the value being parsed,
+// or `None` if the key is being parsed.
+enum Frame<'a> {
+    Array(Vec<Value<'a>>),
+    Object(Object<'a>, Option<String>),
+}
+
+struct Parser<'a, 'o> {
     buf: &'a [u8],
     idx: usize,
+    max_depth: usize,
+    rewrites: &'o [RewriteRule],
+    spiller: Option<Spiller<'o>>,
+    tracker: Option<&'o dyn MemoryTracker>,
+    cancel: Option<&'o CancellationToken>,
+    // The number of the parsed values, to check the cancellation periodically.
+    values: usize,
 }
 
-impl<'a> Parser<'a> {
-    fn new(buf: &'a [u8]) -> Parser<'a> {
-        Self { buf, idx: 0 }
+impl<'a, 'o> Parser<'a, 'o> {
+    fn new(buf: &'a [u8], options: &'o ParseOptions) -> Parser<'a, 'o> {
+        Self {
+            buf,
+            idx: 0,
+            max_depth: options.max_depth,
+            rewrites: &options.rewrites,
+            spiller: None,
+            tracker: None,
+            cancel: options.cancel.as_ref(),
+            values: 0,
+        }
     }
 
     fn parse(&mut self) -> Result<Value<'a>, Error> {
@@ -50,22 +171,232 @@ impl<'a> Parser<'a> {
         Ok(val)
     }
 
+    // The nested arrays and objects are parsed with an explicit stack instead of recursion,
+    // so deeply nested text can't overflow the thread stack.
     fn parse_json_value(&mut self) -> Result<Value<'a>, Error> {
+        let mut stack: Vec<Frame<'a>> = Vec::new();
+        loop {
+            let mut value = match self.parse_json_scalar_or_open(&mut stack)? {
+                Some(value) => value,
+                None => {
+                    if let Some(spiller) = self.spiller.as_mut() {
+                        spiller.sizes.push(0);
+                    }
+                    continue;
+                }
+            };
+            let mut closed = false;
+            // Add the completed value to its parents, closing the containers that end here.
+            loop {
+                if let Some(cancel) = self.cancel {
+                    if self.values % CHECK_INTERVAL == 0 {
+                        cancel.check()?;
+                    }
+                    self.values += 1;
+                }
+                if let Some(tracker) = self.tracker {
+                    let size = match &value {
+                        Value::String(s) => VALUE_SIZE + s.len(),
+                        _ => VALUE_SIZE,
+                    };
+                    tracker.reserve(size)?;
+                }
+                if !self.rewrites.is_empty() {
+                    value = self.rewrite(&stack, value)?;
+                }
+                if self.spiller.is_some() {
+                    value = self.spill(&stack, value, closed)?;
+                }
+                match stack.last_mut() {
+                    None => return Ok(value),
+                    Some(Frame::Array(values)) => {
+                        values.push(value);
+                        self.skip_unused();
+                        let c = self.next()?;
+                        if *c == b']' {
+                            self.step();
+                            let Some(Frame::Array(values)) = stack.pop() else {
+                                unreachable!()
+                            };
+                            value = Value::Array(values);
+                            closed = true;
+                            continue;
+                        }
+                        if *c != b',' {
+                            return Err(self.error(ParseErrorCode::ExpectedArrayCommaOrEnd));
+                        }
+                        self.step();
+                        break;
+                    }
+                    Some(Frame::Object(obj, key)) => match key.take() {
+                        None => {
+                            if !value.is_string() {
+                                return Err(self.error(ParseErrorCode::KeyMustBeAString));
+                            }
+                            self.skip_unused();
+                            let c = self.next()?;
+                            if *c != b':' {
+                                return Err(self.error(ParseErrorCode::ExpectedColon));
+                            }
+                            self.step();
+                            *key = Some(value.as_str().unwrap().to_string());
+                            break;
+                        }
+                        Some(k) => {
+                            obj.insert(k, value);
+                            self.skip_unused();
+                            let c = self.next()?;
+                            if *c == b'}' {
+                                self.step();
+                                let Some(Frame::Object(obj, _)) = stack.pop() else {
+                                    unreachable!()
+                                };
+                                value = Value::Object(obj);
+                                closed = true;
+                                continue;
+                            }
+                            if *c != b',' {
+                                return Err(self.error(ParseErrorCode::ExpectedObjectCommaOrEnd));
+                            }
+                            self.step();
+                            break;
+                        }
+                    },
+                }
+            }
+        }
+    }
+
+    // Account the estimated memory of the completed value, and spill it to the store if the
+    // memory is beyond the budget. `closed` is true if the value is a closed container,
+    // its nested values are already accounted.
+    fn spill(
+        &mut self,
+        stack: &[Frame<'a>],
+        value: Value<'a>,
+        closed: bool,
+    ) -> Result<Value<'a>, Error> {
+        let spiller = self.spiller.as_mut().unwrap();
+        let mut size = VALUE_SIZE;
+        spiller.used += VALUE_SIZE;
+        if closed {
+            size += spiller.sizes.pop().unwrap_or_default();
+        }
+        if let Value::String(s) = &value {
+            size += s.len();
+            spiller.used += s.len();
+        }
+        let is_key = matches!(stack.last(), Some(Frame::Object(_, None)));
+        let spill = !stack.is_empty()
+            && !is_key
+            && matches!(value, Value::String(_) | Value::Array(_))
+            && size >= spiller.options.min_size
+            && spiller.used > spiller.options.budget;
+        let value = if spill {
+            let id = spiller.store.spill(&value.to_vec())?;
+            spiller.spilled.push(SpilledValue {
+                path: Self::location(stack),
+                id,
+            });
+            spiller.used -= size - VALUE_SIZE;
+            size = VALUE_SIZE;
+            Value::Null
+        } else {
+            value
+        };
+        if let Some(parent) = spiller.sizes.last_mut() {
+            *parent += size;
+        }
+        Ok(value)
+    }
+
+    // The path of the value being parsed, written as `JSONPath`.
+    fn location(stack: &[Frame<'a>]) -> String {
+        let mut path = "$".to_string();
+        for frame in stack {
+            match frame {
+                Frame::Array(values) => path.push_str(&format!("[{}]", values.len())),
+                Frame::Object(_, Some(key)) => push_key(&mut path, key),
+                Frame::Object(_, None) => {}
+            }
+        }
+        path
+    }
+
+    // Apply the first rule that matches the location of the completed value,
+    // the location is the positions of the containers on the stack.
+    fn rewrite(&self, stack: &[Frame<'a>], value: Value<'a>) -> Result<Value<'a>, Error> {
+        // The keys of the objects are not rewritten.
+        if let Some(Frame::Object(_, None)) = stack.last() {
+            return Ok(value);
+        }
+        let rule = self.rewrites.iter().find(|rule| {
+            rule.steps.len() == stack.len()
+                && rule
+                    .steps
+                    .iter()
+                    .zip(stack)
+                    .all(|(step, frame)| match (step, frame) {
+                        (PathStep::Key(name), Frame::Object(_, Some(key))) => name == key,
+                        (PathStep::AnyKey, Frame::Object(..)) => true,
+                        (PathStep::Index(idx), Frame::Array(values)) => *idx == values.len(),
+                        (PathStep::AnyIndex, Frame::Array(_)) => true,
+                        (_, _) => false,
+                    })
+        });
+        match rule {
+            Some(rule) => rule.rewrite(value),
+            None => Ok(value),
+        }
+    }
+
+    // Parse a scalar or an empty container, or open a container by pushing it to the stack
+    // and returns `None`.
+    fn parse_json_scalar_or_open(
+        &mut self,
+        stack: &mut Vec<Frame<'a>>,
+    ) -> Result<Option<Value<'a>>, Error> {
         self.skip_unused();
         let c = self.next()?;
-        match c {
-            b'n' => self.parse_json_null(),
-            b't' => self.parse_json_true(),
-            b'f' => self.parse_json_false(),
-            b'0'..=b'9' | b'-' => self.parse_json_number(),
-            b'"' => self.parse_json_string(),
-            b'[' => self.parse_json_array(),
-            b'{' => self.parse_json_object(),
+        let value = match c {
+            b'n' => self.parse_json_null()?,
+            b't' => self.parse_json_true()?,
+            b'f' => self.parse_json_false()?,
+            b'0'..=b'9' | b'-' => self.parse_json_number()?,
+            b'"' => self.parse_json_string()?,
+            b'[' | b'{' => {
+                let open = *c;
+                if stack.len() >= self.max_depth {
+                    return Err(self.error(ParseErrorCode::RecursionLimitExceeded));
+                }
+                self.step();
+                self.skip_unused();
+                let c = self.next()?;
+                match (open, *c) {
+                    (b'[', b']') => {
+                        self.step();
+                        Value::Array(Vec::new())
+                    }
+                    (b'{', b'}') => {
+                        self.step();
+                        Value::Object(Object::new())
+                    }
+                    (b'[', _) => {
+                        stack.push(Frame::Array(Vec::new()));
+                        return Ok(None);
+                    }
+                    _ => {
+                        stack.push(Frame::Object(Object::new(), None));
+                        return Ok(None);
+                    }
+                }
+            }
             _ => {
                 self.step();
-                Err(self.error(ParseErrorCode::ExpectedSomeValue))
+                return Err(self.error(ParseErrorCode::ExpectedSomeValue));
             }
-        }
+        };
+        Ok(Some(value))
     }
 
     fn next(&mut self) -> Result<&u8, Error> {
@@ -313,66 +644,4 @@ impl<'a> Parser<'a> {
         };
         Ok(Value::String(val))
     }
-
-    fn parse_json_array(&mut self) -> Result<Value<'a>, Error> {
-        self.must_is(b'[')?;
-
-        let mut first = true;
-        let mut values = Vec::new();
-        loop {
-            self.skip_unused();
-            let c = self.next()?;
-            if *c == b']' {
-                self.step();
-                break;
-            }
-            if !first {
-                if *c != b',' {
-                    return Err(self.error(ParseErrorCode::ExpectedArrayCommaOrEnd));
-                }
-                self.step();
-            }
-            first = false;
-            let value = self.parse_json_value()?;
-            values.push(value);
-        }
-        Ok(Value::Array(values))
-    }
-
-    fn parse_json_object(&mut self) -> Result<Value<'a>, Error> {
-        self.must_is(b'{')?;
-
-        let mut first = true;
-        let mut obj = Object::new();
-        loop {
-            self.skip_unused();
-            let c = self.next()?;
-            if *c == b'}' {
-                self.step();
-                break;
-            }
-            if !first {
-                if *c != b',' {
-                    return Err(self.error(ParseErrorCode::ExpectedObjectCommaOrEnd));
-                }
-                self.step();
-            }
-            first = false;
-            let key = self.parse_json_value()?;
-            if !key.is_string() {
-                return Err(self.error(ParseErrorCode::KeyMustBeAString));
-            }
-            self.skip_unused();
-            let c = self.next()?;
-            if *c != b':' {
-                return Err(self.error(ParseErrorCode::ExpectedColon));
-            }
-            self.step();
-            let value = self.parse_json_value()?;
-
-            let k = key.as_str().unwrap();
-            obj.insert(k.to_string(), value);
-        }
-        Ok(Value::Object(obj))
-    }
 }
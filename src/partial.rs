@@ -0,0 +1,242 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::Error;
+use crate::error::ParseErrorCode;
+use crate::parser::parse_value;
+use crate::value::Value;
+
+/// Parser of a `JSON` document that arrives in pieces, like the output of a streaming API.
+///
+/// The bytes are fed as they arrive, and the prefix can be read at any time as a best-effort
+/// value with [`value`](PartialParser::value): the open arrays and objects are closed,
+/// an unfinished string value is cut at the end of the prefix, and the other unfinished
+/// values, like a number without its digits or a key without its value, are left out.
+/// Once all the bytes have been fed, [`finish`](PartialParser::finish) encodes the document.
+///
+/// The state of the scan is kept between the pieces, so each byte is scanned once.
+/// Only the nesting is checked while scanning, the other syntax errors are reported
+/// when the value is parsed.
+#[derive(Debug, Clone, Default)]
+pub struct PartialParser {
+    buf: Vec<u8>,
+    // The open arrays and objects.
+    stack: Vec<Container>,
+    mode: Mode,
+    // The end of the last complete value or of the last opening bracket,
+    // the prefix up to it can be completed by closing the open containers.
+    safe: usize,
+    started: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Container {
+    is_object: bool,
+    // The next string of an object is a key.
+    expect_key: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+enum Mode {
+    #[default]
+    Between,
+    // `escape` is the offset of the last escape in the string.
+    String {
+        is_key: bool,
+        start: usize,
+        escape: Option<usize>,
+    },
+    Number,
+    Literal {
+        start: usize,
+    },
+}
+
+impl PartialParser {
+    pub fn new() -> PartialParser {
+        PartialParser::default()
+    }
+
+    /// Append the next bytes of the document, returns the error if the nesting is invalid,
+    /// like a `]` closing an object.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let mut idx = self.buf.len();
+        self.buf.extend_from_slice(bytes);
+        while idx < self.buf.len() {
+            let c = self.buf[idx];
+            match self.mode {
+                Mode::String {
+                    is_key,
+                    ref mut escape,
+                    ..
+                } => match c {
+                    // The escaped character, which may be in the next piece.
+                    _ if idx > 0 && *escape == Some(idx - 1) => {}
+                    b'\\' => *escape = Some(idx),
+                    b'"' => {
+                        self.mode = Mode::Between;
+                        if !is_key {
+                            self.safe = idx + 1;
+                        }
+                    }
+                    _ => {}
+                },
+                Mode::Number if matches!(c, b'0'..=b'9' | b'+' | b'-' | b'.' | b'e' | b'E') => {}
+                Mode::Literal { .. } if c.is_ascii_alphabetic() => {}
+                Mode::Number | Mode::Literal { .. } => {
+                    // The value ends before this byte, which is scanned again.
+                    self.mode = Mode::Between;
+                    self.safe = idx;
+                    continue;
+                }
+                Mode::Between => self.scan_between(c, idx)?,
+            }
+            idx += 1;
+        }
+        Ok(())
+    }
+
+    fn scan_between(&mut self, c: u8, idx: usize) -> Result<(), Error> {
+        match c {
+            b' ' | b'\n' | b'\r' | b'\t' => return Ok(()),
+            b'{' | b'[' => {
+                self.stack.push(Container {
+                    is_object: c == b'{',
+                    expect_key: c == b'{',
+                });
+                self.safe = idx + 1;
+            }
+            b'}' | b']' => {
+                match self.stack.pop() {
+                    Some(container) if container.is_object == (c == b'}') => {}
+                    Some(container) => {
+                        let code = if container.is_object {
+                            ParseErrorCode::ExpectedObjectCommaOrEnd
+                        } else {
+                            ParseErrorCode::ExpectedArrayCommaOrEnd
+                        };
+                        return Err(Error::Syntax(code, idx));
+                    }
+                    None => {
+                        return Err(Error::Syntax(
+                            ParseErrorCode::UnexpectedTrailingCharacters,
+                            idx,
+                        ))
+                    }
+                }
+                self.safe = idx + 1;
+            }
+            b',' | b':' => {
+                if let Some(container) = self.stack.last_mut() {
+                    container.expect_key = c == b',' && container.is_object;
+                }
+            }
+            b'"' => {
+                let is_key = self
+                    .stack
+                    .last()
+                    .map_or(false, |container| container.expect_key);
+                self.mode = Mode::String {
+                    is_key,
+                    start: idx,
+                    escape: None,
+                };
+            }
+            b'-' | b'0'..=b'9' => self.mode = Mode::Number,
+            b'a'..=b'z' => self.mode = Mode::Literal { start: idx },
+            _ => return Err(Error::Syntax(ParseErrorCode::ExpectedSomeValue, idx)),
+        }
+        self.started = true;
+        Ok(())
+    }
+
+    /// Returns true if the fed bytes are a whole document, so more bytes can only be whitespace,
+    /// or digits of a top-level number.
+    pub fn is_complete(&self) -> bool {
+        self.started
+            && self.stack.is_empty()
+            && !matches!(self.mode, Mode::String { .. } | Mode::Literal { .. })
+    }
+
+    /// Returns the best-effort value of the bytes fed so far, `None` if no value has started.
+    /// The error is returned if the completed prefix can't be parsed.
+    pub fn value(&self) -> Result<Option<Value<'static>>, Error> {
+        let mut text = match self.mode {
+            Mode::String {
+                is_key: false,
+                start,
+                escape,
+            } => {
+                let mut end = self.buf.len();
+                // Cut an unfinished escape, and a leading surrogate without the trailing one.
+                if let Some(escape) = escape {
+                    let len = match self.buf.get(escape + 1) {
+                        Some(b'u') => 6,
+                        _ => 2,
+                    };
+                    if end - escape < len {
+                        end = escape;
+                    }
+                }
+                if end - start > 6 && is_leading_surrogate(&self.buf[end - 6..end]) {
+                    end -= 6;
+                }
+                // Cut an unfinished UTF-8 character.
+                if let Err(err) = std::str::from_utf8(&self.buf[start..end]) {
+                    if err.error_len().is_none() {
+                        end = start + err.valid_up_to();
+                    }
+                }
+                let mut text = self.buf[..end].to_vec();
+                text.push(b'"');
+                text
+            }
+            Mode::Number if self.buf.last().map_or(false, u8::is_ascii_digit) => self.buf.clone(),
+            Mode::Literal { start } if is_literal(&self.buf[start..]) => self.buf.clone(),
+            _ => self.buf[..self.safe].to_vec(),
+        };
+        if text.iter().all(u8::is_ascii_whitespace) {
+            return Ok(None);
+        }
+        for container in self.stack.iter().rev() {
+            text.push(if container.is_object { b'}' } else { b']' });
+        }
+        Ok(Some(parse_value(&text)?.into_owned()))
+    }
+
+    /// Returns the encoded document of all the fed bytes, the error is returned
+    /// if the document is incomplete or invalid.
+    pub fn finish(self) -> Result<Vec<u8>, Error> {
+        Ok(parse_value(&self.buf)?.to_vec())
+    }
+
+    /// Returns the value of all the fed bytes, like [`finish`](PartialParser::finish).
+    #[cfg(feature = "tokio")]
+    pub(crate) fn finish_value(self) -> Result<Value<'static>, Error> {
+        Ok(parse_value(&self.buf)?.into_owned())
+    }
+}
+
+fn is_literal(text: &[u8]) -> bool {
+    matches!(text, b"true" | b"false" | b"null")
+}
+
+// `\uD800` to `\uDBFF`, which must be followed by a trailing surrogate.
+fn is_leading_surrogate(escape: &[u8]) -> bool {
+    escape.starts_with(b"\\u")
+        && std::str::from_utf8(&escape[2..])
+            .ok()
+            .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+            .map_or(false, |v| (0xD800..0xDC00).contains(&v))
+}
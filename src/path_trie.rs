@@ -0,0 +1,173 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use crate::encoding::ContainerKind;
+use crate::encoding::ContainerReader;
+use crate::encoding::EntryKind;
+use crate::error::Error;
+use crate::input::JsonInput;
+use crate::jsonpath::JsonPath;
+use crate::rewrite::path_steps;
+use crate::rewrite::PathStep;
+
+/// A trie of many paths to classify documents by the paths they satisfy in a single traversal,
+/// like routing the events to the subscribers based on the content.
+/// A document satisfies a path if there is at least one value at the path.
+///
+/// The paths with common prefixes share the nodes of the trie, and only the nested values
+/// that some of the paths lead to are visited, without decoding the document.
+///
+/// The paths can only have the member accessors, non-negative array indices and wildcards,
+/// like `$.a[0].*`, otherwise returns `Error::InvalidJsonPath`.
+/// The member accessors only match the fields of objects and the array accessors only match
+/// the elements of arrays.
+///
+/// ```
+/// use jsonb::jsonpath::parse_json_path;
+/// use jsonb::PathTrie;
+/// use jsonb::RawJsonb;
+///
+/// let mut trie = PathTrie::new();
+/// let orders = trie.insert(&parse_json_path(b"$.order.id").unwrap()).unwrap();
+/// let refunds = trie.insert(&parse_json_path(b"$.order.refund").unwrap()).unwrap();
+/// let tagged = trie.insert(&parse_json_path(b"$.tags[0]").unwrap()).unwrap();
+///
+/// let value = jsonb::parse_value(br#"{"order":{"id":1},"tags":["a"]}"#).unwrap().to_vec();
+/// assert_eq!(trie.classify(&RawJsonb::new(&value)).unwrap(), vec![orders, tagged]);
+/// assert_ne!(refunds, orders);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PathTrie {
+    // The root node is at index 0.
+    nodes: Vec<Node>,
+    len: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    keys: BTreeMap<String, usize>,
+    any_key: Option<usize>,
+    indices: BTreeMap<usize, usize>,
+    any_index: Option<usize>,
+    // The ids of the paths ending at the node.
+    paths: Vec<usize>,
+}
+
+impl Default for PathTrie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PathTrie {
+    pub fn new() -> PathTrie {
+        PathTrie {
+            nodes: vec![Node::default()],
+            len: 0,
+        }
+    }
+
+    /// Add a path to the trie and returns the id of the path,
+    /// the ids are assigned in the order of insertion starting from 0.
+    pub fn insert(&mut self, json_path: &JsonPath<'_>) -> Result<usize, Error> {
+        let steps = path_steps(json_path)?;
+        let mut node = 0;
+        for step in steps {
+            let next = self.nodes.len();
+            let child = match step {
+                PathStep::Key(name) => *self.nodes[node].keys.entry(name).or_insert(next),
+                PathStep::AnyKey => *self.nodes[node].any_key.get_or_insert(next),
+                PathStep::Index(idx) => *self.nodes[node].indices.entry(idx).or_insert(next),
+                PathStep::AnyIndex => *self.nodes[node].any_index.get_or_insert(next),
+            };
+            if child == next {
+                self.nodes.push(Node::default());
+            }
+            node = child;
+        }
+        let id = self.len;
+        self.nodes[node].paths.push(id);
+        self.len += 1;
+        Ok(id)
+    }
+
+    /// The number of paths in the trie.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the ids of the paths the value satisfies, in ascending order.
+    pub fn classify<T: JsonInput + ?Sized>(&self, value: &T) -> Result<Vec<usize>, Error> {
+        let value = value.to_jsonb()?;
+        let mut matched = vec![false; self.len];
+        self.visit(&[0], &value, &mut matched)?;
+        Ok(matched
+            .into_iter()
+            .enumerate()
+            .filter_map(|(id, matched)| matched.then_some(id))
+            .collect())
+    }
+
+    // Marks the paths ending at the nodes, and visits the nested values of a container
+    // with the nodes the steps lead to.
+    fn visit(&self, nodes: &[usize], value: &[u8], matched: &mut [bool]) -> Result<(), Error> {
+        self.mark(nodes, matched);
+        let reader = ContainerReader::new(value)?;
+        let kind = reader.kind();
+        if kind == ContainerKind::Scalar {
+            return Ok(());
+        }
+        let mut next = Vec::new();
+        for i in 0..reader.len() {
+            next.clear();
+            for node in nodes.iter().map(|node| &self.nodes[*node]) {
+                match kind {
+                    ContainerKind::Object => {
+                        let key = reader.key(i).ok_or(Error::InvalidJsonb)?;
+                        next.extend(node.keys.get(key));
+                        next.extend(node.any_key);
+                    }
+                    _ => {
+                        next.extend(node.indices.get(&i));
+                        next.extend(node.any_index);
+                    }
+                }
+            }
+            if next.is_empty() {
+                continue;
+            }
+            let (entry, data) = reader.value(i).ok_or(Error::InvalidJsonb)?;
+            if entry.kind == EntryKind::Container {
+                self.visit(&next, data, matched)?;
+            } else {
+                self.mark(&next, matched);
+            }
+        }
+        Ok(())
+    }
+
+    fn mark(&self, nodes: &[usize], matched: &mut [bool]) {
+        for node in nodes {
+            for id in &self.nodes[*node].paths {
+                matched[*id] = true;
+            }
+        }
+    }
+}
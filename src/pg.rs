@@ -0,0 +1,711 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Postgres compatible `jsonb` operators and functions.
+//!
+//! Operators are exposed as functions named after them:
+//!
+//! | Operator          | Function                                |
+//! |-------------------|-----------------------------------------|
+//! | `->` text / int   | [`arrow_field`] / [`arrow_index`]       |
+//! | `->>` text / int  | [`arrow_text_field`] / [`arrow_text_index`] |
+//! | `#>`              | [`hash_arrow`]                          |
+//! | `#>>`             | [`hash_arrow_text`]                     |
+//! | `@>` / `<@`       | [`contains`] / [`contained_by`]         |
+//! | `?` / `?\|` / `?&` | [`exists`] / [`exists_any`] / [`exists_all`] |
+//! | `\|\|`            | [`concat`]                              |
+//! | `-` text / text[] / int | [`delete_key`] / [`delete_keys`] / [`delete_index`] |
+//! | `#-`              | [`delete_path`]                         |
+//! | `@?`              | [`path_exists`]                         |
+//! | `@@`              | [`path_match`]                          |
+//!
+//! Functions keep their Postgres names, like [`jsonb_set`] and [`jsonb_path_query`].
+//! A path argument corresponds to a Postgres `text[]`, each element selects a key of
+//! an object or an integer index of an array, negative index counts from the end.
+
+use std::borrow::Cow;
+
+use crate::constants::*;
+use crate::de::from_slice;
+use crate::encoding::ContainerKind;
+use crate::encoding::ContainerReader;
+use crate::encoding::EntryKind;
+use crate::error::Error;
+use crate::functions::array_length;
+use crate::functions::as_str;
+use crate::functions::get_by_index;
+use crate::functions::get_by_name;
+use crate::functions::get_by_path_with_options;
+use crate::functions::is_array;
+use crate::functions::is_null;
+use crate::functions::is_object;
+use crate::functions::pretty;
+use crate::functions::to_string;
+use crate::input::JsonInput;
+use crate::input::RawJsonb;
+use crate::jsonpath::parse_json_path;
+use crate::jsonpath::parse_json_path_predicate;
+use crate::jsonpath::ResultOrder;
+use crate::jsonpath::Selector;
+use crate::jsonpath::SelectorOptions;
+use crate::value::Object;
+use crate::value::Value;
+
+/// `->` text operator, get object field by key.
+pub fn arrow_field<T: JsonInput + ?Sized>(value: &T, key: &str) -> Option<Vec<u8>> {
+    get_by_name(value, key, false)
+}
+
+/// `@?` operator, does the JSON path return any item, like [`jsonb_path_exists`].
+pub fn path_exists<T: JsonInput + ?Sized>(target: &T, path: &str) -> Result<bool, Error> {
+    jsonb_path_exists(target, path)
+}
+
+/// `@@` operator, the result of the JSON path predicate check, like [`jsonb_path_match`].
+/// Returns `None` if the result is not a single boolean.
+pub fn path_match<T: JsonInput + ?Sized>(target: &T, path: &str) -> Result<Option<bool>, Error> {
+    let json_path = match parse_json_path(path.as_bytes()) {
+        Ok(json_path) => json_path,
+        Err(_) => {
+            // The predicate is evaluated as `$ ? (<predicate>)` on the root,
+            // the result is `None` if it's unknown.
+            let json_path = parse_json_path_predicate(path.as_bytes())?;
+            let value = target.to_jsonb()?;
+            return Ok(Selector::new(json_path).predicate(&value));
+        }
+    };
+    let items = get_by_path_with_options(target, json_path, SelectorOptions::default());
+    match items.as_slice() {
+        [item] => Ok(from_slice(item)?.as_bool()),
+        _ => Ok(None),
+    }
+}
+
+/// `->` int operator, get array element, negative index counts from the end.
+pub fn arrow_index<T: JsonInput + ?Sized>(value: &T, index: i32) -> Option<Vec<u8>> {
+    get_index(&value.to_jsonb().ok()?, index)
+}
+
+/// `->>` text operator, get object field by key as text.
+pub fn arrow_text_field<T: JsonInput + ?Sized>(value: &T, key: &str) -> Option<String> {
+    get_by_name(value, key, false).and_then(|value| to_text(&value))
+}
+
+/// `->>` int operator, get array element as text.
+pub fn arrow_text_index<T: JsonInput + ?Sized>(value: &T, index: i32) -> Option<String> {
+    get_index(&value.to_jsonb().ok()?, index).and_then(|value| to_text(&value))
+}
+
+/// `#>` operator, get the value at the specified path.
+pub fn hash_arrow<T: JsonInput + ?Sized>(value: &T, path: &[&str]) -> Option<Vec<u8>> {
+    get_path(value.to_jsonb().ok()?, path).map(Cow::into_owned)
+}
+
+/// `#>>` operator, get the value at the specified path as text.
+pub fn hash_arrow_text<T: JsonInput + ?Sized>(value: &T, path: &[&str]) -> Option<String> {
+    get_path(value.to_jsonb().ok()?, path).and_then(|value| to_text(&value))
+}
+
+/// `@>` operator, does the left value contain the right value.
+pub fn contains<L: JsonInput + ?Sized, R: JsonInput + ?Sized>(
+    left: &L,
+    right: &R,
+) -> Result<bool, Error> {
+    let left = left.to_value()?;
+    let right = right.to_value()?;
+    Ok(contains_value(&left, &right, true))
+}
+
+/// `@>` operator evaluated over many documents with the same right value, the `needle`.
+/// The needle is decoded once, and the documents are rejected by their container type
+/// before they are decoded. For an object needle only the fields with the keys of the needle
+/// are decoded. An invalid document or needle is treated as not contained.
+pub fn contains_batch<'a>(docs: impl Iterator<Item = &'a [u8]>, needle: &[u8]) -> Vec<bool> {
+    match from_slice(needle) {
+        Ok(needle) => docs.map(|doc| contains_needle(doc, &needle)).collect(),
+        Err(_) => docs.map(|_| false).collect(),
+    }
+}
+
+/// `<@` operator, is the left value contained in the right value.
+pub fn contained_by<L: JsonInput + ?Sized, R: JsonInput + ?Sized>(
+    left: &L,
+    right: &R,
+) -> Result<bool, Error> {
+    contains(right, left)
+}
+
+/// `?` operator, does the string exist as a top-level key or array element.
+pub fn exists<T: JsonInput + ?Sized>(value: &T, key: &str) -> bool {
+    exists_any(value, &[key])
+}
+
+/// `?|` operator, do any of the strings exist as top-level keys or array elements.
+pub fn exists_any<T: JsonInput + ?Sized>(value: &T, keys: &[&str]) -> bool {
+    let Ok(value) = value.to_jsonb() else {
+        return false;
+    };
+    match ContainerReader::new(&value) {
+        Ok(reader) => keys.iter().any(|key| exists_key(&reader, key)),
+        Err(_) => false,
+    }
+}
+
+/// `?&` operator, do all of the strings exist as top-level keys or array elements.
+pub fn exists_all<T: JsonInput + ?Sized>(value: &T, keys: &[&str]) -> bool {
+    let Ok(value) = value.to_jsonb() else {
+        return false;
+    };
+    match ContainerReader::new(&value) {
+        Ok(reader) => keys.iter().all(|key| exists_key(&reader, key)),
+        Err(_) => false,
+    }
+}
+
+/// `||` operator, concatenate two values.
+/// Two objects are merged, the fields of the right object take precedence.
+/// Otherwise, the non-array values are treated as single-element arrays and concatenated.
+pub fn concat<L: JsonInput + ?Sized, R: JsonInput + ?Sized>(
+    left: &L,
+    right: &R,
+) -> Result<Vec<u8>, Error> {
+    let left = left.to_value()?;
+    let right = right.to_value()?;
+    let value = match (left, right) {
+        (Value::Object(mut left), Value::Object(right)) => {
+            left.extend(right);
+            Value::Object(left)
+        }
+        (left, right) => {
+            let mut items = into_items(left);
+            items.extend(into_items(right));
+            Value::Array(items)
+        }
+    };
+    Ok(value.to_vec())
+}
+
+/// `-` text operator, delete a key from an object, or matching string elements from an array.
+pub fn delete_key<T: JsonInput + ?Sized>(value: &T, key: &str) -> Result<Vec<u8>, Error> {
+    delete_keys(value, &[key])
+}
+
+/// `-` text[] operator, delete keys from an object, or matching string elements from an array.
+pub fn delete_keys<T: JsonInput + ?Sized>(value: &T, keys: &[&str]) -> Result<Vec<u8>, Error> {
+    let value = match value.to_value()? {
+        Value::Object(mut obj) => {
+            for key in keys {
+                obj.remove(*key);
+            }
+            Value::Object(obj)
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .filter(|item| !matches!(item, Value::String(s) if keys.contains(&s.as_ref())))
+                .collect(),
+        ),
+        _ => return Err(Error::InvalidJsonType),
+    };
+    Ok(value.to_vec())
+}
+
+/// `-` int operator, delete an array element, negative index counts from the end.
+pub fn delete_index<T: JsonInput + ?Sized>(value: &T, index: i32) -> Result<Vec<u8>, Error> {
+    let value = match value.to_value()? {
+        Value::Array(mut items) => {
+            if let Some(idx) = resolve_index(index, items.len()) {
+                items.remove(idx);
+            }
+            Value::Array(items)
+        }
+        _ => return Err(Error::InvalidJsonType),
+    };
+    Ok(value.to_vec())
+}
+
+/// `#-` operator, delete the field or array element at the specified path.
+pub fn delete_path<T: JsonInput + ?Sized>(value: &T, path: &[&str]) -> Result<Vec<u8>, Error> {
+    let mut value = value.to_value()?;
+    if !value.is_array() && !value.is_object() {
+        return Err(Error::InvalidJsonType);
+    }
+    delete_path_value(&mut value, path)?;
+    Ok(value.to_vec())
+}
+
+/// `jsonb_typeof` returns the type of the top-level value.
+pub fn jsonb_typeof<T: JsonInput + ?Sized>(value: &T) -> Result<&'static str, Error> {
+    let ty = match value.to_value()? {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::String(_) => "string",
+        Value::Number(_) => "number",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+        Value::Extension(_) => "extension",
+    };
+    Ok(ty)
+}
+
+/// `jsonb_array_length` returns the number of elements in the top-level array.
+pub fn jsonb_array_length<T: JsonInput + ?Sized>(value: &T) -> Result<usize, Error> {
+    match value.to_value()? {
+        Value::Array(items) => Ok(items.len()),
+        _ => Err(Error::InvalidJsonType),
+    }
+}
+
+/// `jsonb_array_elements` expands the top-level array to a set of values.
+pub fn jsonb_array_elements<T: JsonInput + ?Sized>(value: &T) -> Result<Vec<Vec<u8>>, Error> {
+    match value.to_value()? {
+        Value::Array(items) => Ok(items.iter().map(Value::to_vec).collect()),
+        _ => Err(Error::InvalidJsonType),
+    }
+}
+
+/// `jsonb_array_elements_text` expands the top-level array to a set of text values,
+/// `null` elements are returned as `None`.
+pub fn jsonb_array_elements_text<T: JsonInput + ?Sized>(
+    value: &T,
+) -> Result<Vec<Option<String>>, Error> {
+    let value = value.to_jsonb()?;
+    let value = RawJsonb::new(&value);
+    let items = crate::functions::array_elements_text(&value).ok_or(Error::InvalidJsonType)?;
+    Ok(items.collect())
+}
+
+/// `jsonb_each_text` expands the top-level object to a set of key/value pairs,
+/// the values are returned as text and `null` values are returned as `None`.
+pub fn jsonb_each_text<T: JsonInput + ?Sized>(
+    value: &T,
+) -> Result<Vec<(String, Option<String>)>, Error> {
+    let value = value.to_jsonb()?;
+    let value = RawJsonb::new(&value);
+    let items = crate::functions::object_each_text(&value).ok_or(Error::InvalidJsonType)?;
+    Ok(items.collect())
+}
+
+/// `jsonb_each` expands the top-level object to a set of key/value pairs.
+pub fn jsonb_each<T: JsonInput + ?Sized>(value: &T) -> Result<Vec<(String, Vec<u8>)>, Error> {
+    match value.to_value()? {
+        Value::Object(obj) => Ok(obj.iter().map(|(k, v)| (k.clone(), v.to_vec())).collect()),
+        _ => Err(Error::InvalidJsonType),
+    }
+}
+
+/// `jsonb_extract_path` gets the value at the specified path, like the `#>` operator.
+pub fn jsonb_extract_path<T: JsonInput + ?Sized>(value: &T, path: &[&str]) -> Option<Vec<u8>> {
+    hash_arrow(value, path)
+}
+
+/// `jsonb_extract_path_text` gets the value at the specified path as text,
+/// like the `#>>` operator.
+pub fn jsonb_extract_path_text<T: JsonInput + ?Sized>(value: &T, path: &[&str]) -> Option<String> {
+    hash_arrow_text(value, path)
+}
+
+/// `jsonb_build_array` builds an array of the values.
+pub fn jsonb_build_array<T: JsonInput + ?Sized>(items: &[&T]) -> Result<Vec<u8>, Error> {
+    let items = items
+        .iter()
+        .map(|item| item.to_value())
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::Array(items).to_vec())
+}
+
+/// `jsonb_build_object` builds an object of the key/value pairs,
+/// the last value of a duplicate key is kept.
+pub fn jsonb_build_object<T: JsonInput + ?Sized>(pairs: &[(&str, &T)]) -> Result<Vec<u8>, Error> {
+    let mut obj = Object::new();
+    for (key, value) in pairs {
+        obj.insert(key.to_string(), value.to_value()?);
+    }
+    Ok(Value::Object(obj).to_vec())
+}
+
+/// `jsonb_object_keys` returns the set of keys in the top-level object.
+pub fn jsonb_object_keys<T: JsonInput + ?Sized>(value: &T) -> Result<Vec<String>, Error> {
+    match value.to_value()? {
+        Value::Object(obj) => Ok(obj.into_keys().collect()),
+        _ => Err(Error::InvalidJsonType),
+    }
+}
+
+/// `jsonb_strip_nulls` deletes all object fields that have null values, recursively.
+/// Null values in arrays are untouched.
+pub fn jsonb_strip_nulls<T: JsonInput + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    let mut value = value.to_value()?;
+    strip_nulls(&mut value);
+    Ok(value.to_vec())
+}
+
+/// `jsonb_pretty` returns the value as indented `JSON` text.
+pub fn jsonb_pretty<T: JsonInput + ?Sized>(value: &T) -> Result<String, Error> {
+    let value = value.to_jsonb()?;
+    let mut buf = String::new();
+    pretty(value.as_ref(), &mut buf);
+    Ok(buf)
+}
+
+/// `jsonb_set` replaces the item designated by path with `new_value`,
+/// or adds it if `create_missing` is true and the item doesn't exist.
+/// The parent items of the path must exist, otherwise the value is returned unchanged.
+pub fn jsonb_set<T: JsonInput + ?Sized, V: JsonInput + ?Sized>(
+    target: &T,
+    path: &[&str],
+    new_value: &V,
+    create_missing: bool,
+) -> Result<Vec<u8>, Error> {
+    let mut target = target.to_value()?;
+    let new_value = new_value.to_value()?;
+    if !target.is_array() && !target.is_object() {
+        return Err(Error::InvalidJsonType);
+    }
+    if let Some((last, parents)) = path.split_last() {
+        if let Some(parent) = get_path_mut(&mut target, parents)? {
+            set_value(parent, last, new_value, create_missing)?;
+        }
+    }
+    Ok(target.to_vec())
+}
+
+/// `jsonb_insert` inserts `new_value` into an array before the item designated by path,
+/// or after it if `insert_after` is true. If the path designates an object field,
+/// the value is inserted only if the key doesn't exist.
+pub fn jsonb_insert<T: JsonInput + ?Sized, V: JsonInput + ?Sized>(
+    target: &T,
+    path: &[&str],
+    new_value: &V,
+    insert_after: bool,
+) -> Result<Vec<u8>, Error> {
+    let mut target = target.to_value()?;
+    let new_value = new_value.to_value()?;
+    if !target.is_array() && !target.is_object() {
+        return Err(Error::InvalidJsonType);
+    }
+    if let Some((last, parents)) = path.split_last() {
+        if let Some(parent) = get_path_mut(&mut target, parents)? {
+            match parent {
+                Value::Object(obj) => {
+                    if obj.contains_key(*last) {
+                        return Err(Error::ObjectDuplicateKey);
+                    }
+                    obj.insert(last.to_string(), new_value);
+                }
+                Value::Array(items) => {
+                    let index = parse_path_index(last)?;
+                    let len = items.len() as i64;
+                    // Like Postgres, an index out of range inserts at the nearest end,
+                    // regardless of `insert_after`.
+                    let (mut pos, clamped) = if index < 0 {
+                        let pos = len + index as i64;
+                        (pos.max(0), pos < 0)
+                    } else {
+                        ((index as i64).min(len), index as i64 >= len)
+                    };
+                    if insert_after && !clamped {
+                        pos += 1;
+                    }
+                    items.insert(pos as usize, new_value);
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(target.to_vec())
+}
+
+/// `jsonb_path_query` returns all items returned by the JSON path,
+/// array subscripts return the elements in the order as written.
+pub fn jsonb_path_query<T: JsonInput + ?Sized>(
+    target: &T,
+    path: &str,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let json_path = parse_json_path(path.as_bytes())?;
+    let options = SelectorOptions {
+        order: ResultOrder::Written,
+        ..Default::default()
+    };
+    Ok(get_by_path_with_options(target, json_path, options))
+}
+
+/// `jsonb_path_query` with the `vars` argument, the variables in the JSON path,
+/// like `$min_qty`, are the fields of the `vars` object.
+pub fn jsonb_path_query_with_vars<T: JsonInput + ?Sized, V: JsonInput + ?Sized>(
+    target: &T,
+    path: &str,
+    vars: &V,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let json_path = parse_json_path(path.as_bytes())?;
+    let options = SelectorOptions {
+        order: ResultOrder::Written,
+        ..Default::default()
+    };
+    let target = target.to_jsonb()?;
+    let vars = vars.to_jsonb()?;
+    let selector = Selector::with_options(json_path, options).with_vars(vars.as_ref());
+    Ok(selector.select(target.as_ref()))
+}
+
+/// `jsonb_path_query_first` returns the first item returned by the JSON path.
+pub fn jsonb_path_query_first<T: JsonInput + ?Sized>(
+    target: &T,
+    path: &str,
+) -> Result<Option<Vec<u8>>, Error> {
+    let mut items = jsonb_path_query(target, path)?;
+    if items.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(items.remove(0)))
+    }
+}
+
+/// `jsonb_path_query_array` returns all items returned by the JSON path as an array.
+pub fn jsonb_path_query_array<T: JsonInput + ?Sized>(
+    target: &T,
+    path: &str,
+) -> Result<Vec<u8>, Error> {
+    let items = jsonb_path_query(target, path)?;
+    let mut buf = Vec::new();
+    crate::functions::build_array(items.iter().map(|v| v.as_slice()), &mut buf)?;
+    Ok(buf)
+}
+
+/// `jsonb_path_exists` checks whether the JSON path returns any item.
+pub fn jsonb_path_exists<T: JsonInput + ?Sized>(target: &T, path: &str) -> Result<bool, Error> {
+    Ok(!jsonb_path_query(target, path)?.is_empty())
+}
+
+/// `jsonb_path_match` returns the result of the JSON path predicate check, like `$.a[*] > 2`,
+/// or the single boolean item returned by the JSON path.
+/// Returns `Error::InvalidJsonType` if the result is not a single boolean.
+pub fn jsonb_path_match<T: JsonInput + ?Sized>(target: &T, path: &str) -> Result<bool, Error> {
+    path_match(target, path)?.ok_or(Error::InvalidJsonType)
+}
+
+// Get the element of the `JSONB` array, negative index counts from the end.
+fn get_index(value: &[u8], index: i32) -> Option<Vec<u8>> {
+    let value = RawJsonb::new(value);
+    let idx = resolve_index(index, array_length(&value)?)?;
+    get_by_index(&value, idx)
+}
+
+// Get the `JSONB` value at the path, only the values on the path are copied.
+fn get_path<'a>(value: Cow<'a, [u8]>, path: &[&str]) -> Option<Cow<'a, [u8]>> {
+    let mut current = value;
+    for elem in path {
+        let raw = RawJsonb::new(&current);
+        let next = if is_object(&raw) {
+            get_by_name(&raw, elem, false)?
+        } else if is_array(&raw) {
+            get_index(&current, elem.parse::<i32>().ok()?)?
+        } else {
+            return None;
+        };
+        current = Cow::Owned(next);
+    }
+    Some(current)
+}
+
+// Get the mutable value designated by path, returns None if the path doesn't exist.
+pub(crate) fn get_path_mut<'a, 'b>(
+    value: &'b mut Value<'a>,
+    path: &[&str],
+) -> Result<Option<&'b mut Value<'a>>, Error> {
+    let Some((elem, rest)) = path.split_first() else {
+        return Ok(Some(value));
+    };
+    let next = match value {
+        Value::Object(obj) => obj.get_mut(*elem),
+        Value::Array(items) => {
+            let index = parse_path_index(elem)?;
+            match resolve_index(index, items.len()) {
+                Some(idx) => items.get_mut(idx),
+                None => None,
+            }
+        }
+        _ => None,
+    };
+    match next {
+        Some(next) => get_path_mut(next, rest),
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn set_value<'a>(
+    parent: &mut Value<'a>,
+    key: &str,
+    new_value: Value<'a>,
+    create_missing: bool,
+) -> Result<(), Error> {
+    match parent {
+        Value::Object(obj) if create_missing || obj.contains_key(key) => {
+            obj.insert(key.to_string(), new_value);
+        }
+        Value::Object(_) => {}
+        Value::Array(items) => {
+            let index = parse_path_index(key)?;
+            match resolve_index(index, items.len()) {
+                Some(idx) => items[idx] = new_value,
+                None if create_missing => {
+                    if index < 0 {
+                        items.insert(0, new_value);
+                    } else {
+                        items.push(new_value);
+                    }
+                }
+                None => {}
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+pub(crate) fn delete_path_value(value: &mut Value<'_>, path: &[&str]) -> Result<(), Error> {
+    let Some((last, parents)) = path.split_last() else {
+        return Ok(());
+    };
+    if let Some(parent) = get_path_mut(value, parents)? {
+        match parent {
+            Value::Object(obj) => {
+                obj.remove(*last);
+            }
+            Value::Array(items) => {
+                let index = parse_path_index(last)?;
+                if let Some(idx) = resolve_index(index, items.len()) {
+                    items.remove(idx);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Path elements used to access arrays must be integers.
+fn parse_path_index(elem: &str) -> Result<i32, Error> {
+    elem.trim()
+        .parse::<i32>()
+        .map_err(|_| Error::InvalidKeyPath)
+}
+
+// Convert an index to array position, negative index counts from the end.
+fn resolve_index(index: i32, len: usize) -> Option<usize> {
+    let idx = if index < 0 {
+        len as i64 + index as i64
+    } else {
+        index as i64
+    };
+    if idx >= 0 && idx < len as i64 {
+        Some(idx as usize)
+    } else {
+        None
+    }
+}
+
+fn to_text(value: &[u8]) -> Option<String> {
+    let value = RawJsonb::new(value);
+    if is_null(&value) {
+        return None;
+    }
+    match as_str(&value) {
+        Some(s) => Some(s.into_owned()),
+        None => Some(to_string(&value)),
+    }
+}
+
+// The keys of an object, the string elements of an array, or a string scalar
+// are compared without decoding the values.
+fn exists_key(reader: &ContainerReader<'_>, key: &str) -> bool {
+    match reader.kind() {
+        ContainerKind::Object => (0..reader.len()).any(|i| reader.key(i) == Some(key)),
+        _ => (0..reader.len()).any(|i| {
+            matches!(reader.value(i), Some((entry, data))
+                if entry.kind == EntryKind::String && data == key.as_bytes())
+        }),
+    }
+}
+
+fn into_items(value: Value<'_>) -> Vec<Value<'_>> {
+    match value {
+        Value::Array(items) => items,
+        _ => vec![value],
+    }
+}
+
+// Containment follows the rules of Postgres:
+// an object contains another object if it contains all the key/value pairs,
+// an array contains another array if every element is contained by some element,
+// regardless of order and duplicates. As a special exception, a top-level array
+// contains a primitive value if the value is one of its elements.
+pub(crate) fn contains_value(left: &Value<'_>, right: &Value<'_>, top_level: bool) -> bool {
+    match (left, right) {
+        (Value::Object(left), Value::Object(right)) => right.iter().all(|(key, rval)| {
+            left.get(key)
+                .map_or(false, |lval| contains_value(lval, rval, false))
+        }),
+        (Value::Array(left), Value::Array(right)) => right
+            .iter()
+            .all(|rval| left.iter().any(|lval| contains_value(lval, rval, false))),
+        (Value::Array(left), rval) if top_level && !rval.is_object() => {
+            left.iter().any(|lval| lval == rval)
+        }
+        (Value::Array(_) | Value::Object(_), _) | (_, Value::Array(_) | Value::Object(_)) => false,
+        (left, right) => left == right,
+    }
+}
+
+fn contains_needle(doc: &[u8], needle: &Value<'_>) -> bool {
+    let Some(header) = doc.get(0..4) else {
+        return false;
+    };
+    let header = u32::from_be_bytes(header.try_into().unwrap());
+    let ty = header & CONTAINER_HEADER_TYPE_MASK;
+    match needle {
+        Value::Object(fields) => {
+            let length = (header & CONTAINER_HEADER_LEN_MASK) as usize;
+            ty == OBJECT_CONTAINER_TAG
+                && length >= fields.len()
+                && fields
+                    .iter()
+                    .all(|(key, rval)| match get_by_name(doc, key, false) {
+                        Some(lval) => from_slice(&lval)
+                            .map_or(false, |lval| contains_value(&lval, rval, false)),
+                        None => false,
+                    })
+        }
+        Value::Array(_) if ty != ARRAY_CONTAINER_TAG => false,
+        _ if ty == OBJECT_CONTAINER_TAG => false,
+        _ => from_slice(doc).map_or(false, |doc| contains_value(&doc, needle, true)),
+    }
+}
+
+fn strip_nulls(value: &mut Value<'_>) {
+    match value {
+        Value::Object(obj) => {
+            obj.retain(|_, val| !val.is_null());
+            for val in obj.values_mut() {
+                strip_nulls(val);
+            }
+        }
+        Value::Array(items) => {
+            for item in items.iter_mut() {
+                strip_nulls(item);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,300 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+
+use crate::constants::*;
+use crate::error::Error;
+use crate::functions::build_array;
+use crate::input::JsonInput;
+use crate::jentry::JEntry;
+use crate::jsonpath::decode_scalar_value;
+use crate::jsonpath::JsonPath;
+use crate::jsonpath::PathValue;
+use crate::jsonpath::Selector;
+
+/// Represents the comparison operators of [`Predicate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOperator {
+    /// `=`
+    Eq,
+    /// `!=`
+    NotEq,
+    /// `<`
+    Lt,
+    /// `<=`
+    Lte,
+    /// `>`
+    Gt,
+    /// `>=`
+    Gte,
+}
+
+/// Represents a predicate tree evaluated by [`matches`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate<'a> {
+    /// Compare the values selected by the path with the literal value,
+    /// true if any of the selected values satisfies the comparison.
+    Compare {
+        path: JsonPath<'a>,
+        op: CompareOperator,
+        value: PathValue<'a>,
+    },
+    /// True if the path selects any value.
+    Exists(JsonPath<'a>),
+    /// True if all the predicates are true, or there are no predicates.
+    And(Vec<Predicate<'a>>),
+    /// True if any of the predicates is true.
+    Or(Vec<Predicate<'a>>),
+    /// Negates the predicate.
+    Not(Box<Predicate<'a>>),
+}
+
+/// Evaluate the predicate over the `JSONB` value.
+///
+/// The predicates of `And` and `Or` are evaluated from left to right and stop as soon as
+/// the result is known. Only values of the same type can be compared, comparisons between
+/// different types and with containers are false, like `"1" = 1`.
+/// The `Compare` and `Exists` predicates are false for invalid values, and are combined
+/// by `And`, `Or` and `Not` as usual, so `Not(Exists(..))` is true for invalid values.
+pub fn matches<T: JsonInput + ?Sized>(value: &T, predicate: &Predicate<'_>) -> bool {
+    let value = value.to_jsonb().ok();
+    matches_jsonb(value.as_deref(), predicate)
+}
+
+fn matches_jsonb(value: Option<&[u8]>, predicate: &Predicate<'_>) -> bool {
+    match predicate {
+        Predicate::Compare {
+            path,
+            op,
+            value: literal,
+        } => {
+            let Some(value) = value else {
+                return false;
+            };
+            let selector = Selector::from_ref(path);
+            selector.select(value).iter().any(|v| {
+                decode_scalar_value(v)
+                    .and_then(|v| compare_scalar(&v, literal))
+                    .map(|order| match op {
+                        CompareOperator::Eq => order == Ordering::Equal,
+                        CompareOperator::NotEq => order != Ordering::Equal,
+                        CompareOperator::Lt => order == Ordering::Less,
+                        CompareOperator::Lte => order != Ordering::Greater,
+                        CompareOperator::Gt => order == Ordering::Greater,
+                        CompareOperator::Gte => order != Ordering::Less,
+                    })
+                    .unwrap_or(false)
+            })
+        }
+        Predicate::Exists(path) => {
+            let Some(value) = value else {
+                return false;
+            };
+            let selector = Selector::from_ref(path);
+            !selector.select(value).is_empty()
+        }
+        Predicate::And(predicates) => predicates.iter().all(|p| matches_jsonb(value, p)),
+        Predicate::Or(predicates) => predicates.iter().any(|p| matches_jsonb(value, p)),
+        Predicate::Not(predicate) => !matches_jsonb(value, predicate),
+    }
+}
+
+/// Predicates over the encoded `JSONB` elements, used by [`array_any`], [`array_all`],
+/// [`array_update_where`] and the batch predicates like [`filter_matching`].
+///
+/// Implemented for closures over the encoded elements, [`Predicate`]s and `JSONPath`s,
+/// a `JSONPath` is true if it selects any value from the element, like `$ ? (@ > 5)`.
+pub trait ElementPredicate {
+    fn test(&self, element: &[u8]) -> bool;
+}
+
+impl<F: Fn(&[u8]) -> bool> ElementPredicate for F {
+    fn test(&self, element: &[u8]) -> bool {
+        self(element)
+    }
+}
+
+impl ElementPredicate for Predicate<'_> {
+    fn test(&self, element: &[u8]) -> bool {
+        matches_jsonb(Some(element), self)
+    }
+}
+
+impl ElementPredicate for JsonPath<'_> {
+    fn test(&self, element: &[u8]) -> bool {
+        Selector::from_ref(self).select_first(element).is_some()
+    }
+}
+
+/// Returns true if any element of the `JSONB` array satisfies the predicate,
+/// like SQL `ANY`. Returns `None` if the value is not an array.
+///
+/// The elements are tested one by one and stop as soon as the result is known.
+///
+/// ```
+/// use jsonb::array_any;
+/// use jsonb::jsonpath::parse_json_path;
+///
+/// let path = parse_json_path(b"$ ? (@ > 5)").unwrap();
+/// assert_eq!(array_any("[1,7,3]", &path), Some(true));
+/// assert_eq!(array_any("[]", &path), Some(false));
+/// assert_eq!(array_any("{}", &path), None);
+/// ```
+pub fn array_any<T: JsonInput + ?Sized, P: ElementPredicate + ?Sized>(
+    value: &T,
+    predicate: &P,
+) -> Option<bool> {
+    let value = value.to_jsonb().ok()?;
+    find_element(value.as_ref(), |element| predicate.test(element))
+}
+
+/// Returns true if all the elements of the `JSONB` array satisfy the predicate,
+/// like SQL `ALL`, an empty array is true. Returns `None` if the value is not an array.
+pub fn array_all<T: JsonInput + ?Sized, P: ElementPredicate + ?Sized>(
+    value: &T,
+    predicate: &P,
+) -> Option<bool> {
+    let value = value.to_jsonb().ok()?;
+    find_element(value.as_ref(), |element| !predicate.test(element)).map(|found| !found)
+}
+
+/// Replace the elements of the `JSONB` array that satisfy the predicate with the `JSONB`
+/// value `new_element`, and write the new array to `buf`.
+/// Returns the number of the replaced elements, or [`Error::InvalidJsonType`] if the value
+/// is not an array.
+///
+/// ```
+/// use jsonb::array_update_where;
+/// use jsonb::jsonpath::parse_json_path;
+///
+/// let phones = r#"[{"type":"home","no":"1"},{"type":"work","no":"2"}]"#;
+/// let path = parse_json_path(br#"$ ? (@.type == "home")"#).unwrap();
+/// let new_phone = jsonb::parse_value(br#"{"type":"home","no":"3"}"#).unwrap().to_vec();
+/// let mut buf = Vec::new();
+/// assert_eq!(array_update_where(phones, &path, &new_phone, &mut buf), Ok(1));
+/// assert_eq!(
+///     jsonb::to_string(&buf),
+///     r#"[{"no":"3","type":"home"},{"no":"2","type":"work"}]"#
+/// );
+/// ```
+pub fn array_update_where<T: JsonInput + ?Sized, P: ElementPredicate + ?Sized>(
+    value: &T,
+    predicate: &P,
+    new_element: &[u8],
+    buf: &mut Vec<u8>,
+) -> Result<usize, Error> {
+    let value = value.to_jsonb()?;
+    let mut elements = Vec::new();
+    let mut updated = 0;
+    find_element(value.as_ref(), |element| {
+        if predicate.test(element) {
+            elements.push(new_element.to_vec());
+            updated += 1;
+        } else {
+            elements.push(element.to_vec());
+        }
+        false
+    })
+    .ok_or(Error::InvalidJsonType)?;
+    build_array(elements.iter().map(|element| element.as_slice()), buf)?;
+    Ok(updated)
+}
+
+/// Returns true if any of the `JSONB` documents satisfies the predicate, like a semi-join
+/// with `EXISTS`. The documents are tested one by one and stop at the first match.
+///
+/// A `JSONPath` is true if it selects any value from the document,
+/// the path is parsed once and shared by all the documents.
+///
+/// ```
+/// use jsonb::exists_in_any;
+/// use jsonb::filter_matching;
+/// use jsonb::jsonpath::parse_json_path;
+///
+/// let docs: Vec<_> = [r#"{"a":1}"#, r#"{"b":2}"#, r#"{"a":3}"#]
+///     .iter()
+///     .map(|doc| jsonb::parse_value(doc.as_bytes()).unwrap().to_vec())
+///     .collect();
+/// let path = parse_json_path(b"$.a ? (@ > 2)").unwrap();
+/// assert!(exists_in_any(docs.iter().map(|doc| doc.as_slice()), &path));
+/// assert_eq!(filter_matching(docs.iter().map(|doc| doc.as_slice()), &path), vec![2]);
+/// ```
+pub fn exists_in_any<'a, P: ElementPredicate + ?Sized>(
+    values: impl IntoIterator<Item = &'a [u8]>,
+    predicate: &P,
+) -> bool {
+    values.into_iter().any(|value| predicate.test(value))
+}
+
+/// Returns the positions of the `JSONB` documents that satisfy the predicate,
+/// see [`exists_in_any`].
+pub fn filter_matching<'a, P: ElementPredicate + ?Sized>(
+    values: impl IntoIterator<Item = &'a [u8]>,
+    predicate: &P,
+) -> Vec<usize> {
+    values
+        .into_iter()
+        .enumerate()
+        .filter(|(_, value)| predicate.test(value))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// Returns true if `f` is true for any element of the array, the elements are not copied
+// except the scalars, which are encoded into a reused buffer.
+fn find_element(value: &[u8], mut f: impl FnMut(&[u8]) -> bool) -> Option<bool> {
+    let header = read_u32(value, 0)?;
+    if header & CONTAINER_HEADER_TYPE_MASK != ARRAY_CONTAINER_TAG {
+        return None;
+    }
+    let length = (header & CONTAINER_HEADER_LEN_MASK) as usize;
+    let mut val_offset = 4 + 4 * length;
+    let mut scalar = Vec::new();
+    for i in 0..length {
+        let encoded = read_u32(value, 4 + 4 * i)?;
+        let jentry = JEntry::decode_jentry(encoded);
+        let data = value.get(val_offset..val_offset + jentry.length as usize)?;
+        val_offset += jentry.length as usize;
+        let element = match jentry.type_code {
+            CONTAINER_TAG => data,
+            _ => {
+                scalar.clear();
+                scalar.extend_from_slice(&SCALAR_CONTAINER_TAG.to_be_bytes());
+                scalar.extend_from_slice(&encoded.to_be_bytes());
+                scalar.extend_from_slice(data);
+                &scalar
+            }
+        };
+        if f(element) {
+            return Some(true);
+        }
+    }
+    Some(false)
+}
+
+fn read_u32(value: &[u8], offset: usize) -> Option<u32> {
+    let bytes = value.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn compare_scalar(left: &PathValue<'_>, right: &PathValue<'_>) -> Option<Ordering> {
+    match (left, right) {
+        (PathValue::Null, PathValue::Null) => Some(Ordering::Equal),
+        (PathValue::Boolean(l), PathValue::Boolean(r)) => Some(l.cmp(r)),
+        (PathValue::Number(l), PathValue::Number(r)) => Some(l.cmp(r)),
+        (PathValue::String(l), PathValue::String(r)) => Some(l.cmp(r)),
+        (_, _) => None,
+    }
+}
@@ -0,0 +1,119 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::encoding::ContainerKind;
+use crate::encoding::ContainerReader;
+
+/// A fixed-length vector of bits, one bit per requested key of [`presence_bitmap`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BitVec {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitVec {
+    /// Creates a vector of `len` bits, all unset.
+    pub fn new(len: usize) -> BitVec {
+        BitVec {
+            words: vec![0; (len + 63) / 64],
+            len,
+        }
+    }
+
+    /// Returns the number of bits.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the vector has no bits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the bit at `idx`, false if it is out of range.
+    pub fn get(&self, idx: usize) -> bool {
+        idx < self.len && self.words[idx / 64] & (1 << (idx % 64)) != 0
+    }
+
+    /// Sets the bit at `idx`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of range.
+    pub fn set(&mut self, idx: usize, bit: bool) {
+        assert!(
+            idx < self.len,
+            "index {idx} out of range for {} bits",
+            self.len
+        );
+        if bit {
+            self.words[idx / 64] |= 1 << (idx % 64);
+        } else {
+            self.words[idx / 64] &= !(1 << (idx % 64));
+        }
+    }
+
+    /// Returns the number of set bits.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Returns an iterator over the bits.
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(|idx| self.get(idx))
+    }
+}
+
+/// Reports for each document which of the `keys` exist as its top-level keys,
+/// bit `i` of a document's [`BitVec`] is set if `keys[i]` exists, even if its value is `null`.
+///
+/// Only the object header, the `JEntry`s and the keys are read, the values are never decoded,
+/// so it is cheap enough to push `IS NULL` and `IS NOT NULL` filters down to the variant columns.
+/// A document that is not an object, or is invalid, has no bits set.
+pub fn presence_bitmap<'a>(docs: impl Iterator<Item = &'a [u8]>, keys: &[&str]) -> Vec<BitVec> {
+    // Sort the requested keys once to look up each object key by binary search,
+    // which doesn't depend on the object keys being stored in sorted order.
+    let mut sorted: Vec<(&[u8], usize)> = keys
+        .iter()
+        .enumerate()
+        .map(|(idx, key)| (key.as_bytes(), idx))
+        .collect();
+    sorted.sort();
+    docs.map(|doc| {
+        let mut bits = BitVec::new(keys.len());
+        if !sorted.is_empty() && mark_keys(doc, &sorted, &mut bits).is_none() {
+            bits = BitVec::new(keys.len());
+        }
+        bits
+    })
+    .collect()
+}
+
+fn mark_keys(doc: &[u8], sorted: &[(&[u8], usize)], bits: &mut BitVec) -> Option<()> {
+    let reader = ContainerReader::new(doc).ok()?;
+    if reader.kind() != ContainerKind::Object {
+        return None;
+    }
+    for i in 0..reader.len() {
+        let key = reader.key(i)?.as_bytes();
+        let start = sorted.partition_point(|(requested, _)| *requested < key);
+        for (_, idx) in sorted[start..]
+            .iter()
+            .take_while(|(requested, _)| *requested == key)
+        {
+            bits.set(*idx, true);
+        }
+    }
+    Some(())
+}
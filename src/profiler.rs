@@ -0,0 +1,486 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::error::Error;
+use crate::input::JsonInput;
+use crate::jsonpath::BinaryOperator;
+use crate::jsonpath::Expr;
+use crate::jsonpath::FilterFunc;
+use crate::jsonpath::JsonPath;
+use crate::jsonpath::Path;
+use crate::jsonpath::PathValue;
+use crate::value::Value;
+
+// The default selectivity of an equality and an inequality comparison in a filter expression,
+// the same as the defaults of the Postgres planner.
+const DEFAULT_EQ_SELECTIVITY: f64 = 0.005;
+const DEFAULT_INEQ_SELECTIVITY: f64 = 1.0 / 3.0;
+
+/// The statistics of a key or a path collected by [`KeyProfiler`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyStats {
+    /// The number of documents that contain the key or the path.
+    pub documents: u64,
+    /// The number of occurrences, a path inside arrays can occur many times in a document.
+    pub count: u64,
+    pub null_count: u64,
+    pub boolean_count: u64,
+    pub number_count: u64,
+    pub string_count: u64,
+    pub array_count: u64,
+    pub object_count: u64,
+}
+
+impl KeyStats {
+    fn record(&mut self, value: &Value<'_>) {
+        self.count += 1;
+        match value {
+            Value::Null => self.null_count += 1,
+            Value::Bool(_) => self.boolean_count += 1,
+            Value::Number(_) => self.number_count += 1,
+            Value::String(_) | Value::Extension(_) => self.string_count += 1,
+            Value::Array(_) => self.array_count += 1,
+            Value::Object(_) => self.object_count += 1,
+        }
+    }
+
+    /// Returns true if all the occurrences have the same type.
+    pub fn is_single_type(&self) -> bool {
+        let counts = [
+            self.null_count,
+            self.boolean_count,
+            self.number_count,
+            self.string_count,
+            self.array_count,
+            self.object_count,
+        ];
+        counts.iter().filter(|c| **c > 0).count() <= 1
+    }
+}
+
+/// Collects the frequency and the type mix of the keys and the paths across many documents,
+/// to find out the paths that are worth extracting into real columns.
+///
+/// The paths are written as `JSONPath`, like `$.a.b`, array elements are written as `[*]`
+/// and the keys that are not identifiers are quoted, like `$.items[*]["unit price"]`.
+///
+/// ```
+/// use jsonb::KeyProfiler;
+///
+/// let mut profiler = KeyProfiler::new();
+/// profiler.update(r#"{"id":1,"tags":["a"]}"#).unwrap();
+/// profiler.update(r#"{"id":"2"}"#).unwrap();
+///
+/// let (path, stats) = &profiler.top_paths(1)[0];
+/// assert_eq!(path, "$.id");
+/// assert_eq!(stats.documents, 2);
+/// assert!(!stats.is_single_type());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct KeyProfiler {
+    documents: u64,
+    keys: HashMap<String, KeyStats>,
+    paths: HashMap<String, KeyStats>,
+}
+
+impl KeyProfiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collect the keys and the paths of the document.
+    pub fn update<T: JsonInput + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let value = value.to_value()?;
+        self.documents += 1;
+        let mut seen_keys = HashSet::new();
+        let mut seen_paths = HashSet::new();
+        self.collect(
+            &value,
+            &mut String::from("$"),
+            &mut seen_keys,
+            &mut seen_paths,
+        );
+        for key in seen_keys {
+            self.keys.get_mut(&key).unwrap().documents += 1;
+        }
+        for path in seen_paths {
+            self.paths.get_mut(&path).unwrap().documents += 1;
+        }
+        Ok(())
+    }
+
+    fn collect(
+        &mut self,
+        value: &Value<'_>,
+        path: &mut String,
+        seen_keys: &mut HashSet<String>,
+        seen_paths: &mut HashSet<String>,
+    ) {
+        match value {
+            Value::Object(obj) => {
+                for (key, val) in obj.iter() {
+                    let len = path.len();
+                    push_key(path, key);
+                    self.keys.entry(key.clone()).or_default().record(val);
+                    self.paths.entry(path.clone()).or_default().record(val);
+                    seen_keys.insert(key.clone());
+                    seen_paths.insert(path.clone());
+                    self.collect(val, path, seen_keys, seen_paths);
+                    path.truncate(len);
+                }
+            }
+            Value::Array(vals) => {
+                let len = path.len();
+                path.push_str("[*]");
+                for val in vals.iter() {
+                    self.paths.entry(path.clone()).or_default().record(val);
+                    seen_paths.insert(path.clone());
+                    self.collect(val, path, seen_keys, seen_paths);
+                }
+                path.truncate(len);
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the number of collected documents.
+    pub fn documents(&self) -> u64 {
+        self.documents
+    }
+
+    /// Returns the `k` most common keys, ordered by the number of documents
+    /// and the number of occurrences.
+    pub fn top_keys(&self, k: usize) -> Vec<(String, KeyStats)> {
+        top_k(&self.keys, k)
+    }
+
+    /// Returns the `k` most common paths, ordered by the number of documents
+    /// and the number of occurrences.
+    pub fn top_paths(&self, k: usize) -> Vec<(String, KeyStats)> {
+        top_k(&self.paths, k)
+    }
+
+    /// Merge the statistics collected by another profiler.
+    pub fn merge(&mut self, other: &KeyProfiler) {
+        self.documents += other.documents;
+        for (map, other_map) in [
+            (&mut self.keys, &other.keys),
+            (&mut self.paths, &other.paths),
+        ] {
+            for (key, stats) in other_map.iter() {
+                let entry = map.entry(key.clone()).or_default();
+                entry.documents += stats.documents;
+                entry.count += stats.count;
+                entry.null_count += stats.null_count;
+                entry.boolean_count += stats.boolean_count;
+                entry.number_count += stats.number_count;
+                entry.string_count += stats.string_count;
+                entry.array_count += stats.array_count;
+                entry.object_count += stats.object_count;
+            }
+        }
+    }
+}
+
+/// The estimated selectivity of a JSON path, see [`estimate`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Selectivity {
+    /// The estimated fraction of the documents that have a value matched by the path, from 0 to 1.
+    pub fraction: f64,
+    /// True if the fraction is partly guessed with the default selectivity of the comparisons
+    /// in the filter expressions, or there are no statistics at all,
+    /// instead of only derived from the collected statistics.
+    pub guessed: bool,
+}
+
+/// Estimate the fraction of the documents that have a value matched by the JSON path,
+/// from the statistics of the paths collected by a [`KeyProfiler`] over a sample of the documents,
+/// so a cost-based optimizer can decide whether to push the `JSON` predicates into the scans.
+///
+/// - The member accessors follow the collected paths, and the wildcards of objects cover all
+///   the collected keys. The paths are assumed to occur independently of each other.
+/// - Array indices are estimated like `[*]`, as the lengths of the arrays are not collected.
+/// - A comparison in a filter expression has the default selectivity of the Postgres planner,
+///   scaled by the fraction of the values that have the compared paths.
+///
+/// ```
+/// use jsonb::jsonpath::parse_json_path;
+/// use jsonb::{estimate, KeyProfiler};
+///
+/// let mut profiler = KeyProfiler::new();
+/// for i in 0..100 {
+///     let doc = if i % 4 == 0 { r#"{"a":{"b":1}}"# } else { r#"{"a":{}}"# };
+///     profiler.update(doc).unwrap();
+/// }
+/// let selectivity = estimate(&parse_json_path(b"$.a.b").unwrap(), &profiler);
+/// assert_eq!(selectivity.fraction, 0.25);
+/// assert!(!selectivity.guessed);
+/// ```
+pub fn estimate(json_path: &JsonPath<'_>, stats: &KeyProfiler) -> Selectivity {
+    if stats.documents == 0 {
+        return Selectivity {
+            fraction: 1.0,
+            guessed: true,
+        };
+    }
+    let mut paths = vec![String::from("$")];
+    let mut factor = 1.0;
+    let mut guessed = false;
+    for step in json_path.paths.iter() {
+        match step {
+            Path::Root | Path::Current => {}
+            Path::DotWildcard => {
+                paths = paths
+                    .iter()
+                    .flat_map(|path| stats.child_paths(path))
+                    .collect();
+            }
+            Path::ObjectFields(names) => {
+                paths = paths
+                    .iter()
+                    .flat_map(|path| {
+                        names.iter().map(move |name| {
+                            let mut path = path.clone();
+                            push_key(&mut path, name);
+                            path
+                        })
+                    })
+                    .collect();
+            }
+            Path::RecursiveDescent => {
+                paths = paths
+                    .iter()
+                    .flat_map(|path| stats.descendant_paths(path))
+                    .collect();
+            }
+            Path::RecursiveWildcard => {
+                paths = paths
+                    .iter()
+                    .flat_map(|path| stats.descendant_paths(path).into_iter().skip(1))
+                    .collect();
+            }
+            Path::FilterExpr(expr) => {
+                factor *= stats.expr_selectivity(expr, &paths);
+                guessed = true;
+            }
+            step => {
+                for path in paths.iter_mut() {
+                    push_step(path, step);
+                }
+            }
+        }
+    }
+    let missing: f64 = paths
+        .iter()
+        .map(|path| 1.0 - stats.documents_of(path) as f64 / stats.documents as f64)
+        .product();
+    Selectivity {
+        fraction: (1.0 - missing) * factor,
+        guessed,
+    }
+}
+
+impl KeyProfiler {
+    fn documents_of(&self, path: &str) -> u64 {
+        match path {
+            "$" => self.documents,
+            path => self
+                .paths
+                .get(path)
+                .map(|stats| stats.documents)
+                .unwrap_or(0),
+        }
+    }
+
+    fn count_of(&self, path: &str) -> u64 {
+        match path {
+            "$" => self.documents,
+            path => self.paths.get(path).map(|stats| stats.count).unwrap_or(0),
+        }
+    }
+
+    // The collected paths of the values of the objects at the path.
+    fn child_paths(&self, path: &str) -> Vec<String> {
+        let mut children: Vec<_> = self
+            .paths
+            .keys()
+            .filter(|child| child.strip_prefix(path).map(is_single_key).unwrap_or(false))
+            .cloned()
+            .collect();
+        children.sort();
+        children
+    }
+
+    // The path and the collected paths nested in it.
+    fn descendant_paths(&self, path: &str) -> Vec<String> {
+        let mut descendants: Vec<_> = self
+            .paths
+            .keys()
+            .filter(|descendant| {
+                matches!(descendant.strip_prefix(path),
+                    Some(rest) if rest.starts_with('.') || rest.starts_with('['))
+            })
+            .cloned()
+            .collect();
+        descendants.sort();
+        descendants.insert(0, path.to_string());
+        descendants
+    }
+
+    fn expr_selectivity(&self, expr: &Expr<'_>, paths: &[String]) -> f64 {
+        match expr {
+            Expr::BinaryOp {
+                op: BinaryOperator::And,
+                left,
+                right,
+            } => self.expr_selectivity(left, paths) * self.expr_selectivity(right, paths),
+            Expr::BinaryOp {
+                op: BinaryOperator::Or,
+                left,
+                right,
+            } => {
+                let left = self.expr_selectivity(left, paths);
+                let right = self.expr_selectivity(right, paths);
+                left + right - left * right
+            }
+            Expr::BinaryOp { op, left, right } => {
+                let selectivity = match op {
+                    BinaryOperator::Eq => DEFAULT_EQ_SELECTIVITY,
+                    BinaryOperator::NotEq => 1.0 - DEFAULT_EQ_SELECTIVITY,
+                    _ => DEFAULT_INEQ_SELECTIVITY,
+                };
+                selectivity * self.presence(left, paths) * self.presence(right, paths)
+            }
+            Expr::Value(value) => match **value {
+                PathValue::Boolean(true) => 1.0,
+                PathValue::Boolean(false) | PathValue::Null => 0.0,
+                _ => 1.0,
+            },
+            Expr::Paths(_) => self.presence(expr, paths),
+            Expr::FilterFunc(FilterFunc::Exists(steps)) => self.steps_presence(steps, paths),
+            Expr::FilterFunc(
+                FilterFunc::LikeRegex { paths: steps, .. }
+                | FilterFunc::StartsWith { paths: steps, .. },
+            ) => DEFAULT_INEQ_SELECTIVITY * self.steps_presence(steps, paths),
+        }
+    }
+
+    // The fraction of the current values that have the relative path of the expression,
+    // other expressions are always present.
+    fn presence(&self, expr: &Expr<'_>, paths: &[String]) -> f64 {
+        let Expr::Paths(steps) = expr else {
+            return 1.0;
+        };
+        self.steps_presence(steps, paths)
+    }
+
+    // The fraction of the current values that have the relative path of the steps.
+    fn steps_presence(&self, steps: &[Path<'_>], paths: &[String]) -> f64 {
+        if steps.first() != Some(&Path::Current) {
+            return 1.0;
+        }
+        let mut count = 0;
+        let mut present = 0;
+        for path in paths {
+            let mut relative = path.clone();
+            for step in &steps[1..] {
+                if !push_step(&mut relative, step) {
+                    return 1.0;
+                }
+            }
+            count += self.count_of(path);
+            present += self.count_of(&relative);
+        }
+        if count == 0 {
+            return 0.0;
+        }
+        (present as f64 / count as f64).min(1.0)
+    }
+}
+
+// Append the step to the collected path, returns false if the step can't be collected.
+fn push_step(path: &mut String, step: &Path<'_>) -> bool {
+    match step {
+        Path::DotField(name) | Path::ColonField(name) | Path::ObjectField(name) => {
+            push_key(path, name);
+        }
+        Path::BracketWildcard | Path::ArrayIndices(_) => path.push_str("[*]"),
+        _ => return false,
+    }
+    true
+}
+
+// Whether the rest of a collected path is a single key written by `push_key`.
+fn is_single_key(rest: &str) -> bool {
+    if let Some(key) = rest.strip_prefix('.') {
+        return !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    }
+    let Some(key) = rest
+        .strip_prefix("[\"")
+        .and_then(|rest| rest.strip_suffix("\"]"))
+    else {
+        return false;
+    };
+    // The quotation marks in the key are escaped.
+    let mut escaped = false;
+    for c in key.chars() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return false,
+            _ => escaped = false,
+        }
+    }
+    !escaped
+}
+
+fn top_k(map: &HashMap<String, KeyStats>, k: usize) -> Vec<(String, KeyStats)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by(|(lk, ls), (rk, rs)| {
+        rs.documents
+            .cmp(&ls.documents)
+            .then(rs.count.cmp(&ls.count))
+            .then(lk.cmp(rk))
+    });
+    entries
+        .into_iter()
+        .take(k)
+        .map(|(key, stats)| (key.clone(), stats.clone()))
+        .collect()
+}
+
+// Identifiers are written as `.key`, other keys are quoted as `["key"]`.
+pub(crate) fn push_key(path: &mut String, key: &str) {
+    let is_ident = key
+        .chars()
+        .next()
+        .map(|c| c.is_ascii_alphabetic() || c == '_')
+        .unwrap_or(false)
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_ident {
+        path.push('.');
+        path.push_str(key);
+    } else {
+        path.push_str("[\"");
+        for c in key.chars() {
+            if c == '"' || c == '\\' {
+                path.push('\\');
+            }
+            path.push(c);
+        }
+        path.push_str("\"]");
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::Error;
+use crate::input::JsonInput;
+use crate::value::Value;
+
+/// The kinds of nodes removed by [`prune`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PruneOptions {
+    pub nulls: bool,
+    pub empty_objects: bool,
+    pub empty_arrays: bool,
+    /// Whether the elements of arrays are removed, otherwise only the fields of objects,
+    /// so the positions of the elements are kept.
+    pub array_elements: bool,
+}
+
+impl Default for PruneOptions {
+    fn default() -> Self {
+        Self {
+            nulls: true,
+            empty_objects: true,
+            empty_arrays: true,
+            array_elements: true,
+        }
+    }
+}
+
+/// The number of the nodes removed by [`prune`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    pub nulls: usize,
+    pub empty_objects: usize,
+    pub empty_arrays: usize,
+}
+
+impl PruneReport {
+    /// The total number of the removed nodes.
+    pub fn total(&self) -> usize {
+        self.nulls + self.empty_objects + self.empty_arrays
+    }
+}
+
+/// Remove the `null` values, the empty objects and the empty arrays selected by the options
+/// from a `JSONB` value, including the nested values, and write the value to `buf`.
+/// The containers that become empty after their values are removed are also removed,
+/// the top-level value is always kept.
+///
+/// ```
+/// use jsonb::{prune, PruneOptions};
+///
+/// let mut buf = Vec::new();
+/// let value = r#"{"a":null,"b":{"c":[]},"d":[1,null,{}]}"#;
+/// let report = prune(value, &PruneOptions::default(), &mut buf).unwrap();
+/// assert_eq!(jsonb::to_string(&buf), r#"{"d":[1]}"#);
+/// assert_eq!(report.total(), 5);
+/// ```
+pub fn prune<T: JsonInput + ?Sized>(
+    value: &T,
+    options: &PruneOptions,
+    buf: &mut Vec<u8>,
+) -> Result<PruneReport, Error> {
+    let mut value = value.to_value()?;
+    let mut report = PruneReport::default();
+    prune_value(&mut value, options, &mut report);
+    value.write_to_vec(buf);
+    Ok(report)
+}
+
+fn prune_value(value: &mut Value<'_>, options: &PruneOptions, report: &mut PruneReport) {
+    match value {
+        Value::Object(obj) => obj.retain(|_, val| {
+            prune_value(val, options, report);
+            !is_pruned(val, options, report)
+        }),
+        Value::Array(vals) => vals.retain_mut(|val| {
+            prune_value(val, options, report);
+            !options.array_elements || !is_pruned(val, options, report)
+        }),
+        _ => {}
+    }
+}
+
+// Returns true and counts the value if it is removed.
+fn is_pruned(value: &Value<'_>, options: &PruneOptions, report: &mut PruneReport) -> bool {
+    match value {
+        Value::Null if options.nulls => report.nulls += 1,
+        Value::Object(obj) if options.empty_objects && obj.is_empty() => report.empty_objects += 1,
+        Value::Array(vals) if options.empty_arrays && vals.is_empty() => report.empty_arrays += 1,
+        _ => return false,
+    }
+    true
+}
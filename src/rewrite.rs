@@ -0,0 +1,166 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+
+use crate::error::Error;
+use crate::extension::ExtensionValue;
+use crate::functions::try_to_bool;
+use crate::functions::try_to_f64;
+use crate::functions::try_to_i64;
+use crate::functions::try_to_u64;
+use crate::functions::OnError;
+use crate::jsonpath::ArrayIndex;
+use crate::jsonpath::Index;
+use crate::jsonpath::JsonPath;
+use crate::jsonpath::Path;
+use crate::number::Number;
+use crate::shred::TargetType;
+use crate::value::Value;
+
+/// What a [`RewriteRule`] rewrites the matched values to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RewriteTarget {
+    /// Cast the value like `to_i64`, `to_u64`, `to_f64` and `to_bool`,
+    /// other values are cast to string as their `JSON` text, strings are kept unchanged.
+    Cast(TargetType),
+    /// Build an extension value of the registered extension type with the tag,
+    /// the data is the content of a string, or the `JSON` text of other values.
+    Extension(u8),
+}
+
+// A step of a path that locates values without evaluating the path, such as the rewritten values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PathStep {
+    Key(String),
+    AnyKey,
+    Index(usize),
+    AnyIndex,
+}
+
+/// A rule to rewrite the values at a JSON path while parsing, see [`ParseOptions`].
+/// The values are rewritten in the same pass as parsing, the nested values first,
+/// instead of traversing the parsed value again.
+/// `null` values are never rewritten.
+///
+/// ```
+/// use jsonb::jsonpath::parse_json_path;
+/// use jsonb::{parse_value_with_options, OnError, ParseOptions};
+/// use jsonb::{RewriteRule, RewriteTarget, TargetType};
+///
+/// let rule = |path: &str, target: TargetType| {
+///     let json_path = parse_json_path(path.as_bytes()).unwrap();
+///     RewriteRule::new(&json_path, RewriteTarget::Cast(target), OnError::Null).unwrap()
+/// };
+/// let options = ParseOptions {
+///     rewrites: vec![rule("$.id", TargetType::String), rule("$.items[*].n", TargetType::Int64)],
+///     ..Default::default()
+/// };
+/// let text = r#"{"id":7,"items":[{"n":"1"},{"n":"x"}]}"#;
+/// let value = parse_value_with_options(text.as_bytes(), &options).unwrap();
+/// assert_eq!(value.to_string(), r#"{"id":"7","items":[{"n":1},{"n":null}]}"#);
+/// ```
+///
+/// [`ParseOptions`]: crate::ParseOptions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RewriteRule {
+    pub(crate) steps: Vec<PathStep>,
+    target: RewriteTarget,
+    on_error: OnError,
+}
+
+impl RewriteRule {
+    /// Create a rule to rewrite the values at the path, the values that can't be rewritten
+    /// are handled by `on_error`.
+    ///
+    /// The path can only have the member accessors, non-negative array indices and wildcards,
+    /// like `$.a[0].*`, otherwise returns `Error::InvalidJsonPath`.
+    pub fn new(
+        json_path: &JsonPath<'_>,
+        target: RewriteTarget,
+        on_error: OnError,
+    ) -> Result<RewriteRule, Error> {
+        let steps = path_steps(json_path)?;
+        Ok(RewriteRule {
+            steps,
+            target,
+            on_error,
+        })
+    }
+
+    pub(crate) fn rewrite<'a>(&self, value: Value<'a>) -> Result<Value<'a>, Error> {
+        if value.is_null() {
+            return Ok(value);
+        }
+        match self.apply(value) {
+            Some(value) => Ok(value),
+            None => match self.on_error {
+                OnError::Null => Ok(Value::Null),
+                OnError::Fail => Err(Error::InvalidCast),
+            },
+        }
+    }
+
+    fn apply<'a>(&self, value: Value<'a>) -> Option<Value<'a>> {
+        let value = match self.target {
+            RewriteTarget::Cast(TargetType::Int64) => {
+                Value::Number(Number::Int64(try_to_i64(&value)?))
+            }
+            RewriteTarget::Cast(TargetType::UInt64) => {
+                Value::Number(Number::UInt64(try_to_u64(&value)?))
+            }
+            RewriteTarget::Cast(TargetType::Float64) => {
+                Value::Number(Number::Float64(try_to_f64(&value)?))
+            }
+            RewriteTarget::Cast(TargetType::Bool) => Value::Bool(try_to_bool(&value)?),
+            RewriteTarget::Cast(TargetType::String) => match value {
+                Value::String(_) => value,
+                _ => Value::String(Cow::Owned(value.to_string())),
+            },
+            RewriteTarget::Extension(tag) => {
+                let data = match value {
+                    Value::String(s) => s.into_owned(),
+                    _ => value.to_string(),
+                };
+                Value::Extension(ExtensionValue::new(tag, data.as_bytes()).ok()?)
+            }
+        };
+        Some(value)
+    }
+}
+
+// The steps of a path with only the member accessors, non-negative array indices and wildcards.
+pub(crate) fn path_steps(json_path: &JsonPath<'_>) -> Result<Vec<PathStep>, Error> {
+    let mut paths = json_path.paths.iter().peekable();
+    paths.next_if_eq(&&Path::Root);
+    let mut steps = Vec::with_capacity(json_path.paths.len());
+    for path in paths {
+        let step = match path {
+            Path::DotField(name) | Path::ColonField(name) | Path::ObjectField(name) => {
+                PathStep::Key(name.to_string())
+            }
+            Path::DotWildcard => PathStep::AnyKey,
+            Path::BracketWildcard => PathStep::AnyIndex,
+            Path::ArrayIndices(indices) => match indices.as_slice() {
+                [ArrayIndex::Index(Index::Index(idx))] if *idx >= 0 => {
+                    PathStep::Index(*idx as usize)
+                }
+                _ => return Err(Error::InvalidJsonPath),
+            },
+            _ => return Err(Error::InvalidJsonPath),
+        };
+        steps.push(step);
+    }
+    Ok(steps)
+}
@@ -122,7 +122,7 @@ impl<'a> Encoder<'a> {
     }
 
     // `Null` and `Boolean` only has a `JEntry`
-    // `Number` and `String` has a `JEntry` and an encoded data
+    // `Number`, `String` and `Extension` has a `JEntry` and an encoded data
     // `Array` and `Object` has a container `JEntry` and nested encoded data
     fn encode_value(&mut self, value: &Value<'a>) -> JEntry {
         let jentry = match value {
@@ -153,6 +153,11 @@ impl<'a> Encoder<'a> {
                 let len = self.encode_object(obj);
                 JEntry::make_container_jentry(len)
             }
+            Value::Extension(ext) => {
+                self.buf.push(ext.tag);
+                self.buf.extend_from_slice(&ext.payload);
+                JEntry::make_extension_jentry(ext.payload.len() + 1)
+            }
         };
 
         jentry
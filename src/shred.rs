@@ -0,0 +1,112 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::Error;
+use crate::functions::is_null;
+use crate::functions::to_bool;
+use crate::functions::to_f64;
+use crate::functions::to_i64;
+use crate::functions::to_str;
+use crate::functions::to_u64;
+use crate::functions::OnError;
+use crate::input::JsonInput;
+use crate::input::RawJsonb;
+use crate::jsonpath::JsonPath;
+use crate::jsonpath::Selector;
+
+/// The type of a value extracted by [`shred`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetType {
+    Int64,
+    UInt64,
+    Float64,
+    String,
+    Bool,
+}
+
+/// A value extracted by [`shred`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypedCell {
+    Null,
+    Int64(i64),
+    UInt64(u64),
+    Float64(f64),
+    String(String),
+    Bool(bool),
+}
+
+/// Extract the values of the JSON paths and cast them to the target types in one pass,
+/// like projecting a variant column into typed columns.
+///
+/// The first value matched by each path is cast like `to_i64`, `to_u64`, `to_f64`,
+/// `to_str` and `to_bool`. Missing values and `null` are [`TypedCell::Null`],
+/// the values that can't be cast are handled by the [`OnError`] of the path.
+/// The paths with the same leading steps share the traversal of these steps.
+///
+/// ```
+/// use jsonb::jsonpath::parse_json_path;
+/// use jsonb::shred;
+/// use jsonb::OnError;
+/// use jsonb::TargetType;
+/// use jsonb::TypedCell;
+///
+/// let spec = [
+///     (parse_json_path(b"$.id").unwrap(), TargetType::Int64, OnError::Fail),
+///     (parse_json_path(b"$.name").unwrap(), TargetType::String, OnError::Null),
+///     (parse_json_path(b"$.score").unwrap(), TargetType::Float64, OnError::Null),
+/// ];
+/// let cells = shred(r#"{"id":"7","name":"a","score":[1]}"#, &spec).unwrap();
+/// assert_eq!(
+///     cells,
+///     vec![TypedCell::Int64(7), TypedCell::String("a".to_string()), TypedCell::Null]
+/// );
+/// ```
+pub fn shred<T: JsonInput + ?Sized>(
+    value: &T,
+    spec: &[(JsonPath<'_>, TargetType, OnError)],
+) -> Result<Vec<TypedCell>, Error> {
+    let buf = value.to_jsonb()?;
+    let json_paths: Vec<_> = spec.iter().map(|(json_path, _, _)| json_path).collect();
+    let values = Selector::select_paths(&json_paths, buf.as_ref());
+
+    let mut cells = Vec::with_capacity(spec.len());
+    for ((_, target, on_error), values) in spec.iter().zip(values) {
+        let cell = match values.first() {
+            Some(value) => match cast(&RawJsonb::new(value), *target) {
+                Ok(cell) => cell,
+                Err(err) => match on_error {
+                    OnError::Null => TypedCell::Null,
+                    OnError::Fail => return Err(err),
+                },
+            },
+            None => TypedCell::Null,
+        };
+        cells.push(cell);
+    }
+    Ok(cells)
+}
+
+fn cast(value: &RawJsonb<'_>, target: TargetType) -> Result<TypedCell, Error> {
+    if is_null(value) {
+        return Ok(TypedCell::Null);
+    }
+    let cell = match target {
+        TargetType::Int64 => TypedCell::Int64(to_i64(value)?),
+        TargetType::UInt64 => TypedCell::UInt64(to_u64(value)?),
+        TargetType::Float64 => TypedCell::Float64(to_f64(value)?),
+        TargetType::String => TypedCell::String(to_str(value)?),
+        TargetType::Bool => TypedCell::Bool(to_bool(value)?),
+    };
+    Ok(cell)
+}
@@ -0,0 +1,270 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::error::Error;
+use crate::functions::convert_to_comparable_with_options;
+use crate::functions::CompareOptions;
+use crate::input::JsonInput;
+
+// The size of the key length and the row id of an entry.
+const ENTRY_OVERHEAD: usize = 4 + 8;
+// The number of leading bytes of the comparable keys cached by `sort_indices`.
+const PREFIX_SIZE: usize = 8;
+
+/// Writes the comparable keys of `JSONB` values with their row ids into sorted runs,
+/// to build external sorting on the order of `compare`.
+///
+/// The entries are buffered until the run reaches the run size, then sorted by the key
+/// and the row id and sealed. A sealed run is a byte buffer that can be spilled as it is,
+/// each entry is the big-endian `u32` length of the key, the key and the big-endian `u64`
+/// row id. The runs are merged by [`merge_runs`], which only compares the keys as bytes.
+///
+/// ```
+/// use jsonb::merge_runs;
+/// use jsonb::ComparableKeyWriter;
+///
+/// let mut writer = ComparableKeyWriter::new(32);
+/// for (row_id, value) in [r#""b""#, "3", "null", r#"{"a":1}"#, "1"].iter().enumerate() {
+///     writer.write(*value, row_id as u64);
+/// }
+/// let runs = writer.finish();
+/// assert!(runs.len() > 1);
+///
+/// let runs: Vec<_> = runs.iter().map(|run| run.as_slice()).collect();
+/// let row_ids: Vec<_> = merge_runs(&runs).unwrap().map(|(_, row_id)| row_id).collect();
+/// assert_eq!(row_ids, vec![4, 1, 0, 3, 2]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ComparableKeyWriter {
+    run_size: usize,
+    options: CompareOptions,
+    keys: Vec<u8>,
+    entries: Vec<(usize, usize, u64)>,
+    runs: Vec<Vec<u8>>,
+}
+
+impl ComparableKeyWriter {
+    /// Create a writer that seals a run when its encoded size reaches `run_size` bytes.
+    pub fn new(run_size: usize) -> Self {
+        Self::with_options(run_size, CompareOptions::default())
+    }
+
+    /// Create a writer like `new`, the keys are encoded with the options,
+    /// see `convert_to_comparable_with_options`.
+    pub fn with_options(run_size: usize, options: CompareOptions) -> Self {
+        Self {
+            run_size,
+            options,
+            keys: Vec::new(),
+            entries: Vec::new(),
+            runs: Vec::new(),
+        }
+    }
+
+    /// Write the comparable key of the value with the row id.
+    pub fn write<T: JsonInput + ?Sized>(&mut self, value: &T, row_id: u64) {
+        let start = self.keys.len();
+        convert_to_comparable_with_options(value, &mut self.keys, &self.options);
+        self.entries.push((start, self.keys.len(), row_id));
+        if self.buffered_size() >= self.run_size {
+            self.seal_run();
+        }
+    }
+
+    /// Write a comparable key encoded by `convert_to_comparable` with the row id.
+    pub fn write_key(&mut self, key: &[u8], row_id: u64) {
+        let start = self.keys.len();
+        self.keys.extend_from_slice(key);
+        self.entries.push((start, self.keys.len(), row_id));
+        if self.buffered_size() >= self.run_size {
+            self.seal_run();
+        }
+    }
+
+    /// Take the sealed runs, for example to spill them while writing more entries.
+    pub fn take_runs(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.runs)
+    }
+
+    /// Seal the buffered entries and returns the runs not taken yet.
+    pub fn finish(mut self) -> Vec<Vec<u8>> {
+        if !self.entries.is_empty() {
+            self.seal_run();
+        }
+        self.runs
+    }
+
+    fn buffered_size(&self) -> usize {
+        self.keys.len() + self.entries.len() * ENTRY_OVERHEAD
+    }
+
+    fn seal_run(&mut self) {
+        let keys = &self.keys;
+        self.entries
+            .sort_by(|(l_start, l_end, l_id), (r_start, r_end, r_id)| {
+                keys[*l_start..*l_end]
+                    .cmp(&keys[*r_start..*r_end])
+                    .then(l_id.cmp(r_id))
+            });
+        let mut run = Vec::with_capacity(self.buffered_size());
+        for (start, end, row_id) in self.entries.drain(..) {
+            run.extend_from_slice(&((end - start) as u32).to_be_bytes());
+            run.extend_from_slice(&keys[start..end]);
+            run.extend_from_slice(&row_id.to_be_bytes());
+        }
+        self.keys.clear();
+        self.runs.push(run);
+    }
+}
+
+/// An iterator over the entries of a sorted run written by [`ComparableKeyWriter`].
+#[derive(Debug, Clone)]
+pub struct RunIter<'a> {
+    run: &'a [u8],
+}
+
+impl<'a> RunIter<'a> {
+    /// Iterate the entries of the run, returns [`Error::InvalidEOF`] if the run is truncated.
+    pub fn new(run: &'a [u8]) -> Result<Self, Error> {
+        let mut rest = run;
+        while !rest.is_empty() {
+            rest = split_entry(rest).ok_or(Error::InvalidEOF)?.2;
+        }
+        Ok(Self { run })
+    }
+}
+
+impl<'a> Iterator for RunIter<'a> {
+    type Item = (&'a [u8], u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, row_id, rest) = split_entry(self.run)?;
+        self.run = rest;
+        Some((key, row_id))
+    }
+}
+
+fn split_entry(run: &[u8]) -> Option<(&[u8], u64, &[u8])> {
+    let len = u32::from_be_bytes(run.get(..4)?.try_into().unwrap()) as usize;
+    let key = run.get(4..4 + len)?;
+    let row_id = u64::from_be_bytes(run.get(4 + len..ENTRY_OVERHEAD + len)?.try_into().unwrap());
+    Some((key, row_id, &run[ENTRY_OVERHEAD + len..]))
+}
+
+/// An iterator over the merged entries of sorted runs, returned by [`merge_runs`].
+#[derive(Debug, Clone)]
+pub struct MergedRuns<'a> {
+    runs: Vec<RunIter<'a>>,
+    heap: BinaryHeap<Reverse<(&'a [u8], u64, usize)>>,
+}
+
+impl<'a> Iterator for MergedRuns<'a> {
+    type Item = (&'a [u8], u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((key, row_id, i)) = self.heap.pop()?;
+        if let Some((next_key, next_row_id)) = self.runs[i].next() {
+            self.heap.push(Reverse((next_key, next_row_id, i)));
+        }
+        Some((key, row_id))
+    }
+}
+
+/// Merge the sorted runs written by [`ComparableKeyWriter`] into one sorted sequence of
+/// the keys and row ids, the keys are compared as bytes and the ties by the row ids.
+/// Returns [`Error::InvalidEOF`] if any of the runs is truncated.
+pub fn merge_runs<'a>(runs: &[&'a [u8]]) -> Result<MergedRuns<'a>, Error> {
+    let mut iters = Vec::with_capacity(runs.len());
+    let mut heap = BinaryHeap::with_capacity(runs.len());
+    for (i, run) in runs.iter().enumerate() {
+        let mut iter = RunIter::new(run)?;
+        if let Some((key, row_id)) = iter.next() {
+            heap.push(Reverse((key, row_id, i)));
+        }
+        iters.push(iter);
+    }
+    Ok(MergedRuns { runs: iters, heap })
+}
+
+/// Returns the indices of the `JSONB` values in the order of `compare`,
+/// the indices of the equal values keep their order.
+///
+/// Only the first 8 bytes of the comparable key of each value are kept, the values are
+/// sorted by these prefixes as integers, and the full keys are converted again only for
+/// the values with the same prefix. This is much cheaper than comparing the values pair by pair
+/// or keeping all the comparable keys when sorting many values, like the SQL `ORDER BY`.
+///
+/// # Panics
+///
+/// Panics if there are more than `u32::MAX` values.
+///
+/// ```
+/// let values = [r#""b""#, "3", "null", r#"{"a":1}"#, "1"];
+/// let values: Vec<_> = values.iter().map(|v| v.as_bytes()).collect();
+/// assert_eq!(jsonb::sort_indices(&values), vec![4, 1, 0, 3, 2]);
+/// ```
+pub fn sort_indices(values: &[&[u8]]) -> Vec<u32> {
+    sort_indices_with_options(values, &CompareOptions::default())
+}
+
+/// Returns the indices of the `JSONB` values like `sort_indices`,
+/// in the order of `compare_with_options`.
+pub fn sort_indices_with_options(values: &[&[u8]], options: &CompareOptions) -> Vec<u32> {
+    assert!(values.len() <= u32::MAX as usize, "too many values to sort");
+    let mut key = Vec::new();
+    let mut entries: Vec<(u64, u32)> = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            key.clear();
+            convert_to_comparable_with_options(*value, &mut key, options);
+            // The shorter keys are padded with zeros, so they may tie with the longer keys.
+            let mut prefix = [0; PREFIX_SIZE];
+            let len = key.len().min(PREFIX_SIZE);
+            prefix[..len].copy_from_slice(&key[..len]);
+            (u64::from_be_bytes(prefix), i as u32)
+        })
+        .collect();
+    entries.sort_unstable();
+
+    // Sort the values with the same prefix by the full keys.
+    let mut start = 0;
+    while start < entries.len() {
+        let prefix = entries[start].0;
+        let end = start
+            + entries[start..]
+                .iter()
+                .take_while(|(other, _)| *other == prefix)
+                .count();
+        if end - start > 1 {
+            let mut keys: Vec<_> = entries[start..end]
+                .iter()
+                .map(|(_, i)| {
+                    let mut key = Vec::new();
+                    convert_to_comparable_with_options(values[*i as usize], &mut key, options);
+                    (key, *i)
+                })
+                .collect();
+            keys.sort_unstable();
+            for (entry, (_, i)) in entries[start..end].iter_mut().zip(keys) {
+                entry.1 = i;
+            }
+        }
+        start = end;
+    }
+    entries.into_iter().map(|(_, i)| i).collect()
+}
@@ -0,0 +1,95 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::Error;
+
+/// The default memory budget of [`SpillOptions`], 256 MiB.
+pub const DEFAULT_SPILL_BUDGET: usize = 256 * 1024 * 1024;
+
+/// The default minimum size of the spilled values of [`SpillOptions`], 64 KiB.
+pub const DEFAULT_SPILL_MIN_SIZE: usize = 64 * 1024;
+
+/// A temporary store of the values spilled by [`parse_value_with_spill`],
+/// like a temporary file or an object store provided by the caller.
+///
+/// `Vec<Vec<u8>>` is a store in memory, the id of a value is its index.
+///
+/// [`parse_value_with_spill`]: crate::parse_value_with_spill
+pub trait SpillStore {
+    /// Stores the encoded `JSONB` value, returns the id to read it back.
+    fn spill(&mut self, value: &[u8]) -> Result<u64, Error>;
+}
+
+impl SpillStore for Vec<Vec<u8>> {
+    fn spill(&mut self, value: &[u8]) -> Result<u64, Error> {
+        self.push(value.to_vec());
+        Ok(self.len() as u64 - 1)
+    }
+}
+
+/// Options of [`parse_value_with_spill`].
+///
+/// The memory of the parsed values is estimated while parsing, including the size of `Value`
+/// of each value and the length of the strings and the keys. Once the estimate is beyond
+/// the budget, the completed strings and arrays that are at least `min_size` are spilled.
+///
+/// [`parse_value_with_spill`]: crate::parse_value_with_spill
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpillOptions {
+    /// The estimated memory of the parsed values kept in memory.
+    pub budget: usize,
+    /// The strings and arrays with a smaller estimated memory are never spilled.
+    pub min_size: usize,
+}
+
+impl Default for SpillOptions {
+    fn default() -> Self {
+        Self {
+            budget: DEFAULT_SPILL_BUDGET,
+            min_size: DEFAULT_SPILL_MIN_SIZE,
+        }
+    }
+}
+
+/// A value spilled to the [`SpillStore`], the value is replaced with `null` in the parsed value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpilledValue {
+    /// The path of the value written as `JSONPath`, like `$.a[0]["unit price"]`.
+    pub path: String,
+    /// The id of the value returned by the store.
+    pub id: u64,
+}
+
+// The state of spilling while parsing.
+pub(crate) struct Spiller<'s> {
+    pub(crate) options: SpillOptions,
+    pub(crate) store: &'s mut dyn SpillStore,
+    // The estimated memory of the values kept in memory.
+    pub(crate) used: usize,
+    // The estimated memory of the values of each open container.
+    pub(crate) sizes: Vec<usize>,
+    pub(crate) spilled: Vec<SpilledValue>,
+}
+
+impl<'s> Spiller<'s> {
+    pub(crate) fn new(options: SpillOptions, store: &'s mut dyn SpillStore) -> Self {
+        Self {
+            options,
+            store,
+            used: 0,
+            sizes: Vec::new(),
+            spilled: Vec::new(),
+        }
+    }
+}
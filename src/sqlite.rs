@@ -0,0 +1,215 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SQLite json1 compatible functions.
+//!
+//! The path arguments use the SQLite path syntax:
+//!
+//! - `$` represents the document, followed by zero or more path elements.
+//! - `.key` or `."key"` selects the member of an object.
+//! - `[n]` selects the n-th element of an array, `[#-n]` selects the n-th element
+//!   counting from the end, so `[#-1]` is the last element.
+
+use crate::error::Error;
+use crate::input::JsonInput;
+use crate::util::quote_string;
+use crate::value::Value;
+
+/// `json_extract` returns the values selected by the paths.
+///
+/// If there is only one path, returns the selected value or `None` if the path doesn't exist.
+/// If there are multiple paths, returns an array of the selected values,
+/// the missing values are replaced by `null`.
+pub fn json_extract<T: JsonInput + ?Sized>(
+    value: &T,
+    paths: &[&str],
+) -> Result<Option<Vec<u8>>, Error> {
+    let value = value.to_value()?;
+    let paths = parse_paths(paths)?;
+    if paths.len() == 1 {
+        return Ok(lookup(&value, &paths[0]).map(Value::to_vec));
+    }
+    let items = paths
+        .iter()
+        .map(|path| lookup(&value, path).cloned().unwrap_or_default())
+        .collect();
+    Ok(Some(Value::Array(items).to_vec()))
+}
+
+/// `json_remove` removes the elements selected by the paths, from left to right.
+///
+/// Paths that don't exist are ignored, returns `None` if the root value `$` is removed.
+pub fn json_remove<T: JsonInput + ?Sized>(
+    value: &T,
+    paths: &[&str],
+) -> Result<Option<Vec<u8>>, Error> {
+    let mut value = value.to_value()?;
+    let paths = parse_paths(paths)?;
+    for path in paths.iter() {
+        let Some((last, parent)) = path.split_last() else {
+            return Ok(None);
+        };
+        if let Some(parent) = lookup_mut(&mut value, parent) {
+            match (parent, last) {
+                (Value::Object(obj), PathElement::Key(key)) => {
+                    obj.remove(key);
+                }
+                (Value::Array(vals), PathElement::Index(index)) => {
+                    if let Some(idx) = index.resolve(vals.len()) {
+                        vals.remove(idx);
+                    }
+                }
+                (_, _) => {}
+            }
+        }
+    }
+    Ok(Some(value.to_vec()))
+}
+
+/// `json_patch` applies the `patch` to the `target` with the MergePatch algorithm of RFC-7396.
+///
+/// The members of the patch object are merged into the target object recursively,
+/// a `null` member removes the member from the target.
+/// If the patch is not an object, the patch replaces the target.
+pub fn json_patch<T: JsonInput + ?Sized, P: JsonInput + ?Sized>(
+    target: &T,
+    patch: &P,
+) -> Result<Vec<u8>, Error> {
+    let target = target.to_value()?;
+    let patch = patch.to_value()?;
+    Ok(merge_patch(target, patch).to_vec())
+}
+
+/// `json_quote` converts the string to a `JSON` string literal.
+pub fn json_quote(s: &str) -> String {
+    let mut buf = String::with_capacity(s.len() + 2);
+    quote_string(s, &mut buf);
+    buf
+}
+
+// The index of array element.
+#[derive(Debug, Clone, PartialEq)]
+enum ArrayIndex {
+    // `n` selects the n-th element.
+    Index(usize),
+    // `#-n` selects the n-th element counting from the end.
+    FromEnd(usize),
+}
+
+impl ArrayIndex {
+    // Resolve the index in the array, `None` if the index is out of bounds.
+    fn resolve(&self, len: usize) -> Option<usize> {
+        let idx = match self {
+            ArrayIndex::Index(idx) => *idx,
+            ArrayIndex::FromEnd(idx) => len.checked_sub(*idx)?,
+        };
+        if idx < len {
+            Some(idx)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PathElement {
+    // `.key` or `."key"`
+    Key(String),
+    // `[n]` or `[#-n]`
+    Index(ArrayIndex),
+}
+
+fn lookup<'a, 'b>(value: &'b Value<'a>, path: &[PathElement]) -> Option<&'b Value<'a>> {
+    path.iter()
+        .try_fold(value, |value, elem| match (value, elem) {
+            (Value::Object(obj), PathElement::Key(key)) => obj.get(key),
+            (Value::Array(vals), PathElement::Index(index)) => vals.get(index.resolve(vals.len())?),
+            (_, _) => None,
+        })
+}
+
+fn lookup_mut<'a, 'b>(value: &'b mut Value<'a>, path: &[PathElement]) -> Option<&'b mut Value<'a>> {
+    path.iter()
+        .try_fold(value, |value, elem| match (value, elem) {
+            (Value::Object(obj), PathElement::Key(key)) => obj.get_mut(key),
+            (Value::Array(vals), PathElement::Index(index)) => {
+                let idx = index.resolve(vals.len())?;
+                vals.get_mut(idx)
+            }
+            (_, _) => None,
+        })
+}
+
+fn merge_patch<'a>(target: Value<'a>, patch: Value<'a>) -> Value<'a> {
+    let Value::Object(patch) = patch else {
+        return patch;
+    };
+    let mut obj = match target {
+        Value::Object(obj) => obj,
+        _ => Default::default(),
+    };
+    for (key, val) in patch {
+        if val == Value::Null {
+            obj.remove(&key);
+        } else {
+            let old = obj.remove(&key).unwrap_or_default();
+            obj.insert(key, merge_patch(old, val));
+        }
+    }
+    Value::Object(obj)
+}
+
+fn parse_paths(paths: &[&str]) -> Result<Vec<Vec<PathElement>>, Error> {
+    paths.iter().map(|path| parse_sqlite_path(path)).collect()
+}
+
+fn parse_sqlite_path(path: &str) -> Result<Vec<PathElement>, Error> {
+    let mut rest = path.strip_prefix('$').ok_or(Error::InvalidJsonPath)?;
+    let mut elems = Vec::new();
+    while !rest.is_empty() {
+        if let Some(s) = rest.strip_prefix(".\"") {
+            let end = s.find('"').ok_or(Error::InvalidJsonPath)?;
+            elems.push(PathElement::Key(s[..end].to_string()));
+            rest = &s[end + 1..];
+        } else if let Some(s) = rest.strip_prefix('.') {
+            let end = s.find(['.', '[']).unwrap_or(s.len());
+            if end == 0 {
+                return Err(Error::InvalidJsonPath);
+            }
+            elems.push(PathElement::Key(s[..end].to_string()));
+            rest = &s[end..];
+        } else if let Some(s) = rest.strip_prefix('[') {
+            let end = s.find(']').ok_or(Error::InvalidJsonPath)?;
+            let index = match s[..end].strip_prefix('#') {
+                Some("") => ArrayIndex::FromEnd(0),
+                Some(n) => ArrayIndex::FromEnd(parse_number(n.strip_prefix('-'))?),
+                None => ArrayIndex::Index(parse_number(Some(&s[..end]))?),
+            };
+            elems.push(PathElement::Index(index));
+            rest = &s[end + 1..];
+        } else {
+            return Err(Error::InvalidJsonPath);
+        }
+    }
+    Ok(elems)
+}
+
+fn parse_number(s: Option<&str>) -> Result<usize, Error> {
+    match s {
+        Some(s) if !s.is_empty() && s.bytes().all(|c| c.is_ascii_digit()) => {
+            s.parse().map_err(|_| Error::InvalidJsonPath)
+        }
+        _ => Err(Error::InvalidJsonPath),
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::Error;
+use crate::error::ParseErrorCode;
+
+/// Splits a buffer of back-to-back `JSON` values, like a concatenated-JSON feed, into the
+/// slices of the values. The values may be separated by whitespace, but don't have to be,
+/// except for the adjacent numbers and literals like `1 2`.
+///
+/// The values are not parsed, only the brackets and the strings are tracked to find the end
+/// of each value, so the slices still have to be parsed, e.g. with [`parse_value`](crate::parse_value).
+/// The iterator stops after the first error, like an unclosed container at the end of the buffer.
+pub fn split_stream(buf: &[u8]) -> SplitStream<'_> {
+    SplitStream {
+        buf,
+        idx: 0,
+        done: false,
+    }
+}
+
+/// Iterator over the values of a concatenated-JSON buffer, created by [`split_stream`].
+#[derive(Debug, Clone)]
+pub struct SplitStream<'a> {
+    buf: &'a [u8],
+    idx: usize,
+    done: bool,
+}
+
+impl<'a> SplitStream<'a> {
+    fn next_value(&mut self) -> Result<&'a [u8], Error> {
+        let start = self.idx;
+        match self.buf[start] {
+            b'{' | b'[' => self.skip_container()?,
+            b'"' => self.skip_string()?,
+            b'}' | b']' | b',' | b':' => {
+                return Err(Error::Syntax(ParseErrorCode::ExpectedSomeValue, start));
+            }
+            _ => {
+                // Numbers and literals end at whitespace or at a structural character.
+                while let Some(c) = self.buf.get(self.idx) {
+                    if c.is_ascii_whitespace() || b"{}[]\",:".contains(c) {
+                        break;
+                    }
+                    self.idx += 1;
+                }
+            }
+        }
+        Ok(&self.buf[start..self.idx])
+    }
+
+    fn skip_container(&mut self) -> Result<(), Error> {
+        let mut closers = Vec::new();
+        while let Some(&c) = self.buf.get(self.idx) {
+            match c {
+                b'{' => closers.push(b'}'),
+                b'[' => closers.push(b']'),
+                b'}' | b']' => {
+                    if closers.pop() != Some(c) {
+                        let code = if c == b'}' {
+                            ParseErrorCode::ExpectedArrayCommaOrEnd
+                        } else {
+                            ParseErrorCode::ExpectedObjectCommaOrEnd
+                        };
+                        return Err(Error::Syntax(code, self.idx));
+                    }
+                    if closers.is_empty() {
+                        self.idx += 1;
+                        return Ok(());
+                    }
+                }
+                b'"' => {
+                    self.skip_string()?;
+                    continue;
+                }
+                _ => {}
+            }
+            self.idx += 1;
+        }
+        Err(Error::Syntax(ParseErrorCode::InvalidEOF, self.idx))
+    }
+
+    fn skip_string(&mut self) -> Result<(), Error> {
+        self.idx += 1;
+        while let Some(&c) = self.buf.get(self.idx) {
+            match c {
+                b'"' => {
+                    self.idx += 1;
+                    return Ok(());
+                }
+                b'\\' => self.idx += 2,
+                _ => self.idx += 1,
+            }
+        }
+        self.idx = self.buf.len();
+        Err(Error::Syntax(ParseErrorCode::InvalidEOF, self.idx))
+    }
+}
+
+impl<'a> Iterator for SplitStream<'a> {
+    type Item = Result<&'a [u8], Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        while self
+            .buf
+            .get(self.idx)
+            .map_or(false, u8::is_ascii_whitespace)
+        {
+            self.idx += 1;
+        }
+        if self.idx >= self.buf.len() {
+            self.done = true;
+            return None;
+        }
+        let value = self.next_value();
+        self.done = value.is_err();
+        Some(value)
+    }
+}
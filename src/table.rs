@@ -0,0 +1,194 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::Error;
+use crate::functions::*;
+use crate::input::JsonInput;
+use crate::input::RawJsonb;
+use crate::jsonpath::JsonPath;
+use crate::jsonpath::Path;
+use crate::jsonpath::Selector;
+
+/// The target type of a `json_table` column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonTableColumnType {
+    /// `FOR ORDINALITY`, the 1-based sequence number of the row, the path is not used.
+    Ordinality,
+    Boolean,
+    Int64,
+    UInt64,
+    Float64,
+    String,
+    /// The selected value is kept as `JSONB`, like `FORMAT JSON` columns.
+    Jsonb,
+}
+
+/// The typed value of a `json_table` column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonTableValue {
+    Null,
+    Boolean(bool),
+    Int64(i64),
+    UInt64(u64),
+    Float64(f64),
+    String(String),
+    Jsonb(Vec<u8>),
+}
+
+/// The behavior of a `json_table` column when the path selects nothing (`ON EMPTY`),
+/// or the selected value can't be converted to the column type (`ON ERROR`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonTableBehavior {
+    /// `NULL ON EMPTY` or `NULL ON ERROR`, the default behavior.
+    Null,
+    /// `ERROR ON EMPTY` or `ERROR ON ERROR`, the error is returned as the row.
+    Error,
+    /// `DEFAULT <value> ON EMPTY` or `DEFAULT <value> ON ERROR`.
+    Default(JsonTableValue),
+}
+
+/// The definition of a `json_table` column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonTableColumn<'a> {
+    pub name: String,
+    /// The path to select the column value, relative to the row value.
+    pub path: JsonPath<'a>,
+    pub data_type: JsonTableColumnType,
+    pub on_empty: JsonTableBehavior,
+    pub on_error: JsonTableBehavior,
+}
+
+impl<'a> JsonTableColumn<'a> {
+    /// Create a column with `NULL ON EMPTY` and `NULL ON ERROR` behaviors.
+    pub fn new(
+        name: impl Into<String>,
+        path: JsonPath<'a>,
+        data_type: JsonTableColumnType,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            path,
+            data_type,
+            on_empty: JsonTableBehavior::Null,
+            on_error: JsonTableBehavior::Null,
+        }
+    }
+
+    /// Create a `FOR ORDINALITY` column.
+    pub fn ordinality(name: impl Into<String>) -> Self {
+        let path = JsonPath {
+            paths: vec![Path::Root],
+        };
+        Self::new(name, path, JsonTableColumnType::Ordinality)
+    }
+}
+
+/// The rows produced by [`json_table`].
+pub struct JsonTableRows<'a> {
+    rows: std::vec::IntoIter<Vec<u8>>,
+    columns: &'a [JsonTableColumn<'a>],
+    selectors: Vec<Selector<'a>>,
+    ordinality: u64,
+}
+
+impl<'a> Iterator for JsonTableRows<'a> {
+    type Item = Result<Vec<JsonTableValue>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.rows.next()?;
+        self.ordinality += 1;
+        let values = self
+            .columns
+            .iter()
+            .zip(self.selectors.iter())
+            .map(|(column, selector)| {
+                if column.data_type == JsonTableColumnType::Ordinality {
+                    return Ok(JsonTableValue::UInt64(self.ordinality));
+                }
+                let mut values = selector.select(&row);
+                let value = match values.len() {
+                    // The column paths are in lax mode like the default of `JSON_TABLE`,
+                    // so the missing members are empty results resolved by `ON EMPTY`.
+                    0 => return resolve(&column.on_empty, Error::InvalidJsonPath),
+                    1 => values.pop().unwrap(),
+                    _ => return resolve(&column.on_error, Error::InvalidJsonType),
+                };
+                match convert(value, column.data_type) {
+                    Ok(value) => Ok(value),
+                    Err(err) => resolve(&column.on_error, err),
+                }
+            })
+            .collect();
+        Some(values)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rows.size_hint()
+    }
+}
+
+/// Project the `JSONB` value into rows like the SQL/JSON `JSON_TABLE`.
+///
+/// Each value selected by the `row_path` produces a row, the values of the columns are selected by
+/// the column paths from the row value and converted to the column types.
+/// `null` values are converted to `Null`, except for the `Jsonb` columns.
+/// The column paths are evaluated in lax mode, so the structural errors like the missing
+/// members are empty results instead of errors.
+///
+/// If a column path selects nothing, the value is resolved by the `on_empty` behavior of the column,
+/// and the [`Error::InvalidJsonPath`] is returned for `ERROR ON EMPTY`. If a column path selects
+/// more than one value, or the value can't be converted to the column type, the value is resolved
+/// by the `on_error` behavior of the column.
+pub fn json_table<'a, T: JsonInput + ?Sized>(
+    value: &T,
+    row_path: JsonPath<'_>,
+    columns: &'a [JsonTableColumn<'a>],
+) -> JsonTableRows<'a> {
+    let rows = get_by_path(value, row_path);
+    let selectors = columns
+        .iter()
+        .map(|column| Selector::new(column.path.clone()))
+        .collect();
+    JsonTableRows {
+        rows: rows.into_iter(),
+        columns,
+        selectors,
+        ordinality: 0,
+    }
+}
+
+fn resolve(behavior: &JsonTableBehavior, err: Error) -> Result<JsonTableValue, Error> {
+    match behavior {
+        JsonTableBehavior::Null => Ok(JsonTableValue::Null),
+        JsonTableBehavior::Error => Err(err),
+        JsonTableBehavior::Default(value) => Ok(value.clone()),
+    }
+}
+
+fn convert(value: Vec<u8>, data_type: JsonTableColumnType) -> Result<JsonTableValue, Error> {
+    let raw = RawJsonb::new(&value);
+    if data_type != JsonTableColumnType::Jsonb && is_null(&raw) {
+        return Ok(JsonTableValue::Null);
+    }
+    let value = match data_type {
+        JsonTableColumnType::Ordinality => unreachable!(),
+        JsonTableColumnType::Boolean => JsonTableValue::Boolean(to_bool(&raw)?),
+        JsonTableColumnType::Int64 => JsonTableValue::Int64(to_i64(&raw)?),
+        JsonTableColumnType::UInt64 => JsonTableValue::UInt64(to_u64(&raw)?),
+        JsonTableColumnType::Float64 => JsonTableValue::Float64(to_f64(&raw)?),
+        JsonTableColumnType::String => JsonTableValue::String(to_str(&raw)?),
+        JsonTableColumnType::Jsonb => JsonTableValue::Jsonb(value),
+    };
+    Ok(value)
+}
@@ -0,0 +1,90 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::ser::Error as _;
+use serde::ser::SerializeMap;
+use serde::ser::SerializeSeq;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use serde_json::Value as JsonValue;
+
+use crate::input::JsonInput;
+use crate::number::Number;
+use crate::value::Value;
+
+/// Transcode the data of a `serde` deserializer of any format into `JSONB`,
+/// and append it to `buf`.
+///
+/// ```
+/// use serde::de::value::MapDeserializer;
+/// use serde::de::value::Error;
+///
+/// let data = vec![("a", 1), ("b", 2)];
+/// let deserializer = MapDeserializer::<_, Error>::new(data.into_iter());
+/// let mut buf = Vec::new();
+/// jsonb::transcode_from(deserializer, &mut buf).unwrap();
+/// assert_eq!(jsonb::to_string(&buf), r#"{"a":1,"b":2}"#);
+/// ```
+pub fn transcode_from<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    buf: &mut Vec<u8>,
+) -> Result<(), D::Error> {
+    let value = JsonValue::deserialize(deserializer)?;
+    Value::from(value).write_to_vec(buf);
+    Ok(())
+}
+
+/// Transcode the `JSONB` value into a `serde` serializer of any format.
+///
+/// Extension values are serialized as their plain values.
+pub fn transcode_into<T: JsonInput + ?Sized, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let value = value.to_value().map_err(S::Error::custom)?;
+    value.serialize(serializer)
+}
+
+impl Serialize for Value<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::Number(Number::Int64(v)) => serializer.serialize_i64(*v),
+            Value::Number(Number::UInt64(v)) => serializer.serialize_u64(*v),
+            Value::Number(Number::Float64(v)) => serializer.serialize_f64(*v),
+            Value::String(v) => serializer.serialize_str(v),
+            Value::Array(vals) => {
+                let mut seq = serializer.serialize_seq(Some(vals.len()))?;
+                for val in vals {
+                    seq.serialize_element(val)?;
+                }
+                seq.end()
+            }
+            Value::Object(obj) => {
+                let mut map = serializer.serialize_map(Some(obj.len()))?;
+                for (key, val) in obj {
+                    map.serialize_entry(key, val)?;
+                }
+                map.end()
+            }
+            Value::Extension(v) => v
+                .to_plain_value()
+                .map_err(S::Error::custom)?
+                .serialize(serializer),
+        }
+    }
+}
@@ -42,23 +42,45 @@ static HEX: [u8; 256] = {
     ]
 };
 
-pub fn parse_string(mut data: &[u8], len: usize, idx: &mut usize) -> Result<String, Error> {
-    let mut buf = Vec::with_capacity(len);
-    let mut str_buf = String::with_capacity(4);
+pub fn parse_string(data: &[u8], len: usize, idx: &mut usize) -> Result<String, Error> {
+    let mut buf = String::with_capacity(len);
+    unescape_str(data, idx, &mut buf)?;
+    Ok(buf)
+}
+
+/// Unescape the content of a `JSON` string literal, without the quotation marks,
+/// and append it to `buf`.
+///
+/// The escaped UTF-16 surrogate pairs are combined into one character,
+/// the lone surrogates are kept as the escape sequences, like the `JSONB` parser.
+///
+/// ```
+/// let mut buf = String::new();
+/// jsonb::unescape_into(br#"a\"b\u00e9\ud83d\ude00"#, &mut buf).unwrap();
+/// assert_eq!(buf, "a\"b\u{e9}\u{1f600}");
+/// ```
+pub fn unescape_into(data: &[u8], buf: &mut String) -> Result<(), Error> {
+    buf.reserve(data.len());
+    unescape_str(data, &mut 0, buf)
+}
+
+fn unescape_str(mut data: &[u8], idx: &mut usize, buf: &mut String) -> Result<(), Error> {
     while !data.is_empty() {
-        *idx += 1;
-        let byte = data[0];
-        if byte == b'\\' {
-            data = &data[1..];
-            data = parse_escaped_string(data, idx, &mut str_buf)?;
-            buf.extend_from_slice(str_buf.as_bytes());
-            str_buf.clear();
-        } else {
-            buf.push(byte);
-            data = &data[1..];
+        // Copy the run of the unescaped bytes at once.
+        let len = data.iter().position(|b| *b == b'\\').unwrap_or(data.len());
+        if len > 0 {
+            *idx += len;
+            let s = std::str::from_utf8(&data[..len])
+                .map_err(|_| Error::Syntax(ParseErrorCode::InvalidStringValue, *idx))?;
+            buf.push_str(s);
+            data = &data[len..];
+        }
+        if !data.is_empty() {
+            *idx += 1;
+            data = parse_escaped_string(&data[1..], idx, buf)?;
         }
     }
-    String::from_utf8(buf).map_err(|_| Error::Syntax(ParseErrorCode::InvalidStringValue, *idx))
+    Ok(())
 }
 
 fn parse_escaped_string<'a>(
@@ -66,7 +88,9 @@ fn parse_escaped_string<'a>(
     idx: &mut usize,
     str_buf: &mut String,
 ) -> Result<&'a [u8], Error> {
-    let byte = data[0];
+    let Some(&byte) = data.first() else {
+        return Err(Error::Syntax(ParseErrorCode::InvalidEOF, *idx));
+    };
     *idx += 1;
     data = &data[1..];
     match byte {
@@ -80,10 +104,10 @@ fn parse_escaped_string<'a>(
         b't' => str_buf.push(TT),
         b'u' => {
             let mut numbers = vec![0; UNICODE_LEN];
-            if data[0] == b'{' {
+            if data.first() == Some(&b'{') {
                 data = &data[1..];
                 data.read_exact(numbers.as_mut_slice())?;
-                if data[0] != b'}' {
+                if data.first() != Some(&b'}') {
                     return Err(Error::Syntax(
                         ParseErrorCode::UnexpectedEndOfHexEscape,
                         *idx,
@@ -120,10 +144,10 @@ fn parse_escaped_string<'a>(
                         return Ok(data);
                     }
                     let mut lower_numbers = vec![0; UNICODE_LEN];
-                    if data[0] == b'{' {
+                    if data.first() == Some(&b'{') {
                         data = &data[1..];
                         data.read_exact(lower_numbers.as_mut_slice())?;
-                        if data[0] != b'}' {
+                        if data.first() != Some(&b'}') {
                             return Err(Error::Syntax(
                                 ParseErrorCode::UnexpectedEndOfHexEscape,
                                 *idx,
@@ -190,3 +214,210 @@ fn decode_hex_escape(numbers: Vec<u8>, idx: &usize) -> Result<u16, Error> {
     }
     Ok(n)
 }
+
+// The escaped character of each byte, `0` means the byte doesn't need to be escaped.
+static ESCAPE: [u8; 256] = {
+    let mut table = [0; 256];
+    table[b'\\' as usize] = b'\\';
+    table[b'"' as usize] = b'"';
+    table[b'/' as usize] = b'/';
+    table[0x08] = b'b';
+    table[0x0C] = b'f';
+    table[b'\n' as usize] = b'n';
+    table[b'\r' as usize] = b'r';
+    table[b'\t' as usize] = b't';
+    table
+};
+
+/// Escape the string as the content of a `JSON` string literal, without the quotation marks,
+/// and append it to `buf`. The escapes are the same as `to_string`.
+///
+/// ```
+/// let mut buf = String::from("\"");
+/// jsonb::escape_str_into("a\"b/c\n", &mut buf);
+/// buf.push('"');
+/// assert_eq!(buf, r#""a\"b\/c\n""#);
+/// ```
+pub fn escape_str_into(s: &str, buf: &mut String) {
+    let bytes = s.as_bytes();
+    let mut last_start = 0;
+    for (i, b) in bytes.iter().enumerate() {
+        let escaped = ESCAPE[*b as usize];
+        if escaped == 0 {
+            continue;
+        }
+        // The escaped characters are ASCII, so the runs between them are on char boundaries.
+        if i > last_start {
+            buf.push_str(&s[last_start..i]);
+        }
+        buf.push('\\');
+        buf.push(escaped as char);
+        last_start = i + 1;
+    }
+    if last_start < s.len() {
+        buf.push_str(&s[last_start..]);
+    }
+}
+
+// Write the string as a `JSON` string literal, escape quotation marks,
+// backslashes and control characters.
+pub(crate) fn quote_string(s: &str, buf: &mut String) {
+    buf.push('"');
+    for c in s.chars() {
+        match c {
+            QU => buf.push_str("\\\""),
+            BS => buf.push_str("\\\\"),
+            BB => buf.push_str("\\b"),
+            FF => buf.push_str("\\f"),
+            NN => buf.push_str("\\n"),
+            RR => buf.push_str("\\r"),
+            TT => buf.push_str("\\t"),
+            c if c < ' ' => buf.push_str(&format!("\\u{:04x}", c as u32)),
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+}
+
+/// Parse an ISO-8601 timestamp, such as `2023-05-06`, `2023-05-06T10:20:30Z`
+/// or `2023-05-06 10:20:30.123+08:00`, into nanoseconds since the Unix epoch.
+/// Timestamps without an offset are treated as UTC.
+pub(crate) fn parse_timestamp(s: &str) -> Option<i128> {
+    let bytes = s.as_bytes();
+    let mut pos = 0;
+
+    let year = parse_digits(bytes, &mut pos, 4)? as i64;
+    expect_byte(bytes, &mut pos, b'-')?;
+    let month = parse_digits(bytes, &mut pos, 2)?;
+    expect_byte(bytes, &mut pos, b'-')?;
+    let day = parse_digits(bytes, &mut pos, 2)?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    if pos == bytes.len() {
+        return Some(days as i128 * NANOS_PER_DAY);
+    }
+
+    if !matches!(bytes[pos], b'T' | b't' | b' ') {
+        return None;
+    }
+    pos += 1;
+    let hour = parse_digits(bytes, &mut pos, 2)?;
+    expect_byte(bytes, &mut pos, b':')?;
+    let minute = parse_digits(bytes, &mut pos, 2)?;
+    let mut second = 0;
+    let mut nanos = 0;
+    if bytes.get(pos) == Some(&b':') {
+        pos += 1;
+        second = parse_digits(bytes, &mut pos, 2)?;
+        if matches!(bytes.get(pos), Some(b'.') | Some(b',')) {
+            pos += 1;
+            let start = pos;
+            let mut scale = 100_000_000;
+            while let Some(c) = bytes.get(pos).filter(|c| c.is_ascii_digit()) {
+                nanos += (c - b'0') as u32 * scale;
+                scale /= 10;
+                pos += 1;
+            }
+            if pos == start {
+                return None;
+            }
+        }
+    }
+    // allow leap second
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let offset_seconds = match bytes.get(pos) {
+        None => 0,
+        Some(b'Z') | Some(b'z') => {
+            pos += 1;
+            0
+        }
+        Some(sign @ (b'+' | b'-')) => {
+            let negative = *sign == b'-';
+            pos += 1;
+            let offset_hour = parse_digits(bytes, &mut pos, 2)?;
+            // The minutes are optional, like `+08`, but a `:` must be followed by them.
+            let offset_minute = if bytes.get(pos) == Some(&b':') {
+                pos += 1;
+                parse_digits(bytes, &mut pos, 2)?
+            } else if pos < bytes.len() {
+                parse_digits(bytes, &mut pos, 2)?
+            } else {
+                0
+            };
+            if offset_hour > 23 || offset_minute > 59 {
+                return None;
+            }
+            let offset = (offset_hour * 3600 + offset_minute * 60) as i64;
+            if negative {
+                -offset
+            } else {
+                offset
+            }
+        }
+        Some(_) => return None,
+    };
+    if pos != bytes.len() {
+        return None;
+    }
+
+    let seconds = days * 86400 + (hour * 3600 + minute * 60 + second) as i64 - offset_seconds;
+    Some(seconds as i128 * NANOS_PER_SECOND + nanos as i128)
+}
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+const NANOS_PER_DAY: i128 = 86400 * NANOS_PER_SECOND;
+
+#[inline]
+fn parse_digits(bytes: &[u8], pos: &mut usize, len: usize) -> Option<u32> {
+    let digits = bytes.get(*pos..*pos + len)?;
+    let mut n = 0;
+    for c in digits {
+        if !c.is_ascii_digit() {
+            return None;
+        }
+        n = n * 10 + (c - b'0') as u32;
+    }
+    *pos += len;
+    Some(n)
+}
+
+#[inline]
+fn expect_byte(bytes: &[u8], pos: &mut usize, expected: u8) -> Option<()> {
+    if bytes.get(*pos) == Some(&expected) {
+        *pos += 1;
+        Some(())
+    } else {
+        None
+    }
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        4 | 6 | 9 | 11 => 30,
+        2 => {
+            if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 {
+                29
+            } else {
+                28
+            }
+        }
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 in the proleptic Gregorian calendar.
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
@@ -18,6 +18,7 @@ use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Formatter;
 
+use super::extension::ExtensionValue;
 use super::number::Number;
 use super::ser::Encoder;
 
@@ -33,6 +34,7 @@ pub enum Value<'a> {
     Number(Number),
     Array(Vec<Value<'a>>),
     Object(Object<'a>),
+    Extension(ExtensionValue<'a>),
 }
 
 impl<'a> Debug for Value<'a> {
@@ -52,6 +54,7 @@ impl<'a> Debug for Value<'a> {
                 Debug::fmt(v, formatter)?;
                 formatter.write_str(")")
             }
+            Value::Extension(ref v) => Debug::fmt(v, formatter),
         }
     }
 }
@@ -99,6 +102,11 @@ impl<'a> Display for Value<'a> {
                 }
                 write!(f, "}}")
             }
+            Value::Extension(ref v) => {
+                let mut buf = String::new();
+                v.format(&mut buf);
+                write!(f, "{buf}")
+            }
         }
     }
 }
@@ -192,6 +200,17 @@ impl<'a> Value<'a> {
         }
     }
 
+    pub fn is_extension(&self) -> bool {
+        self.as_extension().is_some()
+    }
+
+    pub fn as_extension(&self) -> Option<&ExtensionValue<'a>> {
+        match self {
+            Value::Extension(v) => Some(v),
+            _ => None,
+        }
+    }
+
     pub fn is_null(&self) -> bool {
         self.as_null().is_some()
     }
@@ -216,6 +235,88 @@ impl<'a> Value<'a> {
         buf
     }
 
+    /// Convert the Value into an owned Value that doesn't borrow the input.
+    pub fn into_owned(self) -> Value<'static> {
+        match self {
+            Value::Null => Value::Null,
+            Value::Bool(v) => Value::Bool(v),
+            Value::String(v) => Value::String(Cow::Owned(v.into_owned())),
+            Value::Number(v) => Value::Number(v),
+            Value::Array(vs) => Value::Array(vs.into_iter().map(Value::into_owned).collect()),
+            Value::Object(vs) => {
+                Value::Object(vs.into_iter().map(|(k, v)| (k, v.into_owned())).collect())
+            }
+            Value::Extension(v) => Value::Extension(ExtensionValue {
+                tag: v.tag,
+                payload: Cow::Owned(v.payload.into_owned()),
+            }),
+        }
+    }
+
+    /// Format the value for the snapshots of the tests, like golden files, the output only
+    /// depends on the value. The keys are in sorted order and the strings are escaped.
+    /// The strings longer than `max_len` characters, and the arrays and objects with more
+    /// than `max_len` elements are truncated, with the number of the omitted items.
+    ///
+    /// ```
+    /// let value = jsonb::parse_value(br#"{"b":[1,2,3,4],"a":"abcdefgh"}"#).unwrap();
+    /// assert_eq!(
+    ///     value.debug_stable(3),
+    ///     r#"{"a": "abc"...(+5 chars), "b": [1, 2, 3, ...(+1 items)]}"#
+    /// );
+    /// ```
+    pub fn debug_stable(&self, max_len: usize) -> String {
+        let mut buf = String::new();
+        self.write_debug_stable(max_len, &mut buf);
+        buf
+    }
+
+    fn write_debug_stable(&self, max_len: usize, buf: &mut String) {
+        match self {
+            Value::String(s) => match s.char_indices().nth(max_len) {
+                Some((end, _)) => {
+                    let omitted = s[end..].chars().count();
+                    buf.push_str(&format!("{:?}...(+{} chars)", &s[..end], omitted));
+                }
+                None => buf.push_str(&format!("{:?}", s)),
+            },
+            Value::Array(vals) => {
+                buf.push('[');
+                for (i, val) in vals.iter().take(max_len).enumerate() {
+                    if i > 0 {
+                        buf.push_str(", ");
+                    }
+                    val.write_debug_stable(max_len, buf);
+                }
+                if vals.len() > max_len {
+                    if max_len > 0 {
+                        buf.push_str(", ");
+                    }
+                    buf.push_str(&format!("...(+{} items)", vals.len() - max_len));
+                }
+                buf.push(']');
+            }
+            Value::Object(obj) => {
+                buf.push('{');
+                for (i, (key, val)) in obj.iter().take(max_len).enumerate() {
+                    if i > 0 {
+                        buf.push_str(", ");
+                    }
+                    buf.push_str(&format!("{:?}: ", key));
+                    val.write_debug_stable(max_len, buf);
+                }
+                if obj.len() > max_len {
+                    if max_len > 0 {
+                        buf.push_str(", ");
+                    }
+                    buf.push_str(&format!("...(+{} fields)", obj.len() - max_len));
+                }
+                buf.push('}');
+            }
+            _ => buf.push_str(&self.to_string()),
+        }
+    }
+
     pub fn get_by_name_ignore_case(&self, name: &str) -> Option<&Value<'a>> {
         match self {
             Value::Object(obj) => match obj.get(name) {
@@ -0,0 +1,75 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! JavaScript bindings of the main API.
+//!
+//! The `JSONB` values are passed as `Uint8Array`, so the documents stored by the backend
+//! can be inspected and edited in the browser without converting them to text.
+//!
+//! ```js
+//! import { parse, stringify, getByPath } from "jsonb";
+//!
+//! const value = parse('{"a":[1,2,3]}');
+//! stringify(getByPath(value, "$.a[1 to last]")); // "[2,3]"
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::Error;
+use crate::functions::get_by_path_array;
+use crate::functions::to_string;
+use crate::input::RawJsonb;
+use crate::jsonpath::parse_json_path;
+use crate::layout::validate;
+use crate::parser::parse_value;
+
+fn to_js_error(err: Error) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+// The bytes come from JavaScript, they are validated before decoding,
+// so that a malformed value is an error instead of a panic.
+fn to_jsonb(value: &[u8]) -> Result<RawJsonb<'_>, JsError> {
+    validate(value).map_err(to_js_error)?;
+    Ok(RawJsonb::new(value))
+}
+
+/// Parse the `JSON` text to `JSONB` value.
+#[wasm_bindgen]
+pub fn parse(text: &str) -> Result<Vec<u8>, JsError> {
+    parse_value(text.as_bytes())
+        .map(|value| value.to_vec())
+        .map_err(to_js_error)
+}
+
+/// Convert the `JSONB` value to `JSON` text.
+#[wasm_bindgen]
+pub fn stringify(value: &[u8]) -> Result<String, JsError> {
+    Ok(to_string(&to_jsonb(value)?))
+}
+
+/// Select the values of the `JSONB` value by the `JSONPath`, returns them as a `JSONB` array.
+#[wasm_bindgen(js_name = getByPath)]
+pub fn get_by_path(value: &[u8], path: &str) -> Result<Vec<u8>, JsError> {
+    let value = to_jsonb(value)?;
+    let json_path = parse_json_path(path.as_bytes()).map_err(to_js_error)?;
+    get_by_path_array(&value, json_path).ok_or_else(|| to_js_error(Error::InvalidJsonb))
+}
+
+/// Compute the differences from the `left` value to the `right` value,
+/// returns them as a `JSONB` array of JSON Patch (RFC 6902) operations.
+#[wasm_bindgen]
+pub fn diff(left: &[u8], right: &[u8]) -> Result<Vec<u8>, JsError> {
+    crate::diff::diff(&to_jsonb(left)?, &to_jsonb(right)?).map_err(to_js_error)
+}
@@ -0,0 +1,56 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonb::{parse_value, parse_value_async, NdjsonReader};
+use tokio::io::AsyncReadExt;
+
+#[tokio::test]
+async fn test_parse_value_async() {
+    let text = r#"{"a":[1,"b",null],"c":{"d":true}}"#;
+    let mut reader = text.as_bytes();
+    let value = parse_value_async(&mut reader).await.unwrap();
+    assert_eq!(value, parse_value(text.as_bytes()).unwrap());
+
+    let mut reader = r#"{"a":"#.as_bytes();
+    assert!(parse_value_async(&mut reader).await.is_err());
+
+    // the value spans several chunks
+    let text = format!("[{}]", vec![r#""\u00e9abc""#; 5000].join(","));
+    let mut reader = text.as_bytes();
+    let value = parse_value_async(&mut reader).await.unwrap();
+    assert_eq!(value, parse_value(text.as_bytes()).unwrap());
+
+    // the invalid nesting is reported without reading the endless rest
+    let mut reader = r#"{"a":[1}"#.as_bytes().chain(tokio::io::repeat(b' '));
+    assert!(parse_value_async(&mut reader).await.is_err());
+}
+
+#[tokio::test]
+async fn test_ndjson_reader() {
+    let text = "{\"a\":1}\n\n[1,2]\r\n  \n{\"b\"\n\"c\"";
+    let mut reader = NdjsonReader::new(text.as_bytes());
+    let mut values = Vec::new();
+    while let Some(value) = reader.next_value().await {
+        values.push(value.map(|v| v.to_string()).ok());
+    }
+    assert_eq!(
+        values,
+        vec![
+            Some(r#"{"a":1}"#.to_string()),
+            Some("[1,2]".to_string()),
+            None,
+            Some(r#""c""#.to_string()),
+        ]
+    );
+}
@@ -0,0 +1,33 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// The fixtures shared by the tests.
+
+use jsonb::{from_slice, parse_value};
+
+// Encode the JSON text as a `JSONB` value.
+pub fn jsonb(s: &str) -> Vec<u8> {
+    parse_value(s.as_bytes()).unwrap().to_vec()
+}
+
+// Assert that the `JSONB` result is equal to the JSON text, or that there is no result.
+pub fn assert_jsonb(res: Option<Vec<u8>>, expect: Option<&str>) {
+    match expect {
+        Some(expect) => {
+            let expect = parse_value(expect.as_bytes()).unwrap();
+            assert_eq!(from_slice(&res.unwrap()).unwrap(), expect);
+        }
+        None => assert_eq!(res, None),
+    }
+}
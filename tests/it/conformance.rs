@@ -0,0 +1,64 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonb::conformance::*;
+
+#[test]
+fn test_conformance() {
+    let texts = [
+        r#"{"b":[1,"x",null],"a":{"c":true}}"#,
+        r#"{"a":{"c":true},"b":[1,"x",null]}"#,
+        "18446744073709551615",
+        "-1.5e-10",
+        r#""\u00e9\n""#,
+    ];
+    assert_eq!(check(&texts), vec![]);
+
+    // Both reject the invalid texts, which are not serialized or compared.
+    for text in ["[1,]", "tru", "{", "01"] {
+        assert_eq!(check_parse(text), None);
+        assert_eq!(check_serialize(text), None);
+        assert_eq!(check_compare(text, text), None);
+    }
+
+    let divergence = |behavior: Behavior, inputs: &[&str], jsonb: &str, serde_json: &str| {
+        Some(Divergence {
+            behavior,
+            inputs: inputs.iter().map(|s| s.to_string()).collect(),
+            jsonb: jsonb.to_string(),
+            serde_json: serde_json.to_string(),
+        })
+    };
+    assert_eq!(
+        check_parse("-0"),
+        divergence(Behavior::Parse, &["-0"], "0", "-0.0")
+    );
+    assert_eq!(
+        check_serialize("[1.0]"),
+        divergence(Behavior::Serialize, &["[1.0]"], "[1]", "[1.0]")
+    );
+    assert_eq!(
+        check_compare(r#"{"a":1}"#, r#"{"a":1.0}"#),
+        divergence(
+            Behavior::Compare,
+            &[r#"{"a":1}"#, r#"{"a":1.0}"#],
+            "equal",
+            "not equal"
+        )
+    );
+
+    let divergences = check(&["2.0", "2"]);
+    let behaviors: Vec<_> = divergences.iter().map(|d| d.behavior).collect();
+    assert_eq!(behaviors, vec![Behavior::Serialize, Behavior::Compare]);
+}
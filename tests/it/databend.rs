@@ -0,0 +1,186 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+
+use jsonb::databend::*;
+use jsonb::Error;
+
+use crate::common::jsonb;
+
+// The cases are taken from the examples of the Databend variant functions documentation.
+
+fn text(res: Option<Vec<u8>>) -> Option<String> {
+    res.map(|v| json_to_string(&v))
+}
+
+#[test]
+fn test_databend_get() {
+    let value = jsonb(r#"{"k1":[0,1,2],"k2":{"k3":3,"k4":4},"K2":null,"k5":null}"#);
+    assert_eq!(text(get(&value, "k1")), Some("[0,1,2]".to_string()));
+    assert_eq!(text(get(&value, "K1")), None);
+    assert_eq!(text(get(&value, "k5")), Some("null".to_string()));
+    assert_eq!(text(get(&value, "k6")), None);
+    assert_eq!(text(get_index(&value, 0)), None);
+    assert_eq!(
+        text(get_ignore_case(&value, "K1")),
+        Some("[0,1,2]".to_string())
+    );
+    assert_eq!(
+        text(get_ignore_case(&value, "k2")),
+        Some(r#"{"k3":3,"k4":4}"#.to_string())
+    );
+    assert_eq!(
+        text(get_ignore_case(&value, "K2")),
+        Some("null".to_string())
+    );
+
+    let array = jsonb("[0,1,2]");
+    assert_eq!(text(get_index(&array, 1)), Some("1".to_string()));
+    assert_eq!(text(get_index(&array, 3)), None);
+    assert_eq!(text(get_index(&array, -1)), None);
+    assert_eq!(text(get(&array, "k1")), None);
+    assert_eq!(text(get(&jsonb("null"), "k1")), None);
+
+    for (path, expect) in [
+        ("k1[0]", Some("0")),
+        ("k2.k3", Some("3")),
+        ("k2:k4", Some("4")),
+        (r#"["k2"]["k3"]"#, Some("3")),
+        ("k1[3]", None),
+        ("k2.k5", None),
+    ] {
+        let res = get_path(&value, path).unwrap();
+        assert_eq!(text(res), expect.map(String::from), "{path}");
+    }
+    assert_eq!(get_path(&value, "k1["), Err(Error::InvalidJsonPath));
+
+    let value = r#"{"k1":"v1","k2":{"k3":[1,"v3"]}}"#;
+    for (path, expect) in [
+        ("k1", Some("v1")),
+        ("k2", Some(r#"{"k3":[1,"v3"]}"#)),
+        ("k2.k3[1]", Some("v3")),
+        ("k2.k3[0]", Some("1")),
+        ("k3", None),
+    ] {
+        let res = json_extract_path_text(value, path).unwrap();
+        assert_eq!(res, expect.map(String::from), "{path}");
+    }
+    assert_eq!(json_extract_path_text("{", "k1").unwrap(), None);
+}
+
+#[test]
+fn test_databend_parse_json() {
+    assert_eq!(parse_json("[1,2]").unwrap(), jsonb("[1,2]"));
+    assert!(parse_json("[1,").is_err());
+    assert_eq!(try_parse_json("[1,2]"), Some(jsonb("[1,2]")));
+    assert_eq!(try_parse_json("[1,"), None);
+    assert_eq!(check_json(r#"{"a":1}"#), None);
+    assert_eq!(
+        check_json("[1,"),
+        Some("EOF while parsing a value, pos 3".to_string())
+    );
+}
+
+#[test]
+fn test_databend_type_functions() {
+    let cases = vec![
+        ("null", "null"),
+        ("true", "boolean"),
+        ("-1", "number"),
+        ("1.5", "number"),
+        (r#""abc""#, "string"),
+        ("[1]", "array"),
+        (r#"{"a":1}"#, "object"),
+    ];
+    for (value, ty) in cases {
+        let value = jsonb(value);
+        assert_eq!(json_typeof(&value).unwrap(), ty);
+        assert_eq!(is_null_value(&value), ty == "null");
+        assert_eq!(is_boolean(&value), ty == "boolean");
+        assert_eq!(is_float(&value), ty == "number");
+        assert_eq!(is_string(&value), ty == "string");
+        assert_eq!(is_array(&value), ty == "array");
+        assert_eq!(is_object(&value), ty == "object");
+    }
+    assert!(is_integer(&jsonb("-1")));
+    assert!(is_integer(&jsonb("18446744073709551615")));
+    assert!(!is_integer(&jsonb("1.5")));
+
+    assert_eq!(as_boolean(&jsonb("true")), Some(true));
+    assert_eq!(as_boolean(&jsonb(r#""true""#)), None);
+    assert_eq!(as_boolean(&jsonb("null")), None);
+    assert_eq!(as_integer(&jsonb("-1")), Some(-1));
+    assert_eq!(as_integer(&jsonb("1.5")), None);
+    assert_eq!(as_integer(&jsonb(r#""1""#)), None);
+    assert_eq!(as_float(&jsonb("1.5")), Some(1.5));
+    assert_eq!(as_float(&jsonb("1")), Some(1.0));
+    assert_eq!(as_string(&jsonb(r#""abc""#)), Some("abc".to_string()));
+    assert_eq!(as_string(&jsonb("1")), None);
+    assert_eq!(as_array(&jsonb("[1]")), Some(jsonb("[1]")));
+    assert_eq!(as_array(&jsonb(r#"{"a":1}"#)), None);
+    assert_eq!(as_object(&jsonb(r#"{"a":1}"#)), Some(jsonb(r#"{"a":1}"#)));
+    assert_eq!(as_object(&jsonb("null")), None);
+
+    assert_eq!(json_array_length(&jsonb("[1,2,3]")), Some(3));
+    assert_eq!(json_array_length(&jsonb(r#"{"a":1}"#)), None);
+    assert_eq!(
+        text(json_object_keys(&jsonb(r#"{"b":1,"a":2}"#))),
+        Some(r#"["a","b"]"#.to_string())
+    );
+    assert_eq!(json_object_keys(&jsonb("[1]")), None);
+}
+
+#[test]
+fn test_databend_cast_functions() {
+    assert_eq!(to_boolean(&jsonb("true")), Ok(true));
+    assert_eq!(to_boolean(&jsonb(r#""False""#)), Ok(false));
+    assert_eq!(to_boolean(&jsonb("1")), Err(Error::InvalidCast));
+    assert_eq!(to_int64(&jsonb("-10")), Ok(-10));
+    assert_eq!(to_int64(&jsonb("true")), Ok(1));
+    assert_eq!(to_int64(&jsonb(r#""12""#)), Ok(12));
+    assert_eq!(to_int64(&jsonb("null")), Err(Error::InvalidCast));
+    assert_eq!(to_uint64(&jsonb("-10")), Err(Error::InvalidCast));
+    assert_eq!(to_float64(&jsonb(r#""1.5""#)), Ok(1.5));
+    assert_eq!(to_string(&jsonb("1.5")), Ok("1.5".to_string()));
+    assert_eq!(to_string(&jsonb(r#""abc""#)), Ok("abc".to_string()));
+    assert_eq!(to_string(&jsonb("[1]")), Err(Error::InvalidCast));
+}
+
+#[test]
+fn test_databend_compare() {
+    let sorted = [
+        "false",
+        "true",
+        "-1",
+        "1.5",
+        "10",
+        r#""a""#,
+        r#""b""#,
+        r#"{"a":1}"#,
+        "[1,2]",
+        "[2]",
+        "null",
+    ];
+    for (i, left) in sorted.iter().enumerate() {
+        for (j, right) in sorted.iter().enumerate() {
+            let res = compare(&jsonb(left), &jsonb(right)).unwrap();
+            assert_eq!(res, i.cmp(&j), "{left} {right}");
+        }
+    }
+    assert_eq!(
+        compare(&jsonb("1"), &jsonb("1.0")).unwrap(),
+        Ordering::Equal
+    );
+}
@@ -0,0 +1,129 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonb::encoding::*;
+use jsonb::{to_string, Error};
+
+use crate::common::jsonb;
+
+#[test]
+fn test_header_and_entry() {
+    let header = Header {
+        kind: ContainerKind::Object,
+        length: 3,
+    };
+    assert_eq!(header.encode(), Ok(OBJECT_CONTAINER_TAG | 3));
+    assert_eq!(
+        Header::decode(0x80000002),
+        Ok(Header {
+            kind: ContainerKind::Array,
+            length: 2,
+        })
+    );
+    assert_eq!(Header::decode(0x00000001), Err(Error::InvalidJsonbHeader));
+    let header = Header {
+        kind: ContainerKind::Array,
+        length: CONTAINER_HEADER_LEN_MASK as usize + 1,
+    };
+    assert_eq!(header.encode(), Err(Error::InvalidJsonbHeader));
+
+    let entry = Entry {
+        kind: EntryKind::String,
+        length: 5,
+    };
+    assert_eq!(entry.encode(), Ok(STRING_TAG | 5));
+    assert_eq!(
+        Entry::decode(CONTAINER_TAG | 12).unwrap().kind,
+        EntryKind::Container
+    );
+    assert_eq!(Entry::decode(0x70000000), Err(Error::InvalidJsonbJEntry));
+}
+
+#[test]
+fn test_container_reader() {
+    let value = jsonb(r#"{"a":1,"b":[true,"x"],"c":null}"#);
+    let reader = ContainerReader::new(&value).unwrap();
+    assert_eq!(reader.kind(), ContainerKind::Object);
+    assert_eq!(reader.len(), 3);
+    assert_eq!(reader.encoded_len(), value.len());
+    let keys: Vec<_> = (0..reader.len()).map(|i| reader.key(i).unwrap()).collect();
+    assert_eq!(keys, vec!["a", "b", "c"]);
+    assert_eq!(reader.key(3), None);
+    assert_eq!(reader.value(2).unwrap().0.kind, EntryKind::Null);
+
+    let (entry, data) = reader.value(1).unwrap();
+    assert_eq!(entry.kind, EntryKind::Container);
+    let array = ContainerReader::new(data).unwrap();
+    assert_eq!(array.kind(), ContainerKind::Array);
+    assert_eq!(array.key(0), None);
+    assert_eq!(array.value(0).unwrap().0.kind, EntryKind::True);
+    assert_eq!(
+        array.value(1).unwrap(),
+        (
+            Entry {
+                kind: EntryKind::String,
+                length: 1
+            },
+            &b"x"[..]
+        )
+    );
+
+    let scalar = jsonb("\"abc\"");
+    let reader = ContainerReader::new(&scalar).unwrap();
+    assert_eq!(reader.kind(), ContainerKind::Scalar);
+    assert_eq!(reader.value(0).unwrap().1, b"abc");
+
+    // The entries must fit in the buffer.
+    assert_eq!(
+        ContainerReader::new(&value[..value.len() - 1]).err(),
+        Some(Error::InvalidEOF)
+    );
+    assert_eq!(
+        ContainerReader::new(&[0x80, 0, 0, 9]).err(),
+        Some(Error::InvalidEOF)
+    );
+    assert_eq!(ContainerReader::new(&[0x80]).err(), Some(Error::InvalidEOF));
+}
+
+#[test]
+fn test_container_writer() {
+    let mut inner = ContainerWriter::array();
+    inner.push_jsonb(&jsonb("1")).unwrap();
+    inner.push_value(EntryKind::True, &[]).unwrap();
+    let mut array = Vec::new();
+    inner.finish(&mut array).unwrap();
+    assert_eq!(to_string(&array), "[1,true]");
+
+    let mut writer = ContainerWriter::object();
+    writer.push_key("a").unwrap();
+    writer.push_jsonb(&array).unwrap();
+    writer.push_key("b").unwrap();
+    writer.push_value(EntryKind::String, b"x").unwrap();
+    let mut buf = Vec::new();
+    writer.finish(&mut buf).unwrap();
+    assert_eq!(to_string(&buf), r#"{"a":[1,true],"b":"x"}"#);
+    assert_eq!(buf, jsonb(r#"{"a":[1,true],"b":"x"}"#));
+
+    // The keys and the values must be written in turn.
+    let mut writer = ContainerWriter::object();
+    assert!(writer.push_value(EntryKind::Null, &[]).is_err());
+    writer.push_key("a").unwrap();
+    assert!(writer.push_key("b").is_err());
+    assert!(writer.finish(&mut Vec::new()).is_err());
+    assert!(ContainerWriter::array().push_key("a").is_err());
+
+    let mut buf = Vec::new();
+    write_scalar(EntryKind::Null, &[], &mut buf).unwrap();
+    assert_eq!(buf, jsonb("null"));
+}
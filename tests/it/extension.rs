@@ -0,0 +1,209 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use jsonb::{
+    compare, convert_to_comparable, from_slice, get_extension_type, parse_value,
+    register_extension_type, to_string, unregister_extension_type, Error, ExtensionType,
+    ExtensionValue, Number, Object, Value,
+};
+
+// Stores a point as two big-endian f64, ordered by latitude and then longitude as the plain object.
+struct GeoPoint;
+
+impl ExtensionType for GeoPoint {
+    fn name(&self) -> &str {
+        "geo_point"
+    }
+
+    fn encode(&self, data: &[u8], buf: &mut Vec<u8>) -> Result<(), Error> {
+        if data.len() != 16 {
+            return Err(Error::InvalidCast);
+        }
+        buf.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Value<'static>, Error> {
+        let (lat, lon) = decode_point(payload);
+        let mut obj = Object::new();
+        obj.insert("lat".to_string(), Value::Number(Number::Float64(lat)));
+        obj.insert("lon".to_string(), Value::Number(Number::Float64(lon)));
+        Ok(Value::Object(obj))
+    }
+
+    fn format(&self, payload: &[u8], buf: &mut String) {
+        let (lat, lon) = decode_point(payload);
+        buf.push_str(&format!("\"POINT({} {})\"", lon, lat));
+    }
+}
+
+fn decode_point(payload: &[u8]) -> (f64, f64) {
+    let lat = f64::from_be_bytes(payload[0..8].try_into().unwrap());
+    let lon = f64::from_be_bytes(payload[8..16].try_into().unwrap());
+    (lat, lon)
+}
+
+fn point(lat: f64, lon: f64) -> Vec<u8> {
+    let mut data = lat.to_be_bytes().to_vec();
+    data.extend_from_slice(&lon.to_be_bytes());
+    data
+}
+
+// Stores an integer as decimal text, use the default callbacks except `decode`.
+struct TextInt;
+
+impl ExtensionType for TextInt {
+    fn name(&self) -> &str {
+        "text_int"
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Value<'static>, Error> {
+        let s = std::str::from_utf8(payload)?;
+        let n = s.parse::<i64>().map_err(|_| Error::InvalidCast)?;
+        Ok(Value::Number(Number::Int64(n)))
+    }
+}
+
+// Decodes the payload to an extension value of itself.
+struct Recursive;
+
+impl ExtensionType for Recursive {
+    fn name(&self) -> &str {
+        "recursive"
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Value<'static>, Error> {
+        Ok(Value::Array(vec![Value::Extension(ExtensionValue {
+            tag: 15,
+            payload: Cow::Owned(payload.to_vec()),
+        })]))
+    }
+}
+
+#[test]
+fn test_extension_registry() {
+    register_extension_type(10, Arc::new(GeoPoint)).unwrap();
+    let res = register_extension_type(10, Arc::new(TextInt));
+    assert_eq!(res, Err(Error::DuplicateExtensionType(10)));
+    assert_eq!(get_extension_type(10).unwrap().name(), "geo_point");
+
+    let removed = unregister_extension_type(10).unwrap();
+    assert_eq!(removed.name(), "geo_point");
+    assert!(get_extension_type(10).is_none());
+    assert_eq!(
+        ExtensionValue::new(10, &point(1.0, 2.0)),
+        Err(Error::UnknownExtensionType(10))
+    );
+}
+
+#[test]
+fn test_extension_encode_decode() {
+    register_extension_type(11, Arc::new(GeoPoint)).unwrap();
+
+    assert_eq!(ExtensionValue::new(11, b"abc"), Err(Error::InvalidCast));
+    let ext = ExtensionValue::new(11, &point(40.5, -73.25)).unwrap();
+    let mut obj = Object::new();
+    obj.insert("id".to_string(), Value::from(1_u64));
+    obj.insert("loc".to_string(), Value::Extension(ext.clone()));
+    let value = Value::Object(obj);
+
+    let buf = value.to_vec();
+    let decoded = from_slice(&buf).unwrap();
+    assert_eq!(decoded, value);
+    let loc = decoded.as_object().unwrap().get("loc").unwrap();
+    assert_eq!(loc.as_extension(), Some(&ext));
+
+    assert_eq!(to_string(&buf), r#"{"id":1,"loc":"POINT(-73.25 40.5)"}"#);
+    assert_eq!(
+        format!("{}", value),
+        r#"{"id":1,"loc":"POINT(-73.25 40.5)"}"#
+    );
+
+    let json: serde_json::Value = value.into();
+    assert_eq!(
+        json.to_string(),
+        r#"{"id":1,"loc":{"lat":40.5,"lon":-73.25}}"#
+    );
+}
+
+#[test]
+fn test_extension_compare() {
+    register_extension_type(12, Arc::new(GeoPoint)).unwrap();
+    register_extension_type(13, Arc::new(TextInt)).unwrap();
+
+    let p1 = Value::Extension(ExtensionValue::new(12, &point(1.0, 2.0)).unwrap()).to_vec();
+    let p2 = Value::Extension(ExtensionValue::new(12, &point(1.0, 3.0)).unwrap()).to_vec();
+    let n1 = Value::Extension(ExtensionValue::new(13, b"-5").unwrap()).to_vec();
+    let n2 = Value::Extension(ExtensionValue::new(13, b"100").unwrap()).to_vec();
+
+    // the extension values are compared as their plain values
+    assert_eq!(compare(&p1, &p2).unwrap(), Ordering::Less);
+    assert_eq!(compare(&p2, &p1).unwrap(), Ordering::Greater);
+    assert_eq!(compare(&n1, &n2).unwrap(), Ordering::Less);
+
+    // compare with builtin values as plain values
+    let cases = vec![
+        (&n1, r#"-5"#, Ordering::Equal),
+        (&n2, r#"99.5"#, Ordering::Greater),
+        (&n2, r#""abc""#, Ordering::Less),
+        (&n2, r#"null"#, Ordering::Less),
+        (&p1, r#"{"lat":1.0,"lon":2.0}"#, Ordering::Equal),
+        (&p1, r#"{"lat":1.0,"lon":1.0}"#, Ordering::Greater),
+    ];
+    for (ext, s, expect) in cases {
+        let value = parse_value(s.as_bytes()).unwrap().to_vec();
+        assert_eq!(compare(ext, &value).unwrap(), expect);
+        assert_eq!(compare(&value, ext).unwrap(), expect.reverse());
+
+        let mut ext_comparable = Vec::new();
+        convert_to_comparable(ext, &mut ext_comparable);
+        let mut comparable = Vec::new();
+        convert_to_comparable(&value, &mut comparable);
+        assert_eq!(ext_comparable.cmp(&comparable), expect);
+    }
+
+    // the order is total and the same as the comparable keys,
+    // "10" is greater than "9" as numbers but less as payload bytes.
+    let values = [
+        Value::Extension(ExtensionValue::new(13, b"9").unwrap()).to_vec(),
+        Value::Extension(ExtensionValue::new(13, b"10").unwrap()).to_vec(),
+        parse_value(b"9.5").unwrap().to_vec(),
+    ];
+    let expects = [
+        (0, 1, Ordering::Less),
+        (1, 2, Ordering::Greater),
+        (0, 2, Ordering::Less),
+    ];
+    for (left, right, expect) in expects {
+        let (left, right) = (&values[left], &values[right]);
+        assert_eq!(compare(left, right).unwrap(), expect);
+        let mut left_comparable = Vec::new();
+        convert_to_comparable(left, &mut left_comparable);
+        let mut right_comparable = Vec::new();
+        convert_to_comparable(right, &mut right_comparable);
+        assert_eq!(left_comparable.cmp(&right_comparable), expect);
+    }
+
+    // the plain values containing extension values are not decoded again
+    register_extension_type(15, Arc::new(Recursive)).unwrap();
+    let r = Value::Extension(ExtensionValue::new(15, b"x").unwrap()).to_vec();
+    let value = parse_value(b"1").unwrap().to_vec();
+    assert_eq!(compare(&r, &value).unwrap(), Ordering::Less);
+    let mut comparable = Vec::new();
+    convert_to_comparable(&r, &mut comparable);
+}
@@ -0,0 +1,188 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ptr;
+use std::slice;
+
+use jsonb::ffi::*;
+use jsonb::parse_value;
+
+fn parse(text: &str) -> JsonbBuffer {
+    let mut buf = JsonbBuffer {
+        data: ptr::null_mut(),
+        len: 0,
+        cap: 0,
+    };
+    let res = unsafe { jsonb_parse(text.as_ptr(), text.len(), &mut buf) };
+    assert_eq!(res, JSONB_OK);
+    buf
+}
+
+fn bytes(buf: &JsonbBuffer) -> &[u8] {
+    unsafe { slice::from_raw_parts(buf.data, buf.len) }
+}
+
+#[test]
+fn test_ffi() {
+    let value = parse(r#"{"a":[1,2,{"b":"c"}]}"#);
+    assert_eq!(
+        bytes(&value),
+        parse_value(r#"{"a":[1,2,{"b":"c"}]}"#.as_bytes())
+            .unwrap()
+            .to_vec()
+    );
+
+    let mut out = JsonbBuffer {
+        data: ptr::null_mut(),
+        len: 0,
+        cap: 0,
+    };
+    let res = unsafe { jsonb_to_string(value.data, value.len, &mut out) };
+    assert_eq!(res, JSONB_OK);
+    assert_eq!(bytes(&out), br#"{"a":[1,2,{"b":"c"}]}"#);
+    unsafe { jsonb_buffer_free(out) };
+
+    let mut out = JsonbBuffer {
+        data: ptr::null_mut(),
+        len: 0,
+        cap: 0,
+    };
+    let path = "$.a[*]";
+    let res =
+        unsafe { jsonb_get_by_path(value.data, value.len, path.as_ptr(), path.len(), &mut out) };
+    assert_eq!(res, JSONB_OK);
+    let expect = parse(r#"[1,2,{"b":"c"}]"#);
+    assert_eq!(bytes(&out), bytes(&expect));
+    unsafe { jsonb_buffer_free(out) };
+    unsafe { jsonb_buffer_free(expect) };
+
+    let path = "$.a[";
+    let res = unsafe {
+        jsonb_get_by_path(
+            value.data,
+            value.len,
+            path.as_ptr(),
+            path.len(),
+            ptr::null_mut(),
+        )
+    };
+    assert_eq!(res, JSONB_ERROR_NULL_POINTER);
+    let mut out = JsonbBuffer {
+        data: ptr::null_mut(),
+        len: 0,
+        cap: 0,
+    };
+    let res =
+        unsafe { jsonb_get_by_path(value.data, value.len, path.as_ptr(), path.len(), &mut out) };
+    assert_eq!(res, JSONB_ERROR_INVALID_PATH);
+    assert!(out.data.is_null());
+
+    let other = parse("[1,2]");
+    let mut order = 0;
+    let res = unsafe { jsonb_compare(value.data, value.len, other.data, other.len, &mut order) };
+    assert_eq!(res, JSONB_OK);
+    assert_eq!(order, -1);
+    let res = unsafe { jsonb_compare(other.data, other.len, other.data, other.len, &mut order) };
+    assert_eq!(res, JSONB_OK);
+    assert_eq!(order, 0);
+    unsafe { jsonb_buffer_free(other) };
+    unsafe { jsonb_buffer_free(value) };
+
+    let mut out = JsonbBuffer {
+        data: ptr::null_mut(),
+        len: 0,
+        cap: 0,
+    };
+    let text = "[1,";
+    let res = unsafe { jsonb_parse(text.as_ptr(), text.len(), &mut out) };
+    assert_eq!(res, JSONB_ERROR_INVALID_JSON);
+    let res = unsafe { jsonb_parse(ptr::null(), 0, &mut out) };
+    assert_eq!(res, JSONB_ERROR_NULL_POINTER);
+}
+
+#[test]
+fn test_ffi_invalid_jsonb() {
+    let value = parse(r#"{"a":[1,2,{"b":"c"}],"d":-1.5}"#);
+    let valid = bytes(&value).to_vec();
+    unsafe { jsonb_buffer_free(value) };
+
+    // Truncated buffers, flipped bytes and invalid numbers and strings must be rejected.
+    let mut inputs = vec![
+        vec![],
+        vec![0xff; 3],
+        vec![0xff; 16],
+        vec![0x20, 0, 0, 0, 0x20, 0, 0, 2, 0xaf, 1],
+        vec![0x20, 0, 0, 0, 0x10, 0, 0, 2, 0xff, 0xfe],
+        vec![0x40, 0, 0, 1, 0x20, 0, 0, 1, 0x10, 0, 0, 1, 0, b'a'],
+    ];
+    for i in 0..valid.len() {
+        inputs.push(valid[..i].to_vec());
+        for b in [0x00, 0x7f, 0xff] {
+            let mut data = valid.clone();
+            data[i] ^= b;
+            inputs.push(data);
+        }
+    }
+
+    let path = "$.a[*]";
+    for data in inputs {
+        let mut out = JsonbBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+            cap: 0,
+        };
+        let res = unsafe { jsonb_to_string(data.as_ptr(), data.len(), &mut out) };
+        assert!(res == JSONB_OK || res == JSONB_ERROR_INVALID_JSONB);
+        unsafe { jsonb_buffer_free(out) };
+
+        let mut out = JsonbBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+            cap: 0,
+        };
+        let res = unsafe {
+            jsonb_get_by_path(
+                data.as_ptr(),
+                data.len(),
+                path.as_ptr(),
+                path.len(),
+                &mut out,
+            )
+        };
+        assert!(res == JSONB_OK || res == JSONB_ERROR_INVALID_JSONB);
+        unsafe { jsonb_buffer_free(out) };
+
+        let mut order = 0;
+        let res = unsafe {
+            jsonb_compare(
+                data.as_ptr(),
+                data.len(),
+                valid.as_ptr(),
+                valid.len(),
+                &mut order,
+            )
+        };
+        assert!(res == JSONB_OK || res == JSONB_ERROR_INVALID_JSONB);
+    }
+
+    let garbage = [0x20, 0, 0, 0, 0x20, 0, 0, 2, 0xaf, 1];
+    let mut out = JsonbBuffer {
+        data: ptr::null_mut(),
+        len: 0,
+        cap: 0,
+    };
+    let res = unsafe { jsonb_to_string(garbage.as_ptr(), garbage.len(), &mut out) };
+    assert_eq!(res, JSONB_ERROR_INVALID_JSONB);
+    assert!(out.data.is_null());
+}
@@ -0,0 +1,83 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonb::jsonpath::parse_json_path;
+use jsonb::{parse_value, string_equals, string_equals_by_path, Fold, RawJsonb};
+
+#[test]
+fn test_string_equals() {
+    let cases = vec![
+        (r#""Hello""#, "hELLO", Fold::Case, true),
+        (r#""Straße""#, "STRASSE", Fold::Case, true),
+        (r#""ΣΊΣΥΦΟΣ""#, "σίσυφος", Fold::Case, true),
+        (r#""café""#, "CAFÉ", Fold::Case, true),
+        // The composed and decomposed forms are equal.
+        (r#""cafe\u0301""#, "CAFÉ", Fold::Case, true),
+        (r#""café""#, "cafe", Fold::Case, false),
+        (r#""café""#, "CAFE", Fold::CaseAndAccent, true),
+        (
+            r#""Crème Brûlée""#,
+            "creme brulee",
+            Fold::CaseAndAccent,
+            true,
+        ),
+        (r#""creme""#, "crème", Fold::CaseAndAccent, true),
+        (r#""creme""#, "cream", Fold::CaseAndAccent, false),
+        (r#"[1,{"a":["x","Ünï"]}]"#, "uni", Fold::CaseAndAccent, true),
+        (r#"{"Key":1}"#, "key", Fold::Case, false),
+        (r#"1"#, "1", Fold::Case, false),
+        (r#"null"#, "null", Fold::Case, false),
+    ];
+    for (source, needle, fold, expected) in cases {
+        assert_eq!(
+            string_equals(source, needle, fold),
+            expected,
+            "{} {}",
+            source,
+            needle
+        );
+        let value = parse_value(source.as_bytes()).unwrap().to_vec();
+        assert_eq!(
+            string_equals(&RawJsonb::new(&value), needle, fold),
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_string_equals_by_path() {
+    let value = parse_value(r#"{"name":"José","city":"SÃO PAULO","tags":["Ação"]}"#.as_bytes())
+        .unwrap()
+        .to_vec();
+    let cases = vec![
+        ("$.name", "JOSE", Fold::CaseAndAccent, true),
+        ("$.name", "JOSE", Fold::Case, false),
+        ("$.name", "josé", Fold::Case, true),
+        ("$.city", "são paulo", Fold::Case, true),
+        ("$.*", "sao paulo", Fold::CaseAndAccent, true),
+        ("$.tags", "acao", Fold::CaseAndAccent, false),
+        ("$.tags[*]", "acao", Fold::CaseAndAccent, true),
+        ("$.missing", "", Fold::Case, false),
+    ];
+    for (path, needle, fold, expected) in cases {
+        let json_path = parse_json_path(path.as_bytes()).unwrap();
+        assert_eq!(
+            string_equals_by_path(&RawJsonb::new(&value), json_path, needle, fold),
+            expected,
+            "{} {}",
+            path,
+            needle
+        );
+    }
+}
@@ -13,16 +13,61 @@
 // limitations under the License.
 
 use std::borrow::Cow;
+use std::cell::Cell;
 use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::sync::Arc;
 
 use jsonb::{
-    array_length, array_values, as_bool, as_null, as_number, as_str, build_array, build_object,
-    compare, convert_to_comparable, from_slice, get_by_index, get_by_name, get_by_path, is_array,
-    is_object, object_keys, parse_value, to_bool, to_f64, to_i64, to_str, to_string, to_u64,
-    Number, Object, Value,
+    analyze, analyze_all, array_all, array_any, array_elements_text, array_first, array_head,
+    array_last, array_length, array_merge_by_key, array_reverse, array_rotate, array_sample,
+    array_tail, array_update_where, array_values, array_values_iter, as_bool, as_i64_exact,
+    as_null, as_number, as_str, as_u64_exact, build_array, build_object, coalesce, compare,
+    compare_bounded, compare_with_options, convert_to_comparable,
+    convert_to_comparable_with_options, debug_stable, diff, diff_report, diff_with_cancellation,
+    dump_layout, escape_str_into, estimate, exists_in_any, explode, extract_f64_array,
+    extract_i64_array, filter_matching, filter_paths, from_slice, get_by_index, get_by_name,
+    get_by_name_prefix, get_by_path, get_by_path_first, get_by_path_with_options,
+    get_by_path_with_vars, get_by_paths, get_by_paths_with_options, get_or_default, group_key,
+    index_entries, is_array, is_null, is_object, join_key, json_table, matches, merge_runs,
+    normalize_keys, object_each_text, object_invert, object_keys, object_keys_iter, object_values,
+    object_values_iter, parse_value, parse_value_with_options, presence_bitmap, pretty, prune,
+    rand_value, shred, similarity, sort_indices, sort_indices_with_options, str_slice, to_bool,
+    to_f64, to_f64_lenient, to_i64, to_i64_lenient, to_str, to_string, to_string_bounded,
+    to_string_into, to_string_with_options, to_text_compatible, to_u64, to_u64_lenient,
+    transcode_from, transcode_into, try_get_by_path_with_options, try_get_by_path_with_tracker,
+    try_to_bool, try_to_f64, try_to_i64, try_to_str, try_to_u64, unescape_into, Aggregator,
+    AsJsonb, BitVec, CancellationToken, Case, CastErrorCode, ChangeKind, Coercion,
+    ComparableKeyWriter, CompareOperator, CompareOptions, DeltaOp, Dialect, Error, FormatOptions,
+    IndexMode, InternPool, JoinKey, JsonTableBehavior, JsonTableColumn, JsonTableColumnType,
+    JsonTableValue, JsonbDelta, JsonbHasher, KeyCollision, KeyProfiler, KeyStats, LazyValue,
+    MemoryLimit, MergeStrategy, NullOrder, Number, Object, OnError, ParseOptions, PathTrie,
+    Predicate, PruneOptions, PruneReport, RawJsonb, RunIter, Serialized, TargetType, Truncated,
+    TypedCell, Value,
 };
 
-use jsonb::jsonpath::parse_json_path;
+use jsonb::jsonpath::{
+    parse_json_path, PathCache, PathValue, ResultOrder, Selector, SelectorOptions,
+};
+
+fn to_strings(values: Vec<Vec<u8>>) -> Vec<String> {
+    values.iter().map(to_string).collect()
+}
+
+fn select(value: &[u8], path: &str) -> Vec<String> {
+    let json_path = parse_json_path(path.as_bytes()).unwrap();
+    to_strings(get_by_path(value, json_path))
+}
+
+fn select_with_options(
+    value: &[u8],
+    path: &str,
+    options: SelectorOptions,
+) -> Result<Vec<String>, Error> {
+    let json_path = parse_json_path(path.as_bytes()).unwrap();
+    try_get_by_path_with_options(value, json_path, options).map(to_strings)
+}
 
 #[test]
 fn test_build_array() {
@@ -64,6 +109,7 @@ fn test_build_array() {
 }
 
 #[test]
+#[allow(clippy::useless_vec)]
 fn test_build_object() {
     let sources = vec![
         r#"true"#,
@@ -391,6 +437,261 @@ fn test_array_values() {
     }
 }
 
+#[test]
+fn test_object_values() {
+    let sources = vec![
+        (r#"[1,2,3]"#, None),
+        (r#"{}"#, Some(r#"[]"#)),
+        (r#"{"a":"v1","b":[1,2,3]}"#, Some(r#"["v1",[1,2,3]]"#)),
+        (
+            r#"{"k1":null,"k2":{"a":1},"k3":2.5}"#,
+            Some(r#"[null,{"a":1},2.5]"#),
+        ),
+    ];
+    for (s, expect) in sources {
+        let value = parse_value(s.as_bytes()).unwrap().to_vec();
+        let res = object_values(&value);
+        assert_eq!(
+            res.as_ref()
+                .map(|v| to_string(&RawJsonb::new(v)))
+                .as_deref(),
+            expect
+        );
+        assert_eq!(object_values(s), res);
+
+        let values = object_values_iter(&value).map(|iter| {
+            iter.map(|v| to_string(&RawJsonb::new(&v)))
+                .collect::<Vec<_>>()
+                .join(",")
+        });
+        assert_eq!(values.map(|v| format!("[{v}]")).as_deref(), expect);
+    }
+}
+
+#[test]
+fn test_array_head_tail_sample() {
+    let value = parse_value(br#"[1,"a",[2,3],{"k":null},true]"#)
+        .unwrap()
+        .to_vec();
+    let cases = vec![
+        (0, "[]", "[]"),
+        (2, r#"[1,"a"]"#, r#"[{"k":null},true]"#),
+        (3, r#"[1,"a",[2,3]]"#, r#"[[2,3],{"k":null},true]"#),
+        (
+            10,
+            r#"[1,"a",[2,3],{"k":null},true]"#,
+            r#"[1,"a",[2,3],{"k":null},true]"#,
+        ),
+    ];
+    for (n, head, tail) in cases {
+        let mut buf = Vec::new();
+        array_head(&value, n, &mut buf).unwrap();
+        assert_eq!(to_string(&RawJsonb::new(&buf)), head);
+        buf.clear();
+        array_tail(&value, n, &mut buf).unwrap();
+        assert_eq!(to_string(&RawJsonb::new(&buf)), tail);
+    }
+
+    let mut buf = Vec::new();
+    assert_eq!(
+        array_head(r#"{"a":1}"#, 1, &mut buf),
+        Err(Error::InvalidJsonType)
+    );
+    assert_eq!(array_tail("1", 1, &mut buf), Err(Error::InvalidJsonType));
+    assert!(array_sample("[1,", 1, 0, &mut buf).is_err());
+
+    let value = parse_value(
+        (0..1000)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+            .as_bytes(),
+    )
+    .err()
+    .map(|_| Value::Array((0..1000u64).map(Value::from).collect()))
+    .unwrap()
+    .to_vec();
+    let sample = |n, seed| {
+        let mut buf = Vec::new();
+        array_sample(&value, n, seed, &mut buf).unwrap();
+        array_values(&buf)
+            .unwrap()
+            .iter()
+            .map(|v| to_u64(&RawJsonb::new(v)).unwrap())
+            .collect::<Vec<_>>()
+    };
+    let values = sample(10, 1);
+    assert_eq!(values.len(), 10);
+    assert!(values.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!(values, sample(10, 1));
+    assert_eq!(values, vec![13, 91, 166, 227, 393, 632, 765, 783, 941, 978]);
+    assert_ne!(values, sample(10, 2));
+    assert_eq!(sample(2000, 1), (0..1000).collect::<Vec<_>>());
+    assert!(sample(0, 1).is_empty());
+}
+
+#[test]
+fn test_array_reverse_rotate() {
+    let value = parse_value(br#"[1,"a",[2,3],{"k":null}]"#)
+        .unwrap()
+        .to_vec();
+    let mut buf = Vec::new();
+    array_reverse(&value, &mut buf).unwrap();
+    assert_eq!(
+        to_string(&RawJsonb::new(&buf)),
+        r#"[{"k":null},[2,3],"a",1]"#
+    );
+
+    let cases = vec![
+        (0, r#"[1,"a",[2,3],{"k":null}]"#),
+        (1, r#"["a",[2,3],{"k":null},1]"#),
+        (-1, r#"[{"k":null},1,"a",[2,3]]"#),
+        (6, r#"[[2,3],{"k":null},1,"a"]"#),
+        (-4, r#"[1,"a",[2,3],{"k":null}]"#),
+    ];
+    for (n, expected) in cases {
+        buf.clear();
+        array_rotate(&value, n, &mut buf).unwrap();
+        assert_eq!(to_string(&RawJsonb::new(&buf)), expected);
+    }
+
+    for f in [array_reverse::<str>, |v: &str, buf: &mut Vec<u8>| {
+        array_rotate(v, 3, buf)
+    }] {
+        buf.clear();
+        f("[]", &mut buf).unwrap();
+        assert_eq!(to_string(&RawJsonb::new(&buf)), "[]");
+        assert_eq!(f(r#"{"a":1}"#, &mut buf), Err(Error::InvalidJsonType));
+    }
+}
+
+#[test]
+fn test_explode() {
+    let value = parse_value(br#"{"a":{"items":[1,"x",[2],{"k":3},null]},"b":"s"}"#)
+        .unwrap()
+        .to_vec();
+    let explode_strings = |value: &[u8], path: &'static str, batch_size| {
+        let json_path = parse_json_path(path.as_bytes()).unwrap();
+        explode(value, &json_path, batch_size)
+            .map(|batch| {
+                batch
+                    .iter()
+                    .map(|v| to_string(&RawJsonb::new(v)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        explode_strings(&value, "$.a.items", 2),
+        vec![
+            vec!["1".to_string(), r#""x""#.to_string()],
+            vec!["[2]".to_string(), r#"{"k":3}"#.to_string()],
+            vec!["null".to_string()],
+        ]
+    );
+    assert_eq!(
+        explode_strings(&value, "$.a.items", 10)
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>(),
+        vec!["1", r#""x""#, "[2]", r#"{"k":3}"#, "null"]
+    );
+    assert_eq!(explode_strings(&value, "$.b", 2), vec![vec![r#""s""#]]);
+    assert_eq!(
+        explode_strings(&value, "$.a", 2),
+        vec![vec![r#"{"items":[1,"x",[2],{"k":3},null]}"#]]
+    );
+    assert!(explode_strings(&value, "$.c", 2).is_empty());
+    assert!(explode_strings(br#"[1,"#, "$", 2).is_empty());
+
+    // `JSON` text is parsed before exploding.
+    let json_path = parse_json_path(b"$").unwrap();
+    let batches: Vec<_> = explode("[1,2,3]", &json_path, 2).collect();
+    assert_eq!(batches.len(), 2);
+}
+
+#[test]
+fn test_extract_numeric_array() {
+    let value = parse_value(br#"{"a":[1,2.5,-3],"b":[1,"2",true,3.0],"c":[1,null],"d":1}"#)
+        .unwrap()
+        .to_vec();
+    let path = |s: &'static str| parse_json_path(s.as_bytes()).unwrap();
+
+    assert_eq!(
+        extract_f64_array(&value, &path("$.a"), Coercion::Strict),
+        Some(vec![1.0, 2.5, -3.0])
+    );
+    assert_eq!(
+        extract_i64_array(&value, &path("$.a"), Coercion::Strict),
+        None
+    );
+    assert_eq!(
+        extract_f64_array(&value, &path("$.b"), Coercion::Strict),
+        None
+    );
+    assert_eq!(
+        extract_f64_array(&value, &path("$.b"), Coercion::Lenient),
+        Some(vec![1.0, 2.0, 1.0, 3.0])
+    );
+    assert_eq!(
+        extract_i64_array(&value, &path("$.b"), Coercion::Lenient),
+        Some(vec![1, 2, 1, 3])
+    );
+    assert_eq!(
+        extract_f64_array(&value, &path("$.c"), Coercion::Lenient),
+        None
+    );
+    assert_eq!(
+        extract_f64_array(&value, &path("$.d"), Coercion::Strict),
+        None
+    );
+    assert_eq!(
+        extract_i64_array(r#"[[4,-5,6.0]]"#, &path("$[0]"), Coercion::Strict),
+        Some(vec![4, -5, 6])
+    );
+    assert_eq!(
+        extract_f64_array("[]", &path("$"), Coercion::Strict),
+        Some(vec![])
+    );
+}
+
+#[test]
+fn test_container_iter() {
+    let value = parse_value(r#"{"a":1,"b":[true],"c":"x"}"#.as_bytes())
+        .unwrap()
+        .to_vec();
+    let mut keys = object_keys_iter(&value).unwrap();
+    assert_eq!(keys.len(), 3);
+    assert_eq!(keys.next(), Some(Value::from("a").to_vec()));
+    assert_eq!(keys.len(), 2);
+    assert_eq!(
+        keys.collect::<Vec<_>>(),
+        vec![Value::from("b").to_vec(), Value::from("c").to_vec()]
+    );
+    assert!(array_values_iter(&value).is_none());
+
+    let value = r#"[1,{"a":null},"x"]"#;
+    let elements: Vec<_> = array_values_iter(value).unwrap().collect();
+    assert_eq!(elements, array_values(value).unwrap());
+    assert!(object_keys_iter(value).is_none());
+    assert!(object_values_iter(value).is_none());
+    assert!(array_values_iter("[1,").is_none());
+
+    assert_eq!(
+        array_first(value),
+        Some(parse_value(b"1").unwrap().to_vec())
+    );
+    assert_eq!(array_last(value), Some(Value::from("x").to_vec()));
+    let value = parse_value(br#"[[1,2]]"#).unwrap().to_vec();
+    let expect = parse_value(br#"[1,2]"#).unwrap().to_vec();
+    assert_eq!(array_first(&value), Some(expect.clone()));
+    assert_eq!(array_last(&value), Some(expect));
+    assert_eq!(array_first("[]"), None);
+    assert_eq!(array_last("[]"), None);
+    assert_eq!(array_first(r#"{"a":1}"#), None);
+    assert_eq!(array_last("1"), None);
+}
+
 #[test]
 fn test_compare() {
     let sources = vec![
@@ -500,6 +801,237 @@ fn test_compare() {
     }
 }
 
+#[test]
+fn test_compare_temporal_strings() {
+    let sources = vec![
+        (
+            r#""2023-05-06T10:00:00+08:00""#,
+            r#""2023-05-06T03:00:00Z""#,
+            Ordering::Less,
+            Ordering::Greater,
+        ),
+        (
+            r#""2023-05-06 02:00:00Z""#,
+            r#""2023-05-06T02:00:00.000+00:00""#,
+            Ordering::Equal,
+            Ordering::Less,
+        ),
+        (
+            r#""2023-05-06""#,
+            r#""2023-05-05T23:59:59.999Z""#,
+            Ordering::Greater,
+            Ordering::Greater,
+        ),
+        // the minutes of the offset must follow the `:`
+        (
+            r#""2023-05-06T10:00:00+08:""#,
+            r#""2023-05-06T10:00:00+08""#,
+            Ordering::Greater,
+            Ordering::Greater,
+        ),
+        (
+            r#""2023-05-06T10:00:00+0800""#,
+            r#""2023-05-06T02:00:00Z""#,
+            Ordering::Equal,
+            Ordering::Greater,
+        ),
+        (
+            r#""1969-12-31T23:59:59-01:00""#,
+            r#""1970-01-01T00:00:00Z""#,
+            Ordering::Greater,
+            Ordering::Less,
+        ),
+        (
+            r#""2023-02-29""#,
+            r#""2023-03-01""#,
+            Ordering::Greater,
+            Ordering::Less,
+        ),
+        (
+            r#""abc""#,
+            r#""2023-05-06""#,
+            Ordering::Greater,
+            Ordering::Greater,
+        ),
+        (
+            r#"{"ts":"2023-05-06T10:00:00+08:00"}"#,
+            r#"{"ts":"2023-05-06T03:00:00Z"}"#,
+            Ordering::Less,
+            Ordering::Greater,
+        ),
+    ];
+
+    let options = CompareOptions {
+        temporal_strings: true,
+        ..Default::default()
+    };
+    for (l, r, expect, expect_lexical) in sources {
+        let lbuf = parse_value(l.as_bytes()).unwrap().to_vec();
+        let rbuf = parse_value(r.as_bytes()).unwrap().to_vec();
+
+        let res = compare_with_options(&lbuf, &rbuf, &options).unwrap();
+        assert_eq!(res, expect);
+        let res = compare_with_options(l.as_bytes(), r.as_bytes(), &options).unwrap();
+        assert_eq!(res, expect);
+
+        let res = compare(&lbuf, &rbuf).unwrap();
+        assert_eq!(res, expect_lexical);
+
+        let mut lcmp = Vec::new();
+        let mut rcmp = Vec::new();
+        convert_to_comparable_with_options(&lbuf, &mut lcmp, &options);
+        convert_to_comparable_with_options(&rbuf, &mut rcmp, &options);
+        assert_eq!(lcmp.cmp(&rcmp), expect, "{l} {r}");
+    }
+}
+
+#[test]
+fn test_compare_temporal_strings_transitive() {
+    let options = CompareOptions {
+        temporal_strings: true,
+        ..Default::default()
+    };
+    // The timestamps are ordered by time before the other strings, which are ordered lexically.
+    let values = [
+        r#""2023-05-06T10:00:00+08:00""#,
+        r#""2023-05-06T03:00:00Z""#,
+        r#""2023-05-06T05""#,
+    ]
+    .map(|v| parse_value(v.as_bytes()).unwrap().to_vec());
+    for (i, j) in [(0, 1), (1, 2), (0, 2)] {
+        let res = compare_with_options(&values[i], &values[j], &options).unwrap();
+        assert_eq!(res, Ordering::Less, "{i} {j}");
+        let res = compare_with_options(&values[j], &values[i], &options).unwrap();
+        assert_eq!(res, Ordering::Greater, "{j} {i}");
+    }
+    let comparables: Vec<_> = values
+        .iter()
+        .map(|v| {
+            let mut buf = Vec::new();
+            convert_to_comparable_with_options(v, &mut buf, &options);
+            buf
+        })
+        .collect();
+    assert!(comparables[0] < comparables[1] && comparables[1] < comparables[2]);
+}
+
+#[test]
+fn test_compare_null_order() {
+    let sources = vec![
+        (r#"null"#, r#"false"#, Ordering::Less),
+        (r#"null"#, r#"1"#, Ordering::Less),
+        (r#"null"#, r#""a""#, Ordering::Less),
+        (r#"null"#, r#"[1]"#, Ordering::Less),
+        (r#"{"k":1}"#, r#"null"#, Ordering::Greater),
+        (r#"null"#, r#"null"#, Ordering::Equal),
+        (r#"[null,1]"#, r#"[false,1]"#, Ordering::Less),
+        (r#"{"k":null}"#, r#"{"k":[]}"#, Ordering::Less),
+        (r#"[1,2]"#, r#"{"k":1}"#, Ordering::Greater),
+        (r#""a""#, r#"2"#, Ordering::Greater),
+    ];
+
+    let options = CompareOptions {
+        nulls: NullOrder::NullsFirst,
+        ..Default::default()
+    };
+    for (l, r, expect) in sources {
+        let lbuf = parse_value(l.as_bytes()).unwrap().to_vec();
+        let rbuf = parse_value(r.as_bytes()).unwrap().to_vec();
+
+        let res = compare_with_options(&lbuf, &rbuf, &options).unwrap();
+        assert_eq!(res, expect);
+        let res = compare_with_options(l.as_bytes(), r.as_bytes(), &options).unwrap();
+        assert_eq!(res, expect);
+        if expect != Ordering::Equal && (l.contains("null") || r.contains("null")) {
+            assert_eq!(compare(&lbuf, &rbuf).unwrap(), expect.reverse());
+        }
+
+        let mut lkey = Vec::new();
+        let mut rkey = Vec::new();
+        convert_to_comparable_with_options(&lbuf, &mut lkey, &options);
+        convert_to_comparable_with_options(&rbuf, &mut rkey, &options);
+        assert_eq!(lkey.cmp(&rkey), expect);
+    }
+
+    // The invalid values are still the least.
+    let res = compare_with_options("nul".as_bytes(), "null".as_bytes(), &options).unwrap();
+    assert_eq!(res, Ordering::Less);
+}
+
+#[test]
+fn test_compare_bounded() {
+    let sources = vec![
+        (r#"[1,2,3]"#, r#"[1,2,4]"#, Ordering::Less),
+        (
+            r#"{"a":[true],"b":1}"#,
+            r#"{"a":[true],"b":1}"#,
+            Ordering::Equal,
+        ),
+        (r#"{"a":1}"#, r#"[1]"#, Ordering::Less),
+        (r#""b""#, r#""a""#, Ordering::Greater),
+        (r#"null"#, r#"[1]"#, Ordering::Greater),
+    ];
+    for (l, r, expect) in sources {
+        assert_eq!(compare_bounded(l, r, usize::MAX), Some(expect));
+        let lbuf = parse_value(l.as_bytes()).unwrap().to_vec();
+        let rbuf = parse_value(r.as_bytes()).unwrap().to_vec();
+        assert_eq!(
+            compare_bounded(&lbuf, &rbuf, lbuf.len() + rbuf.len()),
+            Some(expect)
+        );
+    }
+
+    // the headers, the `JEntries` and the data of the elements are 44 bytes.
+    assert_eq!(
+        compare_bounded("[1,2,3]", "[1,2,4]", 44),
+        Some(Ordering::Less)
+    );
+    assert_eq!(compare_bounded("[1,2,3]", "[1,2,4]", 43), None);
+    assert_eq!(compare_bounded(r#""a""#, r#""b""#, 17), None);
+
+    // the values that differ early are ordered regardless of the size.
+    let long = format!("[{}]", vec!["1"; 10000].join(","));
+    let longer = format!("[2,{}]", vec!["1"; 10000].join(","));
+    assert_eq!(compare_bounded(&long, &longer, 64), Some(Ordering::Less));
+    assert_eq!(compare_bounded(&long, &long, 1024), None);
+    assert_eq!(compare_bounded("[1,", "[1]", 1024), None);
+
+    // the malformed values found by mutating `[1,true,"x",[]]` are invalid rather than a panic.
+    let value = parse_value(br#"[1,true,"x",[]]"#).unwrap().to_vec();
+    let mutated: [&[u8]; 5] = [
+        &[128, 0, 0, 1, 32, 0, 0, 2, 64],
+        &[128, 0, 0, 4, 32, 0, 0, 2, 64, 0, 0, 0],
+        &[
+            128, 0, 0, 4, 32, 0, 0, 1, 64, 0, 0, 0, 16, 0, 0, 1, 80, 0, 0, 4, 80, 1, 120, 128, 0,
+            0, 0,
+        ],
+        &[
+            128, 0, 0, 4, 32, 0, 0, 2, 14, 0, 0, 0, 16, 0, 0, 1, 80, 0, 0, 4, 100, 1, 120, 128, 0,
+            0, 0,
+        ],
+        &[
+            128, 0, 0, 4, 32, 0, 0, 1, 64, 0, 0, 0, 16, 0, 0, 1, 80, 0, 0, 50, 80, 1, 120, 128, 0,
+            0, 0,
+        ],
+    ];
+    for data in mutated {
+        assert_eq!(
+            compare_bounded(data, &value[..], usize::MAX),
+            None,
+            "{data:?}"
+        );
+        assert_eq!(
+            compare_bounded(&value[..], data, usize::MAX),
+            None,
+            "{data:?}"
+        );
+    }
+    let object = parse_value(br#"{"ab":1}"#).unwrap().to_vec();
+    let mut invalid_key = object.clone();
+    invalid_key[12] = 0xff;
+    assert_eq!(compare_bounded(&object[..], &invalid_key[..], 1024), None);
+}
+
 #[test]
 fn test_as_type() {
     let sources = vec![
@@ -740,6 +1272,10 @@ fn test_to_string() {
         (r#""ab\n\"\uD83D\uDC8E测试""#, r#""ab\n\"💎测试""#),
         (r#""မြန်မာဘာသာ""#, r#""မြန်မာဘာသာ""#),
         (r#""⚠️✅❌""#, r#""⚠️✅❌""#),
+        (r#""a\\b\/c\b\f\r\td""#, r#""a\\b\/c\b\f\r\td""#),
+        (r#"18446744073709551615"#, r#"18446744073709551615"#),
+        (r#"-9223372036854775808"#, r#"-9223372036854775808"#),
+        (r#"1e20"#, r#"100000000000000000000"#),
         (r#"[1,2,3,4]"#, r#"[1,2,3,4]"#),
         (
             r#"["a","b",true,false,[1,2,3],{"a":"b"}]"#,
@@ -758,4 +1294,3141 @@ fn test_to_string() {
         assert_eq!(res, expect);
         buf.clear();
     }
+
+    // the text is appended to the buffer.
+    let mut json = String::from("[");
+    to_string_into(&parse_value(br#"{"a":1}"#).unwrap().to_vec(), &mut json);
+    json.push(',');
+    to_string_into(r#"{"b":"\n"}"#, &mut json);
+    json.push(',');
+    to_string_into("{", &mut json);
+    assert_eq!(json, r#"[{"a":1},{"b":"\n"},{"#);
+}
+
+#[test]
+fn test_to_text_compatible() {
+    let sources = vec![
+        r#"null"#,
+        r#"[true,false,-1,18446744073709551615]"#,
+        r#"[1.0,0.1,-2.5,1e20,1.5e-7,123456789.125]"#,
+        r#""a/b\"c\u0000\u001f\n\t é""#,
+        // `serde_json` keeps the order of the keys in the text, and `JSONB` sorts them.
+        r#"{"a":[1,{"k":"v"}],"aa":null,"b":{"x":{},"y":[]}}"#,
+    ];
+    for source in sources {
+        let expected =
+            serde_json::to_string(&serde_json::from_str::<serde_json::Value>(source).unwrap())
+                .unwrap();
+        assert_eq!(
+            to_text_compatible(source, Dialect::SerdeJson).unwrap(),
+            expected
+        );
+        let buf = parse_value(source.as_bytes()).unwrap().to_vec();
+        assert_eq!(
+            to_text_compatible(&buf, Dialect::SerdeJson).unwrap(),
+            expected
+        );
+    }
+
+    let cases = vec![
+        (r#"null"#, r#"null"#),
+        (r#"[1,[],{}]"#, r#"[1, [], {}]"#),
+        (
+            r#"[1.0,-2.5,1e20,1.5e-7]"#,
+            r#"[1, -2.5, 100000000000000000000, 0.00000015]"#,
+        ),
+        (r#""a/b\"c\u0001\n""#, r#""a/b\"c\u0001\n""#),
+        (
+            r#"{"bb":1,"a":{"yy":true,"x":false},"ab":null,"c":"d"}"#,
+            r#"{"a": {"x": false, "yy": true}, "c": "d", "ab": null, "bb": 1}"#,
+        ),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(
+            to_text_compatible(source, Dialect::Postgres).unwrap(),
+            expected
+        );
+    }
+    assert!(to_text_compatible("[1,", Dialect::Postgres).is_err());
+}
+
+#[test]
+fn test_json_input() {
+    let text = r#"{"k1":"v1","k2":[1,2,3]}"#;
+    let value = parse_value(text.as_bytes()).unwrap();
+    let buf = value.to_vec();
+    let raw = RawJsonb::new(&buf);
+
+    assert_eq!(to_string(text), text);
+    assert_eq!(to_string(&text.to_string()), text);
+    assert_eq!(to_string(text.as_bytes()), text);
+    assert_eq!(to_string(&value), text);
+    assert_eq!(to_string(&buf), text);
+    assert_eq!(to_string(&raw), text);
+
+    assert!(is_object(text));
+    assert!(is_object(&value));
+    assert!(is_object(&raw));
+    assert_eq!(
+        get_by_name(text, "k1", false),
+        get_by_name(&raw, "k1", false)
+    );
+    assert_eq!(
+        array_length(&get_by_name(&value, "k2", false).unwrap()),
+        Some(3)
+    );
+    assert_eq!(compare(text, &raw).unwrap(), Ordering::Equal);
+    assert_eq!(compare(&value, r#"{"k1":"v2"}"#).unwrap(), Ordering::Less);
+    assert_eq!(as_str(r#""abc""#), Some(Cow::Owned("abc".to_string())));
+    assert_eq!(
+        as_str(&RawJsonb::new(&parse_value(br#""abc""#).unwrap().to_vec())),
+        Some(Cow::Borrowed("abc"))
+    );
+
+    // invalid `JSON` text
+    let invalid = "{abc";
+    assert_eq!(to_string(invalid), invalid);
+    assert!(!is_object(invalid));
+    assert_eq!(compare(invalid, text).unwrap(), Ordering::Less);
+    assert_eq!(compare(text, invalid).unwrap(), Ordering::Greater);
+    assert_eq!(compare(invalid, "{abd").unwrap(), Ordering::Less);
+}
+
+#[test]
+fn test_build_with_as_jsonb() {
+    #[derive(serde::Serialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+        tags: Vec<&'static str>,
+    }
+
+    let nested = parse_value(br#"[1,2]"#).unwrap();
+    let nested_buf = nested.to_vec();
+    let point = Point {
+        x: 1,
+        y: -2,
+        tags: vec!["a"],
+    };
+
+    let mut buf = Vec::new();
+    build_array(
+        [&true as &dyn AsJsonb, &1u8, &-1i64, &1.5f64, &"abc"],
+        &mut buf,
+    )
+    .unwrap();
+    assert_eq!(to_string(&buf), r#"[true,1,-1,1.5,"abc"]"#);
+
+    let mut buf = Vec::new();
+    let none: Option<i32> = None;
+    build_object(
+        [
+            ("a", &Serialized(&point) as &dyn AsJsonb),
+            ("b", &nested_buf),
+            ("c", &nested),
+            ("d", &none),
+            ("e", &Some("x".to_string())),
+            ("f", &RawJsonb::new(&nested_buf)),
+        ],
+        &mut buf,
+    )
+    .unwrap();
+    assert_eq!(
+        to_string(&buf),
+        r#"{"a":{"tags":["a"],"x":1,"y":-2},"b":[1,2],"c":[1,2],"d":null,"e":"x","f":[1,2]}"#
+    );
+
+    let mut buf = Vec::new();
+    build_array([Serialized(vec![1, 2]), Serialized(vec![3])], &mut buf).unwrap();
+    assert_eq!(to_string(&buf), "[[1,2],[3]]");
+}
+
+#[test]
+fn test_json_table() {
+    let value = parse_value(
+        r#"{"items":[{"id":1,"name":"a","price":"1.5","tags":["x"]},{"id":"b","price":null},{"id":3,"name":{"n":1},"price":2}]}"#
+            .as_bytes(),
+    )
+    .unwrap()
+    .to_vec();
+    let path = |s: &'static str| parse_json_path(s.as_bytes()).unwrap();
+
+    let mut id = JsonTableColumn::new("id", path("$.id"), JsonTableColumnType::Int64);
+    id.on_error = JsonTableBehavior::Default(JsonTableValue::Int64(-1));
+    let mut name = JsonTableColumn::new("name", path("$.name"), JsonTableColumnType::String);
+    name.on_empty = JsonTableBehavior::Default(JsonTableValue::String("unknown".to_string()));
+    let columns = vec![
+        JsonTableColumn::ordinality("seq"),
+        id,
+        name,
+        JsonTableColumn::new("price", path("$.price"), JsonTableColumnType::Float64),
+        JsonTableColumn::new("tags", path("$.tags"), JsonTableColumnType::Jsonb),
+    ];
+    let rows: Vec<_> = json_table(&value, path("$.items[*]"), &columns)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let tags = parse_value(r#"["x"]"#.as_bytes()).unwrap().to_vec();
+    assert_eq!(
+        rows,
+        vec![
+            vec![
+                JsonTableValue::UInt64(1),
+                JsonTableValue::Int64(1),
+                JsonTableValue::String("a".to_string()),
+                JsonTableValue::Float64(1.5),
+                JsonTableValue::Jsonb(tags),
+            ],
+            vec![
+                JsonTableValue::UInt64(2),
+                JsonTableValue::Int64(-1),
+                JsonTableValue::String("unknown".to_string()),
+                JsonTableValue::Null,
+                JsonTableValue::Null,
+            ],
+            vec![
+                JsonTableValue::UInt64(3),
+                JsonTableValue::Int64(3),
+                JsonTableValue::Null,
+                JsonTableValue::Float64(2.0),
+                JsonTableValue::Null,
+            ],
+        ]
+    );
+
+    let mut name = JsonTableColumn::new("name", path("$.name"), JsonTableColumnType::String);
+    name.on_error = JsonTableBehavior::Error;
+    let columns = vec![name.clone()];
+    let rows: Vec<_> = json_table(&value, path("$.items[*]"), &columns).collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[1], Ok(vec![JsonTableValue::Null]));
+    assert_eq!(rows[2], Err(Error::InvalidCast));
+
+    name.on_empty = JsonTableBehavior::Error;
+    let columns = vec![name];
+    let rows: Vec<_> = json_table(&value, path("$.items[*]"), &columns).collect();
+    assert_eq!(rows[1], Err(Error::InvalidJsonPath));
+
+    let columns = vec![JsonTableColumn::new(
+        "tag",
+        path("$.tags[*]"),
+        JsonTableColumnType::String,
+    )];
+    let rows: Vec<_> = json_table(&value, path("$.missing[*]"), &columns).collect();
+    assert!(rows.is_empty());
+
+    // The column paths are in lax mode, the structural errors in strict mode are ignored.
+    let value = parse_value(r#"[{"tags":"y"},{"tags":["z"]}]"#.as_bytes())
+        .unwrap()
+        .to_vec();
+    let columns = vec![
+        JsonTableColumn::new("tag", path("$.tags[*]"), JsonTableColumnType::String),
+        JsonTableColumn::new("n", path("$.tags.n"), JsonTableColumnType::String),
+    ];
+    let rows: Vec<_> = json_table(&value, path("$[*]"), &columns)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            vec![
+                JsonTableValue::String("y".to_string()),
+                JsonTableValue::Null
+            ],
+            vec![
+                JsonTableValue::String("z".to_string()),
+                JsonTableValue::Null
+            ],
+        ]
+    );
+    let strict = SelectorOptions {
+        strict: true,
+        ..Default::default()
+    };
+    assert!(select_with_options(&value, "$[0].tags[*]", strict).is_err());
+    assert!(select_with_options(&value, "$[1].tags.n", strict).is_err());
+}
+
+#[test]
+fn test_matches() {
+    let value = parse_value(r#"{"a":1,"b":"x","c":[1,2,3],"d":null,"e":{"f":true}}"#.as_bytes())
+        .unwrap()
+        .to_vec();
+    let cmp = |path: &'static str, op, value| Predicate::Compare {
+        path: parse_json_path(path.as_bytes()).unwrap(),
+        op,
+        value,
+    };
+    let num = |n| PathValue::Number(Number::Int64(n));
+    let str = |s| PathValue::String(Cow::Borrowed(s));
+
+    let cases = vec![
+        (cmp("$.a", CompareOperator::Eq, num(1)), true),
+        (cmp("$.a", CompareOperator::NotEq, num(1)), false),
+        (cmp("$.a", CompareOperator::Gte, num(1)), true),
+        (cmp("$.a", CompareOperator::Lt, num(1)), false),
+        (cmp("$.a", CompareOperator::Eq, str("1")), false),
+        (cmp("$.a", CompareOperator::NotEq, str("1")), false),
+        (cmp("$.b", CompareOperator::Gt, str("w")), true),
+        (cmp("$.c[*]", CompareOperator::Gt, num(2)), true),
+        (cmp("$.c[*]", CompareOperator::Gt, num(3)), false),
+        (cmp("$.c", CompareOperator::Eq, num(1)), false),
+        (cmp("$.d", CompareOperator::Eq, PathValue::Null), true),
+        (
+            cmp("$.e.f", CompareOperator::Eq, PathValue::Boolean(true)),
+            true,
+        ),
+        (cmp("$.x", CompareOperator::NotEq, num(1)), false),
+        (
+            Predicate::Exists(parse_json_path("$.e.f".as_bytes()).unwrap()),
+            true,
+        ),
+        (
+            Predicate::Exists(parse_json_path("$.e.g".as_bytes()).unwrap()),
+            false,
+        ),
+        (
+            Predicate::And(vec![
+                cmp("$.a", CompareOperator::Eq, num(1)),
+                cmp("$.b", CompareOperator::Eq, str("x")),
+            ]),
+            true,
+        ),
+        (
+            Predicate::And(vec![
+                cmp("$.a", CompareOperator::Eq, num(2)),
+                cmp("$.b", CompareOperator::Eq, str("x")),
+            ]),
+            false,
+        ),
+        (
+            Predicate::Or(vec![
+                cmp("$.a", CompareOperator::Eq, num(2)),
+                cmp("$.b", CompareOperator::Eq, str("x")),
+            ]),
+            true,
+        ),
+        (Predicate::And(vec![]), true),
+        (Predicate::Or(vec![]), false),
+        (
+            Predicate::Not(Box::new(cmp("$.a", CompareOperator::Eq, num(2)))),
+            true,
+        ),
+    ];
+    for (predicate, expect) in cases {
+        assert_eq!(matches(&value, &predicate), expect, "{predicate:?}");
+        assert_eq!(
+            matches(&RawJsonb::new(&value), &predicate),
+            expect,
+            "{predicate:?}"
+        );
+    }
+
+    let predicate = cmp("$.a", CompareOperator::Eq, num(1));
+    assert!(matches(r#"{"a":1.0}"#, &predicate));
+
+    // The predicates over invalid values are false at the leaves.
+    let not = |p| Predicate::Not(Box::new(p));
+    let exists = Predicate::Exists(parse_json_path(b"$.a").unwrap());
+    let cases = vec![
+        (predicate.clone(), false),
+        (exists.clone(), false),
+        (not(predicate.clone()), true),
+        (not(not(predicate.clone())), false),
+        (not(exists.clone()), true),
+        (Predicate::And(vec![]), true),
+        (Predicate::Or(vec![]), false),
+        (not(Predicate::And(vec![])), false),
+        (
+            Predicate::And(vec![not(predicate.clone()), not(exists.clone())]),
+            true,
+        ),
+        (
+            Predicate::And(vec![not(predicate.clone()), exists.clone()]),
+            false,
+        ),
+        (Predicate::Or(vec![predicate.clone(), not(exists)]), true),
+        (not(Predicate::Or(vec![predicate.clone()])), true),
+        (not(Predicate::And(vec![not(predicate)])), false),
+    ];
+    for (predicate, expect) in cases {
+        assert_eq!(matches("{", &predicate), expect, "{predicate:?}");
+    }
+}
+
+#[test]
+fn test_index_entries() {
+    let value = parse_value(r#"{"a":{"b":1.0},"c":["x",null,true]}"#.as_bytes())
+        .unwrap()
+        .to_vec();
+    let path = |keys: &[&str]| {
+        let mut buf = Vec::new();
+        for key in keys {
+            buf.extend_from_slice(&(key.len() as u32).to_be_bytes());
+            buf.extend_from_slice(key.as_bytes());
+        }
+        buf
+    };
+    let entry = |prefix: Vec<u8>, flag: u8, data: &str| {
+        let mut buf = prefix;
+        buf.push(flag);
+        buf.extend_from_slice(data.as_bytes());
+        buf
+    };
+
+    let entries: Vec<_> = index_entries(&value, IndexMode::Keys).collect();
+    assert_eq!(
+        entries,
+        vec![
+            vec![0x01, b'a'],
+            vec![0x01, b'b'],
+            vec![0x01, b'c'],
+            vec![0x01, b'x'],
+            vec![0x02],
+            vec![0x03, b't'],
+            vec![0x04, b'1'],
+        ]
+    );
+    let entries: Vec<_> = index_entries(&value, IndexMode::Paths).collect();
+    assert_eq!(entries, vec![path(&["a"]), path(&["a", "b"]), path(&["c"])]);
+    let entries: Vec<_> = index_entries(&value, IndexMode::PathValues).collect();
+    assert_eq!(
+        entries,
+        vec![
+            entry(path(&["a", "b"]), 0x04, "1"),
+            entry(path(&["c"]), 0x02, ""),
+            entry(path(&["c"]), 0x03, "t"),
+            entry(path(&["c"]), 0x05, "x"),
+        ]
+    );
+    assert_eq!(
+        index_entries(r#""x""#, IndexMode::Keys).collect::<Vec<_>>(),
+        vec![vec![0x01, b'x']]
+    );
+    assert_eq!(index_entries("{", IndexMode::Keys).count(), 0);
+
+    // the entries of the contained value are the subset of the entries of the container.
+    let cases = vec![
+        (r#"{"a":{"b":1,"c":2},"d":[1,"x"]}"#, r#"{"a":{"b":1.0}}"#),
+        (r#"{"a":{"b":1,"c":2},"d":[1,"x"]}"#, r#"{"d":["x"]}"#),
+        (r#"[1,[2,{"a":"b"}]]"#, r#"[[{"a":"b"}]]"#),
+        (r#"["x","y"]"#, r#""x""#),
+    ];
+    for (container, contained) in cases {
+        assert!(jsonb::pg::contains(container, contained).unwrap());
+        for mode in [IndexMode::Keys, IndexMode::Paths, IndexMode::PathValues] {
+            let entries: Vec<_> = index_entries(container, mode).collect();
+            for entry in index_entries(contained, mode) {
+                assert!(entries.contains(&entry), "{container} {contained} {mode:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_diff() {
+    let sources = vec![
+        (r#"{"a":1}"#, r#"{"a":1.0}"#, "[]"),
+        (
+            r#"{"a":1,"b":2}"#,
+            r#"{"a":3,"c":4}"#,
+            r#"[{"op":"replace","path":"\/a","value":3},{"op":"remove","path":"\/b"},{"op":"add","path":"\/c","value":4}]"#,
+        ),
+        (
+            r#"[1,2,3,4]"#,
+            r#"[1,5]"#,
+            r#"[{"op":"replace","path":"\/1","value":5},{"op":"remove","path":"\/3"},{"op":"remove","path":"\/2"}]"#,
+        ),
+        (
+            r#"{"a/b":[1],"~":null}"#,
+            r#"{"a/b":[1,{"x":true}],"~":"y"}"#,
+            r#"[{"op":"add","path":"\/a~1b\/1","value":{"x":true}},{"op":"replace","path":"\/~0","value":"y"}]"#,
+        ),
+        (
+            r#"1"#,
+            r#"[1]"#,
+            r#"[{"op":"replace","path":"","value":[1]}]"#,
+        ),
+    ];
+    for (left, right, expect) in sources {
+        let left = parse_value(left.as_bytes()).unwrap().to_vec();
+        let ops = diff(&RawJsonb::new(&left), right).unwrap();
+        assert_eq!(to_string(&RawJsonb::new(&ops)), expect);
+    }
+    assert!(diff("[1", "[1]").is_err());
+}
+
+#[test]
+fn test_cancellation() {
+    let source = format!(
+        r#"{{"a":[{}]}}"#,
+        vec![r#"{"b":[1,{"c":2}]}"#; 100].join(",")
+    );
+    let value = parse_value(source.as_bytes()).unwrap().to_vec();
+    let json_path = parse_json_path(b"$..c").unwrap();
+
+    let token = CancellationToken::new();
+    let selector = Selector::new(json_path.clone()).with_cancellation(&token);
+    assert_eq!(selector.try_select(&value).unwrap().len(), 100);
+    assert_eq!(selector.select(&value).len(), 100);
+    let changed = source.replace(r#""c":2"#, r#""c":3"#);
+    let patch = diff_with_cancellation(source.as_str(), changed.as_str(), &token).unwrap();
+    assert_eq!(patch, diff(source.as_str(), changed.as_str()).unwrap());
+
+    token.cancel();
+    for options in [
+        SelectorOptions::default(),
+        SelectorOptions {
+            strict: true,
+            ..Default::default()
+        },
+    ] {
+        let selector = Selector::with_options(json_path.clone(), options).with_cancellation(&token);
+        assert_eq!(selector.try_select(&value), Err(Error::Cancelled));
+        assert!(selector.select(&value).is_empty());
+    }
+    let result = diff_with_cancellation(source.as_str(), changed.as_str(), &token);
+    assert_eq!(result, Err(Error::Cancelled));
+}
+
+#[test]
+fn test_dump_layout() {
+    let value = parse_value(r#"{"a":[1,"xy"],"b":true}"#.as_bytes())
+        .unwrap()
+        .to_vec();
+    let expect = r#"0x0000  header  object, 2 fields
+0x0004  jentry  key[0] string, 1 bytes
+0x0008  jentry  key[1] string, 1 bytes
+0x000c  jentry  value[0] container, 16 bytes
+0x0010  jentry  value[1] true
+0x0014  data    key[0] "a"
+0x0015  data    key[1] "b"
+0x0016  data    value[0] container
+0x0016    header  array, 2 elements
+0x001a    jentry  [0] number, 2 bytes
+0x001e    jentry  [1] string, 2 bytes
+0x0022    data    [0] number 1
+0x0024    data    [1] "xy"
+"#;
+    assert_eq!(dump_layout(&value).unwrap(), expect);
+
+    assert_eq!(
+        dump_layout(&value[..value.len() - 1]),
+        Err(Error::InvalidEOF)
+    );
+    assert_eq!(dump_layout(b"{}"), Err(Error::InvalidEOF));
+    assert_eq!(
+        dump_layout(b"\x00\x00\x00\x00"),
+        Err(Error::InvalidJsonbHeader)
+    );
+}
+
+#[test]
+fn test_path_cache() {
+    let value = parse_value(r#"{"a":[1,2,3],"b":{"c":"x"}}"#.as_bytes())
+        .unwrap()
+        .to_vec();
+    let cache = PathCache::new(2);
+    assert!(cache.is_empty());
+
+    let selector = cache.get("$.a[1 to last]").unwrap();
+    let expect = get_by_path(
+        &RawJsonb::new(&value),
+        parse_json_path(b"$.a[1 to last]").unwrap(),
+    );
+    assert_eq!(selector.select(&value), expect);
+    assert!(std::sync::Arc::ptr_eq(
+        &selector,
+        &cache.get("$.a[1 to last]").unwrap()
+    ));
+    assert_eq!(cache.len(), 1);
+
+    // the path text is not borrowed by the cached selector.
+    let path = String::from("$.b.c");
+    let selector = cache.get(&path).unwrap();
+    drop(path);
+    assert_eq!(selector.select(&value), vec![Value::from("x").to_vec()]);
+
+    // `$.b.c` is the least recently used one.
+    let selector = cache.get("$.a[1 to last]").unwrap();
+    cache.get("$.a[0]").unwrap();
+    assert_eq!(cache.len(), 2);
+    assert!(std::sync::Arc::ptr_eq(
+        &selector,
+        &cache.get("$.a[1 to last]").unwrap()
+    ));
+
+    assert_eq!(cache.get("$.a[").err(), Some(Error::InvalidJsonPath));
+    assert_eq!(cache.len(), 2);
+    cache.clear();
+    assert!(cache.is_empty());
+
+    let cache = PathCache::new(0);
+    assert_eq!(cache.get("$.a[0]").unwrap().select(&value).len(), 1);
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn test_aggregator() {
+    let docs = [
+        r#"{"a":3}"#,
+        r#"{"a":-1}"#,
+        r#"{"a":2.5}"#,
+        r#"{"a":null}"#,
+        r#"{"a":"x"}"#,
+        r#"{"b":1}"#,
+    ];
+    let path = parse_json_path(b"$.a").unwrap();
+    let mut agg = Aggregator::new(path.clone());
+    for doc in docs.iter() {
+        agg.update(*doc).unwrap();
+    }
+    assert_eq!(agg.count(), 5);
+    assert_eq!(agg.null_count(), 1);
+    assert_eq!(agg.missing_count(), 1);
+    assert_eq!(agg.number_count(), 3);
+    assert_eq!(agg.sum(), Some(Number::Float64(4.5)));
+    assert_eq!(agg.avg(), Some(1.5));
+    // strings are greater than numbers.
+    assert_eq!(
+        agg.min().map(|v| to_string(&RawJsonb::new(v))),
+        Some("-1".to_string())
+    );
+    assert_eq!(
+        agg.max().map(|v| to_string(&RawJsonb::new(v))),
+        Some(r#""x""#.to_string())
+    );
+    assert!(agg.update("{").is_err());
+
+    // the partial aggregations are merged into the same result.
+    let mut left = Aggregator::new(path.clone());
+    let mut right = Aggregator::new(path.clone());
+    for (i, doc) in docs.iter().enumerate() {
+        if i % 2 == 0 {
+            left.update(*doc).unwrap();
+        } else {
+            right.update(*doc).unwrap();
+        }
+    }
+    left.merge(&right).unwrap();
+    assert_eq!(left.count(), agg.count());
+    assert_eq!(left.null_count(), agg.null_count());
+    assert_eq!(left.missing_count(), agg.missing_count());
+    assert_eq!(left.sum(), agg.sum());
+    assert_eq!(left.min(), agg.min());
+    assert_eq!(left.max(), agg.max());
+
+    // all the selected values are aggregated.
+    let mut agg = Aggregator::new(parse_json_path(b"$.a[*]").unwrap());
+    agg.update(r#"{"a":[1,2,3]}"#).unwrap();
+    agg.update(r#"{"a":[]}"#).unwrap();
+    assert_eq!(agg.count(), 3);
+    assert_eq!(agg.missing_count(), 1);
+    assert_eq!(agg.sum(), Some(Number::Int64(6)));
+    assert_eq!(agg.avg(), Some(2.0));
+
+    // the sum of integers is exact until it overflows.
+    let mut agg = Aggregator::new(path.clone());
+    assert_eq!(agg.sum(), None);
+    assert_eq!(agg.avg(), None);
+    assert_eq!(agg.min(), None);
+    agg.update(r#"{"a":18446744073709551615}"#).unwrap();
+    agg.update(r#"{"a":-1}"#).unwrap();
+    assert_eq!(agg.sum(), Some(Number::UInt64(u64::MAX - 1)));
+    agg.update(r#"{"a":18446744073709551615}"#).unwrap();
+    assert_eq!(agg.sum(), Some(Number::Float64(2.0 * u64::MAX as f64)));
+}
+
+#[test]
+fn test_key_profiler() {
+    let docs = [
+        r#"{"id":1,"user":{"name":"a"},"items":[{"sku":"x","unit price":1.5},{"sku":"y"}]}"#,
+        r#"{"id":2,"user":{"name":null},"items":[]}"#,
+        r#"{"id":"3","user":"b"}"#,
+    ];
+    let mut profiler = KeyProfiler::new();
+    for doc in docs {
+        profiler.update(doc).unwrap();
+    }
+    assert!(profiler.update("{").is_err());
+    assert_eq!(profiler.documents(), 3);
+
+    let paths = profiler.top_paths(10);
+    let names: Vec<_> = paths.iter().map(|(path, _)| path.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "$.id",
+            "$.user",
+            "$.items",
+            "$.user.name",
+            "$.items[*]",
+            "$.items[*].sku",
+            r#"$.items[*]["unit price"]"#,
+        ]
+    );
+    assert_eq!(
+        paths[0].1,
+        KeyStats {
+            documents: 3,
+            count: 3,
+            number_count: 2,
+            string_count: 1,
+            ..Default::default()
+        }
+    );
+    assert!(!paths[0].1.is_single_type());
+    assert_eq!(paths[4].1.documents, 1);
+    assert_eq!(paths[4].1.count, 2);
+    assert!(paths[4].1.is_single_type());
+    assert_eq!(profiler.top_paths(2).len(), 2);
+
+    let keys = profiler.top_keys(3);
+    let names: Vec<_> = keys.iter().map(|(key, _)| key.as_str()).collect();
+    assert_eq!(names, vec!["id", "user", "items"]);
+
+    let mut left = KeyProfiler::new();
+    let mut right = KeyProfiler::new();
+    left.update(docs[0]).unwrap();
+    right.update(docs[1]).unwrap();
+    right.update(docs[2]).unwrap();
+    left.merge(&right);
+    assert_eq!(left.documents(), 3);
+    assert_eq!(left.top_paths(10), profiler.top_paths(10));
+    assert_eq!(left.top_keys(10), profiler.top_keys(10));
+}
+
+#[test]
+fn test_estimate_selectivity() {
+    let docs = [
+        r#"{"a":{"b":1,"c":[1,2]},"x":1}"#,
+        r#"{"a":{"c":[]}}"#,
+        r#"{"a":{"b":2}}"#,
+        r#"{"y":"z"}"#,
+    ];
+    let mut profiler = KeyProfiler::new();
+    let path = |s: &'static str| parse_json_path(s.as_bytes()).unwrap();
+    let selectivity = estimate(&path("$.a"), &profiler);
+    assert_eq!(selectivity.fraction, 1.0);
+    assert!(selectivity.guessed);
+
+    for doc in docs {
+        profiler.update(doc).unwrap();
+    }
+    let cases = vec![
+        ("$", 1.0),
+        ("$.a", 0.75),
+        ("$.a.b", 0.5),
+        ("$.a.c[0]", 0.25),
+        ("$.missing", 0.0),
+        // `$.a`, `$.x` and `$.y` are assumed to be independent.
+        ("$.*", 1.0 - 0.25 * 0.75 * 0.75),
+        ("$.*.b", 0.5),
+        ("$..b", 0.5),
+    ];
+    for (p, expected) in cases {
+        let selectivity = estimate(&path(p), &profiler);
+        assert!((selectivity.fraction - expected).abs() < 1e-9, "{p}");
+        assert!(!selectivity.guessed);
+    }
+
+    // 2 of 3 `$.a` have `b`, compared with the default inequality selectivity.
+    let cases = vec![
+        ("$.a?(@.b > 1)", 0.75 * 2.0 / 3.0 / 3.0),
+        ("$.a?(@.b == 1)", 0.75 * 2.0 / 3.0 * 0.005),
+        ("$.a?(@.missing == 1 || @.b > 1)", 0.75 * 2.0 / 9.0),
+        ("$.a?(@.b > 1 && @.c > 1)", 0.75 * (2.0 / 9.0) * (2.0 / 9.0)),
+        ("$.a?(exists(@.b))", 0.75 * 2.0 / 3.0),
+    ];
+    for (p, expected) in cases {
+        let selectivity = estimate(&path(p), &profiler);
+        assert!((selectivity.fraction - expected).abs() < 1e-9, "{p}");
+        assert!(selectivity.guessed);
+    }
+}
+
+#[test]
+fn test_group_key() {
+    let sources = [
+        r#"{"a":{"b":[1,2]}}"#,
+        r#"{"a":{"b":"x"}}"#,
+        r#"{"a":{"b":{"c":null}}}"#,
+        r#"{"a":[{"b":1.5},{"b":true}]}"#,
+    ];
+    for path in ["$.a.b", "$.a[*].b", "$.a.b[1]"] {
+        let json_path = parse_json_path(path.as_bytes()).unwrap();
+        for source in sources {
+            let mut buf = Vec::new();
+            let found = group_key(source, &json_path, &mut buf);
+            match get_by_path_first(source, json_path.clone()) {
+                Some(matched) => {
+                    let mut expected = Vec::new();
+                    convert_to_comparable(&RawJsonb::new(&matched), &mut expected);
+                    assert!(found);
+                    assert_eq!(buf, expected);
+                }
+                None => {
+                    assert!(!found);
+                    assert!(buf.is_empty());
+                }
+            }
+        }
+    }
+
+    // the group keys are ordered like the values
+    let json_path = parse_json_path(b"$.k").unwrap();
+    let mut keys: Vec<_> = [
+        r#"{"k":2}"#,
+        r#"{"k":"a"}"#,
+        r#"{"k":[1]}"#,
+        r#"{"k":null}"#,
+    ]
+    .iter()
+    .map(|source| {
+        let mut buf = Vec::new();
+        assert!(group_key(*source, &json_path, &mut buf));
+        buf
+    })
+    .collect();
+    keys.sort();
+    let mut expected = Vec::new();
+    convert_to_comparable(r#"[1]"#, &mut expected);
+    assert_eq!(keys[2], expected);
+}
+
+#[test]
+fn test_join_key() {
+    let path = |s: &'static str| parse_json_path(s.as_bytes()).unwrap();
+    let key = |value: &str, p: &'static str| {
+        let mut buf = Vec::new();
+        let res = join_key(value, &path(p), &mut buf);
+        (res, buf)
+    };
+
+    let (res, int_key) = key(r#"{"a":1}"#, "$.a");
+    assert_eq!(res, JoinKey::Key);
+    let (res, float_key) = key(r#"{"a":[1.0]}"#, "$.a[0]");
+    assert_eq!(res, JoinKey::Key);
+    assert_eq!(int_key, float_key);
+    let (res, str_key) = key(r#"{"a":"1"}"#, "$.a");
+    assert_eq!(res, JoinKey::Key);
+    assert_ne!(int_key, str_key);
+
+    assert_eq!(key(r#"{"a":null}"#, "$.a"), (JoinKey::Null, vec![]));
+    assert_eq!(key(r#"{"b":1}"#, "$.a"), (JoinKey::Null, vec![]));
+    assert_eq!(key(r#"[1]"#, "$.a"), (JoinKey::Null, vec![]));
+    assert_eq!(
+        key(r#"{"a":[1,2]}"#, "$.a[*]"),
+        (JoinKey::Error(Error::InvalidJsonPath), vec![])
+    );
+    assert_eq!(
+        key(r#"{"a":{"b":1}}"#, "$.a"),
+        (JoinKey::Error(Error::InvalidJsonType), vec![])
+    );
+    assert!(matches!(key(r#"{"a":"#, "$.a").0, JoinKey::Error(_)));
+}
+
+#[test]
+fn test_get_by_paths() {
+    let source = r#"{"id":1,"user":{"name":"a","tags":["x","y"],"age":20},"items":[{"p":1},{"p":2.5},{"q":3}]}"#;
+    let paths = [
+        "$.id",
+        "$.user.name",
+        "$.user.tags[*]",
+        "$.user.tags[1]",
+        "$.user.age",
+        "$.user",
+        "$.items[*].p",
+        "$.items[*] ? (@.p > 1)",
+        "$.missing.a",
+        "$",
+        "$.user.name",
+    ];
+    let json_paths: Vec<_> = paths
+        .iter()
+        .map(|path| parse_json_path(path.as_bytes()).unwrap())
+        .collect();
+    let results = get_by_paths(source, &json_paths);
+    assert_eq!(results.len(), paths.len());
+    for (json_path, result) in json_paths.iter().zip(results) {
+        assert_eq!(result, get_by_path(source, json_path.clone()));
+    }
+
+    assert!(get_by_paths(source, &[]).is_empty());
+    let invalid: &[u8] = b"{";
+    let results = get_by_paths(invalid, &json_paths[..2]);
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result.is_empty()));
+}
+
+#[test]
+fn test_get_by_path_limit() {
+    let source = r#"{"a":[{"b":1},{"b":[2,3]},{"c":4},{"b":5}],"d":{"b":6}}"#;
+    let paths = [
+        "$.a[*].b",
+        "$.a[*].b[*]",
+        "$..b",
+        "$.a[3, 1 to 3, 0]",
+        "$.a[*] ? (@.b > 1)",
+        "$.a[*] ? (exists(@.c)).c",
+        "$.x",
+    ];
+    let json_paths: Vec<_> = paths
+        .iter()
+        .map(|path| parse_json_path(path.as_bytes()).unwrap())
+        .collect();
+    for order in [ResultOrder::Document, ResultOrder::Written] {
+        for dedup in [false, true] {
+            let options = SelectorOptions {
+                order,
+                dedup,
+                ..Default::default()
+            };
+            for limit in 0..5 {
+                let limited = SelectorOptions {
+                    limit: Some(limit),
+                    ..options
+                };
+                let results = get_by_paths_with_options(source, &json_paths, limited);
+                for (json_path, result) in json_paths.iter().zip(results) {
+                    let mut expected = get_by_path_with_options(source, json_path.clone(), options);
+                    expected.truncate(limit);
+                    assert_eq!(result, expected, "{json_path} {limit}");
+                    let values = get_by_path_with_options(source, json_path.clone(), limited);
+                    assert_eq!(values, expected, "{json_path} {limit}");
+                }
+            }
+        }
+    }
+
+    // In strict mode, the errors after the limit are still reported.
+    let strict = SelectorOptions {
+        strict: true,
+        limit: Some(1),
+        ..Default::default()
+    };
+    let json_path = parse_json_path(b"$.a[*].b").unwrap();
+    assert!(try_get_by_path_with_options(source, json_path, strict).is_err());
+    let json_path = parse_json_path(b"$.d.b").unwrap();
+    let values = try_get_by_path_with_options(source, json_path, strict).unwrap();
+    assert_eq!(to_strings(values), vec!["6"]);
+}
+
+#[test]
+fn test_jsonb_delta() {
+    let path = |elems: &[&str]| elems.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    let value = |s: &str| parse_value(s.as_bytes()).unwrap().to_vec();
+
+    let mut delta = JsonbDelta::new();
+    assert!(delta.is_empty());
+    delta.push(DeltaOp::Set {
+        path: path(&["a", "b"]),
+        value: value(r#""x""#),
+    });
+    delta.push(DeltaOp::Remove {
+        path: path(&["c", "0"]),
+    });
+    delta.push(DeltaOp::Append {
+        path: path(&["c"]),
+        value: value("[4]"),
+    });
+    delta.push(DeltaOp::Set {
+        path: path(&["missing", "k"]),
+        value: value("1"),
+    });
+    delta.push(DeltaOp::Remove { path: path(&["d"]) });
+    assert_eq!(delta.len(), 5);
+
+    let base = r#"{"a":{"b":1},"c":[1,2],"d":null}"#;
+    let result = delta.apply(base).unwrap();
+    assert_eq!(to_string(&result), r#"{"a":{"b":"x"},"c":[2,[4]]}"#);
+
+    let encoded = delta.encode();
+    let decoded = JsonbDelta::decode(&encoded).unwrap();
+    assert_eq!(decoded, delta);
+    for len in 0..encoded.len() {
+        assert_eq!(
+            JsonbDelta::decode(&encoded[..len]).err(),
+            Some(Error::InvalidEOF)
+        );
+    }
+    let mut invalid = encoded.clone();
+    invalid.push(0);
+    assert_eq!(
+        JsonbDelta::decode(&invalid).err(),
+        Some(Error::InvalidJsonb)
+    );
+
+    // empty path replaces the document, appending to a non array is an error
+    let mut root = JsonbDelta::new();
+    root.push(DeltaOp::Set {
+        path: vec![],
+        value: value("[]"),
+    });
+    assert_eq!(to_string(&root.apply(base).unwrap()), "[]");
+    let mut invalid = JsonbDelta::new();
+    invalid.push(DeltaOp::Append {
+        path: path(&["a"]),
+        value: value("1"),
+    });
+    assert_eq!(invalid.apply(base).err(), Some(Error::InvalidJsonType));
+
+    // composing drops the edits that are overwritten later
+    let mut first = JsonbDelta::new();
+    first.push(DeltaOp::Set {
+        path: path(&["a", "b"]),
+        value: value("2"),
+    });
+    first.push(DeltaOp::Append {
+        path: path(&["c"]),
+        value: value("3"),
+    });
+    first.push(DeltaOp::Set {
+        path: path(&["c", "5"]),
+        value: value("5"),
+    });
+    let mut second = JsonbDelta::new();
+    second.push(DeltaOp::Set {
+        path: path(&["a"]),
+        value: value("{}"),
+    });
+    second.push(DeltaOp::Set {
+        path: path(&["c", "5"]),
+        value: value("6"),
+    });
+    let expected = second.apply(&first.apply(base).unwrap()[..]).unwrap();
+    let mut composed = first.clone();
+    composed.compose(&second);
+    assert_eq!(composed.len(), 4);
+    assert_eq!(composed.apply(base).unwrap(), expected);
+    assert_eq!(to_string(&expected), r#"{"a":{},"c":[1,2,3,5,6],"d":null}"#);
+
+    // the edits before a removal are kept, as the array indexes may be shifted
+    let mut first = JsonbDelta::new();
+    first.push(DeltaOp::Set {
+        path: path(&["c", "1", "x"]),
+        value: value("1"),
+    });
+    first.push(DeltaOp::Remove {
+        path: path(&["c", "0"]),
+    });
+    let mut second = JsonbDelta::new();
+    second.push(DeltaOp::Remove {
+        path: path(&["c", "1"]),
+    });
+    let mut composed = first.clone();
+    composed.compose(&second);
+    assert_eq!(composed.len(), 3);
+}
+#[test]
+fn test_jsonb_delta_compose() {
+    let path = |elems: &[&str]| elems.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    let value = |s: &str| parse_value(s.as_bytes()).unwrap().to_vec();
+
+    let paths = [
+        path(&["a"]),
+        path(&["a", "0"]),
+        path(&["a", "-1"]),
+        path(&["a", "0", "x"]),
+        path(&["a", "-1", "x"]),
+        path(&["a", "5"]),
+        path(&["a", "-5"]),
+        path(&["b", "c"]),
+        path(&["b", "c", "0"]),
+    ];
+    let mut ops = Vec::new();
+    for path in paths.iter() {
+        ops.push(DeltaOp::Set {
+            path: path.clone(),
+            value: value(r#"{"x":0}"#),
+        });
+        ops.push(DeltaOp::Remove { path: path.clone() });
+        ops.push(DeltaOp::Append {
+            path: path.clone(),
+            value: value("[1]"),
+        });
+    }
+
+    // applying the composed delta is the same as applying the deltas in order
+    let base = r#"{"a":[{"x":1},[2],{"x":3}],"b":{"c":[4]}}"#;
+    for first in ops.iter() {
+        for second in ops.iter() {
+            for third in ops.iter() {
+                let mut a = JsonbDelta::new();
+                a.push(first.clone());
+                a.push(second.clone());
+                let mut b = JsonbDelta::new();
+                b.push(third.clone());
+                let Ok(expected) = a.apply(base).and_then(|v| b.apply(&v[..])) else {
+                    continue;
+                };
+                let mut composed = a.clone();
+                composed.compose(&b);
+                assert_eq!(
+                    to_string(&composed.apply(base).unwrap()),
+                    to_string(&expected),
+                    "{:?} {:?} {:?}",
+                    first,
+                    second,
+                    third
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_diff_report() {
+    let value = |s: &str| Some(parse_value(s.as_bytes()).unwrap().to_vec());
+
+    let old =
+        r#"{"id":1,"name":"a","tags":["x","y"],"attrs":{"unit price":1.5,"k":null},"gone":true}"#;
+    let new = r#"{"id":1,"name":"b","tags":["x"],"attrs":{"unit price":"1.5","k":null,"n":[1]},"extra":{}}"#;
+    let changes = diff_report(old, new).unwrap();
+    let expected = [
+        ("$.attrs.n", ChangeKind::Added, None, value("[1]")),
+        (
+            r#"$.attrs["unit price"]"#,
+            ChangeKind::Modified,
+            value("1.5"),
+            value(r#""1.5""#),
+        ),
+        ("$.extra", ChangeKind::Added, None, value("{}")),
+        ("$.gone", ChangeKind::Removed, value("true"), None),
+        (
+            "$.name",
+            ChangeKind::Modified,
+            value(r#""a""#),
+            value(r#""b""#),
+        ),
+        ("$.tags[1]", ChangeKind::Removed, value(r#""y""#), None),
+    ];
+    assert_eq!(changes.len(), expected.len());
+    for (change, (path, kind, old, new)) in changes.iter().zip(expected) {
+        assert_eq!(change.path, path);
+        assert_eq!(change.kind, kind);
+        assert_eq!(change.old, old);
+        assert_eq!(change.new, new);
+    }
+
+    assert!(diff_report(old, old).unwrap().is_empty());
+    let changes = diff_report("[1]", r#"{"a":1}"#).unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].path, "$");
+    assert_eq!(changes[0].kind, ChangeKind::Modified);
+}
+
+#[test]
+fn test_similarity() {
+    let left = r#"{"a":1,"b":[1,2],"c":{"d":"x"}}"#;
+    assert_eq!(similarity(left, left).unwrap(), 1.0);
+    assert_eq!(
+        similarity(left, r#"{"a":1,"b":[1,3],"c":{"d":"x"}}"#).unwrap(),
+        0.6
+    );
+    assert_eq!(similarity(left, r#"{"a":1,"b":[1]}"#).unwrap(), 0.5);
+    assert_eq!(similarity(left, r#"[1,2]"#).unwrap(), 0.0);
+    assert_eq!(similarity("{}", "[]").unwrap(), 0.0);
+    assert_eq!(similarity("[]", "[]").unwrap(), 1.0);
+    assert_eq!(similarity("1", "1.0").unwrap(), 1.0);
+    assert!(similarity("{", "1").is_err());
+}
+
+#[test]
+fn test_transcode() {
+    let sources = [
+        r#"null"#,
+        r#"-12"#,
+        r#"1.5"#,
+        r#""a\"b""#,
+        r#"[1,true,{"k":[]}]"#,
+        r#"{"a":{"b":[null,18446744073709551615]},"c":"d"}"#,
+    ];
+    for source in sources {
+        let mut deserializer = serde_json::Deserializer::from_str(source);
+        let mut buf = vec![1, 2];
+        transcode_from(&mut deserializer, &mut buf).unwrap();
+        assert_eq!(&buf[..2], &[1, 2]);
+        assert_eq!(buf[2..], parse_value(source.as_bytes()).unwrap().to_vec());
+
+        let json = transcode_into(&buf[2..], serde_json::value::Serializer).unwrap();
+        assert_eq!(
+            json,
+            serde_json::from_str::<serde_json::Value>(source).unwrap()
+        );
+    }
+
+    let mut deserializer = serde_json::Deserializer::from_str("[1,");
+    assert!(transcode_from(&mut deserializer, &mut Vec::new()).is_err());
+    let invalid: &[u8] = b"[1,";
+    assert!(transcode_into(invalid, serde_json::value::Serializer).is_err());
+}
+
+#[test]
+fn test_analyze() {
+    let source = r#"{"a":1,"b":[{"a":null},{"a":true}]}"#;
+    let report = analyze(source).unwrap();
+    assert_eq!(report.documents, 1);
+    assert_eq!(
+        report.total_bytes,
+        parse_value(source.as_bytes()).unwrap().to_vec().len()
+    );
+    assert_eq!(report.structure_bytes, 56);
+    assert_eq!(report.object_count, 3);
+    assert_eq!(report.key_count, 4);
+    assert_eq!(report.key_bytes, 4);
+    assert_eq!(report.distinct_key_count, 2);
+    assert_eq!(report.distinct_key_bytes, 2);
+    assert_eq!(report.distinct_shape_count, 2);
+    assert_eq!(report.dictionary_savings, 0);
+    assert_eq!(report.shape_savings, 3);
+    assert!(!report.suggest_dictionary(0.1));
+
+    let report = analyze("1").unwrap();
+    assert_eq!(report.structure_bytes, 8);
+    assert_eq!(report.object_count, 0);
+
+    let docs: Vec<_> = (0..50)
+        .map(|i| format!(r#"{{"customer_name":"name","customer_id":{}}}"#, i))
+        .collect();
+    let report = analyze_all(docs.iter().map(|doc| doc.as_str())).unwrap();
+    assert_eq!(report.documents, 50);
+    assert_eq!(report.distinct_key_count, 2);
+    assert_eq!(report.distinct_shape_count, 1);
+    assert!(report.suggest_dictionary(0.3));
+    assert!(report.suggest_shapes(0.3));
+    assert!(report.suggest_compression(0.3));
+
+    let report = analyze_all(Vec::<&str>::new()).unwrap();
+    assert_eq!(report, Default::default());
+    assert!(!report.suggest_compression(0.0));
+    assert!(analyze("{").is_err());
+}
+
+#[test]
+fn test_each_text() {
+    let source = r#"{"a":"x\"y","b":1.5,"c":false,"d":null,"e":{"k":[1,"v"]}}"#;
+    let pairs: Vec<_> = object_each_text(source).unwrap().collect();
+    assert_eq!(
+        pairs,
+        vec![
+            ("a".to_string(), Some(r#"x"y"#.to_string())),
+            ("b".to_string(), Some("1.5".to_string())),
+            ("c".to_string(), Some("false".to_string())),
+            ("d".to_string(), None),
+            ("e".to_string(), Some(r#"{"k":[1,"v"]}"#.to_string())),
+        ]
+    );
+    let value = parse_value(source.as_bytes()).unwrap().to_vec();
+    assert_eq!(object_each_text(&value).unwrap().count(), 5);
+    assert!(object_each_text("[1]").is_none());
+
+    let elems: Vec<_> = array_elements_text(r#"["a",null,2,[]]"#).unwrap().collect();
+    assert_eq!(
+        elems,
+        vec![
+            Some("a".to_string()),
+            None,
+            Some("2".to_string()),
+            Some("[]".to_string())
+        ]
+    );
+    assert!(array_elements_text(r#"{}"#).is_none());
+    assert!(array_elements_text(r#""a""#).is_none());
+}
+
+#[test]
+fn test_array_any_all() {
+    let gt5 = parse_json_path(b"$ ? (@ > 5)").unwrap();
+    assert_eq!(array_any("[1,7,3]", &gt5), Some(true));
+    assert_eq!(array_all("[1,7,3]", &gt5), Some(false));
+    assert_eq!(array_all("[6,7.5]", &gt5), Some(true));
+    assert_eq!(array_any("[]", &gt5), Some(false));
+    assert_eq!(array_all("[]", &gt5), Some(true));
+    assert_eq!(array_any(r#"{"a":6}"#, &gt5), None);
+    assert_eq!(array_all("6", &gt5), None);
+
+    let source = r#"[{"price":3,"tags":["a"]},{"price":10,"tags":[]},null,"x"]"#;
+    let value = parse_value(source.as_bytes()).unwrap().to_vec();
+    let has_tags = parse_json_path(b"$.tags[0]").unwrap();
+    assert_eq!(array_any(&value, &has_tags), Some(true));
+    assert_eq!(array_all(&value, &has_tags), Some(false));
+
+    let predicate = Predicate::Compare {
+        path: parse_json_path(b"$.price").unwrap(),
+        op: CompareOperator::Gte,
+        value: PathValue::Number(Number::UInt64(10)),
+    };
+    assert_eq!(array_any(&value, &predicate), Some(true));
+    assert_eq!(array_all(&value, &predicate), Some(false));
+
+    let is_not_null = |element: &[u8]| !is_null(&RawJsonb::new(element));
+    assert_eq!(array_all(&value, &is_not_null), Some(false));
+    assert_eq!(array_any(&value, &is_not_null), Some(true));
+    let is_string = |element: &[u8]| as_str(&RawJsonb::new(element)).is_some();
+    assert_eq!(array_any(&value, &is_string), Some(true));
+    assert_eq!(array_all(r#"["a","b"]"#, &is_string), Some(true));
+}
+
+#[test]
+fn test_get_by_path_order() {
+    let source = r#"{"a":[null,true,{"b":1},[2],null,"x"]}"#;
+    let value = parse_value(source.as_bytes()).unwrap().to_vec();
+
+    let path = r#"$.a[5, 0, 2 to 3, 0 to 1, last]"#;
+    let values = get_by_path(&value, parse_json_path(path.as_bytes()).unwrap());
+    assert_eq!(
+        to_strings(values),
+        vec![
+            "null",
+            "null",
+            "true",
+            r#"{"b":1}"#,
+            "[2]",
+            r#""x""#,
+            r#""x""#
+        ]
+    );
+
+    let cases = [
+        (
+            ResultOrder::Document,
+            true,
+            vec!["null", "true", r#"{"b":1}"#, "[2]", r#""x""#],
+        ),
+        (
+            ResultOrder::Written,
+            false,
+            vec![
+                r#""x""#,
+                "null",
+                r#"{"b":1}"#,
+                "[2]",
+                "null",
+                "true",
+                r#""x""#,
+            ],
+        ),
+        (
+            ResultOrder::Written,
+            true,
+            vec![r#""x""#, "null", r#"{"b":1}"#, "[2]", "true"],
+        ),
+    ];
+    for (order, dedup, expected) in cases {
+        let options = SelectorOptions {
+            order,
+            dedup,
+            ..Default::default()
+        };
+        let values =
+            get_by_path_with_options(&value, parse_json_path(path.as_bytes()).unwrap(), options);
+        assert_eq!(to_strings(values), expected);
+    }
+
+    // the nulls at different positions are different values
+    let options = SelectorOptions {
+        order: ResultOrder::Document,
+        dedup: true,
+        ..Default::default()
+    };
+    let values =
+        get_by_path_with_options(&value, parse_json_path(b"$.a[0, 4, 4]").unwrap(), options);
+    assert_eq!(to_strings(values), vec!["null", "null"]);
+}
+
+#[test]
+fn test_get_by_path_recursive_descent() {
+    let source = r#"{"a":{"name":1,"b":[{"name":2},{"c":{"name":3}}]},"name":0}"#;
+    let value = parse_value(source.as_bytes()).unwrap().to_vec();
+    let strict = SelectorOptions {
+        strict: true,
+        ..Default::default()
+    };
+
+    let cases: Vec<(&str, Vec<&str>)> = vec![
+        ("$..name", vec!["0", "1", "2", "3"]),
+        ("$.a..name", vec!["1", "2", "3"]),
+        ("$..b[0]", vec![r#"{"name":2}"#]),
+        ("$..[1]", vec![r#"{"c":{"name":3}}"#]),
+        (
+            "$.a.b..*",
+            vec![
+                r#"{"name":2}"#,
+                "2",
+                r#"{"c":{"name":3}}"#,
+                r#"{"name":3}"#,
+                "3",
+            ],
+        ),
+        ("$.a.b[0]..*", vec!["2"]),
+        ("$.name..*", vec![]),
+        ("$..c.name", vec!["3"]),
+        ("$..missing", vec![]),
+        ("$.a.b[*]?(@..name > 2)", vec![r#"{"c":{"name":3}}"#]),
+    ];
+    for (path, expected) in cases {
+        assert_eq!(select(&value, path), expected);
+        // The structural errors are ignored after the recursive descent.
+        assert_eq!(select_with_options(&value, path, strict).unwrap(), expected);
+    }
+    assert!(select_with_options(&value, "$.name..a", strict).is_ok());
+    assert!(select_with_options(&value, "$.x..name", strict).is_err());
+
+    // The recursive wildcard selects the elements of arrays as well as the values of objects.
+    let value = parse_value(b"[[[1]],{\"a\":[2]}]").unwrap().to_vec();
+    let values = get_by_path(&value, parse_json_path(b"$..*").unwrap());
+    assert_eq!(
+        to_strings(values),
+        vec!["[[1]]", "[1]", "1", r#"{"a":[2]}"#, "[2]", "2"]
+    );
+}
+
+#[test]
+fn test_get_by_path_negative_index() {
+    let value = parse_value(br#"{"items":[1,2,3,4]}"#).unwrap().to_vec();
+    let strict = SelectorOptions {
+        strict: true,
+        ..Default::default()
+    };
+
+    let cases: Vec<(&str, Vec<&str>)> = vec![
+        ("$.items[-1]", vec!["4"]),
+        ("$.items[-4]", vec!["1"]),
+        ("$.items[-2 to last]", vec!["3", "4"]),
+        ("$.items[0, -1]", vec!["1", "4"]),
+        ("$.items[-3 to -2]", vec!["2", "3"]),
+    ];
+    for (path, expected) in cases {
+        assert_eq!(select(&value, path), expected);
+        assert_eq!(select_with_options(&value, path, strict).unwrap(), expected);
+    }
+    assert_eq!(select(&value, "$.items[-5]"), Vec::<String>::new());
+    assert!(select_with_options(&value, "$.items[-5]", strict).is_err());
+}
+
+#[test]
+fn test_get_by_path_last_arithmetic() {
+    let value = parse_value(br#"{"a":[1,2,3,4,5]}"#).unwrap().to_vec();
+
+    assert_eq!(select(&value, "$.a[last - 1]"), vec!["4"]);
+    assert_eq!(select(&value, "$.a[last - 2 + 1]"), vec!["4"]);
+    assert_eq!(select(&value, "$.a[1 + 2]"), vec!["4"]);
+    assert_eq!(select(&value, "$.a[3 - last + last]"), vec!["4"]);
+    assert_eq!(select(&value, "$.a[0 + 1 to last - 3]"), vec!["2"]);
+    assert_eq!(select(&value, "$.a[last + 1]"), Vec::<String>::new());
+    assert!(parse_json_path(b"$.a[last + last]").is_err());
+}
+
+#[test]
+fn test_get_by_path_step_slice() {
+    let value = parse_value(b"[0,1,2,3,4,5,6,7,8,9]").unwrap().to_vec();
+    let written = SelectorOptions {
+        order: ResultOrder::Written,
+        ..Default::default()
+    };
+    let strict = SelectorOptions {
+        order: ResultOrder::Written,
+        strict: true,
+        ..Default::default()
+    };
+
+    let cases: Vec<(&str, Vec<&str>)> = vec![
+        ("$[1:10:2]", vec!["1", "3", "5", "7", "9"]),
+        ("$[:3]", vec!["0", "1", "2"]),
+        ("$[7:]", vec!["7", "8", "9"]),
+        ("$[::4]", vec!["0", "4", "8"]),
+        ("$[-3:]", vec!["7", "8", "9"]),
+        ("$[2:last]", vec!["2", "3", "4", "5", "6", "7", "8"]),
+        ("$[::-3]", vec!["9", "6", "3", "0"]),
+        ("$[5:1:-2]", vec!["5", "3"]),
+        ("$[8:100]", vec!["8", "9"]),
+        ("$[3:1]", vec![]),
+        ("$[::0]", vec![]),
+        ("$[0, 8:]", vec!["0", "8", "9"]),
+    ];
+    for (path, expected) in cases {
+        assert_eq!(
+            select_with_options(&value, path, written).unwrap(),
+            expected,
+            "{path}"
+        );
+        // The slices with a step are clamped to the array in strict mode.
+        assert_eq!(
+            select_with_options(&value, path, strict).unwrap(),
+            expected,
+            "{path}"
+        );
+    }
+    assert_eq!(select(&value, "$[5:1:-2]"), vec!["3", "5"]);
+}
+
+#[test]
+fn test_get_by_path_union() {
+    let value = parse_value(br#"[{"a":1,"b":2,"c":3},{"a":4,"c":{"d":5}},6,[7]]"#)
+        .unwrap()
+        .to_vec();
+    let written = SelectorOptions {
+        order: ResultOrder::Written,
+        ..Default::default()
+    };
+    let strict = SelectorOptions {
+        strict: true,
+        ..Default::default()
+    };
+
+    let cases: Vec<(&str, Vec<&str>, Vec<&str>)> = vec![
+        (r#"$[0]["c", 'a']"#, vec!["1", "3"], vec!["3", "1"]),
+        (
+            r#"$[0 to 1]['c', "a"]"#,
+            vec!["1", "3", "4", r#"{"d":5}"#],
+            vec!["3", "1", r#"{"d":5}"#, "4"],
+        ),
+        (r#"$[*]["b", "x"]"#, vec!["2"], vec!["2"]),
+        (
+            r#"$[3, 0, 2]"#,
+            vec![r#"{"a":1,"b":2,"c":3}"#, "6", "[7]"],
+            vec!["[7]", r#"{"a":1,"b":2,"c":3}"#, "6"],
+        ),
+    ];
+    for (path, document, written_order) in cases {
+        assert_eq!(select(&value, path), document);
+        assert_eq!(
+            select_with_options(&value, path, written).unwrap(),
+            written_order
+        );
+    }
+    assert_eq!(
+        select_with_options(&value, r#"$[0]["c", 'a']"#, strict).unwrap(),
+        vec!["1", "3"]
+    );
+    assert_eq!(
+        select_with_options(&value, r#"$[1]["a", "b"]"#, strict)
+            .unwrap_err()
+            .to_string(),
+        r#"member "b" not found, path $[1]"#
+    );
+}
+
+#[test]
+fn test_get_by_path_exists() {
+    let value =
+        parse_value(br#"[{"a":1,"b":null},{"a":2},{"a":3,"b":{"c":[]}},{"a":4,"b":[1,2]},5]"#)
+            .unwrap()
+            .to_vec();
+
+    assert_eq!(select(&value, "$[*]?(exists(@.b)).a"), vec!["1", "3", "4"]);
+    assert_eq!(select(&value, "$[*]?(exists(@.b.c)).a"), vec!["3"]);
+    assert_eq!(select(&value, "$[*]?(exists(@.b[1])).a"), vec!["4"]);
+    assert_eq!(
+        select(&value, "$[*]?(exists(@.b) && @.a > 1).a"),
+        vec!["3", "4"]
+    );
+    assert_eq!(
+        select(&value, "$[*]?(exists(@.x) || @.a == 2).a"),
+        vec!["2"]
+    );
+    assert_eq!(
+        select(&value, "$[*]?(exists($[4])).a"),
+        vec!["1", "2", "3", "4"]
+    );
+    assert_eq!(
+        select(&value, "$[*]?(exists($[5])).a"),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn test_get_by_path_starts_with() {
+    let value = parse_value(
+        br#"[{"a":"abc"},{"a":"ab"},{"a":"b"},{"a":["abc"]},{"a":1},{"a":"\u00e9t\u00e9"}]"#,
+    )
+    .unwrap()
+    .to_vec();
+
+    assert_eq!(
+        select(&value, r#"$[*]?(@.a starts with "ab").a"#),
+        vec![r#""abc""#, r#""ab""#]
+    );
+    assert_eq!(
+        select(&value, r#"$[*]?(@.a starts with "abc").a"#),
+        vec![r#""abc""#]
+    );
+    // In lax mode, `[*]` also selects the scalar values themselves.
+    assert_eq!(
+        select(&value, r#"$[*]?(@.a[*] starts with "abc").a"#),
+        vec![r#""abc""#, r#"["abc"]"#]
+    );
+    assert_eq!(
+        select(&value, r#"$[*]?(@.a starts with "\u00e9").a"#),
+        vec![r#""été""#]
+    );
+    assert_eq!(
+        select(&value, r#"$[*]?(@.a starts with "1").a"#),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        select(&value, r#"$[*]?(@ starts with "a")"#),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        select(&value, r#"$[*].a?(@ starts with "b" || @ == 1)"#),
+        vec![r#""b""#, "1"]
+    );
+    assert!(parse_json_path(br#"$[*]?(@.a startswith "a")"#).is_err());
+    assert!(parse_json_path(br#"$[*]?(@.a starts with 1)"#).is_err());
+}
+
+#[test]
+fn test_get_by_path_compare_paths() {
+    let value = parse_value(
+        br#"{"orders":[{"id":1,"paid":10,"total":10},{"id":2,"paid":5,"total":10.5},{"id":3,"paid":"5","total":10},{"id":4,"total":1},{"id":5,"paid":[1,20],"total":[5]}]}"#,
+    )
+    .unwrap()
+    .to_vec();
+
+    assert_eq!(
+        select(&value, "$.orders[*]?(@.paid < @.total).id"),
+        vec!["2"]
+    );
+    assert_eq!(
+        select(&value, "$.orders[*]?(@.paid == @.total).id"),
+        vec!["1"]
+    );
+    assert_eq!(
+        select(&value, "$.orders[*]?(@.total >= @.paid && @.id > 1).id"),
+        vec!["2"]
+    );
+    assert_eq!(
+        select(&value, "$.orders[*]?(@.paid[*] > @.total[*]).id"),
+        vec!["5"]
+    );
+    assert_eq!(
+        select(&value, "$.orders[*]?(@.paid != @.total).id"),
+        vec!["2"]
+    );
+    assert_eq!(
+        select(&value, "$.orders[*]?(@.total > $.orders[0].paid).id"),
+        vec!["2"]
+    );
+    // The values of different types are not comparable, `null` is only equal to `null`.
+    assert_eq!(
+        select(&value, "$.orders[*]?(@.paid > 1).id"),
+        vec!["1", "2"]
+    );
+    assert_eq!(
+        select(&value, "$.orders[*]?(@.paid != null).id"),
+        vec!["1", "2", "3"]
+    );
+}
+
+#[test]
+fn test_get_by_path_root_in_filter() {
+    let value = parse_value(
+        br#"{"threshold":10,"items":[{"id":1,"price":5},{"id":2,"price":15},{"id":3,"price":20,"tags":["b"]}],"tags":["a","b"]}"#,
+    )
+    .unwrap()
+    .to_vec();
+
+    let path = "$.items[*]?(@.price > $.threshold).id";
+    assert_eq!(select(&value, path), vec!["2", "3"]);
+    let strict = SelectorOptions {
+        strict: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        select_with_options(&value, path, strict).unwrap(),
+        vec!["2", "3"]
+    );
+    let limit = SelectorOptions {
+        limit: Some(1),
+        ..Default::default()
+    };
+    assert_eq!(select_with_options(&value, path, limit).unwrap(), vec!["2"]);
+
+    // The root is the whole document in the nested filters.
+    assert_eq!(
+        select(&value, "$.items[*].tags[*]?(@ == $.tags[*])"),
+        vec![r#""b""#]
+    );
+    assert_eq!(
+        select(&value, "$.items[*]?(exists($.missing)).id"),
+        Vec::<String>::new()
+    );
+
+    let json_paths = [
+        parse_json_path(path.as_bytes()).unwrap(),
+        parse_json_path(b"$.items[*]?(@.price <= $.threshold).id").unwrap(),
+    ];
+    let values = get_by_paths(&value, &json_paths);
+    let values = values.into_iter().map(to_strings).collect::<Vec<_>>();
+    assert_eq!(values, vec![vec!["2", "3"], vec!["1"]]);
+}
+
+#[test]
+fn test_get_by_path_with_vars() {
+    let value = parse_value(
+        br#"{"items":[{"id":1,"qty":5,"tags":["a"]},{"id":2,"qty":15},{"id":3,"qty":20,"tags":["b"]}]}"#,
+    )
+    .unwrap()
+    .to_vec();
+    let select_with_vars = |path: &str, vars: &str| {
+        let json_path = parse_json_path(path.as_bytes()).unwrap();
+        to_strings(get_by_path_with_vars(&value, json_path, vars))
+    };
+
+    let path = "$.items[*]?(@.qty > $min_qty).id";
+    assert_eq!(select_with_vars(path, r#"{"min_qty":10}"#), vec!["2", "3"]);
+    assert_eq!(select_with_vars(path, r#"{"min_qty":15}"#), vec!["3"]);
+    // The paths of the missing variables select no values.
+    assert_eq!(
+        select_with_vars(path, r#"{"max_qty":10}"#),
+        Vec::<String>::new()
+    );
+    assert_eq!(select_with_vars(path, r#"[10]"#), Vec::<String>::new());
+    assert_eq!(
+        select_with_vars(
+            "$.items[*]?(@.tags[*] == $tags[*] && @.qty < $limits.max).id",
+            r#"{"tags":["b","c"],"limits":{"max":100}}"#
+        ),
+        vec!["3"]
+    );
+    assert_eq!(
+        select_with_vars("$.items[*]?(exists($flag)).id", r#"{"flag":false}"#),
+        vec!["1", "2", "3"]
+    );
+
+    let mut vars = Object::new();
+    vars.insert("min_qty".to_string(), Value::from(18));
+    let json_path = parse_json_path(path.as_bytes()).unwrap();
+    let values = get_by_path_with_vars(&value, json_path, &Value::Object(vars));
+    assert_eq!(to_strings(values), vec!["3"]);
+}
+
+#[test]
+fn test_get_by_path_with_tracker() {
+    let value = parse_value(br#"{"a":[{"b":"x"},{"b":"yy"}],"c":1}"#)
+        .unwrap()
+        .to_vec();
+    let json_path = parse_json_path(b"$.a[*]").unwrap();
+    let expected = get_by_path(&value, json_path.clone());
+    let size = expected.iter().map(|v| v.len()).sum::<usize>();
+
+    for options in [
+        SelectorOptions::default(),
+        SelectorOptions {
+            strict: true,
+            ..Default::default()
+        },
+    ] {
+        let limit = MemoryLimit::new(size);
+        let values =
+            try_get_by_path_with_tracker(&value, json_path.clone(), options, &limit).unwrap();
+        assert_eq!(values, expected);
+        assert_eq!(limit.used(), size);
+
+        let limit = MemoryLimit::new(size - 1);
+        let result = try_get_by_path_with_tracker(&value, json_path.clone(), options, &limit);
+        assert_eq!(result, Err(Error::MemoryLimitExceeded));
+        let selector = Selector::with_options(json_path.clone(), options).with_tracker(&limit);
+        assert!(selector.select(&value).is_empty());
+    }
+}
+
+#[test]
+fn test_get_by_path_type_method() {
+    let value = parse_value(br#"{"a":[null,true,1,"x",[],{"type":"b"}],"b":{"type":1.5},"c":"s"}"#)
+        .unwrap()
+        .to_vec();
+    let strict = SelectorOptions {
+        strict: true,
+        ..Default::default()
+    };
+
+    for options in [SelectorOptions::default(), strict] {
+        assert_eq!(
+            select_with_options(&value, "$.a[*].type()", options).unwrap(),
+            vec![
+                r#""null""#,
+                r#""boolean""#,
+                r#""number""#,
+                r#""string""#,
+                r#""array""#,
+                r#""object""#
+            ]
+        );
+        assert_eq!(
+            select_with_options(&value, "$.type()", options).unwrap(),
+            vec![r#""object""#]
+        );
+        assert_eq!(
+            select_with_options(&value, "$.a.type()", options).unwrap(),
+            vec![r#""array""#]
+        );
+        assert_eq!(
+            select_with_options(&value, "$.c.type()", options).unwrap(),
+            vec![r#""string""#]
+        );
+        assert_eq!(
+            select_with_options(&value, "$.b.type.type()", options).unwrap(),
+            vec![r#""number""#]
+        );
+    }
+    // A member named `type` is still a member accessor.
+    assert_eq!(select(&value, "$.a[*].type"), vec![r#""b""#]);
+    assert_eq!(
+        select(&value, r#"$.*?(@.type() == "object").type"#),
+        vec!["1.5"]
+    );
+    assert_eq!(
+        select(
+            &value,
+            r#"$.a[*]?(@.type() != "number" && @.type() != "null")"#
+        ),
+        vec!["true", r#""x""#, "[]", r#"{"type":"b"}"#]
+    );
+}
+
+#[test]
+fn test_get_by_path_size_method() {
+    let value = parse_value(br#"{"items":[{"tags":["a","b"]},{"tags":[]},{"tags":"c"}],"n":1}"#)
+        .unwrap()
+        .to_vec();
+
+    let strict = SelectorOptions {
+        strict: true,
+        ..Default::default()
+    };
+
+    assert_eq!(select(&value, "$.items.size()"), vec!["3"]);
+    assert_eq!(
+        select(&value, "$.items[*].tags.size()"),
+        vec!["2", "0", "1"]
+    );
+    assert_eq!(select(&value, "$.size()"), vec!["1"]);
+    assert_eq!(select(&value, "$.n.size()"), vec!["1"]);
+    assert_eq!(
+        select(&value, "$.items[*]?(@.tags.size() > 1).tags[0]"),
+        vec![r#""a""#]
+    );
+
+    assert_eq!(
+        select_with_options(&value, "$.items.size()", strict),
+        Ok(vec!["3".to_string()])
+    );
+    assert_eq!(
+        select_with_options(&value, "$.items[*].tags.size()", strict)
+            .unwrap_err()
+            .to_string(),
+        "expected an array, path $.items[2].tags"
+    );
+    assert_eq!(
+        select_with_options(&value, "$.n.size()", strict)
+            .unwrap_err()
+            .to_string(),
+        "expected an array, path $.n"
+    );
+}
+
+#[test]
+fn test_get_by_path_keyvalue_method() {
+    let value = parse_value(br#"{"a":{"tmp_x":1,"y":[2]},"b":{"tmp_z":null},"c":3}"#)
+        .unwrap()
+        .to_vec();
+
+    let strict = SelectorOptions {
+        strict: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        select(&value, "$.a.keyvalue()"),
+        vec![
+            r#"{"id":31,"key":"tmp_x","value":1}"#,
+            r#"{"id":31,"key":"y","value":[2]}"#
+        ]
+    );
+    assert_eq!(
+        select(&value, r#"$.*.keyvalue()?(@.key starts with "tmp_").key"#),
+        vec![r#""tmp_x""#, r#""tmp_z""#]
+    );
+    assert_eq!(select(&value, "$.c.keyvalue()"), Vec::<String>::new());
+    assert_eq!(
+        select(&value, r#"$?(@.a.keyvalue().value == 1).c"#),
+        vec!["3"]
+    );
+
+    assert_eq!(
+        select_with_options(&value, "$.b.keyvalue().value", strict),
+        Ok(vec!["null".to_string()])
+    );
+    assert_eq!(
+        select_with_options(&value, "$.*.keyvalue()", strict)
+            .unwrap_err()
+            .to_string(),
+        "expected an object, path $.c"
+    );
+}
+
+#[test]
+fn test_get_by_path_numeric_methods() {
+    let value = parse_value(
+        br#"{"items":[{"price":"12.5"},{"price":" 8 "},{"price":20},{"price":"n/a"},{"price":"1e999"}]}"#,
+    )
+    .unwrap()
+    .to_vec();
+
+    let strict = SelectorOptions {
+        strict: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        select(&value, "$.items[*].price.double()"),
+        vec!["12.5", "8", "20"]
+    );
+    assert_eq!(
+        select(&value, "$.items[*].price.number()"),
+        vec!["12.5", "8", "20"]
+    );
+    assert_eq!(
+        select(&value, "$.items[*]?(@.price.double() > 10).price"),
+        vec![r#""12.5""#, "20"]
+    );
+    assert_eq!(select(&value, "$.items.number()"), Vec::<String>::new());
+
+    assert_eq!(
+        select_with_options(&value, "$.items[0 to 2].price.number()", strict),
+        Ok(vec!["12.5".to_string(), "8".to_string(), "20".to_string()])
+    );
+    assert_eq!(
+        select_with_options(&value, "$.items[*].price.double()", strict)
+            .unwrap_err()
+            .to_string(),
+        "expected a number or a numeric string, path $.items[3].price"
+    );
+}
+
+#[test]
+fn test_get_by_path_rounding_methods() {
+    let value = parse_value(br#"{"n":[-2.5,1.2,-3,7,-0.5,-9223372036854775808],"s":"1.5"}"#)
+        .unwrap()
+        .to_vec();
+
+    assert_eq!(
+        select(&value, "$.n[*].abs()"),
+        vec!["2.5", "1.2", "3", "7", "0.5", "9223372036854775808"]
+    );
+    assert_eq!(
+        select(&value, "$.n[*].floor()"),
+        vec!["-3", "1", "-3", "7", "-1", "-9223372036854775808"]
+    );
+    assert_eq!(
+        select(&value, "$.n[*].ceiling()"),
+        vec!["-2", "2", "-3", "7", "0", "-9223372036854775808"]
+    );
+    assert_eq!(
+        select(&value, "$.n[*]?(@.abs() > 2)"),
+        vec!["-2.5", "-3", "7", "-9223372036854775808"]
+    );
+    assert_eq!(select(&value, "$.s.abs()"), Vec::<String>::new());
+    assert_eq!(select(&value, "$.s.double().floor()"), vec!["1"]);
+
+    let strict = SelectorOptions {
+        strict: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        select_with_options(&value, "$.s.ceiling()", strict)
+            .unwrap_err()
+            .to_string(),
+        "expected a number or a numeric string, path $.s"
+    );
+}
+
+#[test]
+fn test_get_by_path_arithmetic() {
+    let value = parse_value(
+        br#"[{"a":3,"b":5},{"a":1,"b":2},{"a":2.5,"b":4},{"a":"x","b":1},{"a":4,"b":0},{"a":7,"b":3}]"#,
+    )
+    .unwrap()
+    .to_vec();
+
+    assert_eq!(
+        select(&value, "$[*]?(@.a * 2 >= @.b + 1).a"),
+        vec!["3", "2.5", "4", "7"]
+    );
+    assert_eq!(select(&value, "$[*]?((@.a + 1) * 2 == 8).b"), vec!["5"]);
+    assert_eq!(select(&value, "$[*]?(@.a + 1 * 2 == 5).b"), vec!["5"]);
+    assert_eq!(select(&value, "$[*]?(@.a - @.b - 1 == -3).b"), vec!["5"]);
+    assert_eq!(select(&value, "$[*]?(@.a - (@.b - 1) == -1).b"), vec!["5"]);
+    // the division of integers isn't truncated
+    assert_eq!(select(&value, "$[*]?(@.a / 2 == 3.5).b"), vec!["3"]);
+    assert_eq!(select(&value, "$[*]?(@.a / @.b > 2).b"), vec!["3"]);
+    assert_eq!(select(&value, "$[*]?(@.a % @.b == 1).b"), vec!["2", "3"]);
+    // the division by zero is an error, so the comparison is false
+    assert_eq!(
+        select(&value, "$[*]?(@.a / @.b >= 0).b"),
+        vec!["5", "2", "4", "3"]
+    );
+    // the strings are not numbers
+    assert_eq!(
+        select(&value, "$[*]?(@.a * 1 != 0).b"),
+        vec!["5", "2", "4", "0", "3"]
+    );
+
+    assert_eq!(select(&value, "$[1 + 1].a"), vec!["2.5"]);
+    assert_eq!(select(&value, "$[2 * 3 - 1].a"), vec!["7"]);
+    assert_eq!(select(&value, "$[last - 7 / 2].a"), vec!["2.5"]);
+    assert_eq!(select(&value, "$[7 % 4 to last].b"), vec!["1", "0", "3"]);
+}
+
+#[test]
+fn test_get_by_path_quoted_names() {
+    let value = parse_value(
+        br#"{"key with spaces":1,"weird.key[:]":{"it's":2,"a\"b":3},"weird":{"key[:]":4}}"#,
+    )
+    .unwrap()
+    .to_vec();
+
+    assert_eq!(select(&value, "$['key with spaces']"), vec!["1"]);
+    assert_eq!(select(&value, r#"$["weird.key[:]"]['it\'s']"#), vec!["2"]);
+    assert_eq!(select(&value, r#"$["weird.key[:]"]["a\"b"]"#), vec!["3"]);
+    assert_eq!(
+        select(&value, r#"$["weird.key[:]", 'key with spaces']"#),
+        vec!["1", r#"{"a\"b":3,"it's":2}"#]
+    );
+    assert_eq!(select(&value, r#"$.weird["key[:]"]"#), vec!["4"]);
+    assert_eq!(select(&value, r#"$..["a\"b"]"#), vec!["3"]);
+
+    // the names are quoted when the path is formatted, so it selects the same values
+    let json_path = parse_json_path(br#"$["weird.key[:]"]["a\"b"]"#).unwrap();
+    assert_eq!(json_path.to_string(), r#"$["weird.key[:]"]["a\"b"]"#);
+    let json_path = parse_json_path(br#"$."key with spaces""#).unwrap();
+    assert_eq!(json_path.to_string(), r#"$."key with spaces""#);
+    assert_eq!(select(&value, &json_path.to_string()), vec!["1"]);
+}
+
+#[test]
+fn test_pretty() {
+    let sources = vec![
+        (r#"1"#, "1"),
+        (r#"[]"#, "[\n]"),
+        (r#"{}"#, "{\n}"),
+        (r#"{"a":{}}"#, "{\n    \"a\": {\n    }\n}"),
+        (
+            r#"[1,{"b c":[true,null],"d":"x\/y"},[]]"#,
+            "[\n    1,\n    {\n        \"b c\": [\n            true,\n            null\n        ],\n        \"d\": \"x\\/y\"\n    },\n    [\n    ]\n]",
+        ),
+    ];
+    let mut json = String::new();
+    for (source, expected) in sources {
+        let value = parse_value(source.as_bytes()).unwrap().to_vec();
+        json.clear();
+        pretty(&value, &mut json);
+        assert_eq!(json, expected);
+    }
+}
+
+#[test]
+fn test_shred() {
+    let spec = [
+        (
+            parse_json_path(b"$.a.id").unwrap(),
+            TargetType::Int64,
+            OnError::Fail,
+        ),
+        (
+            parse_json_path(b"$.a.n").unwrap(),
+            TargetType::UInt64,
+            OnError::Null,
+        ),
+        (
+            parse_json_path(b"$.a.f").unwrap(),
+            TargetType::Float64,
+            OnError::Null,
+        ),
+        (
+            parse_json_path(b"$.s").unwrap(),
+            TargetType::String,
+            OnError::Null,
+        ),
+        (
+            parse_json_path(b"$.b[*]").unwrap(),
+            TargetType::Bool,
+            OnError::Null,
+        ),
+        (
+            parse_json_path(b"$.missing").unwrap(),
+            TargetType::Int64,
+            OnError::Fail,
+        ),
+    ];
+
+    let value = r#"{"a":{"id":"12","n":-1,"f":1.5},"s":3,"b":["true",false],"missing":null}"#;
+    let cells = shred(value, &spec).unwrap();
+    assert_eq!(
+        cells,
+        vec![
+            TypedCell::Int64(12),
+            TypedCell::Null,
+            TypedCell::Float64(1.5),
+            TypedCell::String("3".to_string()),
+            TypedCell::Bool(true),
+            TypedCell::Null,
+        ]
+    );
+
+    let value = parse_value(r#"{"a":{"id":[1]}}"#.as_bytes())
+        .unwrap()
+        .to_vec();
+    assert_eq!(shred(&value, &spec), Err(Error::InvalidCast));
+    let cells = shred(&value, &spec[1..]).unwrap();
+    assert!(cells.iter().all(|cell| *cell == TypedCell::Null));
+}
+
+#[test]
+fn test_escape_unescape() {
+    let sources = ["", "abc", "a\"b\\c/d", "\u{8}\u{c}\n\r\t", "中文😀"];
+    let mut escaped = String::new();
+    let mut unescaped = String::new();
+    for s in sources {
+        escaped.clear();
+        unescaped.clear();
+        escape_str_into(s, &mut escaped);
+        let json = format!("\"{}\"", escaped);
+        let value = parse_value(json.as_bytes()).unwrap().to_vec();
+        assert_eq!(to_string(&value), json);
+
+        unescape_into(escaped.as_bytes(), &mut unescaped).unwrap();
+        assert_eq!(unescaped, s);
+    }
+
+    unescaped.clear();
+    unescape_into(r#"x中😀\uDE00\u{0041}"#.as_bytes(), &mut unescaped).unwrap();
+    assert_eq!(unescaped, "x中😀\\uDE00A");
+
+    for data in [&br#"a\"#[..], br#"\q"#, br#"\u12"#, br#"\uzzzz"#] {
+        unescaped.clear();
+        assert!(unescape_into(data, &mut unescaped).is_err());
+    }
+}
+
+#[test]
+fn test_jsonb_hasher() {
+    let source = r#"{"a":[1,{"k":null},[]],"b c":"x","d":{"e":true}}"#;
+    let value = parse_value(source.as_bytes()).unwrap().to_vec();
+
+    let mut whole = JsonbHasher::new(DefaultHasher::new());
+    whole.write_value(&value);
+    let digest = whole.digest();
+
+    let mut pieces = JsonbHasher::new(DefaultHasher::new());
+    pieces.begin_object();
+    pieces.write_key("a");
+    pieces.begin_array();
+    pieces.write_value("1");
+    pieces.write_value(r#"{"k":null}"#);
+    pieces.begin_array();
+    pieces.end();
+    pieces.end();
+    pieces.write_key("b c");
+    pieces.write_value(r#""x""#);
+    pieces.write_key("d");
+    pieces.write_value(r#"{"e":true}"#);
+    pieces.end();
+    assert_eq!(pieces.digest(), digest);
+
+    let mut comparable = Vec::new();
+    convert_to_comparable(&value, &mut comparable);
+    for chunk_size in [1, 3, 64, 100] {
+        let mut chunks = JsonbHasher::new(DefaultHasher::new());
+        for chunk in comparable.chunks(chunk_size) {
+            chunks.write(chunk);
+        }
+        assert_eq!(chunks.finish(), digest);
+    }
+
+    let mut other = JsonbHasher::new(DefaultHasher::new());
+    other.write_value(r#"{"a":[1,{"k":null}],"b c":"x","d":{"e":true}}"#);
+    assert_ne!(other.digest(), digest);
+
+    let mut number = JsonbHasher::new(DefaultHasher::new());
+    number.write_value("[1.0]");
+    let mut other = JsonbHasher::new(DefaultHasher::new());
+    other.write_value("[1]");
+    assert_eq!(number.digest(), other.digest());
+}
+
+#[test]
+fn test_get_or_default() {
+    let value = parse_value(r#"{"a":{"b":null,"c":[1,2]},"d":"x"}"#.as_bytes())
+        .unwrap()
+        .to_vec();
+    let default = parse_value(b"0").unwrap().to_vec();
+    let sources = [
+        ("$.a.c[1]", "2"),
+        ("$.a.c[*]", "1"),
+        ("$.a.b", "0"),
+        ("$.a.e", "0"),
+        ("$.d", r#""x""#),
+    ];
+    for (path, expected) in sources {
+        let json_path = parse_json_path(path.as_bytes()).unwrap();
+        let result = get_or_default(&value, &json_path, &default);
+        assert_eq!(to_string(&result), expected);
+    }
+
+    let paths: Vec<_> = ["$.a.b", "$.e", "$.a.c", "$.d"]
+        .iter()
+        .map(|path| parse_json_path(path.as_bytes()).unwrap())
+        .collect();
+    let result = coalesce(&value, &paths);
+    assert_eq!(result.map(|v| to_string(&v)), Some("[1,2]".to_string()));
+    assert_eq!(coalesce(&value, &paths[..2]), None);
+    assert_eq!(coalesce(&value, &[]), None);
+}
+
+#[test]
+fn test_array_update_where() {
+    let value = parse_value(
+        r#"[{"type":"home","no":"1"},{"type":"work","no":"2"},null,{"type":"home"}]"#.as_bytes(),
+    )
+    .unwrap()
+    .to_vec();
+    let new_element = parse_value(br#"{"type":"home","no":"3"}"#)
+        .unwrap()
+        .to_vec();
+
+    let path = parse_json_path(br#"$ ? (@.type == "home")"#).unwrap();
+    let mut buf = Vec::new();
+    assert_eq!(
+        array_update_where(&value, &path, &new_element, &mut buf),
+        Ok(2)
+    );
+    assert_eq!(
+        to_string(&buf),
+        r#"[{"no":"3","type":"home"},{"no":"2","type":"work"},null,{"no":"3","type":"home"}]"#
+    );
+
+    let is_null = |element: &[u8]| jsonb::is_null(&RawJsonb::new(element));
+    let zero = parse_value(b"0").unwrap().to_vec();
+    buf.clear();
+    assert_eq!(array_update_where(&value, &is_null, &zero, &mut buf), Ok(1));
+    assert_eq!(
+        to_string(&buf),
+        r#"[{"no":"1","type":"home"},{"no":"2","type":"work"},0,{"type":"home"}]"#
+    );
+
+    buf.clear();
+    assert_eq!(
+        array_update_where(r#"{"a":1}"#, &path, &zero, &mut buf),
+        Err(Error::InvalidJsonType)
+    );
+    assert_eq!(array_update_where("[]", &path, &zero, &mut buf), Ok(0));
+    assert_eq!(to_string(&buf), "[]");
+}
+
+#[test]
+fn test_intern_pool() {
+    let mut pool = InternPool::new();
+    assert!(pool.is_empty());
+
+    let rows = [
+        r#"{"kind":"a","level":1}"#,
+        r#"{"level":1,"kind":"a"}"#,
+        r#"{"kind":"b","level":2}"#,
+        r#"{"kind":"a","level":1}"#,
+    ];
+    let handles: Vec<_> = rows.iter().map(|row| pool.intern(*row).unwrap()).collect();
+    assert_eq!(pool.len(), 2);
+    assert!(Arc::ptr_eq(&handles[0], &handles[1]));
+    assert!(Arc::ptr_eq(&handles[0], &handles[3]));
+    assert!(!Arc::ptr_eq(&handles[0], &handles[2]));
+    assert_eq!(pool.ref_count(&handles[0]), 3);
+    assert_eq!(pool.ref_count(&handles[2]), 1);
+    assert_eq!(pool.bytes(), handles[0].len() + handles[2].len());
+    assert_eq!(to_string(&*handles[1]), r#"{"kind":"a","level":1}"#);
+
+    // Sub-values are interned by their encoding.
+    let value = parse_value(r#"{"x":{"kind":"b","level":2}}"#.as_bytes())
+        .unwrap()
+        .to_vec();
+    let path = parse_json_path(b"$.x").unwrap();
+    let sub_value = get_by_path_first(&value, path).unwrap();
+    let shared = pool.intern_jsonb(&sub_value);
+    assert!(Arc::ptr_eq(&shared, &handles[2]));
+    assert_eq!(pool.len(), 2);
+
+    assert!(pool.intern("{").is_err());
+    let b = handles[2].to_vec();
+    drop(handles);
+    drop(shared);
+    assert_eq!(pool.ref_count(&b), 0);
+    assert_eq!(pool.purge(), 2);
+    assert!(pool.get(&b).is_none());
+    assert!(pool.is_empty());
+}
+
+#[test]
+fn test_to_lenient() {
+    fn fail<V>(expect: Result<Option<V>, ()>) -> Result<Option<V>, Error> {
+        expect.map_err(|_| Error::InvalidCast)
+    }
+    fn null<V>(expect: Result<Option<V>, ()>) -> Result<Option<V>, Error> {
+        Ok(expect.unwrap_or(None))
+    }
+
+    let sources = [
+        (r#"null"#, Ok(None), Ok(None), Ok(None::<f64>)),
+        (r#"42"#, Ok(Some(42)), Ok(Some(42)), Ok(Some(42.0))),
+        (r#"true"#, Ok(Some(1)), Ok(Some(1)), Ok(Some(1.0))),
+        (r#"" 42 ""#, Ok(Some(42)), Ok(Some(42)), Ok(Some(42.0))),
+        (r#""-7\n""#, Ok(Some(-7)), Err(()), Ok(Some(-7.0))),
+        (r#""1e3""#, Ok(Some(1000)), Ok(Some(1000)), Ok(Some(1000.0))),
+        (r#"" 1.5""#, Err(()), Err(()), Ok(Some(1.5))),
+        (r#""1e30""#, Err(()), Err(()), Ok(Some(1e30))),
+        (r#""abc""#, Err(()), Err(()), Err(())),
+        (r#"[1]"#, Err(()), Err(()), Err(())),
+    ];
+    for (s, expect_i64, expect_u64, expect_f64) in sources {
+        let value = parse_value(s.as_bytes()).unwrap().to_vec();
+
+        assert_eq!(to_i64_lenient(&value, OnError::Fail), fail(expect_i64));
+        assert_eq!(to_i64_lenient(&value, OnError::Null), null(expect_i64));
+        assert_eq!(to_u64_lenient(&value, OnError::Fail), fail(expect_u64));
+        assert_eq!(to_u64_lenient(&value, OnError::Null), null(expect_u64));
+        assert_eq!(to_f64_lenient(&value, OnError::Fail), fail(expect_f64));
+        assert_eq!(to_f64_lenient(&value, OnError::Null), null(expect_f64));
+    }
+}
+
+#[test]
+fn test_as_exact() {
+    let cast = |s: &str| {
+        let value = parse_value(s.as_bytes()).unwrap().to_vec();
+        (as_i64_exact(&value), as_u64_exact(&value))
+    };
+    assert_eq!(cast("42"), (Ok(42), Ok(42)));
+    assert_eq!(
+        cast("-3.0"),
+        (Ok(-3), Err(Error::Cast(CastErrorCode::OutOfRange)))
+    );
+    assert_eq!(cast("1e3"), (Ok(1000), Ok(1000)));
+    assert_eq!(
+        cast("18446744073709551615"),
+        (Err(Error::Cast(CastErrorCode::OutOfRange)), Ok(u64::MAX))
+    );
+    assert_eq!(
+        cast("1e19"),
+        (
+            Err(Error::Cast(CastErrorCode::OutOfRange)),
+            Ok(10_000_000_000_000_000_000)
+        )
+    );
+    assert_eq!(
+        cast("1.5"),
+        (
+            Err(Error::Cast(CastErrorCode::Fractional)),
+            Err(Error::Cast(CastErrorCode::Fractional))
+        )
+    );
+    assert_eq!(
+        cast(r#""1""#),
+        (
+            Err(Error::Cast(CastErrorCode::NotNumber)),
+            Err(Error::Cast(CastErrorCode::NotNumber))
+        )
+    );
+    assert_eq!(
+        Number::Float64(f64::NAN).as_i64_exact(),
+        Err(Error::Cast(CastErrorCode::NotFinite))
+    );
+    assert_eq!(
+        Error::Cast(CastErrorCode::Fractional).to_string(),
+        "number has a fractional part"
+    );
+}
+
+#[test]
+fn test_str_slice() {
+    let value = parse_value(r#""a\u00e9\n😀b""#.as_bytes())
+        .unwrap()
+        .to_vec();
+    let slice = |start, len| str_slice(&value, start, len).map(|v| to_str(&v).unwrap());
+    assert_eq!(slice(0, 2), Some("aé".to_string()));
+    assert_eq!(slice(1, 3), Some("é\n😀".to_string()));
+    assert_eq!(slice(3, 10), Some("😀b".to_string()));
+    assert_eq!(slice(5, 1), Some("".to_string()));
+    assert_eq!(slice(2, 0), Some("".to_string()));
+    assert_eq!(str_slice(r#""abc""#, 1, 1), Some(Value::from("b").to_vec()));
+    assert_eq!(str_slice("1", 0, 1), None);
+    assert_eq!(str_slice(r#"["abc"]"#, 0, 1), None);
+}
+
+#[test]
+fn test_try_to() {
+    let sources = [
+        (
+            r#"true"#,
+            Some(true),
+            Some(1),
+            Some(1),
+            Some(1.0),
+            Some("true"),
+        ),
+        (r#""False""#, Some(false), None, None, None, Some("False")),
+        (r#"-3"#, None, Some(-3), None, Some(-3.0), Some("-3")),
+        (r#""12""#, None, Some(12), Some(12), Some(12.0), Some("12")),
+        (r#"2.5"#, None, None, None, Some(2.5), Some("2.5")),
+        (r#"null"#, None, None, None, None, None),
+        (r#"{"a":1}"#, None, None, None, None, None),
+    ];
+    for (s, expect_bool, expect_i64, expect_u64, expect_f64, expect_str) in sources {
+        let value = parse_value(s.as_bytes()).unwrap().to_vec();
+        assert_eq!(try_to_bool(&value), expect_bool);
+        assert_eq!(try_to_i64(&value), expect_i64);
+        assert_eq!(try_to_u64(&value), expect_u64);
+        assert_eq!(try_to_f64(&value), expect_f64);
+        assert_eq!(try_to_str(&value).as_deref(), expect_str);
+
+        assert_eq!(to_bool(&value).ok(), expect_bool);
+        assert_eq!(to_i64(&value).ok(), expect_i64);
+        assert_eq!(to_str(&value).ok().as_deref(), expect_str);
+
+        // the text input is cast like the binary value
+        assert_eq!(try_to_bool(s), expect_bool);
+        assert_eq!(try_to_i64(s), expect_i64);
+        assert_eq!(try_to_str(s).as_deref(), expect_str);
+    }
+}
+
+#[test]
+fn test_filter_matching() {
+    let docs: Vec<_> = [
+        r#"{"id":1,"tags":["a","b"]}"#,
+        r#"{"id":2}"#,
+        r#"[1,2]"#,
+        r#"{"id":3,"tags":["b"]}"#,
+        r#"null"#,
+    ]
+    .iter()
+    .map(|doc| parse_value(doc.as_bytes()).unwrap().to_vec())
+    .collect();
+    let values = || docs.iter().map(|doc| doc.as_slice());
+
+    let path = parse_json_path(br#"$.tags[*] ? (@ == "b")"#).unwrap();
+    assert!(exists_in_any(values(), &path));
+    assert_eq!(filter_matching(values(), &path), vec![0, 3]);
+
+    let path = parse_json_path(b"$.missing").unwrap();
+    assert!(!exists_in_any(values(), &path));
+    assert!(filter_matching(values(), &path).is_empty());
+
+    // Stop at the first match.
+    let tested = Cell::new(0);
+    let predicate = |doc: &[u8]| {
+        tested.set(tested.get() + 1);
+        jsonb::is_array(&RawJsonb::new(doc))
+    };
+    assert!(exists_in_any(values(), &predicate));
+    assert_eq!(tested.get(), 3);
+    assert!(!exists_in_any(std::iter::empty(), &predicate));
+}
+
+#[test]
+fn test_array_merge_by_key() {
+    let left =
+        r#"[{"id":1,"name":"a","info":{"x":1,"y":1}},{"id":2.0,"name":"b"},"s",{"name":"c"}]"#;
+    let right = r#"[{"id":2,"name":"B","age":3},{"id":1,"info":{"y":2}},{"id":4},{"name":"d"}]"#;
+    let sources = [
+        (
+            MergeStrategy::RightWins,
+            r#"[{"id":1,"info":{"y":2},"name":"a"},{"age":3,"id":2,"name":"B"},"s",{"name":"c"},{"id":4},{"name":"d"}]"#,
+        ),
+        (
+            MergeStrategy::DeepMerge,
+            r#"[{"id":1,"info":{"x":1,"y":2},"name":"a"},{"age":3,"id":2,"name":"B"},"s",{"name":"c"},{"id":4},{"name":"d"}]"#,
+        ),
+    ];
+    for (strategy, expected) in sources {
+        let mut buf = Vec::new();
+        array_merge_by_key(left, right, "id", strategy, &mut buf).unwrap();
+        assert_eq!(to_string(&buf), expected);
+    }
+
+    let mut buf = Vec::new();
+    assert_eq!(
+        array_merge_by_key(
+            left,
+            r#"{"id":1}"#,
+            "id",
+            MergeStrategy::RightWins,
+            &mut buf
+        ),
+        Err(Error::InvalidJsonType)
+    );
+}
+
+#[test]
+fn test_comparable_key_writer() {
+    let values: Vec<_> = (0..200).map(|_| rand_value().to_vec()).collect();
+
+    let mut writer = ComparableKeyWriter::new(256);
+    let mut spilled = Vec::new();
+    for (row_id, value) in values.iter().enumerate() {
+        writer.write(value, row_id as u64);
+        if row_id % 50 == 0 {
+            spilled.extend(writer.take_runs());
+        }
+    }
+    spilled.extend(writer.finish());
+    assert!(spilled.len() > 1);
+    for run in spilled.iter() {
+        let keys: Vec<_> = RunIter::new(run).unwrap().collect();
+        assert!(keys.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    let runs: Vec<_> = spilled.iter().map(|run| run.as_slice()).collect();
+    let merged: Vec<_> = merge_runs(&runs).unwrap().collect();
+    assert_eq!(merged.len(), values.len());
+    for pair in merged.windows(2) {
+        let (left, right) = (&values[pair[0].1 as usize], &values[pair[1].1 as usize]);
+        assert_ne!(compare(left, right).unwrap(), Ordering::Greater);
+        assert!(pair[0] < pair[1]);
+    }
+
+    let mut writer = ComparableKeyWriter::new(1024);
+    let mut key = Vec::new();
+    convert_to_comparable("[1]", &mut key);
+    writer.write_key(&key, 7);
+    let run = writer.finish().remove(0);
+    assert_eq!(
+        RunIter::new(&run).unwrap().collect::<Vec<_>>(),
+        vec![(key.as_slice(), 7)]
+    );
+    assert_eq!(
+        RunIter::new(&run[..run.len() - 1]).err(),
+        Some(Error::InvalidEOF)
+    );
+    assert!(merge_runs(&[&run[..3]]).is_err());
+}
+
+#[test]
+fn test_sort_indices() {
+    let mut values: Vec<_> = (0..200).map(|_| rand_value().to_vec()).collect();
+    // the long strings with the same prefix and the duplicates.
+    values.push(parse_value(br#""abcdefghij2""#).unwrap().to_vec());
+    values.push(parse_value(br#""abcdefghij1""#).unwrap().to_vec());
+    values.push(values[3].clone());
+    values.push(values[3].clone());
+    let slices: Vec<_> = values.iter().map(|v| v.as_slice()).collect();
+
+    let indices = sort_indices(&slices);
+    assert_eq!(indices.len(), values.len());
+    let mut sorted = indices.clone();
+    sorted.sort_unstable();
+    assert_eq!(sorted, (0..values.len() as u32).collect::<Vec<_>>());
+    for pair in indices.windows(2) {
+        let (left, right) = (&values[pair[0] as usize], &values[pair[1] as usize]);
+        match compare(left, right).unwrap() {
+            Ordering::Less => {}
+            Ordering::Equal => assert!(pair[0] < pair[1]),
+            Ordering::Greater => panic!("{} > {}", to_string(left), to_string(right)),
+        }
+    }
+    let position = |i: u32| indices.iter().position(|j| *j == i).unwrap();
+    assert!(position(201) < position(200));
+
+    let values = ["null", "1", "[1]"];
+    let values: Vec<_> = values.iter().map(|v| v.as_bytes()).collect();
+    let options = CompareOptions {
+        nulls: NullOrder::NullsFirst,
+        ..Default::default()
+    };
+    assert_eq!(sort_indices(&values), vec![1, 2, 0]);
+    assert_eq!(sort_indices_with_options(&values, &options), vec![0, 1, 2]);
+    assert!(sort_indices(&[]).is_empty());
+}
+
+#[test]
+fn test_deep_nesting() {
+    let depth = 100_000;
+    let text = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+    let err = parse_value(text.as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "recursion limit exceeded, pos 1024");
+
+    let options = ParseOptions {
+        max_depth: 2,
+        ..Default::default()
+    };
+    assert!(parse_value_with_options(b"[[1]]", &options).is_ok());
+    let err = parse_value_with_options(b"[[[1]]]", &options).unwrap_err();
+    assert_eq!(err.to_string(), "recursion limit exceeded, pos 2");
+    let options = ParseOptions {
+        max_depth: 0,
+        ..Default::default()
+    };
+    assert!(parse_value_with_options(b"1", &options).is_ok());
+    assert!(parse_value_with_options(b"{}", &options).is_err());
+
+    // Nested arrays with a single element, built from the outermost one.
+    let nested = |depth: usize, inner: &[u8]| {
+        let mut buf = Vec::new();
+        for level in (1..=depth).rev() {
+            buf.extend_from_slice(&0x80000001_u32.to_be_bytes());
+            let length = (8 * (level - 1) + inner.len()) as u32;
+            buf.extend_from_slice(&(0x50000000 | length).to_be_bytes());
+        }
+        buf.extend_from_slice(inner);
+        buf
+    };
+    let empty = 0x80000000_u32.to_be_bytes();
+    let one = parse_value(b"[1]").unwrap().to_vec();
+    let two = parse_value(b"[2]").unwrap().to_vec();
+    let left = nested(depth, &one);
+    let right = nested(depth, &two);
+
+    assert_eq!(to_string(&nested(2, &one)), "[[[1]]]");
+    let json = to_string(&left);
+    assert_eq!(
+        json,
+        format!("{}[1]{}", "[".repeat(depth), "]".repeat(depth))
+    );
+    let mut json = String::new();
+    pretty(&nested(2, &empty), &mut json);
+    assert_eq!(json, "[\n    [\n        [\n        ]\n    ]\n]");
+
+    assert_eq!(compare(&left, &right).unwrap(), Ordering::Less);
+    assert_eq!(compare(&right, &left).unwrap(), Ordering::Greater);
+    assert_eq!(compare(&left, &left).unwrap(), Ordering::Equal);
+    assert_eq!(
+        compare(&nested(depth, &empty), &left).unwrap(),
+        Ordering::Less
+    );
+}
+
+#[test]
+fn test_get_by_name_prefix() {
+    let value =
+        parse_value(br#"{"a":1,"ext.b":[1,2],"ext.a":"x","ext":null,"extra":true,"f":{"g":2}}"#)
+            .unwrap()
+            .to_vec();
+    let pairs = |value: &[u8], prefix| {
+        get_by_name_prefix(value, prefix)
+            .unwrap()
+            .map(|(key, val)| (key.into_owned(), to_string(val.as_ref())))
+            .collect::<Vec<_>>()
+    };
+    let keys = |value: &[u8], prefix| {
+        get_by_name_prefix(value, prefix)
+            .unwrap()
+            .map(|(key, _)| key.into_owned())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        pairs(&value, "ext."),
+        vec![
+            ("ext.a".to_string(), "\"x\"".to_string()),
+            ("ext.b".to_string(), "[1,2]".to_string())
+        ]
+    );
+    assert_eq!(keys(&value, "ext"), vec!["ext", "ext.a", "ext.b", "extra"]);
+    assert_eq!(keys(&value, "").len(), 6);
+    let (key, val) = get_by_name_prefix(&value, "f").unwrap().next().unwrap();
+    assert!(matches!(key, Cow::Borrowed("f")));
+    assert!(matches!(val, Cow::Borrowed(_)));
+    assert_eq!(to_string(val.as_ref()), r#"{"g":2}"#);
+    assert_eq!(get_by_name_prefix(&value, "z").unwrap().count(), 0);
+    assert_eq!(get_by_name_prefix(&value, "b").unwrap().count(), 0);
+
+    let array = parse_value(b"[1,2]").unwrap().to_vec();
+    assert!(get_by_name_prefix(&array, "a").is_none());
+
+    // JSON text is parsed and the pairs are owned.
+    let text = r#"{"ext.a":1,"b":2}"#;
+    let (key, val) = get_by_name_prefix(text, "ext.").unwrap().next().unwrap();
+    assert_eq!(key, "ext.a");
+    assert_eq!(to_string(val.as_ref()), "1");
+
+    // The objects built from items have sorted keys.
+    let mut buf = Vec::new();
+    build_object([("ext.b", "1"), ("a", "2"), ("ext.a", "3")], &mut buf).unwrap();
+    assert_eq!(
+        pairs(&buf, "ext."),
+        vec![
+            ("ext.a".to_string(), r#""3""#.to_string()),
+            ("ext.b".to_string(), r#""1""#.to_string())
+        ]
+    );
+
+    // The keys of an object that isn't sorted are scanned, `{"b":true,"a":false}`.
+    let unsorted = [
+        0x40, 0, 0, 2, 0x10, 0, 0, 1, 0x10, 0, 0, 1, 0x40, 0, 0, 0, 0x30, 0, 0, 0, b'b', b'a',
+    ];
+    assert_eq!(
+        pairs(&unsorted, "a"),
+        vec![("a".to_string(), "false".to_string())]
+    );
+    assert_eq!(keys(&unsorted, ""), vec!["b", "a"]);
+
+    // The corrupted values are not read.
+    let mut truncated = value.clone();
+    truncated.truncate(value.len() - 4);
+    assert!(get_by_name_prefix(&truncated, "f").is_none());
+}
+
+#[test]
+fn test_to_string_bounded() {
+    let value = parse_value(br#"{"a":[1,2,3],"b":"hello"}"#)
+        .unwrap()
+        .to_vec();
+    let json = r#"{"a":[1,2,3],"b":"hello"}"#;
+    assert_eq!(to_string_bounded(&value, json.len()), Ok(json.to_string()));
+    assert_eq!(to_string_bounded(&value, 1000), Ok(json.to_string()));
+    assert_eq!(
+        to_string_bounded(&value, json.len() - 1),
+        Err(Truncated {
+            text: json[..json.len() - 1].to_string()
+        })
+    );
+    assert_eq!(
+        to_string_bounded(&value, 8),
+        Err(Truncated {
+            text: r#"{"a":[1,"#.to_string()
+        })
+    );
+    assert_eq!(
+        to_string_bounded(&value, 0),
+        Err(Truncated {
+            text: String::new()
+        })
+    );
+
+    let value = parse_value(r#"["ab","日本"]"#.as_bytes()).unwrap().to_vec();
+    assert_eq!(
+        to_string_bounded(&value, 8),
+        Err(Truncated {
+            text: r#"["ab",""#.to_string()
+        })
+    );
+    assert_eq!(
+        to_string_bounded(&value, 15),
+        Ok(r#"["ab","日本"]"#.to_string())
+    );
+    assert_eq!(
+        to_string_bounded(&value, 10),
+        Err(Truncated {
+            text: r#"["ab","日"#.to_string()
+        })
+    );
+
+    let text = r#"{"a":1"#;
+    assert_eq!(to_string_bounded(text, 10), Ok(text.to_string()));
+    assert!(to_string_bounded(text, 3).is_err());
+}
+
+#[test]
+fn test_debug_stable() {
+    let sources = vec![
+        (r#"null"#, 2, "null"),
+        (r#"-1.5"#, 2, "-1.5"),
+        (r#""ab""#, 2, r#""ab""#),
+        (r#""abc""#, 2, r#""ab"...(+1 chars)"#),
+        (r#""日本語です""#, 3, r#""日本語"...(+2 chars)"#),
+        (r#""a\"\n""#, 5, r#""a\"\n""#),
+        (
+            r#"[1,[2,3,4],true]"#,
+            2,
+            "[1, [2, 3, ...(+1 items)], ...(+1 items)]",
+        ),
+        (r#"[1,2]"#, 0, "[...(+2 items)]"),
+        (r#"[]"#, 0, "[]"),
+        (
+            r#"{"z":1,"a\"b":{"y":"long string","x":null}}"#,
+            4,
+            r#"{"a\"b": {"x": null, "y": "long"...(+7 chars)}, "z": 1}"#,
+        ),
+        (r#"{"b":1,"a":2,"c":3}"#, 1, r#"{"a": 2, ...(+2 fields)}"#),
+    ];
+    for (source, max_len, expected) in sources {
+        let value = parse_value(source.as_bytes()).unwrap();
+        assert_eq!(value.debug_stable(max_len), expected);
+        let buf = value.to_vec();
+        assert_eq!(debug_stable(&RawJsonb::new(&buf), max_len), expected);
+        assert_eq!(debug_stable(source, max_len), expected);
+    }
+    assert_eq!(debug_stable("[1,", 10), "[1,");
+}
+
+#[test]
+fn test_to_string_with_options() {
+    let value = parse_value(br#"{"a":1.0,"b":[2.5,-3.0,4,1e20],"c":0.0}"#)
+        .unwrap()
+        .to_vec();
+    assert_eq!(
+        to_string(&value),
+        r#"{"a":1,"b":[2.5,-3,4,100000000000000000000],"c":0}"#
+    );
+    let options = FormatOptions {
+        float_fraction: true,
+    };
+    assert_eq!(
+        to_string_with_options(&value, &options),
+        r#"{"a":1.0,"b":[2.5,-3.0,4,100000000000000000000.0],"c":0.0}"#
+    );
+    assert_eq!(
+        to_string_with_options(&value, &FormatOptions::default()),
+        to_string(&value)
+    );
+    assert_eq!(to_string_with_options("[1.0]", &options), "[1.0]");
+    assert_eq!(to_string_with_options("[1.0", &options), "[1.0");
+}
+
+#[test]
+fn test_strict_path_errors() {
+    let value = parse_value(br#"{"a":[{"b":1},{"c":2},3],"d name":{"e":[]}}"#)
+        .unwrap()
+        .to_vec();
+    let strict = SelectorOptions {
+        strict: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        select_with_options(&value, "$.a[0].b", strict).unwrap(),
+        vec!["1"]
+    );
+    assert_eq!(
+        select_with_options(&value, "$.a[0 to 1].*", strict).unwrap(),
+        vec!["1", "2"]
+    );
+    assert_eq!(
+        select_with_options(&value, r#"$."d name".e"#, strict).unwrap(),
+        vec!["[]"]
+    );
+    assert_eq!(
+        select_with_options(&value, "$.a[*].b", strict)
+            .unwrap_err()
+            .to_string(),
+        r#"member "b" not found, path $.a[1]"#
+    );
+    assert_eq!(
+        select_with_options(&value, "$.a[2].b", strict)
+            .unwrap_err()
+            .to_string(),
+        "expected an object, path $.a[2]"
+    );
+    assert_eq!(
+        select_with_options(&value, "$.a[last+1]", strict)
+            .unwrap_err()
+            .to_string(),
+        "array index 3 out of bounds, path $.a"
+    );
+    assert_eq!(
+        select_with_options(&value, r#"$."d name".e[0]"#, strict)
+            .unwrap_err()
+            .to_string(),
+        r#"array index 0 out of bounds, path $["d name"].e"#
+    );
+    assert_eq!(
+        select_with_options(&value, "$.a[0][*]", strict)
+            .unwrap_err()
+            .to_string(),
+        "expected an array, path $.a[0]"
+    );
+    assert_eq!(
+        select_with_options(&value, "$.a[0 to 1]?(@.b == 1)", strict).unwrap(),
+        vec![r#"{"b":1}"#]
+    );
+    assert_eq!(
+        select_with_options(&value, "$.x", strict)
+            .unwrap_err()
+            .to_string(),
+        r#"member "x" not found, path $"#
+    );
+
+    // The errors are ignored in lax mode.
+    assert_eq!(select(&value, "$.a[*].b"), vec!["1"]);
+    let json_path = parse_json_path(b"$.a[*].b").unwrap();
+    assert!(get_by_path_with_options(&value, json_path, strict).is_empty());
+}
+
+#[test]
+fn test_normalize_keys() {
+    let value = r#"{"Name":"a","nested":{"KEY":[{"Ab":1}],"Ñu":true},"List":[1,{"X":null}]}"#;
+    let mut buf = Vec::new();
+    normalize_keys(value, Case::Lower, KeyCollision::Error, &mut buf).unwrap();
+    assert_eq!(
+        to_string(&buf),
+        r#"{"list":[1,{"x":null}],"name":"a","nested":{"key":[{"ab":1}],"ñu":true}}"#
+    );
+    buf.clear();
+    normalize_keys(value, Case::Upper, KeyCollision::Error, &mut buf).unwrap();
+    assert_eq!(
+        to_string(&buf),
+        r#"{"LIST":[1,{"X":null}],"NAME":"a","NESTED":{"KEY":[{"AB":1}],"ÑU":true}}"#
+    );
+
+    // The keys are sorted, "ID" is before "Id" and "id".
+    let value = r#"[{"id":3,"Id":2,"ID":1}]"#;
+    let mut buf = Vec::new();
+    assert_eq!(
+        normalize_keys(value, Case::Lower, KeyCollision::Error, &mut buf),
+        Err(Error::ObjectDuplicateKey)
+    );
+    let mut buf = Vec::new();
+    normalize_keys(value, Case::Lower, KeyCollision::FirstWins, &mut buf).unwrap();
+    assert_eq!(to_string(&buf), r#"[{"id":1}]"#);
+    buf.clear();
+    normalize_keys(value, Case::Lower, KeyCollision::LastWins, &mut buf).unwrap();
+    assert_eq!(to_string(&buf), r#"[{"id":3}]"#);
+
+    buf.clear();
+    normalize_keys("\"Abc\"", Case::Upper, KeyCollision::Error, &mut buf).unwrap();
+    assert_eq!(to_string(&buf), "\"Abc\"");
+}
+
+#[test]
+fn test_object_invert() {
+    let value = r#"{"ca":"Canada","fr":"France","pi":3.14,"zero":0}"#;
+    let mut buf = Vec::new();
+    object_invert(value, KeyCollision::Error, &mut buf).unwrap();
+    assert_eq!(
+        to_string(&buf),
+        r#"{"0":"zero","3.14":"pi","Canada":"ca","France":"fr"}"#
+    );
+
+    let value = r#"{"a":"x","b":"y","c":"x"}"#;
+    assert_eq!(
+        object_invert(value, KeyCollision::Error, &mut buf),
+        Err(Error::ObjectDuplicateKey)
+    );
+    buf.clear();
+    object_invert(value, KeyCollision::FirstWins, &mut buf).unwrap();
+    assert_eq!(to_string(&buf), r#"{"x":"a","y":"b"}"#);
+    buf.clear();
+    object_invert(value, KeyCollision::LastWins, &mut buf).unwrap();
+    assert_eq!(to_string(&buf), r#"{"x":"c","y":"b"}"#);
+
+    for value in [r#"{"a":true}"#, r#"{"a":[1]}"#, r#"["a"]"#, r#""a""#] {
+        assert_eq!(
+            object_invert(value, KeyCollision::Error, &mut buf),
+            Err(Error::InvalidJsonType)
+        );
+    }
+}
+
+#[test]
+fn test_prune() {
+    let value = r#"{"a":null,"b":{"c":[],"d":{}},"e":[1,null,[]],"f":"x"}"#;
+    let mut buf = Vec::new();
+    let report = prune(value, &PruneOptions::default(), &mut buf).unwrap();
+    assert_eq!(to_string(&buf), r#"{"e":[1],"f":"x"}"#);
+    assert_eq!(
+        report,
+        PruneReport {
+            nulls: 2,
+            empty_objects: 2,
+            empty_arrays: 2,
+        }
+    );
+
+    let options = PruneOptions {
+        empty_objects: false,
+        array_elements: false,
+        ..Default::default()
+    };
+    let mut buf = Vec::new();
+    let report = prune(value, &options, &mut buf).unwrap();
+    assert_eq!(to_string(&buf), r#"{"b":{"d":{}},"e":[1,null,[]],"f":"x"}"#);
+    assert_eq!(report.total(), 2);
+
+    let mut buf = Vec::new();
+    let report = prune("[null,{}]", &PruneOptions::default(), &mut buf).unwrap();
+    assert_eq!(to_string(&buf), "[]");
+    assert_eq!(report.total(), 2);
+}
+
+#[test]
+fn test_lazy_value() {
+    let source = r#"{"a":{"b":[1,{"c":"x"}],"d":true},"e":[null,"y"],"f":1.5}"#;
+    let doc = parse_value(source.as_bytes()).unwrap().to_vec();
+    let mut value = LazyValue::from_slice(&doc);
+    assert_eq!(value.to_vec().unwrap(), doc);
+
+    let b = value.get_mut("a").unwrap().unwrap().get_mut("b").unwrap();
+    let c = b.unwrap().index_mut(1).unwrap().unwrap();
+    c.insert("c".to_string(), Value::from("z")).unwrap();
+    c.insert("g".to_string(), LazyValue::from_slice(&doc))
+        .unwrap();
+    value
+        .get_mut("e")
+        .unwrap()
+        .unwrap()
+        .push(Value::Bool(false))
+        .unwrap();
+    assert!(value.remove("f").unwrap().is_some());
+    assert!(value.get_mut("missing").unwrap().is_none());
+
+    // The untouched values are still encoded.
+    let a = value.get_mut("a").unwrap().unwrap();
+    assert!(matches!(a.get_mut("d").unwrap(), Some(LazyValue::Raw(_))));
+    let expected = format!(
+        r#"{{"a":{{"b":[1,{{"c":"z","g":{}}}],"d":true}},"e":[null,"y",false]}}"#,
+        source
+    );
+    assert_eq!(to_string(&value.to_vec().unwrap()), expected);
+    assert_eq!(
+        value.to_value().unwrap(),
+        parse_value(expected.as_bytes()).unwrap()
+    );
+
+    let mut scalar = LazyValue::from(Value::from(1));
+    assert!(scalar.get_mut("a").unwrap().is_none());
+    assert_eq!(
+        scalar.insert("a".to_string(), Value::Null),
+        Err(Error::InvalidJsonType)
+    );
+    assert!(LazyValue::from_slice(&[0x80]).get_mut("a").is_err());
+}
+
+#[test]
+fn test_filter_paths() {
+    let value = r#"{"id":1,"items":[1,2,3],"user":{"cards":[{"cvv":2,"no":"1"},{"no":"3"}],"name":"a","ssn":"x"}}"#;
+    let paths = |paths: &[&'static str]| {
+        paths
+            .iter()
+            .map(|path| parse_json_path(path.as_bytes()).unwrap())
+            .collect::<Vec<_>>()
+    };
+    let cases = vec![
+        (vec![], vec![], value),
+        (vec!["$"], vec![], value),
+        (vec![], vec!["$"], "null"),
+        (
+            vec!["$.id", "$.items[1]"],
+            vec![],
+            r#"{"id":1,"items":[2]}"#,
+        ),
+        (
+            vec![],
+            vec!["$.user.ssn", "$.user.cards[*].cvv", "$.items[0]"],
+            r#"{"id":1,"items":[2,3],"user":{"cards":[{"no":"1"},{"no":"3"}],"name":"a"}}"#,
+        ),
+        (
+            vec!["$.user"],
+            vec!["$.user.*[0]"],
+            r#"{"user":{"cards":[{"no":"3"}],"name":"a","ssn":"x"}}"#,
+        ),
+        // the denylist takes precedence over the allowlist.
+        (vec!["$.user.name"], vec!["$.user"], "{}"),
+        // the containers without the allowed paths are removed.
+        (
+            vec!["$.user.cards[*].cvv", "$.missing.a", "$.id.a"],
+            vec![],
+            r#"{"user":{"cards":[{"cvv":2}]}}"#,
+        ),
+        (vec!["$.missing"], vec![], "{}"),
+    ];
+    for (allow, deny, expected) in cases {
+        let mut buf = Vec::new();
+        filter_paths(value, &paths(&allow), &paths(&deny), &mut buf).unwrap();
+        assert_eq!(to_string(&buf), expected, "allow {allow:?} deny {deny:?}");
+    }
+
+    let mut buf = Vec::new();
+    filter_paths("1", &paths(&["$.a"]), &[], &mut buf).unwrap();
+    assert_eq!(to_string(&buf), "null");
+    assert_eq!(
+        filter_paths(value, &paths(&["$.items[last]"]), &[], &mut buf),
+        Err(Error::InvalidJsonPath)
+    );
+}
+
+#[test]
+fn test_path_trie() {
+    let paths = [
+        "$", "$.a", "$.a.b", "$.a.*", "$.c[1]", "$.c[*].d", "$.*[0]", "$.x",
+    ];
+    let mut trie = PathTrie::new();
+    for (i, path) in paths.iter().enumerate() {
+        let json_path = parse_json_path(path.as_bytes()).unwrap();
+        assert_eq!(trie.insert(&json_path).unwrap(), i);
+    }
+    assert_eq!(trie.len(), paths.len());
+
+    let sources = [
+        (r#"1"#, vec![0]),
+        (r#"{"a":1}"#, vec![0, 1]),
+        (r#"{"a":{"b":null}}"#, vec![0, 1, 2, 3]),
+        (r#"{"a":{"e":1},"c":[1]}"#, vec![0, 1, 3, 6]),
+        (r#"{"c":[{"d":1},{"e":2}]}"#, vec![0, 4, 5, 6]),
+        (r#"{"c":{"d":1}}"#, vec![0]),
+        (r#"[{"a":1}]"#, vec![0]),
+    ];
+    for (source, expected) in sources {
+        assert_eq!(trie.classify(source).unwrap(), expected, "{}", source);
+        let value = parse_value(source.as_bytes()).unwrap().to_vec();
+        assert_eq!(trie.classify(&RawJsonb::new(&value)).unwrap(), expected);
+    }
+
+    for path in ["$.a[last]", "$.a?(@ > 1)", "$..a"] {
+        let json_path = parse_json_path(path.as_bytes()).unwrap();
+        assert!(matches!(
+            trie.insert(&json_path),
+            Err(Error::InvalidJsonPath)
+        ));
+    }
+    assert_eq!(trie.len(), paths.len());
+}
+
+#[test]
+fn test_presence_bitmap() {
+    let docs = [
+        r#"{"a":1,"b":null,"c":{"d":2}}"#,
+        r#"{"b":true}"#,
+        r#"[1,2]"#,
+        r#"{}"#,
+        r#"{"d":1,"zz":2}"#,
+    ];
+    let bufs: Vec<Vec<u8>> = docs
+        .iter()
+        .map(|doc| parse_value(doc.as_bytes()).unwrap().to_vec())
+        .collect();
+    let keys = ["b", "d", "a", "b"];
+    let bitmaps = presence_bitmap(bufs.iter().map(|buf| buf.as_slice()), &keys);
+    let bits: Vec<Vec<bool>> = bitmaps.iter().map(|bits| bits.iter().collect()).collect();
+    assert_eq!(
+        bits,
+        vec![
+            vec![true, false, true, true],
+            vec![true, false, false, true],
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![false, true, false, false],
+        ]
+    );
+    assert_eq!(bitmaps[0].count_ones(), 3);
+    assert_eq!(bitmaps[0].len(), 4);
+
+    // invalid documents have no bits set
+    let invalid: &[u8] = &[0x40, 0x00, 0x00, 0x05, 0x10];
+    let bitmaps = presence_bitmap([invalid].into_iter(), &keys);
+    assert_eq!(bitmaps[0].count_ones(), 0);
+
+    // the keys are found in the objects whose keys are not sorted, `{"b":true,"a":false}`
+    let unsorted: &[u8] = &[
+        0x40, 0, 0, 2, 0x10, 0, 0, 1, 0x10, 0, 0, 1, 0x40, 0, 0, 0, 0x30, 0, 0, 0, b'b', b'a',
+    ];
+    let bitmaps = presence_bitmap([unsorted].into_iter(), &["a", "b", "c"]);
+    let bits: Vec<bool> = bitmaps[0].iter().collect();
+    assert_eq!(bits, vec![true, true, false]);
+
+    let mut bits = BitVec::new(130);
+    bits.set(129, true);
+    bits.set(3, true);
+    bits.set(3, false);
+    assert!(bits.get(129));
+    assert!(!bits.get(130));
+    assert_eq!(bits.count_ones(), 1);
 }
@@ -0,0 +1,111 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonb::compare_bounded;
+use jsonb::dump_layout;
+use jsonb::fuzz::mutations;
+use jsonb::fuzz::MutationKind;
+use jsonb::fuzz::Mutator;
+use jsonb::parse_value;
+
+#[test]
+fn test_mutations() {
+    let sources = [
+        r#"null"#,
+        r#"1"#,
+        r#""abc""#,
+        r#"[1,true,"x",[]]"#,
+        r#"{"a":{"b":[1.5,null]},"c":"d"}"#,
+    ];
+    for source in sources {
+        let value = parse_value(source.as_bytes()).unwrap().to_vec();
+        let mutations = mutations(&value).unwrap();
+        for kind in [
+            MutationKind::Truncate,
+            MutationKind::InvalidHeader,
+            MutationKind::InvalidTag,
+            MutationKind::FlippedTag,
+        ] {
+            assert!(
+                mutations.iter().any(|m| m.kind == kind),
+                "{source} {kind:?}"
+            );
+        }
+        for mutation in mutations {
+            assert_ne!(mutation.value, value);
+            // the data of a flipped tag or the extra entry may still happen to be decoded.
+            match mutation.kind {
+                MutationKind::FlippedTag | MutationKind::HeaderLength => {
+                    let _ = dump_layout(&mutation.value);
+                }
+                _ => assert!(dump_layout(&mutation.value).is_err(), "{mutation:?}"),
+            }
+        }
+    }
+
+    let value = parse_value(br#"[null]"#).unwrap().to_vec();
+    let kinds: Vec<_> = mutations(&value).unwrap().iter().map(|m| m.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            MutationKind::Truncate,
+            MutationKind::InvalidHeader,
+            MutationKind::HeaderLength,
+            MutationKind::Truncate,
+            MutationKind::InvalidTag,
+            MutationKind::FlippedTag,
+        ]
+    );
+    let value = parse_value(br#""ab""#).unwrap().to_vec();
+    let mutation = mutations(&value)
+        .unwrap()
+        .into_iter()
+        .find(|m| m.kind == MutationKind::BadOffset)
+        .unwrap();
+    assert_eq!(mutation.offset, 4);
+    assert_eq!(mutation.value[4..8], [0x10, 0, 0, 3]);
+    assert!(mutations(b"\x00\x00").is_err());
+
+    let value = parse_value(br#"{"k":[1,2,3]}"#).unwrap().to_vec();
+    let mut mutator = Mutator::new(3);
+    let values: Vec<_> = (0..10).map(|_| mutator.mutate(&value).unwrap()).collect();
+    let mut mutator = Mutator::new(3);
+    for mutation in values {
+        assert_eq!(mutator.mutate(&value).unwrap(), mutation);
+    }
+}
+
+#[test]
+fn test_compare_bounded_mutations() {
+    let sources = [
+        r#"[1,true,"x",[]]"#,
+        r#"{"a":{"b":[1.5,null]},"c":"d"}"#,
+        r#"[[1,2],{"k":"v"}]"#,
+    ];
+    for source in sources {
+        let value = parse_value(source.as_bytes()).unwrap().to_vec();
+        for mutation in mutations(&value).unwrap() {
+            // the order of the values that are decoded before the malformed data may be found.
+            let _ = compare_bounded(&mutation.value[..], &value[..], usize::MAX);
+            let _ = compare_bounded(&value[..], &mutation.value[..], usize::MAX);
+            if dump_layout(&mutation.value).is_err() {
+                assert_ne!(
+                    compare_bounded(&mutation.value[..], &mutation.value[..], usize::MAX),
+                    Some(std::cmp::Ordering::Equal),
+                    "{mutation:?}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonb::from_slice;
+use jsonb::generator::Generator;
+use jsonb::generator::GeneratorSpec;
+use jsonb::generator::ValueWeights;
+use jsonb::to_string;
+use jsonb::RawJsonb;
+use jsonb::Value;
+
+fn depth(value: &Value) -> usize {
+    match value {
+        Value::Array(vals) => 1 + vals.iter().map(depth).max().unwrap_or(0),
+        Value::Object(obj) => 1 + obj.values().map(depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+#[test]
+fn test_generator() {
+    let spec = GeneratorSpec::default();
+    let values: Vec<_> = Generator::new(7, spec.clone()).take(20).collect();
+    assert_eq!(
+        values,
+        Generator::new(7, spec.clone()).take(20).collect::<Vec<_>>()
+    );
+    assert_ne!(
+        values,
+        Generator::new(8, spec.clone()).take(20).collect::<Vec<_>>()
+    );
+    for value in values.iter() {
+        let value = from_slice(value).unwrap();
+        assert!(depth(&value) <= spec.max_depth);
+    }
+    // The documents of a seed don't change with the versions of `rand`.
+    let texts: Vec<_> = values[..8]
+        .iter()
+        .map(|value| to_string(&RawJsonb::new(value)))
+        .collect();
+    assert_eq!(
+        texts,
+        vec![
+            "true",
+            "false",
+            "null",
+            r#""jcwjzfngln""#,
+            "[1257866903,-952938.5099187198,null,null]",
+            "-1650743443",
+            "{}",
+            "false",
+        ]
+    );
+
+    // only objects and strings, the values at the maximum depth are strings.
+    let spec = GeneratorSpec {
+        max_depth: 2,
+        max_len: 3,
+        key_alphabet: vec!['x', 'y'],
+        max_key_len: 1,
+        max_string_len: 4,
+        weights: ValueWeights {
+            null: 0,
+            boolean: 0,
+            number: 0,
+            string: 1,
+            array: 0,
+            object: 1,
+        },
+    };
+    let mut generator = Generator::new(1, spec);
+    for _ in 0..20 {
+        let value = generator.generate_value();
+        let Value::Object(obj) = value else {
+            assert!(matches!(value, Value::String(_)));
+            continue;
+        };
+        for (key, val) in obj.iter() {
+            assert!(key == "x" || key == "y");
+            match val {
+                Value::String(_) => {}
+                Value::Object(inner) => {
+                    assert!(inner.values().all(|v| matches!(v, Value::String(s)
+                        if s.chars().all(|c| c == 'x' || c == 'y') && s.len() <= 4)));
+                }
+                _ => panic!("unexpected value {val}"),
+            }
+        }
+    }
+
+    // no type can be generated.
+    let spec = GeneratorSpec {
+        weights: ValueWeights {
+            null: 0,
+            boolean: 0,
+            number: 0,
+            string: 0,
+            array: 0,
+            object: 0,
+        },
+        ..Default::default()
+    };
+    assert_eq!(Generator::new(1, spec).generate_value(), Value::Null);
+}
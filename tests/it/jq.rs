@@ -0,0 +1,165 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonb::jq::*;
+use jsonb::{from_slice, parse_value, Error};
+
+fn run(filter: &str, value: &str) -> Result<Vec<String>, Error> {
+    let filter = parse_jq_filter(filter.as_bytes())?;
+    let value = parse_value(value.as_bytes()).unwrap().to_vec();
+    let outputs = run_jq(&value, &filter)?;
+    Ok(outputs
+        .iter()
+        .map(|v| from_slice(v).unwrap().to_string())
+        .collect())
+}
+
+#[test]
+fn test_jq_parser() {
+    let cases = vec![
+        (".", "."),
+        ("..", ".."),
+        (".a", r#"."a""#),
+        (r#"."a b".c"#, r#"."a b"."c""#),
+        (".a[0]", r#"."a"[0]"#),
+        (".[1:]", ".[1:]"),
+        (".a[]?", r#"."a"[]?"#),
+        (
+            ".a[] | select(.x > 1) | {y: .y}",
+            r#"(."a"[] | (select((."x" > 1)) | {"y": (."y")}))"#,
+        ),
+        ("1 + 2 * 3", "(1 + (2 * 3))"),
+        ("1 - 2 - 3", "((1 - 2) - 3)"),
+        (".a // .b // 1", r#"(."a" // (."b" // 1))"#),
+        (".a, .b | .c", r#"((."a", ."b") | ."c")"#),
+        (".a and .b or not", r#"((."a" and ."b") or not)"#),
+        ("-.a", r#"-(."a")"#),
+        ("[.[] | . * 2]", "[(.[] | (. * 2))]"),
+        ("{a, (.k): 1}", r#"{"a": (."a"), (."k"): (1)}"#),
+        (
+            "if . then 1 elif .a then 2 else 3 end",
+            r#"if . then 1 else if ."a" then 2 else 3 end end"#,
+        ),
+        ("[]", "[]"),
+        ("{}", "{}"),
+    ];
+    for (input, expect) in cases {
+        let filter = parse_jq_filter(input.as_bytes()).unwrap();
+        assert_eq!(filter.to_string(), expect);
+        let reparsed = parse_jq_filter(expect.as_bytes()).unwrap();
+        assert_eq!(filter, reparsed);
+    }
+
+    for input in [
+        "",
+        ".a |",
+        "(.a",
+        ".[",
+        "{a:}",
+        "1 == 2 == 3",
+        "if . then 1",
+        "foo",
+        "map",
+        "select(.a; .b)",
+        "and",
+    ] {
+        assert_eq!(
+            parse_jq_filter(input.as_bytes()),
+            Err(Error::InvalidJqFilter)
+        );
+    }
+}
+
+#[test]
+fn test_jq_run() {
+    let value = r#"{"a":[{"x":1,"y":"a"},{"x":2,"y":"b"},{"x":3,"y":"c"}],"b":null,"s":"hello"}"#;
+    let cases = vec![
+        (
+            ".a[] | select(.x > 1) | {y: .y}",
+            vec![r#"{"y":"b"}"#, r#"{"y":"c"}"#],
+        ),
+        (".a[0].x", vec!["1"]),
+        (".a[-1].y", vec![r#""c""#]),
+        (".a[5]", vec!["null"]),
+        (".b.c", vec!["null"]),
+        (".a | length", vec!["3"]),
+        ("[.a[].x] | add", vec!["6"]),
+        (".a | map(.x * 2)", vec!["[2,4,6]"]),
+        (".a[1:] | map(.y)", vec![r#"["b","c"]"#]),
+        (".s[1:3]", vec![r#""el""#]),
+        (".a[0] | keys", vec![r#"["x","y"]"#]),
+        (".b // .s", vec![r#""hello""#]),
+        (".a[].x, .s", vec!["1", "2", "3", r#""hello""#]),
+        (
+            ".a[] | if .x == 1 then \"one\" elif .x == 2 then \"two\" else .x end",
+            vec![r#""one""#, r#""two""#, "3"],
+        ),
+        (
+            ".a[0] | to_entries",
+            vec![r#"[{"key":"x","value":1},{"key":"y","value":"a"}]"#],
+        ),
+        (
+            ".a[0] | with_entries({key, value: .key})",
+            vec![r#"{"x":"x","y":"y"}"#],
+        ),
+        (".a | sort_by(-.x) | first | .x", vec!["3"]),
+        ("[.a[].x] | reverse", vec!["[3,2,1]"]),
+        ("[.a[].x, 1] | unique", vec!["[1,2,3]"]),
+        ("[.a[].x] | max", vec!["3"]),
+        ("[.[] | type]", vec![r#"["array","null","string"]"#]),
+        ("{(.s): .a[0].x}", vec![r#"{"hello":1}"#]),
+        ("{x: (1, 2)}", vec![r#"{"x":1}"#, r#"{"x":2}"#]),
+        (".s / \"l\"", vec![r#"["he","","o"]"#]),
+        ("[.a[].x] - [2]", vec!["[1,3]"]),
+        ("{a: {b: 1}} * {a: {c: 2}}", vec![r#"{"a":{"b":1,"c":2}}"#]),
+        ("7 / 2, 6 / 2, 7 % 2", vec!["3.5", "3", "1"]),
+        ("7.5 % 2, 5 % -2.5, -7 % 2", vec!["1", "1", "-1"]),
+        ("-9223372036854775808 % -1", vec!["0"]),
+        (
+            "1e308 * 10 == 1.7976931348623157e308, -1e308 * 10 < -1e308",
+            vec!["true", "true"],
+        ),
+        ("has(\"a\"), has(\"c\")", vec!["true", "false"]),
+        ("[..] | length", vec!["13"]),
+        (".s[]?", vec![]),
+        (".a[] | select(.x > 5)", vec![]),
+        ("empty", vec![]),
+        (".a[0].x | tostring", vec![r#""1""#]),
+        ("\"12\" | tonumber", vec!["12"]),
+        (
+            "[true, false, null, 1] | map(not)",
+            vec!["[false,true,true,false]"],
+        ),
+        (
+            "[1, null, \"a\", [], {}, true] | sort",
+            vec![r#"[null,true,1,"a",[],{}]"#],
+        ),
+    ];
+    for (filter, expect) in cases {
+        assert_eq!(run(filter, value).unwrap(), expect, "{filter}");
+    }
+
+    for filter in [
+        ".s[]",
+        ".s.a",
+        ".a[\"x\"]",
+        ".a + 1",
+        "1 / 0",
+        "5 % 0.5",
+        "1.5 % 0",
+        "-.s",
+    ] {
+        assert_eq!(run(filter, value), Err(Error::InvalidJsonType), "{filter}");
+    }
+}
@@ -31,6 +31,11 @@ fn test_json_path() {
         r#"$.store.book[last].test_key\uD83D\uDC8E测试"#,
         r#"$.store.book[0,1, last - 2].price"#,
         r#"$.store.book[0,1 to last-1]"#,
+        r#"$.store.book[-1, -3 to last]"#,
+        r#"$.store.book[last - 2 + 1, 1 + 2 to last-1-1, 2 - 3 + last]"#,
+        r#"$.store.book[1:10:2, :3, -2:, ::-1]"#,
+        r#"$.store.book[0]["title", 'price']"#,
+        r#"$['it\'s', "a\"b"]"#,
         r#"$."store"."book""#,
         r#"$."st\"ore"."book\uD83D\uDC8E""#,
         r#"$[*].book.price ? (@ == 10)"#,
@@ -39,6 +44,26 @@ fn test_json_path() {
         r#"$.store.book?(@.price < 10 && @.category == "fiction")"#,
         r#"$.store.book?(@.price > 10 || @.category == "reference")"#,
         r#"$.store.book?(@.price > 20 && (@.category == "reference" || @.category == "fiction"))"#,
+        r#"$..price"#,
+        r#"$.store..*"#,
+        r#"$..[0]"#,
+        r#"$.store.book?(@..isbn != null)"#,
+        r#"$.store.book?(exists(@.isbn)).title"#,
+        r#"$.store.book?(exists( @.isbn[0] ) && @.price > 10 || exists($.expensive))"#,
+        r#"$.store.book?(@.title starts  with "Sword").title"#,
+        r#"$.store.book?(@.price > $min_price && @.category == $category.name)"#,
+        r#"$.store.book[*].type()"#,
+        r#"$.store.book?(@.authors.size() > 1).title.size()"#,
+        r#"$.store.*.keyvalue()?(@.key starts with "b").value"#,
+        r#"$.store.book?(@.price.double() > 10 && @.isbn.number() == 1).title"#,
+        r#"$.store.book?(@.price.floor() == 8 || @.price.ceiling().abs() > 20).price.abs()"#,
+        r#"$.store.book?(@.price * 2 > @.discount+1 && (@.price - 1) % 3 == 0).title"#,
+        r#"$.store.book?(@.a - (@.b - @.c) / 2 * 4 <= 10).title"#,
+        r#"$.store.book[last - 6 / 2, 2 * 3 % 4]"#,
+        r#"$['key with spaces']["weird.key[:]"]"#,
+        r#"$."key with spaces"..["it's"]..['a\'b'].c"#,
+        r#"$.store?(@["x.y"] == "a\"b" && @."x y" starts with "a\\b").z"#,
+        r#"$.store.book?(@.price.type () == "number" && @.type == "novel").title"#,
         // compatible with Snowflake style path
         r#"[1][2]"#,
         r#"["k1"]["k2"]"#,
@@ -56,6 +81,14 @@ fn test_json_path() {
         writeln!(file, "---------- AST ------------").unwrap();
         writeln!(file, "{:#?}", json_path).unwrap();
         writeln!(file, "\n").unwrap();
+
+        // The output is parsed back to the same path.
+        let output = json_path.to_string();
+        assert_eq!(
+            parse_json_path(output.as_bytes()).unwrap(),
+            json_path,
+            "{output}"
+        );
     }
 }
 
@@ -69,6 +102,16 @@ fn test_json_path_error() {
         r#"$.prop+."#,
         r#"$.."#,
         r#"$.prop.."#,
+        r#"$...prop"#,
+        r#"$[1:2:3:4]"#,
+        r#"$[1::a]"#,
+        r#"$["a", 1]"#,
+        r#"$["abc"#,
+        r#"$['abc]"#,
+        r#"$[last + last]"#,
+        r#"$[1 - last]"#,
+        r#"$[last -]"#,
+        r#"$[2147483647 + 1]"#,
         r#"$.foo bar"#,
         r#"$[0, 1, 2 4]"#,
         r#"$['1','2',]"#,
@@ -81,3 +124,75 @@ fn test_json_path_error() {
         assert!(res.is_err());
     }
 }
+
+#[test]
+fn test_json_path_visitor() {
+    use jsonb::jsonpath::visitor::{self, Fold, Visitor};
+    use jsonb::jsonpath::{Path, PathValue};
+
+    // Collect the field names and whether the path has wildcards.
+    #[derive(Default)]
+    struct Analyzer {
+        fields: Vec<String>,
+        wildcard: bool,
+        values: usize,
+    }
+
+    impl<'a> Visitor<'a> for Analyzer {
+        fn visit_path(&mut self, path: &Path<'a>) {
+            match path {
+                Path::DotField(name) | Path::ColonField(name) | Path::ObjectField(name) => {
+                    self.fields.push(name.to_string());
+                }
+                Path::DotWildcard | Path::BracketWildcard => self.wildcard = true,
+                _ => {}
+            }
+            visitor::visit_path(self, path);
+        }
+
+        fn visit_value(&mut self, _value: &PathValue<'a>) {
+            self.values += 1;
+        }
+    }
+
+    let json_path =
+        parse_json_path(br#"$.store.book[0]?(@.price < $.expensive || @["a b"] == "x").title"#)
+            .unwrap();
+    let mut analyzer = Analyzer::default();
+    analyzer.visit_json_path(&json_path);
+    assert_eq!(
+        analyzer.fields,
+        vec!["store", "book", "price", "expensive", "a b", "title"]
+    );
+    assert!(!analyzer.wildcard);
+    assert_eq!(analyzer.values, 1);
+
+    let mut analyzer = Analyzer::default();
+    analyzer.visit_json_path(&parse_json_path(b"$.a.*").unwrap());
+    assert!(analyzer.wildcard);
+
+    // Rename the fields, the other nodes are rebuilt as they are.
+    struct Rename;
+
+    impl<'a> Fold<'a> for Rename {
+        fn fold_path(&mut self, path: Path<'a>) -> Path<'a> {
+            match path {
+                Path::DotField(name) if name == "price" => Path::DotField("cost".into()),
+                path => visitor::fold_path(self, path),
+            }
+        }
+
+        fn fold_value(&mut self, value: PathValue<'a>) -> PathValue<'a> {
+            match value {
+                PathValue::String(s) => PathValue::String(s.to_uppercase().into()),
+                value => value,
+            }
+        }
+    }
+
+    let json_path = Rename.fold_json_path(json_path);
+    assert_eq!(
+        json_path.to_string(),
+        r#"$.store.book[0]?(@.cost < $.expensive || @["a b"] == "X").title"#
+    );
+}
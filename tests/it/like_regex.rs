@@ -0,0 +1,102 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonb::jsonpath::parse_json_path;
+use jsonb::{get_by_path, parse_value, to_string};
+
+fn select(path: &str, value: &str) -> Vec<String> {
+    let json_path = parse_json_path(path.as_bytes()).unwrap();
+    let value = parse_value(value.as_bytes()).unwrap().to_vec();
+    get_by_path(&value, json_path)
+        .iter()
+        .map(to_string)
+        .collect()
+}
+
+#[test]
+fn test_like_regex_parser() {
+    let cases = vec![
+        (
+            r#"$[*]?(@.a like_regex "^ab.*")"#,
+            r#"$[*]?(@.a like_regex "^ab.*")"#,
+        ),
+        (
+            r#"$[*] ? ( @.a  like_regex  "a\\d+\"" flag  "iq" )"#,
+            r#"$[*]?(@.a like_regex "a\\d+\"" flag "iq")"#,
+        ),
+        (
+            r#"$?(@[*] like_regex "b" && exists(@[1]))"#,
+            r#"$?(@[*] like_regex "b" && exists(@[1]))"#,
+        ),
+    ];
+    for (path, expected) in cases {
+        let json_path = parse_json_path(path.as_bytes()).unwrap();
+        assert_eq!(json_path.to_string(), expected);
+        let reparsed = parse_json_path(expected.as_bytes()).unwrap();
+        assert_eq!(reparsed, json_path);
+    }
+
+    for path in [
+        r#"$?(@ like_regex "(")"#,
+        r#"$?(@ like_regex "a" flag "z")"#,
+        r#"$?(@ like_regex 1)"#,
+        r#"$?(@ like_regex)"#,
+    ] {
+        assert!(parse_json_path(path.as_bytes()).is_err(), "{path}");
+    }
+}
+
+#[test]
+fn test_like_regex() {
+    let value = r#"[{"name":"abc"},{"name":"ABD"},{"name":"xab"},{"name":1},{"name":["b","Abe"]},{"name":"a.c\nab"}]"#;
+    let cases = vec![
+        (r#"$[*]?(@.name like_regex "^ab.*").name"#, vec![r#""abc""#]),
+        (
+            r#"$[*]?(@.name like_regex "^ab" flag "i").name"#,
+            vec![r#""abc""#, r#""ABD""#],
+        ),
+        // The pattern matches anywhere in the string.
+        (
+            r#"$[*]?(@.name like_regex "ab").name"#,
+            vec![r#""abc""#, r#""xab""#, r#""a.c\nab""#],
+        ),
+        (
+            r#"$[*]?(@.name like_regex "^ab" flag "m").name"#,
+            vec![r#""abc""#, r#""a.c\nab""#],
+        ),
+        (
+            r#"$[*]?(@.name like_regex "c.a" flag "s").name"#,
+            vec![r#""a.c\nab""#],
+        ),
+        (r#"$[*]?(@.name like_regex "c.a").name"#, vec![]),
+        (
+            r#"$[*]?(@.name like_regex "a.c" flag "q").name"#,
+            vec![r#""a.c\nab""#],
+        ),
+        (
+            r#"$[*]?(@.name like_regex "a b c" flag "x").name"#,
+            vec![r#""abc""#],
+        ),
+        // Non-string values never match, like the comparisons.
+        (r#"$[*]?(@.name like_regex "1").name"#, vec![]),
+        (r#"$[*]?(@.name like_regex "^b$").name"#, vec![]),
+        (
+            r#"$[*]?(@.name[*] like_regex "^b$").name"#,
+            vec![r#"["b","Abe"]"#],
+        ),
+    ];
+    for (path, expected) in cases {
+        assert_eq!(select(path, value), expected, "{path}");
+    }
+}
@@ -12,8 +12,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(feature = "tokio")]
+mod async_parser;
+mod common;
+mod conformance;
+mod databend;
 mod decode;
 mod encode;
+mod encoding;
+mod extension;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(feature = "unicode")]
+mod fold;
 mod functions;
+#[cfg(feature = "fuzz")]
+mod fuzz;
+mod generator;
+#[cfg(feature = "jq")]
+mod jq;
 mod jsonpath_parser;
+#[cfg(feature = "regex")]
+mod like_regex;
+mod mysql;
 mod parser;
+mod pg;
+mod sqlite;
+#[cfg(feature = "wasm")]
+mod wasm;
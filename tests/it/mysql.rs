@@ -0,0 +1,163 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonb::mysql::*;
+use jsonb::Error;
+
+use crate::common::{assert_jsonb, jsonb};
+
+// The cases are taken from the examples of the MySQL JSON functions reference.
+
+#[test]
+fn test_mysql_json_extract() {
+    let cases: Vec<(&str, Vec<&str>, Option<&str>)> = vec![
+        (r#"[10, 20, [30, 40]]"#, vec!["$[1]"], Some("20")),
+        (
+            r#"[10, 20, [30, 40]]"#,
+            vec!["$[1]", "$[0]"],
+            Some("[20, 10]"),
+        ),
+        (r#"[10, 20, [30, 40]]"#, vec!["$[2][*]"], Some("[30, 40]")),
+        (r#"[10, 20, [30, 40]]"#, vec!["$[3]"], None),
+        (r#"[10, 20, [30, 40]]"#, vec!["$[last]"], Some("[30, 40]")),
+        (r#"[10, 20, [30, 40]]"#, vec!["$[last-1]"], Some("20")),
+        (r#"[10, 20, [30, 40]]"#, vec!["$[last-3]"], None),
+        (r#"[1, 2, 3, 4, 5]"#, vec!["$[1 to 3]"], Some("[2, 3, 4]")),
+        (
+            r#"[1, 2, 3, 4, 5]"#,
+            vec!["$[last-3 to last-1]"],
+            Some("[2, 3, 4]"),
+        ),
+        (r#"[1, 2, 3, 4, 5]"#, vec!["$[3 to 10]"], Some("[4, 5]")),
+        (r#"[1, 2, 3, 4, 5]"#, vec!["$[3 to 1]"], None),
+        (
+            r#"{"a": 1, "b": 2, "c": [3, 4, 5]}"#,
+            vec!["$.*"],
+            Some("[1, 2, [3, 4, 5]]"),
+        ),
+        (
+            r#"{"a": 1, "b": 2, "c": [3, 4, 5]}"#,
+            vec!["$.c[*]"],
+            Some("[3, 4, 5]"),
+        ),
+        (
+            r#"{"a": 1, "b": 2, "c": [3, 4, 5]}"#,
+            vec!["$.c"],
+            Some("[3, 4, 5]"),
+        ),
+        (
+            r#"{"a": {"b": 1}, "c": {"b": 2}}"#,
+            vec!["$**.b"],
+            Some("[1, 2]"),
+        ),
+        (
+            r#"{"a": {"b": 1}, "b": [{"b": 2}]}"#,
+            vec!["$**.b"],
+            Some("[[{\"b\": 2}], 1, 2]"),
+        ),
+        (
+            r#"{"a fish": "shark", "a bird": "sparrow"}"#,
+            vec![r#"$."a fish""#],
+            Some(r#""shark""#),
+        ),
+        (r#"{"a\"b": 1}"#, vec![r#"$."a\"b""#], Some("1")),
+        (r#""abc""#, vec!["$[0]"], Some(r#""abc""#)),
+        (r#""abc""#, vec!["$[1]"], None),
+        (r#"{"a": 1}"#, vec!["$[0].a"], Some("1")),
+        (r#"{"a": 1}"#, vec!["$[*]"], None),
+        (r#"{"a": 1}"#, vec!["$"], Some(r#"{"a": 1}"#)),
+        (r#"{"a": [1, 2]}"#, vec![" $ . a [ 1 ] "], Some("2")),
+    ];
+    for (value, paths, expect) in cases {
+        assert_jsonb(json_extract(&jsonb(value), &paths).unwrap(), expect);
+        assert_jsonb(json_extract(value.as_bytes(), &paths).unwrap(), expect);
+    }
+
+    let value = jsonb(r#"{"a": 1}"#);
+    for path in [
+        "a", "$.", "$a", "$[", "$[1", "$[-1]", "$[last-]", "$**", "$.1a", r#"$."a"#,
+    ] {
+        assert_eq!(json_extract(&value, &[path]), Err(Error::InvalidJsonPath));
+    }
+}
+
+#[test]
+fn test_mysql_json_contains_path() {
+    let value = jsonb(r#"{"a": 1, "b": 2, "c": {"d": 4}}"#);
+    let cases = vec![
+        (ContainsPathMode::One, vec!["$.a", "$.e"], true),
+        (ContainsPathMode::All, vec!["$.a", "$.e"], false),
+        (ContainsPathMode::One, vec!["$.c.d"], true),
+        (ContainsPathMode::One, vec!["$.a.d"], false),
+        (ContainsPathMode::All, vec!["$**.d", "$.*"], true),
+    ];
+    for (mode, paths, expect) in cases {
+        assert_eq!(json_contains_path(&value, mode, &paths).unwrap(), expect);
+    }
+    assert_eq!(
+        json_contains_path(&value, ContainsPathMode::One, &["$.a", "$["]),
+        Err(Error::InvalidJsonPath)
+    );
+}
+
+#[test]
+fn test_mysql_json_merge_preserve() {
+    let cases: Vec<(Vec<&str>, &str)> = vec![
+        (vec!["[1, 2]", r#"[true, false]"#], "[1, 2, true, false]"),
+        (
+            vec![r#"{"name": "x"}"#, r#"{"id": 47}"#],
+            r#"{"id": 47, "name": "x"}"#,
+        ),
+        (vec!["1", "true"], "[1, true]"),
+        (vec!["[1, 2]", r#"{"id": 47}"#], r#"[1, 2, {"id": 47}]"#),
+        (
+            vec![r#"{ "a": 1, "b": 2 }"#, r#"{ "a": 3, "c": 4 }"#],
+            r#"{"a": [1, 3], "b": 2, "c": 4}"#,
+        ),
+        (
+            vec![
+                r#"{ "a": 1, "b": 2 }"#,
+                r#"{ "a": 3, "c": 4 }"#,
+                r#"{ "a": 5, "d": 6 }"#,
+            ],
+            r#"{"a": [1, 3, 5], "b": 2, "c": 4, "d": 6}"#,
+        ),
+        (
+            vec![r#"{"a": {"b": [1]}}"#, r#"{"a": {"b": 2, "c": 3}}"#],
+            r#"{"a": {"b": [1, 2], "c": 3}}"#,
+        ),
+    ];
+    for (values, expect) in cases {
+        let values = values.into_iter().map(jsonb).collect::<Vec<_>>();
+        let values = values.iter().map(|v| v.as_slice()).collect::<Vec<_>>();
+        assert_jsonb(Some(json_merge_preserve(&values).unwrap()), Some(expect));
+    }
+}
+
+#[test]
+fn test_mysql_json_quote() {
+    assert_eq!(json_quote("null"), r#""null""#);
+    assert_eq!(json_quote(r#""null""#), r#""\"null\"""#);
+    assert_eq!(json_quote("a\\b/c\n\t\u{1}"), r#""a\\b/c\n\t\u0001""#);
+    assert_eq!(json_quote("中文"), "\"中文\"");
+
+    assert_eq!(json_unquote(r#""abc""#).unwrap(), "abc");
+    assert_eq!(json_unquote(r#"[1, 2, 3]"#).unwrap(), "[1, 2, 3]");
+    assert_eq!(json_unquote(r#""\t2""#).unwrap(), "\t2");
+    assert_eq!(json_unquote(r#"""#).unwrap(), r#"""#);
+    assert!(json_unquote(r#""a"b""#).is_err());
+    for s in ["", "abc", "a\"b\\c\n", "中文\u{1}"] {
+        assert_eq!(json_unquote(&json_quote(s)).unwrap(), s);
+    }
+}
@@ -13,8 +13,17 @@
 // limitations under the License.
 
 use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Instant;
 
-use jsonb::{parse_value, Number, Object, Value};
+use jsonb::jsonpath::parse_json_path;
+use jsonb::{
+    from_slice, from_slice_with_tracker, parse_value, parse_value_with_options,
+    parse_value_with_spill, parse_value_with_tracker, register_extension_type, split_stream,
+    unregister_extension_type, CancellationToken, Error, ExtensionType, ExtensionValue,
+    MemoryLimit, Number, Object, OnError, ParseOptions, PartialParser, RewriteRule, RewriteTarget,
+    SpillOptions, SpillStore, TargetType, Value,
+};
 
 fn test_parse_err(errors: &[(&str, &'static str)]) {
     for &(s, err) in errors {
@@ -415,3 +424,334 @@ fn test_parse_object() {
         (r#"{ \x0C "d":  5}"#, Value::Object(obj5)),
     ]);
 }
+
+// Stores the text of a value, decoded as a string.
+struct Text;
+
+impl ExtensionType for Text {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Value<'static>, Error> {
+        let s = std::str::from_utf8(payload)?;
+        Ok(Value::String(Cow::Owned(s.to_string())))
+    }
+}
+
+#[test]
+fn test_parse_rewrites() {
+    register_extension_type(14, Arc::new(Text)).unwrap();
+    let rule = |path: &str, target: RewriteTarget, on_error: OnError| {
+        RewriteRule::new(&parse_json_path(path.as_bytes()).unwrap(), target, on_error).unwrap()
+    };
+    let options = ParseOptions {
+        rewrites: vec![
+            rule(
+                "$.id",
+                RewriteTarget::Cast(TargetType::String),
+                OnError::Fail,
+            ),
+            rule("$.ts", RewriteTarget::Extension(14), OnError::Fail),
+            rule(
+                "$.items[*].n",
+                RewriteTarget::Cast(TargetType::Int64),
+                OnError::Null,
+            ),
+            rule(
+                "$.items[0]",
+                RewriteTarget::Cast(TargetType::String),
+                OnError::Fail,
+            ),
+            rule(
+                "$.flags.*",
+                RewriteTarget::Cast(TargetType::Bool),
+                OnError::Null,
+            ),
+        ],
+        ..Default::default()
+    };
+    let text = r#"{"id":7,"ts":"2024-01-02","items":[{"n":"1"},{"n":"x"},{"n":2.0,"m":"3"}],
+        "flags":{"a":"true","b":1,"c":null},"other":{"id":1}}"#;
+    let value = parse_value_with_options(text.as_bytes(), &options).unwrap();
+    assert_eq!(
+        value.get_by_name_ignore_case("ts"),
+        Some(&Value::Extension(
+            ExtensionValue::new(14, b"2024-01-02").unwrap()
+        ))
+    );
+    // The nested values are rewritten before their containers.
+    assert_eq!(
+        value.to_string(),
+        r#"{"flags":{"a":true,"b":null,"c":null},"id":"7","items":["{\"n\":1}",{"n":null},{"m":"3","n":null}],"other":{"id":1},"ts":"2024-01-02"}"#
+    );
+
+    // The keys are not rewritten, the first matched rule is applied.
+    let options = ParseOptions {
+        rewrites: vec![
+            rule(
+                "$.*",
+                RewriteTarget::Cast(TargetType::Float64),
+                OnError::Fail,
+            ),
+            rule(
+                "$.a",
+                RewriteTarget::Cast(TargetType::String),
+                OnError::Fail,
+            ),
+        ],
+        ..Default::default()
+    };
+    let value = parse_value_with_options(br#"{"a":1,"1":"2"}"#, &options).unwrap();
+    let mut obj = Object::new();
+    obj.insert("1".to_string(), Value::Number(Number::Float64(2.0)));
+    obj.insert("a".to_string(), Value::Number(Number::Float64(1.0)));
+    assert_eq!(value, Value::Object(obj));
+    let res = parse_value_with_options(br#"{"a":"x"}"#, &options);
+    assert_eq!(res, Err(Error::InvalidCast));
+
+    let path = parse_json_path(b"$.a[last]").unwrap();
+    let res = RewriteRule::new(&path, RewriteTarget::Cast(TargetType::Int64), OnError::Fail);
+    assert_eq!(res, Err(Error::InvalidJsonPath));
+    unregister_extension_type(14);
+}
+
+#[test]
+fn test_parse_with_spill() {
+    let long = "x".repeat(1000);
+    let numbers = (0..100)
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let text =
+        format!(r#"{{"id":1,"{long}":"{long}","a":[["{long}",1],[{numbers}]],"b":"short"}}"#);
+    let options = ParseOptions::default();
+    let parse = |spill_options: SpillOptions| {
+        let mut store: Vec<Vec<u8>> = Vec::new();
+        let (value, spilled) =
+            parse_value_with_spill(text.as_bytes(), &options, spill_options, &mut store).unwrap();
+        let spilled: Vec<_> = spilled
+            .into_iter()
+            .map(|spilled| {
+                let value = from_slice(&store[spilled.id as usize]).unwrap().to_string();
+                (spilled.path, value)
+            })
+            .collect();
+        (value, spilled)
+    };
+
+    // Nothing is spilled within the budget.
+    let (value, spilled) = parse(SpillOptions::default());
+    assert_eq!(value, parse_value(text.as_bytes()).unwrap());
+    assert!(spilled.is_empty());
+
+    // The nested values are spilled first, the keys and the objects are never spilled.
+    let (value, spilled) = parse(SpillOptions {
+        budget: 0,
+        min_size: 1000,
+    });
+    assert_eq!(
+        value.to_string(),
+        format!(r#"{{"a":[[null,1],null],"b":"short","id":1,"{long}":null}}"#)
+    );
+    let expected = vec![
+        (format!("$.{long}"), format!(r#""{long}""#)),
+        ("$.a[0][0]".to_string(), format!(r#""{long}""#)),
+        ("$.a[1]".to_string(), format!("[{numbers}]")),
+    ];
+    assert_eq!(spilled, expected);
+
+    // The values are spilled only after the budget is used.
+    let (value, spilled) = parse(SpillOptions {
+        budget: 4000,
+        min_size: 1000,
+    });
+    assert_eq!(
+        value.to_string(),
+        format!(r#"{{"a":[["{long}",1],null],"b":"short","id":1,"{long}":"{long}"}}"#)
+    );
+    assert_eq!(spilled.len(), 1);
+    assert_eq!(spilled[0].0, "$.a[1]");
+
+    struct Failing;
+    impl SpillStore for Failing {
+        fn spill(&mut self, _value: &[u8]) -> Result<u64, Error> {
+            Err(Error::Io(std::io::ErrorKind::StorageFull))
+        }
+    }
+    let spill_options = SpillOptions {
+        budget: 0,
+        min_size: 1000,
+    };
+    let res = parse_value_with_spill(text.as_bytes(), &options, spill_options, &mut Failing);
+    assert_eq!(res, Err(Error::Io(std::io::ErrorKind::StorageFull)));
+}
+
+#[test]
+fn test_parse_with_tracker() {
+    let text = r#"{"a":[1,2,{"b":"a long string value"}],"c":null}"#;
+    let limit = MemoryLimit::new(usize::MAX);
+    let value = parse_value_with_tracker(text.as_bytes(), &ParseOptions::default(), &limit);
+    assert_eq!(value, parse_value(text.as_bytes()));
+    let used = limit.used();
+    assert!(used > "a long string value".len());
+
+    // The parsing stops once the limit is reached.
+    for limit in [0, used / 2, used - 1] {
+        let limit = MemoryLimit::new(limit);
+        let value = parse_value_with_tracker(text.as_bytes(), &ParseOptions::default(), &limit);
+        assert_eq!(value, Err(Error::MemoryLimitExceeded));
+    }
+    let limit = MemoryLimit::new(used);
+    assert!(parse_value_with_tracker(text.as_bytes(), &ParseOptions::default(), &limit).is_ok());
+
+    let buf = parse_value(text.as_bytes()).unwrap().to_vec();
+    let limit = MemoryLimit::new(usize::MAX);
+    assert_eq!(from_slice_with_tracker(&buf, &limit), from_slice(&buf));
+    let used = limit.used();
+    assert!(used > 0);
+    let limit = MemoryLimit::new(used - 1);
+    assert_eq!(
+        from_slice_with_tracker(&buf, &limit),
+        Err(Error::MemoryLimitExceeded)
+    );
+}
+
+#[test]
+fn test_parse_with_cancellation() {
+    let token = CancellationToken::new();
+    let options = ParseOptions {
+        cancel: Some(token.clone()),
+        ..Default::default()
+    };
+    let text = format!("[{}]", vec!["{\"a\":[1,2]}"; 5000].join(","));
+    assert!(parse_value_with_options(text.as_bytes(), &options).is_ok());
+    token.cancel();
+    assert!(token.is_cancelled());
+    let result = parse_value_with_options(text.as_bytes(), &options);
+    assert_eq!(result, Err(Error::Cancelled));
+
+    let options = ParseOptions {
+        cancel: Some(CancellationToken::with_deadline(Instant::now())),
+        ..Default::default()
+    };
+    let result = parse_value_with_options(text.as_bytes(), &options);
+    assert_eq!(result, Err(Error::Cancelled));
+}
+
+#[test]
+fn test_split_stream() {
+    let buf = br#"{"a":"}{"}[1,[2]] "x\"y"12 true{}null"#;
+    let values: Vec<&[u8]> = split_stream(buf).map(Result::unwrap).collect();
+    assert_eq!(
+        values,
+        vec![
+            br#"{"a":"}{"}"#.as_slice(),
+            b"[1,[2]]",
+            br#""x\"y""#,
+            b"12",
+            b"true",
+            b"{}",
+            b"null",
+        ]
+    );
+    for value in values {
+        assert!(parse_value(value).is_ok());
+    }
+
+    assert_eq!(split_stream(b" \n ").count(), 0);
+
+    let mut values = split_stream(br#"{"a":1} [1,2"#);
+    assert_eq!(values.next().unwrap().unwrap(), br#"{"a":1}"#);
+    assert_eq!(
+        values.next().unwrap().unwrap_err().to_string(),
+        "EOF while parsing a value, pos 12"
+    );
+    assert!(values.next().is_none());
+
+    let mut values = split_stream(b"[1}");
+    assert_eq!(
+        values.next().unwrap().unwrap_err().to_string(),
+        "expected `,` or `]`, pos 2"
+    );
+    let mut values = split_stream(b"1,2");
+    assert_eq!(values.next().unwrap().unwrap(), b"1");
+    assert_eq!(
+        values.next().unwrap().unwrap_err().to_string(),
+        "expected value, pos 1"
+    );
+}
+
+#[test]
+fn test_partial_parser() {
+    let doc =
+        r#"{"name": "café 😀 ok", "tags": ["a", "b\"c"], "n": -12.5, "ok": true, "none": null}"#;
+    let mut parser = PartialParser::new();
+    assert_eq!(parser.value().unwrap(), None);
+    let mut values = Vec::new();
+    for (i, b) in doc.as_bytes().iter().enumerate() {
+        parser.feed(&[*b]).unwrap();
+        let value = parser.value().unwrap().unwrap();
+        values.push((i + 1, value.to_string()));
+    }
+    assert!(parser.is_complete());
+    let expected = [
+        (1, r#"{}"#),
+        (9, r#"{}"#),
+        (10, r#"{"name":""}"#),
+        // the multi-byte characters are cut until they are complete
+        (14, r#"{"name":"caf"}"#),
+        (15, r#"{"name":"café"}"#),
+        (19, r#"{"name":"café "}"#),
+        (20, r#"{"name":"café 😀"}"#),
+        (34, r#"{"name":"café 😀 ok"}"#),
+        (35, r#"{"name":"café 😀 ok","tags":[]}"#),
+        (37, r#"{"name":"café 😀 ok","tags":["a"]}"#),
+        (38, r#"{"name":"café 😀 ok","tags":["a"]}"#),
+        // the escapes are cut until they are complete
+        (43, r#"{"name":"café 😀 ok","tags":["a","b"]}"#),
+        (44, r#"{"name":"café 😀 ok","tags":["a","b\""]}"#),
+        (55, r#"{"name":"café 😀 ok","tags":["a","b\"c"]}"#),
+        (56, r#"{"n":-1,"name":"café 😀 ok","tags":["a","b\"c"]}"#),
+        (58, r#"{"name":"café 😀 ok","tags":["a","b\"c"]}"#),
+        (59, r#"{"n":-12.5,"name":"café 😀 ok","tags":["a","b\"c"]}"#),
+        (70, r#"{"n":-12.5,"name":"café 😀 ok","tags":["a","b\"c"]}"#),
+        (
+            71,
+            r#"{"n":-12.5,"name":"café 😀 ok","ok":true,"tags":["a","b\"c"]}"#,
+        ),
+        (
+            doc.len(),
+            r#"{"n":-12.5,"name":"café 😀 ok","none":null,"ok":true,"tags":["a","b\"c"]}"#,
+        ),
+    ];
+    for (len, value) in expected {
+        assert_eq!(values[len - 1], (len, value.to_string()));
+    }
+    let buf = parser.finish().unwrap();
+    assert_eq!(buf, parse_value(doc.as_bytes()).unwrap().to_vec());
+
+    // the pieces can split the multi-byte characters
+    let mut parser = PartialParser::new();
+    parser.feed("[\"é".as_bytes()[..3].as_ref()).unwrap();
+    assert_eq!(parser.value().unwrap().unwrap().to_string(), r#"[""]"#);
+    parser.feed(&"é".as_bytes()[1..]).unwrap();
+    assert_eq!(parser.value().unwrap().unwrap().to_string(), r#"["é"]"#);
+    assert!(!parser.is_complete());
+    assert!(parser.finish().is_err());
+
+    let mut parser = PartialParser::new();
+    parser.feed(br#"["\uD83D\uDE"#).unwrap();
+    assert_eq!(parser.value().unwrap().unwrap().to_string(), r#"[""]"#);
+    parser.feed(br#"00", tr"#).unwrap();
+    assert_eq!(parser.value().unwrap().unwrap().to_string(), r#"["😀"]"#);
+    parser.feed(b"ue]").unwrap();
+    assert!(parser.is_complete());
+    assert_eq!(
+        parser.finish().unwrap(),
+        parse_value(r#"["😀",true]"#.as_bytes()).unwrap().to_vec()
+    );
+
+    let mut parser = PartialParser::new();
+    assert!(parser.feed(b"[1, {]").is_err());
+}
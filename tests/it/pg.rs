@@ -0,0 +1,467 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonb::pg::*;
+use jsonb::{from_slice, Error};
+
+use crate::common::{assert_jsonb, jsonb};
+
+// The cases are taken from the examples of the Postgres JSON functions and operators documentation.
+
+#[test]
+fn test_pg_arrow() {
+    let v = jsonb(r#"[{"a":"foo"},{"b":"bar"},{"c":"baz"}]"#);
+    assert_jsonb(arrow_index(&v, 2), Some(r#"{"c":"baz"}"#));
+    assert_jsonb(arrow_index(&v, -3), Some(r#"{"a":"foo"}"#));
+    assert_jsonb(arrow_index(&v, 3), None);
+    assert_jsonb(arrow_field(&v, "a"), None);
+
+    let v = jsonb(r#"{"a": {"b":"foo"}}"#);
+    assert_jsonb(arrow_field(&v, "a"), Some(r#"{"b":"foo"}"#));
+    assert_jsonb(arrow_index(&v, 0), None);
+
+    let v = jsonb(r#"[1,2,3]"#);
+    assert_eq!(arrow_text_index(&v, 2), Some("3".to_string()));
+    let v = jsonb(r#"{"a":1,"b":2,"c":"x","d":null}"#);
+    assert_eq!(arrow_text_field(&v, "b"), Some("2".to_string()));
+    assert_eq!(arrow_text_field(&v, "c"), Some("x".to_string()));
+    assert_eq!(arrow_text_field(&v, "d"), None);
+
+    let v = jsonb(r#"{"a": {"b": ["foo","bar"]}}"#);
+    assert_jsonb(hash_arrow(&v, &["a", "b", "1"]), Some(r#""bar""#));
+    assert_eq!(
+        hash_arrow_text(&v, &["a", "b", "1"]),
+        Some("bar".to_string())
+    );
+    assert_eq!(
+        hash_arrow_text(&v, &["a"]),
+        Some(r#"{"b":["foo","bar"]}"#.to_string())
+    );
+    assert_jsonb(hash_arrow(&v, &["a", "b", "x"]), None);
+    assert_jsonb(hash_arrow(&v, &[]), Some(r#"{"a": {"b": ["foo","bar"]}}"#));
+    assert_jsonb(hash_arrow(&v, &["a", "b", "-1"]), Some(r#""bar""#));
+    assert_jsonb(hash_arrow(&v, &["a", "b", "1", "c"]), None);
+
+    // the text input is parsed before the value is accessed
+    let v = r#"{"a": [1, {"b": null}]}"#;
+    assert_jsonb(arrow_field(v, "a"), Some(r#"[1, {"b": null}]"#));
+    assert_jsonb(hash_arrow(v, &["a", "-1", "b"]), Some("null"));
+    assert_eq!(hash_arrow_text(v, &["a", "-1", "b"]), None);
+    assert_eq!(
+        arrow_text_index(&jsonb("[true]"), -1),
+        Some("true".to_string())
+    );
+}
+
+#[test]
+fn test_pg_contains() {
+    let cases = vec![
+        (r#"{"a":1, "b":2}"#, r#"{"b":2}"#, true),
+        (r#"{"b":2}"#, r#"{"a":1, "b":2}"#, false),
+        (r#""foo""#, r#""foo""#, true),
+        (r#"[1, 2, 3]"#, r#"[1, 3]"#, true),
+        (r#"[1, 2, 3]"#, r#"[3, 1]"#, true),
+        (r#"[1, 2, 3]"#, r#"[1, 2, 2]"#, true),
+        (
+            r#"{"product": "PostgreSQL", "version": 9.4, "jsonb": true}"#,
+            r#"{"version": 9.4}"#,
+            true,
+        ),
+        (r#"[1, 2, [1, 3]]"#, r#"[1, 3]"#, false),
+        (r#"[1, 2, [1, 3]]"#, r#"[[1, 3]]"#, true),
+        (r#"{"foo": {"bar": "baz"}}"#, r#"{"bar": "baz"}"#, false),
+        (r#"{"foo": {"bar": "baz"}}"#, r#"{"foo": {}}"#, true),
+        (r#"["foo", "bar"]"#, r#""bar""#, true),
+        (r#""bar""#, r#"["bar"]"#, false),
+        (r#"{"a": [1, 2]}"#, r#"{"a": 1}"#, false),
+        (r#"[1.0]"#, r#"[1]"#, true),
+    ];
+    for (left, right, expect) in cases {
+        assert_eq!(contains(&jsonb(left), &jsonb(right)).unwrap(), expect);
+        assert_eq!(contained_by(&jsonb(right), &jsonb(left)).unwrap(), expect);
+        assert_eq!(contains(left.as_bytes(), right.as_bytes()).unwrap(), expect);
+    }
+}
+
+#[test]
+fn test_pg_contains_batch() {
+    let docs: Vec<_> = [
+        r#"{"a":1,"b":{"c":[1,2]}}"#,
+        r#"{"a":1}"#,
+        r#"{"a":2,"b":{"c":[2]},"d":null}"#,
+        r#"[{"a":1},2]"#,
+        r#"2"#,
+        r#"{}"#,
+    ]
+    .iter()
+    .map(|doc| jsonb(doc))
+    .collect();
+    let cases = vec![
+        (r#"{"a":1}"#, vec![true, true, false, false, false, false]),
+        (
+            r#"{"b":{"c":[2]}}"#,
+            vec![true, false, true, false, false, false],
+        ),
+        (r#"{}"#, vec![true, true, true, false, false, true]),
+        (
+            r#"[{"a":1}]"#,
+            vec![false, false, false, true, false, false],
+        ),
+        (r#"2"#, vec![false, false, false, true, true, false]),
+    ];
+    for (needle, expect) in cases {
+        let res = contains_batch(docs.iter().map(Vec::as_slice), &jsonb(needle));
+        assert_eq!(res, expect);
+        for (doc, expect) in docs.iter().zip(expect) {
+            assert_eq!(contains(doc, &jsonb(needle)).unwrap(), expect);
+        }
+    }
+    let res = contains_batch(docs.iter().map(Vec::as_slice), &[]);
+    assert_eq!(res, vec![false; docs.len()]);
+}
+
+#[test]
+fn test_pg_exists() {
+    let v = jsonb(r#"{"a":1, "b":2}"#);
+    assert!(exists(&v, "b"));
+    assert!(!exists(&v, "c"));
+    let v = jsonb(r#"["a", "b", "c"]"#);
+    assert!(exists(&v, "b"));
+    assert!(!exists(&jsonb(r#"[1, 2]"#), "1"));
+    assert!(exists(&jsonb(r#""foo""#), "foo"));
+    assert!(!exists(&jsonb(r#"{"a":{"b":1}}"#), "b"));
+    assert!(!exists(&jsonb(r#"[["a"]]"#), "a"));
+
+    let v = jsonb(r#"{"a":1, "b":2, "c":3}"#);
+    assert!(exists_any(&v, &["b", "d"]));
+    assert!(!exists_any(&v, &["d", "e"]));
+    assert!(exists_all(&jsonb(r#"["a", "b", "c"]"#), &["a", "b"]));
+    assert!(!exists_all(&v, &["a", "d"]));
+}
+
+#[test]
+fn test_pg_concat() {
+    let cases = vec![
+        (r#"["a", "b"]"#, r#"["a", "d"]"#, r#"["a", "b", "a", "d"]"#),
+        (r#"{"a": "b"}"#, r#"{"c": "d"}"#, r#"{"a": "b", "c": "d"}"#),
+        (r#"{"a": "b"}"#, r#"{"a": "x"}"#, r#"{"a": "x"}"#),
+        (r#"[1, 2]"#, r#"3"#, r#"[1, 2, 3]"#),
+        (r#"1"#, r#"[2, 3]"#, r#"[1, 2, 3]"#),
+        (r#"{"a": "b"}"#, r#"42"#, r#"[{"a": "b"}, 42]"#),
+        (r#"[1, 2]"#, r#"[[3, 4]]"#, r#"[1, 2, [3, 4]]"#),
+    ];
+    for (left, right, expect) in cases {
+        assert_jsonb(
+            Some(concat(&jsonb(left), &jsonb(right)).unwrap()),
+            Some(expect),
+        );
+    }
+}
+
+#[test]
+fn test_pg_delete() {
+    let v = jsonb(r#"{"a": "b", "c": "d"}"#);
+    assert_jsonb(Some(delete_key(&v, "a").unwrap()), Some(r#"{"c": "d"}"#));
+    assert_jsonb(Some(delete_keys(&v, &["a", "c"]).unwrap()), Some(r#"{}"#));
+    let v = jsonb(r#"["a", "b", "c", "b"]"#);
+    assert_jsonb(Some(delete_key(&v, "b").unwrap()), Some(r#"["a", "c"]"#));
+    assert_jsonb(
+        Some(delete_index(&v, 1).unwrap()),
+        Some(r#"["a", "c", "b"]"#),
+    );
+    assert_jsonb(
+        Some(delete_index(&v, -1).unwrap()),
+        Some(r#"["a", "b", "c"]"#),
+    );
+    assert_jsonb(
+        Some(delete_index(&v, 10).unwrap()),
+        Some(r#"["a", "b", "c", "b"]"#),
+    );
+    assert_eq!(delete_key(&jsonb("1"), "a"), Err(Error::InvalidJsonType));
+    assert_eq!(
+        delete_index(&jsonb(r#"{"a":1}"#), 0),
+        Err(Error::InvalidJsonType)
+    );
+
+    let v = jsonb(r#"["a", {"b":1}]"#);
+    assert_jsonb(
+        Some(delete_path(&v, &["1", "b"]).unwrap()),
+        Some(r#"["a", {}]"#),
+    );
+    assert_jsonb(Some(delete_path(&v, &["-1"]).unwrap()), Some(r#"["a"]"#));
+    assert_eq!(delete_path(&v, &["x"]), Err(Error::InvalidKeyPath));
+    assert_eq!(
+        delete_path(&jsonb("1"), &["a"]),
+        Err(Error::InvalidJsonType)
+    );
+}
+
+#[test]
+fn test_pg_functions() {
+    assert_eq!(jsonb_typeof(&jsonb("-123.4")).unwrap(), "number");
+    assert_eq!(jsonb_typeof(&jsonb("null")).unwrap(), "null");
+    assert_eq!(jsonb_typeof(&jsonb("[]")).unwrap(), "array");
+    assert_eq!(jsonb_typeof(&jsonb(r#"{"a":1}"#)).unwrap(), "object");
+
+    let v = jsonb(r#"[1,2,3,{"f1":1,"f2":[5,6]},4]"#);
+    assert_eq!(jsonb_array_length(&v).unwrap(), 5);
+    assert_eq!(jsonb_array_length(&jsonb("1")), Err(Error::InvalidJsonType));
+    let elems = jsonb_array_elements(&jsonb(r#"[1,true,[2,false]]"#)).unwrap();
+    assert_eq!(elems.len(), 3);
+    assert_jsonb(Some(elems[2].clone()), Some("[2,false]"));
+
+    let elems = jsonb_array_elements_text(&jsonb(r#"["foo",1,null,{"a":"b"}]"#)).unwrap();
+    assert_eq!(
+        elems,
+        vec![
+            Some("foo".to_string()),
+            Some("1".to_string()),
+            None,
+            Some(r#"{"a":"b"}"#.to_string())
+        ]
+    );
+    assert_eq!(
+        jsonb_array_elements_text(&jsonb("{}")),
+        Err(Error::InvalidJsonType)
+    );
+    let pairs = jsonb_each_text(&jsonb(r#"{"a":"foo","b":null,"c":[1]}"#)).unwrap();
+    assert_eq!(
+        pairs,
+        vec![
+            ("a".to_string(), Some("foo".to_string())),
+            ("b".to_string(), None),
+            ("c".to_string(), Some("[1]".to_string()))
+        ]
+    );
+    assert_eq!(jsonb_each_text(&jsonb("[]")), Err(Error::InvalidJsonType));
+
+    let keys = jsonb_object_keys(&jsonb(r#"{"f1":"abc","f2":{"f3":"a", "f4":"b"}}"#)).unwrap();
+    assert_eq!(keys, vec!["f1".to_string(), "f2".to_string()]);
+    assert_eq!(
+        jsonb_object_keys(&jsonb("[1]")),
+        Err(Error::InvalidJsonType)
+    );
+
+    let v = jsonb(r#"[{"f1":1, "f2":null}, 2, null, 3]"#);
+    assert_jsonb(
+        Some(jsonb_strip_nulls(&v).unwrap()),
+        Some(r#"[{"f1":1}, 2, null, 3]"#),
+    );
+
+    let v = jsonb(r#"[{"f1":1,"f2":null}, 2]"#);
+    assert_eq!(
+        jsonb_pretty(&v).unwrap(),
+        "[\n    {\n        \"f1\": 1,\n        \"f2\": null\n    },\n    2\n]"
+    );
+    assert_eq!(
+        jsonb_pretty(&jsonb(r#"{"a":[]}"#)).unwrap(),
+        "{\n    \"a\": [\n    ]\n}"
+    );
+}
+
+#[test]
+fn test_pg_jsonb_set() {
+    let v = jsonb(r#"[{"f1":1,"f2":null},2,null,3]"#);
+    let new = jsonb(r#"[2,3,4]"#);
+    assert_jsonb(
+        Some(jsonb_set(&v, &["0", "f1"], &new, false).unwrap()),
+        Some(r#"[{"f1": [2, 3, 4], "f2": null}, 2, null, 3]"#),
+    );
+    assert_jsonb(
+        Some(jsonb_set(&v, &["0", "f3"], &new, true).unwrap()),
+        Some(r#"[{"f1": 1, "f2": null, "f3": [2, 3, 4]}, 2, null, 3]"#),
+    );
+    assert_jsonb(
+        Some(jsonb_set(&v, &["0", "f3"], &new, false).unwrap()),
+        Some(r#"[{"f1":1,"f2":null},2,null,3]"#),
+    );
+    assert_jsonb(
+        Some(jsonb_set(&v, &["9"], &new, true).unwrap()),
+        Some(r#"[{"f1":1,"f2":null},2,null,3,[2,3,4]]"#),
+    );
+    assert_jsonb(
+        Some(jsonb_set(&v, &["-9"], &new, true).unwrap()),
+        Some(r#"[[2,3,4],{"f1":1,"f2":null},2,null,3]"#),
+    );
+    assert_jsonb(
+        Some(jsonb_set(&v, &["5", "a"], &new, true).unwrap()),
+        Some(r#"[{"f1":1,"f2":null},2,null,3]"#),
+    );
+    assert_eq!(
+        jsonb_set(&v, &["a"], &new, true),
+        Err(Error::InvalidKeyPath)
+    );
+    assert_eq!(
+        jsonb_set(&jsonb("1"), &["a"], &new, true),
+        Err(Error::InvalidJsonType)
+    );
+
+    let v = jsonb(r#"{"a": [0,1,2]}"#);
+    let new = jsonb(r#""new_value""#);
+    assert_jsonb(
+        Some(jsonb_insert(&v, &["a", "1"], &new, false).unwrap()),
+        Some(r#"{"a": [0, "new_value", 1, 2]}"#),
+    );
+    assert_jsonb(
+        Some(jsonb_insert(&v, &["a", "1"], &new, true).unwrap()),
+        Some(r#"{"a": [0, 1, "new_value", 2]}"#),
+    );
+    assert_jsonb(
+        Some(jsonb_insert(&v, &["a", "-1"], &new, true).unwrap()),
+        Some(r#"{"a": [0, 1, 2, "new_value"]}"#),
+    );
+    assert_jsonb(
+        Some(jsonb_insert(&v, &["b"], &new, false).unwrap()),
+        Some(r#"{"a": [0,1,2], "b": "new_value"}"#),
+    );
+    assert_eq!(
+        jsonb_insert(&v, &["a"], &new, false),
+        Err(Error::ObjectDuplicateKey)
+    );
+
+    // an index out of range inserts at the nearest end
+    let v = jsonb("[1,2,3]");
+    let new = jsonb("0");
+    assert_jsonb(
+        Some(jsonb_insert(&v, &["-10"], &new, true).unwrap()),
+        Some("[0, 1, 2, 3]"),
+    );
+    assert_jsonb(
+        Some(jsonb_insert(&v, &["-10"], &new, false).unwrap()),
+        Some("[0, 1, 2, 3]"),
+    );
+    assert_jsonb(
+        Some(jsonb_insert(&v, &["10"], &new, false).unwrap()),
+        Some("[1, 2, 3, 0]"),
+    );
+    assert_jsonb(
+        Some(jsonb_insert(&v, &["-3"], &new, true).unwrap()),
+        Some("[1, 0, 2, 3]"),
+    );
+}
+
+#[test]
+fn test_pg_jsonb_path_query() {
+    let v = jsonb(r#"{"a":[1,2,3,4,5]}"#);
+    let items = jsonb_path_query(&v, "$.a[*] ? (@ >= 2 && @ <= 4)").unwrap();
+    assert_eq!(items.len(), 3);
+    assert_jsonb(Some(items[0].clone()), Some("2"));
+    assert_jsonb(
+        Some(jsonb_path_query_array(&v, "$.a[*] ? (@ >= 2 && @ <= 4)").unwrap()),
+        Some("[2, 3, 4]"),
+    );
+    assert_jsonb(
+        jsonb_path_query_first(&v, "$.a[*] ? (@ >= 2 && @ <= 4)").unwrap(),
+        Some("2"),
+    );
+    assert!(jsonb_path_exists(&v, "$.a[*] ? (@ > 4)").unwrap());
+    assert!(!jsonb_path_exists(&v, "$.a[*] ? (@ > 5)").unwrap());
+    assert!(jsonb_path_query(&v, "$.a[").is_err());
+
+    // subscripts are returned in the order as written
+    assert_jsonb(
+        Some(jsonb_path_query_array(&v, "$.a[3, 0 to 1]").unwrap()),
+        Some("[4, 1, 2]"),
+    );
+
+    let vars = jsonb(r#"{"min":2,"max":4}"#);
+    let path = "$.a[*] ? (@ >= $min && @ <= $max)";
+    let items = jsonb_path_query_with_vars(&v, path, &vars).unwrap();
+    assert_eq!(items.len(), 3);
+    assert_jsonb(Some(items[2].clone()), Some("4"));
+    let items = jsonb_path_query_with_vars(&v, path, &jsonb("{}")).unwrap();
+    assert!(items.is_empty());
+}
+
+#[test]
+fn test_pg_path_match() {
+    // (value, path, `@?`, `@@`), the predicate check expressions are only supported by `@@`.
+    let cases = vec![
+        (r#"{"a":[1,2,3,4,5]}"#, "$.a[*] ? (@ > 2)", Some(true), None),
+        (r#"{"a":[1,2,3,4,5]}"#, "$.a[*] > 2", None, Some(true)),
+        (r#"{"a":[1,2,3,4,5]}"#, "$.a[*] > 5", None, Some(false)),
+        (
+            r#"{"a":[1,2,3,4,5]}"#,
+            "$.a[*] >= 2 && $.a[*] <= 4",
+            None,
+            Some(true),
+        ),
+        (r#"{"a":true}"#, "$.a", Some(true), Some(true)),
+        (r#"{"a":[true,false]}"#, "$.a[*]", Some(true), None),
+        (r#"{"a":1}"#, "$.a", Some(true), None),
+        (r#"{"a":1}"#, "$.b", Some(false), None),
+        // the comparison of the values of different types is unknown
+        (r#"{"a":1}"#, r#"$.a == "x""#, None, None),
+        (r#"{"a":{"b":1}}"#, "$.a == 1", None, None),
+        (r#"{"a":1}"#, r#"$.a == "x" || $.a == 1"#, None, Some(true)),
+        (r#"{"a":1}"#, r#"$.a == "x" && $.a == 2"#, None, Some(false)),
+        (r#"{"a":[1,"x"]}"#, "$.a[*] == 1", None, Some(true)),
+        (r#"{"a":[1,"x"]}"#, "$.a[*] == 2", None, None),
+        (r#"{"a":1}"#, "$.b == 1", None, Some(false)),
+        (r#"{"a":1}"#, r#"$.a starts with "x""#, None, None),
+        (r#"{"a":"xy"}"#, r#"$.a starts with "y""#, None, Some(false)),
+        (r#"{"a":1}"#, "exists($.b)", None, Some(false)),
+    ];
+    for (value, path, exists, matched) in cases {
+        let v = jsonb(value);
+        assert_eq!(path_exists(&v, path).ok(), exists, "{path}");
+        assert_eq!(path_match(&v, path).unwrap(), matched, "{path}");
+        assert_eq!(jsonb_path_match(&v, path).ok(), matched, "{path}");
+    }
+    let v = jsonb(r#"{"a":[1,2,3,4,5]}"#);
+    assert_eq!(jsonb_path_match(&v, "$.a[*]"), Err(Error::InvalidJsonType));
+    assert!(path_match(&v, "$.a[*] >").is_err());
+}
+
+#[test]
+fn test_pg_extract_and_build() {
+    let v = jsonb(r#"{"f2":{"f3":1},"f4":{"f5":99,"f6":"foo"}}"#);
+    assert_jsonb(jsonb_extract_path(&v, &["f4", "f6"]), Some(r#""foo""#));
+    assert_eq!(
+        jsonb_extract_path_text(&v, &["f4", "f6"]),
+        Some("foo".to_string())
+    );
+    assert_jsonb(jsonb_extract_path(&v, &["f4", "f7"]), None);
+
+    let pairs = jsonb_each(&jsonb(r#"{"a":"foo", "b":"bar"}"#)).unwrap();
+    let pairs: Vec<_> = pairs
+        .iter()
+        .map(|(k, v)| (k.as_str(), from_slice(v).unwrap().to_string()))
+        .collect();
+    assert_eq!(
+        pairs,
+        vec![("a", r#""foo""#.to_string()), ("b", r#""bar""#.to_string())]
+    );
+    assert_eq!(jsonb_each(&jsonb("[1]")), Err(Error::InvalidJsonType));
+
+    // jsonb_build_array(1, 2, 'foo', 4, 5)
+    let items = ["1", "2", r#""foo""#, "4", "5"];
+    assert_jsonb(
+        Some(jsonb_build_array(&items).unwrap()),
+        Some(r#"[1, 2, "foo", 4, 5]"#),
+    );
+    assert_jsonb(Some(jsonb_build_array::<str>(&[]).unwrap()), Some("[]"));
+    // jsonb_build_object('foo', 1, 2, row(3,'bar'))
+    let pairs = [("foo", "1"), ("2", r#"{"f1":3,"f2":"bar"}"#)];
+    assert_jsonb(
+        Some(jsonb_build_object(&pairs).unwrap()),
+        Some(r#"{"foo": 1, "2": {"f1": 3, "f2": "bar"}}"#),
+    );
+    let pairs = [("a", "1"), ("a", "2")];
+    assert_jsonb(
+        Some(jsonb_build_object(&pairs).unwrap()),
+        Some(r#"{"a": 2}"#),
+    );
+    assert!(jsonb_build_array(&["[1,"]).is_err());
+}
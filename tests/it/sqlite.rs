@@ -0,0 +1,123 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonb::sqlite::*;
+use jsonb::Error;
+
+use crate::common::{assert_jsonb, jsonb};
+
+// The cases are taken from the examples of the SQLite json1 documentation.
+
+#[test]
+fn test_sqlite_json_extract() {
+    let value = r#"{"a":2,"c":[4,5,{"f":7}]}"#;
+    let cases: Vec<(Vec<&str>, Option<&str>)> = vec![
+        (vec!["$"], Some(r#"{"a":2,"c":[4,5,{"f":7}]}"#)),
+        (vec!["$.c"], Some("[4,5,{\"f\":7}]")),
+        (vec!["$.c[2]"], Some(r#"{"f":7}"#)),
+        (vec!["$.c[2].f"], Some("7")),
+        (vec!["$.c", "$.a"], Some("[[4,5,{\"f\":7}],2]")),
+        (vec!["$.x"], None),
+        (vec!["$.x", "$.a"], Some("[null,2]")),
+        (vec!["$.c[#-1]"], Some(r#"{"f":7}"#)),
+        (vec!["$.c[#-3]"], Some("4")),
+        (vec!["$.c[#-4]"], None),
+        (vec!["$.c[#]"], None),
+        (vec!["$.c[3]"], None),
+    ];
+    for (paths, expect) in cases {
+        assert_jsonb(json_extract(&jsonb(value), &paths).unwrap(), expect);
+        assert_jsonb(json_extract(value.as_bytes(), &paths).unwrap(), expect);
+    }
+
+    let value = jsonb(r#"{"a.b": {"c": 1}, "d e": [1]}"#);
+    assert_jsonb(json_extract(&value, &[r#"$."a.b".c"#]).unwrap(), Some("1"));
+    assert_jsonb(json_extract(&value, &["$.d e[0]"]).unwrap(), Some("1"));
+    for path in [
+        "", "a", "$.", "$..a", "$[", "$[a]", "$[-1]", "$[#-]", "$[#+1]", r#"$."a"#, "$a",
+    ] {
+        assert_eq!(json_extract(&value, &[path]), Err(Error::InvalidJsonPath));
+    }
+}
+
+#[test]
+fn test_sqlite_json_remove() {
+    let cases: Vec<(&str, Vec<&str>, Option<&str>)> = vec![
+        ("[0,1,2,3,4]", vec!["$[2]"], Some("[0,1,3,4]")),
+        ("[0,1,2,3,4]", vec!["$[2]", "$[0]"], Some("[1,3,4]")),
+        ("[0,1,2,3,4]", vec!["$[0]", "$[2]"], Some("[1,2,4]")),
+        ("[0,1,2,3,4]", vec!["$[#-1]", "$[0]"], Some("[1,2,3]")),
+        ("[0,1,2,3,4]", vec!["$[#]", "$[9]"], Some("[0,1,2,3,4]")),
+        (r#"{"x":25,"y":42}"#, vec![], Some(r#"{"x":25,"y":42}"#)),
+        (
+            r#"{"x":25,"y":42}"#,
+            vec!["$.z"],
+            Some(r#"{"x":25,"y":42}"#),
+        ),
+        (r#"{"x":25,"y":42}"#, vec!["$.y"], Some(r#"{"x":25}"#)),
+        (r#"{"x":25,"y":42}"#, vec!["$"], None),
+        (
+            r#"{"x":{"y":[1,2]}}"#,
+            vec!["$.x.y[#-2]"],
+            Some(r#"{"x":{"y":[2]}}"#),
+        ),
+    ];
+    for (value, paths, expect) in cases {
+        assert_jsonb(json_remove(&jsonb(value), &paths).unwrap(), expect);
+    }
+    assert_eq!(
+        json_remove(&jsonb("[1]"), &["$[0]", "$x"]),
+        Err(Error::InvalidJsonPath)
+    );
+}
+
+#[test]
+fn test_sqlite_json_patch() {
+    let cases = vec![
+        (
+            r#"{"a":1,"b":2}"#,
+            r#"{"c":3,"d":4}"#,
+            r#"{"a":1,"b":2,"c":3,"d":4}"#,
+        ),
+        (r#"{"a":[1,2],"b":2}"#, r#"{"a":9}"#, r#"{"a":9,"b":2}"#),
+        (r#"{"a":[1,2],"b":2}"#, r#"{"a":null}"#, r#"{"b":2}"#),
+        (
+            r#"{"a":1,"b":2}"#,
+            r#"{"a":9,"b":null,"c":8}"#,
+            r#"{"a":9,"c":8}"#,
+        ),
+        (
+            r#"{"a":{"x":1,"y":2},"b":3}"#,
+            r#"{"a":{"y":9},"c":8}"#,
+            r#"{"a":{"x":1,"y":9},"b":3,"c":8}"#,
+        ),
+        (r#"{"a":"b"}"#, r#"{"a":{"b":null}}"#, r#"{"a":{}}"#),
+        (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+        (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+        (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+    ];
+    for (target, patch, expect) in cases {
+        assert_jsonb(
+            Some(json_patch(&jsonb(target), &jsonb(patch)).unwrap()),
+            Some(expect),
+        );
+    }
+}
+
+#[test]
+fn test_sqlite_json_quote() {
+    assert_eq!(json_quote("verdant"), r#""verdant""#);
+    assert_eq!(json_quote("[1]"), r#""[1]""#);
+    assert_eq!(json_quote("a\"b\\c\n"), r#""a\"b\\c\n""#);
+}
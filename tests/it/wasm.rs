@@ -0,0 +1,66 @@
+// Copyright 2023 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonb::wasm::diff;
+use jsonb::wasm::get_by_path;
+use jsonb::wasm::parse;
+use jsonb::wasm::stringify;
+
+// The errors are `JsError`s, which can only be created on the wasm target,
+// so only the successful calls are tested here.
+#[test]
+fn test_wasm() {
+    let value = parse(r#"{"a":[1,2,3],"b":{"c":"d"}}"#).unwrap();
+    assert_eq!(stringify(&value).unwrap(), r#"{"a":[1,2,3],"b":{"c":"d"}}"#);
+
+    let values = get_by_path(&value, "$.a[1 to last]").unwrap();
+    assert_eq!(stringify(&values).unwrap(), "[2,3]");
+    let values = get_by_path(&value, "$.x").unwrap();
+    assert_eq!(stringify(&values).unwrap(), "[]");
+
+    let sources = vec![
+        (r#"{"a":1}"#, r#"{"a":1}"#, "[]"),
+        (
+            r#"{"a":1,"b":2}"#,
+            r#"{"a":3,"c":4}"#,
+            r#"[{"op":"replace","path":"\/a","value":3},{"op":"remove","path":"\/b"},{"op":"add","path":"\/c","value":4}]"#,
+        ),
+        (
+            r#"[1,2,3,4]"#,
+            r#"[1,5]"#,
+            r#"[{"op":"replace","path":"\/1","value":5},{"op":"remove","path":"\/3"},{"op":"remove","path":"\/2"}]"#,
+        ),
+        (
+            r#"{"a/b":[1],"~":null}"#,
+            r#"{"a/b":[1,{"x":true}],"~":"y"}"#,
+            r#"[{"op":"add","path":"\/a~1b\/1","value":{"x":true}},{"op":"replace","path":"\/~0","value":"y"}]"#,
+        ),
+        (
+            r#"1"#,
+            r#"[1]"#,
+            r#"[{"op":"replace","path":"","value":[1]}]"#,
+        ),
+        (
+            r#"{"a":1,"b":[1]}"#,
+            r#"{"a":1,"b":[1,2]}"#,
+            r#"[{"op":"add","path":"\/b\/1","value":2}]"#,
+        ),
+    ];
+    for (left, right, expect) in sources {
+        let left = parse(left).unwrap();
+        let right = parse(right).unwrap();
+        let ops = diff(&left, &right).unwrap();
+        assert_eq!(stringify(&ops).unwrap(), expect);
+    }
+}